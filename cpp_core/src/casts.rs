@@ -52,6 +52,9 @@ impl<T> StaticUpcast<T> for T {
 ///
 /// Provides access to C++ `static_cast` conversion from base class to derived class.
 /// The conversion in opposite direction can be done with `StaticUpcast`.
+/// If `T` is a virtual base (or a base of a virtual base) of the derived class,
+/// `static_cast` is not allowed by C++, and `dynamic_cast` is used instead.
+/// In this case, `StaticDowncast` is only implemented if `T` is polymorphic.
 ///
 /// If `T1` class is derived (in C++) from `T2` class (directly or indirectly),
 /// `StaticDowncast<T1>` is implemented for `T2`.
@@ -0,0 +1,39 @@
+/// Information about a C++ class wrapped by a Rust type.
+///
+/// Each generated crate provides the list of its classes
+/// through the `cpp_classes()` function in its root module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CppClassMetadata {
+    /// Fully qualified name of the C++ class.
+    pub cpp_name: &'static str,
+    /// Fully qualified name of the Rust type.
+    pub rust_name: &'static str,
+    /// Size of the object in bytes, if it's known in Rust.
+    pub size: Option<usize>,
+    /// True if the class has virtual functions (directly or through its base classes).
+    pub is_polymorphic: bool,
+}
+
+/// Provides information about the C++ class wrapped by this Rust type.
+///
+/// This trait is implemented for all class wrappers in generated crates.
+pub trait CppClass {
+    /// Fully qualified name of the C++ class.
+    const CPP_NAME: &'static str;
+    /// Fully qualified name of the Rust type.
+    const RUST_NAME: &'static str;
+    /// Size of the object in bytes, if it's known in Rust.
+    const CPP_SIZE: Option<usize>;
+    /// True if the class has virtual functions (directly or through its base classes).
+    const IS_POLYMORPHIC: bool;
+
+    /// Returns all metadata of the class.
+    fn metadata() -> CppClassMetadata {
+        CppClassMetadata {
+            cpp_name: Self::CPP_NAME,
+            rust_name: Self::RUST_NAME,
+            size: Self::CPP_SIZE,
+            is_polymorphic: Self::IS_POLYMORPHIC,
+        }
+    }
+}
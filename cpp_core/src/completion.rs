@@ -0,0 +1,190 @@
+use std::error::Error;
+use std::ffi::c_void;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+struct State<T> {
+    value: Option<T>,
+    is_sender_dropped: bool,
+    waker: Option<Waker>,
+}
+
+/// Creates a one-shot completion channel.
+///
+/// Generated `async` wrappers of C++ functions that report their result through a
+/// completion callback pass the sender to the callback and return the `Completion`
+/// to the caller.
+pub fn completion<T>() -> (CompletionSender<T>, Completion<T>) {
+    let state = Arc::new(Mutex::new(State {
+        value: None,
+        is_sender_dropped: false,
+        waker: None,
+    }));
+    (
+        CompletionSender {
+            state: Arc::clone(&state),
+        },
+        Completion { state },
+    )
+}
+
+/// Sending half of a completion channel (see `completion`).
+pub struct CompletionSender<T> {
+    state: Arc<Mutex<State<T>>>,
+}
+
+impl<T> CompletionSender<T> {
+    /// Completes the operation with `value`, waking up the task awaiting
+    /// the corresponding `Completion`.
+    pub fn send(self, value: T) {
+        let mut state = self.state.lock().expect("completion state is poisoned");
+        state.value = Some(value);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Converts the sender to the user data of a completion callback.
+    /// The sender can be taken back with `from_callback_data`.
+    pub fn into_callback_data(self) -> *mut c_void {
+        Box::into_raw(Box::new(self)) as *mut c_void
+    }
+
+    /// Takes the sender from the user data created by `into_callback_data`
+    /// and frees the user data.
+    ///
+    /// # Safety
+    ///
+    /// `data` must be a value returned by `into_callback_data` of a sender
+    /// of the same type. The sender can only be taken once, so the completion
+    /// callback must not be called more than once.
+    pub unsafe fn from_callback_data(data: *mut c_void) -> Self {
+        *Box::from_raw(data as *mut Self)
+    }
+}
+
+impl<T> Drop for CompletionSender<T> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.is_sender_dropped = true;
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for CompletionSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompletionSender").finish()
+    }
+}
+
+/// Error returned by a `Completion` if its sender was dropped without producing a value
+/// (e.g. if the conversion of the callback's arguments panicked).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Canceled;
+
+impl fmt::Display for Canceled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "completion callback was dropped without producing a value"
+        )
+    }
+}
+
+impl Error for Canceled {}
+
+/// Future resolving to the value passed to the completion callback
+/// of an asynchronous C++ operation (see `completion`).
+///
+/// The future resolves to `Err(Canceled)` if the sender was dropped
+/// without producing a value.
+pub struct Completion<T> {
+    state: Arc<Mutex<State<T>>>,
+}
+
+impl<T> Future for Completion<T> {
+    type Output = Result<T, Canceled>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<T, Canceled>> {
+        let mut state = self.state.lock().expect("completion state is poisoned");
+        if let Some(value) = state.value.take() {
+            Poll::Ready(Ok(value))
+        } else if state.is_sender_dropped {
+            Poll::Ready(Err(Canceled))
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl<T> fmt::Debug for Completion<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Completion").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{completion, Canceled, CompletionSender};
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::ptr;
+    use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    fn noop_waker() -> Waker {
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(ptr::null(), &VTABLE)
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &VTABLE)) }
+    }
+
+    #[test]
+    fn send_resolves_completion() {
+        let waker = noop_waker();
+        let mut context = Context::from_waker(&waker);
+        let (sender, mut completion) = completion::<i32>();
+        assert_eq!(Pin::new(&mut completion).poll(&mut context), Poll::Pending);
+        sender.send(42);
+        assert_eq!(
+            Pin::new(&mut completion).poll(&mut context),
+            Poll::Ready(Ok(42))
+        );
+    }
+
+    #[test]
+    fn callback_data_round_trip() {
+        let waker = noop_waker();
+        let mut context = Context::from_waker(&waker);
+        let (sender, mut completion) = completion::<i32>();
+        let data = sender.into_callback_data();
+        unsafe {
+            CompletionSender::<i32>::from_callback_data(data).send(1);
+        }
+        assert_eq!(
+            Pin::new(&mut completion).poll(&mut context),
+            Poll::Ready(Ok(1))
+        );
+    }
+
+    #[test]
+    fn dropped_sender_cancels() {
+        let waker = noop_waker();
+        let mut context = Context::from_waker(&waker);
+        let (sender, mut completion) = completion::<i32>();
+        let data = sender.into_callback_data();
+        drop(unsafe { CompletionSender::<i32>::from_callback_data(data) });
+        assert_eq!(
+            Pin::new(&mut completion).poll(&mut context),
+            Poll::Ready(Err(Canceled))
+        );
+    }
+}
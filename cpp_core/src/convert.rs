@@ -1,5 +1,6 @@
 use crate::ptr::NullPtr;
-use crate::{CppBox, CppDeletable, Ptr, Ref, StaticUpcast};
+use crate::{CppBox, CppDeletable, MutRef, Ptr, Ref, StaticUpcast};
+use std::pin::Pin;
 
 /// Performs some of the conversions that are available implicitly in C++.
 ///
@@ -62,6 +63,26 @@ where
     }
 }
 
+impl<'a, T, U: CppDeletable> CastFrom<&'a Pin<CppBox<U>>> for Ptr<T>
+where
+    U: StaticUpcast<T>,
+{
+    unsafe fn cast_from(value: &'a Pin<CppBox<U>>) -> Self {
+        StaticUpcast::static_upcast(Ptr::from_raw(&**value))
+    }
+}
+
+impl<'a, T, U: CppDeletable> CastFrom<&'a Pin<CppBox<U>>> for Ref<T>
+where
+    U: StaticUpcast<T>,
+{
+    unsafe fn cast_from(value: &'a Pin<CppBox<U>>) -> Self {
+        StaticUpcast::static_upcast(Ptr::from_raw(&**value))
+            .as_ref()
+            .expect("StaticUpcast returned null on CppBox input")
+    }
+}
+
 impl<T, U> CastFrom<Ptr<U>> for Ptr<T>
 where
     U: StaticUpcast<T>,
@@ -82,6 +103,42 @@ where
     }
 }
 
+impl<T, U> CastFrom<MutRef<U>> for MutRef<T>
+where
+    U: StaticUpcast<T>,
+{
+    unsafe fn cast_from(value: MutRef<U>) -> Self {
+        value.static_upcast()
+    }
+}
+
+impl<T, U> CastFrom<MutRef<U>> for Ref<T>
+where
+    U: StaticUpcast<T>,
+{
+    unsafe fn cast_from(value: MutRef<U>) -> Self {
+        value.static_upcast().as_ref()
+    }
+}
+
+impl<T, U> CastFrom<MutRef<U>> for Ptr<T>
+where
+    U: StaticUpcast<T>,
+{
+    unsafe fn cast_from(value: MutRef<U>) -> Self {
+        StaticUpcast::static_upcast(value.as_ptr())
+    }
+}
+
+impl<'a, T, U: CppDeletable> CastFrom<&'a mut CppBox<U>> for MutRef<T>
+where
+    U: StaticUpcast<T>,
+{
+    unsafe fn cast_from(value: &'a mut CppBox<U>) -> Self {
+        value.as_mut_ref().static_upcast()
+    }
+}
+
 impl<T> CastFrom<NullPtr> for Ptr<T> {
     unsafe fn cast_from(_value: NullPtr) -> Self {
         Self::null()
@@ -105,3 +162,123 @@ where
         Self::cast_from(Ptr::from_raw(value))
     }
 }
+
+/// Argument of a C++ function that accepts a `const T&`.
+///
+/// The argument either refers to an existing object or owns a temporary object
+/// created by an implicit conversion. The temporary object is deleted
+/// when `ArgRef` is dropped.
+pub enum ArgRef<T: CppDeletable> {
+    /// Reference to an existing object.
+    Borrowed(Ref<T>),
+    /// Temporary object created by a conversion.
+    Temporary(CppBox<T>),
+}
+
+impl<T: CppDeletable> ArgRef<T> {
+    /// Returns constant raw pointer to the referenced object.
+    pub fn as_raw_ptr(&self) -> *const T {
+        match self {
+            ArgRef::Borrowed(value) => value.as_raw_ptr(),
+            ArgRef::Temporary(value) => value.as_raw_ptr(),
+        }
+    }
+}
+
+/// Performs conversions accepted by C++ functions taking `const T&` arguments,
+/// including implicit conversions through non-explicit constructors of `T`
+/// (e.g. `&str` to `QString`).
+///
+/// Like `CastInto<Ref<T>>`, `IntoArg` is implemented for references to `T`
+/// and its subclasses. Implementations for implicit conversions are generated
+/// together with the types.
+pub trait IntoArg<T: CppDeletable> {
+    /// Performs the conversion.
+    ///
+    /// ### Safety
+    ///
+    /// This operation is safe as long as `self` is valid.
+    unsafe fn into_arg(self) -> ArgRef<T>;
+}
+
+impl<T: CppDeletable, U> IntoArg<T> for Ref<U>
+where
+    U: StaticUpcast<T>,
+{
+    unsafe fn into_arg(self) -> ArgRef<T> {
+        ArgRef::Borrowed(self.cast_into())
+    }
+}
+
+impl<T: CppDeletable, U> IntoArg<T> for MutRef<U>
+where
+    U: StaticUpcast<T>,
+{
+    unsafe fn into_arg(self) -> ArgRef<T> {
+        ArgRef::Borrowed(self.cast_into())
+    }
+}
+
+impl<'a, T: CppDeletable, U: CppDeletable> IntoArg<T> for &'a CppBox<U>
+where
+    U: StaticUpcast<T>,
+{
+    unsafe fn into_arg(self) -> ArgRef<T> {
+        ArgRef::Borrowed(self.cast_into())
+    }
+}
+
+impl<'a, T: CppDeletable, U: CppDeletable> IntoArg<T> for &'a Pin<CppBox<U>>
+where
+    U: StaticUpcast<T>,
+{
+    unsafe fn into_arg(self) -> ArgRef<T> {
+        ArgRef::Borrowed(self.cast_into())
+    }
+}
+
+impl<T: CppDeletable> IntoArg<T> for CppBox<T> {
+    unsafe fn into_arg(self) -> ArgRef<T> {
+        ArgRef::Temporary(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ArgRef, CppDeletable, IntoArg, Ptr, Ref, StaticUpcast};
+
+    #[repr(C)]
+    struct Base {
+        value: i32,
+    }
+
+    #[repr(C)]
+    struct Derived {
+        base: Base,
+    }
+
+    impl CppDeletable for Base {
+        unsafe fn delete(&self) {}
+    }
+
+    impl StaticUpcast<Base> for Derived {
+        unsafe fn static_upcast(ptr: Ptr<Derived>) -> Ptr<Base> {
+            Ptr::from_raw(&(*ptr.as_raw_ptr()).base)
+        }
+    }
+
+    #[test]
+    fn into_arg_accepts_derived_classes() {
+        let derived = Derived {
+            base: Base { value: 1 },
+        };
+        unsafe {
+            let arg: ArgRef<Base> = Ref::from_raw(&derived).unwrap().into_arg();
+            match &arg {
+                ArgRef::Borrowed(value) => assert_eq!(value.value, 1),
+                ArgRef::Temporary(_) => panic!("unexpected temporary"),
+            }
+            assert_eq!(arg.as_raw_ptr(), &derived.base as *const Base);
+        }
+    }
+}
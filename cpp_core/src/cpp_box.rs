@@ -1,7 +1,8 @@
 use crate::ops::{Begin, BeginMut, End, EndMut, Increment, Indirection};
 use crate::vector_ops::{Data, DataMut, Size};
-use crate::{cpp_iter, CppIterator, DynamicCast, Ptr, Ref, StaticDowncast, StaticUpcast};
+use crate::{cpp_iter, CppIterator, DynamicCast, MutRef, Ptr, Ref, StaticDowncast, StaticUpcast};
 use std::ops::Deref;
+use std::pin::Pin;
 use std::{fmt, mem, ptr, slice};
 
 /// Objects that can be deleted using C++'s `delete` operator.
@@ -129,6 +130,15 @@ impl<T: CppDeletable> CppBox<T> {
         ptr
     }
 
+    /// Converts the box into a pinned box.
+    ///
+    /// `CppBox` never moves the object it owns, so the object stays at the same
+    /// address until it's deleted. Classes that can't be moved after construction
+    /// are returned as `Pin<CppBox<T>>` by constructors.
+    pub fn into_pin(self) -> Pin<Self> {
+        unsafe { Pin::new_unchecked(self) }
+    }
+
     /// Destroys the box without deleting the object and returns a pointer to the content.
     /// The caller of the function becomes the owner of the object and should
     /// ensure that the object will be deleted at some point.
@@ -152,6 +162,16 @@ impl<T: CppDeletable> CppBox<T> {
         Ref::from_raw_non_null(self.0)
     }
 
+    /// Returns a non-const reference to the value in the box that can be passed
+    /// to C++ functions modifying the object.
+    ///
+    /// ### Safety
+    ///
+    /// This operation is safe as long as `self` is valid.
+    pub unsafe fn as_mut_ref(&mut self) -> MutRef<T> {
+        MutRef::from_raw_non_null(self.0)
+    }
+
     /// Returns a reference to the value.
     ///
     /// ### Safety
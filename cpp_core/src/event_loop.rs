@@ -0,0 +1,27 @@
+/// Event loop of a C++ library.
+///
+/// Generated crates implement this trait for the library's event loop
+/// if the loop functions are configured, allowing to integrate it with
+/// other event loops and async runtimes.
+pub trait EventLoop {
+    /// Runs the event loop in the current thread until `quit` is called.
+    ///
+    /// # Safety
+    ///
+    /// The library must be initialized as required by its event loop.
+    unsafe fn run(&self);
+
+    /// Asks the running event loop to stop.
+    ///
+    /// # Safety
+    ///
+    /// The library must be initialized as required by its event loop.
+    unsafe fn quit(&self);
+
+    /// Schedules `callback` to be called from the event loop.
+    ///
+    /// # Safety
+    ///
+    /// The library must be initialized as required by its event loop.
+    unsafe fn post(&self, callback: Box<dyn FnOnce() + Send + 'static>);
+}
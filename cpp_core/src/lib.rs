@@ -6,11 +6,16 @@
 //!
 //! # Pointers
 //!
-//! `cpp_core` provides three kinds of pointers:
+//! `cpp_core` provides four kinds of pointers:
 //!
 //! - `CppBox`: owned, non-null (corresponds to C++ objects passed by value)
 //! - `Ptr`: possibly owned, possibly null (correspond to C++ pointers)
-//! - `Ref`: not owned, non-null (correspond to C++ references)
+//! - `Ref`: not owned, non-null (correspond to C++ const references)
+//! - `MutRef`: not owned, non-null (correspond to C++ non-const references)
+//!
+//! Conversions between them follow C++'s const rules: a `MutRef` or a `&mut CppBox`
+//! can be passed where a `Ref` is expected, but a `Ref` or a `&CppBox` can't be passed
+//! where a `MutRef` is expected.
 //!
 //! Accessing objects through these pointers is inherently unsafe,
 //! as the compiler cannot make any guarantee about the validity of pointers to objects
@@ -50,27 +55,48 @@
 //! or even `Ptr<DerivedClass>` (where `DerivedClass` inherits `SomeClass`). You can also
 //! pass a null pointer object (`NullPtr`) if you don't have a value
 //! (`Ptr::null()` is also an option but it can cause type inference issues).
+//!
+//! # Asynchronous operations
+//!
+//! C++ functions that start an operation and report its result through a completion
+//! callback can be wrapped into `async`-friendly functions returning a `Completion`
+//! future that resolves when the callback is called (or to `Err(Canceled)` if the callback's
+//! arguments couldn't be converted).
+//!
+//! If the library has an event loop, the generated crate can implement the `EventLoop` trait
+//! for it, allowing to run the loop and schedule Rust closures onto it.
 
 #![deny(missing_docs)]
 
 pub use crate::casts::{DynamicCast, StaticDowncast, StaticUpcast};
-pub use crate::convert::{CastFrom, CastInto};
+pub use crate::class_metadata::{CppClass, CppClassMetadata};
+pub use crate::completion::{completion, Canceled, Completion, CompletionSender};
+pub use crate::convert::{ArgRef, CastFrom, CastInto, IntoArg};
 pub use crate::cpp_box::{CppBox, CppDeletable};
+pub use crate::event_loop::EventLoop;
 pub use crate::iterator::{cpp_iter, CppIterator, EndPtr};
+pub use crate::mut_ref::MutRef;
 pub use crate::ptr::{NullPtr, Ptr};
 pub use crate::ref_::Ref;
+pub use crate::time::TimeConversionError;
 pub use libc::wchar_t;
 
 mod casts;
+mod class_metadata;
 pub mod cmp;
+mod completion;
 mod convert;
 mod cpp_box;
+mod event_loop;
 mod iterator;
+mod mut_ref;
 pub mod ops;
 mod ops_impls;
 mod ptr;
 mod ref_;
+mod time;
 pub mod vector_ops;
+pub mod wide_string;
 
 // C++ doesn't guarantee these types to be exactly u16 and u32,
 // but they are on all supported platforms.
@@ -83,3 +109,15 @@ pub type char16_t = u16;
 /// any UTF-32 code unit (32 bits). Same as C++'s `char32_t` type.
 #[allow(non_camel_case_types)]
 pub type char32_t = u32;
+
+/// Same as C++'s `long double` type. It's only available on targets
+/// where `long double` has the same representation as `double`.
+/// Rust doesn't support extended precision floating point types
+/// used on other targets.
+#[cfg(any(
+    target_env = "msvc",
+    target_arch = "arm",
+    all(target_arch = "aarch64", any(target_os = "macos", target_os = "ios"))
+))]
+#[allow(non_camel_case_types)]
+pub type long_double = f64;
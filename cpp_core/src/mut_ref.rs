@@ -0,0 +1,357 @@
+use crate::ops::{Begin, BeginMut, End, EndMut, Increment, Indirection};
+use crate::vector_ops::{Data, DataMut, Size};
+use crate::{
+    cpp_iter, CppBox, CppDeletable, CppIterator, DynamicCast, Ptr, Ref, StaticDowncast,
+    StaticUpcast,
+};
+use std::ops::Deref;
+use std::{fmt, ptr, slice};
+
+/// A non-null pointer to a C++ object that may be modified through it
+/// (similar to a non-const C++ reference).
+///
+/// `MutRef` never owns its content.
+///
+/// `MutRef` is used for non-const references in arguments and return values
+/// of C++ functions, while `Ref` is used for const references. A `MutRef` can be
+/// converted to a `Ref` (including `Ref` to a base class) using `CastInto`
+/// or `as_ref`, but there is no safe conversion in the other direction,
+/// so a const object can't be passed where a non-const reference is expected.
+/// A `MutRef` to an object owned by a `CppBox` can be obtained
+/// with `CppBox::as_mut_ref`, which requires a mutable borrow of the box.
+///
+/// Note that unlike Rust references, `MutRef` can be freely copied,
+/// producing multiple mutable pointers to the same object, which is usually necessary
+/// to do when working with C++ libraries.
+///
+/// Like `Ref`, `MutRef` implements operator traits and delegates them
+/// to the corresponding C++ operators, and provides `as_slice` and `iter`
+/// helpers for objects with `data()`/`size()` or `begin()`/`end()` functions.
+///
+/// `MutRef` implements `Deref` allowing to call the object's methods
+/// directly. In addition, methods of the object's first base class are also directly available
+/// thanks to nested `Deref` implementations.
+///
+/// ### Safety
+///
+/// It's not possible to automatically track the ownership of objects possibly managed by C++
+/// libraries. The user must ensure that the object is alive while `MutRef` exists.
+pub struct MutRef<T>(ptr::NonNull<T>);
+
+/// Creates another pointer to the same object.
+impl<T> Clone for MutRef<T> {
+    fn clone(&self) -> Self {
+        MutRef(self.0)
+    }
+}
+
+/// Creates another pointer to the same object.
+impl<T> Copy for MutRef<T> {}
+
+impl<T> fmt::Debug for MutRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MutRef({:?})", self.0)
+    }
+}
+
+impl<T> MutRef<T> {
+    /// Creates a `MutRef` from a `Ptr`. Returns `None` if `ptr` is null.
+    ///
+    /// ### Safety
+    ///
+    /// `ptr` must be valid and must point to an object that may be modified.
+    /// See type level documentation.
+    pub unsafe fn new(ptr: Ptr<T>) -> Option<Self> {
+        Self::from_raw(ptr.as_mut_raw_ptr())
+    }
+
+    /// Creates a `MutRef` from a raw pointer. Returns `None` if `ptr` is null.
+    ///
+    /// ### Safety
+    ///
+    /// `ptr` must be valid. See type level documentation.
+    pub unsafe fn from_raw(ptr: *mut T) -> Option<Self> {
+        ptr::NonNull::new(ptr).map(MutRef)
+    }
+
+    /// Creates a `MutRef` from a non-null pointer.
+    ///
+    /// ### Safety
+    ///
+    /// `ptr` must be valid. See type level documentation.
+    pub unsafe fn from_raw_non_null(ptr: ptr::NonNull<T>) -> Self {
+        MutRef(ptr)
+    }
+
+    /// Converts `self` to a `Ptr`.
+    ///
+    /// ### Safety
+    ///
+    /// `self` must be valid. See type level documentation.
+    pub unsafe fn as_ptr(self) -> Ptr<T> {
+        Ptr::from_raw(self.as_raw_ptr())
+    }
+
+    /// Converts `self` to a constant reference.
+    ///
+    /// ### Safety
+    ///
+    /// `self` must be valid. See type level documentation.
+    #[allow(clippy::should_implement_trait)]
+    pub unsafe fn as_ref(self) -> Ref<T> {
+        Ref::from_raw_non_null(self.0)
+    }
+
+    /// Returns a reference to the value.
+    ///
+    /// ### Safety
+    ///
+    /// `self` must be valid.
+    /// The content must not be modified through other ways while the returned reference
+    /// exists. See type level documentation.
+    pub unsafe fn as_raw_ref<'a>(self) -> &'a T {
+        &*self.0.as_ptr()
+    }
+
+    /// Returns a mutable reference to the value.
+    ///
+    /// ### Safety
+    ///
+    /// `self` must be valid.
+    /// The content must not be read or modified through other ways while the returned reference
+    /// exists. See type level documentation.
+    pub unsafe fn as_mut_raw_ref<'a>(self) -> &'a mut T {
+        &mut *self.0.as_ptr()
+    }
+
+    /// Returns constant raw pointer to the value.
+    pub fn as_raw_ptr(self) -> *const T {
+        self.0.as_ptr()
+    }
+
+    /// Returns mutable raw pointer to the value.
+    pub fn as_mut_raw_ptr(self) -> *mut T {
+        self.0.as_ptr()
+    }
+
+    /// Converts the pointer to the base class type `U`.
+    ///
+    /// ### Safety
+    ///
+    /// This operation is safe as long as `self` is valid.
+    pub unsafe fn static_upcast<U>(self) -> MutRef<U>
+    where
+        T: StaticUpcast<U>,
+    {
+        StaticUpcast::static_upcast(self.as_ptr())
+            .as_mut_ref()
+            .expect("StaticUpcast returned null on MutRef input")
+    }
+
+    /// Converts the pointer to the derived class type `U`.
+    ///
+    /// It's recommended to use `dynamic_cast` instead because it performs a checked conversion.
+    ///
+    /// ### Safety
+    ///
+    /// This operation is safe as long as `self` is valid and it's type is `U` or inherits from `U`.
+    pub unsafe fn static_downcast<U>(self) -> MutRef<U>
+    where
+        T: StaticDowncast<U>,
+    {
+        StaticDowncast::static_downcast(self.as_ptr())
+            .as_mut_ref()
+            .expect("StaticDowncast returned null on MutRef input")
+    }
+
+    /// Converts the pointer to the derived class type `U`. Returns `None` if the object's type
+    /// is not `U` and doesn't inherit `U`.
+    ///
+    /// ### Safety
+    ///
+    /// This operation is safe as long as `self` is valid.
+    pub unsafe fn dynamic_cast<U>(self) -> Option<MutRef<U>>
+    where
+        T: DynamicCast<U>,
+    {
+        DynamicCast::dynamic_cast(self.as_ptr()).as_mut_ref()
+    }
+}
+
+impl<V, T> MutRef<V>
+where
+    V: Data<Output = *const T> + Size,
+{
+    /// Returns the content of the object as a slice, based on `data()` and `size()` methods.
+    ///
+    /// # Safety
+    ///
+    /// The caller must make sure `self` contains a valid pointer. The content must
+    /// not be read or modified through other ways while the returned slice exists.
+    /// This function
+    /// may invoke arbitrary foreign code, so no safety guarantees can be made.
+    pub unsafe fn as_slice<'a>(self) -> &'a [T] {
+        let ptr = self.data();
+        let size = self.size();
+        slice::from_raw_parts(ptr, size)
+    }
+}
+
+impl<V, T> MutRef<V>
+where
+    V: DataMut<Output = *mut T> + Size,
+{
+    /// Returns the content of the vector as a mutable slice,
+    /// based on `data()` and `size()` methods.
+    ///
+    /// # Safety
+    ///
+    /// The caller must make sure `self` contains a valid pointer. The content must
+    /// not be read or modified through other ways while the returned slice exists.
+    /// This function
+    /// may invoke arbitrary foreign code, so no safety guarantees can be made.
+    pub unsafe fn as_mut_slice<'a>(self) -> &'a mut [T] {
+        let ptr = self.data_mut();
+        let size = self.size();
+        slice::from_raw_parts_mut(ptr, size)
+    }
+}
+
+impl<T, T1, T2> MutRef<T>
+where
+    T: Begin<Output = CppBox<T1>> + End<Output = CppBox<T2>>,
+    T1: CppDeletable + PartialEq<Ref<T2>> + Increment + Indirection,
+    T2: CppDeletable,
+{
+    /// Returns an iterator over the content of the object,
+    /// based on `begin()` and `end()` methods.
+    ///
+    /// # Safety
+    ///
+    /// The caller must make sure `self` contains a valid pointer. The content must
+    /// not be read or modified through other ways while the returned slice exists.
+    /// This function
+    /// may invoke arbitrary foreign code, so no safety guarantees can be made.
+    pub unsafe fn iter(self) -> CppIterator<T1, T2> {
+        cpp_iter(self.begin(), self.end())
+    }
+}
+
+impl<T, T1, T2> MutRef<T>
+where
+    T: BeginMut<Output = CppBox<T1>> + EndMut<Output = CppBox<T2>>,
+    T1: CppDeletable + PartialEq<Ref<T2>> + Increment + Indirection,
+    T2: CppDeletable,
+{
+    /// Returns a mutable iterator over the content of the object,
+    /// based on `begin()` and `end()` methods.
+    ///
+    /// # Safety
+    ///
+    /// The caller must make sure `self` contains a valid pointer. The content must
+    /// not be read or modified through other ways while the returned slice exists.
+    /// This function
+    /// may invoke arbitrary foreign code, so no safety guarantees can be made.
+    pub unsafe fn iter_mut(self) -> CppIterator<T1, T2> {
+        cpp_iter(self.begin_mut(), self.end_mut())
+    }
+}
+
+/// Allows to call member functions of `T` and its base classes directly on the pointer.
+impl<T> Deref for MutRef<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.0.as_ref() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmp::{Ge, Gt, Le, Lt};
+    use crate::vector_ops::{Data, DataMut, Size};
+    use crate::MutRef;
+    use std::ops::Add;
+
+    #[derive(PartialEq)]
+    struct Value(i32);
+
+    impl<'a> Add<i32> for &'a Value {
+        type Output = i32;
+
+        fn add(self, rhs: i32) -> i32 {
+            self.0 + rhs
+        }
+    }
+
+    impl Lt for Value {
+        unsafe fn lt(&self, other: &Value) -> bool {
+            self.0 < other.0
+        }
+    }
+
+    impl Le for Value {
+        unsafe fn le(&self, other: &Value) -> bool {
+            self.0 <= other.0
+        }
+    }
+
+    impl Gt for Value {
+        unsafe fn gt(&self, other: &Value) -> bool {
+            self.0 > other.0
+        }
+    }
+
+    impl Ge for Value {
+        unsafe fn ge(&self, other: &Value) -> bool {
+            self.0 >= other.0
+        }
+    }
+
+    struct Vector(Vec<i32>);
+
+    impl Data for Vector {
+        type Output = *const i32;
+
+        unsafe fn data(&self) -> *const i32 {
+            self.0.as_ptr()
+        }
+    }
+
+    impl DataMut for Vector {
+        type Output = *mut i32;
+
+        unsafe fn data_mut(&self) -> *mut i32 {
+            self.0.as_ptr() as *mut i32
+        }
+    }
+
+    impl Size for Vector {
+        unsafe fn size(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    #[test]
+    fn operators() {
+        let mut value = Value(2);
+        unsafe {
+            let ptr = MutRef::from_raw(&mut value).unwrap();
+            assert_eq!(ptr + 3, 5);
+            assert!(ptr == Value(2));
+            assert!(ptr < Value(3));
+            assert!(ptr >= Value(2));
+        }
+    }
+
+    #[test]
+    fn slices() {
+        let mut vector = Vector(vec![1, 2, 3]);
+        unsafe {
+            let ptr = MutRef::from_raw(&mut vector).unwrap();
+            assert_eq!(ptr.as_slice(), &[1, 2, 3]);
+            ptr.as_mut_slice()[1] = 5;
+            assert_eq!(ptr.as_slice(), &[1, 5, 3]);
+        }
+    }
+}
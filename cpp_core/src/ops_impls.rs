@@ -1,6 +1,6 @@
 use crate::{
     cmp::{Ge, Gt, Le, Lt},
-    CppBox, CppDeletable, Ptr, Ref,
+    CppBox, CppDeletable, MutRef, Ptr, Ref,
 };
 use std::cmp::{Ordering, PartialEq, PartialOrd};
 use std::ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Rem, Shl, Shr, Sub};
@@ -39,6 +39,17 @@ macro_rules! define_op {
                 unsafe { (*self.as_raw_ptr()).$func(rhs) }
             }
         }
+
+        impl<T: 'static, U> $trait1<U> for MutRef<T>
+        where
+            &'static T: $trait1<U>,
+        {
+            type Output = <&'static T as $trait1<U>>::Output;
+
+            fn $func(self, rhs: U) -> Self::Output {
+                unsafe { (*self.as_raw_ptr()).$func(rhs) }
+            }
+        }
     };
 }
 
@@ -103,6 +114,7 @@ macro_rules! define_comparison_op {
 
 define_comparison_op!(Ptr);
 define_comparison_op!(Ref);
+define_comparison_op!(MutRef);
 
 impl<T: CppDeletable, U> PartialEq<U> for CppBox<T>
 where
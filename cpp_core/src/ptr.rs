@@ -1,7 +1,8 @@
 use crate::ops::{Begin, BeginMut, End, EndMut, Increment, Indirection};
 use crate::vector_ops::{Data, DataMut, Size};
 use crate::{
-    cpp_iter, CppBox, CppDeletable, CppIterator, DynamicCast, Ref, StaticDowncast, StaticUpcast,
+    cpp_iter, CppBox, CppDeletable, CppIterator, DynamicCast, MutRef, Ref, StaticDowncast,
+    StaticUpcast,
 };
 use std::ops::Deref;
 use std::{fmt, slice};
@@ -101,6 +102,16 @@ impl<T> Ptr<T> {
         Ref::from_raw(self.0)
     }
 
+    /// Returns the content as a `MutRef`. Returns `None` if `self` is a null pointer.
+    ///
+    /// ### Safety
+    ///
+    /// This operation is safe as long as `self` is valid and
+    /// points to an object that may be modified.
+    pub unsafe fn as_mut_ref(self) -> Option<MutRef<T>> {
+        MutRef::from_raw(self.as_mut_raw_ptr())
+    }
+
     /// Returns a reference to the value. Returns `None` if the pointer is null.
     ///
     /// ### Safety
@@ -6,9 +6,9 @@ use crate::{
 use std::ops::Deref;
 use std::{fmt, ptr, slice};
 
-/// A non-null, mutable pointer to a C++ object (similar to a C++ reference).
+/// A non-null pointer to a C++ object (similar to a C++ const reference).
 ///
-/// `Ref` never owns its content.
+/// `Ref` never owns its content. Non-const C++ references are represented by `MutRef`.
 ///
 /// Note that unlike Rust references, `Ref` can be freely copied,
 /// producing multiple mutable pointers to the same object, which is usually necessary
@@ -0,0 +1,26 @@
+//! Errors of conversions between C++ time types and `std::time` types.
+
+use std::error::Error;
+use std::fmt;
+
+/// Error returned when a C++ time value can't be converted to `SystemTime`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeConversionError {
+    /// The C++ value doesn't represent a valid time.
+    Invalid,
+    /// The time is out of range of `SystemTime`.
+    OutOfRange,
+}
+
+impl fmt::Display for TimeConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeConversionError::Invalid => write!(f, "invalid time value"),
+            TimeConversionError::OutOfRange => {
+                write!(f, "time value is out of range of SystemTime")
+            }
+        }
+    }
+}
+
+impl Error for TimeConversionError {}
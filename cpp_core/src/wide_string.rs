@@ -0,0 +1,242 @@
+//! Conversions between Rust strings and null-terminated wide C++ strings.
+//!
+//! `wchar_t` strings are UTF-16 on Windows and UTF-32 on other platforms.
+//! `char16_t` strings are always UTF-16. Generated crates use these functions
+//! to accept Rust strings where C++ functions expect wide strings. They can also
+//! be used to convert wide strings returned by C++ functions.
+
+use crate::{char16_t, wchar_t};
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::{fmt, slice};
+
+/// Error returned when a `wchar_t` string is not valid UTF-16 (on Windows)
+/// or UTF-32 (on other platforms).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FromWideStringError {
+    valid_up_to: usize,
+}
+
+impl FromWideStringError {
+    /// Returns the number of code units before the first invalid one.
+    pub fn valid_up_to(&self) -> usize {
+        self.valid_up_to
+    }
+}
+
+impl fmt::Display for FromWideStringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let encoding = if is_wchar_utf16() { "UTF-16" } else { "UTF-32" };
+        write!(
+            f,
+            "invalid {} code unit at index {}",
+            encoding, self.valid_up_to
+        )
+    }
+}
+
+impl Error for FromWideStringError {}
+
+/// Returns the units of a null-terminated string, excluding the terminator.
+unsafe fn units_until_nul<'a, T: Copy + Default + PartialEq>(ptr: *const T) -> &'a [T] {
+    let mut len = 0;
+    while *ptr.add(len) != T::default() {
+        len += 1;
+    }
+    slice::from_raw_parts(ptr, len)
+}
+
+fn is_wchar_utf16() -> bool {
+    std::mem::size_of::<wchar_t>() == 2
+}
+
+/// Encodes `text` as a null-terminated `wchar_t` string.
+pub fn to_wchar_nul(text: &str) -> Vec<wchar_t> {
+    let mut output: Vec<wchar_t> = if is_wchar_utf16() {
+        text.encode_utf16().map(|unit| unit as wchar_t).collect()
+    } else {
+        text.chars().map(|c| c as u32 as wchar_t).collect()
+    };
+    output.push(0);
+    output
+}
+
+/// Encodes `text` as a null-terminated `wchar_t` string.
+///
+/// On Windows, the conversion is lossless. On other platforms,
+/// invalid Unicode sequences are replaced with U+FFFD.
+pub fn os_str_to_wchar_nul(text: &OsStr) -> Vec<wchar_t> {
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        text.encode_wide()
+            .map(|unit| unit as wchar_t)
+            .chain(Some(0))
+            .collect()
+    }
+    #[cfg(not(windows))]
+    {
+        to_wchar_nul(&text.to_string_lossy())
+    }
+}
+
+/// Decodes a null-terminated `wchar_t` string. Returns `None`
+/// if it's not valid UTF-16 (on Windows) or UTF-32 (on other platforms).
+///
+/// # Safety
+///
+/// `ptr` must be a valid pointer to a null-terminated string.
+pub unsafe fn wchar_ptr_to_string(ptr: *const wchar_t) -> Option<String> {
+    wchar_slice_to_string(units_until_nul(ptr))
+}
+
+/// Decodes a `wchar_t` string. Returns `None`
+/// if it's not valid UTF-16 (on Windows) or UTF-32 (on other platforms).
+pub fn wchar_slice_to_string(units: &[wchar_t]) -> Option<String> {
+    try_wchar_slice_to_string(units).ok()
+}
+
+/// Decodes a `wchar_t` string. Returns an error describing the first invalid code unit
+/// if it's not valid UTF-16 (on Windows) or UTF-32 (on other platforms).
+pub fn try_wchar_slice_to_string(units: &[wchar_t]) -> Result<String, FromWideStringError> {
+    let mut output = String::new();
+    let mut valid_up_to = 0;
+    if is_wchar_utf16() {
+        for c in std::char::decode_utf16(units.iter().map(|&unit| unit as u16)) {
+            let c = c.map_err(|_| FromWideStringError { valid_up_to })?;
+            output.push(c);
+            valid_up_to += c.len_utf16();
+        }
+    } else {
+        for &unit in units {
+            let c = std::char::from_u32(unit as u32).ok_or(FromWideStringError { valid_up_to })?;
+            output.push(c);
+            valid_up_to += 1;
+        }
+    }
+    Ok(output)
+}
+
+/// Decodes a null-terminated `wchar_t` string, replacing invalid code units with U+FFFD.
+///
+/// # Safety
+///
+/// `ptr` must be a valid pointer to a null-terminated string.
+pub unsafe fn wchar_ptr_to_string_lossy(ptr: *const wchar_t) -> String {
+    wchar_slice_to_string_lossy(units_until_nul(ptr))
+}
+
+/// Decodes a `wchar_t` string, replacing invalid code units with U+FFFD.
+pub fn wchar_slice_to_string_lossy(units: &[wchar_t]) -> String {
+    if is_wchar_utf16() {
+        let units = units.iter().map(|&unit| unit as u16).collect::<Vec<_>>();
+        String::from_utf16_lossy(&units)
+    } else {
+        units
+            .iter()
+            .map(|&unit| {
+                std::char::from_u32(unit as u32).unwrap_or(std::char::REPLACEMENT_CHARACTER)
+            })
+            .collect()
+    }
+}
+
+/// Decodes a null-terminated `wchar_t` string. On Windows, the conversion is lossless.
+/// On other platforms, invalid code units are replaced with U+FFFD.
+///
+/// # Safety
+///
+/// `ptr` must be a valid pointer to a null-terminated string.
+pub unsafe fn wchar_ptr_to_os_string(ptr: *const wchar_t) -> OsString {
+    wchar_slice_to_os_string(units_until_nul(ptr))
+}
+
+/// Decodes a `wchar_t` string. On Windows, the conversion is lossless.
+/// On other platforms, invalid code units are replaced with U+FFFD.
+pub fn wchar_slice_to_os_string(units: &[wchar_t]) -> OsString {
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStringExt;
+        let units = units.iter().map(|&unit| unit as u16).collect::<Vec<_>>();
+        OsString::from_wide(&units)
+    }
+    #[cfg(not(windows))]
+    {
+        wchar_slice_to_string_lossy(units).into()
+    }
+}
+
+/// Encodes `text` as a null-terminated UTF-16 (`char16_t`) string.
+pub fn to_char16_nul(text: &str) -> Vec<char16_t> {
+    text.encode_utf16().chain(Some(0)).collect()
+}
+
+/// Decodes a null-terminated UTF-16 (`char16_t`) string. Returns `None` if it's not valid.
+///
+/// # Safety
+///
+/// `ptr` must be a valid pointer to a null-terminated string.
+pub unsafe fn char16_ptr_to_string(ptr: *const char16_t) -> Option<String> {
+    String::from_utf16(units_until_nul(ptr)).ok()
+}
+
+/// Decodes a null-terminated UTF-16 (`char16_t`) string,
+/// replacing invalid code units with U+FFFD.
+///
+/// # Safety
+///
+/// `ptr` must be a valid pointer to a null-terminated string.
+pub unsafe fn char16_ptr_to_string_lossy(ptr: *const char16_t) -> String {
+    String::from_utf16_lossy(units_until_nul(ptr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wchar_round_trip() {
+        let text = "abc \u{44f} \u{1f600}";
+        let wide = to_wchar_nul(text);
+        assert_eq!(wide.last(), Some(&0));
+        unsafe {
+            assert_eq!(wchar_ptr_to_string(wide.as_ptr()).as_deref(), Some(text));
+            assert_eq!(wchar_ptr_to_string_lossy(wide.as_ptr()), text);
+        }
+    }
+
+    #[test]
+    fn wchar_slice() {
+        let text = "abc \u{1f600}";
+        let wide = to_wchar_nul(text);
+        let units = &wide[..wide.len() - 1];
+        assert_eq!(wchar_slice_to_string(units).as_deref(), Some(text));
+        assert_eq!(wchar_slice_to_string_lossy(units), text);
+        assert_eq!(wchar_slice_to_os_string(units), OsString::from(text));
+        assert_eq!(wchar_slice_to_string(&[]).as_deref(), Some(""));
+    }
+
+    #[test]
+    fn invalid_wchar_slice() {
+        // an unpaired surrogate is invalid in both UTF-16 and UTF-32
+        let units = [0x61, 0x62, 0xd800, 0x63];
+        let err = try_wchar_slice_to_string(&units).unwrap_err();
+        assert_eq!(err.valid_up_to(), 2);
+        assert!(err.to_string().contains("at index 2"));
+        assert_eq!(wchar_slice_to_string(&units), None);
+    }
+
+    #[test]
+    fn char16_round_trip() {
+        let text = "abc \u{44f} \u{1f600}";
+        let wide = to_char16_nul(text);
+        unsafe {
+            assert_eq!(char16_ptr_to_string(wide.as_ptr()).as_deref(), Some(text));
+        }
+        let invalid = [0xd800, 0x61, 0];
+        unsafe {
+            assert_eq!(char16_ptr_to_string(invalid.as_ptr()), None);
+            assert_eq!(char16_ptr_to_string_lossy(invalid.as_ptr()), "\u{fffd}a");
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use crate::QDateTime;
+use cpp_core::{CppBox, Ptr, TimeConversionError};
+use std::convert::TryFrom;
+use std::num::TryFromIntError;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Allows to convert Qt date/times to `SystemTime`
+impl<'a> TryFrom<&'a QDateTime> for SystemTime {
+    type Error = TimeConversionError;
+
+    /// Converts the date/time to `SystemTime`. Fails if the value is invalid
+    /// or out of range of `SystemTime`.
+    fn try_from(value: &'a QDateTime) -> Result<Self, TimeConversionError> {
+        let mut millis = 0;
+        if !unsafe { crate::__ffi::qt_core_q_date_time_to_millis(value, &mut millis) } {
+            return Err(TimeConversionError::Invalid);
+        }
+        let duration = Duration::from_millis(i128::from(millis).abs() as u64);
+        if millis < 0 {
+            UNIX_EPOCH.checked_sub(duration)
+        } else {
+            UNIX_EPOCH.checked_add(duration)
+        }
+        .ok_or(TimeConversionError::OutOfRange)
+    }
+}
+
+impl QDateTime {
+    /// Creates a `QDateTime` from `SystemTime`. The value is truncated
+    /// to milliseconds. Fails if the time doesn't fit in `i64` milliseconds
+    /// since the epoch.
+    pub fn from_std_system_time(value: SystemTime) -> Result<CppBox<QDateTime>, TryFromIntError> {
+        // `Duration` always fits in `i128` milliseconds
+        let millis = match value.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_millis() as i128,
+            Err(err) => -(err.duration().as_millis() as i128),
+        };
+        let millis = i64::try_from(millis)?;
+        unsafe {
+            let ptr = Ptr::from_raw(crate::__ffi::qt_core_q_date_time_from_millis(millis));
+            Ok(CppBox::new(ptr).expect("conversion returned null"))
+        }
+    }
+}
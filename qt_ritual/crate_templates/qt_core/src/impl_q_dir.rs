@@ -0,0 +1,42 @@
+use crate::QDir;
+use cpp_core::{CppBox, Ptr};
+use std::ffi::c_void;
+use std::path::{Path, PathBuf};
+
+extern "C" fn copy_bytes(output: *mut c_void, data: *const u8, len: usize) {
+    unsafe {
+        let output = &mut *(output as *mut Vec<u8>);
+        output.extend_from_slice(std::slice::from_raw_parts(data, len));
+    }
+}
+
+/// Allows to convert Qt directories to `std` paths
+impl<'a> From<&'a QDir> for PathBuf {
+    /// Returns the absolute path of the directory.
+    fn from(value: &'a QDir) -> Self {
+        let mut bytes = Vec::new();
+        unsafe {
+            crate::__ffi::qt_core_q_dir_to_bytes(
+                value,
+                &mut bytes as *mut Vec<u8> as *mut c_void,
+                copy_bytes,
+            );
+        }
+        // Qt paths are always valid Unicode
+        String::from_utf8_lossy(&bytes).into_owned().into()
+    }
+}
+
+impl QDir {
+    /// Creates a `QDir` from an `std` path. The path must be valid Unicode.
+    pub fn from_std_path(value: impl AsRef<Path>) -> CppBox<QDir> {
+        let path = value.as_ref().to_string_lossy();
+        unsafe {
+            let ptr = Ptr::from_raw(crate::__ffi::qt_core_q_dir_from_bytes(
+                path.as_ptr(),
+                path.len(),
+            ));
+            CppBox::new(ptr).expect("conversion returned null")
+        }
+    }
+}
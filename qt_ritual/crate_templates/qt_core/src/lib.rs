@@ -5,6 +5,8 @@ mod impl_arguments_compatible;
 mod impl_ptr_ops;
 mod impl_q_byte_array;
 mod impl_q_core_application;
+mod impl_q_date_time;
+mod impl_q_dir;
 mod impl_q_object;
 mod impl_q_string;
 mod q_box;
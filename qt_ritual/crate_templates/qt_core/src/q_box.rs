@@ -1,6 +1,7 @@
 use crate::{QObject, QPtr};
 use cpp_core::{
-    CastFrom, CastInto, CppBox, CppDeletable, DynamicCast, Ptr, Ref, StaticDowncast, StaticUpcast,
+    CastFrom, CastInto, CppBox, CppDeletable, DynamicCast, MutRef, Ptr, Ref, StaticDowncast,
+    StaticUpcast,
 };
 use std::ops::Deref;
 use std::{fmt, mem};
@@ -120,6 +121,15 @@ impl<T: StaticUpcast<QObject> + CppDeletable> QBox<T> {
         self.0.as_ref()
     }
 
+    /// Returns the content as a `MutRef`. Returns `None` if `self` is a null pointer.
+    ///
+    /// ### Safety
+    ///
+    /// See type level documentation.
+    pub unsafe fn as_mut_ref(&self) -> Option<MutRef<T>> {
+        self.0.as_mut_ref()
+    }
+
     /// Returns a reference to the value. Returns `None` if the pointer is null.
     ///
     /// ### Safety
@@ -263,6 +273,19 @@ where
     }
 }
 
+/// Panics if the pointer is null.
+impl<'a, T, U> CastFrom<&'a QBox<U>> for MutRef<T>
+where
+    U: StaticUpcast<T> + StaticUpcast<QObject> + CppDeletable,
+{
+    unsafe fn cast_from(value: &'a QBox<U>) -> Self {
+        value
+            .as_mut_ref()
+            .expect("attempted to convert a null QBox to MutRef")
+            .static_upcast()
+    }
+}
+
 impl<T: StaticUpcast<QObject> + CppDeletable> Drop for QBox<T> {
     fn drop(&mut self) {
         unsafe {
@@ -1,6 +1,7 @@
 use crate::{QBox, QObject, QPointerOfQObject};
 use cpp_core::{
-    CastFrom, CastInto, CppBox, CppDeletable, DynamicCast, Ptr, Ref, StaticDowncast, StaticUpcast,
+    CastFrom, CastInto, CppBox, CppDeletable, DynamicCast, MutRef, Ptr, Ref, StaticDowncast,
+    StaticUpcast,
 };
 use std::fmt;
 use std::ops::Deref;
@@ -135,6 +136,15 @@ impl<T: StaticUpcast<QObject>> QPtr<T> {
         self.as_ptr().as_ref()
     }
 
+    /// Returns the content as a `MutRef`. Returns `None` if `self` is a null pointer.
+    ///
+    /// ### Safety
+    ///
+    /// See type level documentation.
+    pub unsafe fn as_mut_ref(&self) -> Option<MutRef<T>> {
+        self.as_ptr().as_mut_ref()
+    }
+
     /// Returns a reference to the value. Returns `None` if the pointer is null.
     ///
     /// ### Safety
@@ -284,3 +294,26 @@ where
         CastFrom::cast_from(value.as_ptr())
     }
 }
+
+/// Panics if the pointer is null.
+impl<'a, T, U> CastFrom<&'a QPtr<U>> for MutRef<T>
+where
+    U: StaticUpcast<T> + StaticUpcast<QObject>,
+{
+    unsafe fn cast_from(value: &'a QPtr<U>) -> Self {
+        value
+            .as_mut_ref()
+            .expect("attempted to convert a null QPtr to MutRef")
+            .static_upcast()
+    }
+}
+
+/// Panics if the pointer is null.
+impl<T, U> CastFrom<QPtr<U>> for MutRef<T>
+where
+    U: StaticUpcast<T> + StaticUpcast<QObject>,
+{
+    unsafe fn cast_from(value: QPtr<U>) -> Self {
+        CastFrom::cast_from(&value)
+    }
+}
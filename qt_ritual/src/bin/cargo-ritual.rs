@@ -0,0 +1,11 @@
+//! `cargo ritual` subcommand generating Rust-Qt crates for the current Cargo project.
+//!
+//! See `ritual::cli::run_cargo_subcommand` for details.
+
+use qt_ritual::lib_configs::global_config;
+use ritual::cli;
+use ritual_common::errors::FancyUnwrap;
+
+pub fn main() {
+    cli::run_cargo_subcommand(global_config()).fancy_unwrap();
+}
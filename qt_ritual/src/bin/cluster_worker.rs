@@ -12,6 +12,7 @@ use ritual_common::file_utils::create_dir;
 use ritual_common::target::current_target;
 use std::collections::HashMap;
 use std::env;
+use std::time::Duration;
 use tempdir::TempDir;
 
 const QUEUE_ADDRESS_VAR: &str = "QT_RITUAL_WORKER_QUEUE_ADDRESS";
@@ -102,6 +103,8 @@ fn run() -> Result<()> {
                     snippet: item.snippet,
                     data: RemoteSnippetTaskData { id: item.id },
                     output: None,
+                    repro_path: None,
+                    duration: Duration::default(),
                 })
                 .collect_vec();
             checker.binary_check(&mut snippets, None)?;
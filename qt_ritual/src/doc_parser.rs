@@ -216,6 +216,8 @@ impl DocParser {
                             mismatched_declaration: None,
                             url: Some(format!("{}#{}", file_url, item.anchor)),
                             cross_references: item.cross_references.clone(),
+                            cpp_cross_references: Vec::new(),
+                            examples: Vec::new(),
                         });
                     }
                 }
@@ -238,6 +240,8 @@ impl DocParser {
                             mismatched_declaration: None,
                             url: Some(format!("{}#{}", file_url, item.anchor)),
                             cross_references: item.cross_references.clone(),
+                            cpp_cross_references: Vec::new(),
+                            examples: Vec::new(),
                         });
                     }
                 }
@@ -261,6 +265,8 @@ impl DocParser {
                 url: Some(format!("{}#{}", file_url, candidates[0].anchor)),
                 mismatched_declaration: Some(candidates[0].declarations[0].clone()),
                 cross_references: candidates[0].cross_references.clone(),
+                cpp_cross_references: Vec::new(),
+                examples: Vec::new(),
             });
         }
         trace!("Declaration mismatch! Declaration 1: {}", declaration1);
@@ -294,6 +300,8 @@ impl DocParser {
                     html: result.html,
                     url: Some(format!("{}{}#{}", self.base_url, file_name, anchor)),
                     cross_references: result.cross_references,
+                    cpp_cross_references: Vec::new(),
+                    examples: Vec::new(),
                     anchor: None,
                     mismatched_declaration: None,
                 },
@@ -337,6 +345,8 @@ impl DocParser {
                 html,
                 url: Some(url),
                 cross_references: cross_references.into_iter().collect(),
+                cpp_cross_references: Vec::new(),
+                examples: Vec::new(),
                 anchor: None,
                 mismatched_declaration: None,
             },
@@ -797,6 +807,8 @@ pub fn parse_docs(
                                 mismatched_declaration: None,
                                 url: None,
                                 cross_references: vec![],
+                                cpp_cross_references: vec![],
+                                examples: vec![],
                             },
                         ));
                         parser.mark_enum_variant_used(&data.unscoped_path().doc_id());
@@ -878,6 +890,8 @@ pub fn set_crate_root_doc(data: &mut ProcessorData<'_>) -> Result<()> {
             mismatched_declaration: None,
             url: None,
             cross_references: Vec::new(),
+            cpp_cross_references: Vec::new(),
+            examples: Vec::new(),
         },
     );
 
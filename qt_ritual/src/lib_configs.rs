@@ -3,7 +3,7 @@
 use crate::detect_signals_and_slots::detect_signals_and_slots;
 use crate::doc_parser::{parse_docs, set_crate_root_doc};
 use crate::slot_wrappers::add_signal_slot_wrappers;
-use log::info;
+use log::{info, warn};
 use qt_ritual_common::{all_crate_names, get_full_build_config, lib_dependencies, lib_folder_name};
 use ritual::config::{Config, CrateDependencyKind, GlobalConfig};
 use ritual::config::{CrateDependencySource, CrateProperties};
@@ -11,6 +11,7 @@ use ritual_common::cpp_build_config::CppLibraryType;
 use ritual_common::cpp_build_config::{CppBuildConfigData, CppBuildPaths};
 use ritual_common::errors::{bail, format_err, Result, ResultExt};
 use ritual_common::file_utils::repo_dir_path;
+use ritual_common::library_locator::VersionProbe;
 use ritual_common::target;
 use ritual_common::toml;
 use std::path::{Path, PathBuf};
@@ -26,7 +27,12 @@ mod widgets;
 use self::_3d::{
     core_3d_config, extras_3d_config, input_3d_config, logic_3d_config, render_3d_config,
 };
-use self::{charts::charts_config, core::core_config, gui::gui_config, widgets::widgets_config};
+use self::{
+    charts::charts_config,
+    core::{core_config, moqt_core_config},
+    gui::gui_config,
+    widgets::widgets_config,
+};
 use crate::lib_configs::qml::qml_config;
 use crate::lib_configs::ui_tools::ui_tools_config;
 use ritual::cpp_data::{CppItem, CppPath};
@@ -183,6 +189,12 @@ pub fn create_config(
 
         config.add_target_include_path(&qt_config.installation_data.lib_include_path);
         config.set_cpp_lib_version(qt_config.installation_data.qt_version.as_str());
+        // headers may belong to another Qt installation than qmake;
+        // the C++ checker refuses to record results if the versions don't match
+        let probe = VersionProbe::header_macro("QtCore/qconfig.h", "QT_VERSION_STR");
+        if let Err(err) = config.detect_cpp_lib_version(probe) {
+            warn!("failed to detect Qt version from headers: {}", err);
+        }
         // TODO: does parsing work on MacOS without adding "-F"?
 
         config.add_include_directive(&lib_folder_name(&crate_name));
@@ -214,6 +226,8 @@ pub fn create_config(
     }
 
     config.add_after_cpp_parser_hook(detect_signals_and_slots);
+    // QObject subclasses may only be used from the thread they belong to
+    config.add_thread_affine_base_class(CppPath::from_good_str("QObject"));
 
     let steps = config.processing_steps_mut();
     for cpp_parser_stage in &["cpp_parser", "cpp_parser_stage2"] {
@@ -242,7 +256,7 @@ pub fn create_config(
         "qt_ui_tools" => ui_tools_config,
         "qt_charts" => charts_config,
         "qt_qml" => qml_config,
-        "moqt_core" => core_config,
+        "moqt_core" => moqt_core_config,
         "moqt_gui" => gui_config,
         _ => bail!("Unknown crate name: {}", crate_name),
     };
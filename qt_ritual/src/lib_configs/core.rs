@@ -1,5 +1,5 @@
 use itertools::Itertools;
-use ritual::config::{Config, CrateDependencyKind, CrateDependencySource};
+use ritual::config::{Config, CppInjectionPoint, CrateDependencyKind, CrateDependencySource};
 use ritual::cpp_checker::{PreliminaryTest, Snippet};
 use ritual::cpp_data::{CppItem, CppPath, CppPathItem, CppTypeDeclaration, CppTypeDeclarationKind};
 use ritual::cpp_ffi_data::CppFfiFunctionKind;
@@ -13,8 +13,27 @@ use ritual_common::errors::{bail, err_msg, Result};
 use ritual_common::file_utils::repo_dir_path;
 use ritual_common::string_utils::CaseOperations;
 
+/// Library wrapped with the QtCore configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CoreLibrary {
+    /// The real QtCore library.
+    Qt,
+    /// The test library mimicking QtCore (see `test_moqt.rs`).
+    /// It doesn't provide the types used by conversions to `std` types.
+    Moqt,
+}
+
 /// QtCore specific configuration.
 pub fn core_config(config: &mut Config) -> Result<()> {
+    core_library_config(config, CoreLibrary::Qt)
+}
+
+/// Configuration of `moqt_core`, the test library mimicking QtCore.
+pub fn moqt_core_config(config: &mut Config) -> Result<()> {
+    core_library_config(config, CoreLibrary::Moqt)
+}
+
+fn core_library_config(config: &mut Config, library: CoreLibrary) -> Result<()> {
     config.crate_properties_mut().add_dependency(
         "qt_macros",
         CrateDependencyKind::Normal,
@@ -291,7 +310,7 @@ pub fn core_config(config: &mut Config) -> Result<()> {
         Ok(())
     });
 
-    let tests = if config.crate_properties().name().starts_with("moqt") {
+    let tests = if library == CoreLibrary::Moqt {
         vec![PreliminaryTest::new(
             "moqt_abs",
             true,
@@ -335,6 +354,10 @@ pub fn core_config(config: &mut Config) -> Result<()> {
     };
     config.add_cpp_checker_tests(tests);
 
+    if library == CoreLibrary::Qt {
+        add_std_conversions(config);
+    }
+
     config.processing_steps_mut().add_after(
         &["cpp_parser"],
         "add_extra_cpp_items",
@@ -344,6 +367,42 @@ pub fn core_config(config: &mut Config) -> Result<()> {
     Ok(())
 }
 
+/// Adds helpers used by the conversions of `QDateTime` and `QDir`
+/// to `std` types (see `impl_q_date_time.rs` and `impl_q_dir.rs` in the crate template).
+fn add_std_conversions(config: &mut Config) {
+    config.add_cpp_code_injection(
+        CppInjectionPoint::ExternC,
+        "RITUAL_EXPORT bool qt_core_q_date_time_to_millis(const QDateTime* value, long long* millis) {
+  if (!value->isValid()) {
+    return false;
+  }
+  *millis = value->toMSecsSinceEpoch();
+  return true;
+}
+RITUAL_EXPORT QDateTime* qt_core_q_date_time_from_millis(long long millis) {
+  return new QDateTime(QDateTime::fromMSecsSinceEpoch(millis));
+}
+RITUAL_EXPORT void qt_core_q_dir_to_bytes(const QDir* value, void* output,
+  void (*callback)(void*, const char*, size_t)) {
+  QByteArray bytes = value->absolutePath().toUtf8();
+  callback(output, bytes.constData(), static_cast<size_t>(bytes.size()));
+}
+RITUAL_EXPORT QDir* qt_core_q_dir_from_bytes(const char* data, size_t len) {
+  return new QDir(QString::fromUtf8(data, static_cast<int>(len)));
+}",
+    );
+    config.add_ffi_declarations(
+        "pub fn qt_core_q_date_time_to_millis(value: *const crate::QDateTime, millis: *mut i64) -> bool;
+pub fn qt_core_q_date_time_from_millis(millis: i64) -> *mut crate::QDateTime;
+pub fn qt_core_q_dir_to_bytes(
+    value: *const crate::QDir,
+    output: *mut ::std::ffi::c_void,
+    callback: extern \"C\" fn(*mut ::std::ffi::c_void, *const u8, usize),
+);
+pub fn qt_core_q_dir_from_bytes(data: *const u8, len: usize) -> *mut crate::QDir;",
+    );
+}
+
 fn add_extra_cpp_items(data: &mut ProcessorData<'_>) -> Result<()> {
     add_find_child_methods(data)?;
     add_connection_to_bool(data)?;
@@ -403,6 +462,9 @@ fn add_qpointer(data: &mut ProcessorData<'_>) -> Result<()> {
                         ))]),
                     }),
                     kind: CppTypeDeclarationKind::Class,
+                    specialization: None,
+                    is_trivially_copyable: false,
+                    is_immovable: false,
                 }),
             )?;
         }
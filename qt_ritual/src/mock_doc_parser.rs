@@ -22,6 +22,8 @@ impl MockDocParser {
             mismatched_declaration: None,
             url: None,
             cross_references: vec![],
+            cpp_cross_references: vec![],
+            examples: vec![],
         })
     }
 
@@ -36,6 +38,8 @@ impl MockDocParser {
                 mismatched_declaration: None,
                 url: None,
                 cross_references: vec![],
+                cpp_cross_references: vec![],
+                examples: vec![],
             },
             enum_variants_doc: Vec::new(),
         })
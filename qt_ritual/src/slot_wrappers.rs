@@ -15,10 +15,16 @@ fn generate_slot_wrapper(
     name_provider: &mut FfiNameProvider,
 ) -> Result<QtSlotWrapper> {
     let ffi_types = arguments.map_if_ok(|t| ffi_type(&t, CppTypeRole::NotReturnType))?;
-    let class_path = name_provider.create_path(&format!(
-        "slot_wrapper_{}",
-        arguments.iter().map(CppType::ascii_caption).join("_")
-    ));
+    let class_path = name_provider.create_path(
+        &format!(
+            "slot_wrapper_{}",
+            arguments.iter().map(CppType::ascii_caption).join("_")
+        ),
+        &format!(
+            "slot_wrapper({})",
+            arguments.iter().map(CppType::to_cpp_pseudo_code).join(", ")
+        ),
+    );
 
     let void_ptr = CppType::PointerLike {
         is_const: false,
@@ -49,10 +55,16 @@ fn generate_signal_wrapper(
     arguments: &[CppType],
     name_provider: &mut FfiNameProvider,
 ) -> QtSignalWrapper {
-    let class_path = name_provider.create_path(&format!(
-        "signal_wrapper_{}",
-        arguments.iter().map(CppType::ascii_caption).join("_")
-    ));
+    let class_path = name_provider.create_path(
+        &format!(
+            "signal_wrapper_{}",
+            arguments.iter().map(CppType::ascii_caption).join("_")
+        ),
+        &format!(
+            "signal_wrapper({})",
+            arguments.iter().map(CppType::to_cpp_pseudo_code).join(", ")
+        ),
+    );
 
     QtSignalWrapper {
         class_path,
@@ -78,11 +90,14 @@ pub fn add_signal_slot_wrappers(data: &mut ProcessorData<'_>) -> Result<()> {
         } else {
             match generate_slot_wrapper(&arg_types, &mut name_provider) {
                 Ok(slot_wrapper) => {
-                    let id = data
-                        .db
-                        .add_ffi_item(None, CppFfiItem::QtSlotWrapper(slot_wrapper))?;
-                    if id.is_some() {
-                        trace!("adding slot wrapper for args: ({})", arg_types_text);
+                    let item = CppFfiItem::QtSlotWrapper(slot_wrapper);
+                    if data.db.is_ffi_item_rejected(&item.spec_key()) {
+                        trace!("slot wrapper rejected in FFI spec: {}", arg_types_text);
+                    } else {
+                        let id = data.db.add_ffi_item(None, item)?;
+                        if id.is_some() {
+                            trace!("adding slot wrapper for args: ({})", arg_types_text);
+                        }
                     }
                 }
                 Err(err) => {
@@ -103,12 +118,17 @@ pub fn add_signal_slot_wrappers(data: &mut ProcessorData<'_>) -> Result<()> {
         if found {
             trace!("signal wrapper already exists: {}", arg_types_text);
         } else {
-            let signal_wrapper = generate_signal_wrapper(&arg_types, &mut name_provider);
-            let id = data
-                .db
-                .add_ffi_item(None, CppFfiItem::QtSignalWrapper(signal_wrapper))?;
-            if id.is_some() {
-                trace!("adding signal wrapper for args: ({})", arg_types_text);
+            let item = CppFfiItem::QtSignalWrapper(generate_signal_wrapper(
+                &arg_types,
+                &mut name_provider,
+            ));
+            if data.db.is_ffi_item_rejected(&item.spec_key()) {
+                trace!("signal wrapper rejected in FFI spec: {}", arg_types_text);
+            } else {
+                let id = data.db.add_ffi_item(None, item)?;
+                if id.is_some() {
+                    trace!("adding signal wrapper for args: ({})", arg_types_text);
+                }
             }
         }
     }
@@ -9,6 +9,8 @@ use ritual_common::file_utils::{
     canonicalize, copy_recursively, create_dir, create_dir_all, create_file, file_to_string,
     read_dir, remove_dir_all, repo_dir_path,
 };
+use ritual_common::msvc::vcvars_environment;
+use ritual_common::target::current_target;
 use ritual_common::utils::add_env_path_item;
 use std::env;
 use std::io::Write;
@@ -61,6 +63,8 @@ fn build_moqt() -> Result<TempTestDir> {
         install_dir: Some(install_dir.clone()),
         num_jobs: None,
         cmake_vars: Vec::new(),
+        target: current_target(),
+        env_vars: vcvars_environment(&current_target())?,
         capture_output: false,
         skip_cmake: false,
         skip_cmake_after_first_run: false,
@@ -124,9 +128,17 @@ fn test_moqt() {
             local_paths: Some(true),
             crates: vec!["moqt_core".into(), "moqt_gui".into()],
             operations: vec!["discard".into(), "main".into()],
-            output_crates_version: "0.0.0".into(),
+            output_crates_version: Some("0.0.0".into()),
             cluster: None,
             trace: None,
+            explain: None,
+            browse: false,
+            reprocess: None,
+            dump_preprocessed: None,
+            jobs: None,
+            lenient: false,
+            retry_crashed_headers: false,
+            clean: None,
         },
         global_config(),
     )
@@ -46,6 +46,8 @@ fn add_vector_from_pointers_functions(data: &mut ProcessorData<'_>) -> Result<()
                 visibility: CppVisibility::Public,
                 is_signal: false,
                 is_slot: false,
+                is_explicit: false,
+                trivial_field_access: None,
             }),
             operator: None,
             return_type: CppType::Void,
@@ -96,6 +98,8 @@ fn add_vector_from_pointers_functions(data: &mut ProcessorData<'_>) -> Result<()
                 visibility: CppVisibility::Public,
                 is_signal: false,
                 is_slot: false,
+                is_explicit: false,
+                trivial_field_access: None,
             }),
             operator: None,
             return_type: CppType::Void,
@@ -198,6 +202,9 @@ fn add_vector_instantiations(data: &mut ProcessorData<'_>) -> Result<()> {
                     name: "vector".into(),
                     template_arguments: Some(vec![arg.clone(), allocator_type]),
                 }),
+                specialization: None,
+                is_trivially_copyable: false,
+                is_immovable: false,
             }),
         )?;
     }
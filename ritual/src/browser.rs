@@ -0,0 +1,262 @@
+//! Interactive command line browser of the database.
+
+use crate::cpp_data::CppItem;
+use crate::database::{DatabaseClient, DbItem, ItemId};
+use itertools::Itertools;
+use ritual_common::errors::Result;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+const MAX_SEARCH_RESULTS: usize = 30;
+
+const HELP: &str = "\
+Commands:
+    search <text>     fuzzy search of C++ items by qualified name
+    show <name>       show C++ items with the qualified name, their members
+                      and checker status in each environment
+    block <name>      don't generate FFI items for C++ items with the qualified name
+    unblock <name>    remove the block
+    blocked           list blocked names
+    crash <name> <reason>
+                      mark C++ items with the qualified name as known to crash
+                      at runtime; no FFI items are generated for them
+    uncrash <name>    remove the mark
+    crashes           list items known to crash
+    note <id> <text>  add a maintainer note to the item with the id (e.g. qt_core#42)
+    clear-notes <id>  remove all notes of the item
+    notes             list all notes
+    help              show this message
+    quit              exit the browser";
+
+/// Returns a score of `name` matching fuzzy `query` (lower is better),
+/// or `None` if characters of `query` don't appear in `name` in the same order.
+fn fuzzy_score(name: &str, query: &str) -> Option<usize> {
+    let name = name.to_lowercase();
+    let query = query.to_lowercase();
+    if name.contains(&query) {
+        return Some(name.len() - query.len());
+    }
+    let mut name_chars = name.chars();
+    let mut gaps = 0;
+    for query_char in query.chars() {
+        loop {
+            let c = name_chars.next()?;
+            if c == query_char {
+                break;
+            }
+            gaps += 1;
+        }
+    }
+    Some(name.len() + gaps)
+}
+
+fn item_name(item: &CppItem) -> Option<String> {
+    item.path().map(|path| path.to_templateless_string())
+}
+
+struct Browser<'a> {
+    db: &'a mut DatabaseClient,
+}
+
+impl Browser<'_> {
+    fn search(&self, query: &str) {
+        let results = self
+            .db
+            .cpp_items()
+            .filter_map(|item| item_name(item.item))
+            .unique()
+            .filter_map(|name| fuzzy_score(&name, query).map(|score| (score, name)))
+            .sorted()
+            .take(MAX_SEARCH_RESULTS)
+            .collect_vec();
+        if results.is_empty() {
+            println!("nothing found");
+        }
+        for (_, name) in results {
+            println!("{}", name);
+        }
+    }
+
+    fn print_annotations(&self, id: &ItemId, indent: &str) -> Result<()> {
+        for annotation in self.db.annotations(id)? {
+            println!(
+                "{}note (rev. {}, {}): {}",
+                indent, annotation.revision, annotation.date, annotation.text
+            );
+        }
+        Ok(())
+    }
+
+    fn print_checks(&self, item: &DbItem<&CppItem>, indent: &str) -> Result<()> {
+        let envs = self.db.environments();
+        for ffi_item in self.db.children(&item.id) {
+            if !ffi_item.item.is_ffi_item() {
+                continue;
+            }
+            let checks = self.db.cpp_checks(&ffi_item.id)?;
+            let status = envs
+                .iter()
+                .map(|env| {
+                    let result = if !checks.has_env(env) {
+                        "?"
+                    } else if checks.is_success(env) {
+                        "ok"
+                    } else {
+                        "failed"
+                    };
+                    format!("{}: {}", env.short_text(), result)
+                })
+                .join(", ");
+            println!(
+                "{}{}: {} [{}]",
+                indent,
+                ffi_item.id,
+                ffi_item.item.short_text(),
+                status
+            );
+            self.print_annotations(&ffi_item.id, &format!("{}    ", indent))?;
+        }
+        Ok(())
+    }
+
+    fn show(&self, name: &str) -> Result<()> {
+        let items = self
+            .db
+            .cpp_items()
+            .filter(|item| item_name(item.item).as_ref().map(String::as_str) == Some(name))
+            .collect_vec();
+        if items.is_empty() {
+            println!("no items named {}", name);
+            return Ok(());
+        }
+        if self
+            .db
+            .blocked_paths()
+            .any(|blocked_name| blocked_name == name)
+        {
+            println!("(blocked)");
+        }
+        if let Some((_, reason)) = self
+            .db
+            .known_crashes()
+            .find(|(crash_name, _)| *crash_name == name)
+        {
+            println!("(known to crash: {})", reason);
+        }
+        for item in items {
+            println!("{}: {}", item.id, item.item);
+            self.print_annotations(&item.id, "    ")?;
+            self.print_checks(&item, "    ")?;
+            if item.item.as_type_ref().is_some() {
+                for member in self.db.cpp_items() {
+                    let is_member = member
+                        .item
+                        .path()
+                        .and_then(|path| path.parent().ok())
+                        .map_or(false, |parent| parent.to_templateless_string() == name);
+                    if is_member && member.item.as_type_ref().is_none() {
+                        println!("    {}: {}", member.id, member.item);
+                        self.print_annotations(&member.id, "        ")?;
+                        self.print_checks(&member, "        ")?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn run_command(&mut self, line: &str) -> Result<bool> {
+        let mut parts = line.trim().splitn(2, ' ');
+        let command = parts.next().unwrap_or("");
+        let argument = parts.next().map(str::trim).unwrap_or("");
+        match command {
+            "" => {}
+            "search" => self.search(argument),
+            "show" => self.show(argument)?,
+            "block" | "unblock" if !argument.is_empty() => {
+                self.db.set_blocked(argument, command == "block");
+                println!("{}ed {}", command, argument);
+            }
+            "blocked" => {
+                for name in self.db.blocked_paths() {
+                    println!("{}", name);
+                }
+            }
+            "crash" => {
+                let mut parts = argument.splitn(2, ' ');
+                let name = parts.next().unwrap_or("");
+                let reason = parts.next().map(str::trim).unwrap_or("");
+                if name.is_empty() || reason.is_empty() {
+                    println!("usage: crash <name> <reason>");
+                } else {
+                    self.db.set_known_crash(name, Some(reason));
+                    println!("marked {} as known to crash", name);
+                }
+            }
+            "uncrash" if !argument.is_empty() => {
+                self.db.set_known_crash(argument, None);
+                println!("removed crash mark from {}", argument);
+            }
+            "crashes" => {
+                for (name, reason) in self.db.known_crashes() {
+                    println!("{}: {}", name, reason);
+                }
+            }
+            "note" => {
+                let mut parts = argument.splitn(2, ' ');
+                let id = parts.next().unwrap_or("");
+                let text = parts.next().map(str::trim).unwrap_or("");
+                if id.is_empty() || text.is_empty() {
+                    println!("usage: note <id> <text>");
+                } else {
+                    self.db.add_annotation(&ItemId::from_str(id)?, text)?;
+                    println!("added note to {}", id);
+                }
+            }
+            "clear-notes" if !argument.is_empty() => {
+                self.db.clear_annotations(&ItemId::from_str(argument)?)?;
+                println!("removed notes of {}", argument);
+            }
+            "notes" => {
+                for (item_text, annotations) in self.db.all_annotations() {
+                    println!("{}", item_text);
+                    for annotation in annotations {
+                        println!("    rev. {}: {}", annotation.revision, annotation.text);
+                    }
+                }
+            }
+            "quit" | "exit" => return Ok(false),
+            _ => println!("{}", HELP),
+        }
+        Ok(true)
+    }
+}
+
+/// Runs the interactive browser of the current crate's database.
+/// Changes of block flags are saved to the database by the caller.
+pub fn run(db: &mut DatabaseClient) -> Result<()> {
+    let mut browser = Browser { db };
+    println!("Browsing database of {}", browser.db.crate_name());
+    println!("{}", HELP);
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+        if !browser.run_command(&line)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn fuzzy_score_works() {
+    assert_eq!(fuzzy_score("QObject", "qobject"), Some(0));
+    assert_eq!(fuzzy_score("QObject::property", "property"), Some(9));
+    assert_eq!(fuzzy_score("QObject::property", "qoprop"), Some(24));
+    assert_eq!(fuzzy_score("QObject", "qx"), None);
+}
@@ -0,0 +1,171 @@
+//! Cache of parsed clang translation units.
+//!
+//! A translation unit is saved as a clang AST file together with the list of
+//! files it depends on. The cache key covers clang version, parser arguments and
+//! contents of the main file. The saved AST is reused only if contents of all
+//! included files are unchanged.
+
+use crate::clang_compat;
+use clang::{Entity, EntityKind, Index, TranslationUnit};
+use flate2::Crc;
+use log::{debug, trace};
+use ritual_common::errors::{format_err, Result, ResultExt};
+use ritual_common::file_utils::{create_dir_all, load_json, remove_file, save_json};
+use ritual_common::utils::MapIfOk;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Dependency {
+    path: PathBuf,
+    hash: u32,
+}
+
+/// Information about a saved translation unit.
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    /// Full cache key of the translation unit. File names only contain the key,
+    /// but the key is verified on load in case the file was written by another
+    /// version of the cache.
+    #[serde(default)]
+    key: String,
+    dependencies: Vec<Dependency>,
+}
+
+/// Returns CRC32 of `data`. Unlike `DefaultHasher`, the result is stable
+/// across Rust releases, so it can be saved to files.
+pub fn stable_hash(data: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+pub fn content_hash(path: &Path) -> Result<u32> {
+    let content = fs::read(path).with_context(|_| format!("failed to read {}", path.display()))?;
+    Ok(stable_hash(&content))
+}
+
+/// Returns all files included (directly or indirectly) by the translation unit.
+fn included_files(translation_unit: Entity<'_>) -> BTreeSet<PathBuf> {
+    translation_unit
+        .get_children()
+        .into_iter()
+        .filter(|entity| entity.get_kind() == EntityKind::InclusionDirective)
+        .filter_map(|entity| entity.get_file())
+        .map(|file| file.get_path())
+        .collect()
+}
+
+/// Storage of clang AST files in a directory.
+#[derive(Debug)]
+pub struct ClangCache {
+    path: PathBuf,
+}
+
+impl ClangCache {
+    pub fn new(path: PathBuf) -> Result<Self> {
+        create_dir_all(&path)?;
+        Ok(ClangCache { path })
+    }
+
+    /// Returns the cache key for parsing `main_files` with `arguments`.
+    pub fn key(arguments: &[String], main_files: &[&Path]) -> Result<String> {
+        let main_files = main_files
+            .iter()
+            .map_if_ok(|path| -> Result<_> { Ok((path, content_hash(path)?)) })?;
+        let data = serde_json::to_vec(&(clang_compat::version_text()?, arguments, main_files))?;
+        Ok(format!("{:08x}", stable_hash(&data)))
+    }
+
+    fn ast_path(&self, key: &str) -> PathBuf {
+        self.path.join(format!("{}.ast", key))
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.path.join(format!("{}.json", key))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns true if a translation unit is saved with `key`
+    /// and none of its dependencies has changed.
+    pub fn contains(&self, key: &str) -> Result<bool> {
+        if !self.ast_path(key).exists() || !self.entry_path(key).exists() {
+            return Ok(false);
+        }
+        let entry: CacheEntry = load_json(self.entry_path(key))?;
+        if entry.key != key {
+            debug!("cached translation unit has a different key: {}", entry.key);
+            self.remove(key)?;
+            return Ok(false);
+        }
+        for dependency in &entry.dependencies {
+            let is_valid = dependency.path.exists()
+                && content_hash(&dependency.path).ok() == Some(dependency.hash);
+            if !is_valid {
+                debug!(
+                    "cached translation unit is outdated because {} has changed",
+                    dependency.path.display()
+                );
+                self.remove(key)?;
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Loads the translation unit saved with `key`. Returns `None` if
+    /// there is no such translation unit or any of its dependencies has changed.
+    pub fn load<'i>(&self, index: &'i Index<'_>, key: &str) -> Result<Option<TranslationUnit<'i>>> {
+        if !self.contains(key)? {
+            return Ok(None);
+        }
+        let ast_path = self.ast_path(key);
+        match TranslationUnit::from_ast(index, &ast_path) {
+            Ok(tu) => {
+                debug!("loaded cached translation unit: {}", ast_path.display());
+                Ok(Some(tu))
+            }
+            Err(()) => {
+                debug!("failed to load AST file: {}", ast_path.display());
+                self.remove(key)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Saves `tu` with `key`.
+    pub fn save(&self, tu: &TranslationUnit<'_>, key: &str) -> Result<()> {
+        let dependencies =
+            included_files(tu.get_entity())
+                .into_iter()
+                .map_if_ok(|path| -> Result<_> {
+                    let hash = content_hash(&path)?;
+                    Ok(Dependency { path, hash })
+                })?;
+        trace!("translation unit dependencies: {:?}", dependencies);
+        let ast_path = self.ast_path(key);
+        tu.save(&ast_path)
+            .map_err(|err| format_err!("failed to save {}: {:?}", ast_path.display(), err))?;
+        let entry = CacheEntry {
+            key: key.to_string(),
+            dependencies,
+        };
+        save_json(self.entry_path(key), &entry, None)?;
+        debug!("saved translation unit to cache: {}", ast_path.display());
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<()> {
+        for path in &[self.ast_path(key), self.entry_path(key)] {
+            if path.exists() {
+                remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+//! Compatibility layer over the supported range of libclang versions.
+//!
+//! Some libclang functions used by the parser don't exist in older libclang
+//! releases. libclang is loaded at runtime (the `runtime` feature of `clang`),
+//! so missing functions don't prevent loading the library. The parser calls them
+//! through `ClangCompat` that checks the version of the loaded libclang
+//! and falls back to a degraded behavior instead of calling them.
+
+use clang::{get_version, Clang, Entity, Type};
+use itertools::Itertools;
+use log::warn;
+#[cfg(test)]
+use once_cell::sync::Lazy;
+use once_cell::sync::OnceCell;
+use ritual_common::errors::{bail, format_err, Result};
+use std::fmt;
+use std::ops::Deref;
+#[cfg(test)]
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Version of libclang.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ClangVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl ClangVersion {
+    pub const fn new(major: u32, minor: u32) -> Self {
+        ClangVersion { major, minor }
+    }
+
+    /// Parses the version from the output of `clang_getClangVersion`,
+    /// e.g. `clang version 8.0.0 (tags/RELEASE_800/final)`.
+    /// Versions of Apple's libclang are converted to the upstream
+    /// versions they are based on (see `apple_upstream_version`).
+    pub fn parse(text: &str) -> Option<Self> {
+        let words = text.split_whitespace().collect_vec();
+        let index = words.iter().position(|word| *word == "version")?;
+        let mut parts = words.get(index + 1)?.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next().and_then(|part| part.parse().ok()).unwrap_or(0);
+        if words[..index].contains(&"Apple") {
+            Some(apple_upstream_version(major))
+        } else {
+            Some(ClangVersion { major, minor })
+        }
+    }
+}
+
+/// Returns the oldest upstream libclang version that the Apple libclang
+/// with the major version `apple_major` can be based on. Apple uses
+/// its own version numbers, e.g. Apple LLVM 10.0.1 is based on LLVM 7.
+fn apple_upstream_version(apple_major: u32) -> ClangVersion {
+    match apple_major {
+        0..=6 => MINIMUM_VERSION,
+        7 => ClangVersion::new(3, 7),
+        8 => ClangVersion::new(3, 9),
+        9 => ClangVersion::new(4, 0),
+        10 => ClangVersion::new(6, 0),
+        11 => ClangVersion::new(8, 0),
+        _ => ClangVersion::new(10, 0),
+    }
+}
+
+impl fmt::Display for ClangVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// The oldest libclang version supported by the parser.
+pub const MINIMUM_VERSION: ClangVersion = ClangVersion::new(3, 5);
+
+/// A `Clang` context. libclang is loaded while the context exists.
+pub struct ClangContext {
+    clang: Clang,
+    /// Tests run in parallel, and only one context can exist at a time,
+    /// so the tests wait for this lock before creating a context.
+    /// The lock is released after `clang` is dropped.
+    #[cfg(test)]
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Deref for ClangContext {
+    type Target = Clang;
+
+    fn deref(&self) -> &Clang {
+        &self.clang
+    }
+}
+
+#[cfg(test)]
+/// Creates a `Clang` context, waiting until contexts created by other tests are dropped.
+pub fn init_clang() -> Result<ClangContext> {
+    static LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+    // a test that panicked while holding the lock has already dropped its context
+    let lock = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let clang = Clang::new().map_err(|err| format_err!("clang init failed: {}", err))?;
+    Ok(ClangContext { clang, _lock: lock })
+}
+
+#[cfg(not(test))]
+/// Creates a `Clang` context.
+pub fn init_clang() -> Result<ClangContext> {
+    let clang = Clang::new().map_err(|err| format_err!("clang init failed: {}", err))?;
+    Ok(ClangContext { clang })
+}
+
+/// Returns the version string of libclang (output of `clang_getClangVersion`).
+/// If the version is not known yet, libclang is loaded temporarily, so the first
+/// call must happen while the current thread doesn't hold a `Clang` context.
+pub fn version_text() -> Result<String> {
+    static TEXT: OnceCell<String> = OnceCell::new();
+    TEXT.get_or_try_init(|| -> Result<String> {
+        let _clang = init_clang()?;
+        Ok(get_version())
+    })
+    .map(Clone::clone)
+}
+
+/// Parser features that are not available with all supported libclang versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClangFeature {
+    /// Detection of bit fields and offsets of class fields.
+    FieldLayout,
+    /// Template arguments of types (e.g. types of fields and function arguments).
+    TypeTemplateArguments,
+}
+
+impl ClangFeature {
+    pub fn all() -> &'static [ClangFeature] {
+        &[
+            ClangFeature::FieldLayout,
+            ClangFeature::TypeTemplateArguments,
+        ]
+    }
+
+    /// Returns the first libclang version that provides this feature.
+    pub fn minimum_version(self) -> ClangVersion {
+        match self {
+            ClangFeature::FieldLayout => ClangVersion::new(3, 7),
+            ClangFeature::TypeTemplateArguments => ClangVersion::new(3, 9),
+        }
+    }
+
+    /// Describes how the output is affected if the feature is not available.
+    pub fn degradation(self) -> &'static str {
+        match self {
+            ClangFeature::FieldLayout => "bit fields and offsets of class fields are not detected",
+            ClangFeature::TypeTemplateArguments => {
+                "types using template instantiations are not supported"
+            }
+        }
+    }
+}
+
+/// Adapter for calling libclang functions that depend on the libclang version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClangCompat {
+    version: ClangVersion,
+}
+
+impl ClangCompat {
+    /// Returns the adapter for the loaded libclang.
+    pub fn current() -> Result<Self> {
+        static VERSION: OnceCell<ClangVersion> = OnceCell::new();
+        let version = *VERSION.get_or_try_init(|| -> Result<ClangVersion> {
+            let text = version_text()?;
+            Ok(ClangVersion::parse(&text).unwrap_or_else(|| {
+                warn!(
+                    "unrecognized libclang version: {:?}, assuming {}",
+                    text, MINIMUM_VERSION
+                );
+                MINIMUM_VERSION
+            }))
+        })?;
+        if version < MINIMUM_VERSION {
+            bail!(
+                "libclang {} is not supported (minimum version is {})",
+                version,
+                MINIMUM_VERSION
+            );
+        }
+        Ok(ClangCompat { version })
+    }
+
+    pub fn for_version(version: ClangVersion) -> Self {
+        ClangCompat { version }
+    }
+
+    pub fn version(&self) -> ClangVersion {
+        self.version
+    }
+
+    pub fn supports(&self, feature: ClangFeature) -> bool {
+        self.version >= feature.minimum_version()
+    }
+
+    /// Returns features that are not available with this libclang version.
+    pub fn degraded_features(&self) -> Vec<ClangFeature> {
+        ClangFeature::all()
+            .iter()
+            .cloned()
+            .filter(|feature| !self.supports(*feature))
+            .collect()
+    }
+
+    /// Logs a warning for each feature that is not available with this libclang version.
+    pub fn report_degraded_features(&self) {
+        for feature in self.degraded_features() {
+            warn!(
+                "libclang {} is older than {}: {}",
+                self.version,
+                feature.minimum_version(),
+                feature.degradation()
+            );
+        }
+    }
+
+    /// Returns true if `entity` is a bit field.
+    /// Returns `None` if the libclang version can't detect it.
+    pub fn is_bit_field(&self, entity: Entity<'_>) -> Option<bool> {
+        if self.supports(ClangFeature::FieldLayout) {
+            Some(entity.is_bit_field())
+        } else {
+            None
+        }
+    }
+
+    /// Returns offset of the field `name` of `record_type` in bits.
+    pub fn field_offset(&self, record_type: Type<'_>, name: &str) -> Option<usize> {
+        if self.supports(ClangFeature::FieldLayout) {
+            record_type.get_offsetof(name).ok()
+        } else {
+            None
+        }
+    }
+
+    /// Returns template arguments of `type1` if it's a template instantiation.
+    /// Returns an error for template instantiations if the libclang version
+    /// can't provide the arguments.
+    pub fn template_argument_types<'tu>(
+        &self,
+        type1: Type<'tu>,
+    ) -> Result<Option<Vec<Option<Type<'tu>>>>> {
+        if !self.supports(ClangFeature::TypeTemplateArguments) {
+            if !type1.get_display_name().contains('<') {
+                return Ok(None);
+            }
+            bail!(
+                "template arguments of types require libclang {} (loaded version is {})",
+                ClangFeature::TypeTemplateArguments.minimum_version(),
+                self.version
+            );
+        }
+        Ok(type1.get_template_argument_types())
+    }
+}
@@ -0,0 +1,150 @@
+//! Export of the class inheritance graph of the C++ library.
+
+use crate::cpp_checker::type_paths;
+use crate::cpp_data::{CppItem, CppTypeDeclarationKind};
+use crate::processor::ProcessorData;
+use itertools::Itertools;
+use log::info;
+use ritual_common::errors::Result;
+use ritual_common::file_utils::{create_dir_all, create_file, save_json};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ClassGraphEdgeKind {
+    /// `from` is a base class of `to`
+    Inheritance,
+    /// a method or a field of `from` uses `to` type
+    MemberType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ClassGraphEdge {
+    pub from: String,
+    pub to: String,
+    pub kind: ClassGraphEdgeKind,
+}
+
+/// Graph of classes of the current crate.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ClassGraph {
+    pub classes: BTreeSet<String>,
+    pub edges: BTreeSet<ClassGraphEdge>,
+}
+
+impl ClassGraph {
+    /// Collects the inheritance graph from class base items.
+    /// If `include_member_types` is true, also adds edges from each class
+    /// to classes used in its methods and fields.
+    pub fn new(data: &ProcessorData<'_>, include_member_types: bool) -> Self {
+        let items = data.db.cpp_items().map(|item| item.item).collect_vec();
+        Self::from_items(&items, include_member_types)
+    }
+
+    /// Collects the graph from `items` (see `new`).
+    pub fn from_items(items: &[&CppItem], include_member_types: bool) -> Self {
+        let mut graph = ClassGraph::default();
+        for &item in items {
+            match item {
+                CppItem::Type(type1) if type1.kind == CppTypeDeclarationKind::Class => {
+                    graph.classes.insert(type1.path.to_cpp_pseudo_code());
+                }
+                CppItem::ClassBase(base) => {
+                    graph.edges.insert(ClassGraphEdge {
+                        from: base.base_class_type.to_cpp_pseudo_code(),
+                        to: base.derived_class_type.to_cpp_pseudo_code(),
+                        kind: ClassGraphEdgeKind::Inheritance,
+                    });
+                }
+                _ => {}
+            }
+        }
+        if include_member_types {
+            for &item in items {
+                let class_path = match item {
+                    CppItem::Function(function) if function.member.is_some() => {
+                        function.class_path().ok()
+                    }
+                    CppItem::ClassField(field) => field.path.parent().ok(),
+                    CppItem::Variable(variable) if variable.is_static_member => {
+                        variable.path.parent().ok()
+                    }
+                    _ => None,
+                };
+                let class_name = match class_path {
+                    Some(path) => path.to_cpp_pseudo_code(),
+                    None => continue,
+                };
+                for type1 in item.all_involved_types() {
+                    for path in type_paths(&type1) {
+                        let name = path.to_cpp_pseudo_code();
+                        if name != class_name && graph.classes.contains(&name) {
+                            graph.edges.insert(ClassGraphEdge {
+                                from: class_name.clone(),
+                                to: name,
+                                kind: ClassGraphEdgeKind::MemberType,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    /// Writes the graph in Graphviz DOT format.
+    pub fn write_dot(&self, mut output: impl Write) -> Result<()> {
+        writeln!(output, "digraph classes {{")?;
+        writeln!(output, "    rankdir=LR;")?;
+        writeln!(output, "    node [shape=box];")?;
+        for class in &self.classes {
+            writeln!(output, "    {:?};", class)?;
+        }
+        for edge in &self.edges {
+            let style = match edge.kind {
+                ClassGraphEdgeKind::Inheritance => "",
+                ClassGraphEdgeKind::MemberType => " [style=dashed]",
+            };
+            writeln!(output, "    {:?} -> {:?}{};", edge.from, edge.to, style)?;
+        }
+        writeln!(output, "}}")?;
+        Ok(())
+    }
+}
+
+fn export(data: &mut ProcessorData<'_>, include_member_types: bool) -> Result<()> {
+    let graph = ClassGraph::new(data, include_member_types);
+    let dir = data.workspace.out_path().join("graphs");
+    create_dir_all(&dir)?;
+    let name = format!(
+        "{}_{}",
+        data.db.crate_name(),
+        if include_member_types {
+            "type_graph"
+        } else {
+            "class_graph"
+        }
+    );
+    let dot_path = dir.join(format!("{}.dot", name));
+    graph.write_dot(create_file(&dot_path)?)?;
+    save_json(dir.join(format!("{}.json", name)), &graph, None)?;
+    info!(
+        "Exported graph of {} classes and {} edges to {}",
+        graph.classes.len(),
+        graph.edges.len(),
+        dot_path.display()
+    );
+    Ok(())
+}
+
+/// Exports the class inheritance graph to DOT and JSON files.
+pub fn export_class_graph(data: &mut ProcessorData<'_>) -> Result<()> {
+    export(data, false)
+}
+
+/// Exports the class inheritance graph with member type dependencies
+/// to DOT and JSON files.
+pub fn export_type_graph(data: &mut ProcessorData<'_>) -> Result<()> {
+    export(data, true)
+}
@@ -3,17 +3,28 @@
 //! See [README](https://github.com/rust-qt/ritual)
 //! for more information.
 
-use crate::config::{CrateProperties, GlobalConfig};
+use crate::config::{CrateDependencyKind, CrateDependencySource, CrateProperties, GlobalConfig};
+use crate::cpp_parser;
+use crate::crate_graph::CrateGraph;
 use crate::database::ItemId;
-use crate::processor;
-use crate::workspace::Workspace;
+use crate::processor::{self, Inspection};
+use crate::workspace::{CleanScope, Workspace};
 use flexi_logger::{Duplicate, LevelFilter, LogSpecification, Logger};
 use itertools::Itertools;
 use log::{error, info};
-use ritual_common::errors::{bail, err_msg, Result};
-use ritual_common::file_utils::{canonicalize, create_dir, load_json, path_to_str};
+use ritual_common::env_var_names;
+use ritual_common::errors::{bail, err_msg, Result, ResultExt};
+use ritual_common::file_utils::{canonicalize, create_dir, create_dir_all, load_json, path_to_str};
 use ritual_common::target::current_target;
-use std::path::PathBuf;
+use ritual_common::utils::get_command_output;
+use serde_derive::Deserialize;
+use serde_json::Value;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Command};
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
@@ -26,27 +37,314 @@ pub struct Options {
     #[structopt(long = "local-paths")]
     /// Write local paths to `ritual` crates in generated `Cargo.toml`
     pub local_paths: Option<bool>,
-    #[structopt(short = "c", long = "crates", required = true)]
+    #[structopt(short = "c", long = "crates", raw(required_unless = r#""clean""#))]
     /// Crates to process (e.g. `qt_core`)
     pub crates: Vec<String>,
-    #[structopt(short = "o", long = "operations", required = true)]
+    #[structopt(short = "o", long = "operations", raw(required_unless = r#""clean""#))]
     /// Operations to perform
     pub operations: Vec<String>,
-    #[structopt(short = "v", long = "version")]
+    #[structopt(short = "v", long = "version", raw(required_unless = r#""clean""#))]
     /// Version of the output crates.
-    pub output_crates_version: String,
+    pub output_crates_version: Option<String>,
     #[structopt(long = "cluster")]
     /// Cluster configuration
     pub cluster: Option<PathBuf>,
     #[structopt(long = "trace")]
     /// ID of item to trace
     pub trace: Option<String>,
+    #[structopt(long = "explain")]
+    /// Qualified name of C++ item (e.g. `QObject::property`) to explain
+    /// what happened to it during processing
+    pub explain: Option<String>,
+    #[structopt(long = "browse")]
+    /// Run interactive browser of the database
+    pub browse: bool,
+    #[structopt(long = "reprocess")]
+    /// Qualified name of C++ item (e.g. `QObject::property`) to parse
+    /// and process again from scratch
+    pub reprocess: Option<String>,
+    #[structopt(long = "dump-preprocessed", parse(from_os_str))]
+    /// Include directive of a header (e.g. `QtCore/qobject.h`) to write
+    /// its preprocessed source and the declarations seen by clang
+    /// to the log directory
+    pub dump_preprocessed: Option<PathBuf>,
+    #[structopt(short = "j", long = "jobs")]
+    /// Number of independent crates to process in parallel
+    pub jobs: Option<usize>,
+    #[structopt(long = "lenient")]
+    /// Skip C++ constructs that can't be parsed instead of aborting
+    pub lenient: bool,
+    #[structopt(long = "retry-crashed-headers")]
+    /// Parse headers that crashed libclang before instead of excluding them
+    pub retry_crashed_headers: bool,
+    #[structopt(long = "clean")]
+    /// Remove data from the workspace before processing
+    /// (`logs`, `caches` or `all`)
+    pub clean: Option<CleanScope>,
 }
 
 pub fn run_from_args(config: GlobalConfig) -> Result<()> {
+    if cpp_parser::run_clang_subprocess()? {
+        return Ok(());
+    }
     run(Options::from_args(), config)
 }
 
+#[derive(Debug, StructOpt)]
+#[structopt(bin_name = "cargo")]
+enum CargoCommand {
+    #[structopt(name = "ritual")]
+    /// Generates crates for the current Cargo project.
+    /// Settings are read from `[package.metadata.ritual]` section of `Cargo.toml`,
+    /// and command line arguments override them.
+    Ritual(CargoOptions),
+}
+
+#[derive(Debug, StructOpt)]
+struct CargoOptions {
+    #[structopt(short = "c", long = "crates")]
+    /// Crates to process (e.g. `qt_core`)
+    crates: Vec<String>,
+    #[structopt(short = "o", long = "operations")]
+    /// Operations to perform (default: `main`)
+    operations: Vec<String>,
+    #[structopt(short = "v", long = "version")]
+    /// Version of the output crates (default: version of the current package)
+    output_crates_version: Option<String>,
+    #[structopt(long = "local-paths")]
+    /// Write local paths to `ritual` crates in generated `Cargo.toml`
+    local_paths: Option<bool>,
+    #[structopt(short = "j", long = "jobs")]
+    /// Number of independent crates to process in parallel
+    jobs: Option<usize>,
+    #[structopt(long = "lenient")]
+    /// Skip C++ constructs that can't be parsed instead of aborting
+    lenient: bool,
+}
+
+/// Contents of `[package.metadata.ritual]` (or `[workspace.metadata.ritual]`)
+/// section of `Cargo.toml`.
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CargoRitualMetadata {
+    #[serde(default)]
+    pub crates: Vec<String>,
+    #[serde(default)]
+    pub operations: Vec<String>,
+    pub version: Option<String>,
+    pub local_paths: Option<bool>,
+}
+
+fn cargo_json_output(args: &[&str]) -> Result<Value> {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".into());
+    let output = get_command_output(Command::new(cargo).args(args))?;
+    let value = serde_json::from_str(&output)
+        .with_context(|_| format!("invalid output of cargo {}", args.join(" ")))?;
+    Ok(value)
+}
+
+/// Returns ritual settings and the version of the package with `manifest_path`
+/// from the output of `cargo metadata`. In a virtual workspace, `manifest_path`
+/// is the workspace manifest, so there is no package and only
+/// `[workspace.metadata.ritual]` is used.
+pub fn cargo_ritual_metadata(
+    metadata: &Value,
+    manifest_path: &str,
+) -> Result<(CargoRitualMetadata, Option<String>)> {
+    let package = metadata["packages"].as_array().and_then(|packages| {
+        packages
+            .iter()
+            .find(|package| package["manifest_path"] == manifest_path)
+    });
+
+    let mut ritual_metadata =
+        package.map_or(&Value::Null, |package| &package["metadata"]["ritual"]);
+    if ritual_metadata.is_null() {
+        ritual_metadata = &metadata["metadata"]["ritual"];
+    }
+    let ritual_metadata = if ritual_metadata.is_null() {
+        CargoRitualMetadata::default()
+    } else {
+        serde_json::from_value(ritual_metadata.clone())
+            .with_context(|_| "invalid [package.metadata.ritual] section")?
+    };
+    let version = package
+        .and_then(|package| package["version"].as_str())
+        .map(ToString::to_string);
+    Ok((ritual_metadata, version))
+}
+
+/// Entry point of `cargo ritual` subcommand. Reads settings of the Cargo project
+/// in the current directory and uses `<target dir>/ritual` as the workspace,
+/// so the generated crates are placed in `<target dir>/ritual/out`.
+///
+/// If the program wasn't called by cargo, it falls back to `run_from_args`.
+pub fn run_cargo_subcommand(config: GlobalConfig) -> Result<()> {
+    if cpp_parser::run_clang_subprocess()? {
+        return Ok(());
+    }
+    if env::args().nth(1).as_ref().map(String::as_str) != Some("ritual") {
+        // child processes for parallel processing are called with regular arguments
+        return run_from_args(config);
+    }
+    let CargoCommand::Ritual(cargo_options) = CargoCommand::from_args();
+
+    let location = cargo_json_output(&["locate-project"])?;
+    let manifest_path = location["root"]
+        .as_str()
+        .ok_or_else(|| err_msg("cargo locate-project: project root is missing"))?;
+    let metadata = cargo_json_output(&["metadata", "--no-deps", "--format-version", "1"])?;
+    let (ritual_metadata, package_version) = cargo_ritual_metadata(&metadata, manifest_path)?;
+
+    let workspace = PathBuf::from(
+        metadata["target_directory"]
+            .as_str()
+            .ok_or_else(|| err_msg("cargo metadata: target directory is missing"))?,
+    )
+    .join("ritual");
+    create_dir_all(&workspace)?;
+
+    let crates = if cargo_options.crates.is_empty() {
+        ritual_metadata.crates
+    } else {
+        cargo_options.crates
+    };
+    if crates.is_empty() {
+        bail!(
+            "no crates specified (use --crates or `crates` key \
+             in [package.metadata.ritual] or [workspace.metadata.ritual])"
+        );
+    }
+    let mut operations = if cargo_options.operations.is_empty() {
+        ritual_metadata.operations
+    } else {
+        cargo_options.operations
+    };
+    if operations.is_empty() {
+        operations.push("main".to_string());
+    }
+    let output_crates_version = cargo_options
+        .output_crates_version
+        .or(ritual_metadata.version)
+        .or(package_version)
+        .ok_or_else(|| {
+            err_msg("output crates version is not specified (use --version or `version` key)")
+        })?;
+
+    let options = Options {
+        workspace,
+        local_paths: cargo_options.local_paths.or(ritual_metadata.local_paths),
+        crates,
+        operations,
+        output_crates_version: Some(output_crates_version),
+        cluster: None,
+        trace: None,
+        explain: None,
+        browse: false,
+        reprocess: None,
+        dump_preprocessed: None,
+        jobs: cargo_options.jobs,
+        lenient: cargo_options.lenient,
+        retry_crashed_headers: false,
+        clean: None,
+    };
+    run(options, config)
+}
+
+/// Options passed to child processes started by `run_jobs`.
+struct JobOptions<'a> {
+    workspace_path: &'a Path,
+    output_crates_version: &'a str,
+    operations: &'a [String],
+    local_paths: Option<bool>,
+    cluster: Option<&'a PathBuf>,
+    lenient: bool,
+    retry_crashed_headers: bool,
+}
+
+fn start_job(crate_name: &str, options: &JobOptions<'_>) -> Result<Child> {
+    info!("Starting child process for crate: {}", crate_name);
+    let mut command = Command::new(env::current_exe()?);
+    command
+        .env(
+            env_var_names::WORKSPACE_LOCK_OWNER,
+            process::id().to_string(),
+        )
+        .env(env_var_names::JOB_NAME, crate_name)
+        .arg(options.workspace_path)
+        .arg("--crates")
+        .arg(crate_name)
+        .arg("--version")
+        .arg(options.output_crates_version);
+    for operation in options.operations {
+        command.arg("--operations").arg(operation);
+    }
+    if let Some(local_paths) = options.local_paths {
+        command.arg("--local-paths").arg(local_paths.to_string());
+    }
+    if let Some(cluster_config_path) = options.cluster {
+        command.arg("--cluster").arg(cluster_config_path);
+    }
+    if options.lenient {
+        command.arg("--lenient");
+    }
+    if options.retry_crashed_headers {
+        command.arg("--retry-crashed-headers");
+    }
+    let child = command
+        .spawn()
+        .with_context(|_| format!("failed to run command: {:?}", command))?;
+    Ok(child)
+}
+
+/// Processes crates of `graph` in child processes. Up to `jobs` processes
+/// run at the same time, and a crate is started as soon as all crates
+/// it depends on are processed. If processing of any crate fails,
+/// the remaining child processes are terminated.
+fn run_jobs(graph: &CrateGraph, jobs: usize, options: &JobOptions<'_>) -> Result<()> {
+    let mut started = Vec::new();
+    let mut done = Vec::new();
+    let mut running = Vec::<(String, Child)>::new();
+    let result = (|| -> Result<()> {
+        loop {
+            for crate_name in graph.ready_crates(&started, &done) {
+                if running.len() >= jobs {
+                    break;
+                }
+                let child = start_job(&crate_name, options)?;
+                started.push(crate_name.clone());
+                running.push((crate_name, child));
+            }
+            if running.is_empty() {
+                return Ok(());
+            }
+            let mut index = 0;
+            while index < running.len() {
+                if let Some(status) = running[index].1.try_wait()? {
+                    let (crate_name, _) = running.remove(index);
+                    if !status.success() {
+                        bail!("processing crate {} failed with {}", crate_name, status);
+                    }
+                    info!("Child process finished for crate: {}", crate_name);
+                    done.push(crate_name);
+                } else {
+                    index += 1;
+                }
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    })();
+    if result.is_err() {
+        for (crate_name, child) in &mut running {
+            info!("Terminating child process for crate: {}", crate_name);
+            // the process may have exited already
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+    result
+}
+
 pub fn run(options: Options, mut config: GlobalConfig) -> Result<()> {
     if !options.workspace.exists() {
         create_dir(&options.workspace)?;
@@ -54,8 +352,17 @@ pub fn run(options: Options, mut config: GlobalConfig) -> Result<()> {
     let workspace_path = canonicalize(options.workspace)?;
 
     let mut workspace = Workspace::new(workspace_path.clone())?;
+    // logs are removed before the logger opens the log file
+    if let Some(scope) = options.clean {
+        workspace.clean(scope)?;
+    }
 
-    Logger::with(LogSpecification::default(LevelFilter::Trace).build())
+    let mut logger = Logger::with(LogSpecification::default(LevelFilter::Trace).build());
+    if let Ok(job_name) = env::var(env_var_names::JOB_NAME) {
+        // each child process started by `run_jobs` writes its own log file
+        logger = logger.discriminant(job_name);
+    }
+    logger
         .log_to_file()
         .directory(path_to_str(&workspace.log_path())?)
         .suppress_timestamp()
@@ -68,6 +375,9 @@ pub fn run(options: Options, mut config: GlobalConfig) -> Result<()> {
     info!("");
     info!("Workspace: {}", workspace_path.display());
     info!("Current target: {}", current_target().short_text());
+    if let Some(scope) = options.clean {
+        info!("Cleaned workspace: {:?}", scope);
+    }
 
     let mut was_any_action = false;
 
@@ -88,33 +398,38 @@ pub fn run(options: Options, mut config: GlobalConfig) -> Result<()> {
         .collect_vec();
 
     if operations.is_empty() {
-        error!("No action requested. Run \"qt_generator --help\".");
+        if options.clean.is_none() {
+            error!("No action requested. Run \"qt_generator --help\".");
+        }
         return Ok(());
     }
 
-    let trace_item_id = if let Some(text) = options.trace {
-        let mut parts = text.split('#');
-        let crate_name = parts
-            .next()
-            .ok_or_else(|| err_msg("invalid id format for trace"))?;
-        let id = parts
-            .next()
-            .ok_or_else(|| err_msg("invalid id format for trace"))?
-            .parse()?;
-        Some(ItemId::new(crate_name.to_string(), id))
+    let output_crates_version = options
+        .output_crates_version
+        .ok_or_else(|| err_msg("output crates version is not specified (use --version)"))?;
+
+    let inspection = if let Some(text) = options.trace {
+        Some(Inspection::Trace(ItemId::from_str(&text)?))
+    } else if let Some(name) = options.explain {
+        Some(Inspection::Explain(name))
+    } else if options.browse {
+        Some(Inspection::Browse)
+    } else if let Some(name) = options.reprocess {
+        Some(Inspection::Reprocess(name))
+    } else if let Some(header) = options.dump_preprocessed {
+        Some(Inspection::DumpPreprocessed(header))
     } else {
         None
     };
 
+    let mut configs = Vec::new();
+    let mut graph = CrateGraph::new();
     for crate_name in &final_crates {
         let create_config = config
             .create_config_hook()
             .ok_or_else(|| err_msg("create_config_hook is missing"))?;
 
-        let mut config = create_config(CrateProperties::new(
-            crate_name,
-            &options.output_crates_version,
-        ))?;
+        let mut config = create_config(CrateProperties::new(crate_name, &output_crates_version))?;
 
         if let Some(cluster_config_path) = &options.cluster {
             config.set_cluster_config(load_json(cluster_config_path)?);
@@ -123,9 +438,47 @@ pub fn run(options: Options, mut config: GlobalConfig) -> Result<()> {
         if let Some(local_paths) = options.local_paths {
             config.set_write_dependencies_local_paths(local_paths);
         }
+        config.set_lenient(options.lenient);
+        config.set_retry_crashed_headers(options.retry_crashed_headers);
+
+        let dependencies = config
+            .crate_properties()
+            .dependencies()
+            .iter()
+            .filter(|dep| {
+                dep.kind() == CrateDependencyKind::Ritual
+                    && *dep.source() == CrateDependencySource::CurrentWorkspace
+            })
+            .map(|dep| dep.name().to_string())
+            .collect_vec();
+        graph.add_crate(crate_name.clone(), dependencies);
+        configs.push(config);
+    }
 
+    let levels = graph.levels()?;
+    let jobs = options.jobs.unwrap_or(1);
+    if jobs > 1 && inspection.is_none() {
+        let job_options = JobOptions {
+            workspace_path: &workspace_path,
+            output_crates_version: &output_crates_version,
+            operations: &operations,
+            local_paths: options.local_paths,
+            cluster: options.cluster.as_ref(),
+            lenient: options.lenient,
+            retry_crashed_headers: options.retry_crashed_headers,
+        };
+        run_jobs(&graph, jobs, &job_options)?;
         was_any_action = true;
-        processor::process(&mut workspace, &config, &operations, trace_item_id.as_ref())?;
+    } else {
+        for crate_name in levels.iter().flatten() {
+            let config = configs
+                .iter()
+                .find(|config| config.crate_properties().name() == crate_name)
+                .expect("all crates must have configs");
+
+            was_any_action = true;
+            processor::process(&mut workspace, config, &operations, inspection.as_ref())?;
+        }
     }
 
     if was_any_action {
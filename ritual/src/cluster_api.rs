@@ -1,5 +1,5 @@
 use crate::config::ClusterConfig;
-use crate::cpp_checker::{LocalSnippetTask, Snippet, CHUNK_SIZE};
+use crate::cpp_checker::{snippet_chunks, LocalSnippetTask, Snippet};
 use amqp::{protocol::basic::BasicProperties, Basic, Channel, Session, Table};
 use log::{info, warn};
 use ritual_common::cpp_lib_builder::CppLibBuilderOutput;
@@ -77,8 +77,8 @@ pub fn run_checks(config: &ClusterConfig, tasks: &mut [LocalSnippetTask]) -> Res
             Table::new(),
         )?;
 
-        for (key, items) in group {
-            for chunk in items.chunks(CHUNK_SIZE) {
+        for (key, mut items) in group {
+            for chunk in snippet_chunks(&mut items, |item| &item.snippet) {
                 let task = Task {
                     launch_id: launch_id.clone(),
                     group_key: key.clone(),
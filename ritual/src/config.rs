@@ -1,17 +1,26 @@
 //! Interface for configuring and running the generator.
 
-use crate::cpp_checker::PreliminaryTest;
-use crate::cpp_data::{CppItem, CppPath};
+use crate::cpp_checker::{CrossCheckerEnv, PreliminaryTest};
+use crate::cpp_data::{CppItem, CppPath, CppTypeDeclaration};
+use crate::cpp_ffi_data::FfiCallingConvention;
 use crate::cpp_parser::CppParserOutput;
 use crate::processor::{ProcessingSteps, ProcessorData};
-use crate::rust_info::{NameType, RustItem, RustPathScope};
+use crate::rust_info::{EnumRepresentation, NameType, RustItem, RustPathScope};
 use crate::rust_type::RustPath;
-use ritual_common::cpp_build_config::{CppBuildConfig, CppBuildPaths};
+use ritual_common::android::{self, AndroidAbi, AndroidNdk};
+use ritual_common::compile_commands::CompilationDatabase;
+use ritual_common::cpp_build_config::{
+    CompilerFlagSet, CppBuildConfig, CppBuildConfigData, CppBuildPaths, CppStandard,
+};
 use ritual_common::errors::{bail, Result};
-use ritual_common::target::Target;
+use ritual_common::library_locator::{
+    self, LibraryLocation, LibrarySearch, OptionalModule, VersionProbe,
+};
+use ritual_common::target::{current_target, Condition, LibraryTarget, Target, OS};
 use ritual_common::toml;
 use serde_derive::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CrateDependencyKind {
@@ -182,6 +191,9 @@ pub type CppItemFilterHook = dyn Fn(&CppItem) -> Result<bool> + 'static;
 pub struct WorkerLibraryConfig {
     pub crate_name: String,
     pub lib_version: Option<String>,
+    /// Optional modules of the library available on the worker
+    #[serde(default)]
+    pub enabled_modules: BTreeSet<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -197,6 +209,66 @@ pub struct ClusterConfig {
     pub workers: Vec<WorkerConfig>,
 }
 
+/// Layout of rendered HTML documentation of the C++ library
+/// (see `Config::set_html_docs`). The defaults match the layout of Qt documentation.
+#[derive(Debug, Clone)]
+pub struct HtmlDocsConfig {
+    path: PathBuf,
+    base_url: String,
+    member_heading_tag: String,
+    member_heading_class: String,
+    description_class: String,
+}
+
+impl HtmlDocsConfig {
+    /// Creates a config for HTML files located in `path`.
+    /// `base_url` is the URL of the same files published online
+    /// and is used to create links to the original documentation.
+    pub fn new(path: impl Into<PathBuf>, base_url: impl Into<String>) -> Self {
+        HtmlDocsConfig {
+            path: path.into(),
+            base_url: base_url.into(),
+            member_heading_tag: "h3".into(),
+            member_heading_class: "fn".into(),
+            description_class: "descr".into(),
+        }
+    }
+
+    /// Sets the HTML tag and class of a heading that contains the declaration
+    /// of a documented function or enum. The documentation of the member
+    /// is the content between this heading and the next heading with the same tag.
+    pub fn set_member_heading(&mut self, tag: impl Into<String>, class: impl Into<String>) {
+        self.member_heading_tag = tag.into();
+        self.member_heading_class = class.into();
+    }
+
+    /// Sets the class of an HTML element containing detailed description of a class.
+    /// The documented class is determined by the first word of `h1` heading of the page.
+    pub fn set_description_class(&mut self, class: impl Into<String>) {
+        self.description_class = class.into();
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn member_heading_tag(&self) -> &str {
+        &self.member_heading_tag
+    }
+
+    pub fn member_heading_class(&self) -> &str {
+        &self.member_heading_class
+    }
+
+    pub fn description_class(&self) -> &str {
+        &self.description_class
+    }
+}
+
 /// The starting point of `ritual` API.
 /// Create a `Config` object, set its properties,
 /// add custom functions if necessary, and start
@@ -205,23 +277,314 @@ pub struct Config {
     // see setters documentation for information about these properties
     crate_properties: CrateProperties,
     cpp_lib_version: Option<String>,
+    detected_cpp_lib_version: Option<String>,
+    optional_modules: Vec<OptionalModule>,
+    compiler_flag_sets: Vec<CompilerFlagSet>,
     crate_template_path: Option<PathBuf>,
     include_directives: Vec<PathBuf>,
+    header_groups: Vec<Vec<PathBuf>>,
     target_include_paths: Vec<PathBuf>,
     cpp_build_config: CppBuildConfig,
     cpp_build_paths: CppBuildPaths,
     cpp_parser_arguments: Vec<String>,
+    compile_commands: Vec<CompilationDatabase>,
+    cpp_standard: Option<CppStandard>,
     processing_steps: ProcessingSteps,
     movable_types_hook: Option<Box<dyn Fn(&CppPath) -> Result<MovableTypesHookOutput>>>,
+    ffi_function_options_hook: Option<Box<dyn Fn(&CppPath) -> Result<FfiFunctionOptions>>>,
+    overload_strategy_hook: Option<Box<dyn Fn(&CppPath) -> Result<Option<OverloadStrategy>>>>,
+    pinned_types_hook: Option<Box<dyn Fn(&CppPath) -> Result<Option<bool>>>>,
+    safety_policy: SafetyPolicy,
+    thread_affine_base_classes: Vec<CppPath>,
+    function_safety_hook: Option<Box<dyn Fn(&CppPath) -> Result<Option<FunctionSafety>>>>,
+    allocation_strategy_hook: Option<Box<dyn Fn(&CppPath) -> Result<Option<AllocationStrategy>>>>,
+    plain_struct_hook: Option<Box<dyn Fn(&CppPath) -> Result<bool>>>,
+    slice_arguments_hook: Option<Box<dyn Fn(&CppPath) -> Result<bool>>>,
+    borrowed_return_hook: Option<Box<dyn Fn(&CppPath) -> Result<Option<bool>>>>,
+    enum_representation_hook: Option<Box<dyn Fn(&CppPath) -> Result<Option<EnumRepresentation>>>>,
     cpp_parser_path_hook: Option<Box<dyn Fn(&CppPath) -> Result<bool>>>,
     rust_path_scope_hook: Option<Box<RustPathScopeHook>>,
     rust_path_hook: Option<Box<RustPathHook>>,
     rust_item_hook: Option<Box<RustItemHook>>,
     after_cpp_parser_hooks: Vec<Box<AfterCppParserHook>>,
     cpp_item_filter_hook: Option<Box<CppItemFilterHook>>,
+    entry_points: Vec<CppPath>,
+    async_function_rules: Vec<AsyncFunctionRule>,
+    event_loop_functions: Option<EventLoopFunctions>,
+    lifecycle_functions: Option<LifecycleFunctions>,
+    cpp_code_injections: Vec<CppCodeInjection>,
+    ffi_declarations: Vec<String>,
+    optional_type_conversions: Vec<OptionalTypeConversion>,
+    handle_types: Vec<HandleType>,
+    plugin_registries: Vec<PluginRegistry>,
+    rtti_free: bool,
+    library_casts: HashMap<CppPath, CppPath>,
     cluster_config: Option<ClusterConfig>,
     cpp_checker_tests: Vec<PreliminaryTest>,
+    cross_checker_envs: Vec<CrossCheckerEnv>,
     write_dependencies_local_paths: bool,
+    lenient: bool,
+    retry_crashed_headers: bool,
+    doxygen_xml_path: Option<PathBuf>,
+    overrides_path: Option<PathBuf>,
+    doxygen_html_url: Option<String>,
+    html_docs: Option<HtmlDocsConfig>,
+    html_report_theme_path: Option<PathBuf>,
+    max_cpp_checker_repros: Option<usize>,
+    fuzz_path: Option<PathBuf>,
+    fuzz_ritual_source: Option<CrateDependencySource>,
+    cpp_doc_url_template: Option<String>,
+    callback_panic_behavior: CallbackPanicBehavior,
+    string_encoding: Option<StringEncoding>,
+    prelude_types: Vec<CppPath>,
+    root_reexports: Vec<CppPath>,
+    max_module_depth: Option<usize>,
+}
+
+/// Describes a C++ function that starts an asynchronous operation and reports
+/// its result by calling a completion callback (see `Config::add_async_function_rule`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsyncFunctionRule {
+    /// Path of the function. A path without template arguments
+    /// also matches all instantiations of the template.
+    pub function: CppPath,
+    /// Name of the function pointer argument called on completion.
+    /// The callback must return `void` and accept a `void*` user data argument.
+    /// Its other arguments form the result of the operation.
+    pub callback_argument: String,
+    /// Name of the `void*` argument passed back to the callback.
+    pub data_argument: String,
+}
+
+impl AsyncFunctionRule {
+    /// Returns true if the rule applies to the function at `path`.
+    pub fn matches(&self, path: &CppPath) -> bool {
+        path == &self.function
+            || (self.function.last().template_arguments.is_none()
+                && path.to_templateless_string() == self.function.to_templateless_string())
+    }
+}
+
+/// C++ functions controlling the event loop of the library
+/// (see `Config::set_event_loop_functions`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventLoopFunctions {
+    /// Function without arguments that runs the event loop until it's stopped.
+    /// Its return value is ignored.
+    pub run: CppPath,
+    /// Function without arguments that stops the running event loop.
+    pub quit: CppPath,
+    /// Function that schedules a call from the event loop. It must accept
+    /// a `void (*)(void*)` callback and a `void*` argument of the callback.
+    pub post: CppPath,
+}
+
+/// C++ functions initializing and shutting down the library
+/// (see `Config::set_lifecycle_functions`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LifecycleFunctions {
+    /// Function without arguments that initializes the library.
+    /// Its return value is ignored.
+    pub init: CppPath,
+    /// Function without arguments that shuts down the library.
+    pub shutdown: CppPath,
+}
+
+/// Mapping of a C++ type to a type provided by an optional dependency
+/// (see `Config::add_optional_type_conversion`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionalTypeConversion {
+    /// C++ type. Its wrapper type is always generated and serves as
+    /// the raw representation when the feature is disabled.
+    pub cpp_type: CppPath,
+    /// Name of the cargo feature of the generated crate that enables the conversion.
+    pub feature: String,
+    /// Name of the dependency crate.
+    pub dependency: String,
+    /// Source of the dependency crate.
+    pub dependency_source: CrateDependencySource,
+    /// Type of the dependency crate the C++ type is mapped to
+    /// (e.g. `chrono::NaiveDateTime`).
+    pub rust_type: RustPath,
+    /// Function `fn(&Wrapper) -> T` used to implement `From<&Wrapper> for T`,
+    /// where `T` is `rust_type`.
+    pub to_rust: Option<RustPath>,
+    /// Function `fn(&T) -> CppBox<Wrapper>` exposed as `Wrapper::from_<dependency>`.
+    pub from_rust: Option<RustPath>,
+}
+
+/// Integer typedef of a C library that identifies a resource
+/// (see `Config::add_handle_type`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandleType {
+    /// Path of the C++ typedef. The typedef must refer to an integer type.
+    pub typedef: CppPath,
+    /// Name of the Rust newtype generated in the crate root.
+    pub rust_name: String,
+    /// Function that releases the resource. It must accept a single argument
+    /// of the handle type. Its return value is ignored.
+    pub release: Option<CppPath>,
+}
+
+/// Registration entry points of a plugin system of the library
+/// (see `Config::add_plugin_registry`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginRegistry {
+    /// Name of the Rust module generated in the crate root.
+    pub rust_name: String,
+    /// Function that registers a plugin factory. It must accept a `const char*` key,
+    /// a `void* (*)(void*)` factory callback and a `void*` argument of the callback,
+    /// or a `const char*` key and a `void* (*)()` factory callback.
+    /// Its return value is ignored. It can also be a constructor of a registrar class
+    /// that is normally instantiated as a static object (e.g. by a registration macro).
+    /// The constructed object is never deleted.
+    pub register: CppPath,
+    /// Function that creates a plugin object using the factory registered
+    /// for the key. It must accept a `const char*` key and return a pointer.
+    pub create: Option<CppPath>,
+}
+
+/// Location in the C++ wrapper library where a code snippet is inserted
+/// (see `Config::add_cpp_code_injection`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CppInjectionPoint {
+    /// End of the global header, after the include directives of the library.
+    /// Suitable for extra includes and declarations. The header is also used
+    /// by the C++ checker.
+    GlobalHeader,
+    /// Source file, before the wrapper functions. Suitable for helper functions
+    /// used by custom wrappers.
+    BeforeWrappers,
+    /// Inside the `extern "C"` block of the source file, after the wrapper functions.
+    /// Suitable for custom wrappers. Use `Config::add_ffi_declarations` to declare
+    /// them on the Rust side.
+    ExternC,
+}
+
+/// C++ code inserted into the C++ wrapper library.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CppCodeInjection {
+    pub point: CppInjectionPoint,
+    pub code: String,
+}
+
+/// Per-item options of generated FFI functions
+/// (see `Config::set_ffi_function_options_hook`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FfiFunctionOptions {
+    /// Calling convention of the FFI function. Used in both C++ and Rust
+    /// declarations of the function.
+    pub calling_convention: FfiCallingConvention,
+    /// If true, the C++ wrapper is always compiled into the wrapper library
+    /// as a non-inline function, even if it's not used by the Rust API.
+    /// This allows to export APIs that are only available as inline
+    /// or header-only code in the C++ library.
+    pub out_of_line: bool,
+    /// If true, the function is only allowed to be called from the main thread
+    /// (e.g. most GUI APIs). The C++ wrapper checks the current thread at runtime
+    /// and aborts the process if it's not the main thread.
+    pub main_thread_only: bool,
+    /// If true, field accessors of a public field with a trivial type read and write
+    /// the field directly from Rust instead of calling the FFI function.
+    /// Methods defined in the class body that only return or assign such a field
+    /// (see `CppFunctionMemberData::trivial_field_access`) are also replaced.
+    /// Only fields of standard-layout classes are supported. The field offset
+    /// reported by clang is verified by the C++ checker, and the FFI function
+    /// is still used if the offset differs on any of the environments.
+    pub inline_field_access: bool,
+}
+
+/// Behavior of generated callback trampolines when the Rust closure panics.
+/// Unwinding into C++ code is undefined behavior, so panics are always caught
+/// at the FFI boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallbackPanicBehavior {
+    /// Abort the process.
+    Abort,
+    /// Print a message to stderr and return from the callback.
+    /// The panic message itself is reported by the panic hook.
+    Log,
+}
+
+impl Default for CallbackPanicBehavior {
+    fn default() -> Self {
+        CallbackPanicBehavior::Abort
+    }
+}
+
+/// Encoding of `char*` strings in the API of the C++ library
+/// (see `Config::set_string_encoding`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringEncoding {
+    Utf8,
+    /// ISO 8859-1. Characters above U+00FF can't be represented.
+    Latin1,
+    /// Encoding of the current C locale (as used by `mbstowcs` and `wcstombs`).
+    /// Conversions are done by the C++ wrapper library at runtime.
+    /// If `wchar_t` is 16-bit (e.g. on Windows), characters outside of the BMP
+    /// can't be encoded.
+    Locale,
+}
+
+/// Naming strategy for Rust methods generated for overloaded C++ functions
+/// (see `Config::set_overload_strategy_hook`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverloadStrategy {
+    /// Add suffixes based on the arguments (e.g. `resize_2a` or `from_q_string`).
+    Caption,
+    /// Number the overloads (e.g. `resize`, `resize1`, `resize2`). Numbers are assigned
+    /// in order of argument count and C++ signature and recorded in the workspace,
+    /// so adding or removing an overload doesn't change the names of other overloads.
+    Numbered,
+    /// Generate a single generic method taking a tuple of arguments
+    /// (e.g. `resize((10, 20))`) that calls the overload matching the types
+    /// of the arguments. Each overload is also available under a name selected
+    /// by the `Caption` strategy. If the overloads can't be distinguished
+    /// by argument types, only the `Caption` names are generated.
+    ArgsTuple,
+}
+
+/// Default safety of the generated wrapper functions (see `Config::set_safety_policy`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SafetyPolicy {
+    /// All wrapper functions are `unsafe`.
+    UnsafeByDefault,
+    /// Wrapper functions are safe unless they take or return unchecked pointers,
+    /// transfer ownership of the returned object to the caller
+    /// or operate on thread-affine objects (see `Config::add_thread_affine_base_class`).
+    SafeByDefault,
+}
+
+/// Safety of a wrapper function selected by `Config::set_function_safety_hook`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FunctionSafety {
+    Safe,
+    Unsafe,
+}
+
+/// Allocation strategy for objects of a C++ class created by
+/// the generated constructors (see `Config::set_allocation_strategy_hook`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AllocationStrategy {
+    /// Allocate objects with `new` and free them with `delete`.
+    Heap,
+    /// Create objects by calling `create` with the arguments of the constructor
+    /// and free them by calling `destroy` with a pointer to the object.
+    /// `create` must return a pointer to the object. Values returned
+    /// by other functions are passed to `create` as its only argument,
+    /// so such functions are only wrapped if `create` can copy the object.
+    Factory { create: CppPath, destroy: CppPath },
+    /// Construct objects in buffers owned by Rust values. Rust moves values
+    /// by copying their bytes, so this is only valid for trivially relocatable types.
+    /// Only trivially copyable classes are allocated in place. Other classes
+    /// are allocated on the heap.
+    InPlace,
+}
+
+impl Default for AllocationStrategy {
+    fn default() -> Self {
+        AllocationStrategy::Heap
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -241,11 +604,28 @@ impl Config {
             cpp_build_paths: Default::default(),
             target_include_paths: Default::default(),
             include_directives: Default::default(),
+            header_groups: Default::default(),
             cpp_parser_arguments: Default::default(),
+            compile_commands: Default::default(),
+            cpp_standard: Default::default(),
             cpp_build_config: Default::default(),
             movable_types_hook: Default::default(),
+            ffi_function_options_hook: Default::default(),
+            overload_strategy_hook: Default::default(),
+            pinned_types_hook: Default::default(),
+            safety_policy: SafetyPolicy::UnsafeByDefault,
+            thread_affine_base_classes: Vec::new(),
+            function_safety_hook: Default::default(),
+            allocation_strategy_hook: Default::default(),
+            plain_struct_hook: Default::default(),
+            slice_arguments_hook: Default::default(),
+            borrowed_return_hook: Default::default(),
+            enum_representation_hook: Default::default(),
             processing_steps: Default::default(),
             cpp_lib_version: Default::default(),
+            detected_cpp_lib_version: Default::default(),
+            optional_modules: Default::default(),
+            compiler_flag_sets: Default::default(),
             cpp_parser_path_hook: Default::default(),
             rust_path_scope_hook: Default::default(),
             rust_path_hook: Default::default(),
@@ -254,7 +634,35 @@ impl Config {
             cpp_item_filter_hook: Default::default(),
             cluster_config: None,
             cpp_checker_tests: Default::default(),
+            cross_checker_envs: Default::default(),
+            entry_points: Default::default(),
+            async_function_rules: Default::default(),
+            event_loop_functions: None,
+            lifecycle_functions: None,
+            cpp_code_injections: Vec::new(),
+            ffi_declarations: Vec::new(),
+            optional_type_conversions: Vec::new(),
+            handle_types: Vec::new(),
+            plugin_registries: Vec::new(),
+            rtti_free: false,
+            library_casts: Default::default(),
             write_dependencies_local_paths: true,
+            lenient: false,
+            retry_crashed_headers: false,
+            doxygen_xml_path: None,
+            overrides_path: None,
+            doxygen_html_url: None,
+            html_docs: None,
+            html_report_theme_path: None,
+            max_cpp_checker_repros: None,
+            fuzz_path: None,
+            fuzz_ritual_source: None,
+            cpp_doc_url_template: None,
+            callback_panic_behavior: CallbackPanicBehavior::default(),
+            string_encoding: None,
+            prelude_types: Vec::new(),
+            root_reexports: Vec::new(),
+            max_module_depth: None,
         }
     }
 
@@ -302,6 +710,27 @@ impl Config {
         }
     }
 
+    /// Selects the C++ standard used by the C++ parser, the checker and
+    /// the build of the wrapper library. The C++ checker marks items using types
+    /// of the standard library introduced in a newer standard (e.g. `std::optional`
+    /// in C++17) as unavailable unless they are enabled by a compiler flag set
+    /// selecting a newer standard (see `add_compiler_flag_set`).
+    /// If not set, the parser uses C++11, and the checker and the build use
+    /// the compiler's default standard.
+    ///
+    /// This function should be called at most once.
+    pub fn set_cpp_standard(&mut self, standard: CppStandard) {
+        self.cpp_standard = Some(standard);
+        let mut data = CppBuildConfigData::new();
+        data.set_cpp_standard(standard);
+        self.cpp_build_config.add(Condition::True, data);
+    }
+
+    /// Returns the C++ standard selected with `set_cpp_standard`.
+    pub fn cpp_standard(&self) -> Option<CppStandard> {
+        self.cpp_standard
+    }
+
     /// Sets `CppBuildPaths` value for this config. These paths
     /// are used for testing C++ methods while processing the library,
     /// but they are not used when building the generated crate.
@@ -309,6 +738,78 @@ impl Config {
         self.cpp_build_paths = paths;
     }
 
+    /// Returns mutable `CppBuildPaths` value of this config.
+    pub fn cpp_build_paths_mut(&mut self) -> &mut CppBuildPaths {
+        &mut self.cpp_build_paths
+    }
+
+    /// Adds a macOS framework to the libraries linked by the wrapper library,
+    /// the checker and the generated crate. If `search_path` is specified,
+    /// it's used as a framework search path (`-F`) for the C++ parser
+    /// and the checker.
+    pub fn add_framework(&mut self, name: impl Into<String>, search_path: Option<PathBuf>) {
+        if let Some(path) = search_path {
+            self.cpp_build_paths.add_framework_path(path);
+        }
+        let mut data = CppBuildConfigData::new();
+        data.add_linked_framework(name);
+        self.cpp_build_config.add(Condition::OS(OS::MacOS), data);
+    }
+
+    /// Configures the C++ parser to parse headers as if they were compiled
+    /// for Android `abi`, using the sysroot of the NDK found in the environment
+    /// and the API level specified by `ANDROID_PLATFORM` env var.
+    ///
+    /// Use `LibraryTarget::android` to refer to the corresponding target.
+    pub fn add_android_parser_arguments(&mut self, abi: AndroidAbi) -> Result<()> {
+        let ndk = AndroidNdk::find()?;
+        let args = ndk.clang_arguments(abi, android::api_level()?)?;
+        self.add_cpp_parser_arguments(args);
+        Ok(())
+    }
+
+    /// Reads the compilation database (`compile_commands.json`, e.g. produced by CMake
+    /// with `CMAKE_EXPORT_COMPILE_COMMANDS=ON`). If `source_dir` is specified, only
+    /// compilations of files in this directory are used.
+    ///
+    /// Include and framework paths of all compilations are added to `CppBuildPaths`.
+    /// Macro definitions and other flags are selected for each include directive
+    /// (see `CompilationDatabase::header_flags`) and used by the C++ parser,
+    /// the checker and the wrapper library build.
+    pub fn add_compile_commands<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        source_dir: Option<&Path>,
+    ) -> Result<()> {
+        let database = CompilationDatabase::load(path.as_ref(), source_dir)?;
+        database.all_paths().apply_paths(&mut self.cpp_build_paths);
+        self.compile_commands.push(database);
+        Ok(())
+    }
+
+    /// Finds an installed library using `pkg-config` or CMake and adds
+    /// discovered include and library paths to `CppBuildPaths` used by
+    /// the C++ parser and the checker. Discovered compiler flags are passed to
+    /// the C++ parser as well.
+    ///
+    /// The search is also recorded in `CppBuildConfig`, so the build script of
+    /// the generated crate repeats it on the target machine instead of using
+    /// the paths found here. If the library version was not set
+    /// with `Config::set_cpp_lib_version`, the discovered version is used.
+    pub fn locate_library(&mut self, search: LibrarySearch) -> Result<LibraryLocation> {
+        let location = library_locator::locate(&search)?;
+        location.apply_paths(&mut self.cpp_build_paths);
+        self.add_cpp_parser_arguments(location.compiler_flags().iter().cloned());
+        if self.cpp_lib_version.is_none() {
+            self.cpp_lib_version = location.version().map(String::from);
+        }
+
+        let mut data = CppBuildConfigData::new();
+        data.add_library_search(search);
+        self.cpp_build_config.add(Condition::True, data);
+        Ok(location)
+    }
+
     /// Adds path to an include directory or an include file
     /// of the target library.
     /// Any C++ types and methods will be parsed and used only
@@ -329,6 +830,29 @@ impl Config {
         self.include_directives.push(path.into());
     }
 
+    /// Adds a group of include directives parsed in a separate translation unit
+    /// after the headers added with `Config::add_include_directive`. Use it for
+    /// headers that can't be included together with headers of other groups
+    /// (e.g. because of conflicting macros). The translation unit of each group
+    /// includes the common headers and the headers of the group.
+    ///
+    /// Items found in all translation units are merged in the database.
+    /// If a group contains a different definition of an item, it's recorded
+    /// as a conflict (see the `show_conflicts` step) instead of failing the parse.
+    /// Headers of the groups are not included in the global header of the C++
+    /// wrapper library. Items that require a group are checked and compiled
+    /// in separate translation units that include the headers of the group.
+    /// The groups of items are determined using the include graph recorded
+    /// by the parser.
+    pub fn add_header_group<Item, Iter>(&mut self, include_directives: Iter)
+    where
+        Item: Into<PathBuf>,
+        Iter: IntoIterator<Item = Item>,
+    {
+        self.header_groups
+            .push(include_directives.into_iter().map(Into::into).collect());
+    }
+
     /// Sets `CppBuildConfig` value that will be passed to the build script
     /// of the generated crate.
     pub fn set_cpp_build_config(&mut self, cpp_build_config: CppBuildConfig) {
@@ -345,8 +869,103 @@ impl Config {
         self.cpp_lib_version = Some(version.into());
     }
 
+    /// Returns the version set with `Config::set_cpp_lib_version` or found by
+    /// `Config::locate_library`. If neither is available, returns the version
+    /// detected by `Config::detect_cpp_lib_version`.
     pub fn cpp_lib_version(&self) -> Option<&str> {
-        self.cpp_lib_version.as_ref().map(String::as_str)
+        self.cpp_lib_version
+            .as_ref()
+            .or_else(|| self.detected_cpp_lib_version.as_ref())
+            .map(String::as_str)
+    }
+
+    /// Detects the version of the installed C++ library using `probe`.
+    /// Include paths added to `CppBuildPaths` and target include paths are
+    /// used to find headers.
+    ///
+    /// If the version was also set explicitly and the versions don't match,
+    /// the C++ checker refuses to record its results (unless `force_cpp_checker` is used),
+    /// because they would be attributed to the wrong library version.
+    pub fn detect_cpp_lib_version(&mut self, probe: VersionProbe) -> Result<()> {
+        let include_paths = self
+            .cpp_build_paths
+            .include_paths()
+            .iter()
+            .chain(&self.target_include_paths)
+            .cloned()
+            .collect::<Vec<_>>();
+        let version = library_locator::detect_version(&probe, &include_paths)?;
+        self.detected_cpp_lib_version = Some(version);
+        Ok(())
+    }
+
+    pub fn detected_cpp_lib_version(&self) -> Option<&str> {
+        self.detected_cpp_lib_version.as_ref().map(String::as_str)
+    }
+
+    /// Returns the explicitly set and the detected versions of the C++ library
+    /// if both are available and they are different.
+    pub fn cpp_lib_version_mismatch(&self) -> Option<(&str, &str)> {
+        match (&self.cpp_lib_version, &self.detected_cpp_lib_version) {
+            (Some(version), Some(detected)) if version != detected => {
+                Some((version.as_str(), detected.as_str()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Adds an optional compile-time module of the C++ library (e.g. OpenGL support).
+    /// The C++ checker probes the module's availability, and items that are only
+    /// available with the module are gated behind the cargo feature named after the module.
+    /// The build script of the generated crate enables the feature automatically
+    /// if the module is detected.
+    pub fn add_optional_module(&mut self, module: OptionalModule) {
+        self.optional_modules.push(module);
+    }
+
+    pub fn optional_modules(&self) -> &[OptionalModule] {
+        &self.optional_modules
+    }
+
+    /// Returns names of the optional modules available in the current environment.
+    pub fn enabled_optional_modules(&self) -> Result<BTreeSet<String>> {
+        let mut cpp_build_paths = self.cpp_build_paths.clone();
+        cpp_build_paths.apply_env();
+        let include_paths = cpp_build_paths
+            .include_paths()
+            .iter()
+            .chain(&self.target_include_paths)
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut modules = BTreeSet::new();
+        for module in &self.optional_modules {
+            if library_locator::is_module_available(&module.probe, &include_paths)? {
+                modules.insert(module.name.clone());
+            }
+        }
+        Ok(modules)
+    }
+
+    /// Adds a set of compiler flags (e.g. `-std=c++17`). The C++ checker runs
+    /// all checks with each flag set in every checker environment, and items that
+    /// are only available with the flag set are gated behind the cargo feature
+    /// named after it.
+    pub fn add_compiler_flag_set(&mut self, flag_set: CompilerFlagSet) {
+        self.compiler_flag_sets.push(flag_set);
+    }
+
+    pub fn compiler_flag_sets(&self) -> &[CompilerFlagSet] {
+        &self.compiler_flag_sets
+    }
+
+    /// Returns the library target corresponding to the current environment.
+    pub fn current_library_target(&self) -> Result<LibraryTarget> {
+        Ok(LibraryTarget {
+            target: current_target(),
+            cpp_library_version: self.cpp_lib_version().map(ToString::to_string),
+            enabled_modules: self.enabled_optional_modules()?,
+            flag_set: None,
+        })
     }
 
     pub fn processing_steps(&self) -> &ProcessingSteps {
@@ -393,11 +1012,56 @@ impl Config {
         &self.include_directives
     }
 
+    /// Returns values added by `Config::add_header_group`.
+    pub fn header_groups(&self) -> &[Vec<PathBuf>] {
+        &self.header_groups
+    }
+
     /// Returns current `CppBuildConfig` value.
     pub fn cpp_build_config(&self) -> &CppBuildConfig {
         &self.cpp_build_config
     }
 
+    /// Returns `CppBuildConfig` with flags from compilation databases
+    /// added with `Config::add_compile_commands` for all include directives.
+    /// This config should be used for building C++ code that includes
+    /// all headers of the library (the checker and the wrapper library).
+    pub fn full_cpp_build_config(&self) -> CppBuildConfig {
+        let mut config = self.cpp_build_config.clone();
+        let flags = self.compile_commands_flags(&self.include_directives);
+        if !flags.is_empty() {
+            let mut data = CppBuildConfigData::new();
+            data.add_compiler_flags(flags);
+            config.add(Condition::True, data);
+        }
+        config
+    }
+
+    /// Returns flags from compilation databases added with `Config::add_compile_commands`
+    /// that should be used for compiling code containing `include_directives`.
+    pub fn compile_commands_flags(&self, include_directives: &[PathBuf]) -> Vec<String> {
+        let headers = include_directives
+            .iter()
+            .map(|directive| {
+                self.cpp_build_paths
+                    .include_paths()
+                    .iter()
+                    .map(|dir| dir.join(directive))
+                    .find(|path| path.is_file())
+                    .unwrap_or_else(|| directive.clone())
+            })
+            .collect::<Vec<_>>();
+        let mut flags = Vec::new();
+        for database in &self.compile_commands {
+            for flag in database.headers_flags(&headers).compiler_flags() {
+                if !flags.contains(flag) {
+                    flags.push(flag.clone());
+                }
+            }
+        }
+        flags
+    }
+
     pub fn set_movable_types_hook(
         &mut self,
         hook: impl Fn(&CppPath) -> Result<MovableTypesHookOutput> + 'static,
@@ -415,6 +1079,228 @@ impl Config {
         self.movable_types_hook.as_ref().map(|b| &**b)
     }
 
+    /// Sets the hook that returns options of FFI functions generated for
+    /// the C++ function or field with the specified path.
+    /// Use `path.parent()` to apply options to all members of a class.
+    pub fn set_ffi_function_options_hook(
+        &mut self,
+        hook: impl Fn(&CppPath) -> Result<FfiFunctionOptions> + 'static,
+    ) {
+        assert!(
+            self.ffi_function_options_hook.is_none(),
+            "only one hook can be set"
+        );
+        self.ffi_function_options_hook = Some(Box::new(hook));
+    }
+
+    pub fn ffi_function_options_hook(
+        &self,
+    ) -> Option<&(dyn Fn(&CppPath) -> Result<FfiFunctionOptions> + 'static)> {
+        self.ffi_function_options_hook.as_ref().map(|b| &**b)
+    }
+
+    /// Sets the hook that selects whether objects of the C++ class with the specified
+    /// path must stay at the address they were constructed at. Classes that explicitly
+    /// delete their move constructors are detected automatically (see
+    /// `CppTypeDeclaration::is_immovable`). The hook should return `Some(true)`
+    /// for classes that are otherwise movable but store pointers to themselves,
+    /// and `None` to use the detected value.
+    /// Constructors of pinned classes return `Pin<CppBox<T>>`, and such classes
+    /// are never allocated in place.
+    pub fn set_pinned_types_hook(
+        &mut self,
+        hook: impl Fn(&CppPath) -> Result<Option<bool>> + 'static,
+    ) {
+        assert!(self.pinned_types_hook.is_none(), "only one hook can be set");
+        self.pinned_types_hook = Some(Box::new(hook));
+    }
+
+    /// Returns true if objects of the C++ class `type1` must not be moved
+    /// after construction (see `Config::set_pinned_types_hook`).
+    pub fn is_pinned(&self, type1: &CppTypeDeclaration) -> Result<bool> {
+        if let Some(hook) = &self.pinned_types_hook {
+            if let Some(is_pinned) = hook(&type1.path)? {
+                return Ok(is_pinned);
+            }
+        }
+        Ok(type1.is_immovable)
+    }
+
+    /// Sets the hook that selects naming strategy for overloads of the C++ function
+    /// with the specified path. Use `path.parent()` to select the strategy per class.
+    /// `OverloadStrategy::Caption` is used if the hook is not set or returns `None`,
+    /// or if the hook selects different strategies for functions that map
+    /// to the same Rust name.
+    pub fn set_overload_strategy_hook(
+        &mut self,
+        hook: impl Fn(&CppPath) -> Result<Option<OverloadStrategy>> + 'static,
+    ) {
+        assert!(
+            self.overload_strategy_hook.is_none(),
+            "only one hook can be set"
+        );
+        self.overload_strategy_hook = Some(Box::new(hook));
+    }
+
+    pub fn overload_strategy_hook(
+        &self,
+    ) -> Option<&(dyn Fn(&CppPath) -> Result<Option<OverloadStrategy>> + 'static)> {
+        self.overload_strategy_hook.as_ref().map(|b| &**b)
+    }
+
+    /// Selects which wrapper functions are `unsafe`.
+    /// `SafetyPolicy::UnsafeByDefault` is used by default.
+    /// The decision and its justification are saved in the database, and the
+    /// justification is added to the documentation of `unsafe` functions.
+    pub fn set_safety_policy(&mut self, policy: SafetyPolicy) {
+        self.safety_policy = policy;
+    }
+
+    pub fn safety_policy(&self) -> SafetyPolicy {
+        self.safety_policy
+    }
+
+    /// Adds a base class of thread-affine objects (e.g. `QObject`). If safety policy is
+    /// `SafetyPolicy::SafeByDefault`, methods of the class and its subclasses are `unsafe`
+    /// because the objects may only be used from the thread they belong to.
+    pub fn add_thread_affine_base_class(&mut self, path: CppPath) {
+        self.thread_affine_base_classes.push(path);
+    }
+
+    /// Returns values added by `Config::add_thread_affine_base_class`.
+    pub fn thread_affine_base_classes(&self) -> &[CppPath] {
+        &self.thread_affine_base_classes
+    }
+
+    /// Sets the hook that overrides the safety policy for the C++ function
+    /// with the specified path. Use `path.parent()` to select safety per class.
+    /// The safety policy is applied if the hook is not set or returns `None`.
+    pub fn set_function_safety_hook(
+        &mut self,
+        hook: impl Fn(&CppPath) -> Result<Option<FunctionSafety>> + 'static,
+    ) {
+        assert!(
+            self.function_safety_hook.is_none(),
+            "only one hook can be set"
+        );
+        self.function_safety_hook = Some(Box::new(hook));
+    }
+
+    pub fn function_safety_hook(
+        &self,
+    ) -> Option<&(dyn Fn(&CppPath) -> Result<Option<FunctionSafety>> + 'static)> {
+        self.function_safety_hook.as_ref().map(|b| &**b)
+    }
+
+    /// Sets the hook that selects allocation strategy for the C++ class
+    /// with the specified path.
+    /// `AllocationStrategy::Heap` is used if the hook is not set or returns `None`.
+    pub fn set_allocation_strategy_hook(
+        &mut self,
+        hook: impl Fn(&CppPath) -> Result<Option<AllocationStrategy>> + 'static,
+    ) {
+        assert!(
+            self.allocation_strategy_hook.is_none(),
+            "only one hook can be set"
+        );
+        self.allocation_strategy_hook = Some(Box::new(hook));
+    }
+
+    /// Returns allocation strategy for the C++ class `class_path`
+    /// selected by the hook set with `Config::set_allocation_strategy_hook`.
+    pub fn allocation_strategy(&self, class_path: &CppPath) -> Result<AllocationStrategy> {
+        if let Some(hook) = &self.allocation_strategy_hook {
+            if let Some(strategy) = hook(class_path)? {
+                return Ok(strategy);
+            }
+        }
+        Ok(AllocationStrategy::default())
+    }
+
+    /// Sets the hook that selects trivially copyable C++ classes that should be
+    /// wrapped as `#[repr(C)]` Rust structs with public fields that implement `Copy`.
+    /// Values of such classes are returned by value instead of `CppBox`.
+    /// The hook is only called for classes without user-declared copy and move
+    /// operations, destructor, virtual functions and base classes.
+    /// All fields of the class must be public and have trivial types, and the layout
+    /// must be verified by the C++ checker on all environments.
+    pub fn set_plain_struct_hook(&mut self, hook: impl Fn(&CppPath) -> Result<bool> + 'static) {
+        assert!(self.plain_struct_hook.is_none(), "only one hook can be set");
+        self.plain_struct_hook = Some(Box::new(hook));
+    }
+
+    pub fn plain_struct_hook(&self) -> Option<&(dyn Fn(&CppPath) -> Result<bool> + 'static)> {
+        self.plain_struct_hook.as_ref().map(|b| &**b)
+    }
+
+    /// Sets the hook that selects C++ functions whose `(const T* data, size_t len)`
+    /// argument pairs are exposed as `&[T]` (and `(T* data, size_t len)` pairs as `&mut [T]`)
+    /// in the Rust API. Only pointers to numeric types are converted.
+    /// The raw variant of each converted function is still generated
+    /// as an `unsafe` free function in the `unsafe_` module of the crate
+    /// (e.g. `crate::unsafe_::buffer_write` for `crate::Buffer::write`).
+    pub fn set_slice_arguments_hook(&mut self, hook: impl Fn(&CppPath) -> Result<bool> + 'static) {
+        assert!(
+            self.slice_arguments_hook.is_none(),
+            "only one hook can be set"
+        );
+        self.slice_arguments_hook = Some(Box::new(hook));
+    }
+
+    pub fn slice_arguments_hook(&self) -> Option<&(dyn Fn(&CppPath) -> Result<bool> + 'static)> {
+        self.slice_arguments_hook.as_ref().map(|b| &**b)
+    }
+
+    /// Sets the hook that decides whether the reference or pointer returned by
+    /// the C++ method with the specified path points into `this`. Such methods return
+    /// `&T` borrowed from `self` instead of `Ref<T>`, `Ptr<T>` or a raw pointer,
+    /// so the returned value can't outlive the object. A null pointer returned
+    /// by such a method causes a panic.
+    ///
+    /// If the hook is not set, no methods are affected. If it returns `None`,
+    /// a method is assumed to return a reference into `this` if it returns
+    /// a C++ reference and doesn't have any other pointer or reference arguments.
+    /// Operators are never affected. A mutable reference is only returned
+    /// from methods taking `&mut self`, so non-const references returned by
+    /// const methods are never borrowed.
+    pub fn set_borrowed_return_hook(
+        &mut self,
+        hook: impl Fn(&CppPath) -> Result<Option<bool>> + 'static,
+    ) {
+        assert!(
+            self.borrowed_return_hook.is_none(),
+            "only one hook can be set"
+        );
+        self.borrowed_return_hook = Some(Box::new(hook));
+    }
+
+    pub fn borrowed_return_hook(
+        &self,
+    ) -> Option<&(dyn Fn(&CppPath) -> Result<Option<bool>> + 'static)> {
+        self.borrowed_return_hook.as_ref().map(|b| &**b)
+    }
+
+    /// Sets the hook that selects Rust representation of the C++ enum
+    /// with the specified path. `EnumRepresentation::Newtype` is used
+    /// if the hook is not set or returns `None`. The selected representation
+    /// is stored in the database.
+    pub fn set_enum_representation_hook(
+        &mut self,
+        hook: impl Fn(&CppPath) -> Result<Option<EnumRepresentation>> + 'static,
+    ) {
+        assert!(
+            self.enum_representation_hook.is_none(),
+            "only one hook can be set"
+        );
+        self.enum_representation_hook = Some(Box::new(hook));
+    }
+
+    pub fn enum_representation_hook(
+        &self,
+    ) -> Option<&(dyn Fn(&CppPath) -> Result<Option<EnumRepresentation>> + 'static)> {
+        self.enum_representation_hook.as_ref().map(|b| &**b)
+    }
+
     /// Adds a C++ identifier that should be skipped
     /// by the C++ parser. Identifier can contain namespaces
     /// and nested classes, with `::` separator (like in
@@ -499,6 +1385,171 @@ impl Config {
         self.cpp_item_filter_hook.as_ref().map(|b| &**b)
     }
 
+    /// Adds a C++ class, function or variable the generated crate must provide.
+    /// If any entry points are added, FFI wrappers are only generated for
+    /// the entry points and the items they transitively depend on (types used
+    /// in signatures, base classes, fields, enum values, destructors).
+    /// Other parsed items are kept in the database but are not wrapped.
+    /// All members of an entry point class are included. An entry point without
+    /// template arguments includes all instantiations of the template.
+    pub fn add_entry_point(&mut self, path: CppPath) {
+        self.entry_points.push(path);
+    }
+
+    pub fn entry_points(&self) -> &[CppPath] {
+        &self.entry_points
+    }
+
+    /// Adds a rule describing a C++ function that reports completion of an asynchronous
+    /// operation through a callback. In addition to the regular wrapper, an `_async`
+    /// wrapper is generated for each matching function. It accepts the other arguments
+    /// and returns a `cpp_core::Completion` future resolving to the callback's arguments.
+    /// The C++ function must call the callback at most once.
+    pub fn add_async_function_rule(&mut self, rule: AsyncFunctionRule) {
+        self.async_function_rules.push(rule);
+    }
+
+    pub fn async_function_rules(&self) -> &[AsyncFunctionRule] {
+        &self.async_function_rules
+    }
+
+    /// Sets C++ functions controlling the event loop of the library. If set,
+    /// the crate contains the `event_loop` module with the `EventLoop` type
+    /// implementing `cpp_core::EventLoop` through these functions.
+    pub fn set_event_loop_functions(&mut self, functions: EventLoopFunctions) {
+        self.event_loop_functions = Some(functions);
+    }
+
+    pub fn event_loop_functions(&self) -> Option<&EventLoopFunctions> {
+        self.event_loop_functions.as_ref()
+    }
+
+    /// Sets global C++ functions initializing and shutting down the library.
+    /// If set, the crate contains the `LibraryHandle` guard type that calls `init`
+    /// when created and `shutdown` when dropped, and all generated constructors
+    /// require a `&LibraryHandle` argument, so that objects can't be created
+    /// before the library is initialized.
+    pub fn set_lifecycle_functions(&mut self, functions: LifecycleFunctions) {
+        self.lifecycle_functions = Some(functions);
+    }
+
+    pub fn lifecycle_functions(&self) -> Option<&LifecycleFunctions> {
+        self.lifecycle_functions.as_ref()
+    }
+
+    /// Adds a conversion between a wrapper type and a type of a heavy
+    /// dependency (e.g. an image or a date/time crate). The dependency is added
+    /// to the generated crate as optional, and the conversion is only compiled
+    /// if the feature is enabled, so users that don't need it work with
+    /// the wrapper type and don't pay for the dependency.
+    ///
+    /// Conversion functions are usually placed in the crate template and
+    /// must be gated by the same feature.
+    pub fn add_optional_type_conversion(&mut self, conversion: OptionalTypeConversion) {
+        self.optional_type_conversions.push(conversion);
+    }
+
+    /// Returns values added by `Config::add_optional_type_conversion`.
+    pub fn optional_type_conversions(&self) -> &[OptionalTypeConversion] {
+        &self.optional_type_conversions
+    }
+
+    /// Adds an integer handle type (e.g. an object id returned by a C library).
+    /// Instead of a plain integer, the typedef is represented by a distinct
+    /// `#[repr(transparent)]` newtype in the crate root, so handles of different
+    /// kinds can't be mixed up. If `release` is set, the crate also contains
+    /// the `Owned<rust_name>` type that calls the release function when dropped.
+    /// The names must not be taken by other items of the crate root.
+    /// If a ritual dependency already has a newtype for the typedef, it is used instead,
+    /// so dependent crates should add the same handle type to parse the typedef.
+    pub fn add_handle_type(&mut self, handle_type: HandleType) {
+        self.handle_types.push(handle_type);
+    }
+
+    /// Returns values added by `Config::add_handle_type`.
+    pub fn handle_types(&self) -> &[HandleType] {
+        &self.handle_types
+    }
+
+    /// Adds a plugin registry of the library. The crate contains a module
+    /// named `rust_name` with the `register` function that registers a Rust closure
+    /// as a plugin factory, and the `create` function that looks up a plugin
+    /// (if `create` is set). If the factory callback doesn't accept an argument,
+    /// the module contains the `register_raw` function instead, and the crate
+    /// exports the `register_<rust_name>!` macro that registers a Rust function.
+    /// Implementing C++ plugin interfaces in Rust is not supported, so factories
+    /// must return objects created by other means.
+    pub fn add_plugin_registry(&mut self, registry: PluginRegistry) {
+        self.plugin_registries.push(registry);
+    }
+
+    /// Returns values added by `Config::add_plugin_registry`.
+    pub fn plugin_registries(&self) -> &[PluginRegistry] {
+        &self.plugin_registries
+    }
+
+    /// Adds C++ code to the C++ wrapper library at `point`. Snippets added
+    /// for the same point are written in the order they were added.
+    /// This allows to keep library-specific glue code (e.g. helpers
+    /// used by custom wrappers) in the binding project.
+    pub fn add_cpp_code_injection(&mut self, point: CppInjectionPoint, code: impl Into<String>) {
+        self.cpp_code_injections.push(CppCodeInjection {
+            point,
+            code: code.into(),
+        });
+    }
+
+    pub fn cpp_code_injections(&self) -> &[CppCodeInjection] {
+        &self.cpp_code_injections
+    }
+
+    /// Returns code of the snippets added for `point`.
+    pub fn cpp_code_injected_at(&self, point: CppInjectionPoint) -> Vec<&str> {
+        self.cpp_code_injections
+            .iter()
+            .filter(|injection| injection.point == point)
+            .map(|injection| injection.code.as_str())
+            .collect()
+    }
+
+    /// Adds Rust declarations to the `extern "C"` block of the FFI module of the crate
+    /// (e.g. `pub fn my_wrapper(value: ::std::os::raw::c_int) -> bool;`).
+    /// Custom wrappers added with `CppInjectionPoint::ExternC` can then be called
+    /// from the crate template as `crate::__ffi::my_wrapper`.
+    pub fn add_ffi_declarations(&mut self, code: impl Into<String>) {
+        self.ffi_declarations.push(code.into());
+    }
+
+    /// Returns values added by `Config::add_ffi_declarations`.
+    pub fn ffi_declarations(&self) -> &[String] {
+        &self.ffi_declarations
+    }
+
+    /// Enables compatibility with C++ libraries built without RTTI and exceptions
+    /// (e.g. with `-fno-rtti -fno-exceptions`). Generated wrappers don't use
+    /// `dynamic_cast`, and checked downcasts are only generated for classes
+    /// that have a library cast (see `add_library_cast`). The C++ checker verifies
+    /// that RTTI and exceptions are actually disabled by the configured compiler flags.
+    pub fn set_rtti_free(&mut self, value: bool) {
+        self.rtti_free = value;
+    }
+
+    pub fn rtti_free(&self) -> bool {
+        self.rtti_free
+    }
+
+    /// Sets a cast function template provided by the C++ library (e.g. `qobject_cast`)
+    /// that performs checked downcasts from `base_class` and classes derived from it.
+    /// It's used instead of `dynamic_cast` if RTTI-free mode is enabled
+    /// (see `set_rtti_free`). The function is called as `function<Derived*>(ptr)`.
+    pub fn add_library_cast(&mut self, base_class: CppPath, function: CppPath) {
+        self.library_casts.insert(base_class, function);
+    }
+
+    pub fn library_casts(&self) -> &HashMap<CppPath, CppPath> {
+        &self.library_casts
+    }
+
     pub fn set_cluster_config(&mut self, cluster_config: ClusterConfig) {
         self.cluster_config = Some(cluster_config);
     }
@@ -515,6 +1566,18 @@ impl Config {
         &self.cpp_checker_tests
     }
 
+    /// Adds a checker environment that uses a cross-compiler. Items are checked
+    /// for this environment in addition to the current target, but the compiled
+    /// snippets are not executed. This allows to populate checker data for targets
+    /// the current machine can't run. Ignored if the cluster config is set.
+    pub fn add_cross_checker_env(&mut self, env: CrossCheckerEnv) {
+        self.cross_checker_envs.push(env);
+    }
+
+    pub fn cross_checker_envs(&self) -> &[CrossCheckerEnv] {
+        &self.cross_checker_envs
+    }
+
     pub fn set_write_dependencies_local_paths(&mut self, value: bool) {
         self.write_dependencies_local_paths = value;
     }
@@ -522,6 +1585,209 @@ impl Config {
     pub fn write_dependencies_local_paths(&self) -> bool {
         self.write_dependencies_local_paths
     }
+
+    /// Enables or disables the lenient mode. In lenient mode, C++ constructs
+    /// that can't be parsed are skipped instead of aborting the processing.
+    /// Skipped constructs are reported at the end of processing.
+    pub fn set_lenient(&mut self, value: bool) {
+        self.lenient = value;
+    }
+
+    pub fn lenient(&self) -> bool {
+        self.lenient
+    }
+
+    /// If enabled, headers that crashed libclang before are parsed again
+    /// instead of being excluded from parsing.
+    pub fn set_retry_crashed_headers(&mut self, value: bool) {
+        self.retry_crashed_headers = value;
+    }
+
+    pub fn retry_crashed_headers(&self) -> bool {
+        self.retry_crashed_headers
+    }
+
+    /// Sets the directory containing Doxygen XML output (`GENERATE_XML = YES`)
+    /// for the C++ library. If set, documentation from this directory is attached
+    /// to the generated Rust items.
+    pub fn set_doxygen_xml_path<P: Into<PathBuf>>(&mut self, path: P) {
+        self.doxygen_xml_path = Some(path.into());
+    }
+
+    /// Returns value set by `Config::set_doxygen_xml_path`.
+    pub fn doxygen_xml_path(&self) -> Option<&PathBuf> {
+        self.doxygen_xml_path.as_ref()
+    }
+
+    /// Sets path to the file with per-item overrides of the generated API
+    /// (see the `overrides` module for the file format). Overrides with names
+    /// that don't match any C++ item are reported as warnings.
+    pub fn set_overrides_path<P: Into<PathBuf>>(&mut self, path: P) {
+        self.overrides_path = Some(path.into());
+    }
+
+    /// Returns value set by `Config::set_overrides_path`.
+    pub fn overrides_path(&self) -> Option<&PathBuf> {
+        self.overrides_path.as_ref()
+    }
+
+    /// Sets base URL of the Doxygen HTML output for the C++ library.
+    /// It's used to add links to the original documentation.
+    pub fn set_doxygen_html_url(&mut self, url: impl Into<String>) {
+        self.doxygen_html_url = Some(url.into());
+    }
+
+    /// Returns value set by `Config::set_doxygen_html_url`.
+    pub fn doxygen_html_url(&self) -> Option<&str> {
+        self.doxygen_html_url.as_ref().map(String::as_str)
+    }
+
+    /// Enables import of documentation from rendered HTML files
+    /// (e.g. for libraries that don't provide docs in a machine-readable format).
+    /// Documentation of each function, enum and class is attached to the generated
+    /// Rust items along with a link to the original page.
+    pub fn set_html_docs(&mut self, config: HtmlDocsConfig) {
+        self.html_docs = Some(config);
+    }
+
+    /// Returns value set by `Config::set_html_docs`.
+    pub fn html_docs(&self) -> Option<&HtmlDocsConfig> {
+        self.html_docs.as_ref()
+    }
+
+    /// Sets the directory containing templates of HTML reports (e.g. the report
+    /// of failed C++ checks). Any of `header.html`, `summary.html`, `row.html`,
+    /// `footer.html`, `style.css` and `script.js` files can be provided to replace
+    /// the built-in template (see `templates/html_logger` in the `ritual` crate).
+    /// `{title}` and `{summary}` in `header.html`, `{counts}` and `{stages}`
+    /// in `summary.html`, and `{attributes}` and `{cells}` in `row.html`
+    /// are replaced with the corresponding parts of the report.
+    pub fn set_html_report_theme_path<P: Into<PathBuf>>(&mut self, path: P) {
+        self.html_report_theme_path = Some(path.into());
+    }
+
+    /// Returns value set by `Config::set_html_report_theme_path`.
+    pub fn html_report_theme_path(&self) -> Option<&PathBuf> {
+        self.html_report_theme_path.as_ref()
+    }
+
+    /// Enables saving reproducers of failed C++ checks. For each environment,
+    /// at most `count` failed checks are saved with a self-contained C++ file,
+    /// the compiler command line and the diagnostics. Reproducers are linked
+    /// from the report of failed checks. Reproducers are not saved by default.
+    pub fn set_max_cpp_checker_repros(&mut self, count: usize) {
+        self.max_cpp_checker_repros = Some(count);
+    }
+
+    /// Returns value set by `Config::set_max_cpp_checker_repros`.
+    pub fn max_cpp_checker_repros(&self) -> Option<usize> {
+        self.max_cpp_checker_repros
+    }
+
+    /// Sets the directory of the cargo-fuzz project written by the `generate_fuzz_targets`
+    /// and `export_fuzz_corpus` processing steps. By default, `out/fuzz` directory
+    /// of the workspace is used.
+    pub fn set_fuzz_path<P: Into<PathBuf>>(&mut self, path: P) {
+        self.fuzz_path = Some(path.into());
+    }
+
+    /// Returns value set by `Config::set_fuzz_path`.
+    pub fn fuzz_path(&self) -> Option<&PathBuf> {
+        self.fuzz_path.as_ref()
+    }
+
+    /// Sets the source of the `ritual` dependency of the generated fuzz project
+    /// (see `Config::set_fuzz_path`). By default, the version of `ritual`
+    /// generating the project is used from crates.io.
+    pub fn set_fuzz_ritual_source(&mut self, source: CrateDependencySource) {
+        self.fuzz_ritual_source = Some(source);
+    }
+
+    /// Returns value set by `Config::set_fuzz_ritual_source`.
+    pub fn fuzz_ritual_source(&self) -> Option<&CrateDependencySource> {
+        self.fuzz_ritual_source.as_ref()
+    }
+
+    /// Sets the template of a URL pointing to upstream documentation or a source browser
+    /// for a C++ item. The URL is added to the C++ declaration section of
+    /// documentation of each generated item.
+    ///
+    /// The following placeholders are replaced in the template:
+    /// `{path}` (e.g. `QWidget::show`), `{name}` (`show`), `{scope}` (`QWidget`),
+    /// `{scope_lowercase}` (`qwidget`), `{header}` (`qwidget.h`) and `{line}`.
+    pub fn set_cpp_doc_url_template(&mut self, template: impl Into<String>) {
+        self.cpp_doc_url_template = Some(template.into());
+    }
+
+    /// Returns value set by `Config::set_cpp_doc_url_template`.
+    pub fn cpp_doc_url_template(&self) -> Option<&str> {
+        self.cpp_doc_url_template.as_ref().map(String::as_str)
+    }
+
+    /// Sets behavior of generated callback trampolines when the Rust closure panics.
+    /// The default is `CallbackPanicBehavior::Abort`.
+    pub fn set_callback_panic_behavior(&mut self, behavior: CallbackPanicBehavior) {
+        self.callback_panic_behavior = behavior;
+    }
+
+    /// Returns value set by `Config::set_callback_panic_behavior`.
+    pub fn callback_panic_behavior(&self) -> CallbackPanicBehavior {
+        self.callback_panic_behavior
+    }
+
+    /// Declares the encoding of `char*` strings accepted and returned by the library.
+    /// If set, the crate contains the `string_encoding` module with strict and lossy
+    /// functions converting between Rust strings and C strings in this encoding,
+    /// and they are used for implicit conversions from `&str`. Functions returning
+    /// `const char*` return `Option<String>` decoded lossily (`None` for null pointers).
+    /// If a wrapper of `std::string` is available, the module also contains functions
+    /// decoding its content.
+    /// If not set, UTF-8 is assumed and the module is not generated.
+    pub fn set_string_encoding(&mut self, encoding: StringEncoding) {
+        self.string_encoding = Some(encoding);
+    }
+
+    /// Returns value set by `Config::set_string_encoding`.
+    pub fn string_encoding(&self) -> Option<StringEncoding> {
+        self.string_encoding
+    }
+
+    /// Adds a C++ type re-exported in the `prelude` module of the crate.
+    /// The module is only generated if any types are added. It also re-exports
+    /// common `cpp_core` items (`CppBox`, `Ptr`, `Ref`, casting traits),
+    /// so that `use crate_name::prelude::*` is enough for typical code.
+    pub fn add_prelude_type(&mut self, path: CppPath) {
+        self.prelude_types.push(path);
+    }
+
+    /// Returns value set by `Config::add_prelude_type`.
+    pub fn prelude_types(&self) -> &[CppPath] {
+        &self.prelude_types
+    }
+
+    /// Adds a C++ type or free function re-exported at the crate root in addition to its
+    /// original module. All overloads of a function are re-exported. The re-export
+    /// is skipped with a warning if its name conflicts with another item of the crate root.
+    pub fn add_root_reexport(&mut self, path: CppPath) {
+        self.root_reexports.push(path);
+    }
+
+    /// Returns value set by `Config::add_root_reexport`.
+    pub fn root_reexports(&self) -> &[CppPath] {
+        &self.root_reexports
+    }
+
+    /// Sets the maximal depth of modules generated for C++ namespaces.
+    /// Items of namespaces nested deeper are placed in the module of their
+    /// ancestor namespace at this depth (or in the crate root if `depth` is 0).
+    pub fn set_max_module_depth(&mut self, depth: usize) {
+        self.max_module_depth = Some(depth);
+    }
+
+    /// Returns value set by `Config::set_max_module_depth`.
+    pub fn max_module_depth(&self) -> Option<usize> {
+        self.max_module_depth
+    }
 }
 
 #[derive(Default)]
@@ -1,10 +1,15 @@
-use crate::cpp_data::{CppBaseSpecifier, CppItem, CppPath, CppPathItem};
+use crate::config::Config;
+use crate::cpp_data::{CppBaseSpecifier, CppItem, CppPath};
 use crate::cpp_ffi_data::CppCast;
 use crate::cpp_function::{CppFunction, CppFunctionArgument};
 use crate::cpp_type::{CppPointerLikeTypeKind, CppType};
 use crate::database::ItemWithSource;
-use crate::processor::ProcessorData;
-use ritual_common::errors::Result;
+use crate::processor::{ProcessorData, SkippedConstruct};
+use itertools::Itertools;
+use log::{debug, warn};
+use ritual_common::errors::{err_msg, Result};
+use std::collections::{HashMap, HashSet};
+use std::iter::once;
 
 /// Convenience function to create `CppMethod` object for
 /// `static_cast` or `dynamic_cast` from type `from` to type `to`.
@@ -12,10 +17,7 @@ use ritual_common::errors::Result;
 /// about `is_unsafe_static_cast` and `is_direct_static_cast`.
 fn create_cast_method(cast: CppCast, from: &CppType, to: &CppType) -> Result<CppItem> {
     let function = CppFunction {
-        path: CppPath::from_item(CppPathItem {
-            name: cast.cpp_method_name().into(),
-            template_arguments: Some(vec![to.clone()]),
-        }),
+        path: cast.cpp_path(to),
         member: None,
         operator: None,
         return_type: to.clone(),
@@ -31,84 +33,296 @@ fn create_cast_method(cast: CppCast, from: &CppType, to: &CppType) -> Result<Cpp
     Ok(CppItem::Function(function))
 }
 
-/// Performs a portion of `generate_casts` operation.
-/// Adds casts between `target_type` and `base_type` and calls
-/// `generate_casts_one` recursively to add casts between `target_type`
-/// and base types of `base_type`.
-fn generate_casts_one(
-    target_type: &CppPath,
-    base_type: &CppPath,
-    direct_base_index: Option<usize>,
-    data: &ProcessorData<'_>,
-) -> Result<Vec<CppItem>> {
-    let target_ptr_type = CppType::PointerLike {
-        is_const: false,
-        kind: CppPointerLikeTypeKind::Pointer,
-        target: Box::new(CppType::Class(target_type.clone())),
+/// A path from a derived class to one of its direct or indirect bases.
+#[derive(Debug, Clone)]
+struct BasePath {
+    /// Index of the direct base the path starts with
+    direct_base_index: usize,
+    /// Classes on the path, from the direct base to the final base
+    classes: Vec<CppPath>,
+    /// Whether each inheritance step of the path is virtual
+    virtual_steps: Vec<bool>,
+}
+
+impl BasePath {
+    fn is_direct(&self) -> bool {
+        self.classes.len() == 1
+    }
+
+    /// Returns true if the path contains a virtual inheritance step,
+    /// i.e. the final base is a virtual base or a base of a virtual base.
+    /// `static_cast` can't be used for downcasting from such a base.
+    fn is_virtual(&self) -> bool {
+        self.virtual_steps.iter().any(|x| *x)
+    }
+
+    /// Returns the part of the path that identifies the base class subobject.
+    /// All paths going through the same virtual base share its subobject.
+    fn subobject_key(&self) -> (bool, &[CppPath]) {
+        match self.virtual_steps.iter().rposition(|x| *x) {
+            Some(index) => (true, &self.classes[index..]),
+            None => (false, &self.classes[..]),
+        }
+    }
+
+    fn to_cpp_pseudo_code(&self, derived: &CppPath) -> String {
+        once(derived)
+            .chain(&self.classes)
+            .map(CppPath::to_cpp_pseudo_code)
+            .join(" -> ")
+    }
+}
+
+/// Appends to `output` all paths from `class_type` to its bases, continuing `prefix`.
+fn collect_base_paths(
+    class_type: &CppPath,
+    prefix: &BasePath,
+    bases: &HashMap<CppPath, Vec<CppBaseSpecifier>>,
+    output: &mut Vec<BasePath>,
+) {
+    for base in bases.get(class_type).into_iter().flatten() {
+        if prefix.classes.contains(&base.base_class_type) {
+            // invalid inheritance cycle
+            continue;
+        }
+        let mut path = prefix.clone();
+        if path.classes.is_empty() {
+            path.direct_base_index = base.base_index;
+        }
+        path.classes.push(base.base_class_type.clone());
+        path.virtual_steps.push(base.is_virtual);
+        output.push(path.clone());
+        collect_base_paths(&base.base_class_type, &path, bases, output);
+    }
+}
+
+/// Returns the library cast function (see `Config::add_library_cast`)
+/// applicable to `class_type` or one of its bases.
+fn library_cast<'a>(
+    class_type: &CppPath,
+    bases: &HashMap<CppPath, Vec<CppBaseSpecifier>>,
+    config: &'a Config,
+) -> Option<&'a CppPath> {
+    if let Some(function) = config.library_casts().get(class_type) {
+        return Some(function);
+    }
+    bases
+        .get(class_type)
+        .into_iter()
+        .flatten()
+        .find_map(|base| library_cast(&base.base_class_type, bases, config))
+}
+
+/// Generates casts between `derived_type` and all its direct and indirect bases.
+/// Returns casts together with `base_index` of the direct base item
+/// that should be used as their source.
+///
+/// `static_cast` adjusts the pointer correctly for any base that occurs
+/// only once in the class hierarchy, so a cast is generated for every such base.
+/// Casts to ambiguous bases are reported as skipped. Downcasts from virtual bases
+/// use `dynamic_cast` because C++ doesn't allow `static_cast` for them.
+/// Casts using `dynamic_cast` are only generated if the base is in `polymorphic_types`.
+/// In RTTI-free mode (see `Config::set_rtti_free`), `dynamic_cast` is not used,
+/// and checked downcasts are performed by library casts if they are available.
+pub fn generate_casts(
+    derived_type: &CppPath,
+    bases: &HashMap<CppPath, Vec<CppBaseSpecifier>>,
+    polymorphic_types: &HashSet<CppPath>,
+    config: &Config,
+    skipped: &mut Vec<SkippedConstruct>,
+) -> Result<Vec<(usize, CppItem)>> {
+    let root = BasePath {
+        direct_base_index: 0,
+        classes: Vec::new(),
+        virtual_steps: Vec::new(),
     };
-    let base_ptr_type = CppType::PointerLike {
+    let mut paths = Vec::new();
+    collect_base_paths(derived_type, &root, bases, &mut paths);
+
+    let derived_ptr_type = CppType::PointerLike {
         is_const: false,
         kind: CppPointerLikeTypeKind::Pointer,
-        target: Box::new(CppType::Class(base_type.clone())),
+        target: Box::new(CppType::Class(derived_type.clone())),
     };
     let mut new_methods = Vec::new();
-    new_methods.push(create_cast_method(
-        CppCast::Static {
-            is_unsafe: true,
-            base_index: direct_base_index,
-        },
-        &base_ptr_type,
-        &target_ptr_type,
-    )?);
-    new_methods.push(create_cast_method(
-        CppCast::Static {
-            is_unsafe: false,
-            base_index: direct_base_index,
-        },
-        &target_ptr_type,
-        &base_ptr_type,
-    )?);
-    new_methods.push(create_cast_method(
-        CppCast::Dynamic,
-        &base_ptr_type,
-        &target_ptr_type,
-    )?);
-
-    for item in data.db.all_cpp_items().filter_map(|i| i.item.as_base_ref()) {
-        if &item.derived_class_type == base_type {
-            new_methods.extend(generate_casts_one(
-                target_type,
-                &item.base_class_type,
-                None,
-                data,
-            )?);
+    let base_types = paths
+        .iter()
+        .map(|path| path.classes.last().expect("base path can't be empty"))
+        .unique()
+        .collect_vec();
+    for base_type in base_types {
+        let base_paths = paths
+            .iter()
+            .filter(|path| path.classes.last() == Some(base_type))
+            .collect_vec();
+        if base_paths
+            .iter()
+            .map(|path| path.subobject_key())
+            .unique()
+            .count()
+            > 1
+        {
+            let skipped_construct = SkippedConstruct {
+                description: format!(
+                    "casts between {} and {}",
+                    derived_type.to_cpp_pseudo_code(),
+                    base_type.to_cpp_pseudo_code()
+                ),
+                reason: format!(
+                    "ambiguous base class (inherited through {})",
+                    base_paths
+                        .iter()
+                        .map(|path| path.to_cpp_pseudo_code(derived_type))
+                        .join(", ")
+                ),
+                location: None,
+            };
+            warn!(
+                "skipping {}: {}",
+                skipped_construct.description, skipped_construct.reason
+            );
+            skipped.push(skipped_construct);
+            continue;
+        }
+        let path = base_paths
+            .iter()
+            .find(|path| path.is_direct())
+            .unwrap_or(&base_paths[0]);
+        let base_index = if path.is_direct() {
+            Some(path.direct_base_index)
+        } else {
+            None
+        };
+        let base_ptr_type = CppType::PointerLike {
+            is_const: false,
+            kind: CppPointerLikeTypeKind::Pointer,
+            target: Box::new(CppType::Class(base_type.clone())),
+        };
+        let mut add = |cast, from: &CppType, to: &CppType| -> Result<()> {
+            new_methods.push((path.direct_base_index, create_cast_method(cast, from, to)?));
+            Ok(())
+        };
+        let is_virtual_base = base_paths.iter().any(|path| path.is_virtual());
+        // downcasts from virtual bases require `dynamic_cast`
+        let can_downcast =
+            !is_virtual_base || (!config.rtti_free() && polymorphic_types.contains(base_type));
+        if !can_downcast && !config.rtti_free() {
+            let skipped_construct = SkippedConstruct {
+                description: format!(
+                    "static downcast from {} to {}",
+                    base_type.to_cpp_pseudo_code(),
+                    derived_type.to_cpp_pseudo_code()
+                ),
+                reason: "virtual base class is not polymorphic".into(),
+                location: None,
+            };
+            debug!(
+                "skipping {}: {}",
+                skipped_construct.description, skipped_construct.reason
+            );
+            skipped.push(skipped_construct);
+        }
+        if can_downcast {
+            add(
+                CppCast::Static {
+                    is_unsafe: true,
+                    base_index,
+                    is_virtual_base,
+                },
+                &base_ptr_type,
+                &derived_ptr_type,
+            )?;
+        }
+        add(
+            CppCast::Static {
+                is_unsafe: false,
+                base_index,
+                is_virtual_base,
+            },
+            &derived_ptr_type,
+            &base_ptr_type,
+        )?;
+        if !config.rtti_free() {
+            // `dynamic_cast` doesn't compile for non-polymorphic types
+            if polymorphic_types.contains(base_type) {
+                add(CppCast::Dynamic, &base_ptr_type, &derived_ptr_type)?;
+            }
+        } else if let Some(function) = library_cast(base_type, bases, config) {
+            let cast = CppCast::Library {
+                function: function.clone(),
+            };
+            add(cast, &base_ptr_type, &derived_ptr_type)?;
         }
     }
-
     Ok(new_methods)
 }
 
-/// Adds `static_cast` and `dynamic_cast` functions for all appropriate pairs of types
-/// in this `CppData`.
-fn generate_casts(base: &CppBaseSpecifier, data: &ProcessorData<'_>) -> Result<Vec<CppItem>> {
-    generate_casts_one(
-        &base.derived_class_type,
-        &base.base_class_type,
-        Some(base.base_index),
-        data,
-    )
-}
-
 pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
-    let mut results = Vec::new();
-    let bases = data
+    let mut bases = HashMap::<CppPath, Vec<CppBaseSpecifier>>::new();
+    for item in data.db.all_cpp_items().filter_map(|i| i.item.as_base_ref()) {
+        let derived_bases = bases.entry(item.derived_class_type.clone()).or_default();
+        if !derived_bases.contains(item) {
+            derived_bases.push(item.clone());
+        }
+    }
+    for derived_bases in bases.values_mut() {
+        derived_bases.sort_by_key(|base| base.base_index);
+    }
+
+    // classes with virtual functions and classes derived from them
+    let mut polymorphic_types = data
+        .db
+        .all_cpp_items()
+        .filter_map(|item| item.item.as_function_ref())
+        .filter(|function| function.is_virtual())
+        .filter_map(|function| function.class_path().ok())
+        .collect::<HashSet<_>>();
+    loop {
+        let new_types = bases
+            .iter()
+            .filter(|(derived, derived_bases)| {
+                !polymorphic_types.contains(*derived)
+                    && derived_bases
+                        .iter()
+                        .any(|base| polymorphic_types.contains(&base.base_class_type))
+            })
+            .map(|(derived, _)| derived.clone())
+            .collect_vec();
+        if new_types.is_empty() {
+            break;
+        }
+        polymorphic_types.extend(new_types);
+    }
+
+    let direct_bases = data
         .db
         .cpp_items()
-        .filter_map(|item| item.filter_map(|item| item.as_base_ref()));
+        .filter_map(|item| item.filter_map(|item| item.as_base_ref()))
+        .map(|item| (item.id, item.item.clone()))
+        .collect_vec();
+    let derived_types = direct_bases
+        .iter()
+        .map(|(_, base)| &base.derived_class_type)
+        .unique()
+        .collect_vec();
 
-    for item in bases {
-        for value in generate_casts(item.item, &data)? {
-            results.push(ItemWithSource::new(&item.id, value));
+    let mut results = Vec::new();
+    for derived_type in derived_types {
+        for (base_index, value) in generate_casts(
+            derived_type,
+            &bases,
+            &polymorphic_types,
+            data.config,
+            data.skipped,
+        )? {
+            let source_id = direct_bases
+                .iter()
+                .find(|(_, base)| {
+                    &base.derived_class_type == derived_type && base.base_index == base_index
+                })
+                .map(|(id, _)| id.clone())
+                .ok_or_else(|| err_msg("direct base item not found"))?;
+            results.push(ItemWithSource::new(&source_id, value));
         }
     }
     for item in results {
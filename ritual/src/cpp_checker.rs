@@ -1,34 +1,47 @@
-use crate::config::Config;
+use crate::config::{Config, CppInjectionPoint};
 use crate::cpp_checks::CppChecksItem;
-use crate::cpp_code_generator::{all_include_directives, write_include_directives};
+use crate::cpp_code_generator::{
+    all_include_directives, write_include_directives, write_injected_code,
+};
 use crate::cpp_data::{CppItem, CppPath};
 use crate::cpp_ffi_data::CppFfiItem;
 use crate::cpp_type::CppType;
 use crate::database::{DatabaseClient, DbItem, ItemId};
+use crate::html_logger::{anchor, escape_html, HtmlLogger, HtmlTemplates, Severity};
+use crate::include_graph;
+use crate::numeric_types::{NumericTypeResolution, PlatformNumericType};
 use crate::processor::ProcessorData;
 use crate::{cluster_api, cpp_code_generator};
 use itertools::Itertools;
-use log::{debug, error, info, trace};
-use rayon::iter::ParallelIterator;
-use rayon::slice::ParallelSliceMut;
-use ritual_common::cpp_build_config::{CppBuildConfigData, CppBuildPaths};
+use log::{debug, error, info, trace, warn};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use ritual_common::android::{self, AndroidAbi, AndroidNdk};
+use ritual_common::cpp_build_config::{
+    CompilerFlagSet, CppBuildConfigData, CppBuildPaths, CppLibraryType, CppStandard,
+};
 use ritual_common::cpp_lib_builder::{
-    BuildType, CMakeConfigData, CppLibBuilder, CppLibBuilderOutput,
+    BuildType, CMakeConfigData, CMakeVar, CppLibBuilder, CppLibBuilderOutput,
 };
-use ritual_common::errors::{bail, err_msg, Result};
+use ritual_common::emscripten;
+use ritual_common::errors::{bail, err_msg, format_err, Result};
 use ritual_common::file_utils::{
-    copy_recursively, create_dir_all, create_file, os_str_to_str, remove_dir_all,
+    copy_recursively, create_dir_all, create_file, file_to_string, os_str_to_str, path_to_str,
+    remove_dir_all,
 };
-use ritual_common::target::{current_target, LibraryTarget};
-use ritual_common::utils::ProgressBar;
+use ritual_common::library_locator::apply_library_searches;
+use ritual_common::mingw;
+use ritual_common::msvc::vcvars_environment;
+use ritual_common::target::{current_target, Arch, LibraryTarget, Target, OS};
+use ritual_common::utils::{CommandOutput, MapIfOk, ProgressBar};
 use serde_derive::{Deserialize, Serialize};
 use std::collections::{hash_map::Entry, HashMap};
 use std::io::Write;
 use std::iter::once;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread::ThreadId;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::{iter, thread};
 
 pub const CHUNK_SIZE: usize = 64;
@@ -59,14 +72,28 @@ fn snippet_for_item(item: DbItem<&CppFfiItem>, database: &DatabaseClient) -> Res
             item.item.source_item_cpp_code(database)?,
             true,
         )),
+        CppFfiItem::ValueLayout(layout) => Ok(Snippet::new_global(
+            cpp_code_generator::value_layout_assertions(database, layout)?,
+            false,
+        )),
+        CppFfiItem::FieldOffset(offset) => Ok(Snippet::new_global(
+            cpp_code_generator::field_offset_assertion(database, offset)?,
+            false,
+        )),
     }
 }
 
 pub struct CppCheckerInstance {
+    name: String,
     main_cpp_path: PathBuf,
     crate_name: String,
     builder: CppLibBuilder,
     tests: Vec<PreliminaryTest>,
+    /// See `LocalCppChecker::set_repro_dir`.
+    repro_dir: Option<PathBuf>,
+    repro_limit: usize,
+    /// Number of reproducers saved by all instances of the checker
+    repro_count: Arc<AtomicUsize>,
 }
 
 impl CppCheckerInstance {
@@ -75,9 +102,20 @@ impl CppCheckerInstance {
         snippets: impl Iterator<Item = &'a Snippet>,
     ) -> Result<CppLibBuilderOutput> {
         let mut any_needs_moc = false;
+        let snippets = snippets.collect_vec();
+        let header_group = snippets
+            .first()
+            .map_or(&[][..], |snippet| &snippet.header_group[..]);
+        if snippets
+            .iter()
+            .any(|snippet| snippet.header_group != header_group)
+        {
+            bail!("snippets of different header groups can't be checked together");
+        }
 
         let mut file = create_file(&self.main_cpp_path)?;
         writeln!(file, "#include \"global.h\"")?;
+        write_include_directives(&mut file, header_group)?;
         writeln!(file)?;
         let mut main_content = Vec::new();
         for snippet in snippets {
@@ -163,6 +201,44 @@ impl CppCheckerInstance {
         Ok(())
     }
 
+    /// Saves the current snippet file with inlined `global.h`, the compiler
+    /// command line and `output` of the failed check to a new directory
+    /// in the repro directory. Returns path to the created directory,
+    /// or `None` if the limit of reproducers is reached.
+    fn save_repro(&mut self, output: &CommandOutput) -> Result<Option<PathBuf>> {
+        let repro_dir = self
+            .repro_dir
+            .as_ref()
+            .ok_or_else(|| err_msg("repro dir is not set"))?;
+        let index = self.repro_count.fetch_add(1, Ordering::Relaxed);
+        if index >= self.repro_limit {
+            return Ok(None);
+        }
+        let path = repro_dir.join(format!("{}_{}", self.name, index));
+        create_dir_all(&path)?;
+
+        let global_header = file_to_string(self.builder.cmake_source_dir.join("global.h"))?;
+        let main_code = file_to_string(&self.main_cpp_path)?;
+        write!(
+            create_file(path.join("snippet.cpp"))?,
+            "{}",
+            main_code.replacen("#include \"global.h\"", &global_header, 1)
+        )?;
+
+        let command = compile_command(&self.builder.build_dir.join("compile_commands.json"))
+            .unwrap_or_else(|| "<compiler command line is not available>".to_string());
+        writeln!(create_file(path.join("command.txt"))?, "{}", command)?;
+
+        write!(
+            create_file(path.join("diagnostics.txt"))?,
+            "exit status: {}\n\nstdout:\n{}\n\nstderr:\n{}",
+            output.status,
+            output.stdout,
+            output.stderr
+        )?;
+        Ok(Some(path))
+    }
+
     pub fn binary_check<T>(
         &mut self,
         snippets: &mut [SnippetTask<T>],
@@ -170,7 +246,14 @@ impl CppCheckerInstance {
     ) -> Result<()> {
         if snippets.len() < 3 {
             for snippet in &mut *snippets {
+                let instant = Instant::now();
                 let output = self.check_snippets(iter::once(&snippet.snippet))?;
+                snippet.duration += instant.elapsed();
+                if let CppLibBuilderOutput::Fail(command_output) = &output {
+                    if self.repro_dir.is_some() {
+                        snippet.repro_path = self.save_repro(command_output)?;
+                    }
+                }
                 snippet.output = Some(output);
                 if let Some(progress_bar) = progress_bar {
                     progress_bar.add(1);
@@ -179,7 +262,12 @@ impl CppCheckerInstance {
             return Ok(());
         }
 
+        let instant = Instant::now();
         let output = self.check_snippets(snippets.iter().map(|s| &s.snippet))?;
+        let duration = instant.elapsed() / snippets.len() as u32;
+        for snippet in &mut *snippets {
+            snippet.duration += duration;
+        }
         if let CppLibBuilderOutput::Success = output {
             for snippet in &mut *snippets {
                 snippet.output = Some(output.clone());
@@ -200,6 +288,42 @@ impl CppCheckerInstance {
 struct CppChecker<'b, 'a> {
     data: &'b mut ProcessorData<'a>,
     force: bool,
+    /// Failed checks with saved reproducers
+    repro_entries: Vec<ReproEntry>,
+    /// Durations of checks of each environment
+    stage_durations: Vec<(String, Duration)>,
+}
+
+/// Failed check with a saved reproducer.
+struct ReproEntry {
+    item_id: ItemId,
+    env: LibraryTarget,
+    path: PathBuf,
+}
+
+/// Returns the compiler command line for `main.cpp` from the compilation database
+/// exported by CMake.
+fn compile_command(compile_commands_path: &Path) -> Option<String> {
+    let text = file_to_string(compile_commands_path).ok()?;
+    let commands: serde_json::Value = serde_json::from_str(&text).ok()?;
+    let command = commands.as_array()?.iter().find(|command| {
+        command["file"]
+            .as_str()
+            .map_or(false, |file| file.ends_with("main.cpp"))
+    })?;
+    let command_line = match command["command"].as_str() {
+        Some(command_line) => command_line.to_string(),
+        None => command["arguments"]
+            .as_array()?
+            .iter()
+            .filter_map(|arg| arg.as_str())
+            .join(" "),
+    };
+    Some(format!(
+        "cd {}\n{}",
+        command["directory"].as_str().unwrap_or("."),
+        command_line
+    ))
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
@@ -213,6 +337,10 @@ pub struct Snippet {
     code: String,
     context: SnippetContext,
     needs_moc: bool,
+    /// Include directives of the header group required by the snippet
+    /// (see `Config::add_header_group`)
+    #[serde(default)]
+    header_group: Vec<PathBuf>,
 }
 
 #[derive(Debug, Clone)]
@@ -220,6 +348,12 @@ pub struct SnippetTask<T> {
     pub snippet: Snippet,
     pub output: Option<CppLibBuilderOutput>,
     pub data: T,
+    /// Directory with the reproducer of the failed check
+    /// (see `LocalCppChecker::set_repro_dir`).
+    pub repro_path: Option<PathBuf>,
+    /// Compilation time attributed to the snippet. Time of a compilation
+    /// of multiple snippets is divided between them.
+    pub duration: Duration,
 }
 
 pub struct SnippetTaskLocalData {
@@ -236,6 +370,7 @@ impl Snippet {
             code: code.into(),
             context: SnippetContext::Main,
             needs_moc,
+            header_group: Vec::new(),
         }
     }
 
@@ -244,8 +379,37 @@ impl Snippet {
             code: code.into(),
             context: SnippetContext::Global,
             needs_moc,
+            header_group: Vec::new(),
         }
     }
+
+    /// Includes `include_directives` of a header group after the global header
+    /// when checking the snippet.
+    pub fn with_header_group(mut self, include_directives: Vec<PathBuf>) -> Self {
+        self.header_group = include_directives;
+        self
+    }
+}
+
+/// Splits `items` into chunks of at most `CHUNK_SIZE` items for checking.
+/// Snippets of different header groups can't be compiled together, so they are
+/// never placed in the same chunk. `items` should be sorted by the header group
+/// to avoid small chunks.
+pub fn snippet_chunks<T>(items: &mut [T], snippet: impl Fn(&T) -> &Snippet) -> Vec<&mut [T]> {
+    let mut chunks = Vec::new();
+    let mut rest = items;
+    while !rest.is_empty() {
+        let header_group = &snippet(&rest[0]).header_group;
+        let len = rest
+            .iter()
+            .take(CHUNK_SIZE)
+            .take_while(|item| &snippet(item).header_group == header_group)
+            .count();
+        let (chunk, tail) = { rest }.split_at_mut(len);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks
 }
 
 #[derive(Debug, Clone)]
@@ -253,6 +417,7 @@ pub struct PreliminaryTest {
     name: String,
     snippet: Snippet,
     expected: bool,
+    requires_execution: bool,
 }
 
 impl PreliminaryTest {
@@ -261,41 +426,254 @@ impl PreliminaryTest {
             name: name.into(),
             expected,
             snippet,
+            requires_execution: false,
+        }
+    }
+
+    /// Creates a test that can only be verified by running the compiled snippet.
+    /// Such tests are skipped in compile-only checker environments.
+    pub fn new_runtime(name: &str, expected: bool, snippet: Snippet) -> Self {
+        Self {
+            requires_execution: true,
+            ..Self::new(name, expected, snippet)
         }
     }
 }
 
+/// A checker environment for a target the current machine can't run
+/// (e.g. `aarch64-linux-gnu` on an x86 host). Snippets are compiled and linked
+/// with a cross-compiler but not executed.
+#[derive(Debug, Clone)]
+pub struct CrossCheckerEnv {
+    library_target: LibraryTarget,
+    cmake_vars: Vec<CMakeVar>,
+    cpp_build_paths: CppBuildPaths,
+}
+
+impl CrossCheckerEnv {
+    /// Creates an environment without any toolchain configuration.
+    /// Use `add_cmake_var` to select the compiler.
+    pub fn new(library_target: LibraryTarget) -> Self {
+        CrossCheckerEnv {
+            library_target,
+            cmake_vars: Vec::new(),
+            cpp_build_paths: CppBuildPaths::default(),
+        }
+    }
+
+    /// Creates an environment that uses `cxx_compiler` (e.g. `aarch64-linux-gnu-g++`).
+    pub fn with_compiler(library_target: LibraryTarget, cxx_compiler: impl Into<String>) -> Self {
+        let system_name = match library_target.target.os {
+            OS::Windows => "Windows",
+            OS::MacOS => "Darwin",
+            OS::IOS => "iOS",
+            OS::Linux => "Linux",
+            OS::Android => "Android",
+            OS::FreeBSD => "FreeBSD",
+            OS::DragonFly => "DragonFly",
+            OS::Bitrig => "Bitrig",
+            OS::OpenBSD => "OpenBSD",
+            OS::NetBSD => "NetBSD",
+            OS::Emscripten => "Emscripten",
+        };
+        let processor = match library_target.target.arch {
+            Arch::X86 => "i686",
+            Arch::X86_64 => "x86_64",
+            Arch::Mips => "mips",
+            Arch::PowerPC => "powerpc",
+            Arch::PowerPC64 => "powerpc64",
+            Arch::Arm => "arm",
+            Arch::AArch64 => "aarch64",
+            Arch::Wasm32 => "wasm32",
+        };
+        let mut env = Self::new(library_target);
+        env.add_cmake_var(CMakeVar::new("CMAKE_SYSTEM_NAME", system_name));
+        env.add_cmake_var(CMakeVar::new("CMAKE_SYSTEM_PROCESSOR", processor));
+        env.add_cmake_var(CMakeVar::new("CMAKE_CXX_COMPILER", cxx_compiler));
+        env
+    }
+
+    /// Creates an environment that uses a CMake toolchain file.
+    pub fn with_toolchain_file(library_target: LibraryTarget, path: &Path) -> Result<Self> {
+        let mut env = Self::new(library_target);
+        env.add_cmake_var(CMakeVar::new("CMAKE_TOOLCHAIN_FILE", path_to_str(path)?));
+        Ok(env)
+    }
+
+    /// Creates an environment for Android `abi` using the NDK found in the environment.
+    /// The C++ runtime of the NDK is selected according to `runtime_type`.
+    pub fn android(
+        abi: AndroidAbi,
+        cpp_library_version: Option<String>,
+        runtime_type: Option<CppLibraryType>,
+    ) -> Result<Self> {
+        let ndk = AndroidNdk::find()?;
+        let mut env = Self::new(LibraryTarget::android(abi, cpp_library_version));
+        env.cmake_vars = ndk.cmake_vars(abi, android::api_level()?, runtime_type)?;
+        Ok(env)
+    }
+
+    /// Creates an environment for the `wasm32-unknown-emscripten` target
+    /// using the Emscripten SDK found in the environment.
+    pub fn emscripten(cpp_library_version: Option<String>) -> Result<Self> {
+        let path = emscripten::cmake_toolchain_file()?;
+        Self::with_toolchain_file(LibraryTarget::emscripten(cpp_library_version), &path)
+    }
+
+    /// Creates an environment for the Windows GNU target for `arch`
+    /// using the MinGW-w64 cross-compiler found in `PATH`.
+    pub fn mingw(arch: Arch, cpp_library_version: Option<String>) -> Result<Self> {
+        let mut env = Self::new(LibraryTarget::mingw(arch, cpp_library_version));
+        env.cmake_vars = mingw::cmake_vars(arch)?;
+        Ok(env)
+    }
+
+    pub fn add_cmake_var(&mut self, var: CMakeVar) {
+        self.cmake_vars.push(var);
+    }
+
+    pub fn library_target(&self) -> &LibraryTarget {
+        &self.library_target
+    }
+
+    pub fn cmake_vars(&self) -> &[CMakeVar] {
+        &self.cmake_vars
+    }
+
+    /// Returns paths used in addition to `Config::cpp_build_paths`
+    /// when building snippets for this environment
+    /// (e.g. include and library paths of the target sysroot).
+    pub fn cpp_build_paths(&self) -> &CppBuildPaths {
+        &self.cpp_build_paths
+    }
+
+    pub fn cpp_build_paths_mut(&mut self) -> &mut CppBuildPaths {
+        &mut self.cpp_build_paths
+    }
+}
+
+fn global_header_code(config: &Config) -> Vec<String> {
+    config
+        .cpp_code_injected_at(CppInjectionPoint::GlobalHeader)
+        .into_iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub struct LocalCppChecker {
     parent_path: PathBuf,
     all_include_directives: Vec<PathBuf>,
+    /// Code added with `Config::add_cpp_code_injection` to the global header
+    global_header_code: Vec<String>,
     crate_name: String,
     cpp_build_config: CppBuildConfigData,
     cpp_build_paths: CppBuildPaths,
+    target: Target,
+    env_vars: Vec<(String, String)>,
+    extra_cmake_vars: Vec<CMakeVar>,
+    compile_only: bool,
     crate_template_path: Option<PathBuf>,
     tests: Vec<PreliminaryTest>,
+    repro_dir: Option<PathBuf>,
+    repro_limit: usize,
+    repro_count: Arc<AtomicUsize>,
 }
 
 impl LocalCppChecker {
     pub fn new(parent_path: impl Into<PathBuf>, config: &Config) -> Result<LocalCppChecker> {
         let mut tests = builtin_tests();
+        if config.rtti_free() {
+            tests.extend(rtti_free_tests());
+        }
         tests.extend(config.cpp_checker_tests().iter().cloned());
 
+        let mut cpp_build_paths = config.cpp_build_paths().clone();
+        cpp_build_paths.apply_env();
+        let mut cpp_build_config = config.full_cpp_build_config().eval(&current_target())?;
+        apply_library_searches(&mut cpp_build_config, &mut cpp_build_paths)?;
+
         Ok(LocalCppChecker {
             parent_path: parent_path.into(),
             all_include_directives: all_include_directives(config)?,
+            global_header_code: global_header_code(config),
             crate_name: config.crate_properties().name().to_string(),
             crate_template_path: config.crate_template_path().cloned(),
-            cpp_build_paths: {
-                let mut data = config.cpp_build_paths().clone();
-                data.apply_env();
-                data
-            },
-            cpp_build_config: config.cpp_build_config().eval(&current_target())?,
+            cpp_build_paths,
+            cpp_build_config,
+            target: current_target(),
+            env_vars: vcvars_environment(&current_target())?,
+            extra_cmake_vars: Vec::new(),
+            compile_only: false,
             tests,
+            repro_dir: None,
+            repro_limit: 0,
+            repro_count: Default::default(),
         })
     }
 
+    /// Creates a checker that compiles snippets for a cross-compilation
+    /// environment without running them. Library searches are not performed
+    /// because their results would refer to the host system.
+    pub fn new_cross(
+        parent_path: impl Into<PathBuf>,
+        config: &Config,
+        env: &CrossCheckerEnv,
+    ) -> Result<LocalCppChecker> {
+        let mut tests = builtin_tests();
+        if config.rtti_free() {
+            tests.extend(rtti_free_tests());
+        }
+        tests.extend(config.cpp_checker_tests().iter().cloned());
+        tests.retain(|test| !test.requires_execution);
+
+        let target = &env.library_target.target;
+        let mut cpp_build_paths = config.cpp_build_paths().clone();
+        cpp_build_paths.apply_env();
+        for path in env.cpp_build_paths.include_paths() {
+            cpp_build_paths.add_include_path(path.clone());
+        }
+        for path in env.cpp_build_paths.lib_paths() {
+            cpp_build_paths.add_lib_path(path.clone());
+        }
+        for path in env.cpp_build_paths.framework_paths() {
+            cpp_build_paths.add_framework_path(path.clone());
+        }
+
+        Ok(LocalCppChecker {
+            parent_path: parent_path.into(),
+            all_include_directives: all_include_directives(config)?,
+            global_header_code: global_header_code(config),
+            crate_name: config.crate_properties().name().to_string(),
+            crate_template_path: config.crate_template_path().cloned(),
+            cpp_build_paths,
+            cpp_build_config: config.full_cpp_build_config().eval(target)?,
+            target: target.clone(),
+            env_vars: vcvars_environment(target)?,
+            extra_cmake_vars: env.cmake_vars.clone(),
+            compile_only: true,
+            tests,
+            repro_dir: None,
+            repro_limit: 0,
+            repro_count: Default::default(),
+        })
+    }
+
+    /// Enables saving reproducers of failed checks. For each snippet that
+    /// fails individually, a subdirectory of `path` is created containing
+    /// the self-contained C++ file, the compiler command line and the diagnostics.
+    /// At most `limit` reproducers are saved by all instances of the checker.
+    pub fn set_repro_dir(&mut self, path: impl Into<PathBuf>, limit: usize) {
+        self.repro_dir = Some(path.into());
+        self.repro_limit = limit;
+    }
+
+    /// Passes the flags of `flag_set` to the compiler in addition to the configured flags.
+    pub fn add_compiler_flag_set(&mut self, flag_set: &CompilerFlagSet) {
+        self.cpp_build_config
+            .add_compiler_flags(flag_set.flags.iter().map(String::as_str));
+    }
+
     pub fn get(&self, id: &str) -> Result<CppCheckerInstance> {
         let root_path = self.parent_path.join(id);
         if root_path.exists() {
@@ -327,6 +705,14 @@ impl LocalCppChecker {
             include_str!("../templates/c_lib/global.h"),
         )?;
         write_include_directives(&mut global_file, &self.all_include_directives)?;
+        write_injected_code(
+            &mut global_file,
+            &self
+                .global_header_code
+                .iter()
+                .map(String::as_str)
+                .collect_vec(),
+        )?;
 
         let cmake_config = CMakeConfigData {
             cpp_build_config_data: &self.cpp_build_config,
@@ -335,23 +721,37 @@ impl LocalCppChecker {
             cpp_library_version: None,
         };
 
+        let mut cmake_vars = cmake_config.cmake_vars()?;
+        cmake_vars.extend_from_slice(&self.extra_cmake_vars);
+        if self.compile_only {
+            cmake_vars.push(CMakeVar::new("RITUAL_COMPILE_ONLY", "ON"));
+        }
+        // compiler command lines are saved in reproducers of failed checks
+        cmake_vars.push(CMakeVar::new("CMAKE_EXPORT_COMPILE_COMMANDS", "ON"));
+
         let builder = CppLibBuilder {
             cmake_source_dir: src_path.clone(),
             build_dir: root_path.join("build"),
             install_dir: None,
             num_jobs: Some(1),
             build_type: BuildType::Debug,
-            cmake_vars: cmake_config.cmake_vars()?,
+            cmake_vars,
+            target: self.target.clone(),
+            env_vars: self.env_vars.clone(),
             capture_output: true,
             skip_cmake: false,
             skip_cmake_after_first_run: true,
         };
 
         Ok(CppCheckerInstance {
+            name: id.to_string(),
             builder,
             main_cpp_path: src_path.join("main.cpp"),
             crate_name: self.crate_name.clone(),
             tests: self.tests.clone(),
+            repro_dir: self.repro_dir.clone(),
+            repro_limit: self.repro_limit,
+            repro_count: Arc::clone(&self.repro_count),
         })
     }
 }
@@ -417,12 +817,12 @@ fn builtin_tests() -> Vec<PreliminaryTest> {
             Snippet::new_global("int f1() { ritual_assert(2 + 2 == 5); return 1; }", false),
         ),
         PreliminaryTest::new("syntax error", false, Snippet::new_in_main("}", false)),
-        PreliminaryTest::new(
+        PreliminaryTest::new_runtime(
             "incorrect assertion",
             false,
             Snippet::new_in_main("ritual_assert(2 + 2 == 5);", false),
         ),
-        PreliminaryTest::new(
+        PreliminaryTest::new_runtime(
             "status code 1",
             false,
             Snippet::new_in_main("return 1;", false),
@@ -430,14 +830,29 @@ fn builtin_tests() -> Vec<PreliminaryTest> {
     ]
 }
 
-impl CppChecker<'_, '_> {
-    fn env(&self) -> LibraryTarget {
-        LibraryTarget {
-            target: current_target(),
-            cpp_library_version: self.data.config.cpp_lib_version().map(ToString::to_string),
-        }
-    }
+/// Tests verifying that the configured compiler flags disable RTTI and exceptions
+/// if RTTI-free mode is enabled (see `Config::set_rtti_free`).
+fn rtti_free_tests() -> Vec<PreliminaryTest> {
+    vec![
+        PreliminaryTest::new(
+            "dynamic_cast is disabled",
+            false,
+            Snippet::new_global(
+                "struct C1 { virtual ~C1() {} }; \n\
+                 struct C2 : C1 {}; \n\
+                 C2* f1(C1* x) { return dynamic_cast<C2*>(x); }",
+                false,
+            ),
+        ),
+        PreliminaryTest::new(
+            "exceptions are disabled",
+            false,
+            Snippet::new_global("void f1() { try { throw 1; } catch (...) {} }", false),
+        ),
+    ]
+}
 
+impl CppChecker<'_, '_> {
     fn run(&mut self) -> Result<()> {
         if self.data.config.cluster_config().is_some() {
             self.run_cluster()
@@ -461,6 +876,8 @@ impl CppChecker<'_, '_> {
                     .map(move |lib| LibraryTarget {
                         target: worker.target.clone(),
                         cpp_library_version: lib.lib_version.clone(),
+                        enabled_modules: lib.enabled_modules.clone(),
+                        flag_set: None,
                     })
             })
             .collect_vec();
@@ -468,6 +885,8 @@ impl CppChecker<'_, '_> {
         for env in &environments {
             self.data.db.add_environment(env.clone());
         }
+        self.record_numeric_types(&environments)?;
+        self.check_cpp_standard(&environments)?;
 
         let mut snippets = self.create_tasks(&environments)?;
         if snippets.is_empty() {
@@ -482,14 +901,99 @@ impl CppChecker<'_, '_> {
     }
 
     fn run_local(&mut self) -> Result<()> {
-        let instance_provider = LocalCppChecker::new(
-            self.data.workspace.tmp_path().join("cpp_checker"),
-            &self.data.config,
-        )?;
+        let config = self.data.config;
+        if let Some((version, detected)) = config.cpp_lib_version_mismatch() {
+            if self.force {
+                warn!(
+                    "C++ library version is set to {}, but detected version is {}",
+                    version, detected
+                );
+            } else {
+                bail!(
+                    "C++ library version is set to {}, but detected version is {}; \
+                     use force_cpp_checker to record checker results anyway",
+                    version,
+                    detected
+                );
+            }
+        }
+        let checker_path = self.data.tmp_path().join("cpp_checker");
+        let repro_dir = self.report_dir().join("repro");
+        if repro_dir.exists() {
+            remove_dir_all(&repro_dir)?;
+        }
+        // default flags first, then each of the configured flag sets
+        let flag_sets = once(None)
+            .chain(config.compiler_flag_sets().iter().map(Some))
+            .collect_vec();
+        // each flag set is checked in a separate subdirectory
+        let flag_set_path = |path: PathBuf, flag_set: Option<&CompilerFlagSet>| {
+            if let Some(flag_set) = flag_set {
+                path.join(format!("flags_{}", flag_set.name))
+            } else {
+                path
+            }
+        };
+
+        for &flag_set in &flag_sets {
+            let mut instance_provider =
+                LocalCppChecker::new(flag_set_path(checker_path.clone(), flag_set), config)?;
+            if let Some(limit) = config.max_cpp_checker_repros() {
+                instance_provider.set_repro_dir(flag_set_path(repro_dir.clone(), flag_set), limit);
+            }
+            let mut env = self.data.config.current_library_target()?;
+            if let Some(flag_set) = flag_set {
+                info!("Checking items with compiler flag set: {}", flag_set.name);
+                instance_provider.add_compiler_flag_set(flag_set);
+                env.flag_set = Some(flag_set.name.clone());
+            }
+            self.run_local_env(instance_provider, env)?;
+        }
+
+        for (index, cross_env) in config.cross_checker_envs().iter().enumerate() {
+            let dir_name = format!("cross_{}", index);
+            for &flag_set in &flag_sets {
+                let mut env = cross_env.library_target().clone();
+                env.flag_set = flag_set.map(|flag_set| flag_set.name.clone());
+                info!("Checking items for {} (compile only)", env.short_text());
+                let mut instance_provider = LocalCppChecker::new_cross(
+                    flag_set_path(checker_path.join(&dir_name), flag_set),
+                    config,
+                    cross_env,
+                )?;
+                if let Some(limit) = config.max_cpp_checker_repros() {
+                    instance_provider
+                        .set_repro_dir(flag_set_path(repro_dir.join(&dir_name), flag_set), limit);
+                }
+                if let Some(flag_set) = flag_set {
+                    instance_provider.add_compiler_flag_set(flag_set);
+                }
+                self.run_local_env(instance_provider, env)?;
+            }
+        }
+        Ok(())
+    }
 
-        let env = self.env();
+    fn run_local_env(
+        &mut self,
+        instance_provider: LocalCppChecker,
+        env: LibraryTarget,
+    ) -> Result<()> {
+        let start = Instant::now();
+        let stage_name = env.short_text();
+        self.run_local_env_inner(instance_provider, env)?;
+        self.stage_durations.push((stage_name, start.elapsed()));
+        Ok(())
+    }
 
+    fn run_local_env_inner(
+        &mut self,
+        instance_provider: LocalCppChecker,
+        env: LibraryTarget,
+    ) -> Result<()> {
         self.data.db.add_environment(env.clone());
+        self.record_numeric_types(&[env.clone()])?;
+        self.check_cpp_standard(&[env.clone()])?;
 
         let mut snippets = self.create_tasks(&[env])?;
         if snippets.is_empty() {
@@ -503,8 +1007,8 @@ impl CppChecker<'_, '_> {
 
         let instances = InstanceStorage::new(instance_provider);
 
-        snippets
-            .par_chunks_mut(CHUNK_SIZE)
+        snippet_chunks(&mut snippets, |task| &task.snippet)
+            .into_par_iter()
             .map(|chunk| {
                 let progress_bar = progress_bar.clone();
                 let instance = instances.current()?;
@@ -517,8 +1021,108 @@ impl CppChecker<'_, '_> {
         Ok(())
     }
 
+    /// Records Rust representations of platform-dependent numeric types
+    /// for `library_targets` and marks FFI items that use types unsupported
+    /// on a target as failed for that target without compiling them.
+    fn record_numeric_types(&mut self, library_targets: &[LibraryTarget]) -> Result<()> {
+        for env in library_targets {
+            for &cpp_type in PlatformNumericType::all() {
+                self.data
+                    .db
+                    .set_numeric_type_resolution(NumericTypeResolution {
+                        cpp_type,
+                        env: env.clone(),
+                        rust_type: cpp_type.resolve(&env.target).map(ToString::to_string),
+                    });
+            }
+        }
+
+        let mut failed_items = Vec::new();
+        for ffi_item in self.data.db.ffi_items() {
+            for env in library_targets {
+                let types = unsupported_numeric_types(&ffi_item.item, env, &self.data.db);
+                if types.is_empty() {
+                    continue;
+                }
+                let checks = self.data.db.cpp_checks(&ffi_item.id)?;
+                if checks.has_env(env) && (!self.force || !checks.is_success(env)) {
+                    continue;
+                }
+                debug!(
+                    "{} uses types unsupported on {}: {}",
+                    ffi_item.item.short_text(),
+                    env.short_text(),
+                    types.iter().map(|t| t.cpp_name()).join(", ")
+                );
+                failed_items.push((ffi_item.id.clone(), env.clone()));
+            }
+        }
+        self.mark_failed(failed_items);
+        Ok(())
+    }
+
+    /// Marks FFI items that use standard library types introduced in a C++ standard
+    /// newer than the one used for a target (see `env_cpp_standard`) as failed
+    /// for that target without compiling them.
+    fn check_cpp_standard(&mut self, library_targets: &[LibraryTarget]) -> Result<()> {
+        let mut failed_items = Vec::new();
+        for env in library_targets {
+            let standard = match env_cpp_standard(self.data.config, env) {
+                Some(standard) => standard,
+                None => continue,
+            };
+            for ffi_item in self.data.db.ffi_items() {
+                let types = item_unavailable_std_types(&ffi_item.item, standard);
+                if types.is_empty() {
+                    continue;
+                }
+                let checks = self.data.db.cpp_checks(&ffi_item.id)?;
+                if checks.has_env(env) && (!self.force || !checks.is_success(env)) {
+                    continue;
+                }
+                debug!(
+                    "{} uses types unavailable in C++{}: {}",
+                    ffi_item.item.short_text(),
+                    standard.version(),
+                    types
+                        .iter()
+                        .map(|path| path.to_templateless_string())
+                        .join(", ")
+                );
+                failed_items.push((ffi_item.id.clone(), env.clone()));
+            }
+        }
+        self.mark_failed(failed_items);
+        Ok(())
+    }
+
+    /// Records failed checks of FFI items for environments, replacing previous results.
+    fn mark_failed(&mut self, failed_items: Vec<(ItemId, LibraryTarget)>) {
+        for (ffi_item_id, env) in failed_items {
+            self.data.db.delete_items(|item| {
+                item.source_id.as_ref() == Some(&ffi_item_id)
+                    && item
+                        .item
+                        .as_cpp_checks_item()
+                        .map_or(false, |item| item.env == env)
+            });
+            self.data.db.add_cpp_checks_item(
+                ffi_item_id,
+                CppChecksItem {
+                    env,
+                    is_success: false,
+                },
+            );
+        }
+    }
+
     fn create_tasks(&self, library_targets: &[LibraryTarget]) -> Result<Vec<LocalSnippetTask>> {
         let crate_name = self.data.db.crate_name().to_string();
+        let item_header_groups = include_graph::load_ffi_item_header_groups(
+            self.data.workspace,
+            self.data.config,
+            &self.data.db,
+        )?;
 
         let mut snippets = Vec::new();
         let mut old_items_count = 0;
@@ -536,11 +1140,29 @@ impl CppChecker<'_, '_> {
             };
 
             match snippet_for_item(ffi_item.clone(), &self.data.db) {
-                Ok(snippet) => {
+                Ok(mut snippet) => {
+                    if let Some(&group) = item_header_groups.get(&ffi_item.id) {
+                        let directives =
+                            self.data.config.header_groups().get(group).ok_or_else(|| {
+                                format_err!("invalid header group index: {}", group)
+                            })?;
+                        snippet = snippet.with_header_group(directives.clone());
+                    }
                     for library_target in library_targets {
                         if !self.force && checks.as_ref().unwrap().has_env(library_target) {
                             continue;
                         }
+                        if !unsupported_numeric_types(&ffi_item.item, library_target, &self.data.db)
+                            .is_empty()
+                        {
+                            continue;
+                        }
+                        let standard = env_cpp_standard(self.data.config, library_target);
+                        if standard.map_or(false, |standard| {
+                            !item_unavailable_std_types(&ffi_item.item, standard).is_empty()
+                        }) {
+                            continue;
+                        }
                         snippets.push(SnippetTask {
                             data: SnippetTaskLocalData {
                                 ffi_item_id: ffi_item.id.clone(),
@@ -549,6 +1171,8 @@ impl CppChecker<'_, '_> {
                             },
                             snippet: snippet.clone(),
                             output: None,
+                            repro_path: None,
+                            duration: Duration::default(),
                         });
                     }
                 }
@@ -562,6 +1186,9 @@ impl CppChecker<'_, '_> {
             }
         }
 
+        // snippets of the same header group are checked together
+        snippets.sort_by(|a, b| a.snippet.header_group.cmp(&b.snippet.header_group));
+
         if old_items_count == 0 {
             if !snippets.is_empty() {
                 info!("Checking {} items", snippets.len());
@@ -597,6 +1224,18 @@ impl CppChecker<'_, '_> {
                 let ffi_item_id = ffi_item.id;
                 let ffi_item_source_id = ffi_item.source_id;
                 let env = snippet.data.library_target;
+                self.data.record_item_time(
+                    ffi_item_id.clone(),
+                    Some(env.short_text()),
+                    snippet.duration,
+                );
+                if let Some(path) = snippet.repro_path {
+                    self.repro_entries.push(ReproEntry {
+                        item_id: ffi_item_id.clone(),
+                        env: env.clone(),
+                        path,
+                    });
+                }
 
                 if self.force {
                     let old_checks = self.data.db.cpp_checks(&ffi_item_id)?;
@@ -651,15 +1290,240 @@ impl CppChecker<'_, '_> {
 
         Ok(())
     }
+
+    /// Returns the directory of the report of failed checks.
+    fn report_dir(&self) -> PathBuf {
+        self.data
+            .workspace
+            .log_path()
+            .join(format!("cpp_checker_{}", self.data.db.crate_name()))
+    }
+
+    /// Writes the HTML report of failed checks to the log directory.
+    ///
+    /// Items that fail in all environments are reported as errors, and items that
+    /// succeed in some environments are reported as warnings. Failures caused only
+    /// by numeric types unsupported on the environment are reported as info.
+    /// Reproducers of failed checks are linked from the failed environments.
+    fn write_report(&self) -> Result<()> {
+        let crate_name = self.data.db.crate_name();
+        let report_dir = self.report_dir();
+        create_dir_all(&report_dir)?;
+        let title = format!("C++ checker failures: {}", crate_name);
+        let templates = HtmlTemplates::load(
+            self.data
+                .config
+                .html_report_theme_path()
+                .map(PathBuf::as_path),
+        )?;
+        let mut logger = HtmlLogger::new(report_dir.join("index.html"), &title, templates)?;
+        for (name, duration) in &self.stage_durations {
+            logger.add_stage(name, *duration);
+        }
+        logger.add_header(&["Item", "Failed environments"])?;
+        for ffi_item in self.data.db.ffi_items() {
+            let checks = self.data.db.cpp_checks(&ffi_item.id)?;
+            let failed_envs = checks.failed_envs().collect_vec();
+            if failed_envs.is_empty() {
+                continue;
+            }
+            let severity = if failed_envs.iter().all(|env| {
+                !unsupported_numeric_types(&ffi_item.item, env, &self.data.db).is_empty()
+            }) {
+                Severity::Info
+            } else if checks.any_success() {
+                Severity::Warning
+            } else {
+                Severity::Error
+            };
+            let envs = failed_envs
+                .iter()
+                .map_if_ok(|env| -> Result<String> {
+                    let text = escape_html(&env.short_text());
+                    let repro = self
+                        .repro_entries
+                        .iter()
+                        .find(|entry| entry.item_id == ffi_item.id && entry.env == **env);
+                    if let Some(repro) = repro {
+                        let relative_path =
+                            repro.path.strip_prefix(&report_dir).unwrap_or(&repro.path);
+                        Ok(format!(
+                            "{text} (<a href=\"{link}/snippet.cpp\">snippet</a> \
+                             <a href=\"{link}/command.txt\">command</a> \
+                             <a href=\"{link}/diagnostics.txt\">diagnostics</a>)",
+                            text = text,
+                            link = path_to_str(relative_path)?
+                        ))
+                    } else {
+                        Ok(text)
+                    }
+                })?
+                .join("<br>");
+            let mut notes = self.data.db.annotations(&ffi_item.id)?.to_vec();
+            if let Some(source_id) = &ffi_item.source_id {
+                notes.extend_from_slice(self.data.db.annotations(source_id)?);
+            }
+            let notes = notes
+                .iter()
+                .map(|note| format!("<br><em>note: {}</em>", escape_html(&note.text)))
+                .join("");
+            logger.add_item(
+                &anchor(&ffi_item.id.to_string()),
+                severity,
+                &[
+                    &format!(
+                        "<code>{}</code>{}",
+                        escape_html(&ffi_item.item.short_text()),
+                        notes
+                    ),
+                    &envs,
+                ],
+            )?;
+        }
+        let conflicts = self.data.db.cpp_conflict_items().collect_vec();
+        if !conflicts.is_empty() {
+            logger.add_header(&["Conflicting definition", "Existing definition"])?;
+            for conflict in conflicts {
+                let existing_id = conflict
+                    .source_id
+                    .as_ref()
+                    .ok_or_else(|| err_msg("conflict item must have a source"))?;
+                let existing = self.data.db.item(existing_id)?;
+                logger.add_item(
+                    &anchor(&conflict.id.to_string()),
+                    Severity::Warning,
+                    &[
+                        &format!(
+                            "<code>{}</code>",
+                            escape_html(&conflict.item.item.short_text())
+                        ),
+                        &format!("<code>{}</code>", escape_html(&existing.item.short_text())),
+                    ],
+                )?;
+            }
+        }
+        let annotations = self.data.db.all_annotations().collect_vec();
+        if !annotations.is_empty() {
+            logger.add_header(&["Annotated item", "Notes"])?;
+            for (key, notes) in annotations {
+                let notes = notes
+                    .iter()
+                    .map(|note| {
+                        format!(
+                            "rev. {} ({}, version {}): {}",
+                            note.revision,
+                            escape_html(&note.date),
+                            escape_html(&note.crate_version),
+                            escape_html(&note.text)
+                        )
+                    })
+                    .join("<br>");
+                logger.add_item(
+                    &anchor(&format!("note {}", key)),
+                    Severity::Info,
+                    &[&format!("<code>{}</code>", escape_html(key)), &notes],
+                )?;
+            }
+        }
+        logger.finish()?;
+        info!(
+            "C++ checker report saved to {}",
+            report_dir.join("index.html").display()
+        );
+        Ok(())
+    }
 }
 
 pub fn run(data: &mut ProcessorData<'_>, force: bool) -> Result<()> {
-    let mut checker = CppChecker { data, force };
+    let mut checker = CppChecker {
+        data,
+        force,
+        repro_entries: Vec::new(),
+        stage_durations: Vec::new(),
+    };
     checker.run()?;
+    checker.write_report()?;
     Ok(())
 }
 
-fn type_paths(type1: &CppType) -> Vec<&CppPath> {
+/// Returns platform-dependent numeric types used in `ffi_item` that
+/// were recorded as unsupported on `env`.
+fn unsupported_numeric_types(
+    ffi_item: &CppFfiItem,
+    env: &LibraryTarget,
+    db: &DatabaseClient,
+) -> Vec<PlatformNumericType> {
+    let function = match ffi_item.as_function_ref() {
+        Some(function) => function,
+        None => return Vec::new(),
+    };
+    function
+        .arguments
+        .iter()
+        .map(|arg| &arg.argument_type)
+        .chain(once(&function.return_type))
+        .flat_map(|t| PlatformNumericType::used_in(t.original_type()))
+        .filter(|&t| db.is_numeric_type_unsupported(t, env))
+        .unique()
+        .collect()
+}
+
+/// Returns the C++ standard used for checking items for `env`: the standard selected
+/// by the compiler flag set of `env` or by `Config::set_cpp_standard`.
+/// Returns `None` if the compiler's default standard is used.
+pub fn env_cpp_standard(config: &Config, env: &LibraryTarget) -> Option<CppStandard> {
+    let flag_set_standard = env.flag_set.as_ref().and_then(|name| {
+        config
+            .compiler_flag_sets()
+            .iter()
+            .find(|flag_set| &flag_set.name == name)?
+            .flags
+            .iter()
+            .filter_map(|flag| CppStandard::from_compiler_flag(flag))
+            .last()
+    });
+    flag_set_standard.or_else(|| config.cpp_standard())
+}
+
+/// Returns paths of standard library types used in `type1` (including template arguments)
+/// that are not available in `standard`.
+pub fn unavailable_std_types(type1: &CppType, standard: CppStandard) -> Vec<&CppPath> {
+    let mut paths = Vec::new();
+    for path in type_paths(type1) {
+        let is_unavailable = path.items()[0].name == "std"
+            && CppStandard::required_for_std_type(&path.last().name)
+                .map_or(false, |required| required > standard);
+        if is_unavailable {
+            paths.push(path);
+        }
+        for item in path.items() {
+            for arg in item.template_arguments.iter().flatten() {
+                paths.extend(unavailable_std_types(arg, standard));
+            }
+        }
+    }
+    paths
+}
+
+/// Returns paths of standard library types used in arguments and return type
+/// of `ffi_item` that are not available in `standard`.
+fn item_unavailable_std_types(ffi_item: &CppFfiItem, standard: CppStandard) -> Vec<&CppPath> {
+    let function = match ffi_item.as_function_ref() {
+        Some(function) => function,
+        None => return Vec::new(),
+    };
+    function
+        .arguments
+        .iter()
+        .map(|arg| &arg.argument_type)
+        .chain(once(&function.return_type))
+        .flat_map(|t| unavailable_std_types(t.original_type(), standard))
+        .unique()
+        .collect()
+}
+
+/// Returns paths of all classes and enums used in `type1`.
+pub fn type_paths(type1: &CppType) -> Vec<&CppPath> {
     match type1 {
         CppType::Void
         | CppType::BuiltInNumeric(_)
@@ -674,6 +1538,7 @@ fn type_paths(type1: &CppType) -> Vec<&CppPath> {
             .flat_map(|type1| type_paths(type1))
             .collect(),
         CppType::PointerLike { target, .. } => type_paths(target),
+        CppType::Array { element_type, .. } => type_paths(element_type),
     }
 }
 
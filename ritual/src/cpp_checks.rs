@@ -42,6 +42,13 @@ impl CppChecks {
             .map(|item| &item.env)
     }
 
+    pub fn failed_envs(&self) -> impl Iterator<Item = &LibraryTarget> {
+        self.0
+            .iter()
+            .filter(|item| !item.is_success)
+            .map(|item| &item.env)
+    }
+
     pub fn any_success(&self) -> bool {
         self.0.iter().any(|item| item.is_success)
     }
@@ -65,6 +72,127 @@ impl CppChecks {
         if self.all_success(environments) {
             return Condition::True;
         }
+        // items that are only available with an optional module
+        let modules = self
+            .0
+            .iter()
+            .flat_map(|item| item.env.enabled_modules.iter())
+            .unique()
+            .sorted()
+            .collect_vec();
+        for module in modules {
+            if self
+                .0
+                .iter()
+                .all(|item| item.is_success == item.env.enabled_modules.contains(module))
+            {
+                return Condition::Feature(module.clone());
+            }
+        }
+
+        // items that are only available with some of the compiler flag sets
+        // (e.g. items requiring a newer C++ standard)
+        let fails_without_flag_set = self
+            .0
+            .iter()
+            .all(|item| !item.is_success || item.env.flag_set.is_some());
+        if fails_without_flag_set {
+            let good_flag_sets = self
+                .0
+                .iter()
+                .filter(|item| item.is_success)
+                .filter_map(|item| item.env.flag_set.as_ref())
+                .unique()
+                .sorted()
+                .collect_vec();
+            let succeeds_with_whole_flag_set = |flag_set: &&String| {
+                self.0
+                    .iter()
+                    .filter(|item| item.env.flag_set.as_ref() == Some(*flag_set))
+                    .all(|item| item.is_success)
+            };
+            if good_flag_sets.iter().all(succeeds_with_whole_flag_set) {
+                let mut conditions = good_flag_sets
+                    .into_iter()
+                    .map(|flag_set| Condition::FlagSet(flag_set.clone()))
+                    .collect_vec();
+                return if conditions.len() == 1 {
+                    conditions.pop().unwrap()
+                } else {
+                    Condition::Or(conditions)
+                };
+            }
+        }
+
+        // items that fail on some operating systems only
+        // (e.g. thread-related items on Emscripten) are disabled for them
+        let failed_os = self
+            .0
+            .iter()
+            .filter(|item| !item.is_success)
+            .map(|item| item.env.target.os)
+            .unique()
+            .sorted_by_key(|os| os.cfg_value())
+            .collect_vec();
+        let fails_on_whole_os = |os: &OS| {
+            !self
+                .0
+                .iter()
+                .any(|item| item.is_success && item.env.target.os == *os)
+        };
+        if !failed_os.is_empty() && failed_os.iter().all(fails_on_whole_os) {
+            let mut conditions = failed_os
+                .into_iter()
+                .map(|os| Condition::Not(Box::new(Condition::OS(os))))
+                .collect_vec();
+            return if conditions.len() == 1 {
+                conditions.pop().unwrap()
+            } else {
+                Condition::And(conditions)
+            };
+        }
+
+        // items that fail with one of the Windows toolchains only
+        // (e.g. MSVC-specific extensions with MinGW) are disabled for it
+        let failed_windows_envs = self
+            .0
+            .iter()
+            .filter(|item| !item.is_success && item.env.target.os == OS::Windows)
+            .map(|item| item.env.target.env)
+            .unique()
+            .sorted_by_key(|env| env.cfg_value())
+            .collect_vec();
+        let fails_on_whole_env = |env: &Env| {
+            !self.0.iter().any(|item| {
+                item.is_success && item.env.target.os == OS::Windows && item.env.target.env == *env
+            })
+        };
+        let fails_on_windows_only = self
+            .0
+            .iter()
+            .all(|item| item.is_success || item.env.target.os == OS::Windows);
+        if !failed_windows_envs.is_empty()
+            && fails_on_windows_only
+            && failed_windows_envs
+                .iter()
+                .all(|env| (*env == Env::Gnu || *env == Env::Msvc) && fails_on_whole_env(env))
+        {
+            let mut conditions = failed_windows_envs
+                .into_iter()
+                .map(|env| {
+                    Condition::Not(Box::new(Condition::And(vec![
+                        Condition::OS(OS::Windows),
+                        Condition::Env(env),
+                    ])))
+                })
+                .collect_vec();
+            return if conditions.len() == 1 {
+                conditions.pop().unwrap()
+            } else {
+                Condition::And(conditions)
+            };
+        }
+
         if self
             .0
             .iter()
@@ -75,6 +203,8 @@ impl CppChecks {
                 .iter()
                 .filter(|item| item.is_success)
                 .map(|item| item.env.cpp_library_version.clone().unwrap())
+                .unique()
+                .sorted()
                 .collect_vec();
             if good_lib_versions.len() == 1 {
                 let good_version = good_lib_versions.into_iter().next().unwrap();
@@ -106,6 +236,10 @@ impl CppChecks {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Condition {
     CppLibraryVersion(String),
+    /// Optional module of the library is available (see `Config::add_optional_module`)
+    Feature(String),
+    /// Compiler flag set is enabled (see `Config::add_compiler_flag_set`)
+    FlagSet(String),
     Arch(Arch),
     OS(OS),
     Family(Family),
@@ -118,3 +252,22 @@ pub enum Condition {
     True,
     False,
 }
+
+impl Condition {
+    /// Returns the condition that is true if all of `conditions` are true.
+    pub fn all(conditions: impl IntoIterator<Item = Condition>) -> Condition {
+        let mut unique_conditions = Vec::new();
+        for condition in conditions {
+            if condition != Condition::True && !unique_conditions.contains(&condition) {
+                unique_conditions.push(condition);
+            }
+        }
+        if unique_conditions.contains(&Condition::False) {
+            Condition::False
+        } else if unique_conditions.len() <= 1 {
+            unique_conditions.pop().unwrap_or(Condition::True)
+        } else {
+            Condition::And(unique_conditions)
+        }
+    }
+}
@@ -1,21 +1,29 @@
-use crate::config::Config;
+use crate::config::{Config, CppInjectionPoint};
 use crate::cpp_checks::Condition;
+use crate::cpp_data::{CppItem, CppPath};
 use crate::cpp_ffi_data::{
-    CppFfiArgumentMeaning, CppFfiFunctionKind, CppFfiType, CppFieldAccessorType,
-    CppToFfiTypeConversion, QtSignalWrapper, QtSlotWrapper,
+    CppFfiArgumentMeaning, CppFfiFunctionKind, CppFfiType, CppFieldAccessorType, CppFieldOffset,
+    CppToFfiTypeConversion, CppValueLayout, QtSignalWrapper, QtSlotWrapper,
 };
 use crate::cpp_ffi_data::{CppFfiFunction, CppFfiItem};
 use crate::cpp_function::{CppFunction, ReturnValueAllocationPlace};
 use crate::cpp_type::CppPointerLikeTypeKind;
 use crate::cpp_type::CppType;
-use crate::database::{DatabaseClient, DbItem};
+use crate::database::{DatabaseClient, DbItem, ItemId};
 use crate::rust_info::{RustItem, RustStructKind};
+use crate::std_conversions;
+use crate::stream_adapters;
+use crate::string_encoding;
 use itertools::Itertools;
+use log::debug;
+use ritual_common::cpp_build_config::flag_set_macro_name;
 use ritual_common::cpp_lib_builder::version_to_number;
 use ritual_common::errors::{bail, err_msg, format_err, Result};
 use ritual_common::file_utils::{create_file, os_str_to_str, path_to_str, read_dir};
+use ritual_common::library_locator::module_macro_name;
+use ritual_common::target::{Env, OS};
 use ritual_common::utils::MapIfOk;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::io::Write;
 use std::iter::once;
 use std::path::{Path, PathBuf};
@@ -30,7 +38,15 @@ impl Generator<'_> {
         for arg in &method.arguments {
             arg_texts.push(arg.to_cpp_code()?);
         }
-        let name_with_args = format!("{}({})", method.path.to_cpp_code()?, arg_texts.join(", "));
+        let name_with_args = format!(
+            "{}{}({})",
+            method
+                .calling_convention
+                .cpp_attribute()
+                .map_or(String::new(), |attribute| format!("{} ", attribute)),
+            method.path.to_cpp_code()?,
+            arg_texts.join(", ")
+        );
         let return_type = method.return_type.ffi_type();
         let r = if let CppType::FunctionPointer(..) = return_type {
             return_type.to_cpp_code(Some(&name_with_args))?
@@ -96,6 +112,73 @@ impl Generator<'_> {
         ))
     }
 
+    /// Generates static assertions that verify that `layout` matches the layout
+    /// of the C++ union or class, so that it can be represented by
+    /// a `#[repr(C)]` Rust union or struct.
+    fn value_layout_assertions(&self, layout: &CppValueLayout) -> Result<String> {
+        let type_code = layout.path.to_cpp_code()?;
+        let (keyword, mirror_name) = if layout.is_union {
+            ("union", "ritual_union_layout")
+        } else {
+            ("struct", "ritual_struct_layout")
+        };
+        let mirror_name = format!("{}_{}", mirror_name, layout.path.ascii_caption());
+        let fields = layout
+            .fields
+            .iter()
+            .enumerate()
+            .map_if_ok(|(index, field)| -> Result<_> {
+                Ok(format!(
+                    "{} f{};",
+                    field.field_type.to_cpp_code(None)?,
+                    index
+                ))
+            })?
+            .join(" ");
+        let mut code = format!("{} {} {{ {} }};\n", keyword, mirror_name, fields);
+        let mut add_assertion = |condition: String| {
+            code += &format!(
+                "static_assert({}, \"layout mismatch: {}\");\n",
+                condition, type_code
+            );
+        };
+        add_assertion(format!("sizeof({}) == sizeof({})", type_code, mirror_name));
+        add_assertion(format!(
+            "alignof({}) == alignof({})",
+            type_code, mirror_name
+        ));
+        add_assertion(format!("std::is_trivially_copyable<{}>::value", type_code));
+        add_assertion(format!("std::is_standard_layout<{}>::value", type_code));
+        for (index, field) in layout.fields.iter().enumerate() {
+            add_assertion(format!(
+                "std::is_same<decltype({}::{}), {}>::value",
+                type_code,
+                field.name,
+                field.field_type.to_cpp_code(None)?
+            ));
+            if !layout.is_union {
+                add_assertion(format!(
+                    "offsetof({}, {}) == offsetof({}, f{})",
+                    type_code, field.name, mirror_name, index
+                ));
+            }
+        }
+        Ok(code)
+    }
+
+    /// Generates static assertions that verify the offset of the field
+    /// that is accessed directly from Rust. `offsetof` is only valid
+    /// for standard-layout classes, so that is also verified.
+    fn field_offset_assertion(&self, offset: &CppFieldOffset) -> Result<String> {
+        let class_code = offset.path.parent()?.to_cpp_code()?;
+        let field_name = &offset.path.last().name;
+        Ok(format!(
+            "static_assert(std::is_standard_layout<{0}>::value, \"not standard layout: {0}\");\n\
+             static_assert(offsetof({0}, {1}) == {2}, \"offset mismatch: {0}::{1}\");\n",
+            class_code, field_name, offset.offset
+        ))
+    }
+
     /// Generates code that wraps `expression` of type `type1.original_type` and
     /// converts it to type `type1.ffi_type`
     fn convert_type_to_ffi(&self, type1: &CppFfiType, expression: String) -> Result<String> {
@@ -134,13 +217,20 @@ impl Generator<'_> {
         match method.return_type.conversion() {
             CppToFfiTypeConversion::NoChange | CppToFfiTypeConversion::ImplicitCast { .. } => {}
             CppToFfiTypeConversion::ValueToPointer { .. } => {
-                match method.allocation_place {
+                match &method.allocation_place {
                     ReturnValueAllocationPlace::Stack => {
                         bail!("stack allocated wrappers are expected to return void");
                     }
                     ReturnValueAllocationPlace::NotApplicable => {
                         bail!("ValueToPointer conflicts with NotApplicable");
                     }
+                    ReturnValueAllocationPlace::Factory { function } => {
+                        // constructors pass their arguments to the factory function,
+                        // and other functions pass the returned value
+                        if !is_constructor {
+                            result = format!("{}({})", function.to_cpp_code()?, result);
+                        }
+                    }
                     ReturnValueAllocationPlace::Heap => {
                         // constructors are said to return values in parse result,
                         // but in reality we use `new` which returns a pointer,
@@ -231,7 +321,11 @@ impl Generator<'_> {
                 .iter()
                 .find(|x| x.meaning == CppFfiArgumentMeaning::This)
             {
-                format!("ritual::call_destructor({})", arg.name)
+                if let ReturnValueAllocationPlace::Factory { function } = &method.allocation_place {
+                    format!("{}({})", function.to_cpp_code()?, arg.name)
+                } else {
+                    format!("ritual::call_destructor({})", arg.name)
+                }
             } else {
                 bail!("no this arg in destructor");
             }
@@ -260,13 +354,14 @@ impl Generator<'_> {
                     ReturnValueAllocationPlace::Heap => {
                         format!("new {}", cpp_function.class_path()?.to_cpp_code()?)
                     }
+                    ReturnValueAllocationPlace::Factory { function } => function.to_cpp_code()?,
                     ReturnValueAllocationPlace::NotApplicable => {
                         bail!("NotApplicable in constructor");
                     }
                 }
             } else {
                 let path = cpp_item.item.path().ok_or_else(|| {
-                    err_msg("cpp item (function or field) expected to have a path")
+                    err_msg("cpp item (function, field or variable) expected to have a path")
                 })?;
 
                 if let Some(arg) = method
@@ -279,22 +374,25 @@ impl Generator<'_> {
                     path.to_cpp_code()?
                 }
             };
-            if let CppFfiFunctionKind::FieldAccessor { accessor_type, .. } = &method.kind {
-                if accessor_type == &CppFieldAccessorType::Setter {
-                    format!(
-                        "{} = {}",
-                        result_without_args,
-                        self.arguments_values(method)?
-                    )
-                } else {
-                    result_without_args
+            match &method.kind {
+                CppFfiFunctionKind::FieldAccessor { accessor_type, .. } => {
+                    if accessor_type == &CppFieldAccessorType::Setter {
+                        format!(
+                            "{} = {}",
+                            result_without_args,
+                            self.arguments_values(method)?
+                        )
+                    } else {
+                        result_without_args
+                    }
                 }
-            } else {
-                format!(
+                CppFfiFunctionKind::VariableAddress => format!("&{}", result_without_args),
+                CppFfiFunctionKind::VariableValue => result_without_args,
+                CppFfiFunctionKind::Function => format!(
                     "{}({})",
                     result_without_args,
                     self.arguments_values(method)?
-                )
+                ),
             }
         };
         self.convert_return_type(item, result)
@@ -313,18 +411,26 @@ impl Generator<'_> {
             .map_or(false, |f| f.is_destructor());
 
         let method = item.item;
-        if is_destructor && method.allocation_place == ReturnValueAllocationPlace::Heap {
+        let thread_check = if method.main_thread_only {
+            format!(
+                "ritual_assert_main_thread(\"{}\");\n  ",
+                cpp_item.item.short_text().replace('"', "\\\"")
+            )
+        } else {
+            String::new()
+        };
+        let body = if is_destructor && method.allocation_place == ReturnValueAllocationPlace::Heap {
             if let Some(arg) = method
                 .arguments
                 .iter()
                 .find(|x| x.meaning == CppFfiArgumentMeaning::This)
             {
-                Ok(format!("delete {};\n", arg.name))
+                format!("delete {};\n", arg.name)
             } else {
                 panic!("Error: no this argument found\n{:?}", method);
             }
         } else {
-            Ok(format!(
+            format!(
                 "{}{};\n",
                 if method.return_type.ffi_type().is_void() {
                     ""
@@ -332,60 +438,275 @@ impl Generator<'_> {
                     "return "
                 },
                 self.returned_expression(item)?
-            ))
-        }
+            )
+        };
+        Ok(thread_check + &body)
     }
 
     /// Generates implementation of the FFI method for the source file.
     fn function_implementation(&self, method: DbItem<&CppFfiFunction>) -> Result<String> {
         Ok(format!(
-            "RITUAL_EXPORT {} {{\n  {}}}\n\n",
+            "RITUAL_EXPORT {}{} {{\n  {}}}\n\n",
+            if method.item.out_of_line {
+                "RITUAL_NOINLINE "
+            } else {
+                ""
+            },
             self.function_signature(method.item)?,
             self.source_body(method)?
         ))
     }
 
-    fn condition_expression(&self, condition: &Condition) -> String {
-        match condition {
+    /// Returns a string that is equal for FFI functions that have the same ABI shape
+    /// (the signature apart from the function name), the same body and the same
+    /// condition, so they can be implemented by a shared thunk. Returns `None`
+    /// for functions that must keep their own implementation.
+    fn shared_thunk_key(
+        &self,
+        item: DbItem<&CppFfiFunction>,
+        condition: &Condition,
+    ) -> Result<Option<String>> {
+        if item.item.out_of_line {
+            return Ok(None);
+        }
+        Ok(Some(format!(
+            "{}\n{}\n{:?}",
+            self.function_signature(&thunk_function(item.item, 0))?,
+            self.source_body(item)?,
+            condition
+        )))
+    }
+
+    /// Generates implementation of a thunk shared by FFI functions
+    /// that have the same body as `method`.
+    fn shared_thunk(&self, method: DbItem<&CppFfiFunction>, index: usize) -> Result<String> {
+        Ok(format!(
+            "static RITUAL_NOINLINE {} {{\n  {}}}\n\n",
+            self.function_signature(&thunk_function(method.item, index))?,
+            self.source_body(method)?
+        ))
+    }
+
+    /// Generates the FFI method implemented by shared thunk `index`. Depending on
+    /// the compiler, the method is an alias of the thunk or forwards the call to it
+    /// (see `RITUAL_SHARED_THUNK` in the global header).
+    fn shared_thunk_user(&self, method: &CppFfiFunction, index: usize) -> Result<String> {
+        Ok(format!(
+            "RITUAL_EXPORT {} RITUAL_SHARED_THUNK({}, {})\n\n",
+            self.function_signature(method)?,
+            thunk_function(method, index).path.to_cpp_code()?,
+            method.arguments.iter().map(|arg| &arg.name).join(", ")
+        ))
+    }
+
+    fn condition_expression(&self, condition: &Condition) -> Result<String> {
+        Ok(match condition {
             Condition::CppLibraryVersion(version) => {
                 let value = version_to_number(version).expect("version_to_number failed");
                 format!("RITUAL_CPP_LIB_VERSION == {}", value)
             }
-            Condition::Arch(_) => unimplemented!(),
-            Condition::OS(_) => unimplemented!(),
-            Condition::Family(_) => unimplemented!(),
-            Condition::Env(_) => unimplemented!(),
-            Condition::PointerWidth(_) => unimplemented!(),
-            Condition::Endian(_) => unimplemented!(),
+            Condition::Feature(name) => format!("defined({})", module_macro_name(name)),
+            Condition::FlagSet(name) => format!("defined({})", flag_set_macro_name(name)),
+            Condition::Arch(_) => bail!("unsupported condition: {:?}", condition),
+            Condition::OS(os) => match os {
+                OS::Windows => "defined(_WIN32)",
+                // `TARGET_OS_IPHONE` is defined in `TargetConditionals.h`
+                // included by the global header
+                OS::MacOS => "defined(__APPLE__) && !TARGET_OS_IPHONE",
+                OS::IOS => "defined(__APPLE__) && TARGET_OS_IPHONE",
+                // `__linux__` is also defined by the Android NDK
+                OS::Linux => "defined(__linux__) && !defined(__ANDROID__)",
+                OS::Android => "defined(__ANDROID__)",
+                OS::FreeBSD => "defined(__FreeBSD__)",
+                OS::DragonFly => "defined(__DragonFly__)",
+                OS::Bitrig => "defined(__Bitrig__)",
+                OS::OpenBSD => "defined(__OpenBSD__)",
+                OS::NetBSD => "defined(__NetBSD__)",
+                OS::Emscripten => "defined(__EMSCRIPTEN__)",
+            }
+            .to_string(),
+            Condition::Family(_) => bail!("unsupported condition: {:?}", condition),
+            Condition::Env(env) => match env {
+                // `__GNUC__` is defined by MinGW compilers but not by `cl` and `clang-cl`
+                Env::Gnu => "defined(__GNUC__)".to_string(),
+                Env::Msvc => "defined(_MSC_VER)".to_string(),
+                // musl doesn't define a macro that identifies it
+                Env::Musl => "defined(__linux__) && !defined(__ANDROID__) \
+                               && !defined(__GLIBC__)"
+                    .to_string(),
+                Env::None => bail!("no C++ condition corresponds to an empty target environment"),
+            },
+            Condition::PointerWidth(_) => bail!("unsupported condition: {:?}", condition),
+            Condition::Endian(_) => bail!("unsupported condition: {:?}", condition),
             Condition::And(conditions) => conditions
                 .iter()
-                .map(|c| format!("({})", self.condition_expression(c)))
+                .map_if_ok(|c| -> Result<_> { Ok(format!("({})", self.condition_expression(c)?)) })?
                 .join("&&"),
             Condition::Or(conditions) => conditions
                 .iter()
-                .map(|c| format!("({})", self.condition_expression(c)))
+                .map_if_ok(|c| -> Result<_> { Ok(format!("({})", self.condition_expression(c)?)) })?
                 .join("||"),
-            Condition::Not(condition) => format!("!({})", self.condition_expression(condition)),
+            Condition::Not(condition) => format!("!({})", self.condition_expression(condition)?),
             Condition::True => "true".to_string(),
             Condition::False => "false".to_string(),
-        }
+        })
     }
 
-    fn wrap_with_condition(&self, code: &str, condition: &Condition) -> String {
+    fn wrap_with_condition(&self, code: &str, condition: &Condition) -> Result<String> {
         if condition == &Condition::True {
-            return code.to_string();
+            return Ok(code.to_string());
         }
-        format!(
+        Ok(format!(
             "#if {}\n{}\n#endif\n",
-            self.condition_expression(condition),
+            self.condition_expression(condition)?,
             code
-        )
+        ))
+    }
+
+    /// Returns name of the source file for the FFI function `id`. Functions are grouped
+    /// by the class or namespace of the original C++ item, so that changes in one class
+    /// only require recompilation of one file. Names are lowercase to avoid conflicts
+    /// on case-insensitive file systems. Returns `None` for functions placed
+    /// in the main source file.
+    ///
+    /// Methods of Qt slot and signal wrappers are kept in the main source file
+    /// because the wrapper classes are only defined there.
+    fn function_file_name(&self, id: &ItemId) -> Result<Option<String>> {
+        if self.is_qt_wrapper_method(id)? {
+            return Ok(None);
+        }
+        let path = match self.0.original_cpp_item(id)? {
+            Some(item) => match item.item {
+                CppItem::Function(data) => data.path.parent().ok(),
+                CppItem::ClassField(data) => data.path.parent().ok(),
+                CppItem::Variable(data) => data.path.parent().ok(),
+                _ => None,
+            },
+            None => None,
+        };
+        Ok(path.map(|path| {
+            let name = path
+                .items()
+                .iter()
+                .map(|item| {
+                    item.to_cpp_code()
+                        .unwrap_or_else(|_| item.name.clone())
+                        .chars()
+                        .map(|c| {
+                            if c.is_ascii_alphanumeric() {
+                                c.to_ascii_lowercase()
+                            } else {
+                                '_'
+                            }
+                        })
+                        .collect::<String>()
+                })
+                .join("_");
+            format!("ffi_{}.cpp", name)
+        }))
     }
 
-    /// Generates a source file with the specified FFI methods.
-    fn generate_cpp_file(&self, file_path: &Path, global_header_name: &str) -> Result<()> {
-        let mut cpp_file = create_file(file_path)?;
+    /// Returns true if the FFI function `id` wraps a method of a Qt slot
+    /// or signal wrapper class.
+    fn is_qt_wrapper_method(&self, id: &ItemId) -> Result<bool> {
+        Ok(self.0.source_ffi_item(id)?.map_or(false, |item| {
+            item.item.is_slot_wrapper() || item.item.is_signal_wrapper()
+        }))
+    }
+
+    /// Writes implementations of FFI functions inside an `extern "C"` block
+    /// that is opened and closed by the caller.
+    ///
+    /// Functions with identical ABI shape, body and condition are implemented
+    /// by a single shared thunk, so the body is only compiled once.
+    fn write_functions(
+        &self,
+        mut output: impl Write,
+        functions: &[(DbItem<&CppFfiFunction>, Condition)],
+    ) -> Result<()> {
+        let thunk_keys = functions
+            .iter()
+            .map_if_ok(|(item, condition)| self.shared_thunk_key(item.clone(), condition))?;
+        let thunk_indexes = shared_thunk_indexes(&thunk_keys);
+
+        let mut thunks_count = 0;
+        for ((item, condition), thunk_index) in functions.iter().zip(&thunk_indexes) {
+            if *thunk_index == Some(thunks_count) {
+                thunks_count += 1;
+                let code = self.shared_thunk(item.clone(), thunks_count - 1)?;
+                writeln!(output, "{}", self.wrap_with_condition(&code, condition)?)?;
+            }
+        }
+        for ((item, condition), thunk_index) in functions.iter().zip(&thunk_indexes) {
+            let code = if let Some(thunk_index) = *thunk_index {
+                self.shared_thunk_user(item.item, thunk_index)?
+            } else {
+                self.function_implementation(item.clone())?
+            };
+            writeln!(output, "{}", self.wrap_with_condition(&code, condition)?)?;
+        }
+        if thunks_count > 0 {
+            debug!(
+                "{} FFI functions share {} thunks",
+                thunk_indexes.iter().filter(|index| index.is_some()).count(),
+                thunks_count
+            );
+        }
+        Ok(())
+    }
+
+    /// Generates source files of the C++ wrapper library in `dir`.
+    /// FFI functions are split into separate files (see `function_file_name`),
+    /// and all other items are placed in the main source file. Split files only
+    /// include the global header, so helpers that are only used by the main
+    /// source file (Qt wrapper classes, injected code, string encoding and
+    /// stream helpers) are not available in them.
+    ///
+    /// If `item_includes` is specified, the global header doesn't include
+    /// the library headers. Instead, each split file includes the directives
+    /// required by its FFI items, and the main source file includes all of them.
+    ///
+    /// FFI items that require a header group (`item_header_groups`) are placed
+    /// in separate files that include the headers of the group
+    /// (see `Config::add_header_group`).
+    fn generate_cpp_files(
+        &self,
+        dir: &Path,
+        global_header_name: &str,
+        config: &Config,
+        item_includes: Option<&HashMap<ItemId, BTreeSet<PathBuf>>>,
+        item_header_groups: &HashMap<ItemId, usize>,
+    ) -> Result<()> {
+        let file_path = dir.join("file1.cpp");
+        let mut cpp_file = create_file(&file_path)?;
+        if let Some(item_includes) = item_includes {
+            // headers of different groups can't be included together
+            let directives = item_includes
+                .iter()
+                .filter(|(id, _)| !item_header_groups.contains_key(id))
+                .flat_map(|(_, directives)| directives)
+                .collect::<BTreeSet<_>>();
+            write_include_directives(
+                &mut cpp_file,
+                &directives.into_iter().cloned().collect_vec(),
+            )?;
+        }
         writeln!(cpp_file, "#include \"{}\"", global_header_name)?;
+        if let Some(encoding) = config.string_encoding() {
+            for include in string_encoding::cpp_includes(encoding) {
+                writeln!(cpp_file, "#include <{}>", include)?;
+            }
+        }
+        write_injected_code(
+            &mut cpp_file,
+            &config.cpp_code_injected_at(CppInjectionPoint::BeforeWrappers),
+        )?;
+        let stream_types = stream_adapters::find_stream_types(self.0)?;
+        write!(
+            cpp_file,
+            "{}",
+            stream_adapters::cpp_classes(&stream_types, self.0.crate_name())?
+        )?;
 
         let used_ffi_functions = self
             .0
@@ -401,10 +722,17 @@ impl Generator<'_> {
             .filter(|item| {
                 !item.item.is_function()
                     || used_ffi_functions.contains(item.item.path().last().name.as_str())
+                    || item
+                        .item
+                        .as_function_ref()
+                        .map_or(false, |function| function.out_of_line)
             })
             .collect_vec();
 
         let mut needs_moc = false;
+        // assertions of items of header groups, by file name
+        let mut group_assertions = BTreeMap::<String, String>::new();
+        let mut file_groups = HashMap::<String, usize>::new();
         for ffi_item in &ffi_items {
             match &ffi_item.item {
                 CppFfiItem::QtSlotWrapper(qt_slot_wrapper) => {
@@ -415,7 +743,7 @@ impl Generator<'_> {
                     needs_moc = true;
                     let condition = checks.condition(self.0.environments());
                     let code = self.qt_slot_wrapper(qt_slot_wrapper)?;
-                    write!(cpp_file, "{}", self.wrap_with_condition(&code, &condition))?;
+                    write!(cpp_file, "{}", self.wrap_with_condition(&code, &condition)?)?;
                 }
                 CppFfiItem::QtSignalWrapper(qt_signal_wrapper) => {
                     let checks = self.0.cpp_checks(&ffi_item.id)?;
@@ -425,24 +753,138 @@ impl Generator<'_> {
                     needs_moc = true;
                     let condition = checks.condition(self.0.environments());
                     let code = self.qt_signal_wrapper(qt_signal_wrapper)?;
-                    write!(cpp_file, "{}", self.wrap_with_condition(&code, &condition))?;
+                    write!(cpp_file, "{}", self.wrap_with_condition(&code, &condition)?)?;
+                }
+                CppFfiItem::ValueLayout(layout) => {
+                    let checks = self.0.cpp_checks(&ffi_item.id)?;
+                    if !checks.any_success() {
+                        continue;
+                    }
+                    let condition = checks.condition(self.0.environments());
+                    let code = self.value_layout_assertions(layout)?;
+                    let code = self.wrap_with_condition(&code, &condition)?;
+                    match item_header_groups.get(&ffi_item.id) {
+                        Some(&group) => {
+                            let name = header_group_file_name(group, None);
+                            file_groups.insert(name.clone(), group);
+                            group_assertions.entry(name).or_default().push_str(&code);
+                        }
+                        None => write!(cpp_file, "{}", code)?,
+                    }
+                }
+                CppFfiItem::FieldOffset(offset) => {
+                    let checks = self.0.cpp_checks(&ffi_item.id)?;
+                    if !checks.any_success() {
+                        continue;
+                    }
+                    let condition = checks.condition(self.0.environments());
+                    let code = self.field_offset_assertion(offset)?;
+                    let code = self.wrap_with_condition(&code, &condition)?;
+                    match item_header_groups.get(&ffi_item.id) {
+                        Some(&group) => {
+                            let name = header_group_file_name(group, None);
+                            file_groups.insert(name.clone(), group);
+                            group_assertions.entry(name).or_default().push_str(&code);
+                        }
+                        None => write!(cpp_file, "{}", code)?,
+                    }
                 }
                 _ => {}
             }
         }
 
-        writeln!(cpp_file, "extern \"C\" {{")?;
+        let mut main_functions = Vec::new();
+        let mut split_functions = BTreeMap::<_, Vec<_>>::new();
         for ffi_item in &ffi_items {
             if let Some(item) = ffi_item.clone().filter_map(|item| item.as_function_ref()) {
                 let checks = self.0.cpp_checks(&ffi_item.id)?;
                 if !checks.any_success() {
                     continue;
                 }
-                let condition = checks.condition(self.0.environments());
-                let code = self.function_implementation(item)?;
-                writeln!(cpp_file, "{}", self.wrap_with_condition(&code, &condition))?;
+                let function = (item, checks.condition(self.0.environments()));
+                let name = self.function_file_name(&ffi_item.id)?;
+                let group = if self.is_qt_wrapper_method(&ffi_item.id)? {
+                    None
+                } else {
+                    item_header_groups.get(&ffi_item.id)
+                };
+                let name = match group {
+                    Some(&group) => {
+                        let name = header_group_file_name(group, name.as_deref());
+                        file_groups.insert(name.clone(), group);
+                        Some(name)
+                    }
+                    None => name,
+                };
+                match name {
+                    Some(name) => split_functions.entry(name).or_default().push(function),
+                    None => main_functions.push(function),
+                }
+            }
+        }
+        for name in group_assertions.keys() {
+            split_functions.entry(name.clone()).or_default();
+        }
+        for (name, functions) in &split_functions {
+            let mut file = create_file(dir.join(name))?;
+            if let Some(item_includes) = item_includes {
+                let directives = functions
+                    .iter()
+                    .filter_map(|(function, _)| item_includes.get(&function.id))
+                    .flatten()
+                    .collect::<BTreeSet<_>>();
+                write_include_directives(
+                    &mut file,
+                    &directives.into_iter().cloned().collect_vec(),
+                )?;
+            }
+            writeln!(file, "#include \"{}\"", global_header_name)?;
+            if let Some(&group) = file_groups.get(name) {
+                // the group is parsed after the common headers, so it's included
+                // after the global header
+                let directives = config
+                    .header_groups()
+                    .get(group)
+                    .ok_or_else(|| format_err!("invalid header group index: {}", group))?;
+                write_include_directives(&mut file, directives)?;
             }
+            if let Some(code) = group_assertions.get(name) {
+                write!(file, "{}", code)?;
+            }
+            writeln!(file, "extern \"C\" {{")?;
+            self.write_functions(&mut file, functions)?;
+            writeln!(file, "}} // extern \"C\"")?;
+        }
+        debug!(
+            "FFI functions are split into {} source files",
+            split_functions.len()
+        );
+
+        writeln!(cpp_file, "extern \"C\" {{")?;
+        self.write_functions(&mut cpp_file, &main_functions)?;
+        for conversion in std_conversions::find_std_conversions(self.0)? {
+            let code = std_conversions::cpp_helpers(&conversion)?;
+            write!(
+                cpp_file,
+                "{}",
+                self.wrap_with_condition(&code, &conversion.condition)?
+            )?;
+        }
+        write!(
+            cpp_file,
+            "{}",
+            stream_adapters::cpp_helpers(&stream_types, self.0.crate_name())?
+        )?;
+        if let Some(encoding) = config.string_encoding() {
+            let std_string = string_encoding::find_std_string_type(self.0)?;
+            let code =
+                string_encoding::cpp_helpers(encoding, self.0.crate_name(), std_string.as_ref())?;
+            write!(cpp_file, "{}", code)?;
         }
+        write_injected_code(
+            &mut cpp_file,
+            &config.cpp_code_injected_at(CppInjectionPoint::ExternC),
+        )?;
         writeln!(cpp_file, "}} // extern \"C\"")?;
 
         if needs_moc && !self.0.crate_name().starts_with("moqt_") {
@@ -494,6 +936,46 @@ impl Generator<'_> {
     }
 }
 
+/// Returns name of the source file for FFI items of the header group `group`
+/// that would otherwise be placed in the file `name` or in the main source file
+/// (if `name` is `None`).
+fn header_group_file_name(group: usize, name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("group{}_{}", group, name),
+        None => format!("group{}.cpp", group),
+    }
+}
+
+/// Returns a copy of `method` renamed to the name of shared thunk `index`.
+fn thunk_function(method: &CppFfiFunction, index: usize) -> CppFfiFunction {
+    CppFfiFunction {
+        path: CppPath::from_good_str(&format!("ritual_thunk_{}", index)),
+        ..method.clone()
+    }
+}
+
+/// Assigns shared thunks to FFI functions with `keys` returned by
+/// `Generator::shared_thunk_key`. Functions with equal keys get the same thunk index
+/// if there are at least two of them. Thunks are numbered in order of their first use.
+pub fn shared_thunk_indexes(keys: &[Option<String>]) -> Vec<Option<usize>> {
+    let mut counts = HashMap::new();
+    for key in keys.iter().flatten() {
+        *counts.entry(key).or_insert(0) += 1;
+    }
+    let mut indexes = HashMap::new();
+    keys.iter()
+        .map(|key| {
+            let key = key.as_ref().filter(|key| counts[key] > 1)?;
+            let next_index = indexes.len();
+            Some(*indexes.entry(key).or_insert(next_index))
+        })
+        .collect()
+}
+
+pub fn function_signature(db: &DatabaseClient, method: &CppFfiFunction) -> Result<String> {
+    Generator(db).function_signature(method)
+}
+
 pub fn function_implementation(
     db: &DatabaseClient,
     method: DbItem<&CppFfiFunction>,
@@ -501,6 +983,22 @@ pub fn function_implementation(
     Generator(db).function_implementation(method)
 }
 
+pub fn write_functions(
+    db: &DatabaseClient,
+    output: impl Write,
+    functions: &[(DbItem<&CppFfiFunction>, Condition)],
+) -> Result<()> {
+    Generator(db).write_functions(output, functions)
+}
+
+pub fn value_layout_assertions(db: &DatabaseClient, layout: &CppValueLayout) -> Result<String> {
+    Generator(db).value_layout_assertions(layout)
+}
+
+pub fn field_offset_assertion(db: &DatabaseClient, offset: &CppFieldOffset) -> Result<String> {
+    Generator(db).field_offset_assertion(offset)
+}
+
 pub fn qt_slot_wrapper(db: &DatabaseClient, wrapper: &QtSlotWrapper) -> Result<String> {
     Generator(db).qt_slot_wrapper(wrapper)
 }
@@ -509,12 +1007,21 @@ pub fn qt_signal_wrapper(db: &DatabaseClient, wrapper: &QtSignalWrapper) -> Resu
     Generator(db).qt_signal_wrapper(wrapper)
 }
 
-pub fn generate_cpp_file(
+pub fn generate_cpp_files(
     db: &DatabaseClient,
-    file_path: &Path,
+    dir: &Path,
     global_header_name: &str,
+    config: &Config,
+    item_includes: Option<&HashMap<ItemId, BTreeSet<PathBuf>>>,
+    item_header_groups: &HashMap<ItemId, usize>,
 ) -> Result<()> {
-    Generator(db).generate_cpp_file(file_path, global_header_name)
+    Generator(db).generate_cpp_files(
+        dir,
+        global_header_name,
+        config,
+        item_includes,
+        item_header_groups,
+    )
 }
 
 pub fn generate_cpp_type_size_requester(
@@ -525,24 +1032,44 @@ pub fn generate_cpp_type_size_requester(
     Generator(db).generate_cpp_type_size_requester(include_directives, output)
 }
 
-pub fn all_include_directives(config: &Config) -> Result<Vec<PathBuf>> {
-    let mut all_include_directives = config.include_directives().to_vec();
-
+/// Returns include directives of the files from `c_lib/extra` directory
+/// of the crate template.
+pub fn template_include_directives(config: &Config) -> Result<Vec<PathBuf>> {
+    let mut directives = Vec::new();
     if let Some(crate_template_path) = config.crate_template_path() {
         let extra_template = crate_template_path.join("c_lib/extra");
         if extra_template.exists() {
-            for item in read_dir(&extra_template)? {
-                all_include_directives.push(PathBuf::from(format!(
-                    "extra/{}",
-                    os_str_to_str(&item?.file_name())?
-                )));
+            let mut file_names = read_dir(&extra_template)?.map_if_ok(|item| -> Result<_> {
+                Ok(os_str_to_str(&item?.file_name())?.to_string())
+            })?;
+            // `read_dir` order is platform-dependent
+            file_names.sort();
+            for file_name in file_names {
+                directives.push(PathBuf::from(format!("extra/{}", file_name)));
             }
         }
     }
+    Ok(directives)
+}
 
+pub fn all_include_directives(config: &Config) -> Result<Vec<PathBuf>> {
+    let mut all_include_directives = config.include_directives().to_vec();
+    all_include_directives.extend(template_include_directives(config)?);
     Ok(all_include_directives)
 }
 
+/// Writes code snippets added with `Config::add_cpp_code_injection`.
+pub fn write_injected_code(mut destination: impl Write, snippets: &[&str]) -> Result<()> {
+    for snippet in snippets {
+        writeln!(
+            &mut destination,
+            "// injected by the crate configuration\n{}",
+            snippet
+        )?;
+    }
+    Ok(())
+}
+
 pub fn write_include_directives(mut destination: impl Write, directives: &[PathBuf]) -> Result<()> {
     for directive in directives {
         writeln!(
@@ -6,6 +6,7 @@ use crate::cpp_type::{CppTemplateParameter, CppType};
 use crate::database::DatabaseClient;
 use itertools::Itertools;
 use ritual_common::errors::{bail, ensure, Error, Result};
+use ritual_common::target::LibraryTarget;
 use ritual_common::utils::MapIfOk;
 use serde_derive::{Deserialize, Serialize};
 use std::fmt;
@@ -18,6 +19,10 @@ pub struct CppEnumValue {
     pub path: CppPath,
     /// Corresponding value
     pub value: i64,
+    /// Path of the first variant of the enum with the same value
+    /// if this variant is an alias of it
+    #[serde(default)]
+    pub alias_of: Option<CppPath>,
 }
 
 impl CppEnumValue {
@@ -41,6 +46,7 @@ fn unscoped_path_should_work() {
         let v = CppEnumValue {
             path: CppPath::from_good_str(path),
             value: 0,
+            alias_of: None,
         };
         assert_eq!(v.unscoped_path(), CppPath::from_good_str(result));
     }
@@ -57,7 +63,10 @@ pub struct CppClassField {
     pub field_type: CppType,
     /// Visibility
     pub visibility: CppVisibility,
-    pub is_static: bool,
+    /// Offset of the field in bytes, as reported by clang for the parsing environment.
+    /// `None` if the offset is unknown (e.g. for fields of class templates and bit fields).
+    #[serde(default)]
+    pub offset: Option<usize>,
 }
 
 impl CppClassField {
@@ -65,7 +74,6 @@ impl CppClassField {
         self.path == other.path
             && self.field_type == other.field_type
             && self.visibility == other.visibility
-            && self.is_static == other.is_static
     }
 
     pub fn short_text(&self) -> String {
@@ -83,6 +91,54 @@ impl CppClassField {
     }
 }
 
+/// Global variable or static data member of a class
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
+pub struct CppVariable {
+    pub path: CppPath,
+    /// Type of the variable, without the top level `const`
+    pub variable_type: CppType,
+    /// True if the variable can't be modified
+    pub is_const: bool,
+    /// True if this is a static data member of a class
+    pub is_static_member: bool,
+    /// True if this is a static data member initialized in the class definition.
+    /// Before C++17, such a member may have no definition, so its address can't be taken.
+    #[serde(default)]
+    pub has_in_class_initializer: bool,
+    /// Visibility (always public for global variables)
+    pub visibility: CppVisibility,
+}
+
+impl CppVariable {
+    pub fn is_same(&self, other: &CppVariable) -> bool {
+        self.path == other.path
+            && self.variable_type == other.variable_type
+            && self.is_const == other.is_const
+    }
+
+    pub fn short_text(&self) -> String {
+        let visibility_text = match self.visibility {
+            CppVisibility::Public => "",
+            CppVisibility::Protected => "protected ",
+            CppVisibility::Private => "private ",
+        };
+        let prefix = if self.is_static_member {
+            "static "
+        } else {
+            "extern "
+        };
+        let const_text = if self.is_const { "const " } else { "" };
+        format!(
+            "{}{}{}{} {}",
+            visibility_text,
+            prefix,
+            const_text,
+            self.variable_type.to_cpp_pseudo_code(),
+            self.path.to_cpp_pseudo_code(),
+        )
+    }
+}
+
 /// Item of base class list in a class declaration
 #[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
 pub struct CppBaseSpecifier {
@@ -111,6 +167,16 @@ pub struct CppOriginLocation {
     pub column: u32,
 }
 
+impl fmt::Display for CppOriginLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.include_file_path, self.line, self.column
+        )
+    }
+}
+
 /// Visibility of a C++ entity. Defaults to `Public`
 /// for entities that can't have visibility (like free functions)
 #[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
@@ -262,6 +328,40 @@ impl CppPath {
         Ok(new_path)
     }
 
+    /// Checks if `concrete` path can be produced by instantiating `self`
+    /// with some template arguments of `nested_level`. Matched arguments are
+    /// recorded in `arguments` by index of the template parameter.
+    pub fn match_template(
+        &self,
+        concrete: &CppPath,
+        nested_level: usize,
+        arguments: &mut Vec<Option<CppType>>,
+    ) -> bool {
+        if self.items.len() != concrete.items.len() {
+            return false;
+        }
+        for (item, concrete_item) in self.items.iter().zip(&concrete.items) {
+            if item.name != concrete_item.name {
+                return false;
+            }
+            match (&item.template_arguments, &concrete_item.template_arguments) {
+                (None, None) => {}
+                (Some(args), Some(concrete_args)) => {
+                    if args.len() != concrete_args.len() {
+                        return false;
+                    }
+                    for (arg, concrete_arg) in args.iter().zip(concrete_args) {
+                        if !arg.match_template(concrete_arg, nested_level, arguments) {
+                            return false;
+                        }
+                    }
+                }
+                _ => return false,
+            }
+        }
+        true
+    }
+
     pub fn deinstantiate(&self) -> CppPath {
         let mut path = self.clone();
         let mut nested_level = 0;
@@ -369,8 +469,25 @@ impl fmt::Debug for CppPathItem {
 /// Information about a C++ type declaration
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Hash)]
 pub enum CppTypeDeclarationKind {
-    Enum,
+    Enum {
+        /// True for `enum class` and `enum struct`. Unlike values of unscoped enums,
+        /// values of scoped enums are not implicitly converted to integers.
+        is_scoped: bool,
+    },
     Class,
+    Union,
+}
+
+/// Kind of a class template specialization declared in the headers
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Hash)]
+pub enum CppSpecializationKind {
+    /// Explicit (full) specialization, e.g. `template<> class QList<QString>`.
+    /// The path of the type contains the specialized template arguments.
+    Explicit,
+    /// Partial specialization, e.g. `template<class T> class QList<T*>`.
+    /// The path of the type contains the specialized template arguments
+    /// that refer to the template parameters of the specialization.
+    Partial,
 }
 
 /// Information about a C++ type declaration
@@ -379,6 +496,44 @@ pub struct CppTypeDeclaration {
     /// Identifier, including namespaces and nested classes
     pub path: CppPath,
     pub kind: CppTypeDeclarationKind,
+    /// Set if this declaration is a specialization of a class template.
+    /// Members of a specialization are used instead of members of the primary template
+    /// for matching instantiations.
+    #[serde(default)]
+    pub specialization: Option<CppSpecializationKind>,
+    /// Set if the class doesn't have user-declared copy and move operations,
+    /// destructor, virtual functions and base classes, so it's likely
+    /// trivially copyable and can be wrapped as a plain Rust struct
+    /// (see `Config::set_plain_struct_hook`).
+    #[serde(default)]
+    pub is_trivially_copyable: bool,
+    /// Set if the class explicitly deletes its move constructor, so its objects
+    /// must stay at the address they were constructed at. Classes that are
+    /// only non-copyable are not immovable because `CppBox` never moves objects.
+    #[serde(default)]
+    pub is_immovable: bool,
+}
+
+/// Properties of a C++ type reported by the compiler on a library target.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CppTypeProperties {
+    pub path: CppPath,
+    pub env: LibraryTarget,
+    /// Value of `sizeof` for the type.
+    pub size: usize,
+    /// Value of `alignof` for the type.
+    pub alignment: usize,
+    /// Value of `std::is_trivially_copyable` for the type.
+    pub is_trivially_copyable: bool,
+    /// Value of `std::is_polymorphic` for the type.
+    pub is_polymorphic: bool,
+    /// Value of `std::is_standard_layout` for the type.
+    #[serde(default)]
+    pub is_standard_layout: bool,
+    /// For enums, set if the underlying type is signed.
+    /// The size of the underlying type is the size of the enum.
+    #[serde(default)]
+    pub is_underlying_type_signed: Option<bool>,
 }
 
 impl CppTypeDeclaration {
@@ -388,17 +543,24 @@ impl CppTypeDeclaration {
 }
 
 impl CppTypeDeclarationKind {
-    /// Checks if the type is a class type.
+    /// Checks if the type is a class type. Unions are also class types.
     pub fn is_class(&self) -> bool {
         match self {
-            CppTypeDeclarationKind::Class { .. } => true,
+            CppTypeDeclarationKind::Class { .. } | CppTypeDeclarationKind::Union => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_union(&self) -> bool {
+        match self {
+            CppTypeDeclarationKind::Union => true,
             _ => false,
         }
     }
 
     pub fn is_enum(&self) -> bool {
         match self {
-            CppTypeDeclarationKind::Enum => true,
+            CppTypeDeclarationKind::Enum { .. } => true,
             _ => false,
         }
     }
@@ -418,6 +580,7 @@ pub enum CppItem {
     Function(CppFunction),
     ClassField(CppClassField),
     ClassBase(CppBaseSpecifier),
+    Variable(CppVariable),
 }
 
 impl CppItem {
@@ -467,6 +630,36 @@ impl CppItem {
                     false
                 }
             }
+            Variable(v) => {
+                if let Variable(v2) = &other {
+                    v.is_same(v2)
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    /// Returns true if `other` is the same item (as per `is_same`) but has
+    /// different data, e.g. different return type or default arguments.
+    pub fn conflicts_with(&self, other: &CppItem) -> bool {
+        if !self.is_same(other) {
+            return false;
+        }
+        match (self, other) {
+            (CppItem::Function(f1), CppItem::Function(f2)) => {
+                // argument names and declaration code may legitimately differ
+                f1.return_type != f2.return_type
+                    || f1.member != f2.member
+                    || f1.allows_variadic_arguments != f2.allows_variadic_arguments
+                    || f1.cast != f2.cast
+                    || f1
+                        .arguments
+                        .iter()
+                        .zip(&f2.arguments)
+                        .any(|(a1, a2)| a1.has_default_value != a2.has_default_value)
+            }
+            _ => self != other,
         }
     }
 
@@ -477,6 +670,7 @@ impl CppItem {
             CppItem::EnumValue(data) => &data.path,
             CppItem::Function(data) => &data.path,
             CppItem::ClassField(data) => &data.path,
+            CppItem::Variable(data) => &data.path,
             CppItem::ClassBase(_) => return None,
         };
         Some(path)
@@ -485,10 +679,12 @@ impl CppItem {
     pub fn all_involved_types(&self) -> Vec<CppType> {
         match self {
             CppItem::Type(t) => match t.kind {
-                CppTypeDeclarationKind::Enum => vec![CppType::Enum {
+                CppTypeDeclarationKind::Enum { .. } => vec![CppType::Enum {
                     path: t.path.clone(),
                 }],
-                CppTypeDeclarationKind::Class { .. } => vec![CppType::Class(t.path.clone())],
+                CppTypeDeclarationKind::Class { .. } | CppTypeDeclarationKind::Union => {
+                    vec![CppType::Class(t.path.clone())]
+                }
             },
             CppItem::EnumValue(enum_value) => vec![CppType::Enum {
                 path: enum_value
@@ -507,6 +703,19 @@ impl CppItem {
                 CppType::Class(base.base_class_type.clone()),
                 CppType::Class(base.derived_class_type.clone()),
             ],
+            CppItem::Variable(variable) => {
+                let mut types = vec![variable.variable_type.clone()];
+                if variable.is_static_member {
+                    let class_type = CppType::Class(
+                        variable
+                            .path
+                            .parent()
+                            .expect("static member path must have parent"),
+                    );
+                    types.insert(0, class_type);
+                }
+                types
+            }
         }
     }
 
@@ -531,6 +740,13 @@ impl CppItem {
             None
         }
     }
+    pub fn as_variable_ref(&self) -> Option<&CppVariable> {
+        if let CppItem::Variable(data) = self {
+            Some(data)
+        } else {
+            None
+        }
+    }
     pub fn as_enum_value_ref(&self) -> Option<&CppEnumValue> {
         if let CppItem::EnumValue(data) = self {
             Some(data)
@@ -567,6 +783,7 @@ impl CppItem {
             CppItem::EnumValue(value) => format!("enum value {}", value.path.to_cpp_pseudo_code()),
             CppItem::Function(value) => value.short_text(),
             CppItem::ClassField(value) => value.short_text(),
+            CppItem::Variable(value) => value.short_text(),
             CppItem::ClassBase(_) => format!("{:?}", self),
         }
     }
@@ -579,10 +796,17 @@ impl fmt::Display for CppItem {
                 format!("namespace {}", namespace.path.to_cpp_pseudo_code())
             }
             CppItem::Type(type1) => match type1.kind {
-                CppTypeDeclarationKind::Enum => format!("enum {}", type1.path.to_cpp_pseudo_code()),
+                CppTypeDeclarationKind::Enum { is_scoped } => format!(
+                    "enum {}{}",
+                    if is_scoped { "class " } else { "" },
+                    type1.path.to_cpp_pseudo_code()
+                ),
                 CppTypeDeclarationKind::Class { .. } => {
                     format!("class {}", type1.path.to_cpp_pseudo_code())
                 }
+                CppTypeDeclarationKind::Union => {
+                    format!("union {}", type1.path.to_cpp_pseudo_code())
+                }
             },
             CppItem::Function(method) => method.short_text(),
             CppItem::EnumValue(value) => format!(
@@ -591,6 +815,7 @@ impl fmt::Display for CppItem {
                 value.value
             ),
             CppItem::ClassField(field) => field.short_text(),
+            CppItem::Variable(variable) => variable.short_text(),
             CppItem::ClassBase(class_base) => {
                 let virtual_text = if class_base.is_virtual {
                     "virtual "
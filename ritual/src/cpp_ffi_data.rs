@@ -20,6 +20,49 @@ pub enum CppFieldAccessorType {
     Setter,
 }
 
+/// Calling convention of an FFI function.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum FfiCallingConvention {
+    /// Default C calling convention (cdecl)
+    C,
+    /// `__stdcall`
+    Stdcall,
+    /// `__fastcall`
+    Fastcall,
+    /// `__stdcall` on 32-bit Windows and the C calling convention elsewhere
+    System,
+}
+
+impl Default for FfiCallingConvention {
+    fn default() -> Self {
+        FfiCallingConvention::C
+    }
+}
+
+impl FfiCallingConvention {
+    /// Returns ABI string used in Rust's `extern` blocks.
+    pub fn rust_abi(self) -> &'static str {
+        match self {
+            FfiCallingConvention::C => "C",
+            FfiCallingConvention::Stdcall => "stdcall",
+            FfiCallingConvention::Fastcall => "fastcall",
+            FfiCallingConvention::System => "system",
+        }
+    }
+
+    /// Returns the C++ attribute that should be added to the function declaration.
+    /// Attributes are macros defined in the global header of the wrapper library,
+    /// so that they expand to nothing on architectures without these conventions.
+    pub fn cpp_attribute(self) -> Option<&'static str> {
+        match self {
+            FfiCallingConvention::C => None,
+            FfiCallingConvention::Stdcall => Some("RITUAL_STDCALL"),
+            FfiCallingConvention::Fastcall => Some("RITUAL_FASTCALL"),
+            FfiCallingConvention::System => Some("RITUAL_SYSTEM_CALL"),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub enum CppCast {
     Static {
@@ -29,18 +72,42 @@ pub enum CppCast {
         /// Contains index of the base (e.g. 0 for the first base; always
         /// 0 if the class only has one base).
         base_index: Option<usize>,
+
+        /// If true, the base is a virtual base of the derived class or a base
+        /// of a virtual base. C++ doesn't allow `static_cast` from such a base
+        /// to the derived class, so the unsafe cast is performed
+        /// with `ritual::virtual_base_downcast` instead.
+        #[serde(default)]
+        is_virtual_base: bool,
     },
     Dynamic,
     QObject,
+    /// A checked downcast performed by a function provided by the C++ library
+    /// (see `Config::add_library_cast`). Used instead of `dynamic_cast`
+    /// if RTTI is not available.
+    Library {
+        /// Path of the cast function template
+        function: CppPath,
+    },
 }
 
 impl CppCast {
-    pub fn cpp_method_name(&self) -> &'static str {
-        match *self {
-            CppCast::Static { .. } => "static_cast",
-            CppCast::Dynamic => "dynamic_cast",
-            CppCast::QObject => "qobject_cast",
-        }
+    /// Returns the path of the C++ function implementing the cast
+    /// from a pointer to a pointer of type `to`.
+    pub fn cpp_path(&self, to: &CppType) -> CppPath {
+        let mut path = match self {
+            CppCast::Static {
+                is_unsafe: true,
+                is_virtual_base: true,
+                ..
+            } => CppPath::from_good_str("ritual::virtual_base_downcast"),
+            CppCast::Static { .. } => CppPath::from_good_str("static_cast"),
+            CppCast::Dynamic => CppPath::from_good_str("dynamic_cast"),
+            CppCast::QObject => CppPath::from_good_str("qobject_cast"),
+            CppCast::Library { function } => function.clone(),
+        };
+        path.last_mut().template_arguments = Some(vec![to.clone()]);
+        path
     }
 
     pub fn is_unsafe_static_cast(&self) -> bool {
@@ -69,6 +136,10 @@ pub enum CppFfiFunctionKind {
         /// Type of the accessor
         accessor_type: CppFieldAccessorType,
     },
+    /// Returns address of a global variable or a static data member.
+    VariableAddress,
+    /// Returns value of a static data member initialized in the class definition.
+    VariableValue,
 }
 
 /// Relation between original C++ method's argument value
@@ -162,6 +233,20 @@ pub struct CppFfiFunction {
     pub path: CppPath,
 
     pub kind: CppFfiFunctionKind,
+
+    /// Calling convention of the FFI method
+    #[serde(default)]
+    pub calling_convention: FfiCallingConvention,
+
+    /// If true, the FFI method is always compiled into the C++ wrapper library
+    /// as a non-inline function, even if it's not used by the Rust API.
+    #[serde(default)]
+    pub out_of_line: bool,
+
+    /// If true, the FFI method aborts the process if it's called
+    /// from a thread other than the main thread.
+    #[serde(default)]
+    pub main_thread_only: bool,
 }
 
 impl CppFfiFunction {
@@ -198,6 +283,9 @@ impl CppFfiFunction {
                     false
                 }
             }
+            CppFfiFunctionKind::VariableAddress | CppFfiFunctionKind::VariableValue => {
+                other.kind == self.kind
+            }
         }
     }
 }
@@ -280,6 +368,31 @@ pub struct QtSignalWrapper {
     pub class_path: CppPath,
 }
 
+/// Field of a C++ union or class with a trivial layout
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CppValueField {
+    pub name: String,
+    pub field_type: CppType,
+}
+
+/// Layout of a C++ union or a trivially copyable class that can be represented by
+/// a `#[repr(C)]` Rust union or struct.
+/// The layout is verified by static assertions in C++ code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CppValueLayout {
+    pub path: CppPath,
+    pub is_union: bool,
+    pub fields: Vec<CppValueField>,
+}
+
+/// Offset of a C++ class field that allows to access the field directly
+/// from Rust. The offset is verified by a static assertion in C++ code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CppFieldOffset {
+    pub path: CppPath,
+    pub offset: usize,
+}
+
 /// Information about a Qt slot wrapper with
 /// certain slot arguments
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -299,6 +412,8 @@ pub enum CppFfiItem {
     Function(CppFfiFunction),
     QtSlotWrapper(QtSlotWrapper),
     QtSignalWrapper(QtSignalWrapper),
+    ValueLayout(CppValueLayout),
+    FieldOffset(CppFieldOffset),
 }
 
 impl CppFfiItem {
@@ -334,6 +449,22 @@ impl CppFfiItem {
         }
     }
 
+    pub fn as_value_layout_ref(&self) -> Option<&CppValueLayout> {
+        if let CppFfiItem::ValueLayout(data) = self {
+            Some(data)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_field_offset_ref(&self) -> Option<&CppFieldOffset> {
+        if let CppFfiItem::FieldOffset(data) = self {
+            Some(data)
+        } else {
+            None
+        }
+    }
+
     pub fn is_slot_wrapper(&self) -> bool {
         if let CppFfiItem::QtSlotWrapper(_) = self {
             true
@@ -369,6 +500,14 @@ impl CppFfiItem {
                     .map(CppType::to_cpp_pseudo_code)
                     .join(", ")
             ),
+            CppFfiItem::ValueLayout(layout) => format!(
+                "{} layout of {}",
+                if layout.is_union { "union" } else { "struct" },
+                layout.path.to_cpp_pseudo_code()
+            ),
+            CppFfiItem::FieldOffset(offset) => {
+                format!("offset of {}", offset.path.to_cpp_pseudo_code())
+            }
         }
     }
 
@@ -395,6 +534,20 @@ impl CppFfiItem {
                     false
                 }
             }
+            CppFfiItem::ValueLayout(layout) => {
+                if let CppFfiItem::ValueLayout(other_layout) = other {
+                    layout.is_union == other_layout.is_union && layout.fields == other_layout.fields
+                } else {
+                    false
+                }
+            }
+            CppFfiItem::FieldOffset(offset) => {
+                if let CppFfiItem::FieldOffset(other_offset) = other {
+                    offset.offset == other_offset.offset
+                } else {
+                    false
+                }
+            }
         }
     }
 
@@ -403,19 +556,38 @@ impl CppFfiItem {
             CppFfiItem::Function(f) => &f.path,
             CppFfiItem::QtSlotWrapper(s) => &s.class_path,
             CppFfiItem::QtSignalWrapper(s) => &s.class_path,
+            CppFfiItem::ValueLayout(layout) => &layout.path,
+            CppFfiItem::FieldOffset(offset) => &offset.path,
         }
     }
 
+    /// Returns the key identifying this item across runs of the FFI generator.
+    /// Names of FFI functions are kept stable, so they identify the functions.
+    pub fn spec_key(&self) -> String {
+        let kind = match self {
+            CppFfiItem::Function(_) => "function",
+            CppFfiItem::QtSlotWrapper(_) => "slot_wrapper",
+            CppFfiItem::QtSignalWrapper(_) => "signal_wrapper",
+            CppFfiItem::ValueLayout(_) => "value_layout",
+            CppFfiItem::FieldOffset(_) => "field_offset",
+        };
+        format!("{} {}", kind, self.path().to_cpp_pseudo_code())
+    }
+
     pub fn is_source_item(&self) -> bool {
         match self {
-            CppFfiItem::Function(_) => false,
+            CppFfiItem::Function(_) | CppFfiItem::ValueLayout(_) | CppFfiItem::FieldOffset(_) => {
+                false
+            }
             CppFfiItem::QtSlotWrapper(_) | CppFfiItem::QtSignalWrapper(_) => true,
         }
     }
 
     pub fn source_item_cpp_code(&self, db: &DatabaseClient) -> Result<String> {
         match self {
-            CppFfiItem::Function(_) => bail!("not a source item"),
+            CppFfiItem::Function(_) | CppFfiItem::ValueLayout(_) | CppFfiItem::FieldOffset(_) => {
+                bail!("not a source item")
+            }
             CppFfiItem::QtSlotWrapper(slot_wrapper) => {
                 cpp_code_generator::qt_slot_wrapper(db, slot_wrapper)
             }
@@ -1,24 +1,32 @@
+use crate::config::{AllocationStrategy, Config, FfiFunctionOptions};
 use crate::cpp_data::CppClassField;
 use crate::cpp_data::CppItem;
 use crate::cpp_data::CppPath;
 use crate::cpp_data::CppPathItem;
+use crate::cpp_data::CppTypeDeclaration;
+use crate::cpp_data::CppVariable;
 use crate::cpp_data::CppVisibility;
 use crate::cpp_ffi_data::CppFfiType;
 use crate::cpp_ffi_data::{CppFfiArgumentMeaning, CppToFfiTypeConversion};
 use crate::cpp_ffi_data::{CppFfiFunction, CppFfiFunctionKind, CppFieldAccessorType};
-use crate::cpp_ffi_data::{CppFfiFunctionArgument, CppFfiItem};
+use crate::cpp_ffi_data::{CppFfiFunctionArgument, CppFfiItem, FfiCallingConvention};
+use crate::cpp_ffi_data::{CppFieldOffset, CppValueField, CppValueLayout};
 use crate::cpp_function::ReturnValueAllocationPlace;
 use crate::cpp_function::{CppFunction, CppFunctionArgument, CppFunctionKind};
+use crate::cpp_tree_shaker;
 use crate::cpp_type::CppPointerLikeTypeKind;
 use crate::cpp_type::CppType;
 use crate::cpp_type::CppTypeRole;
 use crate::cpp_type::{is_qflags, CppFunctionPointerType};
-use crate::database::DbItem;
+use crate::database::{DatabaseClient, DbItem};
 use crate::processor::ProcessorData;
+use flate2::Crc;
 use itertools::Itertools;
-use log::{debug, trace};
+use log::{debug, trace, warn};
 use ritual_common::errors::{bail, Result, ResultExt};
-use std::collections::HashSet;
+use ritual_common::target::LibraryTarget;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Instant;
 
 /// Converts this C++ type to its adaptation for FFI interface,
 /// removing all features not supported by C ABI
@@ -62,6 +70,18 @@ pub fn ffi_type(original_type: &CppType, role: CppTypeRole) -> Result<CppFfiType
                 }
                 CppToFfiTypeConversion::NoChange
             }
+            CppType::Array { element_type, .. } => {
+                if element_type.is_function_pointer() {
+                    bail!("arrays of function pointers are not supported");
+                }
+                if role == CppTypeRole::ReturnType {
+                    bail!("arrays can't be returned by value");
+                }
+                // the array is passed as a pointer to the array
+                CppToFfiTypeConversion::ValueToPointer {
+                    is_ffi_const: false,
+                }
+            }
             CppType::Class(path) => {
                 if is_qflags(&path) {
                     CppToFfiTypeConversion::QFlagsToInt
@@ -99,76 +119,261 @@ pub fn ffi_type(original_type: &CppType, role: CppTypeRole) -> Result<CppFfiType
     Ok(inner().with_context(|_| format!("Can't express type to FFI: {:?}", original_type))?)
 }
 
+/// Assigns names to FFI functions.
+///
+/// A function receives `<prefix>_<caption>` name if it's not taken yet.
+/// If multiple functions have the same caption (e.g. overloads), the first processed
+/// function receives the plain name and the name of each following function
+/// is suffixed with a hash of its signature. Which overload gets the plain name
+/// depends on the order of processing, so the assigned names are stored
+/// in the database and reused when the FFI items are regenerated. This way
+/// names of exported symbols don't change between runs, and adding a new overload
+/// doesn't rename the existing ones.
 pub struct FfiNameProvider {
+    /// Names that are taken or reserved for other signatures.
     names: HashSet<String>,
     prefix: String,
+    /// Names by signature key, including names loaded from the database.
+    assigned: BTreeMap<String, String>,
+    /// Keys of names assigned in this run.
+    new_keys: Vec<String>,
 }
 
 impl FfiNameProvider {
     pub fn new(data: &ProcessorData<'_>) -> Self {
         let prefix = format!("ctr_{}_ffi", &data.config.crate_properties().name());
+        let assigned: BTreeMap<_, _> = data
+            .db
+            .ffi_names()
+            .map(|(key, name)| (key.to_string(), name.to_string()))
+            .collect();
         let names = data
             .db
             .ffi_items()
             .map(|f| f.item.path().to_cpp_code().unwrap())
+            .chain(assigned.values().cloned())
             .collect();
 
-        FfiNameProvider { prefix, names }
+        FfiNameProvider {
+            prefix,
+            names,
+            assigned,
+            new_keys: Vec::new(),
+        }
     }
 
     pub fn testing() -> Self {
         FfiNameProvider {
             names: HashSet::new(),
             prefix: String::new(),
+            assigned: BTreeMap::new(),
+            new_keys: Vec::new(),
         }
     }
 
-    pub fn create_path(&mut self, name: &str) -> CppPath {
-        let mut num: Option<u32> = None;
-        let full_name = loop {
-            let full_name = format!(
-                "{}_{}{}",
-                self.prefix,
-                name,
-                num.map_or(String::new(), |num| num.to_string())
-            );
-            if !self.names.contains(&full_name) {
-                break full_name;
-            }
-            num = Some(num.map_or(1, |num| num + 1));
+    /// Returns the path of the FFI function with caption `name` and signature `key`.
+    pub fn create_path(&mut self, name: &str, key: &str) -> CppPath {
+        let full_name = if let Some(full_name) = self.assigned.get(key) {
+            full_name.clone()
+        } else {
+            let base_name = format!("{}_{}", self.prefix, name);
+            let full_name = if self.names.contains(&base_name) {
+                let mut crc = Crc::new();
+                crc.update(key.as_bytes());
+                let hashed_name = format!("{}_{:08x}", base_name, crc.sum());
+                let mut num: Option<u32> = None;
+                loop {
+                    let full_name = format!(
+                        "{}{}",
+                        hashed_name,
+                        num.map_or(String::new(), |num| num.to_string())
+                    );
+                    if !self.names.contains(&full_name) {
+                        break full_name;
+                    }
+                    num = Some(num.map_or(1, |num| num + 1));
+                }
+            } else {
+                base_name
+            };
+            self.assigned.insert(key.to_string(), full_name.clone());
+            self.new_keys.push(key.to_string());
+            full_name
         };
         let item = CppPathItem::from_good_str(&full_name);
         self.names.insert(full_name);
         CppPath::from_item(item)
     }
+
+    /// Returns signature keys and names assigned in this run.
+    pub fn new_names(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.new_keys
+            .iter()
+            .map(move |key| (key.as_str(), self.assigned[key].as_str()))
+    }
+}
+
+/// Returns classes that have virtual bases (directly or through their bases).
+/// Offsets of the subobjects of such classes are only known at runtime,
+/// so their layout is never exposed to Rust.
+fn classes_with_virtual_bases(db: &DatabaseClient) -> HashSet<CppPath> {
+    let bases = db
+        .all_cpp_items()
+        .filter_map(|item| item.item.as_base_ref())
+        .collect_vec();
+    let mut classes = bases
+        .iter()
+        .filter(|base| base.is_virtual)
+        .map(|base| base.derived_class_type.clone())
+        .collect::<HashSet<_>>();
+    loop {
+        let new_classes = bases
+            .iter()
+            .filter(|base| {
+                classes.contains(&base.base_class_type)
+                    && !classes.contains(&base.derived_class_type)
+            })
+            .map(|base| base.derived_class_type.clone())
+            .collect_vec();
+        if new_classes.is_empty() {
+            break;
+        }
+        classes.extend(new_classes);
+    }
+    classes
 }
 
 /// Runs the FFI generator
 pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
-    // movable types are not supported for now
-    let movable_types = Vec::new();
+    let virtual_base_classes = classes_with_virtual_bases(&data.db);
+    let plain_struct_layouts = plain_struct_layouts(data, &virtual_base_classes)?;
+    let env = data.config.current_library_target()?;
+    // `offsetof` is only valid for standard-layout classes
+    let standard_layout_classes = data
+        .db
+        .all_cpp_type_properties()
+        .iter()
+        .filter(|properties| properties.env == env && properties.is_standard_layout)
+        .map(|properties| properties.path.clone())
+        .collect::<HashSet<_>>();
+    let mut allocation_strategies = allocation_strategies(data)?;
+    // values of plain structs are always constructed in place
+    let dependency_plain_structs = data
+        .db
+        .all_ffi_items()
+        .filter(|item| item.id.crate_name() != data.db.crate_name())
+        .filter_map(|item| item.item.as_value_layout_ref())
+        .filter(|layout| !layout.is_union)
+        .map(|layout| layout.path.clone())
+        .collect_vec();
+    for path in plain_struct_layouts
+        .keys()
+        .cloned()
+        .chain(dependency_plain_structs)
+    {
+        allocation_strategies.insert(path, AllocationStrategy::InPlace);
+    }
 
     let mut name_provider = FfiNameProvider::new(data);
 
     let all_cpp_item_ids = data.db.cpp_item_ids().collect_vec();
+    let required_item_ids =
+        cpp_tree_shaker::required_item_ids(&data.db, data.config.entry_points());
 
     for cpp_item_id in all_cpp_item_ids {
         let item = data.db.cpp_item(&cpp_item_id)?;
 
         let source_ffi_item = data.db.source_ffi_item(&cpp_item_id)?;
 
+        if item
+            .item
+            .path()
+            .map_or(false, |path| data.db.is_blocked(path))
+        {
+            trace!("skipping blocked item: {}", item.item);
+            continue;
+        }
+        if required_item_ids
+            .as_ref()
+            .map_or(false, |ids| !ids.contains(&cpp_item_id))
+        {
+            trace!("skipping item not required by entry points: {}", item.item);
+            continue;
+        }
+        if let Some(reason) = item
+            .item
+            .path()
+            .and_then(|path| data.db.known_crash_reason(path))
+        {
+            trace!("skipping item known to crash: {} ({})", item.item, reason);
+            continue;
+        }
         if let Err(err) = check_preconditions(&item.item, source_ffi_item) {
             trace!("skipping {}: {}", item.item, err);
             continue;
         }
+        let is_plain_struct = |path: &CppPath| plain_struct_layouts.contains_key(path);
+        let is_plain_struct_member = match &item.item {
+            CppItem::ClassField(field) => {
+                field.path.parent().map_or(false, |p| is_plain_struct(&p))
+            }
+            CppItem::Function(function) => {
+                function.is_destructor()
+                    && function.class_path().map_or(false, |p| is_plain_struct(&p))
+            }
+            _ => false,
+        };
+        if is_plain_struct_member {
+            // fields of plain structs are accessed directly,
+            // and their destructors are trivial
+            trace!("skipping member of plain struct: {}", item.item);
+            continue;
+        }
+        let options = match (data.config.ffi_function_options_hook(), item.item.path()) {
+            (Some(hook), Some(path)) => hook(path)?,
+            _ => FfiFunctionOptions::default(),
+        };
+        let started_time = Instant::now();
         let result = match &item.item {
             CppItem::Function(method) => {
-                generate_ffi_methods_for_method(method, &movable_types, &mut name_provider)
+                generate_ffi_methods_for_method(method, &allocation_strategies, &mut name_provider)
                     .map(|v| v.into_iter().collect_vec())
             }
             CppItem::ClassField(field) => {
-                generate_field_accessors(field, &movable_types, &mut name_provider)
-                    .map(|v| v.into_iter().collect_vec())
+                generate_field_accessors(field, &allocation_strategies, &mut name_provider).map(
+                    |mut v| {
+                        let is_standard_layout = field
+                            .path
+                            .parent()
+                            .map_or(false, |class| standard_layout_classes.contains(&class));
+                        if options.inline_field_access && is_standard_layout {
+                            v.extend(field_offset(field));
+                        }
+                        v
+                    },
+                )
+            }
+            CppItem::Variable(variable) => {
+                let kind = if variable.has_in_class_initializer {
+                    NewFfiFunctionKind::VariableValue {
+                        variable: variable.clone(),
+                    }
+                } else {
+                    NewFfiFunctionKind::VariableAddress {
+                        variable: variable.clone(),
+                    }
+                };
+                to_ffi_method(kind, &allocation_strategies, &mut name_provider)
+                    .map(|function| vec![CppFfiItem::Function(function)])
+            }
+            CppItem::Type(type1) if type1.kind.is_union() => {
+                value_layout(&type1.path, true, &data.db)
+                    .map(|layout| vec![CppFfiItem::ValueLayout(layout)])
+            }
+            CppItem::Type(type1) if plain_struct_layouts.contains_key(&type1.path) => {
+                Ok(vec![CppFfiItem::ValueLayout(
+                    plain_struct_layouts[&type1.path].clone(),
+                )])
             }
             CppItem::ClassBase(_)
             | CppItem::Type(_)
@@ -182,24 +387,155 @@ pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
         match result {
             Err(error) => {
                 debug!(
-                    "failed to add FFI item for {} {}: {}",
-                    item.id, item.item, error
+                    "failed to add FFI item {}: {}",
+                    data.db.item_context(&item.id),
+                    error
                 );
             }
             Ok(r) => {
                 let source_id = item.id;
-                for new_item in r {
+                for mut new_item in r {
+                    if data.db.is_ffi_item_rejected(&new_item.spec_key()) {
+                        trace!(
+                            "skipping FFI item rejected in FFI spec: {}",
+                            new_item.spec_key()
+                        );
+                        continue;
+                    }
+                    if let CppFfiItem::Function(function) = &mut new_item {
+                        function.calling_convention = options.calling_convention;
+                        function.out_of_line = options.out_of_line;
+                        function.main_thread_only = options.main_thread_only;
+                    }
                     data.db.add_ffi_item(Some(source_id.clone()), new_item)?;
                 }
             }
         }
+        data.record_item_time(cpp_item_id, None, started_time.elapsed());
+    }
+    for (key, name) in name_provider.new_names() {
+        data.db.set_ffi_name(key, name);
+    }
+    Ok(())
+}
+
+/// Checks that objects of the class `type1` can be constructed in place
+/// (see `AllocationStrategy::InPlace`). Rust can only move values that are
+/// trivially copyable, as reported by the compiler (or guessed by the parser
+/// if the type properties are not available), and not pinned
+/// (see `Config::set_pinned_types_hook`).
+pub fn check_in_place_allocation(
+    type1: &CppTypeDeclaration,
+    db: &DatabaseClient,
+    config: &Config,
+) -> Result<()> {
+    if config.is_pinned(type1)? {
+        bail!("class can't be moved");
+    }
+    let env = config.current_library_target()?;
+    let is_trivially_copyable = db
+        .cpp_type_properties(&type1.path, &env)
+        .map_or(type1.is_trivially_copyable, |p| p.is_trivially_copyable);
+    if !is_trivially_copyable {
+        bail!("class is not trivially copyable");
     }
     Ok(())
 }
 
+/// Returns true if the class `type1` can be wrapped as a plain Rust struct on `env`:
+/// it must be trivially copyable, and its layout must be mirrored by a `#[repr(C)]`
+/// struct, so it must be a standard-layout class without virtual functions.
+/// The properties reported by the compiler (see `cpp_parser::probe_type_properties`)
+/// are preferred over the parser's guess.
+pub fn is_plain_struct_candidate(
+    type1: &CppTypeDeclaration,
+    db: &DatabaseClient,
+    env: &LibraryTarget,
+) -> bool {
+    match db.cpp_type_properties(&type1.path, env) {
+        Some(properties) => {
+            properties.is_trivially_copyable
+                && properties.is_standard_layout
+                && !properties.is_polymorphic
+        }
+        None => type1.is_trivially_copyable,
+    }
+}
+
+/// Returns allocation strategies of all classes that don't use
+/// the default strategy (see `Config::set_allocation_strategy_hook`).
+fn allocation_strategies(data: &ProcessorData<'_>) -> Result<HashMap<CppPath, AllocationStrategy>> {
+    let mut strategies = HashMap::new();
+    for item in data.db.all_cpp_items() {
+        if let CppItem::Type(type1) = &item.item {
+            if !type1.kind.is_class() {
+                continue;
+            }
+            let strategy = data.config.allocation_strategy(&type1.path)?;
+            if strategy == AllocationStrategy::InPlace {
+                if let Err(err) = check_in_place_allocation(type1, &data.db, data.config) {
+                    warn!(
+                        "{} is allocated on the heap instead of in place: {}",
+                        type1.path.to_cpp_pseudo_code(),
+                        err
+                    );
+                    continue;
+                }
+            }
+            if strategy != AllocationStrategy::default() {
+                strategies.insert(type1.path.clone(), strategy);
+            }
+        }
+    }
+    Ok(strategies)
+}
+
+/// Returns layouts of the classes that should be wrapped as plain Rust structs
+/// (see `Config::set_plain_struct_hook`).
+fn plain_struct_layouts(
+    data: &ProcessorData<'_>,
+    virtual_base_classes: &HashSet<CppPath>,
+) -> Result<HashMap<CppPath, CppValueLayout>> {
+    let mut layouts = HashMap::new();
+    let hook = match data.config.plain_struct_hook() {
+        Some(hook) => hook,
+        None => return Ok(layouts),
+    };
+    let env = data.config.current_library_target()?;
+    for item in data.db.cpp_items() {
+        let type1 = match &item.item {
+            CppItem::Type(type1) if type1.kind.is_class() => type1,
+            _ => continue,
+        };
+        if !is_plain_struct_candidate(type1, &data.db, &env)
+            || virtual_base_classes.contains(&type1.path)
+        {
+            continue;
+        }
+        if CppType::Class(type1.path.clone()).is_or_contains_template_parameter()
+            || !hook(&type1.path)?
+        {
+            continue;
+        }
+        match value_layout(&type1.path, false, &data.db) {
+            Ok(layout) => {
+                layouts.insert(type1.path.clone(), layout);
+            }
+            Err(err) => {
+                debug!(
+                    "can't wrap {} as a plain struct: {}",
+                    type1.path.to_cpp_pseudo_code(),
+                    err
+                );
+            }
+        }
+    }
+    Ok(layouts)
+}
+
 fn generate_ffi_methods_for_method(
     method: &CppFunction,
-    movable_types: &[CppPath],
+    allocation_strategies: &HashMap<CppPath, AllocationStrategy>,
     name_provider: &mut FfiNameProvider,
 ) -> Result<Vec<CppFfiItem>> {
     let mut methods = Vec::new();
@@ -207,7 +543,7 @@ fn generate_ffi_methods_for_method(
         NewFfiFunctionKind::Function {
             cpp_function: method.clone(),
         },
-        movable_types,
+        allocation_strategies,
         name_provider,
     )?));
 
@@ -222,6 +558,12 @@ pub enum NewFfiFunctionKind {
         accessor_type: CppFieldAccessorType,
         field: CppClassField,
     },
+    VariableAddress {
+        variable: CppVariable,
+    },
+    VariableValue {
+        variable: CppVariable,
+    },
 }
 
 /// Creates FFI function signature for this function:
@@ -231,7 +573,7 @@ pub enum NewFfiFunctionKind {
 ///   the return value is stack-allocated.
 pub fn to_ffi_method(
     kind: NewFfiFunctionKind,
-    movable_types: &[CppPath],
+    allocation_strategies: &HashMap<CppPath, AllocationStrategy>,
     name_provider: &mut FfiNameProvider,
 ) -> Result<CppFfiFunction> {
     let ascii_caption = match &kind {
@@ -249,19 +591,51 @@ pub fn to_ffi_method(
                 CppFieldAccessorType::Setter => format!("set_{}", field_caption),
             }
         }
+        NewFfiFunctionKind::VariableAddress { variable }
+        | NewFfiFunctionKind::VariableValue { variable } => variable.path.ascii_caption(),
+    };
+
+    let name_key = match &kind {
+        NewFfiFunctionKind::Function { cpp_function } => format!(
+            "{}({}){}",
+            cpp_function.path.to_cpp_pseudo_code(),
+            cpp_function
+                .arguments
+                .iter()
+                .map(|arg| arg.argument_type.to_cpp_pseudo_code())
+                .join(", "),
+            if cpp_function.member.as_ref().map_or(false, |m| m.is_const) {
+                " const"
+            } else {
+                ""
+            }
+        ),
+        NewFfiFunctionKind::FieldAccessor {
+            field,
+            accessor_type,
+        } => format!("{} {:?}", field.path.to_cpp_pseudo_code(), accessor_type),
+        NewFfiFunctionKind::VariableAddress { variable } => {
+            format!("&{}", variable.path.to_cpp_pseudo_code())
+        }
+        NewFfiFunctionKind::VariableValue { variable } => variable.path.to_cpp_pseudo_code(),
     };
 
     let mut r = CppFfiFunction {
         arguments: Vec::new(),
         return_type: CppFfiType::void(),
-        path: name_provider.create_path(&ascii_caption),
+        path: name_provider.create_path(&ascii_caption, &name_key),
         allocation_place: ReturnValueAllocationPlace::NotApplicable,
         kind: match kind {
             NewFfiFunctionKind::Function { .. } => CppFfiFunctionKind::Function,
             NewFfiFunctionKind::FieldAccessor { accessor_type, .. } => {
                 CppFfiFunctionKind::FieldAccessor { accessor_type }
             }
+            NewFfiFunctionKind::VariableAddress { .. } => CppFfiFunctionKind::VariableAddress,
+            NewFfiFunctionKind::VariableValue { .. } => CppFfiFunctionKind::VariableValue,
         },
+        calling_convention: FfiCallingConvention::default(),
+        out_of_line: false,
+        main_thread_only: false,
     };
 
     let this_arg_type = match &kind {
@@ -276,16 +650,15 @@ pub fn to_ffi_method(
             field,
             accessor_type,
         } => {
-            if field.is_static {
-                None
-            } else {
-                let class_type = CppType::Class(field.path.parent()?);
-                let is_const = match *accessor_type {
-                    CppFieldAccessorType::CopyGetter | CppFieldAccessorType::ConstRefGetter => true,
-                    CppFieldAccessorType::MutRefGetter | CppFieldAccessorType::Setter => false,
-                };
-                Some(CppType::new_pointer(is_const, class_type))
-            }
+            let class_type = CppType::Class(field.path.parent()?);
+            let is_const = match *accessor_type {
+                CppFieldAccessorType::CopyGetter | CppFieldAccessorType::ConstRefGetter => true,
+                CppFieldAccessorType::MutRefGetter | CppFieldAccessorType::Setter => false,
+            };
+            Some(CppType::new_pointer(is_const, class_type))
+        }
+        NewFfiFunctionKind::VariableAddress { .. } | NewFfiFunctionKind::VariableValue { .. } => {
+            None
         }
     };
 
@@ -307,10 +680,14 @@ pub fn to_ffi_method(
                 // destructor doesn't have a return type that needs special handling,
                 // but its `allocation_place` must match `allocation_place` of the type's constructor
                 let class_type = &cpp_function.class_path().unwrap();
-                r.allocation_place = if movable_types.iter().any(|t| t == class_type) {
-                    ReturnValueAllocationPlace::Stack
-                } else {
-                    ReturnValueAllocationPlace::Heap
+                r.allocation_place = match allocation_strategies.get(class_type) {
+                    Some(AllocationStrategy::InPlace) => ReturnValueAllocationPlace::Stack,
+                    Some(AllocationStrategy::Factory { destroy, .. }) => {
+                        ReturnValueAllocationPlace::Factory {
+                            function: destroy.clone(),
+                        }
+                    }
+                    Some(AllocationStrategy::Heap) | None => ReturnValueAllocationPlace::Heap,
                 };
             }
             cpp_function.arguments.clone()
@@ -330,6 +707,9 @@ pub fn to_ffi_method(
                 Vec::new()
             }
         }
+        NewFfiFunctionKind::VariableAddress { .. } | NewFfiFunctionKind::VariableValue { .. } => {
+            Vec::new()
+        }
     };
 
     for (index, arg) in normal_args.iter().enumerate() {
@@ -361,23 +741,54 @@ pub fn to_ffi_method(
             }
             CppFieldAccessorType::Setter => CppType::Void,
         },
+        NewFfiFunctionKind::VariableAddress { variable } => match &variable.variable_type {
+            // a reference can't be pointed to, so the address of the referenced object is returned
+            CppType::PointerLike {
+                kind,
+                is_const,
+                target,
+            } if *kind != CppPointerLikeTypeKind::Pointer => {
+                CppType::new_pointer(*is_const, (**target).clone())
+            }
+            other => CppType::new_pointer(variable.is_const, other.clone()),
+        },
+        NewFfiFunctionKind::VariableValue { variable } => {
+            // the value is copied because taking the address of the member
+            // requires its out-of-class definition before C++17
+            if variable.variable_type.is_array() || !is_trivial_field_type(&variable.variable_type)
+            {
+                bail!(
+                    "in-class initialized static data member of non-trivial type \
+                     can't be accessed: {}",
+                    variable.variable_type.to_cpp_pseudo_code()
+                );
+            }
+            variable.variable_type.clone()
+        }
     };
     let real_return_type_ffi = ffi_type(&real_return_type, CppTypeRole::ReturnType)?;
     match &real_return_type {
         // QFlags is converted to uint in FFI
-        CppType::Class(path) if !is_qflags(path) => {
-            if movable_types.iter().any(|t| t == path) {
+        CppType::Class(path) if !is_qflags(path) => match allocation_strategies.get(path) {
+            Some(AllocationStrategy::InPlace) => {
                 r.arguments.push(CppFfiFunctionArgument {
                     name: "output".to_string(),
                     argument_type: real_return_type_ffi,
                     meaning: CppFfiArgumentMeaning::ReturnValue,
                 });
                 r.allocation_place = ReturnValueAllocationPlace::Stack;
-            } else {
+            }
+            Some(AllocationStrategy::Factory { create, .. }) => {
+                r.return_type = real_return_type_ffi;
+                r.allocation_place = ReturnValueAllocationPlace::Factory {
+                    function: create.clone(),
+                };
+            }
+            Some(AllocationStrategy::Heap) | None => {
                 r.return_type = real_return_type_ffi;
                 r.allocation_place = ReturnValueAllocationPlace::Heap;
             }
-        }
+        },
         _ => {
             r.return_type = real_return_type_ffi;
         }
@@ -389,7 +800,7 @@ pub fn to_ffi_method(
 /// Adds fictional getter and setter methods for each known public field of each class.
 fn generate_field_accessors(
     field: &CppClassField,
-    movable_types: &[CppPath],
+    allocation_strategies: &HashMap<CppPath, AllocationStrategy>,
     name_provider: &mut FfiNameProvider,
 ) -> Result<Vec<CppFfiItem>> {
     let mut new_methods = Vec::new();
@@ -398,25 +809,93 @@ fn generate_field_accessors(
             field: field.clone(),
             accessor_type,
         };
-        let ffi_function = to_ffi_method(kind, movable_types, name_provider)?;
+        let ffi_function = to_ffi_method(kind, allocation_strategies, name_provider)?;
         Ok(CppFfiItem::Function(ffi_function))
     };
 
     if field.visibility == CppVisibility::Public {
         // Classes may be non-copyable, so copy getters may not be possible for them,
         // so we generate reference getters instead.
-        if field.field_type.is_class() {
+        // Arrays can't be returned or assigned, so they are only accessible
+        // through reference getters.
+        if field.field_type.is_class() || field.field_type.is_array() {
             new_methods.push(create_method(CppFieldAccessorType::ConstRefGetter)?);
             new_methods.push(create_method(CppFieldAccessorType::MutRefGetter)?);
         } else {
             new_methods.push(create_method(CppFieldAccessorType::CopyGetter)?);
         }
-        new_methods.push(create_method(CppFieldAccessorType::Setter)?);
+        if !field.field_type.is_array() {
+            new_methods.push(create_method(CppFieldAccessorType::Setter)?);
+        }
     }
 
     Ok(new_methods)
 }
 
+/// Returns the offset item for a field that can be accessed directly from Rust
+/// (see `FfiFunctionOptions::inline_field_access`).
+fn field_offset(field: &CppClassField) -> Option<CppFfiItem> {
+    if field.visibility != CppVisibility::Public || !is_trivial_field_type(&field.field_type) {
+        return None;
+    }
+    Some(CppFfiItem::FieldOffset(CppFieldOffset {
+        path: field.path.clone(),
+        offset: field.offset?,
+    }))
+}
+
+/// Returns true if values of `type1` can be stored in a `#[repr(C)]` Rust union or struct.
+fn is_trivial_field_type(type1: &CppType) -> bool {
+    match type1 {
+        CppType::BuiltInNumeric(_)
+        | CppType::SpecificNumeric(_)
+        | CppType::PointerSizedInteger { .. }
+        | CppType::Enum { .. }
+        | CppType::FunctionPointer(_) => true,
+        CppType::PointerLike { kind, .. } => *kind == CppPointerLikeTypeKind::Pointer,
+        CppType::Array { element_type, .. } => is_trivial_field_type(element_type),
+        CppType::Void | CppType::Class(_) | CppType::TemplateParameter(_) => false,
+    }
+}
+
+/// Returns the layout of a union or a class if all its fields are public
+/// and have trivial types. Other unions are only accessible through field accessors.
+fn value_layout(path: &CppPath, is_union: bool, db: &DatabaseClient) -> Result<CppValueLayout> {
+    let kind_name = if is_union { "union" } else { "class" };
+    let mut fields = Vec::new();
+    for item in db.cpp_items() {
+        let field = match item.item {
+            CppItem::ClassField(field) => field,
+            _ => continue,
+        };
+        if field.path.parent().ok().as_ref() != Some(path) {
+            continue;
+        }
+        if field.visibility != CppVisibility::Public {
+            bail!("{} has non-public fields", kind_name);
+        }
+        if !is_trivial_field_type(&field.field_type) {
+            bail!(
+                "{} field type is not trivial: {}",
+                kind_name,
+                field.field_type.to_cpp_pseudo_code()
+            );
+        }
+        fields.push(CppValueField {
+            name: field.path.last().name.clone(),
+            field_type: field.field_type.clone(),
+        });
+    }
+    if fields.is_empty() {
+        bail!("{} has no known fields", kind_name);
+    }
+    Ok(CppValueLayout {
+        path: path.clone(),
+        is_union,
+        fields,
+    })
+}
+
 fn check_preconditions(item: &CppItem, source_ffi_item: Option<DbItem<&CppFfiItem>>) -> Result<()> {
     match item {
         CppItem::Function(function) => {
@@ -452,6 +931,11 @@ fn check_preconditions(item: &CppItem, source_ffi_item: Option<DbItem<&CppFfiIte
                 bail!("field is protected");
             }
         }
+        CppItem::Variable(variable) => {
+            if variable.visibility != CppVisibility::Public {
+                bail!("variable is not public");
+            }
+        }
         _ => {}
     }
     if item
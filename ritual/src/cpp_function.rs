@@ -1,7 +1,7 @@
 //! Types for handling information about C++ methods.
 
 use crate::cpp_data::{CppPath, CppPathItem, CppVisibility};
-use crate::cpp_ffi_data::CppCast;
+use crate::cpp_ffi_data::{CppCast, CppFieldAccessorType};
 pub use crate::cpp_operator::{CppOperator, CppOperatorInfo};
 use crate::cpp_type::{CppPointerLikeTypeKind, CppType};
 use crate::rust_info::RustQtReceiverType;
@@ -71,6 +71,23 @@ pub struct CppFunctionMemberData {
     pub is_signal: bool,
     /// True if the method is a Qt slot
     pub is_slot: bool,
+    /// True if this is a constructor declared with `explicit` specifier
+    #[serde(default)]
+    pub is_explicit: bool,
+    /// Set if the method is defined in the class body and only reads
+    /// or assigns a field of the class, like a field accessor would
+    #[serde(default)]
+    pub trivial_field_access: Option<CppTrivialFieldAccess>,
+}
+
+/// Field accessed by a trivial getter or setter method
+/// (e.g. `int x() const { return m_x; }`)
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
+pub struct CppTrivialFieldAccess {
+    /// Name of the field
+    pub field_name: String,
+    /// How the method accesses the field
+    pub accessor_type: CppFieldAccessorType,
 }
 
 impl CppFunctionMemberData {
@@ -123,8 +140,27 @@ pub struct CppFunction {
     pub declaration_code: Option<String>,
 }
 
+/// Member function of a class template with a signature that can't be parsed
+/// without knowing the template arguments (e.g. it uses `typename Traits<T>::type`
+/// or `std::enable_if`). The signature is resolved by clang for each concrete
+/// instantiation of the class (see `cpp_parser::resolve_dependent_functions`).
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
+pub struct CppDependentFunction {
+    /// Identifier of the function, including the class template
+    pub path: CppPath,
+    /// True if the function is a function template itself.
+    /// Only function templates with default template arguments can be resolved.
+    pub is_template: bool,
+    pub is_const: bool,
+    pub is_static: bool,
+    /// Names of the arguments
+    pub argument_names: Vec<String>,
+    /// Error that prevented parsing the original declaration
+    pub reason: String,
+}
+
 /// Chosen type allocation place for the method
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
 pub enum ReturnValueAllocationPlace {
     /// The method returns a class object by value (or is a constructor), and
     /// it's translated to "output" FFI argument and placement new
@@ -132,6 +168,10 @@ pub enum ReturnValueAllocationPlace {
     /// The method returns a class object by value (or is a constructor), and
     /// it's translated to pointer FFI return type and plain new
     Heap,
+    /// The method is a constructor or a destructor of a class with
+    /// `AllocationStrategy::Factory`, and it's translated to a call of
+    /// the library's create or destroy `function`
+    Factory { function: CppPath },
     /// The method does not return a class object by value, so
     /// the direct equivalent of the value is used in FFI.
     NotApplicable,
@@ -186,10 +226,12 @@ impl CppFunction {
             (None, None) => true,
             _ => false,
         };
+        // C++ functions can't be overloaded by return type, so declarations
+        // that only differ in return type are conflicting declarations
+        // of the same function
         self.path == other.path
             && member_is_same
             && self.operator == other.operator
-            && self.return_type == other.return_type
             && self.argument_types_equal(other)
     }
 
@@ -341,6 +383,34 @@ impl CppFunction {
         arg == self.arguments[0].argument_type
     }
 
+    /// Returns true if this method is a non-explicit constructor taking a single
+    /// argument of another type, i.e. it can be used for implicit conversions.
+    pub fn is_converting_constructor(&self) -> bool {
+        if !self.is_constructor() || self.arguments.len() != 1 {
+            return false;
+        }
+        if self.member.as_ref().map_or(true, |info| info.is_explicit) {
+            return false;
+        }
+        let class_type = match self.class_path() {
+            Ok(path) => CppType::Class(path),
+            Err(_) => return false,
+        };
+        match &self.arguments[0].argument_type {
+            CppType::PointerLike {
+                kind: CppPointerLikeTypeKind::Reference,
+                target,
+                ..
+            }
+            | CppType::PointerLike {
+                kind: CppPointerLikeTypeKind::RValueReference,
+                target,
+                ..
+            } => **target != class_type,
+            other => *other != class_type,
+        }
+    }
+
     /// Returns true if this method is a destructor.
     pub fn is_destructor(&self) -> bool {
         match &self.member {
@@ -37,6 +37,8 @@ pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
                     visibility: CppVisibility::Public,
                     is_signal: false,
                     is_slot: false,
+                    is_explicit: false,
+                    trivial_field_access: None,
                     kind: CppFunctionKind::Destructor,
                 }),
                 operator: None,
@@ -61,6 +63,8 @@ pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
                     visibility: CppVisibility::Public,
                     is_signal: false,
                     is_slot: false,
+                    is_explicit: false,
+                    trivial_field_access: None,
                     kind: CppFunctionKind::Constructor,
                 }),
                 operator: None,
@@ -91,6 +95,8 @@ pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
                     visibility: CppVisibility::Public,
                     is_signal: false,
                     is_slot: false,
+                    is_explicit: false,
+                    trivial_field_access: None,
                     kind: CppFunctionKind::Constructor,
                 }),
                 operator: None,
@@ -115,6 +121,8 @@ pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
                     visibility: CppVisibility::Public,
                     is_signal: false,
                     is_slot: false,
+                    is_explicit: false,
+                    trivial_field_access: None,
                     kind: CppFunctionKind::Regular,
                 }),
                 operator: Some(CppOperator::Assignment),
@@ -1,36 +1,50 @@
+use crate::clang_cache::ClangCache;
+use crate::clang_compat::{self, init_clang, ClangCompat};
 use crate::config::Config;
 use crate::cpp_code_generator::{all_include_directives, write_include_directives};
 use crate::cpp_data::{
     CppBaseSpecifier, CppClassField, CppEnumValue, CppItem, CppNamespace, CppOriginLocation,
-    CppPath, CppPathItem, CppTypeDeclaration, CppTypeDeclarationKind, CppVisibility,
+    CppPath, CppPathItem, CppSpecializationKind, CppTypeDeclaration, CppTypeDeclarationKind,
+    CppTypeProperties, CppVariable, CppVisibility,
 };
+use crate::cpp_ffi_data::CppFieldAccessorType;
 use crate::cpp_function::{
-    CppFunction, CppFunctionArgument, CppFunctionKind, CppFunctionMemberData,
+    CppDependentFunction, CppFunction, CppFunctionArgument, CppFunctionKind, CppFunctionMemberData,
+    CppTrivialFieldAccess,
 };
 use crate::cpp_operator::CppOperator;
+use crate::cpp_template_instantiator::{is_concrete_instantiation, is_same_template};
 use crate::cpp_type::{
     CppBuiltInNumericType, CppFunctionPointerType, CppPointerLikeTypeKind, CppSpecificNumericType,
     CppSpecificNumericTypeKind, CppTemplateParameter, CppType,
 };
 use crate::database::ItemId;
-use crate::processor::ProcessorData;
+use crate::include_graph::{self, IncludeGraph};
+use crate::processor::{ProcessorData, SkippedConstruct};
 use clang::diagnostic::{Diagnostic, Severity};
 use clang::*;
 use itertools::Itertools;
-use log::{debug, trace, warn};
+use log::{debug, info, trace, warn};
 use regex::Regex;
 use ritual_common::env_var_names;
 use ritual_common::errors::{bail, err_msg, format_err, print_trace, Result, ResultExt};
 use ritual_common::file_utils::{
-    canonicalize, copy_recursively, create_file, open_file, os_str_to_str, path_to_str,
-    remove_dir_all, remove_file,
+    canonicalize, copy_recursively, create_file, load_json, open_file, os_str_to_str, path_to_str,
+    remove_dir_all, remove_file, save_json,
 };
-use ritual_common::target::{current_env, current_target, Env, LibraryTarget};
-use ritual_common::utils::MapIfOk;
+use ritual_common::target::{current_env, Env};
+use ritual_common::utils::{get_command_output, MapIfOk};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsString;
 use std::io::Write;
 use std::mem;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 fn convert_type_kind(kind: TypeKind) -> CppBuiltInNumericType {
     match kind {
@@ -76,7 +90,11 @@ struct CppParser<'b, 'a> {
     data: &'b mut ProcessorData<'a>,
     current_target_paths: Vec<PathBuf>,
     source_id: Option<ItemId>,
+    /// If set, only items with this qualified name (without template arguments)
+    /// are added to the database.
+    path_filter: Option<String>,
     output: CppParserOutput,
+    clang: ClangCompat,
 }
 
 /// Print representation of `entity` and its children to the log.
@@ -146,6 +164,218 @@ fn get_template_arguments(entity: Entity<'_>) -> Option<Vec<CppType>> {
     }
 }
 
+/// Returns the kind of class template specialization if `entity` is a specialization.
+fn specialization_kind(entity: Entity<'_>) -> Option<CppSpecializationKind> {
+    match entity.get_kind() {
+        EntityKind::ClassTemplatePartialSpecialization => Some(CppSpecializationKind::Partial),
+        EntityKind::ClassDecl | EntityKind::StructDecl | EntityKind::UnionDecl
+            if entity.get_template().is_some() =>
+        {
+            Some(CppSpecializationKind::Explicit)
+        }
+        _ => None,
+    }
+}
+
+/// Returns entities of the arguments of function `entity`.
+fn get_argument_entities(entity: Entity<'_>) -> Result<Vec<Entity<'_>>> {
+    if entity.get_kind() == EntityKind::FunctionTemplate {
+        Ok(entity
+            .get_children()
+            .into_iter()
+            .filter(|c| c.get_kind() == EntityKind::ParmDecl)
+            .collect())
+    } else if let Some(args) = entity.get_arguments() {
+        Ok(args)
+    } else {
+        bail!("failed to get function arguments: {:?}", entity);
+    }
+}
+
+/// Returns true if `entity` is a copy or move constructor of `class`.
+fn is_copy_or_move_constructor(entity: Entity<'_>, class: Entity<'_>) -> bool {
+    let args = match get_argument_entities(entity) {
+        Ok(args) => args,
+        // assume the worst if the arguments are unknown
+        Err(_) => return true,
+    };
+    if args.len() != 1 {
+        return false;
+    }
+    args[0]
+        .get_type()
+        .map_or(false, |type1| match type1.get_kind() {
+            TypeKind::LValueReference | TypeKind::RValueReference => type1
+                .get_pointee_type()
+                .and_then(|target| target.get_declaration())
+                .map_or(false, |declaration| {
+                    declaration.get_canonical_entity() == class.get_canonical_entity()
+                }),
+            _ => false,
+        })
+}
+
+/// Returns true if the class `entity` doesn't have user-declared copy and move
+/// operations, destructor, virtual functions and base classes. Other requirements
+/// of trivially copyable types are verified by the C++ compiler
+/// when the layout of the class is checked.
+fn is_trivially_copyable(entity: Entity<'_>) -> bool {
+    entity
+        .get_children()
+        .into_iter()
+        .all(|child| match child.get_kind() {
+            EntityKind::BaseSpecifier | EntityKind::Destructor => false,
+            EntityKind::Constructor => !is_copy_or_move_constructor(child, entity),
+            EntityKind::Method => {
+                !child.is_virtual_method()
+                    && child.get_name().as_ref().map(String::as_str) != Some("operator=")
+            }
+            _ => true,
+        })
+}
+
+/// Returns true if `entity` is a move constructor of `class`.
+fn is_move_constructor(entity: Entity<'_>, class: Entity<'_>) -> bool {
+    is_copy_or_move_constructor(entity, class)
+        && get_argument_entities(entity).map_or(false, |args| {
+            args[0].get_type().map(|type1| type1.get_kind()) == Some(TypeKind::RValueReference)
+        })
+}
+
+/// Returns true if `entity` is declared inside a macro expansion.
+fn is_in_macro_expansion(entity: Entity<'_>) -> bool {
+    entity.get_location().map_or(false, |location| {
+        location.get_spelling_location() != location.get_expansion_location()
+    })
+}
+
+/// Returns true if the class `entity` explicitly deletes its move constructor,
+/// which indicates that its objects depend on their address.
+/// Move constructors deleted by macros (e.g. `Q_DISABLE_COPY_MOVE`)
+/// are ignored because such macros only disable copying and moving
+/// of the values, which is not relevant for objects owned by `CppBox`.
+fn is_immovable(entity: Entity<'_>) -> bool {
+    entity.get_children().into_iter().any(|child| {
+        child.get_kind() == EntityKind::Constructor
+            && is_move_constructor(child, entity)
+            && child.get_availability() == Availability::Unavailable
+            && !is_in_macro_expansion(child)
+    })
+}
+
+/// Skips implicit conversions (e.g. lvalue-to-rvalue) around `entity`.
+fn skip_implicit_expressions(mut entity: Entity<'_>) -> Entity<'_> {
+    while entity.get_kind() == EntityKind::UnexposedExpr {
+        match entity.get_children().as_slice() {
+            [child] => entity = *child,
+            _ => break,
+        }
+    }
+    entity
+}
+
+/// Returns the field referenced by `expression` if the expression is
+/// `field` or `this->field`, and `field` is declared in the class of `method`.
+fn this_field<'tu>(method: Entity<'tu>, expression: Entity<'tu>) -> Option<Entity<'tu>> {
+    let expression = skip_implicit_expressions(expression);
+    if expression.get_kind() != EntityKind::MemberRefExpr {
+        return None;
+    }
+    let is_this = expression
+        .get_children()
+        .into_iter()
+        .all(|child| skip_implicit_expressions(child).get_kind() == EntityKind::ThisExpr);
+    let field = expression.get_reference()?;
+    if !is_this
+        || field.get_kind() != EntityKind::FieldDecl
+        || field.get_semantic_parent() != method.get_semantic_parent()
+    {
+        return None;
+    }
+    Some(field)
+}
+
+/// Detects methods defined in the class body that only return or assign
+/// a field of the class (e.g. `int x() const { return m_x; }`), so they can be
+/// replaced with a direct access to the field
+/// (see `FfiFunctionOptions::inline_field_access`).
+fn trivial_field_access(entity: Entity<'_>) -> Option<CppTrivialFieldAccess> {
+    if entity.get_kind() != EntityKind::Method
+        || entity.is_virtual_method()
+        || entity.is_static_method()
+        || !entity.is_definition()
+        || is_in_macro_expansion(entity)
+    {
+        return None;
+    }
+    let body = entity
+        .get_children()
+        .into_iter()
+        .find(|child| child.get_kind() == EntityKind::CompoundStmt)?;
+    let statement = match body.get_children().as_slice() {
+        [statement] => *statement,
+        _ => return None,
+    };
+    let arguments = entity.get_arguments()?;
+    let result_type = entity.get_result_type()?.get_canonical_type();
+    let (field, accessor_type) = match (statement.get_kind(), arguments.as_slice()) {
+        (EntityKind::ReturnStmt, []) => {
+            let value = match statement.get_children().as_slice() {
+                [value] => *value,
+                _ => return None,
+            };
+            let field = this_field(entity, value)?;
+            let field_type = field.get_type()?.get_canonical_type();
+            let accessor_type = if result_type.get_kind() == TypeKind::LValueReference {
+                let target = result_type.get_pointee_type()?;
+                if target.get_display_name().trim_start_matches("const ")
+                    != field_type.get_display_name()
+                {
+                    return None;
+                }
+                if target.is_const_qualified() {
+                    CppFieldAccessorType::ConstRefGetter
+                } else {
+                    CppFieldAccessorType::MutRefGetter
+                }
+            } else if result_type == field_type {
+                CppFieldAccessorType::CopyGetter
+            } else {
+                return None;
+            };
+            (field, accessor_type)
+        }
+        (EntityKind::BinaryOperator, [argument]) => {
+            let (target, value) = match statement.get_children().as_slice() {
+                [target, value] => (*target, skip_implicit_expressions(*value)),
+                _ => return None,
+            };
+            let field = this_field(entity, target)?;
+            // both operands are plain names, so any `=` token is the operator
+            let is_assignment = statement
+                .get_range()?
+                .tokenize()
+                .iter()
+                .any(|token| token.get_spelling() == "=");
+            if !is_assignment
+                || result_type.get_kind() != TypeKind::Void
+                || value.get_kind() != EntityKind::DeclRefExpr
+                || value.get_reference() != Some(*argument)
+                || argument.get_type()?.get_canonical_type()
+                    != field.get_type()?.get_canonical_type()
+            {
+                return None;
+            }
+            (field, CppFieldAccessorType::Setter)
+        }
+        _ => return None,
+    };
+    Some(CppTrivialFieldAccess {
+        field_name: field.get_name()?,
+        accessor_type,
+    })
+}
+
 fn get_context_template_args(entity: Entity<'_>) -> Vec<CppType> {
     let mut current_entity = entity;
     let mut args = Vec::new();
@@ -183,6 +413,7 @@ fn get_path(entity: Entity<'_>) -> Result<CppPath> {
             EntityKind::ClassDecl
             | EntityKind::ClassTemplate
             | EntityKind::StructDecl
+            | EntityKind::UnionDecl
             | EntityKind::Namespace
             | EntityKind::EnumDecl
             | EntityKind::ClassTemplatePartialSpecialization => {
@@ -209,33 +440,286 @@ fn get_full_name_display(entity: Entity<'_>) -> String {
     }
 }
 
-#[cfg(test)]
-fn init_clang() -> Result<Clang> {
-    for _ in 0..12000 {
-        if let Ok(clang) = Clang::new() {
-            return Ok(clang);
+/// Description of a translation unit parsed by a subprocess
+/// (see `run_clang_subprocess`).
+#[derive(Debug, Serialize, Deserialize)]
+struct ClangSubprocessRequest {
+    cpp_path: PathBuf,
+    arguments: Vec<String>,
+    cache_path: Option<PathBuf>,
+    cache_key: Option<String>,
+}
+
+/// Set if the entry point of the current executable handles
+/// `RITUAL_CLANG_SUBPROCESS` env var, so it can be used to run the parser
+/// in a subprocess.
+static SUBPROCESS_SUPPORTED: AtomicBool = AtomicBool::new(false);
+
+/// Performs the libclang queries the parser makes about types
+/// (see `visit_all_entities`). Declarations of the types are not visited.
+fn visit_type(type1: Type<'_>) {
+    let _ = type1.get_display_name();
+    let _ = type1.get_canonical_type().get_display_name();
+    let _ = type1.get_sizeof();
+    let _ = type1.get_declaration().map(|entity| entity.get_name());
+    let _ = type1.get_template_argument_types();
+    if let Some(pointee) = type1.get_pointee_type() {
+        visit_type(pointee);
+    }
+    if let Some(element) = type1.get_element_type() {
+        visit_type(element);
+    }
+    if let Some(result) = type1.get_result_type() {
+        visit_type(result);
+    }
+    for argument in type1.get_argument_types().into_iter().flatten() {
+        visit_type(argument);
+    }
+}
+
+/// Visits all entities of the translation unit, performing the kinds of
+/// libclang queries the parser makes (names, locations, types, source ranges,
+/// tokens of declarations and enum values), so that a crash in libclang
+/// is likely to happen in the subprocess rather than in the main process.
+fn visit_all_entities(entity: Entity<'_>) {
+    let _ = entity.get_name();
+    let _ = entity.get_display_name();
+    let _ = entity.get_accessibility();
+    let _ = entity.get_availability();
+    let _ = entity.get_semantic_parent();
+    let _ = entity.get_reference().map(|entity| entity.get_name());
+    let _ = entity.get_canonical_entity().get_name();
+    let _ = entity.get_template().map(|entity| entity.get_name());
+    if let Some(location) = entity.get_location() {
+        let _ = location.get_presumed_location();
+        let _ = location.get_file_location();
+    }
+    if let Some(type1) = entity.get_type() {
+        visit_type(type1);
+    }
+    if let Some(type1) = entity.get_typedef_underlying_type() {
+        visit_type(type1);
+    }
+    match entity.get_kind() {
+        EntityKind::FunctionDecl
+        | EntityKind::FunctionTemplate
+        | EntityKind::Method
+        | EntityKind::Constructor
+        | EntityKind::Destructor
+        | EntityKind::ConversionFunction
+        | EntityKind::ParmDecl
+        | EntityKind::BinaryOperator => {
+            // the parser tokenizes declarations of functions and their arguments
+            let _ = entity.get_range().map(|range| range.tokenize());
+        }
+        EntityKind::EnumConstantDecl => {
+            let _ = entity.get_enum_constant_value();
         }
-        std::thread::sleep(std::time::Duration::from_millis(100));
+        _ => {}
+    }
+    for child in entity.get_children() {
+        visit_all_entities(child);
     }
-    Clang::new().map_err(|err| format_err!("clang init failed: {}", err))
 }
 
-#[cfg(not(test))]
-/// Creates a `Clang` context.
-fn init_clang() -> Result<Clang> {
-    Clang::new().map_err(|err| format_err!("clang init failed: {}", err))
+/// If `RITUAL_CLANG_SUBPROCESS` env var is set, parses the translation unit described
+/// by the request file in the variable and returns true. The process should exit
+/// after that. Otherwise, returns false and allows the parser to use the current
+/// executable for isolating libclang crashes (see `clang_crash_reason`).
+pub fn run_clang_subprocess() -> Result<bool> {
+    let request_path = match env::var_os(env_var_names::CLANG_SUBPROCESS) {
+        Some(path) => PathBuf::from(path),
+        None => {
+            SUBPROCESS_SUPPORTED.store(true, Ordering::SeqCst);
+            return Ok(false);
+        }
+    };
+    let request: ClangSubprocessRequest = load_json(&request_path)?;
+    clang_compat::version_text()?;
+    let clang = init_clang()?;
+    let index = Index::new(&clang, false, false);
+    let tu = index
+        .parser(&request.cpp_path)
+        .arguments(&request.arguments)
+        .parse()
+        .with_context(|_| "clang parse failed")?;
+    visit_all_entities(tu.get_entity());
+    let has_errors = tu
+        .get_diagnostics()
+        .iter()
+        .any(|d| d.get_severity() == Severity::Error || d.get_severity() == Severity::Fatal);
+    if let (Some(cache_path), Some(key), false) =
+        (request.cache_path, request.cache_key, has_errors)
+    {
+        ClangCache::new(cache_path)?.save(&tu, &key)?;
+    }
+    Ok(true)
+}
+
+/// Parses `cpp_path` in a subprocess and returns the reason if libclang crashed.
+/// If `cache` is specified, the subprocess saves the translation unit to the cache,
+/// so it's not parsed again by the current process.
+fn clang_crash_reason(
+    tmp_path: &Path,
+    cpp_path: &Path,
+    arguments: &[String],
+    cache: Option<(&ClangCache, &str)>,
+) -> Result<Option<String>> {
+    let request_path = tmp_path.join("clang_subprocess.json");
+    save_json(
+        &request_path,
+        &ClangSubprocessRequest {
+            cpp_path: cpp_path.to_path_buf(),
+            arguments: arguments.to_vec(),
+            cache_path: cache.map(|(cache, _)| cache.path().to_path_buf()),
+            cache_key: cache.map(|(_, key)| key.to_string()),
+        },
+        None,
+    )?;
+    let mut command = Command::new(env::current_exe()?);
+    command.env(env_var_names::CLANG_SUBPROCESS, &request_path);
+    let output = command
+        .output()
+        .with_context(|_| format!("failed to run command: {:?}", command))?;
+    remove_file(&request_path)?;
+    // a negative exit code is an unhandled exception on Windows;
+    // no exit code means that the process was terminated by a signal
+    match output.status.code() {
+        Some(code) if code >= 0 => {
+            if !output.status.success() {
+                // the error will be reported by the parser in the current process
+                trace!(
+                    "clang subprocess failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+            Ok(None)
+        }
+        _ => Ok(Some(format!("libclang crashed ({})", output.status))),
+    }
+}
+
+/// Returns include directives of the translation unit parsed with `config`.
+fn parsed_include_directives(
+    config: &Config,
+    excluded_headers: &[PathBuf],
+) -> Result<Vec<PathBuf>> {
+    Ok(all_include_directives(config)?
+        .into_iter()
+        .filter(|directive| !excluded_headers.contains(directive))
+        .collect_vec())
+}
+
+/// Returns arguments passed to clang when parsing C++ code with `config`
+/// that includes `include_directives`.
+fn clang_arguments(config: &Config, include_directives: &[PathBuf]) -> Result<Vec<String>> {
+    let mut args = vec![
+        "-Xclang".to_string(),
+        "-detailed-preprocessing-record".to_string(),
+    ];
+    if let Some(standard) = config.cpp_standard() {
+        args.push(format!("-std=c++{}", standard.version()));
+    } else if current_env() != Env::Msvc {
+        args.push("-std=c++11".to_string());
+    }
+    args.extend_from_slice(config.cpp_parser_arguments());
+    args.extend(config.compile_commands_flags(include_directives));
+    let mut cpp_build_paths = config.cpp_build_paths().clone();
+    cpp_build_paths.apply_env();
+    for dir in cpp_build_paths.include_paths() {
+        let str = path_to_str(dir)?;
+        args.push("-I".to_string());
+        args.push(str.to_string());
+    }
+    if let Ok(path) = ::std::env::var(env_var_names::CLANG_SYSTEM_INCLUDE_PATH) {
+        if !Path::new(&path).exists() {
+            warn!(
+                "{} environment variable is set to \"{}\" \
+                 but this path does not exist. This may result in parse errors related to system header includes.",
+                env_var_names::CLANG_SYSTEM_INCLUDE_PATH,
+                path
+            );
+        }
+        args.push("-isystem".to_string());
+        args.push(path);
+    } else {
+        trace!("{} environment variable is not set. This may result in parse errors related to system header includes.", env_var_names::CLANG_SYSTEM_INCLUDE_PATH);
+    }
+    for dir in cpp_build_paths.framework_paths() {
+        let str = path_to_str(dir)?;
+        args.push("-F".to_string());
+        args.push(str.to_string());
+    }
+    debug!("clang arguments: {:?}", args);
+    Ok(args)
+}
+
+/// Returns headers that crashed libclang before. They are excluded from parsing.
+fn excluded_headers(db: &DatabaseClient) -> Vec<PathBuf> {
+    db.parse_failures()
+        .map(|(header, _)| PathBuf::from(header))
+        .collect()
+}
+
+/// Result of `try_run_clang`.
+enum ClangOutcome<R> {
+    Parsed(R),
+    /// libclang crashed in the subprocess with the specified reason.
+    Crashed(String),
 }
 
 /// Runs `clang` parser with `config`.
 /// If `cpp_code` is specified, it's written to the C++ file before parsing it.
+/// Include directives of `excluded_headers` are omitted from the global header.
+/// If `cache` is specified, the translation unit is loaded from the cache
+/// if possible and saved to the cache after a successful parse.
+/// If `skipped` is specified (lenient mode), clang errors are recorded
+/// to it instead of aborting the parse.
 /// If successful, calls `f` and passes the topmost entity (the translation unit)
 /// as its argument. Returns output value of `f` or an error.
 fn run_clang<R, F: FnMut(Entity<'_>) -> Result<R>>(
     config: &Config,
     tmp_path: &Path,
     cpp_code: Option<String>,
-    mut f: F,
+    excluded_headers: &[PathBuf],
+    cache: Option<&ClangCache>,
+    skipped: Option<&mut Vec<SkippedConstruct>>,
+    f: F,
 ) -> Result<R> {
+    match try_run_clang(
+        config,
+        tmp_path,
+        cpp_code,
+        excluded_headers,
+        cache,
+        skipped,
+        false,
+        f,
+    )? {
+        ClangOutcome::Parsed(result) => Ok(result),
+        ClangOutcome::Crashed(reason) => bail!("{}", reason),
+    }
+}
+
+/// Same as `run_clang`, but if `isolate_crashes` is true and the translation unit
+/// is not cached, it's parsed in a subprocess first, and a crash of libclang
+/// is returned as `ClangOutcome::Crashed` instead of terminating the current process.
+/// Isolation is only used for parsing the library headers because small snippets
+/// (e.g. slot wrappers) are not worth an extra process.
+#[allow(clippy::too_many_arguments)]
+fn try_run_clang<R, F: FnMut(Entity<'_>) -> Result<R>>(
+    config: &Config,
+    tmp_path: &Path,
+    cpp_code: Option<String>,
+    excluded_headers: &[PathBuf],
+    cache: Option<&ClangCache>,
+    skipped: Option<&mut Vec<SkippedConstruct>>,
+    isolate_crashes: bool,
+    mut f: F,
+) -> Result<ClangOutcome<R>> {
+    // The version must be known before the context is created
+    // because determining it requires loading libclang.
+    clang_compat::version_text()?;
     let clang = init_clang()?;
     let index = Index::new(&clang, false, false);
 
@@ -246,7 +730,8 @@ fn run_clang<R, F: FnMut(Entity<'_>) -> Result<R>>(
         "{}",
         include_str!("../templates/c_lib/global.h"),
     )?;
-    write_include_directives(&mut global_file, &all_include_directives(config)?)?;
+    let directives = parsed_include_directives(config, excluded_headers)?;
+    write_include_directives(&mut global_file, &directives)?;
     drop(global_file);
 
     let tmp_cpp_path = tmp_path.join("1.cpp");
@@ -268,49 +753,44 @@ fn run_clang<R, F: FnMut(Entity<'_>) -> Result<R>>(
         }
     }
 
-    let mut args = vec![
-        "-Xclang".to_string(),
-        "-detailed-preprocessing-record".to_string(),
-    ];
-    if current_env() != Env::Msvc {
-        args.push("-std=c++11".to_string());
-    }
-    args.extend_from_slice(config.cpp_parser_arguments());
-    let mut cpp_build_paths = config.cpp_build_paths().clone();
-    cpp_build_paths.apply_env();
-    for dir in cpp_build_paths.include_paths() {
-        let str = path_to_str(dir)?;
-        args.push("-I".to_string());
-        args.push(str.to_string());
-    }
-    if let Ok(path) = ::std::env::var(env_var_names::CLANG_SYSTEM_INCLUDE_PATH) {
-        if !Path::new(&path).exists() {
-            warn!(
-                "{} environment variable is set to \"{}\" \
-                 but this path does not exist. This may result in parse errors related to system header includes.",
-                env_var_names::CLANG_SYSTEM_INCLUDE_PATH,
-                path
-            );
-        }
-        args.push("-isystem".to_string());
-        args.push(path);
+    let args = clang_arguments(config, &directives)?;
+    let cache_key = if cache.is_some() {
+        Some(ClangCache::key(&args, &[&tmp_cpp_path, &global_file_path])?)
     } else {
-        trace!("{} environment variable is not set. This may result in parse errors related to system header includes.", env_var_names::CLANG_SYSTEM_INCLUDE_PATH);
-    }
-    for dir in config.cpp_build_paths().framework_paths() {
-        let str = path_to_str(dir)?;
-        args.push("-F".to_string());
-        args.push(str.to_string());
+        None
+    };
+    let is_cached = match (cache, &cache_key) {
+        (Some(cache), Some(key)) => cache.contains(key)?,
+        _ => false,
+    };
+    if isolate_crashes && !is_cached && SUBPROCESS_SUPPORTED.load(Ordering::SeqCst) {
+        let cache_and_key = match (cache, &cache_key) {
+            (Some(cache), Some(key)) => Some((cache, key.as_str())),
+            _ => None,
+        };
+        if let Some(reason) = clang_crash_reason(tmp_path, &tmp_cpp_path, &args, cache_and_key)? {
+            remove_file(&tmp_cpp_path)?;
+            remove_file(&global_file_path)?;
+            return Ok(ClangOutcome::Crashed(reason));
+        }
     }
-    debug!("clang arguments: {:?}", args);
 
-    let tu = index
-        .parser(&tmp_cpp_path)
-        .arguments(&args)
-        .parse()
-        .with_context(|_| "clang parse failed")?;
+    let cached_tu = match (cache, &cache_key) {
+        (Some(cache), Some(key)) => cache.load(&index, key)?,
+        _ => None,
+    };
+    let is_cached = cached_tu.is_some();
+    let tu = match cached_tu {
+        Some(tu) => tu,
+        None => index
+            .parser(&tmp_cpp_path)
+            .arguments(&args)
+            .parse()
+            .with_context(|_| "clang parse failed")?,
+    };
     let translation_unit = tu.get_entity();
     assert_eq!(translation_unit.get_kind(), EntityKind::TranslationUnit);
+    let mut has_errors = false;
     {
         let diagnostics = tu.get_diagnostics();
         if !diagnostics.is_empty() {
@@ -319,54 +799,458 @@ fn run_clang<R, F: FnMut(Entity<'_>) -> Result<R>>(
                 trace!("[DebugParser] {}", diag);
             }
         }
-        let should_print_error = |d: &Diagnostic<'_>| {
-            d.get_severity() == Severity::Error || d.get_severity() == Severity::Fatal
+        let should_print_error = |d: &Diagnostic<'_>| {
+            d.get_severity() == Severity::Error || d.get_severity() == Severity::Fatal
+        };
+        if diagnostics.iter().any(should_print_error) {
+            if let Some(skipped) = skipped {
+                for diag in diagnostics.iter().filter(|d| should_print_error(*d)) {
+                    warn!("ignoring clang error in lenient mode: {}", diag);
+                    let location = diag.get_location().get_presumed_location();
+                    skipped.push(SkippedConstruct {
+                        description: "code with clang errors".to_string(),
+                        reason: diag.get_text(),
+                        location: Some(CppOriginLocation {
+                            include_file_path: location.0,
+                            line: location.1,
+                            column: location.2,
+                        }),
+                    });
+                }
+                has_errors = true;
+            } else {
+                bail!(
+                    "fatal clang error:\n{}",
+                    diagnostics.iter().map(ToString::to_string).join("\n")
+                );
+            }
+        }
+    }
+    if let (Some(cache), Some(key), false, false) = (cache, &cache_key, is_cached, has_errors) {
+        if let Err(err) = cache.save(&tu, key) {
+            warn!("failed to cache translation unit: {}", err);
+        }
+    }
+    let result = f(translation_unit);
+    remove_file(&tmp_cpp_path)?;
+    remove_file(&global_file_path)?;
+    result.map(ClangOutcome::Parsed)
+}
+
+/// Runs the parser on specified data.
+pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
+    parse(data, None, None)
+}
+
+/// Parses declarations of C++ items with the qualified name `name`
+/// (without template arguments). If `headers` is not empty, only these headers
+/// are parsed instead of all headers of the library.
+pub fn parse_item(data: &mut ProcessorData<'_>, name: &str, headers: &[PathBuf]) -> Result<()> {
+    if headers.is_empty() {
+        parse(data, Some(name.to_string()), None)
+    } else {
+        parse(data, Some(name.to_string()), Some(headers))
+    }
+}
+
+/// Parses the library headers, or only `headers` if specified.
+fn parse(
+    data: &mut ProcessorData<'_>,
+    path_filter: Option<String>,
+    headers: Option<&[PathBuf]>,
+) -> Result<()> {
+    debug!("clang version: {}", clang_compat::version_text()?);
+    ClangCompat::current()?.report_degraded_features();
+    debug!("Initializing clang");
+    let mut parser = CppParser {
+        current_target_paths: data
+            .config
+            .target_include_paths()
+            .iter()
+            .map_if_ok(canonicalize)?,
+        source_id: None,
+        path_filter,
+        data,
+        output: Default::default(),
+        clang: ClangCompat::current()?,
+    };
+    parser
+        .current_target_paths
+        .push(canonicalize(parser.data.tmp_path())?.join("extra"));
+    let cache = ClangCache::new(parser.data.workspace.clang_cache_path())?;
+    let mut clang_errors = Vec::new();
+    let lenient = parser.data.config.lenient();
+    let global_header_path = parser.data.tmp_path().join("global.h");
+    if parser.data.config.retry_crashed_headers() {
+        let headers = excluded_headers(parser.data.db);
+        for header in &headers {
+            info!(
+                "parsing header that crashed libclang before: {}",
+                header.display()
+            );
+            parser.data.db.set_parse_failure(path_to_str(header)?, None);
+        }
+    }
+    let mut excluded = excluded_headers(parser.data.db);
+    let headers_code = if let Some(headers) = headers {
+        // exclude all library headers from the global header
+        // and include only the requested ones
+        excluded.extend(all_include_directives(&parser.data.config)?);
+        let mut code = Vec::new();
+        write_include_directives(&mut code, headers)?;
+        Some(String::from_utf8(code)?)
+    } else {
+        None
+    };
+    let mut graph = loop {
+        let outcome = try_run_clang(
+            &parser.data.config,
+            &parser.data.tmp_path(),
+            headers_code.clone(),
+            &excluded,
+            Some(&cache),
+            if lenient {
+                Some(&mut clang_errors)
+            } else {
+                None
+            },
+            true,
+            |translation_unit| {
+                parser.parse(translation_unit)?;
+                IncludeGraph::collect(translation_unit, &global_header_path)
+            },
+        )?;
+        match outcome {
+            ClangOutcome::Parsed(graph) => break graph,
+            ClangOutcome::Crashed(reason) => {
+                if headers.is_some() {
+                    bail!("libclang crashed while parsing {:?}: {}", headers, reason);
+                }
+                let crashed = find_crashing_headers(parser.data, &[], &excluded, &reason)?;
+                excluded.extend(crashed);
+            }
+        }
+    };
+    let header_groups = if headers.is_some() {
+        Vec::new()
+    } else {
+        parser.data.config.header_groups().to_vec()
+    };
+    // headers of the group are included by the main file of the translation unit
+    let main_file = parser.data.tmp_path().join("1.cpp");
+    for (index, group) in header_groups.iter().enumerate() {
+        debug!("parsing header group {}", index);
+        loop {
+            let mut code = Vec::new();
+            let group_headers = group
+                .iter()
+                .filter(|header| !excluded.contains(header))
+                .cloned()
+                .collect_vec();
+            write_include_directives(&mut code, &group_headers)?;
+            let outcome = try_run_clang(
+                &parser.data.config,
+                &parser.data.tmp_path(),
+                Some(String::from_utf8(code)?),
+                &excluded,
+                Some(&cache),
+                if lenient {
+                    Some(&mut clang_errors)
+                } else {
+                    None
+                },
+                true,
+                |translation_unit| {
+                    parser.parse(translation_unit)?;
+                    let mut graph = IncludeGraph::collect(translation_unit, &global_header_path)?;
+                    graph.move_to_group(index, &main_file)?;
+                    Ok(graph)
+                },
+            )
+            .with_context(|_| format!("failed to parse header group {}", index))?;
+            match outcome {
+                ClangOutcome::Parsed(group_graph) => {
+                    graph.merge(group_graph);
+                    break;
+                }
+                ClangOutcome::Crashed(reason) => {
+                    let crashed =
+                        find_crashing_headers(parser.data, &group_headers, &excluded, &reason)
+                            .with_context(|_| format!("failed to parse header group {}", index))?;
+                    excluded.extend(crashed);
+                }
+            }
+        }
+    }
+    parser.data.skipped.extend(clang_errors);
+    let crate_name = parser.data.config.crate_properties().name();
+    if parser.path_filter.is_none() && headers.is_none() {
+        include_graph::save(parser.data.workspace, crate_name, &graph)?;
+    } else {
+        // only some items were parsed, so inclusions found earlier are still valid
+        include_graph::save_merged(parser.data.workspace, crate_name, graph)?;
+    }
+
+    Ok(())
+}
+
+/// Parses each of `extra_headers` and the headers included by the config
+/// (except `excluded`) separately to find the ones that crash libclang.
+/// The crashing headers are recorded in the database, so they are excluded
+/// from parsing until they are retried (see `Config::set_retry_crashed_headers`).
+/// Returns an error if no header crashes on its own.
+fn find_crashing_headers(
+    data: &mut ProcessorData<'_>,
+    extra_headers: &[PathBuf],
+    excluded: &[PathBuf],
+    reason: &str,
+) -> Result<Vec<PathBuf>> {
+    warn!("{}, looking for headers that cause the crash", reason);
+    let all_headers = all_include_directives(&data.config)?;
+    let candidates = all_headers
+        .iter()
+        .chain(extra_headers)
+        .filter(|header| !excluded.contains(header))
+        .unique()
+        .cloned()
+        .collect_vec();
+    let mut crashed = Vec::new();
+    for header in candidates {
+        let mut code = Vec::new();
+        write_include_directives(&mut code, &[header.clone()])?;
+        let mut clang_errors = Vec::new();
+        let outcome = try_run_clang(
+            &data.config,
+            &data.tmp_path(),
+            Some(String::from_utf8(code)?),
+            &all_headers,
+            None,
+            Some(&mut clang_errors),
+            true,
+            |_| Ok(()),
+        )?;
+        if let ClangOutcome::Crashed(reason) = outcome {
+            let header_str = path_to_str(&header)?;
+            warn!("header {} crashed libclang and will be skipped", header_str);
+            data.db.set_parse_failure(header_str, Some(&reason));
+            data.skipped.push(SkippedConstruct {
+                description: format!("header {}", header_str),
+                reason,
+                location: None,
+            });
+            crashed.push(header);
+        }
+    }
+    if crashed.is_empty() {
+        bail!("{} (no single header causes the crash)", reason);
+    }
+    Ok(crashed)
+}
+
+/// Returns true if the kind of `entity` is relevant for `dump_preprocessed`.
+fn is_dumped_declaration(kind: EntityKind) -> bool {
+    match kind {
+        EntityKind::ClassDecl
+        | EntityKind::StructDecl
+        | EntityKind::UnionDecl
+        | EntityKind::ClassTemplate
+        | EntityKind::ClassTemplatePartialSpecialization
+        | EntityKind::EnumDecl
+        | EntityKind::TypedefDecl
+        | EntityKind::TypeAliasDecl
+        | EntityKind::FunctionDecl
+        | EntityKind::FunctionTemplate
+        | EntityKind::Method
+        | EntityKind::Constructor
+        | EntityKind::Destructor
+        | EntityKind::ConversionFunction
+        | EntityKind::FieldDecl
+        | EntityKind::VarDecl => true,
+        _ => false,
+    }
+}
+
+/// Returns true if `file_path` is the file included with the `header` directive,
+/// e.g. `/usr/include/QtCore/qobject.h` for `QtCore/qobject.h`.
+pub fn is_header_file(file_path: &Path, header: &Path) -> bool {
+    file_path.ends_with(header)
+}
+
+impl CppParser<'_, '_> {
+    /// Writes a line for each declaration in `entity` (recursively) that is
+    /// located in `header` to `output`.
+    fn dump_declarations(
+        &self,
+        entity: Entity<'_>,
+        header: &Path,
+        output: &mut Vec<String>,
+    ) -> Result<()> {
+        for child in entity.get_children() {
+            let file_path = child
+                .get_location()
+                .and_then(|location| location.get_file_location().file)
+                .map(|file| file.get_path());
+            if !file_path.map_or(false, |path| is_header_file(&path, header)) {
+                continue;
+            }
+            if is_dumped_declaration(child.get_kind()) {
+                let location = get_origin_location(child)
+                    .map(|location| format!("{}:{}", location.include_file_path, location.line))
+                    .unwrap_or_else(|_| "unknown location".to_string());
+                output.push(format!(
+                    "{:?} {} at {}: availability: {:?}, accessibility: {:?}, {}, {}",
+                    child.get_kind(),
+                    get_full_name_display(child),
+                    location,
+                    child.get_availability(),
+                    child.get_accessibility(),
+                    if child.is_definition() {
+                        "definition"
+                    } else {
+                        "declaration"
+                    },
+                    if self.should_process_entity(child)? {
+                        "processed"
+                    } else {
+                        "ignored by the parser (outside of target include paths or blacklisted)"
+                    }
+                ));
+            }
+            match child.get_kind() {
+                EntityKind::Namespace
+                | EntityKind::LinkageSpec
+                | EntityKind::UnexposedDecl
+                | EntityKind::ClassDecl
+                | EntityKind::StructDecl
+                | EntityKind::UnionDecl
+                | EntityKind::ClassTemplate
+                | EntityKind::ClassTemplatePartialSpecialization => {
+                    self.dump_declarations(child, header, output)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the compiler used for preprocessing: the value of `CXX` environment variable
+/// (`cxx_var`) or `clang++` if it's not set. The compiler must accept clang arguments.
+pub fn preprocessor_command(cxx_var: Option<OsString>) -> OsString {
+    cxx_var
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| "clang++".into())
+}
+
+/// Runs the C++ compiler's preprocessor on `cpp_path` with `arguments`
+/// and returns its output.
+fn preprocess(cpp_path: &Path, arguments: &[String]) -> Result<String> {
+    let mut command = Command::new(preprocessor_command(env::var_os("CXX")));
+    command.arg("-E").args(arguments).arg(cpp_path);
+    get_command_output(&mut command)
+}
+
+/// Returns the lines of `preprocessed` output that come from `header`,
+/// using line markers (`# 12 "/path/to/header.h" 1`) emitted by the preprocessor.
+pub fn preprocessed_header_lines(preprocessed: &str, header: &Path) -> String {
+    let mut output = String::new();
+    let mut in_header = false;
+    for line in preprocessed.lines() {
+        let is_marker =
+            line.starts_with("# ") && line[2..].starts_with(|c: char| c.is_ascii_digit());
+        let marker_file = if is_marker {
+            line.splitn(2, '"')
+                .nth(1)
+                .and_then(|rest| rest.rsplitn(2, '"').nth(1))
+        } else {
+            None
         };
-        if diagnostics.iter().any(should_print_error) {
-            bail!(
-                "fatal clang error:\n{}",
-                diagnostics.iter().map(ToString::to_string).join("\n")
-            );
+        if let Some(file) = marker_file {
+            let file = file.replace("\\\\", "\\");
+            in_header = is_header_file(Path::new(&file), header);
+        } else if in_header {
+            output.push_str(line);
+            output.push('\n');
         }
     }
-    let result = f(translation_unit);
-    remove_file(&tmp_cpp_path)?;
-    remove_file(&global_file_path)?;
-    result
+    output
 }
 
-/// Runs the parser on specified data.
-pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
-    debug!("clang version: {}", get_version());
-    debug!("Initializing clang");
-    let mut parser = CppParser {
+/// Writes the preprocessed source of `header` (as seen by the parser) and
+/// the list of declarations clang found in it, with their availability
+/// and whether the parser processes them, to the log directory of the workspace.
+/// Useful for debugging why an expected item doesn't appear in the database.
+pub fn dump_preprocessed(data: &mut ProcessorData<'_>, header: &Path) -> Result<()> {
+    let log_path = data.workspace.log_path();
+    let name = path_to_str(header)?
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    let preprocessed_path = log_path.join(format!("preprocessed_{}.cpp", name));
+    let declarations_path = log_path.join(format!("declarations_{}.txt", name));
+
+    let parser = CppParser {
         current_target_paths: data
             .config
             .target_include_paths()
             .iter()
             .map_if_ok(canonicalize)?,
         source_id: None,
+        path_filter: None,
         data,
         output: Default::default(),
+        clang: ClangCompat::current()?,
     };
-    parser
-        .current_target_paths
-        .push(canonicalize(parser.data.workspace.tmp_path())?.join("extra"));
-    run_clang(
+    let mut code = Vec::new();
+    write_include_directives(&mut code, &[header.to_path_buf()])?;
+    let tmp_path = parser.data.tmp_path();
+    let excluded = excluded_headers(parser.data.db);
+    let directives = parsed_include_directives(&parser.data.config, &excluded)?;
+    let arguments = clang_arguments(&parser.data.config, &directives)?;
+    let mut clang_errors = Vec::new();
+    let (preprocessed, declarations) = run_clang(
         &parser.data.config,
-        &parser.data.workspace.tmp_path(),
+        &tmp_path,
+        Some(String::from_utf8(code)?),
+        &excluded,
         None,
-        |translation_unit| parser.parse(translation_unit),
+        Some(&mut clang_errors),
+        |translation_unit| {
+            let preprocessed = preprocess(&tmp_path.join("1.cpp"), &arguments)
+                .map(|output| preprocessed_header_lines(&output, header));
+            let mut declarations = Vec::new();
+            parser.dump_declarations(translation_unit, header, &mut declarations)?;
+            Ok((preprocessed, declarations))
+        },
     )?;
 
+    match preprocessed {
+        Ok(preprocessed) => {
+            let mut file = create_file(&preprocessed_path)?;
+            write!(file, "{}", preprocessed)?;
+            info!("preprocessed source: {}", preprocessed_path.display());
+        }
+        Err(err) => warn!("failed to run preprocessor: {}", err),
+    }
+
+    let mut file = create_file(&declarations_path)?;
+    for error in &clang_errors {
+        writeln!(file, "clang error: {}", error.reason)?;
+    }
+    for line in &declarations {
+        writeln!(file, "{}", line)?;
+    }
+    info!(
+        "{} declarations, {} clang errors: {}",
+        declarations.len(),
+        clang_errors.len(),
+        declarations_path.display()
+    );
     Ok(())
 }
 
 pub fn parse_generated_items(data: &mut ProcessorData<'_>) -> Result<()> {
-    let current_target = LibraryTarget {
-        cpp_library_version: data.config.cpp_lib_version().map(ToString::to_string),
-        target: current_target(),
-    };
+    let current_target = data.config.current_library_target()?;
     for ffi_item_id in data.db.ffi_item_ids().collect_vec() {
         let ffi_item = data.db.ffi_item(&ffi_item_id)?;
         if !ffi_item.item.is_source_item() {
@@ -381,15 +1265,21 @@ pub fn parse_generated_items(data: &mut ProcessorData<'_>) -> Result<()> {
         }
         let code = ffi_item.item.source_item_cpp_code(data.db)?;
         let mut parser = CppParser {
-            current_target_paths: vec![canonicalize(data.workspace.tmp_path())?.join("1.cpp")],
+            current_target_paths: vec![canonicalize(data.tmp_path())?.join("1.cpp")],
             source_id: Some(ffi_item_id),
+            path_filter: None,
             data,
             output: Default::default(),
+            clang: ClangCompat::current()?,
         };
+        let excluded = excluded_headers(parser.data.db);
         run_clang(
             &parser.data.config,
-            &parser.data.workspace.tmp_path(),
+            &parser.data.tmp_path(),
             Some(code),
+            &excluded,
+            None,
+            None,
             |translation_unit| {
                 parser.parse(translation_unit)?;
                 Ok(())
@@ -399,6 +1289,318 @@ pub fn parse_generated_items(data: &mut ProcessorData<'_>) -> Result<()> {
     Ok(())
 }
 
+/// Parses `code` placed in a temporary source file and adds the parsed items
+/// to the database. Unlike `run`, it doesn't parse the library headers and doesn't use
+/// the translation unit cache or the include graph, so it's fast enough for fuzzing
+/// (see the `fuzz` module).
+pub fn parse_code(data: &mut ProcessorData<'_>, code: &str) -> Result<()> {
+    let mut parser = CppParser {
+        current_target_paths: vec![canonicalize(data.tmp_path())?.join("1.cpp")],
+        source_id: None,
+        path_filter: None,
+        data,
+        output: Default::default(),
+        clang: ClangCompat::current()?,
+    };
+    run_clang(
+        &parser.data.config,
+        &parser.data.tmp_path(),
+        Some(code.to_string()),
+        &[],
+        None,
+        None,
+        |translation_unit| parser.parse(translation_unit),
+    )
+}
+
+/// Resolves signatures of dependent functions (see `CppDependentFunction`)
+/// for each concrete instantiation of their class templates by letting clang
+/// evaluate the type of the function's address. If the signature can't be resolved
+/// for an instantiation (e.g. the function is disabled by `std::enable_if`
+/// or its name is overloaded), the function is not added for that instantiation
+/// and the reason is recorded as a skipped construct.
+/// Default values of the arguments of resolved functions are not preserved.
+pub fn resolve_dependent_functions(data: &mut ProcessorData<'_>) -> Result<()> {
+    const TYPEDEF_PREFIX: &str = "ritual_dependent_";
+    let mut requests = Vec::new();
+    for function in data.db.cpp_dependent_functions() {
+        let class_path = function.item.path.parent()?;
+        for type1 in data
+            .db
+            .all_cpp_items()
+            .filter_map(|item| item.item.as_type_ref())
+        {
+            if type1.specialization.is_none()
+                && is_same_template(&type1.path, &class_path)
+                && is_concrete_instantiation(&type1.path)
+            {
+                requests.push((
+                    function.id.clone(),
+                    function.item.clone(),
+                    type1.path.clone(),
+                ));
+            }
+        }
+    }
+    if requests.is_empty() {
+        return Ok(());
+    }
+
+    let mut code = String::new();
+    for (index, (_, function, class_path)) in requests.iter().enumerate() {
+        code.push_str(&format!(
+            "typedef decltype(&{}::{}{}{}) {}{};\n",
+            class_path.to_cpp_code()?,
+            if function.is_template {
+                "template "
+            } else {
+                ""
+            },
+            function.path.last().name,
+            if function.is_template { "<>" } else { "" },
+            TYPEDEF_PREFIX,
+            index
+        ));
+    }
+
+    let parser = CppParser {
+        current_target_paths: vec![canonicalize(data.tmp_path())?.join("1.cpp")],
+        source_id: None,
+        path_filter: None,
+        data,
+        output: Default::default(),
+        clang: ClangCompat::current()?,
+    };
+    let excluded = excluded_headers(parser.data.db);
+    let mut clang_errors = Vec::new();
+    let mut results = run_clang(
+        &parser.data.config,
+        &parser.data.tmp_path(),
+        Some(code),
+        &excluded,
+        None,
+        Some(&mut clang_errors),
+        |translation_unit| {
+            let mut resolved = HashMap::new();
+            for entity in translation_unit.get_children() {
+                if entity.get_kind() != EntityKind::TypedefDecl {
+                    continue;
+                }
+                let name = match entity.get_name() {
+                    Some(name) if name.starts_with(TYPEDEF_PREFIX) => name,
+                    _ => continue,
+                };
+                let index = match usize::from_str(&name[TYPEDEF_PREFIX.len()..]) {
+                    Ok(index) if index < requests.len() => index,
+                    _ => continue,
+                };
+                let (_, function, class_path) = &requests[index];
+                let result = entity
+                    .get_typedef_underlying_type()
+                    .ok_or_else(|| err_msg("failed to get typedef type"))
+                    .and_then(|type1| parser.parse_resolved_function(function, class_path, type1));
+                resolved.insert(index, result);
+            }
+            Ok(resolved)
+        },
+    )?;
+
+    // the first line of the generated file is the include directive
+    let mut snippet_errors = HashMap::new();
+    for error in clang_errors {
+        if let Some(location) = &error.location {
+            if location.include_file_path.ends_with("1.cpp") && location.line >= 2 {
+                snippet_errors
+                    .entry(location.line as usize - 2)
+                    .or_insert(error.reason);
+            }
+        }
+    }
+
+    let data = parser.data;
+    let mut resolved_count = 0;
+    for (index, (source_id, function, class_path)) in requests.into_iter().enumerate() {
+        let result = match snippet_errors.remove(&index) {
+            Some(reason) => Err(err_msg(reason)),
+            None => results
+                .remove(&index)
+                .unwrap_or_else(|| Err(err_msg("clang didn't produce the typedef"))),
+        };
+        let description = format!(
+            "{} for {}",
+            function.path.to_cpp_pseudo_code(),
+            class_path.to_cpp_pseudo_code()
+        );
+        match result {
+            Ok(resolved) => {
+                trace!("resolved dependent function: {}", resolved.short_text());
+                data.add_cpp_item(Some(source_id), CppItem::Function(resolved))?;
+                resolved_count += 1;
+            }
+            Err(err) => {
+                debug!(
+                    "failed to resolve dependent function {}: {}",
+                    description, err
+                );
+                data.skipped.push(SkippedConstruct {
+                    description: format!("dependent function {}", description),
+                    reason: format!("{} (original error: {})", err, function.reason),
+                    location: None,
+                });
+            }
+        }
+    }
+    debug!("resolved dependent functions: {}", resolved_count);
+    Ok(())
+}
+
+/// Asks the compiler for size, alignment and type traits of all complete
+/// class and enum types and records the answers for the current library target.
+/// The answers decide which classes are allocated in place or wrapped
+/// as plain structs, and the generated `#[repr(C)]` types assert that
+/// their size and alignment match the recorded values.
+pub fn probe_type_properties(data: &mut ProcessorData<'_>) -> Result<()> {
+    const ENUM_PREFIX: &str = "ritual_probe_";
+    let types = data
+        .db
+        .cpp_items()
+        .filter_map(|item| item.item.as_type_ref())
+        .filter(|type1| type1.kind.is_class() || type1.kind.is_enum())
+        .filter(|type1| !CppType::Class(type1.path.clone()).is_or_contains_template_parameter())
+        .map(|type1| (type1.path.clone(), type1.kind.is_enum()))
+        .collect_vec();
+    if types.is_empty() {
+        return Ok(());
+    }
+
+    let mut code = "#include <type_traits>\n".to_string();
+    for (index, (path, is_enum)) in types.iter().enumerate() {
+        let path = path.to_cpp_code()?;
+        code.push_str(&format!(
+            "enum class {prefix}{index} : unsigned long long {{ \
+             size = sizeof({path}), alignment = alignof({path}), \
+             trivially_copyable = std::is_trivially_copyable<{path}>::value, \
+             polymorphic = std::is_polymorphic<{path}>::value, \
+             standard_layout = std::is_standard_layout<{path}>::value{signed} }};\n",
+            prefix = ENUM_PREFIX,
+            index = index,
+            path = path,
+            signed = if *is_enum {
+                format!(
+                    ", is_signed = std::is_signed<std::underlying_type<{}>::type>::value",
+                    path
+                )
+            } else {
+                String::new()
+            }
+        ));
+    }
+
+    let excluded = excluded_headers(data.db);
+    let mut clang_errors = Vec::new();
+    let mut results = run_clang(
+        &data.config,
+        &data.tmp_path(),
+        Some(code),
+        &excluded,
+        None,
+        Some(&mut clang_errors),
+        |translation_unit| {
+            let mut values = HashMap::new();
+            for entity in translation_unit.get_children() {
+                if entity.get_kind() != EntityKind::EnumDecl {
+                    continue;
+                }
+                let name = match entity.get_name() {
+                    Some(name) if name.starts_with(ENUM_PREFIX) => name,
+                    _ => continue,
+                };
+                let index = match usize::from_str(&name[ENUM_PREFIX.len()..]) {
+                    Ok(index) if index < types.len() => index,
+                    _ => continue,
+                };
+                let constants = entity
+                    .get_children()
+                    .into_iter()
+                    .filter(|child| child.get_kind() == EntityKind::EnumConstantDecl)
+                    .filter_map(|child| {
+                        Some((child.get_name()?, child.get_enum_constant_value()?.1))
+                    })
+                    .collect::<HashMap<_, _>>();
+                values.insert(index, constants);
+            }
+            Ok(values)
+        },
+    )?;
+
+    // the first two lines of the generated file are the include directives
+    let mut failed_lines = HashMap::new();
+    for error in clang_errors {
+        if let Some(location) = &error.location {
+            if location.include_file_path.ends_with("1.cpp") && location.line >= 3 {
+                failed_lines
+                    .entry(location.line as usize - 3)
+                    .or_insert(error.reason);
+            }
+        }
+    }
+
+    let env = data.config.current_library_target()?;
+    let mut probed_count = 0;
+    for (index, (path, is_enum)) in types.into_iter().enumerate() {
+        if let Some(reason) = failed_lines.remove(&index) {
+            trace!(
+                "failed to probe properties of {}: {}",
+                path.to_cpp_pseudo_code(),
+                reason
+            );
+            continue;
+        }
+        let constants = match results.remove(&index) {
+            Some(constants) => constants,
+            None => continue,
+        };
+        let value = |name: &str| constants.get(name).copied();
+        let is_underlying_type_signed = if is_enum {
+            match value("is_signed") {
+                Some(is_signed) => Some(is_signed != 0),
+                None => continue,
+            }
+        } else {
+            None
+        };
+        let properties = match (
+            value("size"),
+            value("alignment"),
+            value("trivially_copyable"),
+            value("polymorphic"),
+            value("standard_layout"),
+        ) {
+            (
+                Some(size),
+                Some(alignment),
+                Some(trivially_copyable),
+                Some(polymorphic),
+                Some(standard_layout),
+            ) => CppTypeProperties {
+                path,
+                env: env.clone(),
+                size: size as usize,
+                alignment: alignment as usize,
+                is_trivially_copyable: trivially_copyable != 0,
+                is_polymorphic: polymorphic != 0,
+                is_standard_layout: standard_layout != 0,
+                is_underlying_type_signed,
+            },
+            _ => continue,
+        };
+        data.db.set_cpp_type_properties(properties);
+        probed_count += 1;
+    }
+    debug!("probed properties of types: {}", probed_count);
+    Ok(())
+}
+
 impl CppParser<'_, '_> {
     fn add_output(
         &mut self,
@@ -406,7 +1608,18 @@ impl CppParser<'_, '_> {
         origin_location: CppOriginLocation,
         item: CppItem,
     ) -> Result<()> {
+        if let Some(filter) = &self.path_filter {
+            if item
+                .path()
+                .map_or(true, |path| path.to_templateless_string() != *filter)
+            {
+                return Ok(());
+            }
+        }
         if let Some(id) = self.data.add_cpp_item(self.source_id.clone(), item)? {
+            self.data
+                .db
+                .set_cpp_origin_location(&id, origin_location.clone())?;
             self.output.0.push(CppParserOutputItem {
                 include_file,
                 origin_location,
@@ -459,6 +1672,7 @@ impl CppParser<'_, '_> {
                 if declaration.get_kind() == EntityKind::ClassDecl
                     || declaration.get_kind() == EntityKind::ClassTemplate
                     || declaration.get_kind() == EntityKind::StructDecl
+                    || declaration.get_kind() == EntityKind::UnionDecl
                 {
                     if declaration
                         .get_accessibility()
@@ -586,7 +1800,7 @@ impl CppParser<'_, '_> {
                 CppTypeDeclarationKind::Enum { .. } => {
                     return Ok(CppType::Enum { path });
                 }
-                CppTypeDeclarationKind::Class { .. } => {
+                CppTypeDeclarationKind::Class { .. } | CppTypeDeclarationKind::Union => {
                     return Ok(CppType::Class(path));
                 }
             }
@@ -669,6 +1883,9 @@ impl CppParser<'_, '_> {
                 if name.starts_with("const ") {
                     name = name[6..].trim().to_string();
                 }
+                if let Some(r) = self.parse_handle_typedef(&name, type1)? {
+                    return Ok(r);
+                }
                 if let Some(r) = self.parse_special_typedef(&name) {
                     return Ok(r);
                 }
@@ -732,7 +1949,7 @@ impl CppParser<'_, '_> {
                             );
                         }
                     }
-                    let template_arguments = match type1.get_template_argument_types() {
+                    let template_arguments = match self.clang.template_argument_types(type1)? {
                         None => None,
                         Some(arg_types) => {
                             let mut r = Vec::new();
@@ -840,6 +2057,29 @@ impl CppParser<'_, '_> {
                     None => bail!("can't get pointee type"),
                 }
             }
+            TypeKind::ConstantArray => {
+                let element_type = type1
+                    .get_element_type()
+                    .ok_or_else(|| err_msg("can't get array element type"))?;
+                let size = type1
+                    .get_size()
+                    .ok_or_else(|| err_msg("can't get array size"))?;
+                Ok(CppType::Array {
+                    element_type: Box::new(self.parse_type(element_type, context_template_args)?),
+                    size,
+                })
+            }
+            TypeKind::IncompleteArray => {
+                // arrays of unknown size can only be used as pointers
+                let element_type = type1
+                    .get_element_type()
+                    .ok_or_else(|| err_msg("can't get array element type"))?;
+                Ok(CppType::PointerLike {
+                    kind: CppPointerLikeTypeKind::Pointer,
+                    is_const: element_type.is_const_qualified(),
+                    target: Box::new(self.parse_type(element_type, context_template_args)?),
+                })
+            }
             TypeKind::Elaborated => {
                 self.parse_type(type1.get_canonical_type(), context_template_args)
             }
@@ -883,6 +2123,36 @@ impl CppParser<'_, '_> {
         }
     }
 
+    /// Checks if the typedef `name` is configured as a handle type
+    /// (see `Config::add_handle_type`). Handles are kept as fixed-size integers
+    /// with the typedef's path so that the Rust generator can replace them
+    /// with newtypes.
+    fn parse_handle_typedef(&self, name: &str, type1: Type<'_>) -> Result<Option<CppType>> {
+        let is_handle = self
+            .data
+            .config
+            .handle_types()
+            .iter()
+            .any(|handle| handle.typedef.to_cpp_pseudo_code() == name);
+        if !is_handle {
+            return Ok(None);
+        }
+        let canonical = type1.get_canonical_type();
+        let is_signed = match self.parse_type(canonical, &[])? {
+            CppType::BuiltInNumeric(numeric) if numeric.is_signed_integer() => true,
+            CppType::BuiltInNumeric(numeric) if numeric.is_unsigned_integer() => false,
+            _ => bail!("handle type is not an integer: {}", name),
+        };
+        let size = canonical
+            .get_sizeof()
+            .map_err(|err| format_err!("failed to get size of {}: {}", name, err))?;
+        Ok(Some(CppType::SpecificNumeric(CppSpecificNumericType {
+            path: CppPath::from_good_str(name),
+            bits: size * 8,
+            kind: CppSpecificNumericTypeKind::Integer { is_signed },
+        })))
+    }
+
     /// Checks if the typedef `name` has a special meaning for the parser.
     fn parse_special_typedef(&self, name: &str) -> Option<CppType> {
         match name {
@@ -942,7 +2212,13 @@ impl CppParser<'_, '_> {
                     kind: CppSpecificNumericTypeKind::Integer { is_signed: false },
                 }))
             }
-            "qintptr" | "qptrdiff" | "QList::difference_type" => {
+            // qreal is double unless Qt is configured otherwise
+            "qreal" => Some(CppType::SpecificNumeric(CppSpecificNumericType {
+                path: CppPath::from_good_str(name),
+                bits: 64,
+                kind: CppSpecificNumericTypeKind::FloatingPoint,
+            })),
+            "qintptr" | "qptrdiff" | "ptrdiff_t" | "std::ptrdiff_t" | "QList::difference_type" => {
                 Some(CppType::PointerSizedInteger {
                     path: CppPath::from_good_str(name),
                     is_signed: true,
@@ -966,21 +2242,24 @@ impl CppParser<'_, '_> {
     #[allow(clippy::cognitive_complexity)]
     fn parse_function(&mut self, entity: Entity<'_>) -> Result<()> {
         let class_name = match entity.get_semantic_parent() {
+            Some(p) if specialization_kind(p).is_some() => {
+                Some(self.get_specialization_path(p).with_context(|_| {
+                    "failed to get path of the template specialization containing the function"
+                })?)
+            }
             Some(p) => match p.get_kind() {
-                EntityKind::ClassDecl | EntityKind::ClassTemplate | EntityKind::StructDecl => {
-                    match get_path(p) {
-                        Ok(class_name) => Some(class_name),
-                        Err(msg) => {
-                            bail!(
-                                "function parent is a class but it doesn't have a name: {}",
-                                msg
-                            );
-                        }
+                EntityKind::ClassDecl
+                | EntityKind::ClassTemplate
+                | EntityKind::StructDecl
+                | EntityKind::UnionDecl => match get_path(p) {
+                    Ok(class_name) => Some(class_name),
+                    Err(msg) => {
+                        bail!(
+                            "function parent is a class but it doesn't have a name: {}",
+                            msg
+                        );
                     }
-                }
-                EntityKind::ClassTemplatePartialSpecialization => {
-                    bail!("this function is part of a template partial specialization");
-                }
+                },
                 _ => None,
             },
             None => None,
@@ -1000,25 +2279,16 @@ impl CppParser<'_, '_> {
             Ok(x) => x,
             Err(msg) => {
                 trace!("return type: {:?}", return_type);
-                bail!(
+                let reason = format!(
                     "Can't parse return type: {}: {}",
                     return_type.get_display_name(),
                     msg
                 );
+                return self.add_dependent_function(entity, class_name.as_ref(), reason);
             }
         };
         let mut arguments = Vec::new();
-        let argument_entities = if entity.get_kind() == EntityKind::FunctionTemplate {
-            entity
-                .get_children()
-                .into_iter()
-                .filter(|c| c.get_kind() == EntityKind::ParmDecl)
-                .collect()
-        } else if let Some(args) = entity.get_arguments() {
-            args
-        } else {
-            bail!("failed to get function arguments: {:?}", entity);
-        };
+        let argument_entities = get_argument_entities(entity)?;
 
         let mut is_signal = false;
         for (argument_number, argument_entity) in argument_entities.into_iter().enumerate() {
@@ -1035,15 +2305,18 @@ impl CppParser<'_, '_> {
                 is_signal = true;
                 continue;
             }
-            let argument_type = self
-                .parse_type(clang_type, &context_template_args)
-                .with_context(|_| {
-                    format!(
-                        "Can't parse argument type: {}: {}",
+            let argument_type = match self.parse_type(clang_type, &context_template_args) {
+                Ok(x) => x,
+                Err(msg) => {
+                    let reason = format!(
+                        "Can't parse argument type: {}: {}: {}",
                         name,
-                        clang_type.get_display_name()
-                    )
-                })?;
+                        clang_type.get_display_name(),
+                        msg
+                    );
+                    return self.add_dependent_function(entity, class_name.as_ref(), reason);
+                }
+            };
             let mut has_default_value = false;
             for token in argument_entity
                 .get_range()
@@ -1218,6 +2491,12 @@ impl CppParser<'_, '_> {
             }
             Some(token_strings.join(" "))
         };
+        let is_explicit = entity.get_kind() == EntityKind::Constructor
+            && declaration_code.as_ref().map_or(false, |code| {
+                code.split('(').next().map_or(false, |head| {
+                    head.split_whitespace().any(|t| t == "explicit")
+                })
+            });
 
         let function = CppFunction {
             path: name_with_namespace,
@@ -1241,6 +2520,8 @@ impl CppParser<'_, '_> {
                     // not all signals are detected here! see CppData::detect_signals_and_slots
                     is_signal,
                     is_slot: false,
+                    is_explicit,
+                    trivial_field_access: trivial_field_access(entity),
                 })
             } else {
                 None
@@ -1261,6 +2542,157 @@ impl CppParser<'_, '_> {
         Ok(())
     }
 
+    /// Records function `entity` with a signature that couldn't be parsed
+    /// because of `reason` as a dependent function, so that it can be resolved
+    /// for concrete instantiations of its class template later.
+    /// Returns `reason` as an error if `entity` is not a public member function
+    /// of a class template.
+    fn add_dependent_function(
+        &mut self,
+        entity: Entity<'_>,
+        class_path: Option<&CppPath>,
+        reason: String,
+    ) -> Result<()> {
+        let is_class_template_member = entity
+            .get_semantic_parent()
+            .map_or(false, |p| p.get_kind() == EntityKind::ClassTemplate);
+        let is_method = match entity.get_kind() {
+            EntityKind::Method | EntityKind::FunctionTemplate => true,
+            _ => false,
+        };
+        let is_public = entity.get_accessibility() == Some(Accessibility::Public);
+        let name = entity
+            .get_name()
+            .ok_or_else(|| err_msg("failed to get function name"))?;
+        let class_path = match class_path {
+            Some(path)
+                if is_class_template_member
+                    && is_method
+                    && is_public
+                    && !name.starts_with("operator") =>
+            {
+                path
+            }
+            _ => bail!("{}", reason),
+        };
+        let name = name.split('<').next().unwrap_or_default();
+        let path = class_path.join(CppPathItem::from_good_str(name));
+        if let Some(filter) = &self.path_filter {
+            if path.to_templateless_string() != *filter {
+                return Ok(());
+            }
+        }
+        let class_id = self
+            .data
+            .db
+            .cpp_items()
+            .find(|item| {
+                item.item
+                    .as_type_ref()
+                    .map_or(false, |t| &t.path == class_path)
+            })
+            .map(|item| item.id)
+            .ok_or_else(|| format_err!("{} (class template is not available)", reason))?;
+        let argument_names = get_argument_entities(entity)?
+            .into_iter()
+            .enumerate()
+            .map(|(index, argument)| {
+                argument
+                    .get_name()
+                    .unwrap_or_else(|| format!("arg{}", index + 1))
+            })
+            .collect();
+        let function = CppDependentFunction {
+            path,
+            is_template: entity.get_kind() == EntityKind::FunctionTemplate,
+            is_const: entity.is_const_method(),
+            is_static: entity.is_static_method(),
+            argument_names,
+            reason,
+        };
+        debug!(
+            "dependent function: {}: {}",
+            function.path.to_cpp_pseudo_code(),
+            function.reason
+        );
+        self.data
+            .db
+            .add_cpp_dependent_function_item(class_id, function);
+        Ok(())
+    }
+
+    /// Creates a function from the signature of `dependent_function`
+    /// resolved by clang for `class_path`. `type1` is the type of the address
+    /// of the function.
+    fn parse_resolved_function(
+        &self,
+        dependent_function: &CppDependentFunction,
+        class_path: &CppPath,
+        type1: Type<'_>,
+    ) -> Result<CppFunction> {
+        let canonical = type1.get_canonical_type();
+        let function_type = match canonical.get_kind() {
+            TypeKind::MemberPointer | TypeKind::Pointer => canonical
+                .get_pointee_type()
+                .ok_or_else(|| err_msg("failed to get pointee type"))?,
+            _ => bail!(
+                "unexpected type of function address: {}",
+                canonical.get_display_name()
+            ),
+        };
+        if function_type.get_kind() != TypeKind::FunctionPrototype {
+            bail!(
+                "unexpected function type: {}",
+                function_type.get_display_name()
+            );
+        }
+        let return_type = function_type
+            .get_result_type()
+            .ok_or_else(|| err_msg("failed to get function return type"))?;
+        let argument_types = function_type
+            .get_argument_types()
+            .ok_or_else(|| err_msg("failed to get function argument types"))?;
+        if argument_types.len() != dependent_function.argument_names.len() {
+            bail!(
+                "argument count mismatch: {}",
+                function_type.get_display_name()
+            );
+        }
+        let arguments = argument_types
+            .into_iter()
+            .zip(&dependent_function.argument_names)
+            .map_if_ok(|(argument_type, name)| -> Result<_> {
+                Ok(CppFunctionArgument {
+                    name: name.clone(),
+                    argument_type: self.parse_type(argument_type, &[])?,
+                    has_default_value: false,
+                })
+            })?;
+        Ok(CppFunction {
+            path: class_path.join(CppPathItem::from_good_str(
+                &dependent_function.path.last().name,
+            )),
+            member: Some(CppFunctionMemberData {
+                kind: CppFunctionKind::Regular,
+                is_virtual: false,
+                is_pure_virtual: false,
+                is_const: dependent_function.is_const,
+                is_static: dependent_function.is_static,
+                visibility: CppVisibility::Public,
+                is_signal: false,
+                is_slot: false,
+                is_explicit: false,
+                trivial_field_access: None,
+            }),
+            operator: None,
+            return_type: self.parse_type(return_type, &[])?,
+            arguments,
+            allows_variadic_arguments: function_type.is_variadic(),
+            cast: None,
+            declaration_code: None,
+        })
+    }
+
     /// Parses an enum `entity`.
     fn parse_enum(&mut self, entity: Entity<'_>) -> Result<()> {
         let include_file = self.entity_include_file(entity).with_context(|_| {
@@ -1275,10 +2707,16 @@ impl CppParser<'_, '_> {
             include_file.clone(),
             get_origin_location(entity)?,
             CppItem::Type(CppTypeDeclaration {
-                kind: CppTypeDeclarationKind::Enum,
+                kind: CppTypeDeclarationKind::Enum {
+                    is_scoped: entity.is_scoped(),
+                },
                 path: enum_name.clone(),
+                specialization: None,
+                is_trivially_copyable: false,
+                is_immovable: false,
             }),
         )?;
+        let mut first_paths = HashMap::new();
         for child in entity.get_children() {
             if child.get_kind() == EntityKind::EnumConstantDecl {
                 let val = child
@@ -1288,12 +2726,21 @@ impl CppParser<'_, '_> {
                 let value_name = child
                     .get_name()
                     .ok_or_else(|| err_msg("failed to get name of enum variant"))?;
+                let path = enum_name.join(CppPathItem::from_good_str(&value_name));
+                let alias_of = match first_paths.entry(val.0) {
+                    Entry::Occupied(entry) => Some(entry.get().clone()),
+                    Entry::Vacant(entry) => {
+                        entry.insert(path.clone());
+                        None
+                    }
+                };
                 self.add_output(
                     include_file.clone(),
                     get_origin_location(child)?,
                     CppItem::EnumValue(CppEnumValue {
-                        path: enum_name.join(CppPathItem::from_good_str(&value_name)),
+                        path,
                         value: val.0,
+                        alias_of,
                     }),
                 )?;
             }
@@ -1315,6 +2762,16 @@ impl CppParser<'_, '_> {
         let field_type = self
             .parse_type(field_clang_type, &get_context_template_args(entity))
             .with_context(|_| err_msg("failed to parse field type"))?;
+        let offset = if self.clang.is_bit_field(entity).unwrap_or(true) {
+            None
+        } else {
+            entity
+                .get_semantic_parent()
+                .and_then(|parent| parent.get_type())
+                .and_then(|parent_type| self.clang.field_offset(parent_type, &field_name))
+                .filter(|bits| bits % 8 == 0)
+                .map(|bits| bits / 8)
+        };
         self.add_output(
             include_file,
             get_origin_location(entity)?,
@@ -1326,13 +2783,59 @@ impl CppParser<'_, '_> {
                     Accessibility::Protected => CppVisibility::Protected,
                     Accessibility::Private => CppVisibility::Private,
                 },
-                is_static: entity.get_kind() == EntityKind::VarDecl,
+                offset,
             }),
         )?;
 
         Ok(())
     }
 
+    /// Parses a global variable or a static data member `entity`.
+    fn parse_variable(&mut self, entity: Entity<'_>) -> Result<()> {
+        if entity.get_linkage() == Some(Linkage::Internal) {
+            bail!("variable has internal linkage");
+        }
+        let parent = entity
+            .get_semantic_parent()
+            .ok_or_else(|| err_msg("failed to get variable parent"))?;
+        let is_static_member = match parent.get_kind() {
+            EntityKind::TranslationUnit | EntityKind::Namespace => false,
+            EntityKind::ClassDecl | EntityKind::StructDecl | EntityKind::UnionDecl => true,
+            _ => bail!("unsupported variable parent: {:?}", parent.get_kind()),
+        };
+        let include_file = self
+            .entity_include_file(entity)
+            .with_context(|_| err_msg("Origin of variable is unknown"))?;
+        let clang_type = entity
+            .get_type()
+            .ok_or_else(|| err_msg("failed to get variable type"))?;
+        let variable_type = self
+            .parse_type(clang_type, &get_context_template_args(entity))
+            .with_context(|_| err_msg("failed to parse variable type"))?;
+        let has_in_class_initializer = is_static_member
+            && entity
+                .get_children()
+                .iter()
+                .any(|child| child.is_expression());
+        self.add_output(
+            include_file,
+            get_origin_location(entity)?,
+            CppItem::Variable(CppVariable {
+                path: get_path(entity)?,
+                variable_type,
+                is_const: clang_type.is_const_qualified(),
+                is_static_member,
+                has_in_class_initializer,
+                visibility: match entity.get_accessibility().unwrap_or(Accessibility::Public) {
+                    Accessibility::Public => CppVisibility::Public,
+                    Accessibility::Protected => CppVisibility::Protected,
+                    Accessibility::Private => CppVisibility::Private,
+                },
+            }),
+        )?;
+        Ok(())
+    }
+
     // we pass parent manually because both lexical and semantic parent are missing for these
     // entities for some reason
     fn parse_class_base(
@@ -1350,7 +2853,7 @@ impl CppParser<'_, '_> {
         if let CppType::Class(base_type) = &base_type {
             self.add_output(
                 self.entity_include_file(entity)?,
-                get_origin_location(entity).unwrap(),
+                get_origin_location(entity)?,
                 CppItem::ClassBase(CppBaseSpecifier {
                     base_class_type: base_type.clone(),
                     is_virtual: entity.is_virtual_base(),
@@ -1360,7 +2863,7 @@ impl CppParser<'_, '_> {
                         Accessibility::Private => CppVisibility::Private,
                     },
                     base_index,
-                    derived_class_type: get_path(parent)?,
+                    derived_class_type: self.get_class_path(parent)?,
                 }),
             )?;
         } else {
@@ -1369,6 +2872,37 @@ impl CppParser<'_, '_> {
         Ok(())
     }
 
+    /// Returns path of a class template specialization `entity` with the specialized
+    /// template arguments (e.g. `std::hash<QString>` or `QList<T*>`).
+    fn get_specialization_path(&self, entity: Entity<'_>) -> Result<CppPath> {
+        let display_name = entity
+            .get_display_name()
+            .ok_or_else(|| err_msg("template specialization has no name"))?;
+        let (_, args) = parse_template_args(&display_name).ok_or_else(|| {
+            format_err!(
+                "failed to parse template specialization arguments: {}",
+                display_name
+            )
+        })?;
+        let context_template_args = get_context_template_args(entity);
+        let arg_types = args.iter().map_if_ok(|arg| {
+            self.parse_unexposed_type(None, Some(arg.trim().to_string()), &context_template_args)
+        })?;
+        let mut path = get_path(entity)?;
+        path.last_mut().template_arguments = Some(arg_types);
+        Ok(path)
+    }
+
+    /// Returns path of a class `entity`, including specialized template arguments
+    /// if it's a template specialization.
+    fn get_class_path(&self, entity: Entity<'_>) -> Result<CppPath> {
+        if specialization_kind(entity).is_some() {
+            self.get_specialization_path(entity)
+        } else {
+            get_path(entity)
+        }
+    }
+
     /// Parses a class or a struct `entity`.
     fn parse_class(&mut self, entity: Entity<'_>) -> Result<()> {
         let include_file = self.entity_include_file(entity).with_context(|_| {
@@ -1378,7 +2912,8 @@ impl CppParser<'_, '_> {
                 entity
             )
         })?;
-        let full_name = get_path(entity)?;
+        let specialization = specialization_kind(entity);
+        let full_name = self.get_class_path(entity)?;
         let template_arguments = get_template_arguments(entity);
         if entity.get_kind() == EntityKind::ClassTemplate {
             if entity
@@ -1393,13 +2928,12 @@ impl CppParser<'_, '_> {
                 dump_entity(entity, 0);
                 bail!("missing template arguments");
             }
-        } else if template_arguments.is_some() {
+        } else if template_arguments.is_some() && specialization.is_none() {
             bail!("unexpected template arguments");
         }
         let mut current_base_index = 0;
         for child in entity.get_children() {
-            if child.get_kind() == EntityKind::FieldDecl || child.get_kind() == EntityKind::VarDecl
-            {
+            if child.get_kind() == EntityKind::FieldDecl {
                 if let Err(err) = self.parse_class_field(child, &full_name) {
                     debug!(
                         "failed to parse class field: {}: {}",
@@ -1425,10 +2959,18 @@ impl CppParser<'_, '_> {
         }
         self.add_output(
             include_file,
-            get_origin_location(entity).unwrap(),
+            get_origin_location(entity)?,
             CppItem::Type(CppTypeDeclaration {
-                kind: CppTypeDeclarationKind::Class,
+                kind: if entity.get_kind() == EntityKind::UnionDecl {
+                    CppTypeDeclarationKind::Union
+                } else {
+                    CppTypeDeclarationKind::Class
+                },
                 path: full_name,
+                specialization,
+                is_trivially_copyable: entity.get_kind() != EntityKind::UnionDecl
+                    && is_trivially_copyable(entity),
+                is_immovable: is_immovable(entity),
             }),
         )?;
         Ok(())
@@ -1520,13 +3062,16 @@ impl CppParser<'_, '_> {
                     }
                 }
             }
-            EntityKind::ClassDecl | EntityKind::ClassTemplate | EntityKind::StructDecl => {
+            EntityKind::ClassDecl
+            | EntityKind::ClassTemplate
+            | EntityKind::ClassTemplatePartialSpecialization
+            | EntityKind::StructDecl
+            | EntityKind::UnionDecl => {
                 if entity.get_accessibility() == Some(Accessibility::Private) {
                     return Ok(()); // skipping private stuff
                 }
                 let ok = entity.get_name().is_some() && // not an anonymous struct
-                    entity.is_definition() && // not a forward declaration
-                    entity.get_template().is_none(); // not a template specialization
+                    entity.is_definition(); // not a forward declaration
                 if ok {
                     if let Err(error) = self.parse_class(entity) {
                         debug!(
@@ -1542,7 +3087,7 @@ impl CppParser<'_, '_> {
                 Ok(path) => {
                     self.add_output(
                         self.entity_include_file(entity)?,
-                        get_origin_location(entity).unwrap(),
+                        get_origin_location(entity)?,
                         CppItem::Namespace(CppNamespace { path }),
                     )?;
                 }
@@ -1554,11 +3099,17 @@ impl CppParser<'_, '_> {
             EntityKind::TranslationUnit
             | EntityKind::Namespace
             | EntityKind::StructDecl
+            | EntityKind::UnionDecl
             | EntityKind::ClassDecl
             | EntityKind::UnexposedDecl
             | EntityKind::ClassTemplate => {
                 for c in entity.get_children() {
-                    self.parse_types(c)?;
+                    let result = self.parse_types(c);
+                    self.data.skip_if_lenient(
+                        result,
+                        || get_full_name_display(c),
+                        get_origin_location(c).ok(),
+                    )?;
                 }
             }
             _ => {}
@@ -1588,10 +3139,34 @@ impl CppParser<'_, '_> {
                     trace!("entity: {:?}", entity);
                 }
             }
+            EntityKind::VarDecl => {
+                if let Err(error) = self.parse_variable(entity) {
+                    debug!(
+                        "failed to parse variable: {}: {}",
+                        get_full_name_display(entity),
+                        error
+                    );
+                    trace!("entity: {:?}", entity);
+                }
+            }
             EntityKind::StructDecl
+            | EntityKind::UnionDecl
             | EntityKind::ClassDecl
             | EntityKind::ClassTemplate
             | EntityKind::ClassTemplatePartialSpecialization => {
+                if specialization_kind(entity).is_some() {
+                    // members of specializations are parsed separately from
+                    // members of the primary template
+                    for c in entity.get_children() {
+                        let result = self.parse_functions(c);
+                        self.data.skip_if_lenient(
+                            result,
+                            || get_full_name_display(c),
+                            get_origin_location(c).ok(),
+                        )?;
+                    }
+                    return Ok(());
+                }
                 if let Some(name) = entity.get_display_name() {
                     if let Ok(parent_type) = self.parse_unexposed_type(
                         None,
@@ -1622,11 +3197,17 @@ impl CppParser<'_, '_> {
             EntityKind::TranslationUnit
             | EntityKind::Namespace
             | EntityKind::StructDecl
+            | EntityKind::UnionDecl
             | EntityKind::ClassDecl
             | EntityKind::UnexposedDecl
             | EntityKind::ClassTemplate => {
                 for c in entity.get_children() {
-                    self.parse_functions(c)?;
+                    let result = self.parse_functions(c);
+                    self.data.skip_if_lenient(
+                        result,
+                        || get_full_name_display(c),
+                        get_origin_location(c).ok(),
+                    )?;
                 }
             }
             _ => {}
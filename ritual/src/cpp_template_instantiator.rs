@@ -1,4 +1,4 @@
-use crate::cpp_data::{CppItem, CppPath, CppPathItem, CppTypeDeclaration};
+use crate::cpp_data::{CppItem, CppPath, CppPathItem, CppSpecializationKind, CppTypeDeclaration};
 use crate::cpp_function::{CppFunction, CppFunctionArgument, CppOperator};
 use crate::cpp_type::CppType;
 use crate::database::{DatabaseClient, ItemWithSource};
@@ -6,6 +6,7 @@ use crate::processor::ProcessorData;
 use log::{debug, trace};
 use ritual_common::errors::{bail, err_msg, Result};
 use ritual_common::utils::MapIfOk;
+use std::collections::HashMap;
 
 /// Returns true if `type1` is a known template instantiation.
 fn check_template_type(data: &ProcessorData<'_>, type1: &CppType) -> Result<()> {
@@ -103,56 +104,175 @@ pub fn instantiate_function(
 // TODO: instantiations of QObject::findChild and QObject::findChildren should be available
 
 #[derive(Debug)]
-struct Substitution<'a> {
+struct Substitution {
     nested_level: usize,
-    arguments: &'a [CppType],
+    arguments: Vec<CppType>,
 }
 
-fn find_suitable_template_arguments<'a>(
+/// Returns true if `type1` and `other` are declarations of the same template
+/// (including specializations and instantiations).
+pub fn is_same_template(type1: &CppPath, other: &CppPath) -> bool {
+    type1.parent_parts().ok() == other.parent_parts().ok() && type1.last().name == other.last().name
+}
+
+/// Returns true if `path` is a template instantiation with concrete arguments.
+pub fn is_concrete_instantiation(path: &CppPath) -> bool {
+    path.last()
+        .template_arguments
+        .as_ref()
+        .map_or(false, |args| {
+            !args.iter().all(CppType::is_or_contains_template_parameter)
+        })
+}
+
+/// Returns the nested level of template parameters of a partial specialization `path`.
+fn partial_specialization_level(path: &CppPath) -> Option<usize> {
+    fn max_level(type1: &CppType) -> Option<usize> {
+        match type1 {
+            CppType::TemplateParameter(param) => Some(param.nested_level),
+            CppType::Class(path) => path
+                .items()
+                .iter()
+                .flat_map(|item| item.template_arguments.iter().flatten())
+                .filter_map(max_level)
+                .max(),
+            CppType::PointerLike { target, .. } => max_level(target),
+            CppType::Array { element_type, .. } => max_level(element_type),
+            _ => None,
+        }
+    }
+    path.last()
+        .template_arguments
+        .as_ref()?
+        .iter()
+        .filter_map(max_level)
+        .max()
+}
+
+/// Returns template arguments of the partial specialization `pattern` that
+/// produce the concrete type `path`, or `None` if `path` doesn't match `pattern`.
+fn match_partial_specialization(pattern: &CppPath, path: &CppPath) -> Option<Substitution> {
+    let nested_level = partial_specialization_level(pattern)?;
+    let mut arguments = Vec::new();
+    if !pattern.match_template(path, nested_level, &mut arguments) {
+        return None;
+    }
+    let arguments = arguments.into_iter().collect::<Option<Vec<_>>>()?;
+    Some(Substitution {
+        nested_level,
+        arguments,
+    })
+}
+
+/// Types of the current crate grouped by their template (see `is_same_template`),
+/// so that instantiations and specializations of a template can be found
+/// without scanning the whole database.
+struct TemplateIndex<'a> {
+    types: HashMap<(Option<Vec<CppPathItem>>, String), Vec<&'a CppTypeDeclaration>>,
+}
+
+impl<'a> TemplateIndex<'a> {
+    fn new(db: &'a DatabaseClient) -> Self {
+        let mut types = HashMap::new();
+        for type1 in db.cpp_items().filter_map(|item| item.item.as_type_ref()) {
+            types
+                .entry(Self::key(&type1.path))
+                .or_insert_with(Vec::new)
+                .push(type1);
+        }
+        TemplateIndex { types }
+    }
+
+    fn key(path: &CppPath) -> (Option<Vec<CppPathItem>>, String) {
+        let parent = path.parent_parts().ok().map(<[CppPathItem]>::to_vec);
+        (parent, path.last().name.clone())
+    }
+
+    /// Returns all types with the same template as `path`.
+    fn same_template(&self, path: &CppPath) -> &[&'a CppTypeDeclaration] {
+        self.types
+            .get(&Self::key(path))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Returns the specialization declared for the concrete type `path`, if any.
+    fn find_specialization(&self, path: &CppPath) -> Option<&'a CppTypeDeclaration> {
+        self.same_template(path)
+            .iter()
+            .find(|type1| match type1.specialization {
+                Some(CppSpecializationKind::Explicit) => &type1.path == path,
+                Some(CppSpecializationKind::Partial) => {
+                    match_partial_specialization(&type1.path, path).is_some()
+                }
+                None => false,
+            })
+            .copied()
+    }
+}
+
+fn find_suitable_template_arguments(
     path: &CppPath,
-    db: &'a DatabaseClient,
-) -> Result<Vec<Substitution<'a>>> {
+    index: &TemplateIndex<'_>,
+) -> Result<Vec<Substitution>> {
     let mut current_path = path.clone();
     let mut result = Vec::new();
     loop {
         if let Some(template_arguments) = &current_path.last().template_arguments {
             assert!(!template_arguments.is_empty());
-            if template_arguments.iter().all(|t| t.is_template_parameter()) {
-                let items =
-                    db.cpp_items()
-                        .filter_map(|item| item.item.as_type_ref())
-                        .filter(|type1| {
-                            type1.path.parent_parts().ok() == current_path.parent_parts().ok()
-                                && type1.path.last().name == current_path.last().name
-                                && type1.path.last().template_arguments.as_ref().map_or(
-                                    false,
-                                    |args| {
-                                        !args.iter().all(CppType::is_or_contains_template_parameter)
-                                    },
-                                )
-                        })
-                        .map_if_ok(|type1| {
-                            let nested_level =
-                                if let CppType::TemplateParameter(param) = &template_arguments[0] {
-                                    param.nested_level
-                                } else {
-                                    bail!("only template parameters can be here");
-                                };
+            let is_partial_specialization =
+                index.same_template(&current_path).iter().any(|type1| {
+                    type1.path == current_path
+                        && type1.specialization == Some(CppSpecializationKind::Partial)
+                });
+            if is_partial_specialization {
+                // members of the partial specialization are used for
+                // matching concrete types
+                let items = index
+                    .same_template(&current_path)
+                    .iter()
+                    .filter(|type1| {
+                        is_concrete_instantiation(&type1.path) && type1.specialization.is_none()
+                    })
+                    .filter(|type1| {
+                        index.find_specialization(&type1.path).map(|s| &s.path)
+                            == Some(&current_path)
+                    })
+                    .filter_map(|type1| match_partial_specialization(&current_path, &type1.path));
+                result.extend(items);
+            } else if template_arguments.iter().all(|t| t.is_template_parameter()) {
+                let items = index
+                    .same_template(&current_path)
+                    .iter()
+                    .filter(|type1| {
+                        is_concrete_instantiation(&type1.path)
+                            && type1.specialization.is_none()
+                            // specializations have their own members
+                            && index.find_specialization(&type1.path).is_none()
+                    })
+                    .map_if_ok(|type1| {
+                        let nested_level =
+                            if let CppType::TemplateParameter(param) = &template_arguments[0] {
+                                param.nested_level
+                            } else {
+                                bail!("only template parameters can be here");
+                            };
 
-                            let arguments = type1
+                        let arguments =
+                            type1
                                 .path
                                 .last()
                                 .template_arguments
-                                .as_ref()
+                                .clone()
                                 .ok_or_else(|| {
                                     err_msg("template instantiation must have template arguments")
                                 })?;
 
-                            Ok(Substitution {
-                                nested_level,
-                                arguments,
-                            })
-                        })?;
+                        Ok(Substitution {
+                            nested_level,
+                            arguments,
+                        })
+                    })?;
                 result.extend(items);
             }
         }
@@ -167,13 +287,14 @@ fn find_suitable_template_arguments<'a>(
 fn instantiate_types(data: &mut ProcessorData<'_>) -> Result<()> {
     loop {
         let mut new_types = Vec::<ItemWithSource<_>>::new();
+        let index = TemplateIndex::new(data.db);
         for type1 in data
             .db
             .all_cpp_items()
             .filter_map(|item| item.filter_map(|item| item.as_type_ref()))
         {
             trace!("class: {}", type1.item.path.to_cpp_pseudo_code());
-            for substitution in find_suitable_template_arguments(&type1.item.path, data.db)? {
+            for substitution in find_suitable_template_arguments(&type1.item.path, &index)? {
                 trace!("found template instantiation: {:?}", substitution);
 
                 let new_type = CppTypeDeclaration {
@@ -181,7 +302,10 @@ fn instantiate_types(data: &mut ProcessorData<'_>) -> Result<()> {
                     path: type1
                         .item
                         .path
-                        .instantiate(substitution.nested_level, substitution.arguments)?,
+                        .instantiate(substitution.nested_level, &substitution.arguments)?,
+                    specialization: None,
+                    is_trivially_copyable: type1.item.is_trivially_copyable,
+                    is_immovable: type1.item.is_immovable,
                 };
 
                 if data
@@ -210,6 +334,7 @@ fn instantiate_types(data: &mut ProcessorData<'_>) -> Result<()> {
 
 fn instantiate_functions(data: &mut ProcessorData<'_>) -> Result<()> {
     let mut new_methods = Vec::new();
+    let index = TemplateIndex::new(data.db);
     for item in data.db.all_cpp_items() {
         let function = if let Some(f) = item.item.as_function_ref() {
             f
@@ -227,14 +352,14 @@ fn instantiate_functions(data: &mut ProcessorData<'_>) -> Result<()> {
                 _ => continue,
             };
 
-            for substitution in find_suitable_template_arguments(path, data.db)? {
+            for substitution in find_suitable_template_arguments(path, &index)? {
                 trace!("method: {}", function.short_text());
                 trace!("found template instantiation: {:?}", substitution);
 
                 match instantiate_function(
                     function,
                     substitution.nested_level,
-                    substitution.arguments,
+                    &substitution.arguments,
                 ) {
                     Ok(method) => {
                         let mut ok = true;
@@ -261,7 +386,7 @@ fn instantiate_functions(data: &mut ProcessorData<'_>) -> Result<()> {
                             }
                         }
                     }
-                    Err(msg) => trace!("failed: {}", msg),
+                    Err(msg) => trace!("failed {}: {}", data.db.item_context(&item.id), msg),
                 }
             }
         }
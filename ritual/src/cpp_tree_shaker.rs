@@ -0,0 +1,139 @@
+//! Selects C++ items that are required by the entry points
+//! (see `Config::add_entry_point`).
+
+use crate::cpp_checker::type_paths;
+use crate::cpp_data::{CppItem, CppPath};
+use crate::database::{DatabaseClient, ItemId};
+use itertools::Itertools;
+use log::info;
+use std::collections::HashSet;
+
+/// Returns true if `path` is selected by `entry_point`. An entry point
+/// without template arguments selects the template and all its instantiations.
+fn matches_entry_point(path: &CppPath, entry_point: &CppPath) -> bool {
+    if path == entry_point {
+        return true;
+    }
+    entry_point.last().template_arguments.is_none()
+        && path.to_templateless_string() == entry_point.to_templateless_string()
+}
+
+/// Adds `path`, its parent types and types used in its template arguments
+/// to `required_types`.
+fn add_required_type(path: &CppPath, required_types: &mut HashSet<CppPath>) {
+    if !required_types.insert(path.clone()) {
+        return;
+    }
+    let items = path.items();
+    for index in 1..items.len() {
+        add_required_type(
+            &CppPath::from_items(items[..index].to_vec()),
+            required_types,
+        );
+    }
+    for item in items {
+        for arg in item.template_arguments.iter().flatten() {
+            for arg_path in type_paths(arg) {
+                add_required_type(arg_path, required_types);
+            }
+        }
+    }
+}
+
+fn is_required(
+    item: &CppItem,
+    entry_points: &[CppPath],
+    required_types: &HashSet<CppPath>,
+) -> bool {
+    let is_entry = |path: &CppPath| {
+        entry_points
+            .iter()
+            .any(|entry_point| matches_entry_point(path, entry_point))
+    };
+    let is_entry_member = |path: &CppPath| path.parent().map_or(false, |parent| is_entry(&parent));
+    match item {
+        CppItem::Namespace(_) => true,
+        CppItem::Type(data) => is_entry(&data.path) || required_types.contains(&data.path),
+        CppItem::EnumValue(data) => data
+            .path
+            .parent()
+            .map_or(false, |parent| required_types.contains(&parent)),
+        CppItem::Function(data) => {
+            is_entry(&data.path)
+                || data.class_path().map_or(false, |class_path| {
+                    // destructors are required to release values of required types
+                    is_entry(&class_path)
+                        || (data.is_destructor() && required_types.contains(&class_path))
+                })
+        }
+        // field types are required to construct values of required types
+        CppItem::ClassField(data) => {
+            is_entry_member(&data.path)
+                || data
+                    .path
+                    .parent()
+                    .map_or(false, |parent| required_types.contains(&parent))
+        }
+        CppItem::ClassBase(data) => required_types.contains(&data.derived_class_type),
+        CppItem::Variable(data) => {
+            is_entry(&data.path) || (data.is_static_member && is_entry_member(&data.path))
+        }
+    }
+}
+
+/// Returns indexes of `items` that are required by `entry_points`.
+///
+/// An entry point selects the functions, variables and types with matching path.
+/// All members of selected types are also selected. After that, types used
+/// in signatures of selected items, their base classes, fields, enum values
+/// and destructors are added until no more items are required.
+pub fn required_items(items: &[&CppItem], entry_points: &[CppPath]) -> HashSet<usize> {
+    let mut required = HashSet::new();
+    let mut required_types = HashSet::new();
+    loop {
+        let mut changed = false;
+        for (index, item) in items.iter().enumerate() {
+            if required.contains(&index) || !is_required(item, entry_points, &required_types) {
+                continue;
+            }
+            required.insert(index);
+            changed = true;
+            for type1 in item.all_involved_types() {
+                for path in type_paths(&type1) {
+                    add_required_type(path, &mut required_types);
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    required
+}
+
+/// Returns IDs of C++ items of the current crate that are required
+/// by `entry_points`, or `None` if no entry points are configured.
+///
+/// Other items are not deleted from the database, so changing
+/// the entry points doesn't require parsing the headers again.
+pub fn required_item_ids(db: &DatabaseClient, entry_points: &[CppPath]) -> Option<HashSet<ItemId>> {
+    if entry_points.is_empty() {
+        return None;
+    }
+    let cpp_items = db.cpp_items().collect_vec();
+    let required = required_items(
+        &cpp_items.iter().map(|item| item.item).collect_vec(),
+        entry_points,
+    );
+    info!(
+        "{} of {} C++ items are required by entry points",
+        required.len(),
+        cpp_items.len()
+    );
+    Some(
+        required
+            .into_iter()
+            .map(|index| cpp_items[index].id.clone())
+            .collect(),
+    )
+}
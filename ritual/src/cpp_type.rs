@@ -120,6 +120,11 @@ pub enum CppType {
         is_const: bool,
         target: Box<CppType>,
     },
+    /// Array with a known size, like `int[4]`
+    Array {
+        element_type: Box<CppType>,
+        size: usize,
+    },
 }
 
 impl CppBuiltInNumericType {
@@ -243,6 +248,14 @@ impl CppType {
         }
     }
 
+    /// Returns true if this is an array.
+    pub fn is_array(&self) -> bool {
+        match *self {
+            CppType::Array { .. } => true,
+            _ => false,
+        }
+    }
+
     pub fn is_pointer(&self) -> bool {
         match self {
             CppType::PointerLike { kind, .. } => *kind == CppPointerLikeTypeKind::Pointer,
@@ -256,6 +269,7 @@ impl CppType {
         match self {
             CppType::TemplateParameter { .. } => true,
             CppType::PointerLike { target, .. } => target.is_or_contains_template_parameter(),
+            CppType::Array { element_type, .. } => element_type.is_or_contains_template_parameter(),
             CppType::FunctionPointer(type1) => {
                 type1.return_type.is_or_contains_template_parameter()
                     || type1
@@ -282,6 +296,7 @@ impl CppType {
                 self_params.nested_level == param.nested_level && self_params.index == param.index
             }
             CppType::PointerLike { target, .. } => target.contains_template_parameter(param),
+            CppType::Array { element_type, .. } => element_type.contains_template_parameter(param),
             CppType::FunctionPointer(type1) => {
                 type1.return_type.contains_template_parameter(param)
                     || type1
@@ -354,6 +369,13 @@ impl CppType {
                     CppPointerLikeTypeKind::RValueReference => "&&",
                 }
             )),
+            // `identity` alias allows to use array types without declarator syntax
+            // (e.g. `::ritual::identity<int[4]>*` instead of `int (*)[4]`)
+            CppType::Array { element_type, size } => Ok(format!(
+                "::ritual::identity<{}[{}]>",
+                element_type.to_cpp_code(None)?,
+                size
+            )),
         }
     }
 
@@ -386,6 +408,9 @@ impl CppType {
                     }
                 );
             }
+            CppType::Array { element_type, size } => {
+                return format!("{}[{}]", element_type.to_cpp_pseudo_code(), size);
+            }
             _ => {}
         };
         self.to_cpp_code(None).unwrap_or_else(|_| "[?]".to_string())
@@ -416,6 +441,9 @@ impl CppType {
                     CppPointerLikeTypeKind::RValueReference => "_rref",
                 },
             ),
+            CppType::Array { element_type, size } => {
+                format!("{}_array{}", element_type.ascii_caption(), size)
+            }
         }
     }
 
@@ -506,9 +534,70 @@ impl CppType {
                 is_const: *is_const,
                 target: Box::new(target.instantiate(nested_level, template_arguments1)?),
             }),
+            CppType::Array { element_type, size } => Ok(CppType::Array {
+                element_type: Box::new(
+                    element_type.instantiate(nested_level, template_arguments1)?,
+                ),
+                size: *size,
+            }),
             _ => Ok(self.clone()),
         }
     }
+
+    /// Checks if `concrete` type can be produced by instantiating `self`
+    /// with some template arguments of `nested_level`. Matched arguments are
+    /// recorded in `arguments` by index of the template parameter.
+    pub fn match_template(
+        &self,
+        concrete: &CppType,
+        nested_level: usize,
+        arguments: &mut Vec<Option<CppType>>,
+    ) -> bool {
+        match (self, concrete) {
+            (CppType::TemplateParameter(param), _) if param.nested_level == nested_level => {
+                if arguments.len() <= param.index {
+                    arguments.resize(param.index + 1, None);
+                }
+                match &arguments[param.index] {
+                    Some(argument) => argument == concrete,
+                    None => {
+                        arguments[param.index] = Some(concrete.clone());
+                        true
+                    }
+                }
+            }
+            (CppType::Class(path), CppType::Class(concrete_path)) => {
+                path.match_template(concrete_path, nested_level, arguments)
+            }
+            (
+                CppType::PointerLike {
+                    kind,
+                    is_const,
+                    target,
+                },
+                CppType::PointerLike {
+                    kind: concrete_kind,
+                    is_const: concrete_is_const,
+                    target: concrete_target,
+                },
+            ) => {
+                kind == concrete_kind
+                    && is_const == concrete_is_const
+                    && target.match_template(concrete_target, nested_level, arguments)
+            }
+            (
+                CppType::Array { element_type, size },
+                CppType::Array {
+                    element_type: concrete_element_type,
+                    size: concrete_size,
+                },
+            ) => {
+                size == concrete_size
+                    && element_type.match_template(concrete_element_type, nested_level, arguments)
+            }
+            _ => self == concrete,
+        }
+    }
 }
 
 impl PartialEq for CppSpecificNumericType {
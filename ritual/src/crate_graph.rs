@@ -0,0 +1,78 @@
+//! Dependency graph of crates processed in one workspace.
+
+use itertools::Itertools;
+use ritual_common::errors::{bail, Result};
+
+/// Crates processed together and their dependencies on each other.
+/// Dependencies on crates that are not part of the graph are ignored
+/// because their databases are expected to be already available.
+#[derive(Debug, Default, Clone)]
+pub struct CrateGraph {
+    crates: Vec<(String, Vec<String>)>,
+}
+
+impl CrateGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a crate with names of crates it depends on.
+    pub fn add_crate(&mut self, crate_name: impl Into<String>, dependencies: Vec<String>) {
+        self.crates.push((crate_name.into(), dependencies));
+    }
+
+    /// Returns `dependencies` of a crate that are part of the graph.
+    fn graph_dependencies<'a>(&'a self, dependencies: &'a [String]) -> Vec<&'a String> {
+        dependencies
+            .iter()
+            .filter(|dep| self.crates.iter().any(|(name, _)| name == *dep))
+            .collect()
+    }
+
+    /// Returns crates that are not `started` yet and only depend on crates
+    /// that are `done`, in the order they were added in.
+    pub fn ready_crates(&self, started: &[String], done: &[String]) -> Vec<String> {
+        self.crates
+            .iter()
+            .filter(|(name, dependencies)| {
+                !started.contains(name)
+                    && self
+                        .graph_dependencies(dependencies)
+                        .iter()
+                        .all(|dep| done.contains(dep))
+            })
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Returns crates grouped in levels. Crates of each level only depend
+    /// on crates of previous levels, so crates within one level can be processed
+    /// in parallel. Order of crates within a level is the same as
+    /// the order they were added in.
+    pub fn levels(&self) -> Result<Vec<Vec<String>>> {
+        let mut remaining = self
+            .crates
+            .iter()
+            .map(|(name, dependencies)| (name, self.graph_dependencies(dependencies)))
+            .collect_vec();
+
+        let mut done = Vec::<&String>::new();
+        let mut levels = Vec::new();
+        while !remaining.is_empty() {
+            let (ready, not_ready): (Vec<_>, Vec<_>) = remaining
+                .into_iter()
+                .partition(|(_, dependencies)| dependencies.iter().all(|dep| done.contains(dep)));
+            if ready.is_empty() {
+                bail!(
+                    "dependency cycle detected between crates: {}",
+                    not_ready.iter().map(|(name, _)| name).join(", ")
+                );
+            }
+            let level = ready.iter().map(|(name, _)| (*name).clone()).collect_vec();
+            done.extend(ready.into_iter().map(|(name, _)| name));
+            levels.push(level);
+            remaining = not_ready;
+        }
+        Ok(levels)
+    }
+}
@@ -1,20 +1,29 @@
-use crate::config::{CrateDependencyKind, CrateDependencySource};
+use crate::config::{CppInjectionPoint, CrateDependencyKind, CrateDependencySource};
 use crate::cpp_code_generator;
 use crate::cpp_code_generator::{
-    all_include_directives, generate_cpp_type_size_requester, write_include_directives,
+    all_include_directives, generate_cpp_type_size_requester, template_include_directives,
+    write_include_directives, write_injected_code,
 };
-use crate::database::CRATE_DB_FILE_NAME;
+use crate::database::{ItemId, CRATE_DB_FILE_NAME, LEGACY_CRATE_DB_FILE_NAME};
+use crate::include_graph;
+use crate::lockfile::{Lockfile, CRATE_LOCKFILE_NAME};
 use crate::processor::ProcessorData;
 use crate::rust_code_generator;
 use itertools::Itertools;
-use ritual_common::errors::Result;
+use log::debug;
+use ritual_common::errors::{Result, ResultExt};
 use ritual_common::file_utils::{
     copy_file, copy_recursively, crate_version, create_dir, create_dir_all, create_file,
-    diff_paths, path_to_str, read_dir, remove_dir_all, repo_dir_path, save_json, save_toml_table,
+    diff_paths, path_to_str, read_dir, remove_dir_all, remove_file, rename_file, repo_dir_path,
+    save_json, save_toml_table,
 };
 use ritual_common::toml;
 use ritual_common::utils::run_command;
 use ritual_common::BuildScriptData;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::hash::{Hash, Hasher};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -96,13 +105,20 @@ fn generate_crate_template(data: &mut ProcessorData<'_>, output_path: &Path) ->
     package.insert("build".into(), toml::Value::String("build.rs".into()));
     package.insert("edition".into(), toml::Value::String("2018".into()));
 
-    let docs_rs_metadata = toml_table_with_single_item(
-        "features",
-        vec![
-            toml::Value::String("ritual_rustdoc".into()),
-            toml::Value::String("ritual_rustdoc_nightly".into()),
-        ],
-    );
+    // document conversions provided by optional dependencies
+    let docs_rs_features = ["ritual_rustdoc", "ritual_rustdoc_nightly"]
+        .iter()
+        .map(|&feature| feature.to_string())
+        .chain(
+            data.config
+                .optional_type_conversions()
+                .iter()
+                .map(|conversion| conversion.feature.clone()),
+        )
+        .unique()
+        .map(toml::Value::String)
+        .collect_vec();
+    let docs_rs_metadata = toml_table_with_single_item("features", docs_rs_features);
     package.insert(
         "metadata".into(),
         toml_table_with_single_item("docs", toml_table_with_single_item("rs", docs_rs_metadata)),
@@ -158,6 +174,44 @@ fn generate_crate_template(data: &mut ProcessorData<'_>, output_path: &Path) ->
     for dep in data.config.crate_properties().dependencies() {
         add_dependency(&mut dependencies, dep.name(), dep.source())?;
     }
+    for conversion in data.config.optional_type_conversions() {
+        if dependencies.contains_key(&conversion.dependency) {
+            continue;
+        }
+        add_dependency(
+            &mut dependencies,
+            &conversion.dependency,
+            &conversion.dependency_source,
+        )?;
+        let value = dependencies
+            .remove(&conversion.dependency)
+            .expect("dependency was just added");
+        let mut table = match value {
+            toml::Value::Table(table) => table,
+            version => {
+                let mut table = toml::value::Table::new();
+                table.insert("version".into(), version);
+                table
+            }
+        };
+        table.insert("optional".into(), toml::Value::Boolean(true));
+        dependencies.insert(conversion.dependency.clone(), table.into());
+    }
+    // Types of indirect dependencies can appear in the generated API,
+    // so they have to be accessible from the generated crate.
+    for dep in data.db.indirect_dependencies() {
+        if dependencies.contains_key(&dep.crate_name) {
+            continue;
+        }
+        let source = if data.workspace.database_path(&dep.crate_name).exists() {
+            CrateDependencySource::CurrentWorkspace
+        } else {
+            CrateDependencySource::CratesIo {
+                version: dep.crate_version.clone(),
+            }
+        };
+        add_dependency(&mut dependencies, &dep.crate_name, &source)?;
+    }
     let mut build_dependencies = toml::value::Table::new();
     if !data
         .config
@@ -188,6 +242,30 @@ fn generate_crate_template(data: &mut ProcessorData<'_>, output_path: &Path) ->
 
         features.insert(feature.into(), dep_features.into());
     }
+    // enabled automatically by the build script if the module is detected
+    for module in data.config.optional_modules() {
+        features.insert(module.name.clone(), toml::Value::Array(Vec::new()));
+    }
+    // enables conversions that require optional dependencies
+    for conversion in data.config.optional_type_conversions() {
+        // cargo creates an implicit feature for the optional dependency
+        if conversion.feature == conversion.dependency {
+            continue;
+        }
+        let value = features
+            .entry(conversion.feature.clone())
+            .or_insert_with(|| toml::Value::Array(Vec::new()));
+        if let toml::Value::Array(array) = value {
+            let dependency = toml::Value::String(conversion.dependency.clone());
+            if !array.contains(&dependency) {
+                array.push(dependency);
+            }
+        }
+    }
+    // passes the flags to the C++ compiler in the build script
+    for flag_set in data.config.compiler_flag_sets() {
+        features.insert(flag_set.name.clone(), toml::Value::Array(Vec::new()));
+    }
 
     let mut table = toml::value::Table::new();
     table.insert("package".into(), package.into());
@@ -220,6 +298,7 @@ fn generate_c_lib_template(
     lib_path: &Path,
     global_header_name: &str,
     include_directives: &[PathBuf],
+    injected_code: &[&str],
 ) -> Result<()> {
     let name_upper = lib_name.to_uppercase();
     let cmakelists_path = lib_path.join("CMakeLists.txt");
@@ -240,13 +319,96 @@ fn generate_c_lib_template(
         include_str!("../templates/c_lib/global.h"),
     )?;
     write_include_directives(&mut global_header_file, include_directives)?;
+    write_injected_code(&mut global_header_file, injected_code)?;
     Ok(())
 }
 
+/// Returns include directives required by each FFI item of the C++ wrapper library,
+/// based on the include graph recorded by the parser. Returns `None` if the graph
+/// is not available or not all declarations can be mapped to includable headers.
+/// In this case, all configured include directives should be used.
+fn c_lib_item_include_directives(
+    data: &ProcessorData<'_>,
+) -> Result<Option<HashMap<ItemId, BTreeSet<PathBuf>>>> {
+    let graph = match include_graph::load(data.workspace, data.config.crate_properties().name())? {
+        Some(graph) => graph,
+        None => return Ok(None),
+    };
+    let item_headers = match include_graph::required_entry_headers(&data.db, &graph)? {
+        Some(headers) => headers,
+        None => {
+            debug!("not all declarations are found in the include graph, using all includes");
+            return Ok(None);
+        }
+    };
+    let mut cpp_build_paths = data.config.cpp_build_paths().clone();
+    cpp_build_paths.apply_env();
+    let mut output = HashMap::new();
+    for (id, headers) in item_headers {
+        let mut directives = BTreeSet::new();
+        for header in headers {
+            match include_graph::include_directive(&header, cpp_build_paths.include_paths()) {
+                Some(directive) => directives.insert(directive),
+                None => {
+                    debug!(
+                        "header is not in include paths: {}, using all includes",
+                        header.display()
+                    );
+                    return Ok(None);
+                }
+            };
+        }
+        output.insert(id, directives);
+    }
+    Ok(Some(output))
+}
+
+fn file_hash(path: &Path) -> Result<u64> {
+    let content =
+        fs::read(path).with_context(|_| format!("failed to read file: {}", path.display()))?;
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
+/// Replaces files in `new_dir` with files of the same name from `old_dir`
+/// if their contents have the same hash. Restored files keep their modification time,
+/// so the build system only recompiles the C++ files that were actually changed.
+fn restore_unchanged_files(old_dir: &Path, new_dir: &Path) -> Result<(usize, usize)> {
+    let mut unchanged_count = 0;
+    let mut changed_count = 0;
+    for item in read_dir(new_dir)? {
+        let path = item?.path();
+        let old_path = old_dir.join(path.strip_prefix(new_dir)?);
+        if path.is_dir() {
+            if old_path.is_dir() {
+                let (unchanged, changed) = restore_unchanged_files(&old_path, &path)?;
+                unchanged_count += unchanged;
+                changed_count += changed;
+            }
+        } else if old_path.is_file() && file_hash(&old_path)? == file_hash(&path)? {
+            rename_file(&old_path, &path)?;
+            unchanged_count += 1;
+        } else {
+            changed_count += 1;
+        }
+    }
+    Ok((unchanged_count, changed_count))
+}
+
 pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
     let crate_name = data.config.crate_properties().name();
     let output_path = data.workspace.crate_path(crate_name);
 
+    // the previous C++ wrapper library is kept to reuse its unchanged files
+    let old_c_lib_path = data.tmp_path().join("old_c_lib");
+    if old_c_lib_path.exists() {
+        remove_dir_all(&old_c_lib_path)?;
+    }
+    if output_path.join("c_lib").exists() {
+        create_dir_all(data.tmp_path())?;
+        rename_file(output_path.join("c_lib"), &old_c_lib_path)?;
+    }
     if output_path.exists() {
         remove_dir_all(&output_path)?;
     }
@@ -261,26 +423,57 @@ pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
     }
     let c_lib_name = format!("{}_c", data.config.crate_properties().name());
     let global_header_name = format!("{}_global.h", c_lib_name);
+    let injected_code = data
+        .config
+        .cpp_code_injected_at(CppInjectionPoint::GlobalHeader);
+    // injected code may depend on the library headers, so they must be
+    // included in the global header
+    let item_include_directives = if injected_code.is_empty() {
+        c_lib_item_include_directives(data)?
+    } else {
+        None
+    };
+    let global_include_directives = if item_include_directives.is_some() {
+        template_include_directives(data.config)?
+    } else {
+        all_include_directives(data.config)?
+    };
     generate_c_lib_template(
         &c_lib_name,
         &c_lib_path,
         &global_header_name,
-        &all_include_directives(data.config)?,
+        &global_include_directives,
+        &injected_code,
     )?;
 
-    cpp_code_generator::generate_cpp_file(
+    cpp_code_generator::generate_cpp_files(
         &data.db,
-        &c_lib_path.join("file1.cpp"),
+        &c_lib_path,
         &global_header_name,
+        data.config,
+        item_include_directives.as_ref(),
+        &include_graph::load_ffi_item_header_groups(data.workspace, data.config, &data.db)?,
     )?;
 
-    let file = create_file(c_lib_path.join("sized_types.cxx"))?;
-    generate_cpp_type_size_requester(data.db, data.config.include_directives(), file)?;
+    {
+        let file = create_file(c_lib_path.join("sized_types.cxx"))?;
+        generate_cpp_type_size_requester(data.db, data.config.include_directives(), file)?;
+    }
+
+    if old_c_lib_path.exists() {
+        let (unchanged, changed) = restore_unchanged_files(&old_c_lib_path, &c_lib_path)?;
+        debug!(
+            "C++ wrapper library files: {} unchanged, {} changed",
+            unchanged, changed
+        );
+        remove_dir_all(&old_c_lib_path)?;
+    }
 
     rust_code_generator::generate(
         &data.db,
         &output_path.join("src"),
         data.config.crate_template_path().map(|s| s.join("src")),
+        data.config,
     )?;
 
     // -p shouldn't be needed, it's a workaround for this bug on Windows:
@@ -300,17 +493,28 @@ pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
     save_json(
         output_path.join("build_script_data.json"),
         &BuildScriptData {
-            cpp_build_config: data.config.cpp_build_config().clone(),
+            cpp_build_config: data.config.full_cpp_build_config(),
             cpp_wrapper_lib_name: c_lib_name,
             known_targets: data.db.environments().to_vec(),
+            optional_modules: data.config.optional_modules().to_vec(),
+            compiler_flag_sets: data.config.compiler_flag_sets().to_vec(),
         },
         None,
     )?;
 
-    copy_file(
-        data.workspace.database_path(crate_name),
-        output_path.join(CRATE_DB_FILE_NAME),
+    save_json(
+        output_path.join(CRATE_LOCKFILE_NAME),
+        &Lockfile::collect(data.workspace, data.config)?,
+        None,
     )?;
 
+    data.db
+        .data()
+        .save(output_path.join(CRATE_DB_FILE_NAME), None)?;
+    let legacy_db_path = output_path.join(LEGACY_CRATE_DB_FILE_NAME);
+    if legacy_db_path.exists() {
+        remove_file(legacy_db_path)?;
+    }
+
     Ok(())
 }
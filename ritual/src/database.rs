@@ -1,23 +1,57 @@
 use crate::cpp_checks::{CppChecks, CppChecksItem};
-use crate::cpp_data::{CppItem, CppPath};
+use crate::cpp_data::{CppItem, CppOriginLocation, CppPath, CppTypeProperties};
 use crate::cpp_ffi_data::CppFfiItem;
-use crate::rust_info::RustItem;
+use crate::cpp_function::CppDependentFunction;
+use crate::numeric_types::{NumericTypeResolution, PlatformNumericType};
+use crate::rust_checker::RustChecksItem;
+use crate::rust_info::{RustItem, RustStruct, RustStructKind};
 use crate::rust_type::RustPath;
+use crate::symbol_audit::SymbolAudit;
+use flate2::Crc;
+use itertools::Itertools;
 use log::{debug, error, info, trace, warn};
 use once_cell::sync::OnceCell;
-use ritual_common::errors::{bail, err_msg, format_err, Result};
-use ritual_common::file_utils::load_json;
+use ritual_common::errors::{bail, err_msg, format_err, Error, Result, ResultExt};
+use ritual_common::file_utils::{
+    create_dir_all, file_to_string, load_json, open_file, os_str_to_str, read_dir, remove_file,
+    rename_file, save_json, write_file_atomically,
+};
 use ritual_common::string_utils::ends_with_digit;
 use ritual_common::target::LibraryTarget;
+use ritual_common::utils::MapIfOk;
 use ritual_common::ReadOnly;
 use serde_derive::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader, Lines, Write};
 use std::iter::once;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
-use std::{fmt, mem};
-
-pub const CRATE_DB_FILE_NAME: &str = "ritual_db_v1.json";
+use std::{fmt, mem, vec};
+
+/// Name of the database file in the generated crate. The file contains
+/// the header on the first line and one item per line (see `Database::save`).
+pub const CRATE_DB_FILE_NAME: &str = "ritual_db_v2.jsonl";
+
+/// Name of the database file in crates generated by older versions.
+/// The file contains the whole database as one JSON object. It can still be loaded
+/// (see `DatabaseReader`).
+pub const LEGACY_CRATE_DB_FILE_NAME: &str = "ritual_db_v1.json";
+
+/// Returns path to the database file in the crate directory `crate_path`.
+/// The legacy file is used if the crate was generated by an older version.
+pub fn crate_db_path(crate_path: &Path) -> PathBuf {
+    let path = crate_path.join(CRATE_DB_FILE_NAME);
+    let legacy_path = crate_path.join(LEGACY_CRATE_DB_FILE_NAME);
+    if !path.exists() && legacy_path.exists() {
+        legacy_path
+    } else {
+        path
+    }
+}
 
 pub struct DatabaseCache(HashMap<PathBuf, IndexedDatabase>);
 
@@ -41,7 +75,7 @@ impl DatabaseCache {
             }
             if path.exists() {
                 info!("Loading database for {}", crate_name);
-                let db = load_json(&path)?;
+                let db = Database::load(&path)?;
                 return Ok(IndexedDatabase::new(db, path));
             }
         }
@@ -85,6 +119,9 @@ pub struct DbItem<T> {
     pub id: ItemId,
     pub source_id: Option<ItemId>,
     pub item: T,
+    /// Location of the declaration of a C++ item, if it's known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin_location: Option<Arc<CppOriginLocation>>,
 }
 
 impl<T> DbItem<T> {
@@ -93,6 +130,7 @@ impl<T> DbItem<T> {
             id: self.id.clone(),
             source_id: self.source_id.clone(),
             item: &self.item,
+            origin_location: self.origin_location.clone(),
         }
     }
 
@@ -101,6 +139,7 @@ impl<T> DbItem<T> {
             id: self.id.clone(),
             source_id: self.source_id.clone(),
             item: &mut self.item,
+            origin_location: self.origin_location.clone(),
         }
     }
 
@@ -112,6 +151,7 @@ impl<T> DbItem<T> {
             id: self.id,
             source_id: self.source_id,
             item: func(self.item),
+            origin_location: self.origin_location,
         }
     }
 
@@ -123,6 +163,7 @@ impl<T> DbItem<T> {
             id: self.id,
             source_id: self.source_id,
             item: func(self.item)?,
+            origin_location: self.origin_location,
         })
     }
 }
@@ -152,6 +193,36 @@ impl fmt::Display for ItemId {
     }
 }
 
+impl FromStr for ItemId {
+    type Err = Error;
+
+    /// Parses an item id in `crate_name#id` format.
+    fn from_str(text: &str) -> Result<Self> {
+        let mut parts = text.splitn(2, '#');
+        let crate_name = parts.next().unwrap_or("");
+        let id = parts
+            .next()
+            .ok_or_else(|| format_err!("invalid item id format: {}", text))?
+            .parse()
+            .with_context(|_| format!("invalid item id format: {}", text))?;
+        Ok(ItemId::new(crate_name.to_string(), id))
+    }
+}
+
+/// A note written by a maintainer about a database item.
+/// Annotations are stored by the item's full text
+/// (see `DatabaseItemData::annotation_key`), so they survive regeneration of the item.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct ItemAnnotation {
+    pub text: String,
+    /// Number of this annotation in the history of annotations of the item,
+    /// starting from 1.
+    pub revision: u32,
+    /// Version of the crate when the annotation was added.
+    pub crate_version: String,
+    pub date: String,
+}
+
 /// C++ documentation for a method
 #[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
 pub struct DocItem {
@@ -168,6 +239,31 @@ pub struct DocItem {
     pub url: Option<String>,
     /// Absolute documentation URLs encountered in the content
     pub cross_references: Vec<String>,
+    /// C++ items referenced in the content. They are rendered as links
+    /// to the corresponding Rust items.
+    #[serde(default)]
+    pub cpp_cross_references: Vec<CppPath>,
+    /// C++ code examples found in the content and their automatic Rust translations
+    #[serde(default)]
+    pub examples: Vec<DocExample>,
+}
+
+/// A C++ code example from the documentation
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
+pub struct DocExample {
+    pub cpp_code: String,
+    /// Heuristic (untested) translation of `cpp_code` to Rust
+    pub rust_code: String,
+}
+
+/// A C++ item that matches an existing item (as per `CppItem::is_same`)
+/// but has different data, e.g. different default arguments
+/// in another configuration. The existing item is the source of this item.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct CppConflictItem {
+    /// Source of the conflicting item
+    pub source_id: Option<ItemId>,
+    pub item: CppItem,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -178,6 +274,11 @@ pub enum DatabaseItemData {
     CppChecksItem(CppChecksItem),
     RustItem(RustItem),
     DocItem(DocItem),
+    CppConflictItem(CppConflictItem),
+    /// Errors reported by the Rust compiler for the source Rust item
+    RustChecksItem(RustChecksItem),
+    /// Class template member function with an unresolved signature
+    CppDependentFunctionItem(CppDependentFunction),
 }
 
 impl DatabaseItemData {
@@ -258,6 +359,20 @@ impl DatabaseItemData {
             None
         }
     }
+    pub fn as_rust_checks_item(&self) -> Option<&RustChecksItem> {
+        if let DatabaseItemData::RustChecksItem(data) = self {
+            Some(data)
+        } else {
+            None
+        }
+    }
+    pub fn as_cpp_dependent_function_item(&self) -> Option<&CppDependentFunction> {
+        if let DatabaseItemData::CppDependentFunctionItem(data) = self {
+            Some(data)
+        } else {
+            None
+        }
+    }
     pub fn is_doc_item(&self) -> bool {
         if let DatabaseItemData::DocItem(_) = self {
             true
@@ -280,6 +395,40 @@ impl DatabaseItemData {
         }
     }
 
+    pub fn is_cpp_conflict_item(&self) -> bool {
+        if let DatabaseItemData::CppConflictItem(_) = self {
+            true
+        } else {
+            false
+        }
+    }
+    pub fn as_cpp_conflict_item(&self) -> Option<&CppConflictItem> {
+        if let DatabaseItemData::CppConflictItem(data) = self {
+            Some(data)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the text that identifies the item among all items of the database
+    /// and doesn't change when the item is regenerated. Returns `None` for items
+    /// that only describe their source item (e.g. checks and documentation).
+    pub fn annotation_key(&self) -> Option<String> {
+        match self {
+            DatabaseItemData::CppItem(item) => Some(format!("cpp: {}", item)),
+            DatabaseItemData::FfiItem(item) => Some(format!("ffi: {}", item.short_text())),
+            DatabaseItemData::RustItem(item) => Some(format!("rust: {}", item.short_text())),
+            DatabaseItemData::CppDependentFunctionItem(item) => Some(format!(
+                "cpp: dependent function {}",
+                item.path.to_cpp_pseudo_code()
+            )),
+            DatabaseItemData::CppChecksItem(_)
+            | DatabaseItemData::DocItem(_)
+            | DatabaseItemData::CppConflictItem(_)
+            | DatabaseItemData::RustChecksItem(_) => None,
+        }
+    }
+
     pub fn short_text(&self) -> String {
         match self {
             DatabaseItemData::CppItem(item) => item.short_text(),
@@ -287,10 +436,25 @@ impl DatabaseItemData {
             DatabaseItemData::RustItem(item) => item.short_text(),
             DatabaseItemData::CppChecksItem(_) => "CppChecksItem".into(),
             DatabaseItemData::DocItem(_) => "DocItem".into(),
+            DatabaseItemData::CppConflictItem(item) => {
+                format!("conflict: {}", item.item.short_text())
+            }
+            DatabaseItemData::RustChecksItem(_) => "RustChecksItem".into(),
+            DatabaseItemData::CppDependentFunctionItem(item) => {
+                format!("dependent function: {}", item.path.to_cpp_pseudo_code())
+            }
         }
     }
 }
 
+/// Reference to the database of another ritual crate
+/// that the current crate depends on.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct DatabaseDependency {
+    pub crate_name: String,
+    pub crate_version: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Database {
     crate_name: Arc<String>,
@@ -298,6 +462,49 @@ pub struct Database {
     items: Vec<DbItem<DatabaseItemData>>,
     targets: Vec<LibraryTarget>,
     next_id: u32,
+    /// Direct ritual dependencies of the crate. Used to load
+    /// databases of indirect dependencies.
+    #[serde(default)]
+    dependencies: Vec<DatabaseDependency>,
+    /// Qualified names of C++ items that were blocked by the user.
+    /// No FFI items are generated for blocked items.
+    #[serde(default)]
+    blocked_paths: BTreeSet<String>,
+    /// Qualified names of C++ items that are known to crash or cause UB at runtime,
+    /// with the reason. No FFI items are generated for them.
+    #[serde(default)]
+    known_crashes: BTreeMap<String, String>,
+    /// Headers that crashed libclang and are excluded from parsing,
+    /// with the reason of the crash.
+    #[serde(default)]
+    parse_failures: BTreeMap<String, String>,
+    /// Maintainer annotations by item key (see `DatabaseItemData::annotation_key`).
+    #[serde(default)]
+    annotations: BTreeMap<String, Vec<ItemAnnotation>>,
+    /// Names of FFI functions by signature of the wrapped C++ item.
+    /// Reused when FFI items are regenerated to keep exported symbols stable.
+    #[serde(default)]
+    ffi_names: BTreeMap<String, String>,
+    /// Keys of FFI items rejected in the reviewed FFI spec
+    /// (see `CppFfiItem::spec_key`). These items are not generated again.
+    #[serde(default)]
+    rejected_ffi_items: BTreeSet<String>,
+    /// Rust representations of platform-dependent numeric types
+    /// for each checked library target.
+    #[serde(default)]
+    numeric_types: Vec<NumericTypeResolution>,
+    /// Properties of C++ types reported by the compiler
+    /// for each checked library target.
+    #[serde(default)]
+    type_properties: Vec<CppTypeProperties>,
+    /// Results of the last symbol audit of the built C++ wrapper library
+    /// for each library target.
+    #[serde(default)]
+    symbol_audits: Vec<SymbolAudit>,
+    /// Hashes of shard files as they were last loaded or saved.
+    /// Used to skip rewriting unchanged shards.
+    #[serde(skip)]
+    saved_shards: HashMap<String, u64>,
 }
 
 impl Database {
@@ -308,9 +515,32 @@ impl Database {
             items: Vec::new(),
             targets: Vec::new(),
             next_id: 1,
+            dependencies: Vec::new(),
+            blocked_paths: BTreeSet::new(),
+            known_crashes: BTreeMap::new(),
+            parse_failures: BTreeMap::new(),
+            annotations: BTreeMap::new(),
+            ffi_names: BTreeMap::new(),
+            rejected_ffi_items: BTreeSet::new(),
+            numeric_types: Vec::new(),
+            type_properties: Vec::new(),
+            symbol_audits: Vec::new(),
+            saved_shards: HashMap::new(),
         }
     }
 
+    pub fn crate_name(&self) -> &str {
+        &self.crate_name
+    }
+
+    pub fn crate_version(&self) -> &str {
+        &self.crate_version
+    }
+
+    pub fn dependencies(&self) -> &[DatabaseDependency] {
+        &self.dependencies
+    }
+
     fn items(&self) -> impl Iterator<Item = DbItem<&DatabaseItemData>> {
         self.items.iter().map(|item| item.as_ref())
     }
@@ -337,6 +567,344 @@ impl Database {
         self.items()
             .filter_map(|item| item.filter_map(|v| v.as_rust_item()))
     }
+    fn cpp_conflict_items(&self) -> impl Iterator<Item = DbItem<&CppConflictItem>> {
+        self.items()
+            .filter_map(|item| item.filter_map(|v| v.as_cpp_conflict_item()))
+    }
+    fn cpp_dependent_functions(&self) -> impl Iterator<Item = DbItem<&CppDependentFunction>> {
+        self.items()
+            .filter_map(|item| item.filter_map(|v| v.as_cpp_dependent_function_item()))
+    }
+
+    fn from_header(header: DatabaseHeader, items: Vec<DbItem<DatabaseItemData>>) -> Self {
+        Database {
+            crate_name: header.crate_name,
+            crate_version: header.crate_version,
+            items,
+            targets: header.targets,
+            next_id: header.next_id,
+            dependencies: header.dependencies,
+            blocked_paths: header.blocked_paths,
+            known_crashes: header.known_crashes,
+            parse_failures: header.parse_failures,
+            annotations: header.annotations,
+            ffi_names: header.ffi_names,
+            rejected_ffi_items: header.rejected_ffi_items,
+            numeric_types: header.numeric_types,
+            type_properties: header.type_properties,
+            symbol_audits: header.symbol_audits,
+            saved_shards: HashMap::new(),
+        }
+    }
+
+    fn header(&self) -> DatabaseHeader {
+        DatabaseHeader {
+            crate_name: self.crate_name.clone(),
+            crate_version: self.crate_version.clone(),
+            targets: self.targets.clone(),
+            next_id: self.next_id,
+            dependencies: self.dependencies.clone(),
+            blocked_paths: self.blocked_paths.clone(),
+            known_crashes: self.known_crashes.clone(),
+            parse_failures: self.parse_failures.clone(),
+            annotations: self.annotations.clone(),
+            ffi_names: self.ffi_names.clone(),
+            rejected_ffi_items: self.rejected_ffi_items.clone(),
+            numeric_types: self.numeric_types.clone(),
+            type_properties: self.type_properties.clone(),
+            symbol_audits: self.symbol_audits.clone(),
+            items: Vec::new(),
+        }
+    }
+
+    /// Loads the database from `path`, which may be either a single file
+    /// or a sharded database directory. Items are deserialized one by one,
+    /// so the complete JSON tree is never kept in memory.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        if path.as_ref().is_dir() {
+            return Self::load_sharded(path.as_ref());
+        }
+        let mut reader = DatabaseReader::open(path)?;
+        let mut items = Vec::new();
+        for item in &mut reader {
+            items.push(item?);
+        }
+        Ok(Self::from_header(reader.header, items))
+    }
+
+    fn load_sharded(dir: &Path) -> Result<Self> {
+        let header = load_json(dir.join(SHARDED_DB_HEADER_FILE_NAME))?;
+        let mut items = Vec::new();
+        let mut saved_shards = HashMap::new();
+        for entry in read_dir(dir.join(SHARDED_DB_ITEMS_DIR_NAME))? {
+            let path = entry?.path();
+            // skip temporary files left by interrupted saves
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let name = match path.file_stem() {
+                Some(stem) => os_str_to_str(stem)?.to_string(),
+                None => continue,
+            };
+            let content = file_to_string(&path)?;
+            for line in content.lines().filter(|line| !line.is_empty()) {
+                let item = serde_json::from_str(line).with_context(|_| {
+                    format!("failed to parse database item: {}", path.display())
+                })?;
+                items.push(item);
+            }
+            saved_shards.insert(name, shard_hash(content.as_bytes()));
+        }
+        items.sort_by(|a: &DbItem<DatabaseItemData>, b| a.id.cmp(&b.id));
+        let mut db = Self::from_header(header, items);
+        db.saved_shards = saved_shards;
+        Ok(db)
+    }
+
+    /// Saves the database to `path`. The first line of the file contains
+    /// crate properties, and each of the following lines contains one item.
+    /// If the file already exists, it's moved to `backup_path`.
+    pub fn save(&self, path: impl AsRef<Path>, backup_path: Option<&Path>) -> Result<()> {
+        let header = self.header();
+        write_file_atomically(path.as_ref(), backup_path, |file| {
+            serde_json::to_writer(&mut *file, &header)?;
+            writeln!(file)?;
+            for item in &self.items {
+                serde_json::to_writer(&mut *file, item)?;
+                writeln!(file)?;
+            }
+            Ok(())
+        })
+        .with_context(|_| format!("failed to save database: {}", path.as_ref().display()))?;
+        Ok(())
+    }
+}
+
+impl Database {
+    /// Saves the database as a directory containing a header file and one file of items
+    /// per C++ class (or namespace). Each item is placed in the same shard as the item
+    /// it was derived from. Shards that didn't change since the database was loaded or
+    /// last saved are not rewritten. Replaced and removed shards are moved
+    /// to `backup_dir` if it's specified.
+    pub fn save_sharded(&mut self, dir: impl AsRef<Path>, backup_dir: Option<&Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        let items_dir = dir.join(SHARDED_DB_ITEMS_DIR_NAME);
+        create_dir_all(&items_dir)?;
+
+        let mut shards = BTreeMap::<String, Vec<u8>>::new();
+        let mut item_shards = Vec::<String>::with_capacity(self.items.len());
+        for (index, item) in self.items.iter().enumerate() {
+            let source_index = item.source_id.as_ref().and_then(|source_id| {
+                self.items[..index]
+                    .binary_search_by_key(&source_id, |item| &item.id)
+                    .ok()
+            });
+            let shard = match source_index {
+                Some(source_index) => item_shards[source_index].clone(),
+                None => shard_name(&item.item),
+            };
+            let buffer = shards.entry(shard.clone()).or_default();
+            serde_json::to_writer(&mut *buffer, item)?;
+            buffer.push(b'\n');
+            item_shards.push(shard);
+        }
+
+        let backup = |name: &str| -> Result<Option<PathBuf>> {
+            Ok(match backup_dir {
+                Some(backup_dir) => {
+                    create_dir_all(backup_dir)?;
+                    Some(backup_dir.join(format!("{}.json", name)))
+                }
+                None => None,
+            })
+        };
+
+        let mut saved_shards = HashMap::new();
+        for (name, content) in shards {
+            let hash = shard_hash(&content);
+            let path = items_dir.join(format!("{}.json", name));
+            if self.saved_shards.get(&name) != Some(&hash) || !path.exists() {
+                let backup_path = backup(&name)?;
+                write_file_atomically(&path, backup_path.as_ref().map(|p| p.as_path()), |file| {
+                    file.write_all(&content)?;
+                    Ok(())
+                })?;
+            }
+            saved_shards.insert(name, hash);
+        }
+
+        for entry in read_dir(&items_dir)? {
+            let path = entry?.path();
+            let name = match path.file_stem() {
+                Some(stem) => os_str_to_str(stem)?.to_string(),
+                None => continue,
+            };
+            if !saved_shards.contains_key(&name) {
+                match backup(&name)? {
+                    Some(backup_path) => rename_file(&path, &backup_path)?,
+                    None => remove_file(&path)?,
+                }
+            }
+        }
+        // the header is written last and atomically, so it's never
+        // left partially written
+        save_json(dir.join(SHARDED_DB_HEADER_FILE_NAME), &self.header(), None)?;
+        self.saved_shards = saved_shards;
+        Ok(())
+    }
+}
+
+const SHARDED_DB_HEADER_FILE_NAME: &str = "header.json";
+const SHARDED_DB_ITEMS_DIR_NAME: &str = "items";
+
+fn shard_hash(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Returns name of the shard for an item that was not derived from another item
+/// of the same database. C++ items are grouped by the class or namespace they
+/// belong to. Names are lowercase to avoid conflicts on case-insensitive file systems,
+/// and a hash of the original path is added to keep names of different
+/// classes (e.g. `Foo` and `foo`) distinct.
+fn shard_name(item: &DatabaseItemData) -> String {
+    let path = item.as_cpp_item().and_then(|item| match item {
+        CppItem::Namespace(data) => Some(data.path.clone()),
+        CppItem::Type(data) => Some(data.path.clone()),
+        CppItem::EnumValue(data) => data.path.parent().ok(),
+        CppItem::Function(data) => data.path.parent().ok(),
+        CppItem::ClassField(data) => data.path.parent().ok(),
+        CppItem::Variable(data) => data.path.parent().ok(),
+        CppItem::ClassBase(data) => Some(data.derived_class_type.clone()),
+    });
+    match path {
+        Some(path) => {
+            let name = path
+                .items()
+                .iter()
+                .map(|item| {
+                    item.name
+                        .chars()
+                        .map(|c| {
+                            if c.is_ascii_alphanumeric() {
+                                c.to_ascii_lowercase()
+                            } else {
+                                '_'
+                            }
+                        })
+                        .collect::<String>()
+                })
+                .join("_");
+            let mut crc = Crc::new();
+            crc.update(path.to_templateless_string().as_bytes());
+            format!("{}_{:08x}", name, crc.sum())
+        }
+        None if item.is_cpp_item() => "global".to_string(),
+        None => "other".to_string(),
+    }
+}
+
+/// Properties of the database stored in the first line of the database file.
+#[derive(Debug, Serialize, Deserialize)]
+struct DatabaseHeader {
+    crate_name: Arc<String>,
+    crate_version: String,
+    targets: Vec<LibraryTarget>,
+    next_id: u32,
+    #[serde(default)]
+    dependencies: Vec<DatabaseDependency>,
+    #[serde(default)]
+    blocked_paths: BTreeSet<String>,
+    #[serde(default)]
+    known_crashes: BTreeMap<String, String>,
+    #[serde(default)]
+    parse_failures: BTreeMap<String, String>,
+    #[serde(default)]
+    annotations: BTreeMap<String, Vec<ItemAnnotation>>,
+    #[serde(default)]
+    ffi_names: BTreeMap<String, String>,
+    #[serde(default)]
+    rejected_ffi_items: BTreeSet<String>,
+    #[serde(default)]
+    numeric_types: Vec<NumericTypeResolution>,
+    #[serde(default)]
+    type_properties: Vec<CppTypeProperties>,
+    #[serde(default)]
+    symbol_audits: Vec<SymbolAudit>,
+    /// Only present in database files written in the old format
+    /// that stored the whole database as one JSON object.
+    #[serde(default, skip_serializing)]
+    items: Vec<DbItem<DatabaseItemData>>,
+}
+
+/// Reads items of a database file one by one without loading
+/// the whole database into memory.
+pub struct DatabaseReader {
+    header: DatabaseHeader,
+    legacy_items: vec::IntoIter<DbItem<DatabaseItemData>>,
+    lines: Lines<BufReader<File>>,
+    path: PathBuf,
+}
+
+impl DatabaseReader {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut lines = open_file(&path)?.into_inner().lines();
+        let first_line = lines
+            .next()
+            .ok_or_else(|| format_err!("database file is empty: {}", path.display()))??;
+        let mut header: DatabaseHeader = serde_json::from_str(&first_line)
+            .with_context(|_| format!("failed to parse database: {}", path.display()))?;
+        let legacy_items = mem::replace(&mut header.items, Vec::new()).into_iter();
+        Ok(DatabaseReader {
+            header,
+            legacy_items,
+            lines,
+            path,
+        })
+    }
+
+    pub fn crate_name(&self) -> &str {
+        &self.header.crate_name
+    }
+
+    pub fn crate_version(&self) -> &str {
+        &self.header.crate_version
+    }
+
+    pub fn targets(&self) -> &[LibraryTarget] {
+        &self.header.targets
+    }
+
+    pub fn dependencies(&self) -> &[DatabaseDependency] {
+        &self.header.dependencies
+    }
+}
+
+impl Iterator for DatabaseReader {
+    type Item = Result<DbItem<DatabaseItemData>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(item) = self.legacy_items.next() {
+            return Some(Ok(item));
+        }
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err.into())),
+            };
+            if line.is_empty() {
+                continue;
+            }
+            let path = &self.path;
+            return Some(
+                serde_json::from_str(&line)
+                    .with_context(|_| format!("failed to parse database item: {}", path.display()))
+                    .map_err(From::from),
+            );
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -436,7 +1004,7 @@ impl IndexedDatabase {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct Counters {
     pub items_added: u32,
     pub items_ignored: u32,
@@ -493,6 +1061,12 @@ impl DatabaseClient {
         self.is_modified = false;
     }
 
+    /// Saves the current database as a sharded database directory.
+    /// See `Database::save_sharded`.
+    pub fn save_sharded(&mut self, dir: &Path, backup_dir: Option<&Path>) -> Result<()> {
+        self.current_database.db.save_sharded(dir, backup_dir)
+    }
+
     pub fn items(&self) -> impl Iterator<Item = DbItem<&DatabaseItemData>> {
         self.current_database.db.items()
     }
@@ -517,6 +1091,14 @@ impl DatabaseClient {
         self.current_database.db.rust_items()
     }
 
+    pub fn cpp_conflict_items(&self) -> impl Iterator<Item = DbItem<&CppConflictItem>> {
+        self.current_database.db.cpp_conflict_items()
+    }
+
+    pub fn cpp_dependent_functions(&self) -> impl Iterator<Item = DbItem<&CppDependentFunction>> {
+        self.current_database.db.cpp_dependent_functions()
+    }
+
     pub fn cpp_item_ids<'a>(&'a self) -> impl Iterator<Item = ItemId> + 'a {
         self.cpp_items().map(|item| item.id)
     }
@@ -620,6 +1202,7 @@ impl DatabaseClient {
             id: id.clone(),
             source_id,
             item: DatabaseItemData::FfiItem(item),
+            origin_location: None,
         });
         self.counters.items_added += 1;
         Ok(Some(id))
@@ -645,8 +1228,19 @@ impl DatabaseClient {
         source_id: Option<ItemId>,
         data: CppItem,
     ) -> Result<Option<ItemId>> {
-        if self.cpp_items().any(|item| item.item.is_same(&data)) {
-            self.counters.items_ignored += 1;
+        if let Some(existing) = self.cpp_items().find(|item| item.item.is_same(&data)) {
+            if existing.item.conflicts_with(&data) {
+                let existing_id = existing.id;
+                self.add_cpp_conflict_item(
+                    existing_id,
+                    CppConflictItem {
+                        source_id,
+                        item: data,
+                    },
+                );
+            } else {
+                self.counters.items_ignored += 1;
+            }
             return Ok(None);
         }
         self.is_modified = true;
@@ -659,6 +1253,7 @@ impl DatabaseClient {
             id: id.clone(),
             source_id,
             item: DatabaseItemData::CppItem(data),
+            origin_location: None,
         };
         trace!("    cpp item data: {:?}", item);
         self.current_database.push(item);
@@ -666,6 +1261,274 @@ impl DatabaseClient {
         Ok(Some(id))
     }
 
+    /// Records `conflict` with the existing item `existing_id`
+    /// unless the same conflict is already recorded.
+    fn add_cpp_conflict_item(&mut self, existing_id: ItemId, conflict: CppConflictItem) {
+        if self
+            .current_database
+            .filter_by_source(&Some(existing_id.clone()))
+            .any(|other| other.item.as_cpp_conflict_item() == Some(&conflict))
+        {
+            self.counters.items_ignored += 1;
+            return;
+        }
+        warn!(
+            "conflicting definitions of {}: {}",
+            existing_id,
+            conflict.item.short_text()
+        );
+        self.is_modified = true;
+        let id = self.new_id();
+        self.current_database.push(DbItem {
+            id,
+            source_id: Some(existing_id),
+            item: DatabaseItemData::CppConflictItem(conflict),
+            origin_location: None,
+        });
+        self.counters.items_added += 1;
+    }
+
+    /// Records location of the declaration of the C++ item `id`.
+    pub fn set_cpp_origin_location(
+        &mut self,
+        id: &ItemId,
+        location: CppOriginLocation,
+    ) -> Result<()> {
+        if *id.crate_name != self.crate_name() {
+            bail!("can't modify item of dependency");
+        }
+        let items = &mut self.current_database.db.items;
+        let index = items
+            .binary_search_by_key(&id, |item| &item.id)
+            .map_err(|_| format_err!("invalid item id: {}", id))?;
+        let item = &mut items[index];
+        if item.item.as_cpp_item().is_none() {
+            bail!("not a C++ item: {}", id);
+        }
+        if item.origin_location.as_ref().map(|l| &**l) != Some(&location) {
+            item.origin_location = Some(Arc::new(location));
+            self.is_modified = true;
+        }
+        Ok(())
+    }
+
+    /// Returns location of the declaration of the C++ item `id`
+    /// if it's known.
+    pub fn cpp_origin_location(&self, id: &ItemId) -> Result<Option<&CppOriginLocation>> {
+        let db = self.database(&id.crate_name)?;
+        match db.db.items.binary_search_by_key(&id, |item| &item.id) {
+            Ok(index) => Ok(db.db.items[index].origin_location.as_ref().map(|l| &**l)),
+            Err(_) => bail!("invalid item id: {}", id),
+        }
+    }
+
+    /// Returns a description of the item `id` and the chain of items it
+    /// was derived from, including locations of their C++ declarations.
+    /// Used to provide context for errors.
+    pub fn item_context(&self, id: &ItemId) -> String {
+        let mut parts = Vec::new();
+        let mut current_id = Some(id.clone());
+        while let Some(id) = current_id {
+            let item = match self.item(&id) {
+                Ok(item) => item,
+                Err(_) => break,
+            };
+            let text = format!("{} {}", id, item.item.short_text());
+            match self.cpp_origin_location(&id) {
+                Ok(Some(location)) => parts.push(format!("{} (at {})", text, location)),
+                _ => parts.push(text),
+            }
+            current_id = item.source_id.clone();
+        }
+        format!("while processing {}", parts.join(", derived from "))
+    }
+
+    /// Returns true if the C++ item with `path` was blocked by the user.
+    pub fn is_blocked(&self, path: &CppPath) -> bool {
+        self.current_database
+            .db
+            .blocked_paths
+            .contains(&path.to_templateless_string())
+    }
+
+    pub fn blocked_paths(&self) -> impl Iterator<Item = &str> {
+        self.current_database
+            .db
+            .blocked_paths
+            .iter()
+            .map(String::as_str)
+    }
+
+    /// Blocks or unblocks C++ items with the qualified name `path`
+    /// (without template arguments).
+    pub fn set_blocked(&mut self, path: &str, blocked: bool) {
+        let paths = &mut self.current_database.db.blocked_paths;
+        let changed = if blocked {
+            paths.insert(path.to_string())
+        } else {
+            paths.remove(path)
+        };
+        if changed {
+            self.is_modified = true;
+        }
+    }
+
+    /// Returns the reason if the C++ item with `path` was marked as known to crash
+    /// at runtime.
+    pub fn known_crash_reason(&self, path: &CppPath) -> Option<&str> {
+        self.current_database
+            .db
+            .known_crashes
+            .get(&path.to_templateless_string())
+            .map(String::as_str)
+    }
+
+    /// Returns qualified names and reasons of all items marked as known to crash.
+    pub fn known_crashes(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.current_database
+            .db
+            .known_crashes
+            .iter()
+            .map(|(path, reason)| (path.as_str(), reason.as_str()))
+    }
+
+    /// Marks C++ items with the qualified name `path` (without template arguments)
+    /// as known to crash at runtime for `reason`, or removes the mark if `reason` is `None`.
+    pub fn set_known_crash(&mut self, path: &str, reason: Option<&str>) {
+        let crashes = &mut self.current_database.db.known_crashes;
+        let changed = match reason {
+            Some(reason) => {
+                crashes
+                    .insert(path.to_string(), reason.to_string())
+                    .as_ref()
+                    .map(String::as_str)
+                    != Some(reason)
+            }
+            None => crashes.remove(path).is_some(),
+        };
+        if changed {
+            self.is_modified = true;
+        }
+    }
+
+    /// Returns headers that crashed libclang and the reasons of the crashes.
+    pub fn parse_failures(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.current_database
+            .db
+            .parse_failures
+            .iter()
+            .map(|(header, reason)| (header.as_str(), reason.as_str()))
+    }
+
+    /// Records that parsing `header` crashed libclang for `reason`, or removes
+    /// the record if `reason` is `None`, so the header is parsed again.
+    pub fn set_parse_failure(&mut self, header: &str, reason: Option<&str>) {
+        let failures = &mut self.current_database.db.parse_failures;
+        let changed = match reason {
+            Some(reason) => {
+                failures
+                    .insert(header.to_string(), reason.to_string())
+                    .as_ref()
+                    .map(String::as_str)
+                    != Some(reason)
+            }
+            None => failures.remove(header).is_some(),
+        };
+        if changed {
+            self.is_modified = true;
+        }
+    }
+
+    /// Returns signature keys and assigned names of FFI functions of the current crate.
+    pub fn ffi_names(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.current_database
+            .db
+            .ffi_names
+            .iter()
+            .map(|(key, name)| (key.as_str(), name.as_str()))
+    }
+
+    /// Records `name` as the name of the FFI function with signature `key`.
+    pub fn set_ffi_name(&mut self, key: &str, name: &str) {
+        let previous = self
+            .current_database
+            .db
+            .ffi_names
+            .insert(key.to_string(), name.to_string());
+        if previous.as_ref().map(String::as_str) != Some(name) {
+            self.is_modified = true;
+        }
+    }
+
+    /// Returns true if the FFI item with `key` (see `CppFfiItem::spec_key`)
+    /// was rejected in the FFI spec.
+    pub fn is_ffi_item_rejected(&self, key: &str) -> bool {
+        self.current_database.db.rejected_ffi_items.contains(key)
+    }
+
+    /// Records that the FFI item with `key` was rejected in the FFI spec.
+    pub fn reject_ffi_item(&mut self, key: String) {
+        if self.current_database.db.rejected_ffi_items.insert(key) {
+            self.is_modified = true;
+        }
+    }
+
+    /// Returns all annotations of the item, oldest first.
+    pub fn annotations(&self, id: &ItemId) -> Result<&[ItemAnnotation]> {
+        let key = match self.item(id)?.item.annotation_key() {
+            Some(key) => key,
+            None => return Ok(&[]),
+        };
+        Ok(self
+            .current_database
+            .db
+            .annotations
+            .get(&key)
+            .map_or(&[][..], Vec::as_slice))
+    }
+
+    fn annotation_key(&self, id: &ItemId) -> Result<String> {
+        self.item(id)?.item.annotation_key().ok_or_else(|| {
+            format_err!(
+                "item {} can't be annotated; annotate its source item instead",
+                id
+            )
+        })
+    }
+
+    /// Returns item texts and annotations of all annotated items.
+    pub fn all_annotations(&self) -> impl Iterator<Item = (&str, &[ItemAnnotation])> {
+        self.current_database
+            .db
+            .annotations
+            .iter()
+            .map(|(key, annotations)| (key.as_str(), annotations.as_slice()))
+    }
+
+    /// Adds an annotation to the item.
+    pub fn add_annotation(&mut self, id: &ItemId, text: &str) -> Result<()> {
+        let key = self.annotation_key(id)?;
+        let crate_version = self.crate_version().to_string();
+        let annotations = self.current_database.db.annotations.entry(key).or_default();
+        annotations.push(ItemAnnotation {
+            text: text.to_string(),
+            revision: annotations.last().map_or(1, |last| last.revision + 1),
+            crate_version,
+            date: chrono::Local::now().format("%Y-%m-%d").to_string(),
+        });
+        self.is_modified = true;
+        Ok(())
+    }
+
+    /// Removes all annotations of the item.
+    pub fn clear_annotations(&mut self, id: &ItemId) -> Result<()> {
+        let key = self.annotation_key(id)?;
+        if self.current_database.db.annotations.remove(&key).is_some() {
+            self.is_modified = true;
+        }
+        Ok(())
+    }
+
     pub fn add_environment(&mut self, env: LibraryTarget) {
         if !self.current_database.db.targets.iter().any(|e| e == &env) {
             self.is_modified = true;
@@ -677,6 +1540,76 @@ impl DatabaseClient {
         &self.current_database.db.targets
     }
 
+    /// Records the Rust representation of a platform-dependent numeric type
+    /// on a library target, replacing the previous record for the same type and target.
+    pub fn set_numeric_type_resolution(&mut self, resolution: NumericTypeResolution) {
+        let numeric_types = &mut self.current_database.db.numeric_types;
+        if numeric_types.iter().any(|r| r == &resolution) {
+            return;
+        }
+        numeric_types.retain(|r| r.cpp_type != resolution.cpp_type || r.env != resolution.env);
+        numeric_types.push(resolution);
+        self.is_modified = true;
+    }
+
+    pub fn numeric_type_resolutions(&self) -> &[NumericTypeResolution] {
+        &self.current_database.db.numeric_types
+    }
+
+    /// Returns true if `cpp_type` was recorded as unusable on `env`.
+    pub fn is_numeric_type_unsupported(
+        &self,
+        cpp_type: PlatformNumericType,
+        env: &LibraryTarget,
+    ) -> bool {
+        self.numeric_type_resolutions()
+            .iter()
+            .any(|r| r.cpp_type == cpp_type && &r.env == env && r.rust_type.is_none())
+    }
+
+    /// Records properties of a C++ type, replacing previously recorded
+    /// properties of the same type on the same library target.
+    pub fn set_cpp_type_properties(&mut self, properties: CppTypeProperties) {
+        let type_properties = &mut self.current_database.db.type_properties;
+        if type_properties.iter().any(|p| p == &properties) {
+            return;
+        }
+        type_properties.retain(|p| p.path != properties.path || p.env != properties.env);
+        type_properties.push(properties);
+        self.is_modified = true;
+    }
+
+    pub fn all_cpp_type_properties(&self) -> &[CppTypeProperties] {
+        &self.current_database.db.type_properties
+    }
+
+    /// Returns properties of the C++ type `path` reported by the compiler on `env`.
+    pub fn cpp_type_properties(
+        &self,
+        path: &CppPath,
+        env: &LibraryTarget,
+    ) -> Option<&CppTypeProperties> {
+        self.all_cpp_type_properties()
+            .iter()
+            .find(|p| &p.path == path && &p.env == env)
+    }
+
+    /// Records the result of a symbol audit, replacing the previous result
+    /// for the same library target.
+    pub fn set_symbol_audit(&mut self, audit: SymbolAudit) {
+        let symbol_audits = &mut self.current_database.db.symbol_audits;
+        if symbol_audits.iter().any(|a| a == &audit) {
+            return;
+        }
+        symbol_audits.retain(|a| a.env != audit.env);
+        symbol_audits.push(audit);
+        self.is_modified = true;
+    }
+
+    pub fn symbol_audits(&self) -> &[SymbolAudit] {
+        &self.current_database.db.symbol_audits
+    }
+
     pub fn find_rust_item(&self, path: &RustPath) -> Option<DbItem<&RustItem>> {
         self.current_database.find_rust_item(path)
     }
@@ -738,6 +1671,7 @@ impl DatabaseClient {
             id: id.clone(),
             source_id,
             item: DatabaseItemData::RustItem(item),
+            origin_location: None,
         });
         self.counters.items_added += 1;
         Ok(Some(id))
@@ -768,7 +1702,8 @@ impl DatabaseClient {
         // TODO: check for conflicts with types from crate template (how?)
     }
 
-    pub fn report_counters(&mut self) {
+    /// Logs and resets the counters of changed items.
+    pub fn report_counters(&mut self) -> Counters {
         if self.counters.items_added > 0 || self.counters.items_ignored > 0 {
             if self.counters.items_ignored == 0 {
                 info!("Items added: {}", self.counters.items_added);
@@ -782,7 +1717,7 @@ impl DatabaseClient {
         if self.counters.items_deleted > 0 {
             info!("Items deleted: {}", self.counters.items_deleted);
         }
-        self.counters = Counters::default();
+        mem::replace(&mut self.counters, Counters::default())
     }
 
     pub fn add_cpp_checks_item(
@@ -806,11 +1741,56 @@ impl DatabaseClient {
             id: id.clone(),
             source_id: Some(source_id),
             item: DatabaseItemData::CppChecksItem(item),
+            origin_location: None,
+        });
+        self.counters.items_added += 1;
+        Some(id)
+    }
+
+    pub fn add_rust_checks_item(&mut self, source_id: ItemId, item: RustChecksItem) -> ItemId {
+        let id = self.new_id();
+        self.current_database.push(DbItem {
+            id: id.clone(),
+            source_id: Some(source_id),
+            item: DatabaseItemData::RustChecksItem(item),
+            origin_location: None,
+        });
+        self.counters.items_added += 1;
+        id
+    }
+
+    pub fn add_cpp_dependent_function_item(
+        &mut self,
+        source_id: ItemId,
+        item: CppDependentFunction,
+    ) -> Option<ItemId> {
+        if self
+            .current_database
+            .filter_by_source(&Some(source_id.clone()))
+            .filter_map(|other| other.item.as_cpp_dependent_function_item())
+            .any(|other| other == &item)
+        {
+            self.counters.items_ignored += 1;
+            return None;
+        }
+        self.is_modified = true;
+        let id = self.new_id();
+        self.current_database.push(DbItem {
+            id: id.clone(),
+            source_id: Some(source_id),
+            item: DatabaseItemData::CppDependentFunctionItem(item),
+            origin_location: None,
         });
         self.counters.items_added += 1;
         Some(id)
     }
 
+    pub fn rust_checks(&self, source_id: &ItemId) -> impl Iterator<Item = &RustChecksItem> {
+        self.current_database
+            .filter_by_source(&Some(source_id.clone()))
+            .filter_map(|item| item.item.as_rust_checks_item())
+    }
+
     pub fn add_doc_item(&mut self, source_id: ItemId, item: DocItem) -> Option<ItemId> {
         if self
             .current_database
@@ -827,6 +1807,7 @@ impl DatabaseClient {
             id: id.clone(),
             source_id: Some(source_id),
             item: DatabaseItemData::DocItem(item),
+            origin_location: None,
         });
         self.counters.items_added += 1;
         Some(id)
@@ -983,6 +1964,18 @@ impl DatabaseClient {
         bail!("unknown cpp path: {}", cpp_path.to_cpp_pseudo_code())
     }
 
+    /// Returns the newtype of the handle typedef at `cpp_path`
+    /// (see `Config::add_handle_type`) from the current crate or its dependencies.
+    pub fn find_handle_type(&self, cpp_path: &CppPath) -> Option<DbItem<&RustStruct>> {
+        self.all_databases()
+            .flat_map(|d| d.db.rust_items())
+            .filter_map(|item| item.filter_map(|item| item.as_struct_ref()))
+            .find(|item| match &item.item.kind {
+                RustStructKind::HandleType(handle) => &handle.cpp_path == cpp_path,
+                _ => false,
+            })
+    }
+
     fn database(&self, crate_name: &str) -> Result<&IndexedDatabase> {
         self.all_databases()
             .find(|db| *db.db.crate_name == crate_name)
@@ -993,6 +1986,47 @@ impl DatabaseClient {
         Ok(&self.database(crate_name)?.db.crate_version)
     }
 
+    /// Records names of the direct ritual dependencies of the current crate.
+    /// All of them must be already loaded.
+    pub fn set_direct_dependencies(&mut self, crate_names: &[&str]) -> Result<()> {
+        let dependencies = crate_names.iter().map_if_ok(|&name| -> Result<_> {
+            Ok(DatabaseDependency {
+                crate_name: name.to_string(),
+                crate_version: self.dependency_version(name)?.to_string(),
+            })
+        })?;
+        if self.current_database.db.dependencies != dependencies {
+            self.is_modified = true;
+            self.current_database.db.dependencies = dependencies;
+        }
+        Ok(())
+    }
+
+    /// Returns direct ritual dependencies of the current crate.
+    pub fn direct_dependencies(&self) -> &[DatabaseDependency] {
+        &self.current_database.db.dependencies
+    }
+
+    /// Returns dependencies that were loaded only because a direct
+    /// dependency depends on them. Types from these crates may still appear
+    /// in the API, so the generated crate must depend on them as well.
+    pub fn indirect_dependencies(&self) -> impl Iterator<Item = DatabaseDependency> + '_ {
+        self.dependencies
+            .iter()
+            .filter(move |db| {
+                !self
+                    .current_database
+                    .db
+                    .dependencies
+                    .iter()
+                    .any(|dep| dep.crate_name == *db.db.crate_name)
+            })
+            .map(|db| DatabaseDependency {
+                crate_name: db.db.crate_name.to_string(),
+                crate_version: db.db.crate_version.clone(),
+            })
+    }
+
     pub fn print_item_trace(&self, item_id: &ItemId) -> Result<()> {
         info!("Sources:");
         let mut sources = Vec::new();
@@ -1022,14 +2056,17 @@ impl DatabaseClient {
     }
 
     fn print_item_children(&self, item_id: &ItemId) {
-        let item_id = Some(item_id.clone());
-        let children = self
-            .all_databases()
-            .flat_map(|db| db.filter_by_source(&item_id));
-
-        for child in children {
+        for child in self.children(item_id) {
             info!("{:?}", child);
             self.print_item_children(&child.id);
         }
     }
+
+    /// Returns all items whose source is `item_id`.
+    pub fn children(&self, item_id: &ItemId) -> Vec<DbItem<&DatabaseItemData>> {
+        let item_id = Some(item_id.clone());
+        self.all_databases()
+            .flat_map(|db| db.filter_by_source(&item_id))
+            .collect()
+    }
 }
@@ -0,0 +1,177 @@
+//! Heuristic translation of C++ code examples found in the documentation to Rust.
+//!
+//! The translation is purely textual and is not expected to compile. It's included
+//! in the generated documentation as an untested hint for the reader.
+
+use crate::database::{DocExample, ItemId};
+use crate::processor::ProcessorData;
+use crate::rust_info::RustFunctionKind;
+use log::info;
+use regex::{Captures, Regex};
+use ritual_common::errors::Result;
+use ritual_common::string_utils::CaseOperations;
+use std::collections::HashMap;
+
+/// Returns C++ code contained in `<pre>` blocks of `html`.
+fn code_blocks(html: &str) -> Vec<String> {
+    let pre_regex = Regex::new(r"(?s)<pre[^>]*>(.*?)</pre>").unwrap();
+    let tag_regex = Regex::new(r"<[^>]*>").unwrap();
+    pre_regex
+        .captures_iter(html)
+        .map(|captures| {
+            tag_regex
+                .replace_all(&captures[1], "")
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&#39;", "'")
+                .replace("&amp;", "&")
+                .trim_matches('\n')
+                .to_string()
+        })
+        .filter(|code| !code.trim().is_empty())
+        .collect()
+}
+
+/// Translates C++ example `code` to Rust. `method_names` maps C++ method names
+/// to names of the corresponding Rust methods.
+pub fn translate_example(code: &str, method_names: &HashMap<String, String>) -> String {
+    let type_pattern =
+        r"(?:const\s+)?(?:[A-Z][\w:]*(?:<[\w:,\s*]*>)?|int|bool|double|float|qreal|auto)";
+    let new_regex = Regex::new(r"\bnew\s+([A-Z][\w:]*)\s*(\(|;)").unwrap();
+    let constructor_regex = Regex::new(&format!(
+        r"^(\s*){}(?:\s*[*&]\s*|\s+)(\w+)\s*\((.*)\)\s*;",
+        type_pattern
+    ))
+    .unwrap();
+    let default_constructor_regex =
+        Regex::new(r"^(\s*)(?:const\s+)?([A-Z][\w:]*)\s+(\w+)\s*;").unwrap();
+    let declaration_regex = Regex::new(&format!(
+        r"^(\s*){}(?:\s*[*&]\s*|\s+)(\w+)\s*=\s*",
+        type_pattern
+    ))
+    .unwrap();
+    let method_regex = Regex::new(r"(\.|::)(\w+)\s*\(").unwrap();
+
+    code.lines()
+        .map(|line| {
+            let line = line.replace("->", ".").replace("this.", "self.");
+            let line = new_regex.replace_all(&line, |captures: &Captures<'_>| {
+                if &captures[2] == "(" {
+                    format!("{}::new(", &captures[1])
+                } else {
+                    format!("{}::new();", &captures[1])
+                }
+            });
+            let line = constructor_regex.replace(&line, |captures: &Captures<'_>| {
+                let type_name = captures[0]
+                    .trim_start()
+                    .trim_start_matches("const ")
+                    .split(|c: char| c.is_whitespace() || c == '*' || c == '&')
+                    .next()
+                    .unwrap_or("")
+                    .to_string();
+                format!(
+                    "{}let {} = {}::new({});",
+                    &captures[1], &captures[2], type_name, &captures[3]
+                )
+            });
+            let line = default_constructor_regex.replace(&line, "${1}let $3 = ${2}::new();");
+            let line = declaration_regex.replace(&line, "${1}let $2 = ");
+            method_regex
+                .replace_all(&line, |captures: &Captures<'_>| {
+                    let name = &captures[2];
+                    let rust_name = method_names
+                        .get(name)
+                        .cloned()
+                        .unwrap_or_else(|| name.to_snake_case());
+                    format!("{}{}(", &captures[1], rust_name)
+                })
+                .into_owned()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Returns the mapping of C++ method names to Rust method names
+/// based on the generated FFI wrappers. If a C++ method corresponds to
+/// multiple Rust methods (e.g. overloads), the shortest name is used.
+fn method_names(data: &ProcessorData<'_>) -> Result<HashMap<String, String>> {
+    let mut names = HashMap::<String, String>::new();
+    for item in data.db.rust_items() {
+        let function = match item.item.as_function_ref() {
+            Some(function) => function,
+            None => continue,
+        };
+        match function.kind {
+            RustFunctionKind::FfiWrapper(_) => {}
+            _ => continue,
+        }
+        let cpp_item = match data.db.source_cpp_item(&item.id)? {
+            Some(item) => item,
+            None => continue,
+        };
+        let cpp_name = match cpp_item.item.as_function_ref() {
+            Some(cpp_function) => cpp_function.path.last().name.clone(),
+            None => continue,
+        };
+        let rust_name = function.path.last().to_string();
+        let entry = names.entry(cpp_name).or_insert_with(|| rust_name.clone());
+        if rust_name.len() < entry.len() {
+            *entry = rust_name;
+        }
+    }
+    Ok(names)
+}
+
+/// Translates C++ code examples in all documentation items of the current crate.
+pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
+    let names = method_names(data)?;
+    let mut translations = Vec::<(ItemId, Vec<DocExample>)>::new();
+    for item in data.db.items() {
+        let doc = match item.item.as_doc_item() {
+            Some(doc) => doc,
+            None => continue,
+        };
+        let examples = code_blocks(&doc.html)
+            .into_iter()
+            .map(|cpp_code| DocExample {
+                rust_code: translate_example(&cpp_code, &names),
+                cpp_code,
+            })
+            .collect::<Vec<_>>();
+        if examples != doc.examples {
+            translations.push((item.id.clone(), examples));
+        }
+    }
+    info!("Translated examples in {} doc items", translations.len());
+    for (id, examples) in translations {
+        if let Some(doc) = data.db.item_mut(&id)?.item.as_doc_item_mut() {
+            doc.examples = examples;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn translate_example_works() {
+    let mut names = HashMap::new();
+    names.insert("setText".to_string(), "set_text".to_string());
+    let code = "QLabel *label = new QLabel(parent);\n\
+                label->setText(\"Hello\");\n\
+                QString text(\"x\");\n\
+                QSize size;\n\
+                int width = label->sizeHint().width();";
+    assert_eq!(
+        translate_example(code, &names),
+        "let label = QLabel::new(parent);\n\
+         label.set_text(\"Hello\");\n\
+         let text = QString::new(\"x\");\n\
+         let size = QSize::new();\n\
+         let width = label.size_hint().width();"
+    );
+    assert_eq!(
+        code_blocks("<p>x</p><pre class=\"cpp\"><span>a</span> &lt; b;</pre>"),
+        vec!["a < b;".to_string()]
+    );
+}
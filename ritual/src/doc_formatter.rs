@@ -3,6 +3,7 @@
 
 #![allow(dead_code)]
 
+use crate::cpp_data::{CppItem, CppPath};
 use crate::cpp_ffi_data::{CppFfiFunctionKind, CppFfiItem, CppFieldAccessorType};
 use crate::cpp_type::CppType;
 use crate::database::{DatabaseClient, DbItem, DocItem};
@@ -14,6 +15,7 @@ use crate::rust_info::{
 use itertools::Itertools;
 use ritual_common::errors::{bail, err_msg, Result};
 use std::fmt::Write;
+use std::path::Path;
 
 pub fn wrap_inline_cpp_code(code: &str) -> String {
     format!("<span style='color: green;'>```{}```</span>", code)
@@ -55,6 +57,19 @@ pub fn module_doc(module: DbItem<&RustModule>, database: &DatabaseClient) -> Res
                     "Types with the same size and alignment as corresponding C++ types"
                 )?;
             }
+            RustSpecialModuleKind::Unsafe => {
+                write!(
+                    output,
+                    "Raw variants of functions that have safe wrappers in other modules"
+                )?;
+            }
+            RustSpecialModuleKind::Prelude => {
+                write!(
+                    output,
+                    "Commonly used types and traits. Use `use {}::prelude::*;` to import them",
+                    database.crate_name()
+                )?;
+            }
         },
         RustModuleKind::CppNamespace { .. } => {
             let cpp_item = database
@@ -92,8 +107,13 @@ fn first_phrase(html: &str) -> &str {
     html
 }
 
-pub fn struct_doc(type1: DbItem<&RustStruct>, database: &DatabaseClient) -> Result<String> {
+pub fn struct_doc(
+    type1: DbItem<&RustStruct>,
+    database: &DatabaseClient,
+    cpp_doc_url_template: Option<&str>,
+) -> Result<String> {
     let mut output = String::new();
+    let mut declaration_section = String::new();
 
     let doc_item = database.find_doc_for(&type1.id)?;
     if let Some(doc_item) = &doc_item {
@@ -113,9 +133,11 @@ pub fn struct_doc(type1: DbItem<&RustStruct>, database: &DatabaseClient) -> Resu
                 .path()
                 .ok_or_else(|| err_msg("cpp item expected to have path"))?
                 .to_cpp_pseudo_code();
+            declaration_section =
+                cpp_declaration_section(&cpp_item, database, cpp_doc_url_template)?;
 
             match kind {
-                RustWrapperTypeKind::EnumWrapper => {
+                RustWrapperTypeKind::EnumWrapper { .. } => {
                     writeln!(
                         output,
                         "C++ enum: {}.\n",
@@ -129,6 +151,42 @@ pub fn struct_doc(type1: DbItem<&RustStruct>, database: &DatabaseClient) -> Resu
                         wrap_inline_cpp_code(&cpp_type_code)
                     )?;
                 }
+                RustWrapperTypeKind::PinnedClassWrapper => {
+                    writeln!(
+                        output,
+                        "C++ class: {}.\n",
+                        wrap_inline_cpp_code(&cpp_type_code)
+                    )?;
+                    writeln!(
+                        output,
+                        "Objects of this class can't be moved after construction, \
+                         so owned objects are returned as `Pin<CppBox<T>>`.\n"
+                    )?;
+                }
+                RustWrapperTypeKind::UnionWrapper { .. } => {
+                    writeln!(
+                        output,
+                        "C++ union: {}.\n",
+                        wrap_inline_cpp_code(&cpp_type_code)
+                    )?;
+                    writeln!(
+                        output,
+                        "This union has the same memory layout as the C++ union. \
+                         Reading its fields is unsafe.\n"
+                    )?;
+                }
+                RustWrapperTypeKind::PlainStructWrapper { .. } => {
+                    writeln!(
+                        output,
+                        "C++ class: {}.\n",
+                        wrap_inline_cpp_code(&cpp_type_code)
+                    )?;
+                    writeln!(
+                        output,
+                        "This struct has the same memory layout as the C++ class. \
+                         It's passed by value, and its fields can be accessed directly.\n"
+                    )?;
+                }
                 RustWrapperTypeKind::MovableClassWrapper { .. } => {
                     // not supported now
                 }
@@ -168,7 +226,9 @@ pub fn struct_doc(type1: DbItem<&RustStruct>, database: &DatabaseClient) -> Resu
                     .item;
 
                 let cpp_signal_arguments = match ffi_item {
-                    CppFfiItem::Function(_) => bail!("invalid source ffi item type"),
+                    CppFfiItem::Function(_)
+                    | CppFfiItem::ValueLayout(_)
+                    | CppFfiItem::FieldOffset(_) => bail!("invalid source ffi item type"),
                     CppFfiItem::QtSlotWrapper(w) => &w.signal_arguments,
                     CppFfiItem::QtSignalWrapper(w) => &w.signal_arguments,
                 };
@@ -220,11 +280,28 @@ pub fn struct_doc(type1: DbItem<&RustStruct>, database: &DatabaseClient) -> Resu
         }
         // private struct, no doc needed
         RustStructKind::SizedType(_) => {}
+        RustStructKind::HandleType(handle) => {
+            writeln!(
+                output,
+                "C++ type: {}.\n\nHandle identifying a resource of the C++ library.\n",
+                wrap_inline_cpp_code(&handle.cpp_path.to_cpp_pseudo_code())
+            )?;
+        }
+        RustStructKind::OwnedHandleType(owned) => {
+            writeln!(
+                output,
+                "Owned `{}` that is released when dropped.\n\n\
+                 Dropping the value calls {}.\n",
+                owned.handle_path.last(),
+                wrap_inline_cpp_code(&owned.release.to_cpp_pseudo_code())
+            )?;
+        }
     };
 
     if let Some(doc_item) = doc_item {
-        write!(output, "{}", format_doc_item(doc_item.item))?;
+        write!(output, "{}", format_doc_item(doc_item.item, database)?)?;
     }
+    write!(output, "{}", declaration_section)?;
     Ok(output)
 }
 
@@ -244,12 +321,73 @@ pub fn enum_value_doc(value: DbItem<&RustEnumValue>, database: &DatabaseClient)
             value.item.value
         ))
     );
+    if let Some(alias_of) = &cpp_item.alias_of {
+        doc = format!(
+            "{}, alias of {}",
+            doc,
+            wrap_inline_cpp_code(&alias_of.last().name)
+        );
+    }
     if let Some(doc_item) = database.find_doc_for(&value.id)? {
         doc = format!("{} ({})", doc_item.item.html, doc);
     }
     Ok(doc)
 }
 
+/// Formats the section referring to the C++ declaration of `cpp_item`,
+/// its header file and (if `url_template` is set) its upstream documentation.
+fn cpp_declaration_section(
+    cpp_item: &DbItem<&CppItem>,
+    database: &DatabaseClient,
+    url_template: Option<&str>,
+) -> Result<String> {
+    let path = match cpp_item.item.path() {
+        Some(path) => path,
+        None => return Ok(String::new()),
+    };
+    let mut declaration = path.to_cpp_pseudo_code();
+    if cpp_item.item.as_function_ref().is_some() {
+        declaration.push_str("()");
+    }
+    let mut location = database.cpp_origin_location(&cpp_item.id)?;
+    if location.is_none() {
+        if let Some(original) = database.original_cpp_item(&cpp_item.id)? {
+            location = database.cpp_origin_location(&original.id)?;
+        }
+    }
+    let header = location.and_then(|location| {
+        Path::new(&location.include_file_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+    });
+
+    let declaration = if let Some(template) = url_template {
+        let scope = path
+            .parent()
+            .map(|parent| parent.to_templateless_string())
+            .unwrap_or_default();
+        let url = template
+            .replace("{path}", &path.to_templateless_string())
+            .replace("{name}", &path.last().name)
+            .replace("{scope_lowercase}", &scope.to_lowercase())
+            .replace("{scope}", &scope)
+            .replace("{header}", header.as_ref().map_or("", String::as_str))
+            .replace(
+                "{line}",
+                &location.map_or(String::new(), |location| location.line.to_string()),
+            );
+        format!("[`{}`]({})", declaration, url)
+    } else {
+        format!("`{}`", declaration)
+    };
+    let mut output = format!("\n\nC++ documentation: {}", declaration);
+    if let Some(header) = header {
+        write!(output, " (declared in `{}`)", header)?;
+    }
+    writeln!(output, ".\n")?;
+    Ok(output)
+}
+
 fn format_maybe_link(url: &Option<String>, text: &str) -> String {
     if let Some(url) = url {
         format!("<a href=\"{}\">{}</a>", url, text)
@@ -258,7 +396,7 @@ fn format_maybe_link(url: &Option<String>, text: &str) -> String {
     }
 }
 
-fn format_doc_item(cpp_doc: &DocItem) -> String {
+fn format_doc_item(cpp_doc: &DocItem, database: &DatabaseClient) -> Result<String> {
     let mut output = if let Some(declaration) = &cpp_doc.mismatched_declaration {
         format!(
             "Warning: no exact match found in C++ documentation. \
@@ -269,11 +407,54 @@ fn format_doc_item(cpp_doc: &DocItem) -> String {
     } else {
         format!("{}:", format_maybe_link(&cpp_doc.url, "C++ documentation"))
     };
-    write!(output, "{}", wrap_cpp_doc_block(&cpp_doc.html)).unwrap();
-    output
+    write!(output, "{}", wrap_cpp_doc_block(&cpp_doc.html))?;
+    let links = cpp_doc
+        .cpp_cross_references
+        .iter()
+        .map(|path| cross_reference_link(path, database))
+        .collect_vec();
+    if !links.is_empty() {
+        write!(output, "\n\nSee also: {}.\n\n", links.join(", "))?;
+    }
+    for example in &cpp_doc.examples {
+        write!(
+            output,
+            "\n\nC++ example:\n\n```cpp\n{}\n```\n\n\
+             Automatic translation to Rust (untested, may not compile):\n\n\
+             ```ignore\n{}\n```\n\n",
+            example.cpp_code, example.rust_code
+        )?;
+    }
+    Ok(output)
 }
 
-pub fn function_doc(function: DbItem<&RustFunction>, database: &DatabaseClient) -> Result<String> {
+/// Formats a link to the Rust item corresponding to C++ item `path`.
+/// Falls back to the parent item (e.g. the class of a method) and then to
+/// plain C++ name if there is no such Rust item.
+fn cross_reference_link(path: &CppPath, database: &DatabaseClient) -> String {
+    let mut current = Some(path.clone());
+    while let Some(cpp_path) = current {
+        let rust_path = database
+            .find_rust_items_for_cpp_path(&cpp_path, true)
+            .ok()
+            .and_then(|mut items| items.find_map(|item| item.item.path().cloned()));
+        if let Some(rust_path) = rust_path {
+            return format!(
+                "[`{}`]({})",
+                path.to_cpp_pseudo_code(),
+                rust_path.full_name(Some(database.crate_name()))
+            );
+        }
+        current = cpp_path.parent().ok();
+    }
+    format!("`{}`", path.to_cpp_pseudo_code())
+}
+
+pub fn function_doc(
+    function: DbItem<&RustFunction>,
+    database: &DatabaseClient,
+    cpp_doc_url_template: Option<&str>,
+) -> Result<String> {
     let cpp_item = database
         .source_cpp_item(&function.id)?
         .ok_or_else(|| err_msg("source cpp item not found"))?;
@@ -416,6 +597,38 @@ pub fn function_doc(function: DbItem<&RustFunction>, database: &DatabaseClient)
                         }
                     };
                 }
+                CppFfiFunctionKind::VariableAddress | CppFfiFunctionKind::VariableValue => {
+                    let variable = cpp_item
+                        .item
+                        .as_variable_ref()
+                        .ok_or_else(|| err_msg("invalid source cpp item type"))?;
+                    let kind_text = if variable.is_static_member {
+                        "static data member"
+                    } else {
+                        "global variable"
+                    };
+                    let result_text = if cpp_ffi_function.kind == CppFfiFunctionKind::VariableValue
+                    {
+                        "the value of"
+                    } else {
+                        "a pointer to"
+                    };
+                    write!(
+                        output,
+                        "Returns {} the {} {}.",
+                        result_text,
+                        wrap_inline_cpp_code(&variable.path.to_cpp_pseudo_code()),
+                        kind_text
+                    )?;
+                }
+            }
+            if cpp_ffi_function.main_thread_only {
+                write!(
+                    output,
+                    "\n\nThis function must only be called from the main thread \
+                     (the thread that loaded the library). Calling it from another thread \
+                     aborts the process.\n\n"
+                )?;
             }
         }
         RustFunctionKind::SignalOrSlotGetter(_) => {
@@ -444,7 +657,14 @@ pub fn function_doc(function: DbItem<&RustFunction>, database: &DatabaseClient)
         RustFunctionKind::FfiFunction => {}
     }
     if let Some(doc_item) = database.find_doc_for(&function.id)? {
-        write!(output, "{}", format_doc_item(doc_item.item))?;
+        write!(output, "{}", format_doc_item(doc_item.item, database)?)?;
+    }
+    if function.item.kind != RustFunctionKind::FfiFunction {
+        write!(
+            output,
+            "{}",
+            cpp_declaration_section(&cpp_item, database, cpp_doc_url_template)?
+        )?;
     }
     Ok(output)
 }
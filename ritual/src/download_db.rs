@@ -1,6 +1,6 @@
 // inspired by https://github.com/Xion/cargo-download/
 
-use crate::database::CRATE_DB_FILE_NAME;
+use crate::database::{CRATE_DB_FILE_NAME, LEGACY_CRATE_DB_FILE_NAME};
 use log::{info, trace};
 use reqwest::header::CONTENT_LENGTH;
 use ritual_common::errors::{bail, Result};
@@ -43,9 +43,13 @@ pub fn download_db(crate_name: &str, crate_version: &str, path: impl AsRef<Path>
     for entry in archive.entries()? {
         let mut entry = entry?;
         let entry_path = entry.path()?;
-        if entry_path.components().count() == 2
-            && entry_path.components().nth(1).unwrap().as_os_str() == CRATE_DB_FILE_NAME
-        {
+        // crates generated by older versions contain the legacy file,
+        // which can be loaded as well
+        let is_db_file = entry_path.components().count() == 2
+            && [CRATE_DB_FILE_NAME, LEGACY_CRATE_DB_FILE_NAME]
+                .iter()
+                .any(|name| entry_path.components().nth(1).unwrap().as_os_str() == *name);
+        if is_db_file {
             info!("Unpacking database file");
             entry.unpack(path)?;
             info!("Database file unpacked");
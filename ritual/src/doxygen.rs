@@ -0,0 +1,483 @@
+//! Importer of Doxygen XML documentation.
+//!
+//! Doxygen produces one XML file per documented compound (class, namespace, file).
+//! Documentation entries are matched to C++ items by qualified name. Functions
+//! are additionally matched by argument types and constness.
+
+use crate::cpp_data::{CppItem, CppPath};
+use crate::cpp_function::CppFunction;
+use crate::database::{DocItem, ItemWithSource};
+use crate::html_logger::escape_html;
+use crate::processor::ProcessorData;
+use itertools::Itertools;
+use log::{debug, info, trace};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use ritual_common::errors::{format_err, Result, ResultExt};
+use ritual_common::file_utils::{file_to_string, os_str_to_str, read_dir};
+use roxmltree::{Document, Node};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Namespace or class qualifier of a name (e.g. `std::`).
+static QUALIFIER: Lazy<Regex> = Lazy::new(|| Regex::new(r"[A-Za-z_]\w*::").unwrap());
+
+/// `const` qualifier after the argument list of a member function.
+static CONST_QUALIFIER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\)\s*const\b").unwrap());
+
+/// A documentation entry of a compound or a member.
+#[derive(Debug, Clone)]
+struct DoxygenEntry {
+    /// Doxygen id of the entry.
+    id: String,
+    /// Doxygen id of the compound containing the entry.
+    compound_id: String,
+    /// C++ declaration as written by Doxygen.
+    declaration: String,
+    /// Normalized types of function arguments.
+    argument_types: Vec<String>,
+    /// True if this is a const member function.
+    is_const: bool,
+    html: String,
+    /// Doxygen ids of entries referenced in the description.
+    references: Vec<String>,
+}
+
+/// Removes whitespaces and namespace qualifiers from a C++ type
+/// to compare types written in documentation and by the C++ parser.
+pub fn normalize_type(text: &str) -> String {
+    let text = text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>();
+    QUALIFIER.replace_all(&text, "").into_owned()
+}
+
+fn node_text(node: Node<'_, '_>) -> String {
+    node.descendants()
+        .filter(|node| node.is_text())
+        .filter_map(|node| node.text())
+        .collect()
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+fn child_text(node: Node<'_, '_>, name: &str) -> String {
+    child(node, name).map(node_text).unwrap_or_default()
+}
+
+/// Converts a Doxygen description to HTML.
+struct DescriptionWriter {
+    html: String,
+    references: Vec<String>,
+}
+
+impl DescriptionWriter {
+    fn write_children(&mut self, node: Node<'_, '_>) {
+        for child in node.children() {
+            self.write(child);
+        }
+    }
+
+    fn write_wrapped(&mut self, node: Node<'_, '_>, tag: &str) {
+        self.html += &format!("<{}>", tag);
+        self.write_children(node);
+        self.html += &format!("</{}>", tag);
+    }
+
+    fn write(&mut self, node: Node<'_, '_>) {
+        if node.is_text() {
+            self.html += &escape_html(node.text().unwrap_or(""));
+            return;
+        }
+        if !node.is_element() {
+            return;
+        }
+        match node.tag_name().name() {
+            "para" => self.write_wrapped(node, "p"),
+            "bold" => self.write_wrapped(node, "b"),
+            "emphasis" => self.write_wrapped(node, "i"),
+            "computeroutput" => self.write_wrapped(node, "code"),
+            "itemizedlist" => self.write_wrapped(node, "ul"),
+            "orderedlist" => self.write_wrapped(node, "ol"),
+            "listitem" => self.write_wrapped(node, "li"),
+            "programlisting" => self.write_wrapped(node, "pre"),
+            "codeline" => {
+                self.write_children(node);
+                self.html.push('\n');
+            }
+            "sp" => self.html.push(' '),
+            "linebreak" => self.html += "<br>",
+            "ref" => {
+                if let Some(id) = node.attribute("refid") {
+                    self.references.push(id.to_string());
+                }
+                self.write_wrapped(node, "code");
+            }
+            "ulink" => {
+                self.html += &format!(
+                    "<a href=\"{}\">",
+                    escape_html(node.attribute("url").unwrap_or(""))
+                );
+                self.write_children(node);
+                self.html += "</a>";
+            }
+            "simplesect" => {
+                let title = match node.attribute("kind") {
+                    Some("return") => "Returns",
+                    Some("see") => "See also",
+                    Some("note") => "Note",
+                    Some("warning") => "Warning",
+                    Some("since") => "Since",
+                    Some("deprecated") => "Deprecated",
+                    _ => {
+                        self.write_children(node);
+                        return;
+                    }
+                };
+                self.html += &format!("<p><b>{}:</b></p>", title);
+                self.write_children(node);
+            }
+            "parameterlist" => {
+                let title = match node.attribute("kind") {
+                    Some("templateparam") => "Template parameters",
+                    Some("exception") => "Exceptions",
+                    _ => "Parameters",
+                };
+                self.html += &format!("<p><b>{}:</b></p><ul>", title);
+                for item in node.children().filter(|n| n.has_tag_name("parameteritem")) {
+                    self.html += "<li>";
+                    let names = item
+                        .descendants()
+                        .filter(|n| n.has_tag_name("parametername"))
+                        .map(|n| format!("<code>{}</code>", escape_html(&node_text(n))))
+                        .join(", ");
+                    self.html += &names;
+                    if let Some(description) = child(item, "parameterdescription") {
+                        self.html += " - ";
+                        self.write_children(description);
+                    }
+                    self.html += "</li>";
+                }
+                self.html += "</ul>";
+            }
+            _ => self.write_children(node),
+        }
+    }
+}
+
+/// Converts brief and detailed descriptions of `node` to HTML.
+/// Returns the HTML and Doxygen ids of the referenced entries.
+fn description_html(node: Node<'_, '_>) -> (String, Vec<String>) {
+    let mut writer = DescriptionWriter {
+        html: String::new(),
+        references: Vec::new(),
+    };
+    for name in &["briefdescription", "detaileddescription"] {
+        if let Some(description) = child(node, name) {
+            writer.write_children(description);
+        }
+    }
+    (writer.html.trim().to_string(), writer.references)
+}
+
+/// Documentation entries loaded from a Doxygen XML directory.
+#[derive(Debug, Default)]
+struct DoxygenIndex {
+    /// Entries by qualified C++ name.
+    entries: HashMap<String, Vec<DoxygenEntry>>,
+    /// Qualified C++ names by Doxygen id.
+    names: HashMap<String, String>,
+}
+
+impl DoxygenIndex {
+    fn add(&mut self, name: String, entry: DoxygenEntry) {
+        self.names.insert(entry.id.clone(), name.clone());
+        let entries = self.entries.entry(name).or_default();
+        if entries.iter().all(|e| e.id != entry.id) {
+            entries.push(entry);
+        }
+    }
+
+    fn add_member(&mut self, scope: Option<&str>, compound_id: &str, member: Node<'_, '_>) {
+        let id = member.attribute("id").unwrap_or("").to_string();
+        let name = child_text(member, "name");
+        let qualified_name = child(member, "qualifiedname")
+            .map(node_text)
+            .unwrap_or_else(|| match scope {
+                Some(scope) => format!("{}::{}", scope, name),
+                None => name.clone(),
+            });
+        let args = child_text(member, "argsstring");
+        let (html, references) = description_html(member);
+
+        if member.attribute("kind") == Some("enum") {
+            for value in member.children().filter(|n| n.has_tag_name("enumvalue")) {
+                let (html, references) = description_html(value);
+                let value_name = child_text(value, "name");
+                self.add(
+                    format!("{}::{}", qualified_name, value_name),
+                    DoxygenEntry {
+                        id: value.attribute("id").unwrap_or("").to_string(),
+                        compound_id: compound_id.to_string(),
+                        declaration: value_name,
+                        argument_types: Vec::new(),
+                        is_const: false,
+                        html,
+                        references,
+                    },
+                );
+            }
+        }
+
+        let argument_types = member
+            .children()
+            .filter(|n| n.has_tag_name("param"))
+            .map(|param| normalize_type(&child_text(param, "type")))
+            .filter(|type1| type1 != "void")
+            .collect();
+        let entry = DoxygenEntry {
+            id,
+            compound_id: compound_id.to_string(),
+            declaration: format!("{}{}", child_text(member, "definition"), args),
+            argument_types,
+            is_const: CONST_QUALIFIER.is_match(&args),
+            html,
+            references,
+        };
+        self.add(qualified_name, entry);
+    }
+
+    fn add_file(&mut self, content: &str) -> Result<()> {
+        let document =
+            Document::parse(content).map_err(|err| format_err!("invalid XML: {}", err))?;
+        for compound in document
+            .root_element()
+            .children()
+            .filter(|n| n.has_tag_name("compounddef"))
+        {
+            let compound_id = compound.attribute("id").unwrap_or("");
+            let kind = compound.attribute("kind").unwrap_or("");
+            let compound_name = child_text(compound, "compoundname");
+            let scope = match kind {
+                "class" | "struct" | "union" | "namespace" => {
+                    let (html, references) = description_html(compound);
+                    self.add(
+                        compound_name.clone(),
+                        DoxygenEntry {
+                            id: compound_id.to_string(),
+                            compound_id: compound_id.to_string(),
+                            declaration: format!("{} {}", kind, compound_name),
+                            argument_types: Vec::new(),
+                            is_const: false,
+                            html,
+                            references,
+                        },
+                    );
+                    Some(compound_name.as_str())
+                }
+                "file" => None,
+                _ => continue,
+            };
+            for section in compound.children().filter(|n| n.has_tag_name("sectiondef")) {
+                for member in section.children().filter(|n| n.has_tag_name("memberdef")) {
+                    self.add_member(scope, compound_id, member);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn load(path: &Path) -> Result<Self> {
+        let mut index = DoxygenIndex::default();
+        let mut file_paths = Vec::new();
+        for item in read_dir(path)? {
+            let item = item?;
+            let file_name = os_str_to_str(&item.file_name())?.to_string();
+            if file_name.ends_with(".xml") && file_name != "index.xml" {
+                file_paths.push(item.path());
+            }
+        }
+        file_paths.sort();
+        for file_path in file_paths {
+            let content = file_to_string(&file_path)?;
+            index
+                .add_file(&content)
+                .with_context(|_| format!("failed to parse {}", file_path.display()))?;
+        }
+        Ok(index)
+    }
+
+    /// Returns entries suitable for documenting `function`
+    /// and true if they match the function's signature exactly.
+    fn function_entries(&self, function: &CppFunction) -> (Vec<&DoxygenEntry>, bool) {
+        let entries = match self.entries.get(&function.path.to_templateless_string()) {
+            Some(entries) => entries,
+            None => return (Vec::new(), false),
+        };
+        let argument_types = function
+            .arguments
+            .iter()
+            .map(|arg| normalize_type(&arg.argument_type.to_cpp_pseudo_code()))
+            .collect_vec();
+        let is_const = function.member.as_ref().map_or(false, |m| m.is_const);
+        let exact = entries
+            .iter()
+            .filter(|entry| entry.argument_types == argument_types && entry.is_const == is_const)
+            .collect_vec();
+        if exact.is_empty() {
+            (entries.iter().collect(), false)
+        } else {
+            (exact, true)
+        }
+    }
+
+    fn doc_item(&self, entry: &DoxygenEntry, is_exact: bool, html_url: Option<&str>) -> DocItem {
+        let url = html_url.map(|base| {
+            let page = format!("{}/{}.html", base.trim_end_matches('/'), entry.compound_id);
+            let prefix = format!("{}_1", entry.compound_id);
+            if entry.id.starts_with(&prefix) {
+                format!("{}#{}", page, &entry.id[prefix.len()..])
+            } else {
+                page
+            }
+        });
+        let cpp_cross_references = entry
+            .references
+            .iter()
+            .filter_map(|id| self.names.get(id))
+            .unique()
+            .filter_map(|name| CppPath::from_str(name).ok())
+            .collect();
+        DocItem {
+            anchor: Some(entry.id.clone()),
+            html: entry.html.clone(),
+            mismatched_declaration: if is_exact {
+                None
+            } else {
+                Some(entry.declaration.clone())
+            },
+            url,
+            cross_references: Vec::new(),
+            cpp_cross_references,
+            examples: Vec::new(),
+        }
+    }
+}
+
+/// Attaches documentation from the Doxygen XML output specified in
+/// `Config::set_doxygen_xml_path` to C++ items of the current crate.
+pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
+    let path = match data.config.doxygen_xml_path() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let index = DoxygenIndex::load(path)?;
+    let html_url = data.config.doxygen_html_url();
+    let mut new_items = Vec::new();
+    let mut used_entries = HashSet::new();
+    for item in data.db.cpp_items() {
+        if item.source_id.is_some() {
+            continue;
+        }
+        let (entries, is_exact) = match &item.item {
+            CppItem::Function(function) => index.function_entries(function),
+            CppItem::Namespace(_)
+            | CppItem::Type(_)
+            | CppItem::EnumValue(_)
+            | CppItem::ClassField(_)
+            | CppItem::Variable(_) => {
+                let path = item.item.path().expect("item must have path");
+                match index.entries.get(&path.to_templateless_string()) {
+                    Some(entries) => (entries.iter().collect(), true),
+                    None => (Vec::new(), false),
+                }
+            }
+            CppItem::ClassBase(_) => continue,
+        };
+        let entry = match entries.iter().find(|entry| !entry.html.is_empty()) {
+            Some(entry) => entry,
+            None => {
+                trace!("no Doxygen documentation for {}", item.item);
+                continue;
+            }
+        };
+        if !is_exact {
+            debug!(
+                "no exact Doxygen documentation match for {}; using {}",
+                item.item, entry.declaration
+            );
+        }
+        used_entries.insert(entry.id.clone());
+        new_items.push(ItemWithSource::new(
+            &item.id,
+            index.doc_item(entry, is_exact, html_url),
+        ));
+    }
+    let count = new_items.len();
+    for item in new_items {
+        data.db.add_doc_item(item.source_id, item.item);
+    }
+    info!(
+        "Doxygen documentation found for {} items ({} entries used)",
+        count,
+        used_entries.len()
+    );
+    Ok(())
+}
+
+#[test]
+fn description_to_html() {
+    let xml = r#"<doxygen><compounddef id="classns_1_1Foo" kind="class">
+        <compoundname>ns::Foo</compoundname>
+        <briefdescription><para>A <bold>foo</bold>.</para></briefdescription>
+        <detaileddescription><para>See <ref refid="classns_1_1Bar">Bar</ref>.</para>
+        </detaileddescription>
+        <sectiondef kind="public-func">
+          <memberdef kind="function" id="classns_1_1Foo_1a01" const="yes">
+            <type>int</type><definition>int ns::Foo::get</definition>
+            <argsstring>(const Bar &amp;bar) const</argsstring><name>get</name>
+            <param><type>const <ref refid="classns_1_1Bar">Bar</ref> &amp;</type>
+            <declname>bar</declname></param>
+            <briefdescription><para>Returns x.</para></briefdescription>
+            <detaileddescription/>
+          </memberdef>
+        </sectiondef>
+      </compounddef></doxygen>"#;
+    let mut index = DoxygenIndex::default();
+    index.add_file(xml).unwrap();
+    index
+        .names
+        .insert("classns_1_1Bar".to_string(), "ns::Bar".to_string());
+
+    let class = &index.entries["ns::Foo"][0];
+    assert_eq!(
+        class.html,
+        "<p>A <b>foo</b>.</p><p>See <code>Bar</code>.</p>"
+    );
+    let doc = index.doc_item(class, true, Some("https://example.com/docs/"));
+    assert_eq!(
+        doc.url.as_ref().map(String::as_str),
+        Some("https://example.com/docs/classns_1_1Foo.html")
+    );
+    assert_eq!(
+        doc.cpp_cross_references,
+        vec![CppPath::from_str("ns::Bar").unwrap()]
+    );
+
+    let method = &index.entries["ns::Foo::get"][0];
+    assert_eq!(method.argument_types, vec!["constBar&".to_string()]);
+    assert!(method.is_const);
+    let doc = index.doc_item(method, false, Some("https://example.com/docs"));
+    assert_eq!(
+        doc.url.as_ref().map(String::as_str),
+        Some("https://example.com/docs/classns_1_1Foo.html#a01")
+    );
+    assert_eq!(
+        doc.mismatched_declaration.as_ref().map(String::as_str),
+        Some("int ns::Foo::get(const Bar &bar) const")
+    );
+}
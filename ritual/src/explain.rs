@@ -0,0 +1,143 @@
+//! Explains what happened to a C++ item during processing.
+
+use crate::config::Config;
+use crate::cpp_checker::check_cpp_parser_hook;
+use crate::cpp_data::{CppItem, CppPath};
+use crate::database::{DatabaseClient, DbItem, ItemId};
+use itertools::Itertools;
+use log::info;
+use ritual_common::errors::Result;
+use std::str::FromStr;
+
+/// Prints information about all stages of processing of C++ items
+/// with the specified qualified name (e.g. `QObject::property`).
+pub fn explain(config: &Config, db: &DatabaseClient, name: &str) -> Result<()> {
+    let path = CppPath::from_str(name)?;
+    let items = db
+        .all_cpp_items()
+        .filter(|item| {
+            item.item.path().map_or(false, |item_path| {
+                item_path.to_templateless_string() == name
+            })
+        })
+        .collect_vec();
+
+    if items.is_empty() {
+        info!("{}: no C++ items found", name);
+        if let Some(hook) = config.cpp_parser_path_hook() {
+            if !hook(&path)? {
+                info!("    blocked by cpp_parser_path_hook");
+                return Ok(());
+            }
+        }
+        info!(
+            "    the item wasn't parsed; check include directives and target include paths \
+             (in lenient mode, see also the log of skipped constructs)"
+        );
+        return Ok(());
+    }
+
+    for item in items {
+        explain_cpp_item(config, db, item)?;
+    }
+    Ok(())
+}
+
+fn explain_annotations(db: &DatabaseClient, id: &ItemId, indent: &str) -> Result<()> {
+    for annotation in db.annotations(id)? {
+        info!(
+            "{}note (rev. {}, {}, version {}): {}",
+            indent, annotation.revision, annotation.date, annotation.crate_version, annotation.text
+        );
+    }
+    Ok(())
+}
+
+fn explain_cpp_item(config: &Config, db: &DatabaseClient, item: DbItem<&CppItem>) -> Result<()> {
+    info!("{}: {}", item.id, item.item);
+    explain_annotations(db, &item.id, "    ")?;
+    match db.cpp_origin_location(&item.id)? {
+        Some(location) => info!("    declared at {}", location),
+        None => info!("    declaration location is unknown"),
+    }
+    if let Some(source_id) = &item.source_id {
+        info!("    {}", db.item_context(source_id));
+    }
+
+    if item.item.path().map_or(false, |path| db.is_blocked(path)) {
+        info!("    blocked in the database");
+    }
+    if let Some(reason) = item
+        .item
+        .path()
+        .and_then(|path| db.known_crash_reason(path))
+    {
+        info!("    known to crash at runtime: {}", reason);
+    }
+    if let Some(hook) = config.cpp_item_filter_hook() {
+        if !hook(item.item)? {
+            info!("    blocked by cpp_item_filter_hook");
+        }
+    }
+    if let Some(hook) = config.cpp_parser_path_hook() {
+        if !check_cpp_parser_hook(item.item, &hook)? {
+            info!(
+                "    blocked by cpp_parser_path_hook (will be deleted by delete_blacklisted_items)"
+            );
+        }
+    }
+
+    let children = db.children(&item.id);
+    for conflict in children
+        .iter()
+        .filter_map(|child| child.item.as_cpp_conflict_item())
+    {
+        info!("    conflicting definition: {}", conflict.item);
+    }
+
+    let ffi_items = children
+        .iter()
+        .filter_map(|child| child.clone().filter_map(|item| item.as_ffi_item()))
+        .collect_vec();
+    if ffi_items.is_empty() {
+        info!("    no FFI items (see debug log of cpp_ffi_generator for the reason)");
+    }
+    for ffi_item in ffi_items {
+        info!(
+            "    FFI item {}: {}",
+            ffi_item.id,
+            ffi_item.item.short_text()
+        );
+        explain_annotations(db, &ffi_item.id, "        ")?;
+        let checks = db.cpp_checks(&ffi_item.id)?;
+        for env in db.environments() {
+            let status = if !checks.has_env(env) {
+                "not checked"
+            } else if checks.is_success(env) {
+                "success"
+            } else {
+                "failed"
+            };
+            info!("        check for {}: {}", env.short_text(), status);
+        }
+        explain_rust_items(db, &ffi_item.id, 2)?;
+    }
+    explain_rust_items(db, &item.id, 1)?;
+    Ok(())
+}
+
+fn explain_rust_items(db: &DatabaseClient, source_id: &ItemId, level: usize) -> Result<()> {
+    let indent = "    ".repeat(level);
+    for child in db.children(source_id) {
+        if let Some(rust_item) = child.item.as_rust_item() {
+            info!(
+                "{}Rust item {}: {}",
+                indent,
+                child.id,
+                rust_item.short_text()
+            );
+            explain_annotations(db, &child.id, &"    ".repeat(level + 1))?;
+        }
+    }
+    Ok(())
+}
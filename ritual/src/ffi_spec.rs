@@ -0,0 +1,158 @@
+//! Export and import of the FFI specification for manual review.
+//!
+//! `export_ffi_spec` writes all FFI items of the current crate to a human-readable
+//! JSON file. The file can be reviewed, edited or filtered (removing an entry
+//! removes the FFI item). `import_ffi_spec` applies the reviewed file to the database,
+//! after which the code generators only see the approved items. Removed items are
+//! recorded in the database, so `cpp_ffi_generator` doesn't generate them again.
+
+use crate::cpp_code_generator;
+use crate::cpp_ffi_data::CppFfiItem;
+use crate::database::{DatabaseClient, ItemId};
+use crate::processor::{with_derived_items, ProcessorData};
+use log::info;
+use ritual_common::errors::{bail, Result, ResultExt};
+use ritual_common::file_utils::{create_dir_all, create_file, load_json};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+/// FFI item as it appears in the spec file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FfiSpecItem {
+    pub id: ItemId,
+    /// Text of the C++ item this item was generated from (informational only).
+    #[serde(default)]
+    pub source: Option<String>,
+    /// C++ declaration of the FFI function (informational only).
+    #[serde(default)]
+    pub declaration: Option<String>,
+    pub item: CppFfiItem,
+}
+
+/// Complete FFI specification of a crate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FfiSpec {
+    pub crate_name: String,
+    pub items: Vec<FfiSpecItem>,
+}
+
+fn spec_path(data: &ProcessorData<'_>) -> PathBuf {
+    data.workspace
+        .out_path()
+        .join("ffi_spec")
+        .join(format!("{}.json", data.db.crate_name()))
+}
+
+/// Returns the spec containing all FFI items of the current crate.
+pub fn ffi_spec(db: &DatabaseClient) -> Result<FfiSpec> {
+    let mut items = Vec::new();
+    for ffi_item in db.ffi_items() {
+        let source = match &ffi_item.source_id {
+            Some(id) => Some(db.item(id)?.item.short_text()),
+            None => None,
+        };
+        let declaration = match ffi_item.item.as_function_ref() {
+            Some(function) => Some(cpp_code_generator::function_signature(db, function)?),
+            None => None,
+        };
+        items.push(FfiSpecItem {
+            id: ffi_item.id.clone(),
+            source,
+            declaration,
+            item: ffi_item.item.clone(),
+        });
+    }
+    Ok(FfiSpec {
+        crate_name: db.crate_name().to_string(),
+        items,
+    })
+}
+
+/// Writes all FFI items of the current crate to the spec file.
+pub fn export_ffi_spec(data: &mut ProcessorData<'_>) -> Result<()> {
+    let spec = ffi_spec(&data.db)?;
+    let path = spec_path(data);
+    create_dir_all(path.parent().expect("spec path must have parent"))?;
+    serde_json::to_writer_pretty(create_file(&path)?, &spec)
+        .with_context(|_| format!("failed to write FFI spec: {}", path.display()))?;
+    info!(
+        "Exported {} FFI items to {}",
+        spec.items.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Applies the reviewed spec file to the database (see `apply_ffi_spec`).
+pub fn import_ffi_spec(data: &mut ProcessorData<'_>) -> Result<()> {
+    let path = spec_path(data);
+    let spec: FfiSpec = load_json(&path)?;
+    let (deleted_count, changed_count) = apply_ffi_spec(&mut data.db, spec)?;
+    info!(
+        "Imported FFI spec from {}: {} items deleted, {} items changed",
+        path.display(),
+        deleted_count,
+        changed_count
+    );
+    Ok(())
+}
+
+/// Applies the reviewed `spec` to the database. FFI items missing from the spec
+/// are deleted and recorded as rejected. FFI items changed in the spec are replaced.
+/// Items derived from deleted or changed items (C++ checks and Rust items) are deleted,
+/// so `cpp_checker` and `rust_generator` need to be run afterwards.
+/// Returns the numbers of deleted and changed items.
+pub fn apply_ffi_spec(db: &mut DatabaseClient, spec: FfiSpec) -> Result<(usize, usize)> {
+    if spec.crate_name != db.crate_name() {
+        bail!(
+            "FFI spec is for crate {}, but current crate is {}",
+            spec.crate_name,
+            db.crate_name()
+        );
+    }
+
+    let mut spec_items = HashMap::new();
+    for spec_item in spec.items {
+        if db.ffi_item(&spec_item.id).is_err() {
+            bail!(
+                "FFI spec contains unknown item {}; adding new items is not supported",
+                spec_item.id
+            );
+        }
+        spec_items.insert(spec_item.id, spec_item.item);
+    }
+
+    let mut deleted_ids = HashSet::new();
+    let mut rejected_keys = Vec::new();
+    let mut changed_items = Vec::new();
+    for ffi_item in db.ffi_items() {
+        match spec_items.remove(&ffi_item.id) {
+            None => {
+                deleted_ids.insert(ffi_item.id.clone());
+                rejected_keys.push(ffi_item.item.spec_key());
+            }
+            Some(item) => {
+                if serde_json::to_value(&item)? != serde_json::to_value(ffi_item.item)? {
+                    changed_items.push((ffi_item.id.clone(), item));
+                }
+            }
+        }
+    }
+
+    let stale_ids = changed_items.iter().map(|(id, _)| id.clone()).collect();
+    let mut derived_ids = with_derived_items(db, stale_ids);
+    for (id, _) in &changed_items {
+        derived_ids.remove(id);
+    }
+    let ids = with_derived_items(db, deleted_ids.clone());
+    db.delete_items(|item| ids.contains(&item.id) || derived_ids.contains(&item.id));
+    for key in rejected_keys {
+        db.reject_ffi_item(key);
+    }
+
+    for (id, item) in &changed_items {
+        *db.ffi_item_mut(id)?.item = item.clone();
+    }
+    Ok((deleted_ids.len(), changed_items.len()))
+}
@@ -0,0 +1,224 @@
+//! Fuzzing of the C++ parser.
+//!
+//! `generate_fuzz_targets` writes a cargo-fuzz project to `<workspace>/out/fuzz`
+//! (see `Config::set_fuzz_path`). Its targets pass arbitrary input to the C++ parser,
+//! either as a header snippet (`cpp_parser` target) or as a type of a function argument
+//! (`cpp_type` target). Parse errors are expected, but panics and crashes indicate
+//! bugs in the parser.
+//!
+//! `export_fuzz_corpus` writes the headers of the current crate and the types
+//! used in its functions to the corpus directories of these targets,
+//! so that fuzzing starts from real inputs.
+
+use crate::config::{Config, CrateDependencySource, CrateProperties};
+use crate::cpp_parser;
+use crate::database::{Database, DatabaseClient, IndexedDatabase};
+use crate::include_graph;
+use crate::processor::ProcessorData;
+use crate::workspace::Workspace;
+use itertools::Itertools;
+use log::info;
+use ritual_common::errors::{bail, Result};
+use ritual_common::file_utils::{
+    canonicalize, create_dir_all, create_file, file_to_string, path_to_str,
+};
+use ritual_common::utils::MapIfOk;
+use ritual_common::ReadOnly;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Names of the generated fuzz targets.
+const TARGETS: &[&str] = &["cpp_parser", "cpp_type"];
+
+/// Name of the crate used for parsing fuzz inputs.
+const CRATE_NAME: &str = "fuzz";
+
+/// Parser of fuzz inputs. Creating the parser is slow, so a fuzz target
+/// should create it once and use it for all inputs.
+pub struct FuzzParser {
+    _dir: tempdir::TempDir,
+    workspace: Workspace,
+    config: Config,
+}
+
+impl FuzzParser {
+    /// Creates a parser using a temporary workspace.
+    pub fn new() -> Result<Self> {
+        let dir = tempdir::TempDir::new("ritual_fuzz")?;
+        let workspace = Workspace::new(dir.path().into())?;
+        create_dir_all(workspace.crate_tmp_path(CRATE_NAME))?;
+        Ok(FuzzParser {
+            _dir: dir,
+            workspace,
+            config: Config::new(CrateProperties::new(CRATE_NAME, "0.0.0")),
+        })
+    }
+
+    /// Runs the C++ parser on `code` placed in a source file.
+    /// Parsed items are discarded.
+    pub fn parse_header_snippet(&mut self, code: &str) -> Result<()> {
+        let mut db = DatabaseClient::new(
+            IndexedDatabase::new(Database::empty(CRATE_NAME.into()), Default::default()),
+            ReadOnly::new(Vec::new()),
+        );
+        let mut data = ProcessorData {
+            workspace: &mut self.workspace,
+            config: &self.config,
+            db: &mut db,
+            skipped: &mut Vec::new(),
+            item_timings: &mut Vec::new(),
+        };
+        cpp_parser::parse_code(&mut data, code)
+    }
+
+    /// Runs the C++ parser on a function declaration with an argument of type `type_text`.
+    pub fn parse_type_snippet(&mut self, type_text: &str) -> Result<()> {
+        self.parse_header_snippet(&format!("void ritual_fuzz_function({} arg);", type_text))
+    }
+}
+
+fn fuzz_path(data: &ProcessorData<'_>) -> PathBuf {
+    match data.config.fuzz_path() {
+        Some(path) => path.clone(),
+        None => data.workspace.out_path().join("fuzz"),
+    }
+}
+
+/// Returns the value of the `ritual` dependency in the fuzz project's `Cargo.toml`.
+fn ritual_dependency(data: &ProcessorData<'_>) -> Result<String> {
+    Ok(match data.config.fuzz_ritual_source() {
+        None => format!("{:?}", env!("CARGO_PKG_VERSION")),
+        Some(CrateDependencySource::CratesIo { version }) => format!("{:?}", version),
+        Some(CrateDependencySource::Local { path }) => {
+            format!("{{ path = {:?} }}", path_to_str(path)?)
+        }
+        Some(CrateDependencySource::CurrentWorkspace) => {
+            bail!("ritual can't be a dependency from the current workspace")
+        }
+    })
+}
+
+fn target_code(target: &str) -> String {
+    let function = match target {
+        "cpp_parser" => "parse_header_snippet",
+        "cpp_type" => "parse_type_snippet",
+        _ => unreachable!(),
+    };
+    format!(
+        "#![no_main]
+use libfuzzer_sys::fuzz_target;
+use ritual::fuzz::FuzzParser;
+use std::cell::RefCell;
+
+thread_local! {{
+    static PARSER: RefCell<FuzzParser> =
+        RefCell::new(FuzzParser::new().expect(\"failed to create parser\"));
+}}
+
+fuzz_target!(|data: &[u8]| {{
+    if let Ok(text) = std::str::from_utf8(data) {{
+        // parse errors are expected, only panics and crashes are reported
+        PARSER.with(|parser| {{
+            let _ = parser.borrow_mut().{}(text);
+        }});
+    }}
+}});
+",
+        function
+    )
+}
+
+/// Writes the cargo-fuzz project with the parser fuzz targets.
+pub fn generate_fuzz_targets(data: &mut ProcessorData<'_>) -> Result<()> {
+    let path = fuzz_path(data);
+    create_dir_all(path.join("fuzz_targets"))?;
+
+    let mut cargo_toml = create_file(path.join("Cargo.toml"))?;
+    writeln!(
+        cargo_toml,
+        "[package]
+name = \"ritual_fuzz\"
+version = \"0.0.0\"
+edition = \"2018\"
+publish = false
+
+[package.metadata]
+cargo-fuzz = true
+
+[dependencies]
+libfuzzer-sys = \"0.3\"
+ritual = {}
+
+# keep the project out of any parent workspace
+[workspace]
+members = [\".\"]
+",
+        ritual_dependency(data)?
+    )?;
+    for target in TARGETS {
+        writeln!(
+            cargo_toml,
+            "[[bin]]
+name = \"{0}\"
+path = \"fuzz_targets/{0}.rs\"
+test = false
+doc = false
+",
+            target
+        )?;
+        write!(
+            create_file(path.join("fuzz_targets").join(format!("{}.rs", target)))?,
+            "{}",
+            target_code(target)
+        )?;
+    }
+    info!("Generated fuzz targets in {}", path.display());
+    Ok(())
+}
+
+/// Writes the parsed headers of the current crate and the types of its
+/// function arguments to the corpus directories of the fuzz targets.
+pub fn export_fuzz_corpus(data: &mut ProcessorData<'_>) -> Result<()> {
+    let graph = match include_graph::load(data.workspace, data.db.crate_name())? {
+        Some(graph) => graph,
+        None => bail!("include graph not found (run cpp_parser first)"),
+    };
+    let target_paths = data
+        .config
+        .target_include_paths()
+        .iter()
+        .map_if_ok(canonicalize)?;
+    let headers = graph
+        .roots
+        .iter()
+        .chain(graph.includes.values().flatten())
+        .filter(|path| target_paths.iter().any(|target| path.starts_with(target)))
+        .collect::<BTreeSet<_>>();
+
+    let mut types = BTreeSet::new();
+    for item in data.db.cpp_items() {
+        if let Some(function) = item.item.as_function_ref() {
+            for arg in &function.arguments {
+                if let Ok(code) = arg.argument_type.to_cpp_code(None) {
+                    types.insert(code);
+                }
+            }
+        }
+    }
+
+    let corpus_path = fuzz_path(data).join("corpus");
+    let write_corpus = |target: &str, inputs: Vec<String>| -> Result<()> {
+        let dir = corpus_path.join(target);
+        create_dir_all(&dir)?;
+        for (index, input) in inputs.iter().enumerate() {
+            let file_name = format!("{}_{}", data.db.crate_name(), index);
+            write!(create_file(dir.join(file_name))?, "{}", input)?;
+        }
+        info!("Exported {} inputs to {}", inputs.len(), dir.display());
+        Ok(())
+    };
+    write_corpus("cpp_parser", headers.into_iter().map_if_ok(file_to_string)?)?;
+    write_corpus("cpp_type", types.into_iter().collect_vec())?;
+    Ok(())
+}
@@ -0,0 +1,379 @@
+//! Importer of documentation from rendered HTML pages
+//! (see `Config::set_html_docs`).
+//!
+//! Each member is documented by a heading containing its declaration,
+//! followed by the description. Headings are matched to C++ items by the qualified
+//! name found in the declaration, and functions are additionally matched by
+//! argument types.
+
+use crate::config::HtmlDocsConfig;
+use crate::cpp_data::CppItem;
+use crate::cpp_function::CppFunction;
+use crate::database::{DocItem, ItemWithSource};
+use crate::doxygen::normalize_type;
+use crate::processor::ProcessorData;
+use itertools::Itertools;
+use log::{debug, info, trace};
+use regex::{Captures, Regex};
+use ritual_common::errors::{Result, ResultExt};
+use ritual_common::file_utils::{file_to_string, os_str_to_str, read_dir};
+use select::document::Document;
+use select::node::Node;
+use select::predicate::{And, Class, Name};
+use std::collections::{BTreeSet, HashMap};
+
+/// Documentation of a member found in an HTML page.
+#[derive(Debug, Clone)]
+struct HtmlDocEntry {
+    /// HTML link anchor of the heading.
+    anchor: String,
+    /// URL of the page.
+    url: String,
+    /// C++ declaration from the heading.
+    declaration: String,
+    html: String,
+    cross_references: Vec<String>,
+}
+
+impl HtmlDocEntry {
+    fn doc_item(&self, is_exact: bool) -> DocItem {
+        DocItem {
+            anchor: Some(self.anchor.clone()),
+            html: self.html.clone(),
+            mismatched_declaration: if is_exact {
+                None
+            } else {
+                Some(self.declaration.clone())
+            },
+            url: Some(if self.anchor.is_empty() {
+                self.url.clone()
+            } else {
+                format!("{}#{}", self.url, self.anchor)
+            }),
+            cross_references: self.cross_references.clone(),
+            cpp_cross_references: Vec::new(),
+            examples: Vec::new(),
+        }
+    }
+}
+
+/// Returns the qualified name of the declared item,
+/// e.g. `QObject::setObjectName` for `void QObject::setObjectName(const QString &name)`.
+fn declared_name(declaration: &str) -> Option<&str> {
+    let head = match declaration.find('(') {
+        Some(index) => &declaration[..index],
+        None => declaration,
+    };
+    head.split_whitespace()
+        .last()
+        .map(|name| name.trim_start_matches(|c| c == '*' || c == '&'))
+        .filter(|name| !name.is_empty())
+}
+
+/// Returns true if argument types of `declaration` are `argument_types`.
+/// Argument names and default values in the declaration are ignored.
+fn arguments_match(declaration: &str, argument_types: &[String]) -> bool {
+    let (start, end) = match (declaration.find('('), declaration.rfind(')')) {
+        (Some(start), Some(end)) if start < end => (start, end),
+        _ => return false,
+    };
+    let arguments = declaration[start + 1..end]
+        .split(',')
+        .map(str::trim)
+        .filter(|arg| !arg.is_empty() && *arg != "void")
+        .collect_vec();
+    if arguments.len() != argument_types.len() {
+        return false;
+    }
+    arguments
+        .iter()
+        .zip(argument_types)
+        .all(|(argument, expected)| {
+            let argument = match argument.find('=') {
+                Some(index) => argument[..index].trim(),
+                None => argument,
+            };
+            if normalize_type(argument) == *expected {
+                return true;
+            }
+            let without_name = match argument.rfind(|c: char| !c.is_alphanumeric() && c != '_') {
+                Some(index) => &argument[..=index],
+                None => argument,
+            };
+            normalize_type(without_name) == *expected
+        })
+}
+
+/// Returns a copy of `html` with relative link URLs replaced with absolute URLs.
+/// Also returns the absolute URLs.
+fn process_links(html: &str, base_url: &str) -> (String, BTreeSet<String>) {
+    let link_regex = Regex::new("(href|src)=\"([^\"]*)\"").unwrap();
+    let mut cross_references = BTreeSet::new();
+    let html = link_regex
+        .replace_all(html.trim(), |captures: &Captures<'_>| {
+            let mut link = captures[2].to_string();
+            if link.starts_with('#') {
+                // keep in-page links as is
+            } else if !link.contains(':') {
+                link = format!("{}{}", base_url, link);
+                cross_references.insert(link.clone());
+            }
+            format!("{}=\"{}\"", &captures[1], link)
+        })
+        .into_owned();
+    (html, cross_references)
+}
+
+/// Documentation entries loaded from a directory of HTML pages.
+#[derive(Debug, Default)]
+struct HtmlDocsIndex {
+    /// Entries by qualified C++ name.
+    entries: HashMap<String, Vec<HtmlDocEntry>>,
+}
+
+impl HtmlDocsIndex {
+    fn add(&mut self, name: &str, entry: HtmlDocEntry) {
+        self.entries
+            .entry(name.to_string())
+            .or_default()
+            .push(entry);
+    }
+
+    /// Returns HTML content following `node` until the next element with `end_tag`.
+    fn content_after(node: Node<'_>, end_tag: &str) -> String {
+        let mut html = String::new();
+        let mut current = node.next();
+        while let Some(node) = current {
+            if node.name() == Some(end_tag) {
+                break;
+            }
+            if node.as_comment().is_none() {
+                html.push_str(&node.html());
+            }
+            current = node.next();
+        }
+        html
+    }
+
+    /// Adds documentation of enum values listed in a Qt-style `table.valuelist`.
+    fn add_enum_values(&mut self, enum_name: &str, entry: &HtmlDocEntry) {
+        let document = Document::from(entry.html.as_str());
+        for row in document.find(And(Name("table"), Class("valuelist"))) {
+            for tr in row.find(Name("tr")) {
+                let tds = tr.find(Name("td")).collect_vec();
+                if tds.len() < 3 {
+                    continue;
+                }
+                let name = tds[0].text();
+                let name = name.trim().rsplit("::").next().unwrap_or("");
+                if name.is_empty() {
+                    continue;
+                }
+                self.add(
+                    &format!("{}::{}", enum_name, name),
+                    HtmlDocEntry {
+                        anchor: entry.anchor.clone(),
+                        url: entry.url.clone(),
+                        declaration: name.to_string(),
+                        html: tds[2].inner_html().trim().to_string(),
+                        cross_references: Vec::new(),
+                    },
+                );
+            }
+        }
+    }
+
+    fn add_page(&mut self, config: &HtmlDocsConfig, file_name: &str, content: &str) {
+        let document = Document::from(content);
+        let base_url = config.base_url();
+        let url = format!("{}{}", base_url, file_name);
+        let heading_tag = config.member_heading_tag();
+
+        let headings = document.find(And(Name(heading_tag), Class(config.member_heading_class())));
+        for heading in headings {
+            let declaration = heading.text().trim().to_string();
+            let name = match declared_name(&declaration) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            let anchor = heading
+                .attr("id")
+                .or_else(|| {
+                    heading
+                        .find(Name("a"))
+                        .filter_map(|a| a.attr("name"))
+                        .next()
+                })
+                .unwrap_or("")
+                .to_string();
+            let (html, cross_references) =
+                process_links(&Self::content_after(heading, heading_tag), base_url);
+            trace!("found HTML documentation for {} in {}", name, file_name);
+            let entry = HtmlDocEntry {
+                anchor,
+                url: url.clone(),
+                declaration: declaration.clone(),
+                html,
+                cross_references: cross_references.into_iter().collect(),
+            };
+            if declaration.starts_with("enum ") {
+                self.add_enum_values(&name, &entry);
+            }
+            self.add(&name, entry);
+        }
+
+        let title = document
+            .find(Name("h1"))
+            .next()
+            .map(|h1| h1.text())
+            .unwrap_or_default();
+        let type_name = match title.split_whitespace().next() {
+            Some(name) => name,
+            None => return,
+        };
+        if let Some(description) = document.find(Class(config.description_class())).next() {
+            let html = match description.find(Name("h2")).next() {
+                Some(h2) => Self::content_after(h2, heading_tag),
+                None => description.inner_html(),
+            };
+            let (html, cross_references) = process_links(&html, base_url);
+            self.add(
+                type_name,
+                HtmlDocEntry {
+                    anchor: String::new(),
+                    url,
+                    declaration: title.trim().to_string(),
+                    html,
+                    cross_references: cross_references.into_iter().collect(),
+                },
+            );
+        }
+    }
+
+    fn load(config: &HtmlDocsConfig) -> Result<Self> {
+        let mut index = HtmlDocsIndex::default();
+        let mut file_names = Vec::new();
+        for item in read_dir(config.path())? {
+            let file_name = os_str_to_str(&item?.file_name())?.to_string();
+            if file_name.ends_with(".html") {
+                file_names.push(file_name);
+            }
+        }
+        file_names.sort();
+        for file_name in file_names {
+            let path = config.path().join(&file_name);
+            let content =
+                file_to_string(&path).with_context(|_| format!("failed to read {}", file_name))?;
+            index.add_page(config, &file_name, &content);
+        }
+        Ok(index)
+    }
+
+    /// Returns the documentation entry for `function` and true if
+    /// the argument types of the entry match the function exactly.
+    fn function_entry(&self, function: &CppFunction) -> Option<(&HtmlDocEntry, bool)> {
+        let entries = self.entries.get(&function.path.to_templateless_string())?;
+        let argument_types = function
+            .arguments
+            .iter()
+            .map(|arg| normalize_type(&arg.argument_type.to_cpp_pseudo_code()))
+            .collect_vec();
+        let is_const = function.member.as_ref().map_or(false, |m| m.is_const);
+        if let Some(entry) = entries.iter().find(|entry| {
+            arguments_match(&entry.declaration, &argument_types)
+                && entry.declaration.trim_end().ends_with("const") == is_const
+        }) {
+            return Some((entry, true));
+        }
+        if entries.len() == 1 {
+            return Some((&entries[0], false));
+        }
+        None
+    }
+}
+
+/// Attaches documentation from HTML pages specified in `Config::set_html_docs`
+/// to C++ items of the current crate.
+pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
+    let config = match data.config.html_docs() {
+        Some(config) => config,
+        None => return Ok(()),
+    };
+    let index = HtmlDocsIndex::load(config)?;
+    let mut new_items = Vec::new();
+    for item in data.db.cpp_items() {
+        if item.source_id.is_some() {
+            continue;
+        }
+        let found = match &item.item {
+            CppItem::Function(function) => index.function_entry(function),
+            CppItem::Type(_) | CppItem::EnumValue(_) => {
+                let path = item.item.path().expect("item must have path");
+                index
+                    .entries
+                    .get(&path.to_templateless_string())
+                    .and_then(|entries| entries.first())
+                    .map(|entry| (entry, true))
+            }
+            _ => continue,
+        };
+        match found {
+            Some((entry, is_exact)) if !entry.html.is_empty() => {
+                if !is_exact {
+                    debug!(
+                        "no exact HTML documentation match for {}; using {}",
+                        item.item, entry.declaration
+                    );
+                }
+                new_items.push(ItemWithSource::new(&item.id, entry.doc_item(is_exact)));
+            }
+            _ => trace!("no HTML documentation for {}", item.item),
+        }
+    }
+    info!("HTML documentation found for {} items", new_items.len());
+    for item in new_items {
+        data.db.add_doc_item(item.source_id, item.item);
+    }
+    Ok(())
+}
+
+#[test]
+fn html_docs_matching() {
+    let html = r#"<html><body><h1 class="title">QFoo Class</h1>
+        <div class="descr"><h2 id="details">Detailed Description</h2>
+        <p>The QFoo class does <a href="qbar.html">things</a>.</p></div>
+        <h3 class="fn" id="setName"><a name="setName"></a>void QFoo::setName(const QString &amp;<i>name</i>, int <i>flags</i> = 0)</h3>
+        <p>Sets the name.</p>
+        <h3 class="fn" id="name"><a name="name"></a>QString QFoo::name() const</h3>
+        <p>Returns the name.</p>
+        </body></html>"#;
+    let config = HtmlDocsConfig::new(".", "https://example.com/docs/");
+    let mut index = HtmlDocsIndex::default();
+    index.add_page(&config, "qfoo.html", html);
+
+    let class = &index.entries["QFoo"][0];
+    assert_eq!(
+        class.html,
+        "<p>The QFoo class does <a href=\"https://example.com/docs/qbar.html\">things</a>.</p>"
+    );
+    assert_eq!(
+        class.doc_item(true).url.as_ref().map(String::as_str),
+        Some("https://example.com/docs/qfoo.html")
+    );
+
+    let set_name = &index.entries["QFoo::setName"][0];
+    assert_eq!(set_name.anchor, "setName");
+    assert_eq!(set_name.html, "<p>Sets the name.</p>");
+    assert!(arguments_match(
+        &set_name.declaration,
+        &["constQString&".to_string(), "int".to_string()]
+    ));
+    assert!(!arguments_match(
+        &set_name.declaration,
+        &["constQString&".to_string()]
+    ));
+    assert!(arguments_match(
+        &index.entries["QFoo::name"][0].declaration,
+        &[]
+    ));
+}
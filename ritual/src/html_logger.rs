@@ -0,0 +1,235 @@
+//! Writer of HTML reports (e.g. the report of failed C++ checks).
+//!
+//! Rows describing items can have a severity and an anchor, so that a row
+//! can be linked to (e.g. `index.html#item-1`). The report starts with a summary
+//! containing the number of rows of each severity and durations of the stages
+//! of the run.
+//!
+//! The structure and style of the reports are defined by templates.
+//! Built-in templates can be replaced with files from a theme directory
+//! (see `Config::set_html_report_theme_path`).
+
+use itertools::Itertools;
+use ritual_common::errors::{format_err, Result};
+use ritual_common::file_utils::{create_file, file_to_string, File};
+use std::collections::BTreeMap;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Templates of an HTML report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtmlTemplates {
+    /// Start of the page. `{title}` is replaced with the title of the report,
+    /// and `{summary}` is replaced with the summary.
+    pub header: String,
+    /// Summary of the report. `{counts}` is replaced with the numbers of rows
+    /// by severity, and `{stages}` is replaced with the list of stages.
+    pub summary: String,
+    /// Row of the table. `{attributes}` is replaced with the attributes
+    /// of the `tr` element, and `{cells}` is replaced with the cells of the row.
+    pub row: String,
+    /// End of the page.
+    pub footer: String,
+    /// Content of `style.css` saved next to the report.
+    pub style: String,
+    /// Content of `script.js` saved next to the report.
+    pub script: String,
+}
+
+impl Default for HtmlTemplates {
+    fn default() -> Self {
+        HtmlTemplates {
+            header: include_str!("../templates/html_logger/header.html").to_string(),
+            summary: include_str!("../templates/html_logger/summary.html").to_string(),
+            row: include_str!("../templates/html_logger/row.html").to_string(),
+            footer: include_str!("../templates/html_logger/footer.html").to_string(),
+            style: include_str!("../templates/html_logger/style.css").to_string(),
+            script: include_str!("../templates/html_logger/script.js").to_string(),
+        }
+    }
+}
+
+impl HtmlTemplates {
+    /// Loads templates from `theme_path`. Templates missing in the directory
+    /// (or all templates if `theme_path` is `None`) are built-in.
+    pub fn load(theme_path: Option<&Path>) -> Result<Self> {
+        let mut templates = HtmlTemplates::default();
+        if let Some(theme_path) = theme_path {
+            for (file_name, template) in vec![
+                ("header.html", &mut templates.header),
+                ("summary.html", &mut templates.summary),
+                ("row.html", &mut templates.row),
+                ("footer.html", &mut templates.footer),
+                ("style.css", &mut templates.style),
+                ("script.js", &mut templates.script),
+            ] {
+                let path = theme_path.join(file_name);
+                if path.exists() {
+                    *template = file_to_string(path)?;
+                }
+            }
+        }
+        Ok(templates)
+    }
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Converts `text` to a value usable as an element id and a URL fragment.
+pub fn anchor(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Severity of an item of a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// Returns the name of the severity. It's also used as the CSS class of the rows.
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// Writes an HTML report consisting of a summary and a table.
+/// The content of cells is not escaped.
+///
+/// Rows are written to the file by `finish` because the summary
+/// is only known at the end.
+pub struct HtmlLogger {
+    file: File<BufWriter<std::fs::File>>,
+    templates: HtmlTemplates,
+    title: String,
+    rows: String,
+    counts: BTreeMap<Severity, usize>,
+    stages: Vec<(String, Duration)>,
+}
+
+impl HtmlLogger {
+    /// Creates the report at `path` and saves `style.css` and `script.js`
+    /// to the same directory.
+    pub fn new(path: impl Into<PathBuf>, title: &str, templates: HtmlTemplates) -> Result<Self> {
+        let path = path.into();
+        let dir = path
+            .parent()
+            .ok_or_else(|| format_err!("invalid report path: {}", path.display()))?;
+        write!(create_file(dir.join("style.css"))?, "{}", templates.style)?;
+        write!(create_file(dir.join("script.js"))?, "{}", templates.script)?;
+        let file = create_file(&path)?;
+        Ok(HtmlLogger {
+            file,
+            templates,
+            title: title.to_string(),
+            rows: String::new(),
+            counts: BTreeMap::new(),
+            stages: Vec::new(),
+        })
+    }
+
+    /// Records duration of a stage of the run for the summary.
+    pub fn add_stage(&mut self, name: &str, duration: Duration) {
+        self.stages.push((name.to_string(), duration));
+    }
+
+    /// Adds a row with header cells.
+    pub fn add_header(&mut self, titles: &[&str]) -> Result<()> {
+        let cells = titles
+            .iter()
+            .map(|title| format!("<th>{}</th>", title))
+            .collect::<String>();
+        self.add_row_cells("", &cells);
+        Ok(())
+    }
+
+    /// Adds a row describing an item with `severity`. The row can be linked to
+    /// using `anchor` (see `anchor()`) as the URL fragment.
+    pub fn add_item(&mut self, anchor: &str, severity: Severity, data: &[&str]) -> Result<()> {
+        let attributes = format!(
+            " id=\"{}\" class=\"{}\"",
+            escape_html(anchor),
+            severity.name()
+        );
+        self.add_row_cells(&attributes, &data_cells(data));
+        *self.counts.entry(severity).or_insert(0) += 1;
+        Ok(())
+    }
+
+    fn add_row_cells(&mut self, attributes: &str, cells: &str) {
+        let row = self
+            .templates
+            .row
+            .replace("{attributes}", attributes)
+            .replace("{cells}", cells);
+        self.rows.push_str(&row);
+    }
+
+    fn summary(&self) -> String {
+        let counts = [Severity::Error, Severity::Warning, Severity::Info]
+            .iter()
+            .map(|&severity| {
+                format!(
+                    "<span class=\"{name}\">{name}: {count}</span>",
+                    name = severity.name(),
+                    count = self.counts.get(&severity).cloned().unwrap_or(0)
+                )
+            })
+            .join(" ");
+        let stages = self
+            .stages
+            .iter()
+            .map(|(name, duration)| {
+                format!(
+                    "<li>{}: {:.1} s</li>",
+                    escape_html(name),
+                    duration.as_secs_f64()
+                )
+            })
+            .collect::<String>();
+        self.templates
+            .summary
+            .replace("{counts}", &counts)
+            .replace("{stages}", &stages)
+    }
+
+    /// Writes the report.
+    pub fn finish(mut self) -> Result<()> {
+        let header = self
+            .templates
+            .header
+            .replace("{title}", &escape_html(&self.title))
+            .replace("{summary}", &self.summary());
+        write!(self.file, "{}", header)?;
+        write!(self.file, "{}", self.rows)?;
+        write!(self.file, "{}", self.templates.footer)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+fn data_cells(data: &[&str]) -> String {
+    data.iter()
+        .map(|item| format!("<td>{}</td>", item))
+        .collect()
+}
@@ -0,0 +1,421 @@
+//! Include dependency graph of the parsed headers.
+//!
+//! The graph is recorded by the C++ parser and used by the crate writer
+//! to include only the headers that declare the wrapped items
+//! instead of all configured include directives.
+
+use crate::config::Config;
+use crate::cpp_data::CppPath;
+use crate::cpp_type::CppType;
+use crate::database::{DatabaseClient, ItemId};
+use crate::workspace::Workspace;
+use clang::{Entity, EntityKind};
+use itertools::Itertools;
+use log::debug;
+use ritual_common::errors::{bail, Result};
+use ritual_common::file_utils::{canonicalize, create_dir_all, load_json, save_json};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+/// Include dependency graph. All paths are canonicalized.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IncludeGraph {
+    /// Files included by the configured include directives
+    pub roots: BTreeSet<PathBuf>,
+    /// Files included by each file
+    pub includes: BTreeMap<PathBuf, BTreeSet<PathBuf>>,
+    /// Files included by the include directives of each header group
+    /// (see `Config::add_header_group`)
+    #[serde(default)]
+    pub groups: Vec<BTreeSet<PathBuf>>,
+}
+
+impl IncludeGraph {
+    /// Collects inclusion directives of `translation_unit`. Files included
+    /// from `global_header` become the roots of the graph.
+    pub fn collect(translation_unit: Entity<'_>, global_header: &Path) -> Result<Self> {
+        let global_header = canonicalize(global_header)?;
+        let mut graph = IncludeGraph::default();
+        for entity in translation_unit.get_children() {
+            if entity.get_kind() != EntityKind::InclusionDirective {
+                continue;
+            }
+            let includer = entity
+                .get_location()
+                .and_then(|location| location.get_file_location().file)
+                .map(|file| file.get_path());
+            let included = entity.get_file().map(|file| file.get_path());
+            if let (Some(includer), Some(included)) = (includer, included) {
+                let includer = canonicalize(includer)?;
+                let included = canonicalize(included)?;
+                if includer == global_header {
+                    graph.roots.insert(included);
+                } else {
+                    graph.includes.entry(includer).or_default().insert(included);
+                }
+            }
+        }
+        Ok(graph)
+    }
+
+    /// Moves files included by `source_file` to the header group `index`.
+    /// `source_file` must be the main file of the translation unit
+    /// that includes the headers of the group.
+    pub fn move_to_group(&mut self, index: usize, source_file: &Path) -> Result<()> {
+        let source_file = canonicalize(source_file)?;
+        let included = self.includes.remove(&source_file).unwrap_or_default();
+        if self.groups.len() <= index {
+            self.groups.resize_with(index + 1, Default::default);
+        }
+        self.groups[index].extend(included);
+        Ok(())
+    }
+
+    /// Adds roots, inclusions and header groups of `other` to `self`.
+    pub fn merge(&mut self, other: IncludeGraph) {
+        self.roots.extend(other.roots);
+        for (includer, included) in other.includes {
+            self.includes.entry(includer).or_default().extend(included);
+        }
+        if self.groups.len() < other.groups.len() {
+            self.groups
+                .resize_with(other.groups.len(), Default::default);
+        }
+        for (group, files) in self.groups.iter_mut().zip(other.groups) {
+            group.extend(files);
+        }
+    }
+
+    /// Returns index of the header group that makes declarations of the entry header
+    /// `header` available, or `None` if it's included by the common headers.
+    pub fn header_group(&self, header: &Path) -> Option<usize> {
+        let is_entry_header = |roots: &BTreeSet<PathBuf>| {
+            roots.contains(header)
+                || roots.iter().any(|root| {
+                    self.includes
+                        .get(root)
+                        .map_or(false, |included| included.contains(header))
+                })
+        };
+        if is_entry_header(&self.roots) {
+            return None;
+        }
+        self.groups.iter().position(is_entry_header)
+    }
+
+    /// Returns all files of the graph.
+    pub fn files(&self) -> BTreeSet<&Path> {
+        let mut files = BTreeSet::new();
+        files.extend(self.roots.iter().map(PathBuf::as_path));
+        files.extend(self.groups.iter().flatten().map(PathBuf::as_path));
+        for (includer, included) in &self.includes {
+            files.insert(includer.as_path());
+            files.extend(included.iter().map(PathBuf::as_path));
+        }
+        files
+    }
+
+    /// Returns the header that should be included to make declarations of `header`
+    /// available. This is `header` itself if it's an entry header (a root, a file
+    /// included by a header group or a file directly included by one of them),
+    /// or the closest entry header
+    /// that includes `header` indirectly. Returns `None` if `header` is not
+    /// reachable from the roots.
+    ///
+    /// Use `EntryHeaderResolver` to look up multiple headers.
+    pub fn entry_header(&self, header: &Path) -> Option<PathBuf> {
+        EntryHeaderResolver::new(self).entry_header(header)
+    }
+}
+
+/// Finds entry headers (see `IncludeGraph::entry_header`) of multiple headers,
+/// reusing the results of previous lookups.
+pub struct EntryHeaderResolver<'a> {
+    /// Roots and files directly included by roots
+    entry_headers: HashSet<&'a Path>,
+    /// Files including each file
+    included_by: HashMap<&'a Path, Vec<&'a Path>>,
+    cache: HashMap<PathBuf, Option<PathBuf>>,
+}
+
+impl<'a> EntryHeaderResolver<'a> {
+    pub fn new(graph: &'a IncludeGraph) -> Self {
+        let mut entry_headers = HashSet::new();
+        for root in graph.roots.iter().chain(graph.groups.iter().flatten()) {
+            entry_headers.insert(root.as_path());
+            if let Some(included) = graph.includes.get(root) {
+                entry_headers.extend(included.iter().map(PathBuf::as_path));
+            }
+        }
+        let mut included_by = HashMap::<_, Vec<_>>::new();
+        for (includer, included) in &graph.includes {
+            for path in included {
+                included_by
+                    .entry(path.as_path())
+                    .or_default()
+                    .push(includer.as_path());
+            }
+        }
+        EntryHeaderResolver {
+            entry_headers,
+            included_by,
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn entry_header(&mut self, header: &Path) -> Option<PathBuf> {
+        if let Some(result) = self.cache.get(header) {
+            return result.clone();
+        }
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(header);
+        let mut result = None;
+        while let Some(path) = queue.pop_front() {
+            if self.entry_headers.contains(path) {
+                result = Some(path.to_path_buf());
+                break;
+            }
+            if !visited.insert(path) {
+                continue;
+            }
+            if let Some(includers) = self.included_by.get(path) {
+                queue.extend(
+                    includers
+                        .iter()
+                        .cloned()
+                        .filter(|includer| !visited.contains(includer)),
+                );
+            }
+        }
+        self.cache.insert(header.to_path_buf(), result.clone());
+        result
+    }
+}
+
+/// Returns path to the include graph of the crate in the workspace.
+pub fn include_graph_path(workspace: &Workspace, crate_name: &str) -> PathBuf {
+    workspace
+        .path()
+        .join("include_graph")
+        .join(format!("{}.json", crate_name))
+}
+
+/// Saves `graph` to the workspace.
+pub fn save(workspace: &Workspace, crate_name: &str, graph: &IncludeGraph) -> Result<()> {
+    let path = include_graph_path(workspace, crate_name);
+    create_dir_all(path.parent().expect("include graph path must have parent"))?;
+    save_json(path, graph, None)
+}
+
+/// Adds `graph` to the graph saved in the workspace. Used when only some headers
+/// were parsed, so the inclusions found in other headers are kept.
+pub fn save_merged(workspace: &Workspace, crate_name: &str, graph: IncludeGraph) -> Result<()> {
+    let mut saved = load(workspace, crate_name)?.unwrap_or_default();
+    saved.merge(graph);
+    save(workspace, crate_name, &saved)
+}
+
+/// Loads the include graph of the crate if it was saved.
+pub fn load(workspace: &Workspace, crate_name: &str) -> Result<Option<IncludeGraph>> {
+    let path = include_graph_path(workspace, crate_name);
+    if path.exists() {
+        Ok(Some(load_json(path)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Returns the include directive for `header` relative to one of `include_paths`,
+/// choosing the shortest relative path.
+pub fn include_directive(header: &Path, include_paths: &[PathBuf]) -> Option<PathBuf> {
+    include_paths
+        .iter()
+        .filter_map(|dir| canonicalize(dir).ok())
+        .filter_map(|dir| header.strip_prefix(&dir).ok().map(Path::to_path_buf))
+        .min_by_key(|path| path.components().count())
+}
+
+/// Returns the file containing the declaration of item `id`,
+/// or of the closest item it was derived from.
+fn declaration_file(db: &DatabaseClient, id: &ItemId) -> Result<Option<PathBuf>> {
+    let mut current_id = Some(id.clone());
+    while let Some(id) = current_id {
+        if let Some(location) = db.cpp_origin_location(&id)? {
+            // generated files used for parsing don't exist anymore
+            return Ok(canonicalize(&location.include_file_path).ok());
+        }
+        current_id = db.item(&id)?.source_id.clone();
+    }
+    Ok(None)
+}
+
+fn add_type_paths(type1: &CppType, output: &mut Vec<CppPath>) {
+    match type1 {
+        CppType::Class(path) => {
+            output.push(path.clone());
+            if let Some(args) = &path.last().template_arguments {
+                for arg in args {
+                    add_type_paths(arg, output);
+                }
+            }
+        }
+        CppType::PointerLike { target, .. } => add_type_paths(target, output),
+        CppType::Array { element_type, .. } => add_type_paths(element_type, output),
+        _ => {}
+    }
+}
+
+/// Finds entry headers declaring C++ items and the class types they use.
+struct ItemEntryHeaders<'a> {
+    db: &'a DatabaseClient,
+    resolver: EntryHeaderResolver<'a>,
+    /// Declarations of each type, ignoring template arguments
+    type_declarations: HashMap<String, Vec<ItemId>>,
+    type_headers: HashMap<String, Option<PathBuf>>,
+}
+
+impl<'a> ItemEntryHeaders<'a> {
+    fn new(db: &'a DatabaseClient, graph: &'a IncludeGraph) -> Self {
+        // instantiations may be declared in generated code,
+        // so all declarations of the template are considered
+        let mut type_declarations = HashMap::<String, Vec<ItemId>>::new();
+        for item in db.all_cpp_items() {
+            if let Some(type1) = item.item.as_type_ref() {
+                type_declarations
+                    .entry(type1.path.to_templateless_string())
+                    .or_default()
+                    .push(item.id.clone());
+            }
+        }
+        ItemEntryHeaders {
+            db,
+            resolver: EntryHeaderResolver::new(graph),
+            type_declarations,
+            type_headers: HashMap::new(),
+        }
+    }
+
+    fn entry_header(&mut self, id: &ItemId) -> Result<Option<PathBuf>> {
+        Ok(declaration_file(self.db, id)?.and_then(|file| self.resolver.entry_header(&file)))
+    }
+
+    /// Returns entry headers of the C++ item the FFI item `id` was generated from
+    /// and of the class types it uses. `None` values correspond to declarations
+    /// that can't be mapped to an entry header. Returns `None` if the FFI item
+    /// is not derived from a C++ item.
+    fn ffi_item_headers(&mut self, id: &ItemId) -> Result<Option<Vec<Option<PathBuf>>>> {
+        let cpp_item = match self.db.original_cpp_item(id)? {
+            Some(item) => item,
+            None => return Ok(None),
+        };
+        let mut headers = vec![self.entry_header(&cpp_item.id)?];
+        let mut paths = Vec::new();
+        for type1 in cpp_item.item.all_involved_types() {
+            add_type_paths(&type1, &mut paths);
+        }
+        for path in paths {
+            let name = path.to_templateless_string();
+            let header = match self.type_headers.get(&name) {
+                Some(header) => header.clone(),
+                None => {
+                    let mut header = None;
+                    let ids = self
+                        .type_declarations
+                        .get(&name)
+                        .cloned()
+                        .unwrap_or_default();
+                    for id in ids {
+                        header = self.entry_header(&id)?;
+                        if header.is_some() {
+                            break;
+                        }
+                    }
+                    self.type_headers.insert(name, header.clone());
+                    header
+                }
+            };
+            headers.push(header);
+        }
+        Ok(Some(headers))
+    }
+}
+
+/// Returns the entry headers (see `IncludeGraph::entry_header`) declaring the wrapped
+/// items (and the class types they use) of each FFI item of the current crate
+/// that passed C++ checks. Returns `None` if any of the declarations can't be
+/// mapped to an entry header.
+pub fn required_entry_headers(
+    db: &DatabaseClient,
+    graph: &IncludeGraph,
+) -> Result<Option<HashMap<ItemId, BTreeSet<PathBuf>>>> {
+    let mut entry_headers = ItemEntryHeaders::new(db, graph);
+    let mut output = HashMap::new();
+    for ffi_item in db.ffi_items() {
+        if !db.cpp_checks(&ffi_item.id)?.any_success() {
+            continue;
+        }
+        let headers = match entry_headers.ffi_item_headers(&ffi_item.id)? {
+            Some(headers) => headers,
+            None => continue,
+        };
+        match headers.into_iter().collect::<Option<BTreeSet<_>>>() {
+            Some(headers) => output.insert(ffi_item.id.clone(), headers),
+            None => return Ok(None),
+        };
+    }
+    Ok(Some(output))
+}
+
+/// Loads the include graph of the current crate and returns header groups required
+/// by its FFI items (see `ffi_item_header_groups`). Returns an empty map
+/// if no header groups are configured.
+pub fn load_ffi_item_header_groups(
+    workspace: &Workspace,
+    config: &Config,
+    db: &DatabaseClient,
+) -> Result<HashMap<ItemId, usize>> {
+    if config.header_groups().is_empty() {
+        return Ok(HashMap::new());
+    }
+    match load(workspace, config.crate_properties().name())? {
+        Some(graph) => ffi_item_header_groups(db, &graph),
+        None => bail!("include graph is not available, run the parser to record header groups"),
+    }
+}
+
+/// Returns the header group (see `Config::add_header_group`) required by each
+/// FFI item of the current crate that wraps an item declared in a header group
+/// or uses a type declared in it. Other FFI items are compiled with the common
+/// headers only.
+pub fn ffi_item_header_groups(
+    db: &DatabaseClient,
+    graph: &IncludeGraph,
+) -> Result<HashMap<ItemId, usize>> {
+    let mut entry_headers = ItemEntryHeaders::new(db, graph);
+    let mut output = HashMap::new();
+    for ffi_item in db.ffi_items() {
+        let headers = match entry_headers.ffi_item_headers(&ffi_item.id)? {
+            Some(headers) => headers,
+            None => continue,
+        };
+        let groups = headers
+            .iter()
+            .flatten()
+            .filter_map(|header| graph.header_group(header))
+            .unique()
+            .collect_vec();
+        if groups.len() > 1 {
+            debug!(
+                "{} requires multiple header groups: {:?}",
+                ffi_item.item.short_text(),
+                groups
+            );
+        }
+        if let Some(&group) = groups.first() {
+            output.insert(ffi_item.id.clone(), group);
+        }
+    }
+    Ok(output)
+}
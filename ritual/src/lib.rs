@@ -8,6 +8,10 @@
 
 pub use ritual_common as common;
 
+mod browser;
+mod clang_cache;
+mod clang_compat;
+mod class_graph;
 pub mod cli;
 pub mod cluster_api;
 pub mod config;
@@ -25,16 +29,35 @@ mod cpp_omitting_arguments;
 mod cpp_operator;
 pub mod cpp_parser;
 pub mod cpp_template_instantiator;
+mod cpp_tree_shaker;
 pub mod cpp_type;
+pub mod crate_graph;
 mod crate_writer;
 pub mod database;
+mod doc_examples;
 mod doc_formatter;
 mod download_db;
+mod doxygen;
+mod explain;
+mod ffi_spec;
+pub mod fuzz;
+mod html_docs;
+mod html_logger;
+mod include_graph;
+mod lockfile;
+pub mod numeric_types;
+pub mod overrides;
 pub mod processor;
+mod rust_checker;
 mod rust_code_generator;
 mod rust_generator;
 pub mod rust_info;
 pub mod rust_type;
+mod std_conversions;
+mod stream_adapters;
+mod string_encoding;
+mod symbol_audit;
+pub mod test_harness;
 mod type_allocation_places;
 pub mod workspace;
 
@@ -0,0 +1,174 @@
+//! Lockfile recording the inputs of a generation run.
+//!
+//! The lockfile is saved to the workspace after a successful run and compared with
+//! the current inputs on the next run that reuses the database, so changes of headers,
+//! library version, libclang or configuration that mix with the data produced from
+//! the old inputs are reported.
+//! A copy is also written to the generated crate for provenance.
+
+use crate::clang_cache::{content_hash, stable_hash};
+use crate::clang_compat;
+use crate::config::Config;
+use crate::include_graph;
+use crate::workspace::Workspace;
+use itertools::Itertools;
+use log::warn;
+use ritual_common::errors::Result;
+use ritual_common::file_utils::{canonicalize, create_dir_all, load_json, save_json};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// File name of the lockfile in the generated crate.
+pub const CRATE_LOCKFILE_NAME: &str = "ritual_lock.json";
+
+/// Inputs of a generation run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub crate_name: String,
+    pub generator_version: String,
+    pub clang_version: String,
+    pub cpp_lib_version: Option<String>,
+    /// Hash of the configuration options that affect the output.
+    pub config_hash: String,
+    /// Content hashes of the parsed headers in the target include paths
+    /// (or all parsed headers if there are no target include paths).
+    pub headers: BTreeMap<PathBuf, String>,
+}
+
+/// Returns content hashes of the headers recorded in the include graph
+/// of the last parsing run.
+fn header_hashes(workspace: &Workspace, config: &Config) -> Result<BTreeMap<PathBuf, String>> {
+    let graph = match include_graph::load(workspace, config.crate_properties().name())? {
+        Some(graph) => graph,
+        None => return Ok(BTreeMap::new()),
+    };
+    let target_paths = config
+        .target_include_paths()
+        .iter()
+        .filter_map(|path| canonicalize(path).ok())
+        .collect_vec();
+    let mut output = BTreeMap::new();
+    for path in graph.files() {
+        let is_target = target_paths.is_empty()
+            || target_paths
+                .iter()
+                .any(|target_path| path.starts_with(target_path));
+        if is_target && path.is_file() {
+            output.insert(path.to_path_buf(), format!("{:08x}", content_hash(path)?));
+        }
+    }
+    Ok(output)
+}
+
+fn config_hash(config: &Config) -> Result<String> {
+    let data = serde_json::to_vec(&(
+        config.crate_properties().name(),
+        config.crate_properties().version(),
+        config.include_directives(),
+        config.target_include_paths(),
+        config.cpp_parser_arguments(),
+        config.full_cpp_build_config(),
+    ))?;
+    Ok(format!("{:08x}", stable_hash(&data)))
+}
+
+impl Lockfile {
+    /// Collects the current inputs for the crate configured by `config`.
+    /// Headers are taken from the include graph saved by the last parsing run.
+    pub fn collect(workspace: &Workspace, config: &Config) -> Result<Self> {
+        let headers = header_hashes(workspace, config)?;
+        Ok(Lockfile {
+            crate_name: config.crate_properties().name().to_string(),
+            generator_version: env!("CARGO_PKG_VERSION").to_string(),
+            clang_version: clang_compat::version_text()?,
+            cpp_lib_version: config.cpp_lib_version().map(ToString::to_string),
+            config_hash: config_hash(config)?,
+            headers,
+        })
+    }
+
+    /// Returns descriptions of differences between `self` and `other`.
+    pub fn differences(&self, other: &Lockfile) -> Vec<String> {
+        let mut result = Vec::new();
+        let mut compare = |name: &str, old: String, new: String| {
+            if old != new {
+                result.push(format!("{}: {} -> {}", name, old, new));
+            }
+        };
+        compare(
+            "generator version",
+            self.generator_version.clone(),
+            other.generator_version.clone(),
+        );
+        compare(
+            "libclang version",
+            self.clang_version.clone(),
+            other.clang_version.clone(),
+        );
+        compare(
+            "library version",
+            format!("{:?}", self.cpp_lib_version),
+            format!("{:?}", other.cpp_lib_version),
+        );
+        compare(
+            "config hash",
+            self.config_hash.clone(),
+            other.config_hash.clone(),
+        );
+        for (path, hash) in &self.headers {
+            match other.headers.get(path) {
+                None => result.push(format!("header removed: {}", path.display())),
+                Some(other_hash) if other_hash != hash => {
+                    result.push(format!("header changed: {}", path.display()))
+                }
+                Some(_) => {}
+            }
+        }
+        for path in other.headers.keys() {
+            if !self.headers.contains_key(path) {
+                result.push(format!("header added: {}", path.display()));
+            }
+        }
+        result
+    }
+}
+
+/// Returns path to the lockfile of the crate in the workspace. Each library version
+/// has a separate lockfile because data for multiple versions can be
+/// merged in the same database.
+pub fn lockfile_path(workspace: &Workspace, config: &Config) -> PathBuf {
+    let name = config.crate_properties().name();
+    let file_name = match config.cpp_lib_version() {
+        Some(version) => format!("{}-{}.json", name, version),
+        None => format!("{}.json", name),
+    };
+    workspace.lock_path().join(file_name)
+}
+
+/// Compares `current` inputs with the lockfile saved in the workspace (if any)
+/// and reports the differences. Returns descriptions of the differences.
+pub fn verify(workspace: &Workspace, config: &Config, current: &Lockfile) -> Result<Vec<String>> {
+    let path = lockfile_path(workspace, config);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let saved: Lockfile = load_json(&path)?;
+    let differences = saved.differences(current);
+    if !differences.is_empty() {
+        warn!(
+            "generation inputs have changed since the last run (see {}):\n{}\n\
+             Use \"discard\" operation to process the crate from scratch.",
+            path.display(),
+            differences.join("\n")
+        );
+    }
+    Ok(differences)
+}
+
+/// Saves `current` inputs to the workspace lockfile.
+pub fn save(workspace: &Workspace, config: &Config, current: &Lockfile) -> Result<()> {
+    let path = lockfile_path(workspace, config);
+    create_dir_all(path.parent().expect("lockfile path must have parent"))?;
+    save_json(path, current, None)
+}
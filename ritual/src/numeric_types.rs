@@ -0,0 +1,162 @@
+//! Mapping of C++ numeric types whose representation depends on the target platform.
+
+use crate::cpp_type::{CppBuiltInNumericType, CppType};
+use ritual_common::target::{Arch, Env, LibraryTarget, Target, OS};
+use serde_derive::{Deserialize, Serialize};
+use std::iter::once;
+
+/// C++ numeric type that doesn't have the same Rust representation on all targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum PlatformNumericType {
+    LongDouble,
+    Int128,
+    UInt128,
+    WChar,
+    Char16,
+    Char32,
+    QReal,
+    SizeT,
+    PtrDiffT,
+}
+
+impl PlatformNumericType {
+    /// Returns all known platform-dependent types.
+    pub fn all() -> &'static [PlatformNumericType] {
+        use self::PlatformNumericType::*;
+        &[
+            LongDouble, Int128, UInt128, WChar, Char16, Char32, QReal, SizeT, PtrDiffT,
+        ]
+    }
+
+    /// Returns C++ name of the type.
+    pub fn cpp_name(self) -> &'static str {
+        use self::PlatformNumericType::*;
+        match self {
+            LongDouble => "long double",
+            Int128 => "__int128",
+            UInt128 => "unsigned __int128",
+            WChar => "wchar_t",
+            Char16 => "char16_t",
+            Char32 => "char32_t",
+            QReal => "qreal",
+            SizeT => "size_t",
+            PtrDiffT => "ptrdiff_t",
+        }
+    }
+
+    /// Returns the Rust type used for this type in the generated code.
+    /// The type is the same for all targets, but it may be an alias
+    /// that resolves to different types (or doesn't exist) on different targets.
+    pub fn rust_path(self) -> &'static str {
+        use self::PlatformNumericType::*;
+        match self {
+            LongDouble => "cpp_core::long_double",
+            Int128 => "i128",
+            UInt128 => "u128",
+            WChar => "cpp_core::wchar_t",
+            Char16 => "cpp_core::char16_t",
+            Char32 => "cpp_core::char32_t",
+            QReal => "f64",
+            SizeT => "usize",
+            PtrDiffT => "isize",
+        }
+    }
+
+    /// Returns the Rust type with the same representation as this type on `target`,
+    /// or `None` if the type can't be represented in Rust on this target.
+    ///
+    /// The rules here must match the definitions of the aliases in `cpp_core`.
+    pub fn resolve(self, target: &Target) -> Option<&'static str> {
+        use self::PlatformNumericType::*;
+        match self {
+            // `long double` is the same as `double` only on these targets;
+            // Rust doesn't have 80-bit or 128-bit floats.
+            LongDouble => {
+                let is_double = target.env == Env::Msvc
+                    || target.arch == Arch::Arm
+                    || (target.arch == Arch::AArch64
+                        && (target.os == OS::MacOS || target.os == OS::IOS));
+                if is_double {
+                    Some("f64")
+                } else {
+                    None
+                }
+            }
+            // Rust's `i128` and `u128` are not FFI-safe on the supported toolchains
+            // (they trigger `improper_ctypes`, and their alignment doesn't match
+            // `__int128` on x86_64), so `__int128` is never supported.
+            Int128 | UInt128 => None,
+            // Same as `libc::wchar_t`.
+            WChar => {
+                if target.os == OS::Windows {
+                    Some("u16")
+                } else if target.os == OS::MacOS || target.os == OS::IOS {
+                    Some("i32")
+                } else if target.arch == Arch::Arm || target.arch == Arch::AArch64 {
+                    Some("u32")
+                } else {
+                    Some("i32")
+                }
+            }
+            Char16 => Some("u16"),
+            Char32 => Some("u32"),
+            QReal => Some("f64"),
+            SizeT => Some("usize"),
+            PtrDiffT => Some("isize"),
+        }
+    }
+
+    /// Returns the platform-dependent type corresponding to `cpp_type`, if any.
+    /// Nested types (e.g. pointer targets) are not considered.
+    pub fn from_cpp_type(cpp_type: &CppType) -> Option<Self> {
+        use self::PlatformNumericType::*;
+        match cpp_type {
+            CppType::BuiltInNumeric(numeric) => match numeric {
+                CppBuiltInNumericType::LongDouble => Some(LongDouble),
+                CppBuiltInNumericType::Int128 => Some(Int128),
+                CppBuiltInNumericType::UInt128 => Some(UInt128),
+                CppBuiltInNumericType::WChar => Some(WChar),
+                CppBuiltInNumericType::Char16 => Some(Char16),
+                CppBuiltInNumericType::Char32 => Some(Char32),
+                _ => None,
+            },
+            CppType::SpecificNumeric(numeric) if numeric.path.to_cpp_pseudo_code() == "qreal" => {
+                Some(QReal)
+            }
+            CppType::PointerSizedInteger { is_signed, .. } => {
+                Some(if *is_signed { PtrDiffT } else { SizeT })
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns all platform-dependent types used in `cpp_type`, including nested types.
+    pub fn used_in(cpp_type: &CppType) -> Vec<Self> {
+        let nested = match cpp_type {
+            CppType::PointerLike { target, .. } => Self::used_in(target),
+            CppType::Array { element_type, .. } => Self::used_in(element_type),
+            CppType::FunctionPointer(function) => function
+                .arguments
+                .iter()
+                .chain(once(&*function.return_type))
+                .flat_map(Self::used_in)
+                .collect(),
+            _ => Vec::new(),
+        };
+        Self::from_cpp_type(cpp_type)
+            .into_iter()
+            .chain(nested)
+            .collect()
+    }
+}
+
+/// Rust representation of a platform-dependent type on a library target,
+/// as recorded in the database.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NumericTypeResolution {
+    pub cpp_type: PlatformNumericType,
+    pub env: LibraryTarget,
+    /// Rust type with the same representation, or `None` if
+    /// the type can't be used on this target.
+    pub rust_type: Option<String>,
+}
@@ -0,0 +1,212 @@
+//! Per-item overrides of the generated Rust API.
+//!
+//! The overrides file (see `Config::set_overrides_path`) is a TOML file
+//! with a table for each overridden C++ item. Keys are fully-qualified C++ names
+//! without template arguments. Overrides apply to all overloads of a function.
+//! `skip` only removes the items generated directly from the C++ item,
+//! so skipping a type also requires skipping the functions that use it.
+//!
+//! ```toml
+//! ["QObject::deleteLater"]
+//! force_unsafe = true
+//!
+//! ["QWidget::setParent"]
+//! rename = "set_parent_widget"
+//!
+//! ["QString::detach"]
+//! receiver = "mut"
+//!
+//! ["QWidget::parentWidget"]
+//! nullable = true
+//!
+//! ["QPrivateSignal"]
+//! skip = true
+//! ```
+
+use crate::cpp_data::CppPath;
+use crate::database::{DatabaseClient, ItemId};
+use crate::rust_info::{RustFunction, RustItem};
+use crate::rust_type::{RustFinalType, RustToFfiTypeConversion};
+use itertools::Itertools;
+use log::warn;
+use ritual_common::errors::{bail, Result, ResultExt};
+use ritual_common::file_utils::load_toml_table;
+use serde_derive::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+/// Mutability of the `self` argument of a method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiverMutability {
+    Const,
+    Mut,
+}
+
+/// Directives applied to the Rust items generated for a C++ item.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ItemOverride {
+    /// Don't generate Rust items for the C++ item.
+    #[serde(default)]
+    pub skip: bool,
+    /// Mark the function `unsafe` even if it's considered safe by the generator.
+    #[serde(default)]
+    pub force_unsafe: bool,
+    /// New name of the function (without the module path). Overloads and collisions
+    /// with other names are resolved as for the generated names.
+    #[serde(default)]
+    pub rename: Option<String>,
+    /// Mutability of the `self` argument.
+    #[serde(default)]
+    pub receiver: Option<ReceiverMutability>,
+    /// The returned reference can be null (`Option<Ref<T>>` is returned).
+    #[serde(default)]
+    pub nullable: bool,
+}
+
+/// Overrides loaded from the overrides file.
+#[derive(Debug, Clone, Default)]
+pub struct Overrides(BTreeMap<String, ItemOverride>);
+
+impl Overrides {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut items = BTreeMap::new();
+        for (name, value) in load_toml_table(path)? {
+            let item = value
+                .try_into::<ItemOverride>()
+                .with_context(|_| format!("invalid override for {} in {}", name, path.display()))?;
+            items.insert(name, item);
+        }
+        Ok(Overrides(items))
+    }
+
+    pub fn get(&self, cpp_path: &CppPath) -> Option<&ItemOverride> {
+        self.0.get(&cpp_path.to_templateless_string())
+    }
+
+    /// Emits warnings for overrides that don't correspond
+    /// to any C++ item of the current crate.
+    pub fn validate(&self, db: &DatabaseClient) {
+        let known_names = db
+            .cpp_items()
+            .filter_map(|item| item.item.path())
+            .map(CppPath::to_templateless_string)
+            .collect::<HashSet<_>>();
+        let unknown = self
+            .0
+            .keys()
+            .filter(|name| !known_names.contains(*name))
+            .collect_vec();
+        for name in unknown {
+            warn!("override for unknown C++ item: {}", name);
+        }
+    }
+
+    /// Returns the path of the C++ item `source_id` was generated from
+    /// and its override.
+    fn find<'a>(
+        &'a self,
+        db: &'a DatabaseClient,
+        source_id: &ItemId,
+    ) -> Result<Option<(&'a CppPath, &'a ItemOverride)>> {
+        let cpp_item = if let Some(cpp_item) = db.item(source_id)?.filter_map(|i| i.as_cpp_item()) {
+            cpp_item
+        } else if let Some(cpp_item) = db.source_cpp_item(source_id)? {
+            cpp_item
+        } else {
+            return Ok(None);
+        };
+        let path = match cpp_item.item.path() {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        Ok(self.get(path).map(|item_override| (path, item_override)))
+    }
+
+    /// Returns the new name of the function generated from `source_id`.
+    /// The generator uses it instead of the generated name, so overloads
+    /// and collisions are resolved as usual.
+    pub fn rename<'a>(
+        &'a self,
+        db: &'a DatabaseClient,
+        source_id: &ItemId,
+    ) -> Result<Option<&'a str>> {
+        Ok(self
+            .find(db, source_id)?
+            .and_then(|(_, item_override)| item_override.rename.as_ref())
+            .map(String::as_str))
+    }
+
+    /// Applies the override of the C++ item `source_id` was generated from.
+    /// Returns `false` if the item should be skipped.
+    /// `rename` is not applied here (see `Overrides::rename`).
+    pub fn apply(
+        &self,
+        db: &DatabaseClient,
+        source_id: &ItemId,
+        item: &mut RustItem,
+    ) -> Result<bool> {
+        match item {
+            // FFI declarations must match the C++ wrapper library
+            RustItem::Function(function) if function.kind.is_ffi_function() => return Ok(true),
+            // re-exports follow their targets
+            RustItem::Reexport(_) => return Ok(true),
+            _ => {}
+        }
+        let (path, item_override) = match self.find(db, source_id)? {
+            Some(value) => value,
+            None => return Ok(true),
+        };
+        if item_override.skip {
+            return Ok(false);
+        }
+        if let RustItem::Function(function) = item {
+            apply_to_function(item_override, function).with_context(|_| {
+                format!(
+                    "failed to apply override for {}",
+                    path.to_templateless_string()
+                )
+            })?;
+        } else if item_override.has_function_directives() {
+            bail!(
+                "override for {} is not applicable to {:?}",
+                path.to_templateless_string(),
+                item.path()
+            );
+        }
+        Ok(true)
+    }
+}
+
+impl ItemOverride {
+    fn has_function_directives(&self) -> bool {
+        self.force_unsafe || self.rename.is_some() || self.receiver.is_some() || self.nullable
+    }
+}
+
+fn apply_to_function(item_override: &ItemOverride, function: &mut RustFunction) -> Result<()> {
+    if item_override.force_unsafe {
+        function.is_unsafe = true;
+    }
+    if let Some(receiver) = item_override.receiver {
+        let self_arg = match function.arguments.iter_mut().find(|arg| arg.name == "self") {
+            Some(arg) => arg,
+            None => bail!("function doesn't have self argument"),
+        };
+        self_arg.argument_type = self_arg
+            .argument_type
+            .with_api_mutability(receiver == ReceiverMutability::Const)?;
+    }
+    if item_override.nullable {
+        if !function.return_type.conversion().is_utils_ref_to_ptr() {
+            bail!("only references can be marked nullable");
+        }
+        function.return_type = RustFinalType::new(
+            function.return_type.ffi_type().clone(),
+            RustToFfiTypeConversion::OptionUtilsRefToPtr {},
+        )?;
+    }
+    Ok(())
+}
@@ -1,27 +1,32 @@
 use crate::config::Config;
 use crate::cpp_checker::{check_cpp_parser_hook, delete_blacklisted_items};
-use crate::cpp_data::CppItem;
-use crate::database::{DatabaseClient, ItemId};
+use crate::cpp_data::{CppItem, CppOriginLocation};
+use crate::database::{Counters, DatabaseClient, ItemId};
+use crate::html_logger::{anchor, escape_html, HtmlLogger, HtmlTemplates, Severity};
 use crate::workspace::Workspace;
 use crate::{
-    cpp_casts, cpp_checker, cpp_ffi_generator, cpp_implicit_methods, cpp_omitting_arguments,
-    cpp_parser, cpp_template_instantiator, crate_writer, rust_generator,
+    browser, class_graph, cpp_casts, cpp_checker, cpp_ffi_generator, cpp_implicit_methods,
+    cpp_omitting_arguments, cpp_parser, cpp_template_instantiator, crate_writer, doc_examples,
+    doxygen, explain, ffi_spec, fuzz, html_docs, lockfile, rust_checker, rust_generator,
+    symbol_audit,
 };
 use itertools::Itertools;
 use log::debug;
-use log::{error, info, trace};
+use log::{error, info, trace, warn};
 use regex::Regex;
-use ritual_common::env_var_names::WORKSPACE_TARGET_DIR;
 use ritual_common::errors::{bail, err_msg, format_err, Result, ResultExt};
+use ritual_common::file_utils::{create_dir_all, save_json};
 use ritual_common::target::LibraryTarget;
 use ritual_common::utils::{run_command, MapIfOk};
+use serde_derive::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
 use std::ops::Bound;
 use std::path::PathBuf;
 use std::process::Command;
 use std::time::{Duration, Instant};
-use std::{env, fmt};
 
 /// Creates output and cache directories if they don't exist.
 /// Returns `Err` if any path in `config` is invalid or relative.
@@ -60,10 +65,235 @@ fn check_all_paths(config: &Config) -> Result<()> {
     Ok(())
 }
 
+/// A request to inspect the database instead of running processing steps.
+#[derive(Debug, Clone)]
+pub enum Inspection {
+    /// Print sources and children of the item.
+    Trace(ItemId),
+    /// Explain what happened to C++ items with the qualified name.
+    Explain(String),
+    /// Run the interactive database browser.
+    Browse,
+    /// Reprocess C++ items with the qualified name (see `reprocess_item`).
+    Reprocess(String),
+    /// Write the preprocessed source of the header and the declarations
+    /// clang sees in it (see `cpp_parser::dump_preprocessed`).
+    DumpPreprocessed(PathBuf),
+}
+
 pub struct ProcessorData<'a> {
     pub workspace: &'a mut Workspace,
     pub config: &'a Config,
     pub db: &'a mut DatabaseClient,
+    pub skipped: &'a mut Vec<SkippedConstruct>,
+    /// Time spent on processing of individual items in the current step
+    /// (see `ProcessorData::record_item_time`).
+    pub item_timings: &'a mut Vec<ItemTiming>,
+}
+
+/// A C++ construct that was skipped in lenient mode (see `Config::set_lenient`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedConstruct {
+    pub description: String,
+    pub reason: String,
+    pub location: Option<CppOriginLocation>,
+}
+
+/// Time spent on processing of a database item (e.g. on compiling its C++ check).
+#[derive(Debug, Clone)]
+pub struct ItemTiming {
+    pub item_id: ItemId,
+    /// Additional information, e.g. the library target of the check.
+    pub context: Option<String>,
+    pub duration: Duration,
+}
+
+/// Number of the slowest items of each step saved in the profiling report.
+const SLOWEST_ITEMS_COUNT: usize = 10;
+
+/// An item that took most of the time of a processing step.
+#[derive(Debug, Serialize)]
+pub struct ItemProfile {
+    pub item: String,
+    /// Time spent on the item in seconds.
+    pub seconds: f64,
+}
+
+/// Duration and resource usage of a processing step, saved in the profiling report.
+#[derive(Debug, Serialize)]
+struct StepProfile {
+    name: String,
+    /// Duration of the step in seconds.
+    seconds: f64,
+    /// Numbers of database items changed by the step.
+    counters: Counters,
+    /// Peak resident memory of the process in kilobytes after the step,
+    /// if it's available on the current platform.
+    peak_memory_kb: Option<u64>,
+    /// Items that took most of the time of the step.
+    slowest_items: Vec<ItemProfile>,
+}
+
+/// Returns the slowest of the items processed by a step.
+pub fn slowest_items(db: &DatabaseClient, mut timings: Vec<ItemTiming>) -> Vec<ItemProfile> {
+    timings.sort_by(|a, b| b.duration.cmp(&a.duration));
+    timings
+        .into_iter()
+        .take(SLOWEST_ITEMS_COUNT)
+        .map(|timing| {
+            let mut item = db.item(&timing.item_id).map_or_else(
+                |_| timing.item_id.to_string(),
+                |item| item.item.short_text(),
+            );
+            if let Some(context) = timing.context {
+                item = format!("{} ({})", item, context);
+            }
+            ItemProfile {
+                item,
+                seconds: timing.duration.as_secs_f64(),
+            }
+        })
+        .collect()
+}
+
+/// Returns the peak resident memory of the process in kilobytes.
+/// Only supported on Linux.
+fn peak_memory_kb() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find(|line| line.starts_with("VmHWM:"))?
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+/// Logs the steps that took most of the time and saves
+/// the profiling report to the log directory.
+fn save_profile(workspace: &Workspace, config: &Config, profile: &[StepProfile]) -> Result<()> {
+    if profile.is_empty() {
+        return Ok(());
+    }
+    let total = profile.iter().map(|step| step.seconds).sum::<f64>();
+    info!("Processing took {:.1} s", total);
+    for step in profile
+        .iter()
+        .sorted_by(|a, b| b.seconds.partial_cmp(&a.seconds).unwrap_or(Ordering::Equal))
+        .take(5)
+    {
+        info!(
+            "  {}: {:.1} s ({:.0}%)",
+            step.name,
+            step.seconds,
+            if total > 0.0 {
+                step.seconds / total * 100.0
+            } else {
+                0.0
+            }
+        );
+    }
+    if let Some(memory) = profile.iter().filter_map(|step| step.peak_memory_kb).max() {
+        info!("Peak memory usage: {} MiB", memory / 1024);
+    }
+    let crate_name = config.crate_properties().name();
+    let path = workspace
+        .log_path()
+        .join(format!("profile_{}.json", crate_name));
+    save_json(path, profile, None)?;
+
+    let report_dir = workspace.log_path().join(format!("profile_{}", crate_name));
+    create_dir_all(&report_dir)?;
+    let title = format!("Profile: {}", crate_name);
+    let templates = HtmlTemplates::load(config.html_report_theme_path().map(PathBuf::as_path))?;
+    let mut logger = HtmlLogger::new(report_dir.join("index.html"), &title, templates)?;
+    for step in profile {
+        logger.add_stage(&step.name, Duration::from_secs_f64(step.seconds));
+    }
+    logger.add_header(&[
+        "Step",
+        "Time",
+        "Items added / ignored / deleted",
+        "Peak memory",
+        "Slowest items",
+    ])?;
+    for step in profile {
+        let share = if total > 0.0 {
+            step.seconds / total
+        } else {
+            0.0
+        };
+        // highlight the steps that dominate the run
+        let severity = if share > 0.25 {
+            Severity::Warning
+        } else {
+            Severity::Info
+        };
+        let slowest_items = step
+            .slowest_items
+            .iter()
+            .map(|item| format!("{:.2} s: {}", item.seconds, escape_html(&item.item)))
+            .join("<br>");
+        logger.add_item(
+            &anchor(&step.name),
+            severity,
+            &[
+                &escape_html(&step.name),
+                &format!("{:.1} s ({:.0}%)", step.seconds, share * 100.0),
+                &format!(
+                    "{} / {} / {}",
+                    step.counters.items_added,
+                    step.counters.items_ignored,
+                    step.counters.items_deleted
+                ),
+                &step
+                    .peak_memory_kb
+                    .map_or_else(String::new, |memory| format!("{} MiB", memory / 1024)),
+                &slowest_items,
+            ],
+        )?;
+    }
+    logger.finish()?;
+    Ok(())
+}
+
+impl ProcessorData<'_> {
+    /// Returns `result` unchanged if lenient mode is disabled.
+    /// Otherwise, records the error as a skipped construct and returns `Ok`.
+    pub fn skip_if_lenient(
+        &mut self,
+        result: Result<()>,
+        description: impl FnOnce() -> String,
+        location: Option<CppOriginLocation>,
+    ) -> Result<()> {
+        match result {
+            Err(err) if self.config.lenient() => {
+                let skipped = SkippedConstruct {
+                    description: description(),
+                    reason: err.iter_chain().join(": "),
+                    location,
+                };
+                warn!("skipping {}: {}", skipped.description, skipped.reason);
+                self.skipped.push(skipped);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Records time spent on processing of an item for the profiling report.
+    pub fn record_item_time(
+        &mut self,
+        item_id: ItemId,
+        context: Option<String>,
+        duration: Duration,
+    ) {
+        self.item_timings.push(ItemTiming {
+            item_id,
+            context,
+            duration,
+        });
+    }
 }
 
 struct ProcessingStep {
@@ -105,6 +335,14 @@ impl Default for ProcessingSteps {
                 &format!("instantiate_templates{}", suffix),
                 cpp_template_instantiator::instantiate_templates,
             );
+            s.push(
+                &format!("resolve_dependent_functions{}", suffix),
+                cpp_parser::resolve_dependent_functions,
+            );
+            s.push(
+                &format!("probe_type_properties{}", suffix),
+                cpp_parser::probe_type_properties,
+            );
             s.push(
                 &format!("omitting_arguments{}", suffix),
                 cpp_omitting_arguments::run,
@@ -120,12 +358,17 @@ impl Default for ProcessingSteps {
         };
 
         s.push("cpp_parser", cpp_parser::run);
+        s.push("doxygen_parser", doxygen::run);
+        s.push("html_docs_parser", html_docs::run);
         push_cpp_post_processing(&mut s, "");
         s.push("cpp_parser_stage2", cpp_parser::parse_generated_items);
         push_cpp_post_processing(&mut s, "_stage2");
         s.push("rust_generator", rust_generator::run);
+        s.push("translate_doc_examples", doc_examples::run);
         s.push("crate_writer", crate_writer::run);
+        s.push("rust_checker", |data| rust_checker::run(data, false));
         s.push("build_crate", build_crate);
+        s.push("symbol_audit", symbol_audit::run);
 
         s.add_custom("clear_ffi", |data| {
             data.db.delete_items(|i| i.item.is_ffi_item());
@@ -140,10 +383,22 @@ impl Default for ProcessingSteps {
             Ok(())
         });
         s.add_custom("show_non_portable", show_non_portable);
+        s.add_custom("show_conflicts", show_conflicts);
+        s.add_custom("show_known_crashes", show_known_crashes);
+        s.add_custom("show_annotations", show_annotations);
+        s.add_custom("show_coverage", show_coverage);
+        s.add_custom("export_class_graph", class_graph::export_class_graph);
+        s.add_custom("export_type_graph", class_graph::export_type_graph);
+        s.add_custom("export_ffi_spec", ffi_spec::export_ffi_spec);
+        s.add_custom("import_ffi_spec", ffi_spec::import_ffi_spec);
+        s.add_custom("generate_fuzz_targets", fuzz::generate_fuzz_targets);
+        s.add_custom("export_fuzz_corpus", fuzz::export_fuzz_corpus);
         s.add_custom("migrate", migrate);
         s.add_custom("delete_orphans", delete_orphans);
+        s.add_custom("regenerate_ffi", regenerate_ffi);
         s.add_custom("delete_blacklisted_items", delete_blacklisted_items);
         s.add_custom("force_cpp_checker", |data| cpp_checker::run(data, true));
+        s.add_custom("rust_checker_build", |data| rust_checker::run(data, true));
         s
     }
 }
@@ -210,11 +465,7 @@ fn build_crate(data: &mut ProcessorData<'_>) -> Result<()> {
         let mut command = Command::new("cargo");
         command.arg(cargo_cmd).arg("-p").arg(crate_name);
 
-        if let Ok(dir) = env::var(WORKSPACE_TARGET_DIR) {
-            command.env("CARGO_TARGET_DIR", dir);
-        } else {
-            command.env_remove("CARGO_TARGET_DIR");
-        }
+        command.env("CARGO_TARGET_DIR", data.workspace.target_path());
 
         if cargo_cmd == &"doc" {
             command.arg("--features").arg("ritual_rustdoc");
@@ -295,6 +546,160 @@ fn show_non_portable(data: &mut ProcessorData<'_>) -> Result<()> {
     Ok(())
 }
 
+/// Reports C++ items marked as known to crash at runtime
+/// and suppressed by the FFI generator.
+fn show_known_crashes(data: &mut ProcessorData<'_>) -> Result<()> {
+    let mut count = 0;
+    for (name, reason) in data.db.known_crashes() {
+        let items = data
+            .db
+            .cpp_items()
+            .filter(|item| {
+                item.item
+                    .path()
+                    .map_or(false, |path| path.to_templateless_string() == name)
+            })
+            .count();
+        if items == 0 {
+            info!("{}: {} (no matching C++ items)", name, reason);
+        } else {
+            info!("{}: {} ({} suppressed items)", name, reason, items);
+        }
+        count += 1;
+    }
+    info!("Found {} items known to crash", count);
+    Ok(())
+}
+
+/// Reports all maintainer annotations and whether annotated items
+/// are still present in the database.
+fn show_annotations(data: &mut ProcessorData<'_>) -> Result<()> {
+    let existing = data
+        .db
+        .items()
+        .filter_map(|item| item.item.annotation_key())
+        .collect::<HashSet<_>>();
+    let mut count = 0;
+    for (item_text, annotations) in data.db.all_annotations() {
+        if existing.contains(item_text) {
+            info!("{}", item_text);
+        } else {
+            info!("{} (item no longer exists)", item_text);
+        }
+        for annotation in annotations {
+            info!(
+                "    rev. {} ({}, version {}): {}",
+                annotation.revision, annotation.date, annotation.crate_version, annotation.text
+            );
+        }
+        count += 1;
+    }
+    info!("Found {} annotated items", count);
+    Ok(())
+}
+
+/// Numbers of C++ functions of one kind and of those wrapped by Rust API functions
+/// (see `function_coverage`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionCoverage {
+    pub kind: &'static str,
+    pub total: u32,
+    pub wrapped: u32,
+}
+
+/// Returns how many C++ functions of each kind (methods, free functions
+/// and operators) of the current crate are wrapped by Rust API functions.
+pub fn function_coverage(db: &DatabaseClient) -> Result<Vec<FunctionCoverage>> {
+    let mut wrapped = HashSet::new();
+    for item in db.rust_items() {
+        let is_wrapper = item
+            .item
+            .as_function_ref()
+            .map_or(false, |function| function.kind.is_ffi_wrapper());
+        if is_wrapper {
+            if let Some(cpp_item) = db.source_cpp_item(&item.id)? {
+                wrapped.insert(cpp_item.id);
+            }
+        }
+    }
+
+    let mut output = ["methods", "free functions", "operators"]
+        .iter()
+        .map(|&kind| FunctionCoverage {
+            kind,
+            total: 0,
+            wrapped: 0,
+        })
+        .collect_vec();
+    for item in db.cpp_items() {
+        let function = match item.item.as_function_ref() {
+            Some(function) => function,
+            None => continue,
+        };
+        let index = if function.is_operator() {
+            2
+        } else if function.member.is_some() {
+            0
+        } else {
+            1
+        };
+        output[index].total += 1;
+        if wrapped.contains(&item.id) {
+            output[index].wrapped += 1;
+        }
+    }
+    Ok(output)
+}
+
+fn show_coverage(data: &mut ProcessorData<'_>) -> Result<()> {
+    for coverage in function_coverage(data.db)? {
+        let percent = if coverage.total == 0 {
+            100.0
+        } else {
+            f64::from(coverage.wrapped) * 100.0 / f64::from(coverage.total)
+        };
+        info!(
+            "{}: {} of {} wrapped ({:.1}%)",
+            coverage.kind, coverage.wrapped, coverage.total, percent
+        );
+    }
+    Ok(())
+}
+
+fn show_conflicts(data: &mut ProcessorData<'_>) -> Result<()> {
+    let source_text = |source_id: &Option<ItemId>| {
+        source_id
+            .as_ref()
+            .map_or_else(|| "<none>".to_string(), ToString::to_string)
+    };
+    let mut count = 0;
+    for conflict in data.db.cpp_conflict_items() {
+        let existing_id = conflict
+            .source_id
+            .as_ref()
+            .ok_or_else(|| err_msg("conflict item must have a source"))?;
+        let existing = data.db.item(existing_id)?;
+        let existing_item = existing
+            .item
+            .as_cpp_item()
+            .ok_or_else(|| err_msg("conflict source must be a C++ item"))?;
+        info!("conflicting definitions of {}:", existing_id);
+        info!(
+            "    {} (source: {})",
+            existing_item,
+            source_text(&existing.source_id)
+        );
+        info!(
+            "    {} (source: {})",
+            conflict.item.item,
+            source_text(&conflict.item.source_id)
+        );
+        count += 1;
+    }
+    info!("Found {} conflicts", count);
+    Ok(())
+}
+
 fn migrate(data: &mut ProcessorData<'_>) -> Result<()> {
     data.db.delete_items(|item| {
         item.item
@@ -319,6 +724,114 @@ fn delete_orphans(data: &mut ProcessorData<'_>) -> Result<()> {
     Ok(())
 }
 
+/// Adds all items derived from `ids` (directly or indirectly) to `ids`.
+pub fn with_derived_items(db: &DatabaseClient, mut ids: HashSet<ItemId>) -> HashSet<ItemId> {
+    loop {
+        let derived = db
+            .items()
+            .filter(|item| !ids.contains(&item.id))
+            .filter(|item| item.source_id.as_ref().map_or(false, |id| ids.contains(id)))
+            .map(|item| item.id.clone())
+            .collect_vec();
+        if derived.is_empty() {
+            return ids;
+        }
+        ids.extend(derived);
+    }
+}
+
+/// Deletes C++ items with the qualified name `name` (without template arguments)
+/// that were parsed from the headers, and all items derived from them.
+/// Returns the headers the deleted items were declared in.
+pub fn delete_parsed_item(db: &mut DatabaseClient, name: &str) -> Result<Vec<PathBuf>> {
+    let cpp_ids: HashSet<ItemId> = db
+        .cpp_items()
+        .filter(|item| item.source_id.is_none())
+        .filter(|item| {
+            item.item
+                .path()
+                .map_or(false, |path| path.to_templateless_string() == name)
+        })
+        .map(|item| item.id)
+        .collect();
+    if cpp_ids.is_empty() {
+        bail!("no C++ items found for {}", name);
+    }
+    let mut headers = Vec::new();
+    for id in &cpp_ids {
+        if let Some(location) = db.cpp_origin_location(id)? {
+            let header = PathBuf::from(&location.include_file_path);
+            if !headers.contains(&header) {
+                headers.push(header);
+            }
+        }
+    }
+    headers.sort();
+    let ids = with_derived_items(db, cpp_ids);
+    info!("deleting {} items of {}", ids.len(), name);
+    db.delete_items(|item| ids.contains(&item.id));
+    Ok(headers)
+}
+
+/// Reprocesses C++ items with the qualified name `name` (without template arguments)
+/// from scratch. The items and all items derived from them are deleted,
+/// the declarations are parsed again from the headers they were declared in,
+/// and the steps of the main procedure after `cpp_parser` up to `rust_generator`
+/// are run. Steps only process the items they haven't processed yet,
+/// so only the new items are processed.
+pub fn reprocess_item(data: &mut ProcessorData<'_>, name: &str) -> Result<()> {
+    let headers = delete_parsed_item(data.db, name)?;
+    info!("parsing {}", name);
+    cpp_parser::parse_item(data, name, &headers)?;
+    run_main_procedure_range(data, "doxygen_parser", "rust_generator")
+}
+
+/// Runs steps of the main procedure from `first` to `last` (inclusive).
+fn run_main_procedure_range(data: &mut ProcessorData<'_>, first: &str, last: &str) -> Result<()> {
+    let config = data.config;
+    let steps = config.processing_steps();
+    let position = |name: &str| {
+        steps
+            .main_procedure
+            .iter()
+            .position(|s| s == name)
+            .ok_or_else(|| format_err!("step not found in main procedure: {}", name))
+    };
+    let range = position(first)?..=position(last)?;
+    for step_name in &steps.main_procedure[range] {
+        let step = steps
+            .all_steps
+            .iter()
+            .find(|step| &step.name == step_name)
+            .expect("main procedure step must be in all_steps");
+        info!("Running processing step: {}", step.name);
+        (step.function)(data)?;
+    }
+    Ok(())
+}
+
+/// Deletes all FFI items and items derived from them and reruns
+/// the FFI generation steps of the main procedure (from `cpp_ffi_generator`
+/// to `cpp_checker_stage2`) on the existing C++ data. Headers are not reparsed.
+/// Rust items derived from FFI items are deleted as well, so `rust_generator`
+/// needs to be run afterwards.
+fn regenerate_ffi(data: &mut ProcessorData<'_>) -> Result<()> {
+    let ids = with_derived_items(
+        data.db,
+        data.db
+            .items()
+            .filter(|item| item.item.is_ffi_item())
+            .map(|item| item.id.clone())
+            .collect(),
+    );
+    info!(
+        "deleting {} FFI items and items derived from them",
+        ids.len()
+    );
+    data.db.delete_items(|item| ids.contains(&item.id));
+    run_main_procedure_range(data, "cpp_ffi_generator", "cpp_checker_stage2")
+}
+
 #[derive(Debug)]
 struct MainItemRef<'a> {
     step: &'a ProcessingStep,
@@ -352,7 +865,7 @@ pub fn process(
     workspace: &mut Workspace,
     config: &Config,
     mut step_names: &[String],
-    trace_item_id: Option<&ItemId>,
+    inspection: Option<&Inspection>,
 ) -> Result<()> {
     info!("Processing crate: {}", config.crate_properties().name());
     check_all_paths(&config)?;
@@ -379,13 +892,51 @@ pub fn process(
         .with_context(|_| "failed to load current crate data")?;
 
     db_client.set_crate_version(config.crate_properties().version().to_string());
+    create_dir_all(workspace.crate_tmp_path(config.crate_properties().name()))?;
 
-    if let Some(trace_item_id) = trace_item_id {
-        db_client.print_item_trace(trace_item_id)?;
+    if let Some(inspection) = inspection {
+        match inspection {
+            Inspection::Trace(item_id) => db_client.print_item_trace(item_id)?,
+            Inspection::Explain(name) => explain::explain(config, &db_client, name)?,
+            Inspection::Browse => {
+                browser::run(&mut db_client)?;
+                workspace.save_database(&mut db_client)?;
+            }
+            Inspection::Reprocess(name) => {
+                let mut skipped = Vec::new();
+                let mut data = ProcessorData {
+                    workspace,
+                    db: &mut db_client,
+                    config,
+                    skipped: &mut skipped,
+                    item_timings: &mut Vec::new(),
+                };
+                reprocess_item(&mut data, name)?;
+                workspace.save_database(&mut db_client)?;
+            }
+            Inspection::DumpPreprocessed(header) => {
+                let mut skipped = Vec::new();
+                let mut data = ProcessorData {
+                    workspace,
+                    db: &mut db_client,
+                    config,
+                    skipped: &mut skipped,
+                    item_timings: &mut Vec::new(),
+                };
+                cpp_parser::dump_preprocessed(&mut data, header)?;
+            }
+        }
         return Ok(());
     }
 
+    if allow_load {
+        let inputs = lockfile::Lockfile::collect(workspace, config)?;
+        lockfile::verify(workspace, config, &inputs)?;
+    }
+
     let mut steps_result = Ok(());
+    let mut skipped = Vec::new();
+    let mut profile = Vec::new();
 
     let step_index = |name| {
         config
@@ -448,25 +999,34 @@ pub fn process(
 
             info!("Running processing step: {}", &step.name);
 
+            let mut item_timings = Vec::new();
             let mut data = ProcessorData {
                 workspace,
                 db: &mut db_client,
                 config,
+                skipped: &mut skipped,
+                item_timings: &mut item_timings,
             };
 
             let started_time = Instant::now();
+            let result = (step.function)(&mut data);
+            let elapsed = started_time.elapsed();
+            profile.push(StepProfile {
+                name: step.name.clone(),
+                seconds: elapsed.as_secs_f64(),
+                counters: db_client.report_counters(),
+                peak_memory_kb: peak_memory_kb(),
+                slowest_items: slowest_items(&db_client, item_timings),
+            });
 
-            if let Err(err) = (step.function)(&mut data) {
+            if let Err(err) = result {
                 steps_result = Err(err);
                 error!("Step failed! Aborting...");
                 break;
             }
 
-            let elapsed = started_time.elapsed();
             trace!("Step '{}' completed in {:?}", step.name, elapsed);
 
-            db_client.report_counters();
-
             if elapsed > Duration::from_secs(15) {
                 workspace.save_database(&mut db_client)?;
             }
@@ -474,6 +1034,25 @@ pub fn process(
     }
 
     workspace.save_database(&mut db_client)?;
+    save_profile(workspace, config, &profile)?;
+
+    if !skipped.is_empty() {
+        let path = workspace
+            .log_path()
+            .join(format!("skipped_{}.json", config.crate_properties().name()));
+        warn!(
+            "{} C++ constructs were skipped (see {})",
+            skipped.len(),
+            path.display()
+        );
+        save_json(path, &skipped, None)?;
+    }
+
+    if steps_result.is_ok() {
+        // the include graph may have been updated by the parser
+        let inputs = lockfile::Lockfile::collect(workspace, config)?;
+        lockfile::save(workspace, config, &inputs)?;
+    }
 
     steps_result
 }
@@ -592,6 +1171,12 @@ fn test_parse_steps_spec() {
 }
 
 impl ProcessorData<'_> {
+    /// Returns path to the directory for temporary files of the current crate.
+    pub fn tmp_path(&self) -> PathBuf {
+        self.workspace
+            .crate_tmp_path(self.config.crate_properties().name())
+    }
+
     pub fn add_cpp_item(
         &mut self,
         source_id: Option<ItemId>,
@@ -0,0 +1,311 @@
+//! Verification of the generated Rust crate using the Rust compiler.
+//!
+//! `cargo check` is run on the generated crate for each known library target
+//! that has a Rust target triple. Targets other than the current one are checked
+//! without building the C++ wrapper library, so only the Rust standard library
+//! for the target needs to be installed. Errors reported by the compiler are mapped
+//! back to the Rust items that produced the erroneous code and recorded
+//! in the database as `RustChecksItem`s.
+//!
+//! The checker runs as the `rust_checker` step of the main procedure.
+//! `rust_checker_build` runs `cargo build` for the current target instead.
+
+use crate::database::ItemId;
+use crate::processor::ProcessorData;
+use itertools::Itertools;
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use ritual_common::env_var_names;
+use ritual_common::errors::{bail, Result};
+use ritual_common::file_utils::file_to_string;
+use ritual_common::target::{current_target, LibraryTarget};
+use ritual_common::utils::run_command_and_capture_output;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Declaration of a named item on one line of the generated code.
+static DECLARATION: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(\s*)(?:pub(?:\([^)]*\))?\s+)?(?:unsafe\s+)?(?:fn|struct|enum|union|type|trait|mod)\s+(\w+)",
+    )
+    .unwrap()
+});
+
+/// Header of an `impl` block. The name of the implementing type is captured.
+static IMPL_BLOCK: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(\s*)(?:unsafe\s+)?impl(?:<.*>)?\s+(?:.+\s+for\s+)?(?:[\w]+::)*(\w+)").unwrap()
+});
+
+/// Result of compiling the code generated for a Rust item on a library target.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RustChecksItem {
+    pub env: LibraryTarget,
+    /// Rendered compiler errors.
+    pub errors: Vec<String>,
+}
+
+/// Compiler error with the location of its primary span.
+#[derive(Debug, Clone)]
+struct CompilerError {
+    file: PathBuf,
+    /// 1-based line number.
+    line: usize,
+    rendered: String,
+}
+
+/// Extracts errors from the output of `cargo check --message-format=json`.
+fn parse_compiler_errors(output: &str, workspace_path: &Path) -> Vec<CompilerError> {
+    let mut errors = Vec::new();
+    for line in output.lines() {
+        let value: Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        if value["reason"] != "compiler-message" || value["message"]["level"] != "error" {
+            continue;
+        }
+        let message = &value["message"];
+        let rendered = message["rendered"]
+            .as_str()
+            .or_else(|| message["message"].as_str())
+            .unwrap_or_default()
+            .to_string();
+        let primary_span = message["spans"]
+            .as_array()
+            .and_then(|spans| spans.iter().find(|span| span["is_primary"] == true));
+        if let Some(span) = primary_span {
+            let file_name = span["file_name"].as_str().unwrap_or_default();
+            let line = span["line_start"].as_u64().unwrap_or_default() as usize;
+            errors.push(CompilerError {
+                file: workspace_path.join(file_name),
+                line,
+                rendered,
+            });
+        }
+    }
+    errors
+}
+
+/// Returns the path of the Rust module corresponding to `file`.
+pub fn module_parts(crate_name: &str, src_path: &Path, file: &Path) -> Option<Vec<String>> {
+    let relative = file.strip_prefix(src_path).ok()?;
+    let mut parts = vec![crate_name.to_string()];
+    for component in relative.with_extension("").iter() {
+        parts.push(component.to_str()?.to_string());
+    }
+    // `lib.rs` and `ffi.in.rs` contain items of the crate root
+    if parts.len() == 2 && (parts[1] == "lib" || parts[1] == "ffi.in") {
+        parts.pop();
+    }
+    Some(parts)
+}
+
+/// Returns the path of the innermost item declared at or above line `line` of `source`,
+/// relative to the module of the file. Generated code is formatted, so items nested
+/// in `impl` blocks, traits and inline modules are detected by their indentation.
+pub fn enclosing_item_path(source: &str, line: usize) -> Option<Vec<String>> {
+    let mut path = Vec::new();
+    let mut indent: Option<usize> = None;
+    for line in source.lines().take(line).collect_vec().into_iter().rev() {
+        let captures = DECLARATION
+            .captures(line)
+            .or_else(|| indent.and_then(|_| IMPL_BLOCK.captures(line)));
+        let captures = match captures {
+            Some(captures) => captures,
+            None => continue,
+        };
+        let line_indent = captures[1].len();
+        if indent.map_or(true, |indent| line_indent < indent) {
+            path.insert(0, captures[2].to_string());
+            indent = Some(line_indent);
+            if line_indent == 0 {
+                break;
+            }
+        }
+    }
+    if path.is_empty() {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+struct RustChecker<'a, 'b> {
+    data: &'a mut ProcessorData<'b>,
+    build: bool,
+}
+
+impl RustChecker<'_, '_> {
+    /// Returns the Rust item that generated the code at the error location.
+    /// Returns `None` if no item or more than one item has the path
+    /// of the enclosing declaration.
+    fn find_item(&self, src_path: &Path, error: &CompilerError) -> Result<Option<ItemId>> {
+        let crate_name = self.data.db.crate_name();
+        let mut path = match module_parts(crate_name, src_path, &error.file) {
+            Some(parts) => parts,
+            None => return Ok(None),
+        };
+        let source = file_to_string(&error.file)?;
+        match enclosing_item_path(&source, error.line) {
+            Some(parts) => path.extend(parts),
+            None => return Ok(None),
+        }
+        let candidates = self
+            .data
+            .db
+            .rust_items()
+            .filter(|item| item.item.path().map_or(false, |p| p.parts() == &path[..]))
+            .map(|item| item.id)
+            .collect_vec();
+        if candidates.len() > 1 {
+            warn!(
+                "Compiler error location matches {} items: {}",
+                candidates.len(),
+                path.join("::")
+            );
+        }
+        if candidates.len() == 1 {
+            Ok(candidates.into_iter().next())
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Library targets to check: the current target and one library target
+    /// for each other known target that has a Rust target triple.
+    fn targets(&self) -> Result<Vec<LibraryTarget>> {
+        let current = self.data.config.current_library_target()?;
+        let mut targets = vec![current];
+        if self.build {
+            return Ok(targets);
+        }
+        for target in self.data.db.environments() {
+            if targets.iter().any(|t| t.target == target.target) {
+                continue;
+            }
+            if target.target.rust_triple().is_none() {
+                warn!(
+                    "Rust target triple is unknown, skipping target: {}",
+                    target.target.short_text()
+                );
+                continue;
+            }
+            targets.push(target.clone());
+        }
+        Ok(targets)
+    }
+
+    /// Compiles the crate for `target` and records the errors.
+    /// Returns false if the crate failed to compile.
+    fn check_target(&mut self, target: &LibraryTarget) -> Result<bool> {
+        let crate_name = self.data.config.crate_properties().name().to_string();
+        let workspace_path = self.data.workspace.path().to_path_buf();
+        let src_path = self.data.workspace.crate_path(&crate_name).join("src");
+        let is_current = target.target == current_target();
+
+        self.data.db.delete_items(|item| {
+            item.item
+                .as_rust_checks_item()
+                .map_or(false, |item| item.env.target == target.target)
+        });
+
+        let mut command = Command::new("cargo");
+        command
+            .arg(if self.build { "build" } else { "check" })
+            .arg("--message-format=json")
+            .arg("-p")
+            .arg(&crate_name)
+            .current_dir(&workspace_path)
+            .env("CARGO_TARGET_DIR", self.data.workspace.target_path());
+        if !is_current {
+            let triple = target
+                .target
+                .rust_triple()
+                .expect("targets without triple are skipped");
+            command
+                .arg("--target")
+                .arg(triple)
+                .env(env_var_names::SKIP_CPP_BUILD, "1");
+        }
+        let output = run_command_and_capture_output(&mut command)?;
+        let errors = parse_compiler_errors(&output.stdout, &workspace_path);
+
+        let mut item_errors = HashMap::<ItemId, Vec<String>>::new();
+        let mut unmapped_count = 0;
+        for error in &errors {
+            match self.find_item(&src_path, error)? {
+                Some(id) => item_errors
+                    .entry(id)
+                    .or_default()
+                    .push(error.rendered.clone()),
+                None => {
+                    unmapped_count += 1;
+                    warn!("Compiler error not mapped to any item:\n{}", error.rendered);
+                }
+            }
+        }
+
+        for (id, errors) in item_errors.iter().sorted_by_key(|(id, _)| *id) {
+            self.data.db.add_rust_checks_item(
+                id.clone(),
+                RustChecksItem {
+                    env: target.clone(),
+                    errors: errors.clone(),
+                },
+            );
+        }
+
+        info!(
+            "Compiler errors on {}: {} (in {} items; {} not mapped to items)",
+            target.target.short_text(),
+            errors.len(),
+            item_errors.len(),
+            unmapped_count
+        );
+        if output.is_success() {
+            return Ok(true);
+        }
+        if errors.is_empty() {
+            warn!("cargo stderr:\n{}", output.stderr);
+            if !is_current {
+                // e.g. the standard library for the target is not installed
+                warn!(
+                    "failed to check target, skipping: {}",
+                    target.target.short_text()
+                );
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn run(&mut self) -> Result<()> {
+        self.data.workspace.update_cargo_toml()?;
+        let mut failed_targets = Vec::new();
+        for target in self.targets()? {
+            if !self.check_target(&target)? {
+                failed_targets.push(target.target.short_text());
+            }
+        }
+        if !failed_targets.is_empty() {
+            bail!(
+                "generated crate failed to compile: {} (targets: {})",
+                self.data.config.crate_properties().name(),
+                failed_targets.join(", ")
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Compiles the generated crate and records the errors. If `build` is false,
+/// `cargo check` is performed for all known targets. Otherwise, the crate is built
+/// for the current target only.
+pub fn run(data: &mut ProcessorData<'_>, build: bool) -> Result<()> {
+    let mut checker = RustChecker { data, build };
+    checker.run()
+}
@@ -1,26 +1,42 @@
 //! Types and functions used for Rust code generation.
 
+use crate::config::{
+    CallbackPanicBehavior, Config, EventLoopFunctions, LifecycleFunctions, OptionalTypeConversion,
+    PluginRegistry, StringEncoding,
+};
 use crate::cpp_checks::Condition;
-use crate::cpp_ffi_data::{CppFfiArgumentMeaning, CppFfiItem};
+use crate::cpp_data::{CppPath, CppTypeProperties};
+use crate::cpp_ffi_data::{
+    CppFfiArgumentMeaning, CppFfiFunction, CppFfiFunctionKind, CppFfiItem, CppFieldAccessorType,
+    FfiCallingConvention,
+};
 use crate::cpp_function::CppFunction;
+use crate::cpp_type::{CppBuiltInNumericType, CppType};
 use crate::database::{DatabaseClient, DbItem, ItemId};
 use crate::doc_formatter;
 use crate::rust_generator::qt_core_path;
 use crate::rust_info::{
-    RustEnumValue, RustExtraImpl, RustExtraImplKind, RustFfiWrapperData, RustFunction,
-    RustFunctionArgument, RustFunctionKind, RustItem, RustModule, RustModuleKind,
-    RustQtReceiverType, RustSpecialModuleKind, RustStruct, RustStructKind, RustTraitImpl,
-    RustWrapperTypeKind,
+    EnumRepresentation, RustEnumValue, RustExtraImpl, RustExtraImplKind, RustFfiWrapperData,
+    RustFunction, RustFunctionArgument, RustFunctionKind, RustFunctionSelfArgKind, RustHandleType,
+    RustInlineFieldAccess, RustItem, RustModule, RustModuleKind, RustOverloadTrait,
+    RustOwnedHandleType, RustQtReceiverType, RustSpecialModuleKind, RustStruct, RustStructKind,
+    RustTraitImpl, RustWrapperTypeKind,
 };
 use crate::rust_type::{
-    RustClosureToCallbackConversion, RustCommonType, RustFinalType, RustPath,
-    RustPointerLikeTypeKind, RustToFfiTypeConversion, RustType,
+    RustAsyncCompletionConversion, RustClosureToCallbackConversion, RustCommonType, RustFinalType,
+    RustPath, RustPointerLikeTypeKind, RustToFfiTypeConversion, RustType,
 };
+use crate::std_conversions;
+use crate::stream_adapters;
+use crate::string_encoding;
 use itertools::Itertools;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use ritual_common::errors::{bail, err_msg, format_err, Result};
 use ritual_common::file_utils::{create_dir_all, create_file, file_to_string, File};
 use ritual_common::string_utils::trim_slice;
+use ritual_common::target::LibraryTarget;
 use ritual_common::utils::MapIfOk;
+use std::collections::HashSet;
 use std::fmt::Write as _;
 use std::fs;
 use std::io::{self, BufWriter, Write};
@@ -110,6 +126,14 @@ pub fn rust_type_to_code(rust_type: &RustType, current_crate: Option<&str>) -> S
             "impl {}",
             rust_common_type_to_code(trait_type, current_crate)
         ),
+        RustType::Array { element_type, size } => format!(
+            "[{}; {}]",
+            rust_type_to_code(element_type, current_crate),
+            size
+        ),
+        RustType::Slice { element_type } => {
+            format!("[{}]", rust_type_to_code(element_type, current_crate))
+        }
     }
 }
 
@@ -118,6 +142,26 @@ struct Generator<'a> {
     crate_template_src_path: Option<PathBuf>,
     destination: Vec<File<BufWriter<fs::File>>>,
     current_database: &'a DatabaseClient,
+    /// See `Config::set_cpp_doc_url_template`.
+    cpp_doc_url_template: Option<&'a str>,
+    /// See `Config::set_callback_panic_behavior`.
+    callback_panic_behavior: CallbackPanicBehavior,
+    /// See `Config::set_event_loop_functions`.
+    event_loop_functions: Option<&'a EventLoopFunctions>,
+    /// See `Config::set_lifecycle_functions`.
+    lifecycle_functions: Option<&'a LifecycleFunctions>,
+    /// See `Config::add_optional_type_conversion`.
+    optional_type_conversions: &'a [OptionalTypeConversion],
+    /// See `Config::add_plugin_registry`.
+    plugin_registries: &'a [PluginRegistry],
+    /// See `Config::set_string_encoding`.
+    string_encoding: Option<StringEncoding>,
+    /// See `Config::add_ffi_declarations`.
+    ffi_declarations: &'a [String],
+    /// Modules in separate files that were declared but not generated yet.
+    pending_modules: Vec<ItemId>,
+    /// C++ classes that have virtual functions directly or through their bases.
+    polymorphic_classes: &'a HashSet<CppPath>,
 }
 
 impl Write for Generator<'_> {
@@ -179,12 +223,13 @@ fn format_doc_extended(doc: &str, is_outer: bool) -> String {
 fn condition_expression(condition: &Condition) -> String {
     match condition {
         Condition::CppLibraryVersion(version) => format!("cpp_lib_version={:?}", version),
-        Condition::Arch(_) => unimplemented!(),
-        Condition::OS(_) => unimplemented!(),
-        Condition::Family(_) => unimplemented!(),
-        Condition::Env(_) => unimplemented!(),
-        Condition::PointerWidth(_) => unimplemented!(),
-        Condition::Endian(_) => unimplemented!(),
+        Condition::Feature(name) | Condition::FlagSet(name) => format!("feature = {:?}", name),
+        Condition::Arch(arch) => format!("target_arch = {:?}", arch.cfg_value()),
+        Condition::OS(os) => format!("target_os = {:?}", os.cfg_value()),
+        Condition::Family(family) => format!("target_family = {:?}", family.cfg_value()),
+        Condition::Env(env) => format!("target_env = {:?}", env.cfg_value()),
+        Condition::PointerWidth(width) => format!("target_pointer_width = {:?}", width.cfg_value()),
+        Condition::Endian(endian) => format!("target_endian = {:?}", endian.cfg_value()),
         Condition::And(conditions) => {
             let list = conditions.iter().map(condition_expression).join(", ");
             format!("all({})", list)
@@ -199,6 +244,22 @@ fn condition_expression(condition: &Condition) -> String {
     }
 }
 
+/// Returns conditional compilation attribute for `condition`.
+fn condition_texts(condition: &Condition) -> ConditionTexts {
+    let mut condition_texts = ConditionTexts::default();
+    if condition != &Condition::True {
+        let expression = condition_expression(condition);
+        condition_texts.attribute = format!(
+            "#[cfg_attr(feature = \"ritual_rustdoc_nightly\", doc(cfg({})))]\n\
+            #[cfg(any({}, feature = \"ritual_rustdoc\"))]\n",
+            expression, expression
+        );
+        // condition_texts.doc_text =
+        // format!("\n\nThis item is available if `{}`.", expression);
+    }
+    condition_texts
+}
+
 impl Generator<'_> {
     fn module_path(&self, rust_path: &RustPath, root_path: &Path) -> Result<PathBuf> {
         let parts = &rust_path.parts;
@@ -281,28 +342,7 @@ impl Generator<'_> {
             }
         }
 
-        let ffi_item = self
-            .current_database
-            .source_ffi_item(&item_for_condition.id)?;
-
-        let mut condition_texts = ConditionTexts::default();
-
-        if let Some(ffi_item) = ffi_item {
-            let condition = self
-                .current_database
-                .cpp_checks(&ffi_item.id)?
-                .condition(self.current_database.environments());
-            if condition != Condition::True {
-                let expression = condition_expression(&condition);
-                condition_texts.attribute = format!(
-                    "#[cfg_attr(feature = \"ritual_rustdoc_nightly\", doc(cfg({})))]\n\
-                    #[cfg(any({}, feature = \"ritual_rustdoc\"))]\n",
-                    expression, expression
-                );
-                // condition_texts.doc_text =
-                // format!("\n\nThis item is available if `{}`.", expression);
-            }
-        }
+        let condition_texts = self.condition_texts(&item_for_condition.id)?;
 
         match &item.item {
             RustItem::Module(_) => self.generate_module(item.map(|i| i.as_module_ref().unwrap())),
@@ -329,7 +369,8 @@ impl Generator<'_> {
             RustItem::Reexport(reexport) => {
                 writeln!(
                     self,
-                    "pub use {} as {};",
+                    "{}pub use {} as {};",
+                    condition_texts.attribute,
                     self.rust_path_to_string(&reexport.target),
                     reexport.path.last()
                 )?;
@@ -338,6 +379,25 @@ impl Generator<'_> {
         }
     }
 
+    /// Returns the condition of availability of the item based on
+    /// the checks of its source FFI item.
+    fn condition(&self, id: &ItemId) -> Result<Condition> {
+        let condition = match self.current_database.source_ffi_item(id)? {
+            Some(ffi_item) => self
+                .current_database
+                .cpp_checks(&ffi_item.id)?
+                .condition(self.current_database.environments()),
+            None => Condition::True,
+        };
+        Ok(condition)
+    }
+
+    /// Returns conditional compilation attribute for the item based on
+    /// the checks of its source FFI item.
+    fn condition_texts(&self, id: &ItemId) -> Result<ConditionTexts> {
+        Ok(condition_texts(&self.condition(id)?))
+    }
+
     fn rust_type_to_code(&self, rust_type: &RustType) -> String {
         rust_type_to_code(rust_type, Some(&self.current_database.crate_name()))
     }
@@ -362,8 +422,11 @@ impl Generator<'_> {
         let vis = if module.item.is_public { "pub " } else { "" };
         let mut content_from_template = None;
         if module.item.kind.is_in_separate_file() {
-            if module.item.kind != RustModuleKind::Special(RustSpecialModuleKind::CrateRoot) {
+            if !self.destination.is_empty() {
+                // content of the module will be generated by a separate task
                 writeln!(self, "{}mod {};", vis, module.item.path.last())?;
+                self.pending_modules.push(module.id.clone());
+                return Ok(());
             }
             let path = self.module_path(&module.item.path, &self.output_src_path)?;
             self.push_file(&path)?;
@@ -411,8 +474,45 @@ impl Generator<'_> {
                     "include!(concat!(env!(\"OUT_DIR\"), \"/sized_types.rs\"));"
                 )?;
             }
-            RustModuleKind::Special(RustSpecialModuleKind::CrateRoot)
-            | RustModuleKind::Special(RustSpecialModuleKind::Ops)
+            RustModuleKind::Special(RustSpecialModuleKind::CrateRoot) => {
+                self.generate_children(&module.item.path, None)?;
+                self.generate_class_registry()?;
+                if let Some(functions) = self.event_loop_functions {
+                    self.generate_event_loop_module(functions)?;
+                }
+                if let Some(functions) = self.lifecycle_functions {
+                    self.generate_library_handle(functions)?;
+                }
+                for conversion in self.optional_type_conversions {
+                    self.generate_optional_type_conversion(conversion)?;
+                }
+                for registry in self.plugin_registries {
+                    self.generate_plugin_registry(registry)?;
+                }
+                for conversion in std_conversions::find_std_conversions(self.current_database)? {
+                    let condition_texts = condition_texts(&conversion.condition);
+                    let code = std_conversions::rust_impls(&conversion)?;
+                    write!(self, "{}{}", condition_texts.attribute, code)?;
+                }
+                let stream_types = stream_adapters::find_stream_types(self.current_database)?;
+                let code = stream_adapters::rust_module(
+                    &stream_types,
+                    self.current_database.crate_name(),
+                )?;
+                write!(self, "{}", code)?;
+                if let Some(encoding) = self.string_encoding {
+                    let std_string = string_encoding::find_std_string_type(self.current_database)?;
+                    let code = string_encoding::rust_module(
+                        encoding,
+                        self.current_database.crate_name(),
+                        std_string.as_ref(),
+                    );
+                    write!(self, "{}", code)?;
+                }
+            }
+            RustModuleKind::Special(RustSpecialModuleKind::Ops)
+            | RustModuleKind::Special(RustSpecialModuleKind::Prelude)
+            | RustModuleKind::Special(RustSpecialModuleKind::Unsafe)
             | RustModuleKind::CppNamespace { .. }
             | RustModuleKind::CppNestedTypes { .. } => {
                 self.generate_children(&module.item.path, None)?;
@@ -431,6 +531,9 @@ impl Generator<'_> {
             self.destination.push(create_file(&path)?);
             writeln!(self, "extern \"C\" {{\n")?;
             self.generate_children(&module.item.path, None)?;
+            for code in self.ffi_declarations {
+                writeln!(self, "{}\n", code)?;
+            }
             writeln!(self, "}}\n")?;
             self.pop_file();
         }
@@ -451,13 +554,476 @@ impl Generator<'_> {
         }
     }
 
+    /// Generates `cpp_core::CppClass` implementation with the class metadata
+    /// for a class wrapper.
+    fn generate_cpp_class_impl(
+        &mut self,
+        rust_struct: DbItem<&RustStruct>,
+        kind: &RustWrapperTypeKind,
+        condition_texts: &ConditionTexts,
+    ) -> Result<()> {
+        let size = match kind {
+            RustWrapperTypeKind::EnumWrapper { .. } => return Ok(()),
+            RustWrapperTypeKind::ImmovableClassWrapper
+            | RustWrapperTypeKind::PinnedClassWrapper => "None".to_string(),
+            RustWrapperTypeKind::MovableClassWrapper { .. }
+            | RustWrapperTypeKind::UnionWrapper { .. }
+            | RustWrapperTypeKind::PlainStructWrapper { .. } => {
+                "Some(::std::mem::size_of::<Self>())".to_string()
+            }
+        };
+        let cpp_path = match self.current_database.source_cpp_item(&rust_struct.id)? {
+            Some(item) => match item.item.path() {
+                Some(path) => path.clone(),
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+        writeln!(
+            self,
+            "{}impl ::cpp_core::CppClass for {} {{",
+            condition_texts.attribute,
+            rust_struct.item.path.last()
+        )?;
+        writeln!(
+            self,
+            "const CPP_NAME: &'static str = {:?};",
+            cpp_path.to_cpp_pseudo_code()
+        )?;
+        writeln!(
+            self,
+            "const RUST_NAME: &'static str = {:?};",
+            rust_struct.item.path.parts().join("::")
+        )?;
+        writeln!(self, "const CPP_SIZE: Option<usize> = {};", size)?;
+        writeln!(
+            self,
+            "const IS_POLYMORPHIC: bool = {};",
+            self.polymorphic_classes.contains(&cpp_path)
+        )?;
+        writeln!(self, "}}")?;
+        writeln!(self)?;
+        Ok(())
+    }
+
+    /// Generates static assertions verifying that the size and alignment
+    /// of the `#[repr(C)]` struct or union match the C++ type.
+    fn generate_layout_assertions(&mut self, rust_struct: DbItem<&RustStruct>) -> Result<()> {
+        let cpp_path = match self.current_database.source_cpp_item(&rust_struct.id)? {
+            Some(item) => match item.item.path() {
+                Some(path) => path.clone(),
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+        let properties = self
+            .current_database
+            .all_cpp_type_properties()
+            .iter()
+            .filter(|properties| properties.path == cpp_path)
+            .collect_vec();
+        let condition = self.condition(&rust_struct.id)?;
+        let code = layout_assertions_code(rust_struct.item.path.last(), &properties, &condition);
+        write!(self, "{}", code)?;
+        Ok(())
+    }
+
+    /// Generates `cpp_classes()` function in the crate root that returns metadata
+    /// of all classes of the crate.
+    fn generate_class_registry(&mut self) -> Result<()> {
+        let crate_name = self.current_database.crate_name().to_string();
+        let mut lines = Vec::new();
+        for item in self.current_database.rust_items() {
+            let rust_struct = match item.filter_map(|i| i.as_struct_ref()) {
+                Some(rust_struct) => rust_struct,
+                None => continue,
+            };
+            match &rust_struct.item.kind {
+                RustStructKind::WrapperType(RustWrapperTypeKind::EnumWrapper { .. }) => continue,
+                RustStructKind::WrapperType(_) => {}
+                _ => continue,
+            }
+            if self
+                .current_database
+                .source_cpp_item(&rust_struct.id)?
+                .map_or(true, |item| item.item.path().is_none())
+            {
+                continue;
+            }
+            let condition_texts = self.condition_texts(&rust_struct.id)?;
+            lines.push(format!(
+                "{}classes.push(<{} as ::cpp_core::CppClass>::metadata());",
+                condition_texts.attribute,
+                rust_struct.item.path.full_name(Some(&crate_name))
+            ));
+        }
+        writeln!(
+            self,
+            "/// Returns metadata of all C++ classes wrapped by this crate."
+        )?;
+        writeln!(
+            self,
+            "pub fn cpp_classes() -> Vec<::cpp_core::CppClassMetadata> {{"
+        )?;
+        writeln!(self, "#[allow(unused_mut)]")?;
+        writeln!(self, "let mut classes = Vec::new();")?;
+        for line in lines {
+            writeln!(self, "{}", line)?;
+        }
+        writeln!(self, "classes")?;
+        writeln!(self, "}}")?;
+        Ok(())
+    }
+
+    /// Returns the FFI function generated for the C++ function at `cpp_path`
+    /// and the source FFI item.
+    fn find_ffi_function(
+        &self,
+        cpp_path: &CppPath,
+    ) -> Result<(DbItem<&RustFunction>, &CppFfiFunction)> {
+        for item in self.current_database.rust_items() {
+            let function = match item.filter_map(|i| i.as_function_ref()) {
+                Some(function) => function,
+                None => continue,
+            };
+            if function.item.kind != RustFunctionKind::FfiFunction {
+                continue;
+            }
+            let is_match = self
+                .current_database
+                .source_cpp_item(&function.id)?
+                .map_or(false, |item| item.item.path() == Some(cpp_path));
+            if !is_match {
+                continue;
+            }
+            let ffi_function = self
+                .current_database
+                .source_ffi_item(&function.id)?
+                .and_then(|item| item.item.as_function_ref())
+                .ok_or_else(|| err_msg("source ffi function not found"))?;
+            if let CppFfiFunctionKind::Function = ffi_function.kind {
+                return Ok((function, ffi_function));
+            }
+        }
+        bail!(
+            "FFI function not found for {}",
+            cpp_path.to_cpp_pseudo_code()
+        );
+    }
+
+    /// Generates the `event_loop` module implementing `cpp_core::EventLoop`
+    /// through the configured C++ functions.
+    fn generate_event_loop_module(&mut self, functions: &EventLoopFunctions) -> Result<()> {
+        let crate_name = self.current_database.crate_name().to_string();
+        let mut paths = Vec::new();
+        // the module is only available if all functions are available
+        let mut conditions = Vec::new();
+        for cpp_path in &[&functions.run, &functions.quit] {
+            let (function, ffi_function) = self.find_ffi_function(cpp_path)?;
+            conditions.push(self.condition(&function.id)?);
+            if !ffi_function.arguments.is_empty() {
+                bail!(
+                    "event loop function must not have arguments: {}",
+                    cpp_path.to_cpp_pseudo_code()
+                );
+            }
+            paths.push(function.item.path.full_name(Some(&crate_name)));
+        }
+
+        let (post_function, post_ffi_function) = self.find_ffi_function(&functions.post)?;
+        let void_ptr = CppType::new_pointer(false, CppType::Void);
+        let is_callback = |type1: &CppType| {
+            type1.as_function_pointer().map_or(false, |t| {
+                *t.return_type == CppType::Void
+                    && t.arguments == vec![void_ptr.clone()]
+                    && !t.allows_variadic_arguments
+            })
+        };
+        let is_valid_post = match post_ffi_function.arguments.as_slice() {
+            [callback, data] => {
+                is_callback(callback.argument_type.original_type())
+                    && data.argument_type.original_type() == &void_ptr
+            }
+            _ => false,
+        };
+        if !is_valid_post {
+            bail!(
+                "event loop post function must accept void (*)(void*) and void* arguments: {}",
+                functions.post.to_cpp_pseudo_code()
+            );
+        }
+        let post_path = post_function.item.path.full_name(Some(&crate_name));
+        conditions.push(self.condition(&post_function.id)?);
+        let condition = Condition::all(conditions);
+
+        writeln!(
+            self,
+            "/// Integration with the event loop of the C++ library.
+            {condition}pub mod event_loop {{
+                /// Event loop of the C++ library.
+                ///
+                /// Calls `{run}`, `{quit}` and `{post}` C++ functions.
+                #[derive(Debug, Clone, Copy, Default)]
+                pub struct EventLoop;
+
+                impl ::cpp_core::EventLoop for EventLoop {{
+                    unsafe fn run(&self) {{
+                        let _ = {run_path}();
+                    }}
+
+                    unsafe fn quit(&self) {{
+                        {quit_path}();
+                    }}
+
+                    unsafe fn post(&self, callback: Box<dyn FnOnce() + Send + 'static>) {{
+                        extern \"C\" fn ffi_callback(data: *mut ::std::ffi::c_void) {{
+                            let callback = unsafe {{
+                                Box::from_raw(data as *mut Box<dyn FnOnce() + Send + 'static>)
+                            }};
+                            let result = ::std::panic::catch_unwind(
+                                ::std::panic::AssertUnwindSafe(move || callback()),
+                            );
+                            if result.is_err() {{
+                                {on_panic}
+                            }}
+                        }}
+                        let data = Box::into_raw(Box::new(callback)) as *mut ::std::ffi::c_void;
+                        {post_path}(Some(ffi_callback), data);
+                    }}
+                }}
+            }}",
+            run = functions.run.to_cpp_pseudo_code(),
+            quit = functions.quit.to_cpp_pseudo_code(),
+            post = functions.post.to_cpp_pseudo_code(),
+            run_path = paths[0],
+            quit_path = paths[1],
+            post_path = post_path,
+            on_panic = self.on_panic_code(),
+            condition = condition_texts(&condition).attribute,
+        )?;
+        Ok(())
+    }
+
+    /// Generates the module registering plugin factories through the configured
+    /// C++ functions (see `Config::add_plugin_registry`).
+    fn generate_plugin_registry(&mut self, registry: &PluginRegistry) -> Result<()> {
+        let crate_name = self.current_database.crate_name().to_string();
+        let (register_function, register_ffi_function) =
+            self.find_ffi_function(&registry.register)?;
+        let register_path = register_function.item.path.full_name(Some(&crate_name));
+        let create = match &registry.create {
+            Some(create) => {
+                let (create_function, create_ffi_function) = self.find_ffi_function(create)?;
+                let create_path = create_function.item.path.full_name(Some(&crate_name));
+                Some((create_ffi_function, create_path))
+            }
+            None => None,
+        };
+        let code = plugin_registry_code(
+            registry,
+            (register_ffi_function, &register_path),
+            create
+                .as_ref()
+                .map(|(function, path)| (*function, path.as_str())),
+            self.on_panic_code(),
+        )?;
+        writeln!(self, "{}", code)?;
+        Ok(())
+    }
+
+    /// Generates the `LibraryHandle` guard type calling the configured
+    /// initialization and shutdown functions.
+    fn generate_library_handle(&mut self, functions: &LifecycleFunctions) -> Result<()> {
+        let crate_name = self.current_database.crate_name().to_string();
+        let mut paths = Vec::new();
+        for cpp_path in &[&functions.init, &functions.shutdown] {
+            let (function, ffi_function) = self.find_ffi_function(cpp_path)?;
+            if !ffi_function.arguments.is_empty() {
+                bail!(
+                    "library lifecycle function must not have arguments: {}",
+                    cpp_path.to_cpp_pseudo_code()
+                );
+            }
+            paths.push(function.item.path.full_name(Some(&crate_name)));
+        }
+        writeln!(
+            self,
+            "static LIBRARY_INITIALIZED: ::std::sync::atomic::AtomicBool =
+                ::std::sync::atomic::AtomicBool::new(false);
+
+            /// Guard that keeps the C++ library initialized.
+            ///
+            /// Creating the handle calls `{init}`, and dropping it calls `{shutdown}`.
+            /// Constructors of C++ classes require a reference to the handle,
+            /// so objects can't be created before the library is initialized.
+            #[derive(Debug)]
+            pub struct LibraryHandle {{
+                _private: (),
+            }}
+
+            impl LibraryHandle {{
+                /// Initializes the library. Returns `None` if the library
+                /// is already initialized by another handle.
+                ///
+                /// # Safety
+                ///
+                /// Objects created using the handle must not be used
+                /// after the handle is dropped.
+                pub unsafe fn init() -> Option<LibraryHandle> {{
+                    if LIBRARY_INITIALIZED.swap(true, ::std::sync::atomic::Ordering::SeqCst) {{
+                        return None;
+                    }}
+                    let _ = {init_path}();
+                    Some(LibraryHandle {{ _private: () }})
+                }}
+            }}
+
+            impl Drop for LibraryHandle {{
+                fn drop(&mut self) {{
+                    unsafe {{
+                        {shutdown_path}();
+                    }}
+                    LIBRARY_INITIALIZED.store(false, ::std::sync::atomic::Ordering::SeqCst);
+                }}
+            }}",
+            init = functions.init.to_cpp_pseudo_code(),
+            shutdown = functions.shutdown.to_cpp_pseudo_code(),
+            init_path = paths[0],
+            shutdown_path = paths[1],
+        )?;
+        Ok(())
+    }
+
+    /// Generates conversions between the wrapper type and the type
+    /// of the optional dependency.
+    fn generate_optional_type_conversion(
+        &mut self,
+        conversion: &OptionalTypeConversion,
+    ) -> Result<()> {
+        let crate_name = self.current_database.crate_name().to_string();
+        let wrapper_path = self
+            .current_database
+            .find_rust_items_for_cpp_path(&conversion.cpp_type, false)?
+            .find(|item| item.item.is_wrapper_type())
+            .and_then(|item| item.item.path().cloned())
+            .ok_or_else(|| {
+                format_err!(
+                    "wrapper type not found for optional conversion: {}",
+                    conversion.cpp_type.to_cpp_pseudo_code()
+                )
+            })?;
+        let code = optional_type_conversion_code(conversion, &wrapper_path, &crate_name)?;
+        write!(self, "{}", code)?;
+        Ok(())
+    }
+
+    /// Generates the newtype of a handle type (see `Config::add_handle_type`).
+    fn generate_handle_type(&mut self, name: &str, handle: &RustHandleType) -> Result<()> {
+        writeln!(
+            self,
+            "#[repr(transparent)]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+            pub struct {name}({raw_type});
+
+            impl {name} {{
+                /// Creates a handle from its integer value.
+                pub fn from_raw(value: {raw_type}) -> Self {{
+                    {name}(value)
+                }}
+
+                /// Returns integer value of the handle.
+                pub fn to_raw(self) -> {raw_type} {{
+                    self.0
+                }}
+            }}",
+            name = name,
+            raw_type = handle.raw_type,
+        )?;
+        Ok(())
+    }
+
+    /// Generates the guard that releases a handle when dropped
+    /// (see `Config::add_handle_type`).
+    fn generate_owned_handle_type(
+        &mut self,
+        name: &str,
+        owned: &RustOwnedHandleType,
+    ) -> Result<()> {
+        let crate_name = self.current_database.crate_name().to_string();
+        let handle_type = self
+            .current_database
+            .find_rust_item(&owned.handle_path)
+            .and_then(|item| item.item.as_struct_ref())
+            .and_then(|item| match &item.kind {
+                RustStructKind::HandleType(handle) => Some(handle.cpp_path.clone()),
+                _ => None,
+            })
+            .ok_or_else(|| format_err!("handle type not found: {:?}", owned.handle_path))?;
+        let (function, ffi_function) = self.find_ffi_function(&owned.release)?;
+        let is_valid = ffi_function.arguments.len() == 1
+            && match ffi_function.arguments[0].argument_type.ffi_type() {
+                CppType::SpecificNumeric(numeric_type) => numeric_type.path == handle_type,
+                _ => false,
+            };
+        if !is_valid {
+            bail!(
+                "release function must accept a single argument of the handle type: {}",
+                owned.release.to_cpp_pseudo_code()
+            );
+        }
+        writeln!(
+            self,
+            "#[derive(Debug)]
+            pub struct {name}({handle});
+
+            impl {name} {{
+                /// Takes ownership of `handle`.
+                ///
+                /// # Safety
+                ///
+                /// `handle` must be valid and must not be released by other means.
+                pub unsafe fn new(handle: {handle}) -> Self {{
+                    {name}(handle)
+                }}
+
+                /// Returns the handle without releasing ownership.
+                pub fn handle(&self) -> {handle} {{
+                    self.0
+                }}
+
+                /// Returns the handle and releases ownership, so that the resource
+                /// is not released when `self` is dropped.
+                pub fn into_handle(self) -> {handle} {{
+                    let handle = self.0;
+                    ::std::mem::forget(self);
+                    handle
+                }}
+            }}
+
+            impl Drop for {name} {{
+                fn drop(&mut self) {{
+                    unsafe {{
+                        {release_path}(self.0);
+                    }}
+                }}
+            }}",
+            name = name,
+            handle = self.rust_path_to_string(&owned.handle_path),
+            release_path = function.item.path.full_name(Some(&crate_name)),
+        )?;
+        Ok(())
+    }
+
     fn generate_struct(
         &mut self,
         rust_struct: DbItem<&RustStruct>,
         condition_texts: &ConditionTexts,
     ) -> Result<()> {
-        let doc = doc_formatter::struct_doc(rust_struct.clone(), self.current_database)?
-            + &condition_texts.doc_text;
+        let doc = doc_formatter::struct_doc(
+            rust_struct.clone(),
+            self.current_database,
+            self.cpp_doc_url_template,
+        )? + &condition_texts.doc_text;
         write!(self, "{}", format_doc(&doc))?;
 
         let visibility = if rust_struct.item.is_public {
@@ -467,12 +1033,49 @@ impl Generator<'_> {
         };
         match &rust_struct.item.kind {
             RustStructKind::WrapperType(kind) => match kind {
-                RustWrapperTypeKind::EnumWrapper => {
+                RustWrapperTypeKind::EnumWrapper {
+                    representation,
+                    underlying_type,
+                    ..
+                } => {
+                    let int_type = underlying_type
+                        .as_ref()
+                        .map_or("::std::os::raw::c_int", String::as_str);
+                    match representation {
+                        EnumRepresentation::Newtype | EnumRepresentation::Bitflags => {
+                            writeln!(
+                                self,
+                                include_str!("../templates/crate/enum_wrapper.rs.in"),
+                                vis = visibility,
+                                name = rust_struct.item.path.last(),
+                                int_type = int_type
+                            )?;
+                            if *representation == EnumRepresentation::Bitflags {
+                                writeln!(
+                                    self,
+                                    include_str!("../templates/crate/enum_bitflags.rs.in"),
+                                    name = rust_struct.item.path.last()
+                                )?;
+                            }
+                        }
+                        EnumRepresentation::RustEnum => {
+                            self.generate_rust_enum(
+                                &rust_struct.item.path,
+                                visibility,
+                                underlying_type.as_ref().map(String::as_str),
+                            )?;
+                        }
+                    }
+                    // Only values of unscoped enums are implicitly converted to `int` in C++,
+                    // but the conversion is also kept for scoped enums to avoid breaking
+                    // existing code.
                     writeln!(
                         self,
-                        include_str!("../templates/crate/enum_wrapper.rs.in"),
-                        vis = visibility,
-                        name = rust_struct.item.path.last()
+                        "{}",
+                        enum_to_int_code(
+                            rust_struct.item.path.last(),
+                            underlying_type.as_ref().map(String::as_str)
+                        )
                     )?;
                 }
                 RustWrapperTypeKind::ImmovableClassWrapper => {
@@ -484,6 +1087,57 @@ impl Generator<'_> {
                         rust_struct.item.path.last()
                     )?;
                 }
+                RustWrapperTypeKind::PinnedClassWrapper => {
+                    writeln!(self, "#[repr(C)]")?;
+                    writeln!(
+                        self,
+                        "{}struct {} {{ _unused: u8, _pinned: ::std::marker::PhantomPinned, }}",
+                        visibility,
+                        rust_struct.item.path.last()
+                    )?;
+                }
+                RustWrapperTypeKind::UnionWrapper { fields } => {
+                    writeln!(self, "#[repr(C)]")?;
+                    writeln!(self, "#[derive(Clone, Copy)]")?;
+                    writeln!(
+                        self,
+                        "{}union {} {{",
+                        visibility,
+                        rust_struct.item.path.last()
+                    )?;
+                    for field in fields {
+                        writeln!(
+                            self,
+                            "pub {}: {},",
+                            field.name,
+                            self.rust_type_to_code(&field.rust_type)
+                        )?;
+                    }
+                    writeln!(self, "}}")?;
+                    writeln!(self)?;
+                    self.generate_layout_assertions(rust_struct.clone())?;
+                }
+                RustWrapperTypeKind::PlainStructWrapper { fields } => {
+                    writeln!(self, "#[repr(C)]")?;
+                    writeln!(self, "#[derive(Clone, Copy)]")?;
+                    writeln!(
+                        self,
+                        "{}struct {} {{",
+                        visibility,
+                        rust_struct.item.path.last()
+                    )?;
+                    for field in fields {
+                        writeln!(
+                            self,
+                            "pub {}: {},",
+                            field.name,
+                            self.rust_type_to_code(&field.rust_type)
+                        )?;
+                    }
+                    writeln!(self, "}}")?;
+                    writeln!(self)?;
+                    self.generate_layout_assertions(rust_struct.clone())?;
+                }
                 RustWrapperTypeKind::MovableClassWrapper { sized_type_path } => {
                     writeln!(self, "#[repr(transparent)]")?;
                     writeln!(
@@ -502,6 +1156,16 @@ impl Generator<'_> {
             RustStructKind::SizedType(_) => {
                 bail!("sized struct can't be generated with rust code generator")
             }
+            RustStructKind::HandleType(handle) => {
+                self.generate_handle_type(rust_struct.item.path.last(), handle)?;
+            }
+            RustStructKind::OwnedHandleType(owned) => {
+                self.generate_owned_handle_type(rust_struct.item.path.last(), owned)?;
+            }
+        }
+
+        if let RustStructKind::WrapperType(kind) = &rust_struct.item.kind {
+            self.generate_cpp_class_impl(rust_struct.clone(), kind, condition_texts)?;
         }
 
         if self
@@ -519,12 +1183,267 @@ impl Generator<'_> {
             self.generate_children(&rust_struct.item.path, Some(&struct_type))?;
             writeln!(self, "}}")?;
             writeln!(self)?;
+
+            self.generate_into_arg_impls(&rust_struct.item.path)?;
         }
 
         Ok(())
     }
 
+    /// Generates `cpp_core::IntoArg` implementations for implicit conversions
+    /// performed by converting constructors of the struct.
+    fn generate_into_arg_impls(&mut self, struct_path: &RustPath) -> Result<()> {
+        let struct_name = self.rust_path_to_string(struct_path);
+        let mut impls = Vec::new();
+        for item in self.current_database.rust_children(struct_path) {
+            let function = if let Some(function) = item.item.as_function_ref() {
+                function
+            } else {
+                continue;
+            };
+            if function.arguments.len() != 1
+                || function.return_type.conversion() != &RustToFfiTypeConversion::CppBoxToPtr
+            {
+                continue;
+            }
+            let is_converting_constructor = self
+                .current_database
+                .source_cpp_item(&item.id)?
+                .and_then(|cpp_item| cpp_item.item.as_function_ref())
+                .map_or(false, |f| f.is_converting_constructor());
+            if !is_converting_constructor {
+                continue;
+            }
+            let argument_type = &function.arguments[0].argument_type;
+            let function_path = self.rust_path_to_string(&function.path);
+            let call = format!("{}(self)", function_path);
+            let mut sources = Vec::new();
+            match argument_type.conversion() {
+                RustToFfiTypeConversion::None => {
+                    let api_type = argument_type.api_type();
+                    let c_char = RustType::new_pointer(
+                        true,
+                        RustType::Common(RustCommonType {
+                            path: RustPath::from_good_str("std::os::raw::c_char"),
+                            generic_arguments: None,
+                        }),
+                    );
+                    if api_type == &c_char {
+                        // conversions can't fail, so null bytes are removed and
+                        // unrepresentable characters are replaced
+                        let encode = if self.string_encoding.is_some() {
+                            "crate::string_encoding::encode_lossy(self)"
+                        } else {
+                            "::std::ffi::CString::new(self.replace('\\0', \"\")).unwrap_or_default()"
+                        };
+                        let body = format!(
+                            "let string = {};\n{}(string.as_ptr())",
+                            encode, function_path
+                        );
+                        sources.push(("&'a str".to_string(), body));
+                    } else if api_type == &wide_char_pointer("cpp_core::wchar_t") {
+                        for (source, function) in &[
+                            ("&'a str", "to_wchar_nul"),
+                            ("&'a ::std::ffi::OsStr", "os_str_to_wchar_nul"),
+                        ] {
+                            let body = format!(
+                                "let string = ::cpp_core::wide_string::{}(self);\n\
+                                 {}(string.as_ptr())",
+                                function, function_path
+                            );
+                            sources.push((source.to_string(), body));
+                        }
+                    } else if api_type == &wide_char_pointer("cpp_core::char16_t") {
+                        let body = format!(
+                            "let string = ::cpp_core::wide_string::to_char16_nul(self);\n\
+                             {}(string.as_ptr())",
+                            function_path
+                        );
+                        sources.push(("&'a str".to_string(), body));
+                    } else if let RustType::PointerLike { .. } = api_type {
+                        continue;
+                    } else {
+                        sources.push((self.rust_type_to_code(api_type), call));
+                    }
+                }
+                RustToFfiTypeConversion::ImplIntoArg | RustToFfiTypeConversion::ImplCastInto(_) => {
+                    let ffi_type = argument_type.ffi_type();
+                    let is_ref = argument_type.conversion()
+                        == &RustToFfiTypeConversion::ImplIntoArg
+                        || argument_type.conversion()
+                            == &RustToFfiTypeConversion::ImplCastInto(Box::new(
+                                RustToFfiTypeConversion::UtilsRefToPtr {},
+                            ));
+                    if !is_ref || !ffi_type.is_const_pointer_like()? {
+                        continue;
+                    }
+                    let target = ffi_type.pointer_like_to_target()?;
+                    let deletable_trait = RustPath::from_good_str("cpp_core::CppDeletable");
+                    let is_deletable = self.current_database.rust_items().any(|item| {
+                        item.item.as_trait_impl_ref().map_or(false, |item| {
+                            item.target_type == target && item.trait_type.path == deletable_trait
+                        })
+                    });
+                    let target = self.rust_type_to_code(&target);
+                    sources.push((format!("::cpp_core::Ref<{}>", target), call.clone()));
+                    if is_deletable {
+                        sources.push((format!("&'a ::cpp_core::CppBox<{}>", target), call));
+                    }
+                }
+                _ => continue,
+            }
+            let condition_texts = self.condition_texts(&item.id)?;
+            for (source, body) in sources {
+                if impls.iter().any(|(s, _, _)| s == &source) {
+                    continue;
+                }
+                impls.push((source, body, condition_texts.attribute.clone()));
+            }
+        }
+
+        for (source, body, condition) in impls {
+            let generic_args = if source.contains("'a") { "<'a>" } else { "" };
+            writeln!(
+                self,
+                "{condition}impl{generic_args} ::cpp_core::IntoArg<{target}> for {source} {{
+                    unsafe fn into_arg(self) -> ::cpp_core::ArgRef<{target}> {{
+                        ::cpp_core::ArgRef::Temporary({{ {body} }})
+                    }}
+                }}\n",
+                condition = condition,
+                generic_args = generic_args,
+                target = struct_name,
+                source = source,
+                body = body
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Returns representation and underlying type of the enum wrapper
+    /// with the specified path.
+    fn enum_wrapper_info(&self, path: &RustPath) -> (EnumRepresentation, Option<&str>) {
+        match self.current_database.find_rust_item(path) {
+            Some(DbItem {
+                item:
+                    RustItem::Struct(RustStruct {
+                        kind:
+                            RustStructKind::WrapperType(RustWrapperTypeKind::EnumWrapper {
+                                representation,
+                                underlying_type,
+                                ..
+                            }),
+                        ..
+                    }),
+                ..
+            }) => (
+                *representation,
+                underlying_type.as_ref().map(String::as_str),
+            ),
+            _ => (EnumRepresentation::default(), None),
+        }
+    }
+
+    /// Returns representation of the enum wrapper with the specified path.
+    fn enum_representation(&self, path: &RustPath) -> EnumRepresentation {
+        self.enum_wrapper_info(path).0
+    }
+
+    /// Returns variants of a Rust enum wrapper. If multiple values have the same
+    /// integer value, only the first of them is a variant.
+    fn rust_enum_variants(&self, enum_path: &RustPath) -> Vec<DbItem<&RustEnumValue>> {
+        let values = self
+            .current_database
+            .rust_children(enum_path)
+            .filter_map(|item| item.filter_map(|i| i.as_enum_value_ref()))
+            .collect_vec();
+        let mut variants: Vec<DbItem<&RustEnumValue>> = Vec::new();
+        // aliases only become variants if the original variant is not available
+        for value in values
+            .iter()
+            .filter(|v| !v.item.is_alias)
+            .chain(values.iter().filter(|v| v.item.is_alias))
+        {
+            if variants.iter().all(|v| v.item.value != value.item.value) {
+                variants.push(value.clone());
+            }
+        }
+        variants
+    }
+
+    fn generate_rust_enum(
+        &mut self,
+        path: &RustPath,
+        visibility: &str,
+        underlying_type: Option<&str>,
+    ) -> Result<()> {
+        let name = path.last();
+        let variants = self.rust_enum_variants(path);
+        let declarations = variants
+            .iter()
+            .map_if_ok(|v| -> Result<_> {
+                Ok(format!(
+                    "{}{} = {},\n",
+                    format_doc(&doc_formatter::enum_value_doc(
+                        v.clone(),
+                        self.current_database
+                    )?),
+                    v.item.path.last(),
+                    v.item.value
+                ))
+            })?
+            .join("");
+        let to_int_arms = variants
+            .iter()
+            .map(|v| format!("{}::{} => {},\n", name, v.item.path.last(), v.item.value))
+            .join("");
+        let try_from_arms = variants
+            .iter()
+            .map(|v| {
+                format!(
+                    "{} => Ok({}::{}),\n",
+                    v.item.value,
+                    name,
+                    v.item.path.last()
+                )
+            })
+            .join("");
+        writeln!(
+            self,
+            include_str!("../templates/crate/rust_enum.rs.in"),
+            vis = visibility,
+            name = name,
+            repr = underlying_type.map_or(String::new(), |t| format!("#[repr({})]\n", t)),
+            int_type = underlying_type.unwrap_or("::std::os::raw::c_int"),
+            declarations = declarations,
+            to_int_arms = to_int_arms,
+            try_from_arms = try_from_arms,
+        )?;
+        Ok(())
+    }
+
     fn generate_enum_value(&mut self, value: DbItem<&RustEnumValue>) -> Result<()> {
+        let enum_path = value
+            .item
+            .path
+            .parent()
+            .expect("enum value must have parent");
+        let struct_path = self.rust_path_to_string(&enum_path);
+        let initializer = if self.enum_representation(&enum_path) == EnumRepresentation::RustEnum {
+            let variant = self
+                .rust_enum_variants(&enum_path)
+                .into_iter()
+                .find(|v| v.item.value == value.item.value)
+                .ok_or_else(|| err_msg("enum variant not found"))?;
+            if variant.id == value.id {
+                // declared as a variant of the enum
+                return Ok(());
+            }
+            // duplicate values are declared as aliases of the first variant
+            format!("{}::{}", struct_path, variant.item.path.last())
+        } else {
+            format!("{}({})", struct_path, value.item.value)
+        };
         write!(
             self,
             "{}",
@@ -533,20 +1452,13 @@ impl Generator<'_> {
                 self.current_database
             )?)
         )?;
-        let struct_path = self.rust_path_to_string(
-            &value
-                .item
-                .path
-                .parent()
-                .expect("enum value must have parent"),
-        );
         writeln!(self, "#[allow(non_upper_case_globals)]")?;
         writeln!(
             self,
-            "pub const {value_name}: {struct_path} = {struct_path}({value});",
+            "pub const {value_name}: {struct_path} = {initializer};",
             value_name = value.item.path.last(),
             struct_path = struct_path,
-            value = value.item.value
+            initializer = initializer
         )?;
         Ok(())
     }
@@ -608,6 +1520,14 @@ impl Generator<'_> {
                 );
                 wrap_unsafe(in_unsafe_context, &code)
             }
+            RustToFfiTypeConversion::PinnedCppBoxToPtr => {
+                let code = format!(
+                    "::cpp_core::CppBox::from_raw({}).expect(\"attempted to \
+                     construct a null CppBox\")",
+                    source_expr
+                );
+                format!("{}.into_pin()", wrap_unsafe(in_unsafe_context, &code))
+            }
             RustToFfiTypeConversion::QBoxToPtr => {
                 let code = format!("{}::QBox::from_raw({})", self.qt_core_prefix(), source_expr);
                 wrap_unsafe(in_unsafe_context, &code)
@@ -656,6 +1576,22 @@ impl Generator<'_> {
                 );
                 wrap_unsafe(in_unsafe_context, &code)
             }
+            RustToFfiTypeConversion::RustEnumToInt { api_type } => {
+                // the API type is `Result<T, int>` for values received from C++
+                let enum_type = match api_type {
+                    RustType::Common(RustCommonType {
+                        generic_arguments: Some(args),
+                        ..
+                    }) if !args.is_empty() => &args[0],
+                    _ => bail!("unexpected API type for RustEnumToInt: {:?}", api_type),
+                };
+                format!(
+                    "<{t} as ::std::convert::TryFrom<{i}>>::try_from({e})",
+                    t = self.rust_type_to_code(enum_type),
+                    i = self.rust_type_to_code(type1.ffi_type()),
+                    e = source_expr
+                )
+            }
             RustToFfiTypeConversion::QFlagsToUInt { .. } => {
                 let mut qflags_type = type1.api_type().clone();
                 if let RustType::Common(RustCommonType {
@@ -690,9 +1626,29 @@ impl Generator<'_> {
             RustToFfiTypeConversion::ImplCastInto(_) => {
                 bail!("ImplCastInto is not convertable from FFI type");
             }
+            RustToFfiTypeConversion::ImplIntoArg => {
+                bail!("ImplIntoArg is not convertable from FFI type");
+            }
             RustToFfiTypeConversion::ClosureToCallback { .. } => {
                 bail!("ClosureToCallback is not convertable from FFI type");
             }
+            RustToFfiTypeConversion::AsyncCompletion { .. } => {
+                bail!("AsyncCompletion is not convertable from FFI type");
+            }
+            RustToFfiTypeConversion::LibraryHandle { .. } => {
+                bail!("LibraryHandle is not convertable from FFI type");
+            }
+            RustToFfiTypeConversion::SliceToPtr { .. } => {
+                bail!("SliceToPtr is not convertable from FFI type");
+            }
+            RustToFfiTypeConversion::DecodedString => {
+                let code = format!(
+                    "{{ let ptr = {}; if ptr.is_null() {{ None }} else {{ \
+                     Some(crate::string_encoding::decode_lossy(ptr)) }} }}",
+                    source_expr
+                );
+                wrap_unsafe(in_unsafe_context, &code)
+            }
         };
         Ok(code1 + &code2)
     }
@@ -728,6 +1684,13 @@ impl Generator<'_> {
             RustToFfiTypeConversion::CppBoxToPtr | RustToFfiTypeConversion::QBoxToPtr => {
                 format!("{}.into_raw_ptr()", expr)
             }
+            RustToFfiTypeConversion::PinnedCppBoxToPtr => {
+                // the object is not moved, only its ownership is transferred
+                format!(
+                    "::std::pin::Pin::into_inner_unchecked({}).into_raw_ptr()",
+                    expr
+                )
+            }
             RustToFfiTypeConversion::UtilsPtrToPtr { .. }
             | RustToFfiTypeConversion::UtilsRefToPtr { .. }
             | RustToFfiTypeConversion::QPtrToPtr { .. } => {
@@ -752,7 +1715,8 @@ impl Generator<'_> {
             RustToFfiTypeConversion::OptionUtilsRefToPtr { .. } => {
                 bail!("OptionUtilsRefToPtr is not supported in argument position");
             }
-            RustToFfiTypeConversion::QFlagsToUInt { .. } => format!("{}.to_int()", expr),
+            RustToFfiTypeConversion::QFlagsToUInt { .. }
+            | RustToFfiTypeConversion::RustEnumToInt { .. } => format!("{}.to_int()", expr),
             RustToFfiTypeConversion::UnitToAnything => {
                 bail!("UnitToAnything is not possible to use in argument position");
             }
@@ -780,13 +1744,49 @@ impl Generator<'_> {
                 );
                 self.convert_type_to_ffi(&intermediate_expr, &intermediate)?
             }
+            RustToFfiTypeConversion::ImplIntoArg => {
+                // The temporary `ArgRef` lives until the end of the FFI call statement.
+                format!(
+                    "::cpp_core::IntoArg::<{}>::into_arg({}).as_raw_ptr()",
+                    self.rust_type_to_code(&type1.ffi_type().pointer_like_to_target()?),
+                    expr
+                )
+            }
             RustToFfiTypeConversion::ClosureToCallback { .. } => {
                 "Some(ffi_callback::<T>), Some(deleter::<T>), data".to_string()
             }
+            RustToFfiTypeConversion::AsyncCompletion { .. } => {
+                bail!("AsyncCompletion is not possible to use in argument position");
+            }
+            RustToFfiTypeConversion::LibraryHandle { .. } => {
+                bail!("LibraryHandle is not passed to FFI");
+            }
+            RustToFfiTypeConversion::DecodedString => {
+                bail!("DecodedString is not possible to use in argument position");
+            }
+            RustToFfiTypeConversion::SliceToPtr { .. } => {
+                if type1.ffi_type().is_const_pointer_like()? {
+                    format!("{}.as_ptr()", expr)
+                } else {
+                    format!("{}.as_mut_ptr()", expr)
+                }
+            }
         };
         Ok(code)
     }
 
+    /// Returns the code handling a panic caught in a function called from C++.
+    /// Unwinding into C++ code is undefined behavior, so panics must be caught
+    /// at the FFI boundary (see `Config::set_callback_panic_behavior`).
+    fn on_panic_code(&self) -> &'static str {
+        match self.callback_panic_behavior {
+            CallbackPanicBehavior::Abort => "::std::process::abort();",
+            CallbackPanicBehavior::Log => {
+                "eprintln!(\"panic in a callback was caught at the FFI boundary\");"
+            }
+        }
+    }
+
     fn callback_bound_code(&self, conversion: &RustClosureToCallbackConversion) -> String {
         let return_type_text = if conversion.closure_return_type.api_type().is_unit() {
             String::new()
@@ -837,10 +1837,17 @@ impl Generator<'_> {
             })?
             .join(", ");
 
-        code.push_str(&wrap_unsafe(
-            false,
-            &format!("(*(data as *mut T))({})", func_args),
-        ));
+        writeln!(
+            code,
+            "let result = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {{
+                {}
+            }}));
+            if result.is_err() {{
+                {}
+            }}",
+            wrap_unsafe(false, &format!("(*(data as *mut T))({})", func_args)),
+            self.on_panic_code()
+        )?;
 
         writeln!(code, "}}")?;
         writeln!(
@@ -851,6 +1858,130 @@ impl Generator<'_> {
         Ok(code)
     }
 
+    /// Generates the completion callback of an asynchronous operation and
+    /// the `completion` future it resolves. The callback takes the completion
+    /// sender from the user data and frees it, so it must be called at most once.
+    fn async_completion_glue_code(
+        &self,
+        conversion: &RustAsyncCompletionConversion,
+    ) -> Result<String> {
+        let mut code = String::new();
+        let value_type = self.rust_type_to_code(&conversion.value_type());
+        let mut callback_args = conversion
+            .completion_arguments
+            .iter()
+            .enumerate()
+            .map(|(i, arg)| format!("arg{}: {}", i, self.rust_type_to_code(arg.ffi_type())))
+            .collect_vec();
+        if conversion.callback_data_index > callback_args.len() {
+            bail!("invalid callback data index");
+        }
+        callback_args.insert(
+            conversion.callback_data_index,
+            "data: *mut ::std::ffi::c_void".to_string(),
+        );
+        writeln!(
+            code,
+            "extern \"C\" fn completion_callback({}) {{",
+            callback_args.join(", ")
+        )?;
+
+        let values = conversion
+            .completion_arguments
+            .iter()
+            .enumerate()
+            .map_if_ok(|(num, t)| {
+                self.convert_type_from_ffi(t, format!("arg{}", num), true, false)
+            })?;
+        let value = if values.len() == 1 {
+            values[0].clone()
+        } else {
+            format!("({})", values.join(", "))
+        };
+
+        writeln!(
+            code,
+            "let result = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {{
+                let sender = {};
+                sender.send({});
+            }}));
+            if result.is_err() {{
+                {}
+            }}",
+            wrap_unsafe(
+                false,
+                &format!(
+                    "::cpp_core::CompletionSender::<{}>::from_callback_data(data)",
+                    value_type
+                )
+            ),
+            wrap_unsafe(false, &value),
+            self.on_panic_code()
+        )?;
+
+        writeln!(code, "}}")?;
+        writeln!(
+            code,
+            "let (sender, completion) = ::cpp_core::completion::<{}>();
+            let data = sender.into_callback_data();",
+            value_type
+        )?;
+
+        Ok(code)
+    }
+
+    /// Generates Rust code that reads or writes the field directly
+    /// instead of calling the FFI function.
+    /// `final_args` are the values of the FFI arguments.
+    fn inline_field_access(
+        &self,
+        access: &RustInlineFieldAccess,
+        arguments: &[RustFunctionArgument],
+        return_type: &RustFinalType,
+        final_args: &[String],
+    ) -> Result<String> {
+        let offset = access.offset;
+        let this_arg = final_args
+            .get(0)
+            .ok_or_else(|| err_msg("field accessor must have this argument"))?;
+        let field_pointer = |is_const: bool, pointer_type: &str| {
+            format!(
+                "({} as *{} u8).add({}) as {}",
+                this_arg,
+                if is_const { "const" } else { "mut" },
+                offset,
+                pointer_type
+            )
+        };
+        Ok(match access.accessor_type {
+            CppFieldAccessorType::CopyGetter => {
+                let field_type = self.rust_type_to_code(return_type.ffi_type());
+                format!(
+                    "*({})",
+                    field_pointer(true, &format!("*const {}", field_type))
+                )
+            }
+            CppFieldAccessorType::ConstRefGetter => {
+                field_pointer(true, &self.rust_type_to_code(return_type.ffi_type()))
+            }
+            CppFieldAccessorType::MutRefGetter => {
+                field_pointer(false, &self.rust_type_to_code(return_type.ffi_type()))
+            }
+            CppFieldAccessorType::Setter => {
+                let value_arg = arguments
+                    .iter()
+                    .find(|arg| arg.ffi_index == Some(1))
+                    .ok_or_else(|| err_msg("setter must have value argument"))?;
+                let field_type = self.rust_type_to_code(value_arg.argument_type.ffi_type());
+                format!(
+                    "*({}) = {}",
+                    field_pointer(false, &format!("*mut {}", field_type)),
+                    final_args[1]
+                )
+            }
+        })
+    }
+
     /// Generates Rust code for calling an FFI function from a wrapper function.
     /// If `in_unsafe_context` is `true`, the output code will be placed inside
     /// an `unsafe` block.
@@ -864,9 +1995,24 @@ impl Generator<'_> {
     ) -> Result<String> {
         let mut final_args = Vec::new();
         for arg in arguments {
+            let ffi_index = match arg.ffi_index {
+                Some(index) => index,
+                // e.g. `&LibraryHandle` only ensures that the library is initialized
+                None => continue,
+            };
             let code = self.convert_type_to_ffi(&arg.name, &arg.argument_type)?;
-            final_args.resize(arg.ffi_index + 1, None);
-            final_args[arg.ffi_index] = Some(code);
+            if final_args.len() <= ffi_index {
+                final_args.resize(ffi_index + 1, None);
+            }
+            final_args[ffi_index] = Some(code);
+            if let RustToFfiTypeConversion::SliceToPtr { len_ffi_index } =
+                arg.argument_type.conversion()
+            {
+                if final_args.len() <= *len_ffi_index {
+                    final_args.resize(len_ffi_index + 1, None);
+                }
+                final_args[*len_ffi_index] = Some(format!("{}.len()", arg.name));
+            }
         }
 
         let mut result = Vec::new();
@@ -879,6 +2025,17 @@ impl Generator<'_> {
             result.push(self.callback_glue_code(conversion)?);
         }
 
+        let async_completion = return_type.conversion().as_async_completion_ref();
+        if let Some(conversion) = async_completion {
+            result.push(self.async_completion_glue_code(conversion)?);
+            let len = conversion.callback_ffi_index.max(conversion.data_ffi_index) + 1;
+            if final_args.len() < len {
+                final_args.resize(len, None);
+            }
+            final_args[conversion.callback_ffi_index] = Some("Some(completion_callback)".into());
+            final_args[conversion.data_ffi_index] = Some("data".into());
+        }
+
         let mut maybe_result_var_name = None;
 
         let ffi_item = self
@@ -937,12 +2094,20 @@ impl Generator<'_> {
             .into_iter()
             .map_if_ok(|x| x.ok_or_else(|| err_msg("ffi argument is missing")))?;
 
+        let call = if let Some(access) = &wrapper_data.inline_field_access {
+            self.inline_field_access(access, arguments, return_type, &final_args)?
+        } else {
+            format!(
+                "{}({})",
+                self.rust_path_to_string(&wrapper_data.ffi_function_path),
+                final_args.join(", ")
+            )
+        };
         result.push(wrap_unsafe(
             in_unsafe_context,
             &format!(
-                "{}({}){maybe_semicolon}",
-                self.rust_path_to_string(&wrapper_data.ffi_function_path),
-                final_args.join(", "),
+                "{}{maybe_semicolon}",
+                call,
                 maybe_semicolon = if maybe_result_var_name.is_some() {
                     ";"
                 } else {
@@ -954,7 +2119,9 @@ impl Generator<'_> {
             result.push(format!("{}\n}}", name));
         }
         let code = result.join("");
-        if maybe_result_var_name.is_none() {
+        if async_completion.is_some() {
+            Ok(format!("{};\ncompletion", code))
+        } else if maybe_result_var_name.is_none() {
             self.convert_type_from_ffi(&return_type, code, in_unsafe_context, true)
         } else {
             Ok(code)
@@ -1100,9 +2267,85 @@ impl Generator<'_> {
             format!("<{}>", generic_args.join(", "))
         };
 
+        // FFI functions are declared inside the `extern "C"` block of the ffi module,
+        // so functions with other calling conventions need a separate block.
+        let calling_convention = match &func.item.kind {
+            RustFunctionKind::FfiFunction => self
+                .current_database
+                .source_ffi_item(&func.id)?
+                .and_then(|item| item.item.as_function_ref())
+                .map_or_else(FfiCallingConvention::default, |f| f.calling_convention),
+            _ => FfiCallingConvention::default(),
+        };
+        if calling_convention != FfiCallingConvention::C {
+            writeln!(
+                self,
+                "}}\n\nextern \"{}\" {{\n",
+                calling_convention.rust_abi()
+            )?;
+        }
+
         // TODO: move condition texts to doc parser
-        let doc = doc_formatter::function_doc(func.clone(), self.current_database)?
-            + &condition_texts.doc_text;
+        let doc = doc_formatter::function_doc(
+            func.clone(),
+            self.current_database,
+            self.cpp_doc_url_template,
+        )? + &condition_texts.doc_text;
+        let has_callback = func.item.arguments.iter().any(|arg| {
+            if let RustToFfiTypeConversion::ClosureToCallback(_) = arg.argument_type.conversion() {
+                true
+            } else {
+                false
+            }
+        });
+        let doc = if has_callback {
+            let behavior = match self.callback_panic_behavior {
+                CallbackPanicBehavior::Abort => "the process is aborted",
+                CallbackPanicBehavior::Log => {
+                    "the panic is caught, an error is printed to stderr, and \
+                     the callback returns normally"
+                }
+            };
+            format!(
+                "{}\n\nPanics in the callback are not propagated to C++ code: \
+                 if the callback panics, {}.",
+                doc, behavior
+            )
+        } else {
+            doc
+        };
+        let doc = if func
+            .item
+            .return_type
+            .conversion()
+            .as_async_completion_ref()
+            .is_some()
+        {
+            format!(
+                "{}\n\nStarts the operation and returns a future that resolves \
+                 when the C++ library calls the completion callback. \
+                 The future resolves to `Err(cpp_core::Canceled)` if the callback's \
+                 arguments can't be converted and never resolves if the callback \
+                 is not called. The C++ library must not call the callback more than once.",
+                doc
+            )
+        } else {
+            doc
+        };
+        let safety_reason = match &func.item.kind {
+            RustFunctionKind::FfiWrapper(data) if func.item.is_unsafe => {
+                data.safety_reason.as_ref()
+            }
+            _ => None,
+        };
+        let doc = if let Some(reason) = safety_reason {
+            format!(
+                "{}\n\n# Safety\n\nThis function is `unsafe` because {}.",
+                doc, reason
+            )
+        } else {
+            doc
+        };
         writeln!(
             self,
             "{doc}{maybe_inline}{condition}{maybe_pub}{maybe_unsafe} \
@@ -1125,6 +2368,9 @@ impl Generator<'_> {
             return_type = return_type_for_signature,
             maybe_body = maybe_body
         )?;
+        if calling_convention != FfiCallingConvention::C {
+            writeln!(self, "}}\n\nextern \"C\" {{\n")?;
+        }
         Ok(())
     }
 
@@ -1179,13 +2425,26 @@ impl Generator<'_> {
             RustExtraImplKind::FlagEnum(data) => {
                 let enum_path = self.rust_path_to_string(&data.enum_path);
                 let qflags = self.rust_path_to_string(&self.qt_core_path().join("QFlags"));
+                // `QFlags` is always constructed from `c_int`, regardless of
+                // the underlying type of the enum.
+                let value = to_c_int_expression(self.enum_wrapper_info(&data.enum_path).1);
 
                 writeln!(
                     self,
                     include_str!("../templates/crate/flag_enum_impl.rs.in"),
                     e = enum_path,
-                    qflags = qflags
+                    qflags = qflags,
+                    value = value
                 )?;
+                // bitflags enums have their own `BitOr` implementation
+                if self.enum_representation(&data.enum_path) != EnumRepresentation::Bitflags {
+                    writeln!(
+                        self,
+                        include_str!("../templates/crate/flag_enum_bitor.rs.in"),
+                        e = enum_path,
+                        qflags = qflags
+                    )?;
+                }
             }
             RustExtraImplKind::QtReceiverImpl(data) => {
                 let wrapper = &self
@@ -1195,7 +2454,9 @@ impl Generator<'_> {
                     .item;
 
                 let receiver_id = match wrapper {
-                    CppFfiItem::Function(_) => bail!("invalid source ffi item type"),
+                    CppFfiItem::Function(_)
+                    | CppFfiItem::ValueLayout(_)
+                    | CppFfiItem::FieldOffset(_) => bail!("invalid source ffi item type"),
                     CppFfiItem::QtSlotWrapper(w) => CppFunction::receiver_id_from_data(
                         RustQtReceiverType::Slot,
                         "slot_",
@@ -1230,25 +2491,590 @@ impl Generator<'_> {
                     )?;
                 }
             }
+            // the trait is available if any of the overloads is available,
+            // so `condition_texts` of the source of the item are not used
+            RustExtraImplKind::OverloadTrait(data) => self.generate_overload_trait(data)?,
+        }
+        Ok(())
+    }
+
+    /// Generates the trait implemented for tuples of arguments of overloads
+    /// and the generic method calling the overloads (see `OverloadStrategy::ArgsTuple`).
+    fn generate_overload_trait(&mut self, data: &RustOverloadTrait) -> Result<()> {
+        let overloads = data.overloads.iter().map_if_ok(|path| {
+            self.current_database
+                .find_rust_item(path)
+                .and_then(|item| item.filter_map(|item| item.as_function_ref()))
+                .ok_or_else(|| format_err!("overload not found: {:?}", path))
+        })?;
+        if overloads.is_empty() {
+            return Ok(());
+        }
+        let conditions = overloads.iter().map_if_ok(|f| self.condition(&f.id))?;
+        let trait_condition = if conditions.contains(&Condition::True) {
+            Condition::True
+        } else {
+            Condition::Or(conditions.clone())
+        };
+        let trait_condition_texts = condition_texts(&trait_condition);
+
+        let method_text = data.method_path.parts[1..].join("::");
+        let maybe_unsafe = if data.is_unsafe { "unsafe " } else { "" };
+        let this_arg = if data.self_arg_kind == RustFunctionSelfArgKind::None {
+            String::new()
+        } else {
+            let self_arg = overloads[0]
+                .item
+                .arguments
+                .get(0)
+                .ok_or_else(|| err_msg("self argument expected"))?;
+            format!(
+                ", this: {}",
+                self.rust_type_to_code(self_arg.argument_type.api_type())
+            )
+        };
+        writeln!(
+            self,
+            "{doc}{condition}pub trait {name} {{\n\
+             /// Return type of the overload.\n\
+             type Output;\n\n\
+             /// Calls the overload corresponding to the types of the arguments.\n\
+             {maybe_unsafe}fn call(self{this_arg}) -> Self::Output;\n\
+             }}\n",
+            doc = format_doc(&format!(
+                "Tuples of arguments accepted by `{}`.",
+                method_text
+            )),
+            condition = trait_condition_texts.attribute,
+            name = data.trait_path.last(),
+            maybe_unsafe = maybe_unsafe,
+            this_arg = this_arg,
+        )?;
+
+        let mut overloads_doc = Vec::new();
+        for (function, condition) in overloads.iter().zip(&conditions) {
+            let mut lifetimes = Vec::new();
+            let mut generic_params = Vec::new();
+            let mut types = Vec::new();
+            let args = function
+                .item
+                .arguments
+                .iter()
+                .filter(|arg| arg.name != "self")
+                .collect_vec();
+            for (index, arg) in args.iter().enumerate() {
+                let api_type = arg.argument_type.api_type();
+                if let Some(lifetime) = api_type.lifetime() {
+                    let lifetime = format!("'{}", lifetime);
+                    if !lifetimes.contains(&lifetime) {
+                        lifetimes.push(lifetime);
+                    }
+                }
+                if let RustType::ImplTrait(trait_type) = api_type {
+                    let name = format!("T{}", index);
+                    generic_params.push(format!(
+                        "{}: {}",
+                        name,
+                        self.rust_common_type_to_code(trait_type)
+                    ));
+                    types.push(name);
+                } else {
+                    types.push(self.rust_type_to_code(api_type));
+                }
+            }
+            let tuple = if types.len() == 1 {
+                format!("({},)", types[0])
+            } else {
+                format!("({})", types.join(", "))
+            };
+            let all_generic_params = lifetimes.into_iter().chain(generic_params).collect_vec();
+            let generics = if all_generic_params.is_empty() {
+                String::new()
+            } else {
+                format!("<{}>", all_generic_params.join(", "))
+            };
+            let mut call_args = Vec::new();
+            if data.self_arg_kind != RustFunctionSelfArgKind::None {
+                call_args.push("this".to_string());
+            }
+            call_args.extend((0..args.len()).map(|index| format!("self.{}", index)));
+            let output = self.rust_type_to_code(function.item.return_type.api_type());
+            writeln!(
+                self,
+                "{condition}impl{generics} {name} for {tuple} {{\n\
+                 type Output = {output};\n\n\
+                 #[inline(always)]\n\
+                 {maybe_unsafe}fn call(self{this_arg}) -> {output} {{\n\
+                 {path}({call_args})\n\
+                 }}\n\
+                 }}\n",
+                condition = condition_texts(condition).attribute,
+                generics = generics,
+                name = data.trait_path.last(),
+                tuple = tuple,
+                output = output,
+                maybe_unsafe = maybe_unsafe,
+                this_arg = this_arg,
+                path = self.rust_path_to_string(&function.item.path),
+                call_args = call_args.join(", "),
+            )?;
+            overloads_doc.push(format!(
+                "* `{}`: calls `{}`.",
+                tuple,
+                function.item.path.last()
+            ));
+        }
+
+        let (self_arg, this) = match data.self_arg_kind {
+            RustFunctionSelfArgKind::None => ("", ""),
+            RustFunctionSelfArgKind::ConstRef => ("&self, ", "self"),
+            RustFunctionSelfArgKind::MutRef => ("&mut self, ", "self"),
+            RustFunctionSelfArgKind::Value => ("self, ", "self"),
+        };
+        let method = format!(
+            "{doc}#[inline(always)]\n\
+             pub {maybe_unsafe}fn {name}<A: {trait_path}>({self_arg}args: A) -> A::Output {{\n\
+             args.call({this})\n\
+             }}\n",
+            doc = format_doc(&format!(
+                "Calls one of the overloads depending on the type of `args`:\n\n{}",
+                overloads_doc.join("\n")
+            )),
+            maybe_unsafe = maybe_unsafe,
+            name = data.method_path.last(),
+            trait_path = self.rust_path_to_string(&data.trait_path),
+            self_arg = self_arg,
+            this = this,
+        );
+        let parent = data.method_path.parent()?;
+        let is_method = self
+            .current_database
+            .find_rust_item(&parent)
+            .map_or(false, |item| item.item.as_struct_ref().is_some());
+        if is_method {
+            writeln!(
+                self,
+                "{}impl {} {{\n{}}}\n",
+                trait_condition_texts.attribute,
+                self.rust_path_to_string(&parent),
+                method
+            )?;
+        } else {
+            writeln!(self, "{}{}", trait_condition_texts.attribute, method)?;
         }
         Ok(())
     }
 }
 
+/// Returns paths of C++ classes that have virtual functions
+/// or inherit a class that has virtual functions. Classes reported as polymorphic
+/// by the compiler on `env` (see `cpp_parser::probe_type_properties`) are also included.
+pub fn polymorphic_classes(db: &DatabaseClient, env: &LibraryTarget) -> HashSet<CppPath> {
+    let mut result = db
+        .all_cpp_items()
+        .filter_map(|item| item.item.as_function_ref())
+        .filter(|function| function.member.as_ref().map_or(false, |m| m.is_virtual))
+        .filter_map(|function| function.class_path().ok())
+        .collect::<HashSet<_>>();
+    result.extend(
+        db.all_cpp_type_properties()
+            .iter()
+            .filter(|p| p.is_polymorphic && &p.env == env)
+            .map(|p| p.path.clone()),
+    );
+    let bases = db
+        .all_cpp_items()
+        .filter_map(|item| item.item.as_base_ref())
+        .collect_vec();
+    loop {
+        let mut changed = false;
+        for base in &bases {
+            if result.contains(&base.base_class_type)
+                && result.insert(base.derived_class_type.clone())
+            {
+                changed = true;
+            }
+        }
+        if !changed {
+            return result;
+        }
+    }
+}
+
+/// Returns `*const T` type, where `T` is a wide character type at `path`.
+fn wide_char_pointer(path: &str) -> RustType {
+    RustType::new_pointer(
+        true,
+        RustType::Common(RustCommonType {
+            path: RustPath::from_good_str(path),
+            generic_arguments: None,
+        }),
+    )
+}
+
+/// Returns the condition that holds when the crate is compiled for the library target `env`.
+fn env_condition(env: &LibraryTarget) -> Condition {
+    let mut conditions = vec![
+        Condition::Arch(env.target.arch),
+        Condition::OS(env.target.os),
+        Condition::Env(env.target.env),
+    ];
+    if let Some(version) = &env.cpp_library_version {
+        conditions.push(Condition::CppLibraryVersion(version.clone()));
+    }
+    if let Some(flag_set) = &env.flag_set {
+        conditions.push(Condition::FlagSet(flag_set.clone()));
+    }
+    Condition::And(conditions)
+}
+
+/// Returns an expression converting `value` of an enum wrapper with
+/// the specified underlying type to `c_int`.
+fn to_c_int_expression(underlying_type: Option<&str>) -> &'static str {
+    if underlying_type.is_some() {
+        "value.to_int() as ::std::os::raw::c_int"
+    } else {
+        "value.to_int()"
+    }
+}
+
+/// Returns `From` implementations converting the enum wrapper `name` to integers.
+/// The conversion to `c_int` is always available. If the enum has another
+/// underlying type, a conversion to that type is also generated.
+pub fn enum_to_int_code(name: &str, underlying_type: Option<&str>) -> String {
+    let mut code = format!(
+        include_str!("../templates/crate/enum_to_int.rs.in"),
+        name = name,
+        int_type = "::std::os::raw::c_int",
+        value = to_c_int_expression(underlying_type)
+    );
+    if let Some(underlying_type) = underlying_type {
+        code += &format!(
+            include_str!("../templates/crate/enum_to_int.rs.in"),
+            name = name,
+            int_type = underlying_type,
+            value = "value.to_int()"
+        );
+    }
+    code
+}
+
+/// Returns static assertions verifying that the size and alignment of the Rust type
+/// `name` match the `properties` of the C++ type reported by the compiler
+/// (see `cpp_parser::probe_type_properties`). Each assertion is only compiled
+/// for the library target it was reported for and if `condition` holds.
+pub fn layout_assertions_code(
+    name: &str,
+    properties: &[&CppTypeProperties],
+    condition: &Condition,
+) -> String {
+    let mut code = String::new();
+    for properties in properties {
+        let env_condition = env_condition(&properties.env);
+        let condition = if condition == &Condition::True {
+            env_condition
+        } else {
+            Condition::And(vec![condition.clone(), env_condition])
+        };
+        let attribute = format!("#[cfg({})]", condition_expression(&condition));
+        code += &format!(
+            "{attribute}\nconst _: [(); {size}] = [(); ::std::mem::size_of::<{name}>()];\n\
+             {attribute}\nconst _: [(); {alignment}] = [(); ::std::mem::align_of::<{name}>()];\n",
+            attribute = attribute,
+            size = properties.size,
+            alignment = properties.alignment,
+            name = name,
+        );
+    }
+    code
+}
+
+/// Returns name of the macro registering a Rust function as a plugin factory
+/// (see `Config::add_plugin_registry`).
+fn registry_macro_name(registry: &PluginRegistry) -> String {
+    format!("register_{}", registry.rust_name)
+}
+
+/// Returns the code of the module registering Rust closures as plugin factories
+/// through the C++ function or registrar constructor `register`. If the library
+/// doesn't pass data to the factories, a macro registering Rust functions
+/// is generated instead. `register` and `create` contain the FFI functions
+/// and paths of their Rust FFI functions.
+pub fn plugin_registry_code(
+    registry: &PluginRegistry,
+    register: (&CppFfiFunction, &str),
+    create: Option<(&CppFfiFunction, &str)>,
+    on_panic: &str,
+) -> Result<String> {
+    let void_ptr = CppType::new_pointer(false, CppType::Void);
+    let key_type = CppType::new_pointer(true, CppType::BuiltInNumeric(CppBuiltInNumericType::Char));
+
+    let (register_ffi_function, register_path) = register;
+    if register_ffi_function
+        .arguments
+        .iter()
+        .any(|arg| arg.meaning == CppFfiArgumentMeaning::ReturnValue)
+    {
+        bail!(
+            "registrar object must be allocated on the heap: {}",
+            registry.register.to_cpp_pseudo_code()
+        );
+    }
+    let is_factory = |type1: &CppType, arguments: &[CppType]| {
+        type1.as_function_pointer().map_or(false, |t| {
+            *t.return_type == void_ptr && t.arguments == arguments && !t.allows_variadic_arguments
+        })
+    };
+    // If `register` is a constructor of a registrar class, the created object
+    // is never deleted, just like a static registrar object in C++.
+    let has_data = match register_ffi_function.arguments.as_slice() {
+        [key, factory, data]
+            if key.argument_type.original_type() == &key_type
+                && is_factory(factory.argument_type.original_type(), &[void_ptr.clone()])
+                && data.argument_type.original_type() == &void_ptr =>
+        {
+            true
+        }
+        [key, factory]
+            if key.argument_type.original_type() == &key_type
+                && is_factory(factory.argument_type.original_type(), &[]) =>
+        {
+            false
+        }
+        _ => bail!(
+            "plugin registration function must accept const char* and \
+             void* (*)(void*), void* or const char* and void* (*)() arguments: {}",
+            registry.register.to_cpp_pseudo_code()
+        ),
+    };
+
+    let create_code = if let Some((create_ffi_function, create_path)) = create {
+        let create = registry
+            .create
+            .as_ref()
+            .ok_or_else(|| err_msg("plugin creation function is not set"))?;
+        let is_valid_create = match create_ffi_function.arguments.as_slice() {
+            [key] => {
+                key.argument_type.original_type() == &key_type
+                    && create_ffi_function.return_type.original_type().is_pointer()
+            }
+            _ => false,
+        };
+        if !is_valid_create {
+            bail!(
+                "plugin creation function must accept a const char* argument \
+                 and return a pointer: {}",
+                create.to_cpp_pseudo_code()
+            );
+        }
+        format!(
+            "/// Creates a plugin object registered for `key` using `{create}`.
+            ///
+            /// # Safety
+            ///
+            /// The returned pointer must be cast to the type of plugin objects
+            /// of the library.
+            pub unsafe fn create(key: &::std::ffi::CStr) -> *mut ::std::ffi::c_void {{
+                {create_path}(key.as_ptr()) as *mut ::std::ffi::c_void
+            }}",
+            create = create.to_cpp_pseudo_code(),
+            create_path = create_path,
+        )
+    } else {
+        String::new()
+    };
+
+    let register_code = if has_data {
+        format!(
+            "type Factory = Box<dyn Fn() -> *mut ::std::ffi::c_void + Send + Sync + 'static>;
+
+            /// Registers `factory` as the factory of plugins with `key`
+            /// using `{register}`.
+            ///
+            /// # Safety
+            ///
+            /// `factory` must return a pointer to a new object of the type expected
+            /// by the library. The factory is never freed because the library
+            /// can call it until the program exits.
+            pub unsafe fn register<F>(key: &::std::ffi::CStr, factory: F)
+            where
+                F: Fn() -> *mut ::std::ffi::c_void + Send + Sync + 'static,
+            {{
+                extern \"C\" fn ffi_factory(
+                    data: *mut ::std::ffi::c_void,
+                ) -> *mut ::std::ffi::c_void {{
+                    let factory = unsafe {{ &*(data as *const Factory) }};
+                    let result = ::std::panic::catch_unwind(
+                        ::std::panic::AssertUnwindSafe(|| factory()),
+                    );
+                    match result {{
+                        Ok(object) => object,
+                        Err(_) => {{
+                            {on_panic}
+                            ::std::ptr::null_mut()
+                        }}
+                    }}
+                }}
+                let factory: Factory = Box::new(factory);
+                let data = Box::into_raw(Box::new(factory)) as *mut ::std::ffi::c_void;
+                let _ = {register_path}(key.as_ptr(), Some(ffi_factory), data);
+            }}",
+            register = registry.register.to_cpp_pseudo_code(),
+            register_path = register_path,
+            on_panic = on_panic,
+        )
+    } else {
+        format!(
+            "/// Registers `factory` as the factory of plugins with `key`
+            /// using `{register}`. The `{macro_name}!` macro can be used
+            /// to register a Rust function instead.
+            ///
+            /// # Safety
+            ///
+            /// `factory` must return a pointer to a new object of the type expected
+            /// by the library and must not unwind.
+            pub unsafe fn register_raw(
+                key: &::std::ffi::CStr,
+                factory: extern \"C\" fn() -> *mut ::std::ffi::c_void,
+            ) {{
+                let _ = {register_path}(key.as_ptr(), Some(factory));
+            }}",
+            register = registry.register.to_cpp_pseudo_code(),
+            macro_name = registry_macro_name(registry),
+            register_path = register_path,
+        )
+    };
+
+    let mut code = format!(
+        "/// Registration of plugins using `{register}`.
+        pub mod {name} {{
+            {register_code}
+
+            {create_code}
+        }}",
+        register = registry.register.to_cpp_pseudo_code(),
+        name = registry.rust_name,
+        register_code = register_code,
+        create_code = create_code,
+    );
+
+    if !has_data {
+        // the library doesn't pass any data to the factory, so each Rust function
+        // needs its own FFI function
+        code += &format!(
+            "\n\n/// Registers the function `$factory` as the factory of plugins
+            /// with `$key` using `{register}`. `$key` must be a `&CStr`, and
+            /// `$factory` must be a function returning `*mut c_void`.
+            /// Panics in `$factory` are caught at the FFI boundary.
+            ///
+            /// This macro is `unsafe` to use: the factory must return a pointer
+            /// to a new object of the type expected by the library.
+            #[macro_export]
+            macro_rules! {macro_name} {{
+                ($key:expr, $factory:path) => {{{{
+                    extern \"C\" fn ffi_factory() -> *mut ::std::ffi::c_void {{
+                        match ::std::panic::catch_unwind(|| $factory()) {{
+                            Ok(object) => object,
+                            Err(_) => {{
+                                {on_panic}
+                                ::std::ptr::null_mut()
+                            }}
+                        }}
+                    }}
+                    $crate::{name}::register_raw($key, ffi_factory)
+                }}}};
+            }}",
+            register = registry.register.to_cpp_pseudo_code(),
+            macro_name = registry_macro_name(registry),
+            name = registry.rust_name,
+            on_panic = on_panic,
+        );
+    }
+    Ok(code)
+}
+
+/// Returns the code of the conversions between the wrapper type at `wrapper_path`
+/// and the type of the optional dependency. The code is only compiled
+/// if the feature of the conversion is enabled.
+pub fn optional_type_conversion_code(
+    conversion: &OptionalTypeConversion,
+    wrapper_path: &RustPath,
+    current_crate: &str,
+) -> Result<String> {
+    if conversion.rust_type.crate_name() != conversion.dependency.replace('-', "_") {
+        bail!(
+            "type of optional conversion must be provided by {}: {}",
+            conversion.dependency,
+            conversion.rust_type.full_name(None)
+        );
+    }
+    let attributes = format!(
+        "#[cfg(feature = {feature:?})]\n\
+         #[cfg_attr(feature = \"ritual_rustdoc_nightly\", doc(cfg(feature = {feature:?})))]\n",
+        feature = conversion.feature
+    );
+    let wrapper = wrapper_path.full_name(Some(current_crate));
+    let rust_type = conversion.rust_type.full_name(Some(current_crate));
+    let mut code = String::new();
+    if let Some(to_rust) = &conversion.to_rust {
+        writeln!(
+            code,
+            "{attributes}impl<'a> ::std::convert::From<&'a {wrapper}> for {rust_type} {{
+    fn from(value: &'a {wrapper}) -> Self {{
+        {function}(value)
+    }}
+}}
+",
+            attributes = attributes,
+            wrapper = wrapper,
+            rust_type = rust_type,
+            function = to_rust.full_name(Some(current_crate)),
+        )?;
+    }
+    if let Some(from_rust) = &conversion.from_rust {
+        writeln!(
+            code,
+            "{attributes}impl {wrapper} {{
+    /// Creates a C++ value from `{rust_type}`.
+    pub fn from_{dependency}(value: &{rust_type}) -> ::cpp_core::CppBox<Self> {{
+        {function}(value)
+    }}
+}}
+",
+            attributes = attributes,
+            wrapper = wrapper,
+            rust_type = rust_type,
+            dependency = conversion.dependency.replace('-', "_"),
+            function = from_rust.full_name(Some(current_crate)),
+        )?;
+    }
+    Ok(code)
+}
+
+/// Generates Rust source files of the crate.
+///
+/// Each module stored in a separate file is generated by a separate task,
+/// and the tasks are executed in parallel. Every output file is written by exactly
+/// one task, so the output doesn't depend on the order of execution.
 pub fn generate(
     current_database: &DatabaseClient,
     output_src_path: impl Into<PathBuf>,
     crate_template_src_path: Option<impl Into<PathBuf>>,
+    config: &Config,
 ) -> Result<()> {
-    let mut generator = Generator {
-        destination: Vec::new(),
-        output_src_path: output_src_path.into(),
-        crate_template_src_path: crate_template_src_path.map(Into::into),
-        current_database,
-    };
-
-    let crate_root = generator
-        .current_database
+    // `Config` can't be shared between threads, so the values are extracted beforehand.
+    let cpp_doc_url_template = config.cpp_doc_url_template();
+    let callback_panic_behavior = config.callback_panic_behavior();
+    let event_loop_functions = config.event_loop_functions();
+    let lifecycle_functions = config.lifecycle_functions();
+    let optional_type_conversions = config.optional_type_conversions();
+    let plugin_registries = config.plugin_registries();
+    let string_encoding = config.string_encoding();
+    let ffi_declarations = config.ffi_declarations();
+    let output_src_path = output_src_path.into();
+    let crate_template_src_path = crate_template_src_path.map(Into::into);
+
+    let crate_root = current_database
         .rust_items()
         .filter_map(|i| i.filter_map(|i| i.as_module_ref()))
         .find(|module| {
@@ -1256,6 +3082,39 @@ pub fn generate(
         })
         .ok_or_else(|| err_msg("crate root not found"))?;
 
-    generator.generate_module(crate_root)?;
+    let polymorphic_classes =
+        polymorphic_classes(current_database, &config.current_library_target()?);
+
+    let mut modules = vec![crate_root.id];
+    while !modules.is_empty() {
+        let pending_modules = modules
+            .par_iter()
+            .map(|id| -> Result<_> {
+                let mut generator = Generator {
+                    destination: Vec::new(),
+                    output_src_path: output_src_path.clone(),
+                    crate_template_src_path: crate_template_src_path.clone(),
+                    current_database,
+                    cpp_doc_url_template,
+                    callback_panic_behavior,
+                    event_loop_functions,
+                    lifecycle_functions,
+                    optional_type_conversions,
+                    plugin_registries,
+                    string_encoding,
+                    ffi_declarations,
+                    pending_modules: Vec::new(),
+                    polymorphic_classes: &polymorphic_classes,
+                };
+                let module = current_database
+                    .rust_item(id)?
+                    .filter_map(|i| i.as_module_ref())
+                    .ok_or_else(|| err_msg("expected a module"))?;
+                generator.generate_module(module)?;
+                Ok(generator.pending_modules)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        modules = pending_modules.into_iter().flatten().collect();
+    }
     Ok(())
 }
@@ -1,4 +1,7 @@
-use crate::config::CrateDependencyKind;
+use crate::config::{
+    AllocationStrategy, AsyncFunctionRule, CrateDependencyKind, FunctionSafety, HandleType,
+    OverloadStrategy, SafetyPolicy,
+};
 use crate::cpp_checks::CppChecks;
 use crate::cpp_data::{
     inherits, CppItem, CppPath, CppPathItem, CppTypeDeclaration, CppTypeDeclarationKind,
@@ -7,35 +10,58 @@ use crate::cpp_ffi_data::{
     CppCast, CppFfiArgumentMeaning, CppFfiFunction, CppFfiFunctionKind, CppFfiItem, CppFfiType,
     CppFieldAccessorType, CppToFfiTypeConversion,
 };
-use crate::cpp_ffi_generator::ffi_type;
+use crate::cpp_ffi_generator::{check_in_place_allocation, ffi_type};
 use crate::cpp_function::{CppFunction, CppOperator, ReturnValueAllocationPlace};
 use crate::cpp_type::{
     is_qflags, CppBuiltInNumericType, CppFunctionPointerType, CppPointerLikeTypeKind,
     CppSpecificNumericType, CppSpecificNumericTypeKind, CppType, CppTypeRole,
 };
 use crate::database::{DbItem, ItemId, ItemWithSource};
+use crate::html_logger::{anchor, escape_html, HtmlLogger, HtmlTemplates, Severity};
+use crate::numeric_types::PlatformNumericType;
+use crate::overrides::Overrides;
 use crate::processor::ProcessorData;
+use crate::rust_code_generator::rust_type_to_code;
 use crate::rust_info::{
-    NameType, RustEnumValue, RustExtraImpl, RustExtraImplKind, RustFfiWrapperData,
-    RustFlagEnumImpl, RustFunction, RustFunctionArgument, RustFunctionCaptionStrategy,
-    RustFunctionKind, RustFunctionSelfArgKind, RustItem, RustModule, RustModuleKind, RustPathScope,
-    RustQtReceiverData, RustQtReceiverImpl, RustQtReceiverType, RustReexport, RustReexportSource,
-    RustSignalOrSlotGetter, RustSizedType, RustSpecialModuleKind, RustStruct, RustStructKind,
-    RustTraitAssociatedType, RustTraitImpl, RustTraitImplExtraKind, RustTypeCaptionStrategy,
-    RustWrapperTypeKind, UnnamedRustFunction,
+    EnumRepresentation, NameType, RustEnumValue, RustExtraImpl, RustExtraImplKind,
+    RustFfiWrapperData, RustFlagEnumImpl, RustFunction, RustFunctionArgument,
+    RustFunctionCaptionStrategy, RustFunctionKind, RustFunctionSelfArgKind, RustHandleType,
+    RustInlineFieldAccess, RustItem, RustLayoutField, RustModule, RustModuleKind,
+    RustOverloadTrait, RustOwnedHandleType, RustPathScope, RustQtReceiverData, RustQtReceiverImpl,
+    RustQtReceiverType, RustReexport, RustReexportSource, RustSignalOrSlotGetter, RustSizedType,
+    RustSpecialModuleKind, RustStruct, RustStructKind, RustTraitAssociatedType, RustTraitImpl,
+    RustTraitImplExtraKind, RustTypeCaptionStrategy, RustWrapperTypeKind, UnnamedRustFunction,
 };
 use crate::rust_type::{
-    RustClosureToCallbackConversion, RustCommonType, RustFinalType, RustFunctionPointerType,
-    RustPath, RustPointerLikeTypeKind, RustToFfiTypeConversion, RustType,
+    RustAsyncCompletionConversion, RustClosureToCallbackConversion, RustCommonType, RustFinalType,
+    RustFunctionPointerType, RustPath, RustPointerLikeTypeKind, RustToFfiTypeConversion, RustType,
 };
+use crate::workspace::Workspace;
 use itertools::Itertools;
-use log::{debug, trace};
+use log::{debug, trace, warn};
 use ritual_common::errors::{bail, err_msg, format_err, print_trace, Result};
-use ritual_common::string_utils::CaseOperations;
+use ritual_common::file_utils::{create_dir_all, load_json, remove_dir_all, save_json};
+use ritual_common::string_utils::{ends_with_digit, CaseOperations};
 use ritual_common::utils::MapIfOk;
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::iter::Iterator;
+use std::mem;
 use std::ops::Deref;
+use std::path::PathBuf;
+
+/// Items of `cpp_core` re-exported in the `prelude` module.
+const CPP_CORE_PRELUDE_ITEMS: &[&str] = &[
+    "CastFrom",
+    "CastInto",
+    "CppBox",
+    "CppDeletable",
+    "DynamicCast",
+    "NullPtr",
+    "Ptr",
+    "Ref",
+    "StaticDowncast",
+    "StaticUpcast",
+];
 
 pub fn qt_core_path(crate_name: &str) -> RustPath {
     if crate_name.starts_with("moqt_") {
@@ -78,6 +104,133 @@ struct FunctionWithDesiredPath {
     desired_path: RustPath,
 }
 
+/// Group of overloads that couldn't be named as requested
+/// by the selected overload strategy.
+#[derive(Debug)]
+struct OverloadDiagnostic {
+    /// Desired Rust path of the functions
+    path: String,
+    /// C++ functions and the Rust names assigned to them
+    functions: Vec<(String, String)>,
+    problems: Vec<String>,
+}
+
+/// Numbers assigned to overloads by `OverloadStrategy::Numbered`.
+/// Keys are desired Rust paths of groups of overloads, and values are
+/// keys of the overloads (see `overload_keys`) in order of their numbers.
+/// The numbers are saved in the workspace, so names of overloads
+/// don't change when other overloads are added or removed.
+pub type OverloadNumbers = BTreeMap<String, Vec<String>>;
+
+fn overload_numbers_path(workspace: &Workspace, crate_name: &str) -> PathBuf {
+    workspace
+        .lock_path()
+        .join(format!("overload_numbers_{}.json", crate_name))
+}
+
+/// Returns keys identifying overloads with C++ signatures `cpp_texts`
+/// (sorted by priority). Keys of overloads with identical signatures
+/// (e.g. produced from the same C++ function) get a suffix.
+pub fn overload_keys<'a>(cpp_texts: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut keys = Vec::<String>::new();
+    for text in cpp_texts {
+        let count = keys
+            .iter()
+            .filter(|key| key.as_str() == text || key.starts_with(&format!("{} #", text)))
+            .count();
+        keys.push(if count == 0 {
+            text.to_string()
+        } else {
+            format!("{} #{}", text, count + 1)
+        });
+    }
+    keys
+}
+
+/// Returns numbers of overloads with `keys`. Numbers recorded in `numbers`
+/// are reused, and new overloads get new numbers in order of `keys`.
+/// Numbers of removed overloads are not reused.
+pub fn assign_overload_numbers(numbers: &mut Vec<String>, keys: &[String]) -> Vec<usize> {
+    keys.iter()
+        .map(|key| {
+            if let Some(index) = numbers.iter().position(|k| k == key) {
+                index
+            } else {
+                numbers.push(key.clone());
+                numbers.len() - 1
+            }
+        })
+        .collect()
+}
+
+/// Returns path of the overload with `number` (e.g. `resize`, `resize1`).
+fn numbered_path(desired_path: &RustPath, number: usize) -> RustPath {
+    let mut path = desired_path.clone();
+    if number > 0 {
+        let last = path.last().to_string();
+        *path.last_mut() = if ends_with_digit(&last) {
+            format!("{}_{}", last, number)
+        } else {
+            format!("{}{}", last, number)
+        };
+    }
+    path
+}
+
+fn contains_generic_type(rust_type: &RustType) -> bool {
+    match rust_type {
+        RustType::ImplTrait(_) | RustType::GenericParameter(_) => true,
+        RustType::Tuple(types) => types.iter().any(contains_generic_type),
+        RustType::Common(common) => common
+            .generic_arguments
+            .iter()
+            .flatten()
+            .any(contains_generic_type),
+        RustType::PointerLike { target, .. } => contains_generic_type(target),
+        RustType::Array { element_type, .. } | RustType::Slice { element_type } => {
+            contains_generic_type(element_type)
+        }
+        RustType::Primitive(_) | RustType::FunctionPointer(_) => false,
+    }
+}
+
+fn contains_reference(rust_type: &RustType) -> bool {
+    match rust_type {
+        RustType::PointerLike { kind, target, .. } => {
+            if let RustPointerLikeTypeKind::Reference { .. } = kind {
+                true
+            } else {
+                contains_reference(target)
+            }
+        }
+        RustType::Tuple(types) => types.iter().any(contains_reference),
+        RustType::Common(common) => common
+            .generic_arguments
+            .iter()
+            .flatten()
+            .any(contains_reference),
+        RustType::Array { element_type, .. } | RustType::Slice { element_type } => {
+            contains_reference(element_type)
+        }
+        RustType::Primitive(_)
+        | RustType::FunctionPointer(_)
+        | RustType::ImplTrait(_)
+        | RustType::GenericParameter(_) => false,
+    }
+}
+
+/// Returns true if implementations of a trait for tuples of arguments `args1` and `args2`
+/// would conflict, so a generic method taking a tuple of arguments can't distinguish
+/// the overloads (see `OverloadStrategy::ArgsTuple`). Generic arguments
+/// (e.g. `impl CastInto<Ref<T>>`) can match any type.
+pub fn args_tuples_overlap(args1: &[RustType], args2: &[RustType]) -> bool {
+    args1.len() == args2.len()
+        && args1
+            .iter()
+            .zip(args2)
+            .all(|(a, b)| contains_generic_type(a) || contains_generic_type(b) || a == b)
+}
+
 enum ProcessedFfiItem {
     Item(RustItem),
     Function(FunctionWithDesiredPath),
@@ -547,6 +700,21 @@ fn check_trait_impl_uniqueness(
 struct State<'b, 'a> {
     data: &'b mut ProcessorData<'a>,
     special_module_paths: HashMap<RustSpecialModuleKind, RustPath>,
+    /// Classes with converting constructors.
+    /// Const reference arguments of these types accept `impl IntoArg<T>`.
+    into_arg_classes: HashSet<CppPath>,
+    overrides: Overrides,
+    /// Paths of all C++ namespaces of the current crate
+    /// and namespaces of its free functions.
+    namespaces: HashSet<CppPath>,
+    /// Modules generated for namespaces of free functions that don't have
+    /// a namespace item in the current crate.
+    dependency_namespace_modules: HashMap<CppPath, RustPath>,
+    /// Ids and values of field offsets by field path
+    /// (see `FfiFunctionOptions::inline_field_access`).
+    field_offsets: HashMap<CppPath, (ItemId, usize)>,
+    /// Paths of the handle newtypes by typedef path (see `Config::add_handle_type`).
+    handle_types: HashMap<CppPath, RustPath>,
 }
 
 impl State<'_, '_> {
@@ -599,25 +767,27 @@ impl State<'_, '_> {
                         CppBuiltInNumericType::ULongLong => "c_ulonglong",
                         CppBuiltInNumericType::Float => "c_float",
                         CppBuiltInNumericType::Double => "c_double",
-                        CppBuiltInNumericType::WChar => {
-                            return Ok(RustType::Common(RustCommonType {
-                                path: RustPath::from_good_str("cpp_core::wchar_t"),
-                                generic_arguments: None,
-                            }));
-                        }
-                        CppBuiltInNumericType::Char16 => {
-                            return Ok(RustType::Common(RustCommonType {
-                                path: RustPath::from_good_str("cpp_core::char16_t"),
-                                generic_arguments: None,
-                            }));
+                        CppBuiltInNumericType::Int128 | CppBuiltInNumericType::UInt128 => {
+                            bail!("128-bit integers are not FFI-safe: {:?}", numeric);
                         }
-                        CppBuiltInNumericType::Char32 => {
-                            return Ok(RustType::Common(RustCommonType {
-                                path: RustPath::from_good_str("cpp_core::char32_t"),
-                                generic_arguments: None,
-                            }));
+                        CppBuiltInNumericType::WChar
+                        | CppBuiltInNumericType::Char16
+                        | CppBuiltInNumericType::Char32
+                        | CppBuiltInNumericType::LongDouble => {
+                            let platform_type = PlatformNumericType::from_cpp_type(cpp_ffi_type)
+                                .ok_or_else(|| {
+                                    format_err!("unsupported numeric type: {:?}", numeric)
+                                })?;
+                            let path = RustPath::from_good_str(platform_type.rust_path());
+                            return Ok(if path.parts.len() == 1 {
+                                RustType::Primitive(path.parts[0].clone())
+                            } else {
+                                RustType::Common(RustCommonType {
+                                    path,
+                                    generic_arguments: None,
+                                })
+                            });
                         }
-                        _ => bail!("unsupported numeric type: {:?}", numeric),
                     };
                     let path = RustPath::from_good_str("std::os::raw").join(own_name);
                     RustType::Common(RustCommonType {
@@ -626,7 +796,14 @@ impl State<'_, '_> {
                     })
                 }
             }
-            CppType::SpecificNumeric(CppSpecificNumericType { bits, kind, .. }) => {
+            CppType::SpecificNumeric(CppSpecificNumericType { path, bits, kind }) => {
+                if let Some(handle_path) = self.handle_types.get(path) {
+                    // the newtype is `#[repr(transparent)]`, so it can be used in FFI directly
+                    return Ok(RustType::Common(RustCommonType {
+                        path: handle_path.clone(),
+                        generic_arguments: None,
+                    }));
+                }
                 let letter = match kind {
                     CppSpecificNumericTypeKind::Integer { is_signed } => {
                         if *is_signed {
@@ -645,6 +822,10 @@ impl State<'_, '_> {
                 RustType::Primitive(name.into())
             }
             CppType::Enum { path } | CppType::Class(path) => {
+                if let Some(int_type) = self.rust_enum_int_type(cpp_ffi_type)? {
+                    // Rust enums can't hold unknown values, so they are passed as integers
+                    return Ok(int_type);
+                }
                 let rust_item = self.find_wrapper_type(path)?;
                 let path = rust_item
                     .item
@@ -679,6 +860,10 @@ impl State<'_, '_> {
                 })
             }
             CppType::TemplateParameter { .. } => bail!("invalid cpp type"),
+            CppType::Array { element_type, size } => RustType::Array {
+                element_type: Box::new(self.ffi_type_to_rust_ffi_type(element_type)?),
+                size: *size,
+            },
         };
 
         Ok(rust_type)
@@ -748,6 +933,110 @@ impl State<'_, '_> {
         Ok(is_deletable)
     }
 
+    /// Returns the Rust primitive type matching the underlying type of the C++ enum
+    /// reported by the compiler, or `None` if it's unknown or differs between targets.
+    fn enum_underlying_type(&self, path: &CppPath) -> Option<String> {
+        let types = self
+            .data
+            .db
+            .all_cpp_type_properties()
+            .iter()
+            .filter(|p| &p.path == path)
+            .filter_map(|p| {
+                let is_signed = p.is_underlying_type_signed?;
+                Some(format!(
+                    "{}{}",
+                    if is_signed { "i" } else { "u" },
+                    p.size * 8
+                ))
+            })
+            .collect::<BTreeSet<_>>();
+        if types.len() > 1 {
+            warn!(
+                "underlying type of {} differs between targets: {}",
+                path.to_cpp_pseudo_code(),
+                types.iter().join(", ")
+            );
+            return None;
+        }
+        types.into_iter().next()
+    }
+
+    /// Returns the integer type used to pass `cpp_type` through FFI
+    /// if it's an enum with `EnumRepresentation::RustEnum` representation.
+    fn rust_enum_int_type(&self, cpp_type: &CppType) -> Result<Option<RustType>> {
+        if let CppType::Enum { path } = cpp_type {
+            let rust_item = self.find_wrapper_type(path)?;
+            if let RustItem::Struct(RustStruct {
+                kind:
+                    RustStructKind::WrapperType(RustWrapperTypeKind::EnumWrapper {
+                        representation: EnumRepresentation::RustEnum,
+                        underlying_type,
+                        ..
+                    }),
+                ..
+            }) = &rust_item.item
+            {
+                let int_type = match underlying_type {
+                    Some(name) => RustType::Primitive(name.clone()),
+                    None => RustType::Common(RustCommonType {
+                        path: RustPath::from_good_str("std::os::raw::c_int"),
+                        generic_arguments: None,
+                    }),
+                };
+                return Ok(Some(int_type));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns Rust type of `cpp_type` if it's an enum with
+    /// `EnumRepresentation::RustEnum` representation.
+    fn rust_enum_type(&self, cpp_type: &CppType) -> Result<Option<RustType>> {
+        if let CppType::Enum { path } = cpp_type {
+            let rust_item = self.find_wrapper_type(path)?;
+            if let RustItem::Struct(RustStruct {
+                path,
+                kind:
+                    RustStructKind::WrapperType(RustWrapperTypeKind::EnumWrapper {
+                        representation: EnumRepresentation::RustEnum,
+                        ..
+                    }),
+                ..
+            }) = &rust_item.item
+            {
+                return Ok(Some(RustType::Common(RustCommonType {
+                    path: path.clone(),
+                    generic_arguments: None,
+                })));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns true if a reference argument of `ffi_type` should accept
+    /// `impl IntoArg<T>` instead of `impl CastInto<Ref<T>>`.
+    /// `IntoArg` requires `T` to implement `CppDeletable`.
+    fn accepts_into_arg(&self, ffi_type: &CppType, checks: Option<&CppChecks>) -> Result<bool> {
+        if let CppType::PointerLike {
+            is_const: true,
+            target,
+            ..
+        } = ffi_type
+        {
+            if let CppType::Class(path) = &**target {
+                if !self.into_arg_classes.contains(path) {
+                    return Ok(false);
+                }
+                return match checks {
+                    Some(checks) => self.is_type_deletable(ffi_type, checks),
+                    None => Ok(true),
+                };
+            }
+        }
+        Ok(false)
+    }
+
     /// Generates `CompleteType` from `CppFfiType`, adding
     /// Rust API type, Rust FFI type and conversion between them.
     #[allow(clippy::collapsible_if)]
@@ -755,7 +1044,7 @@ impl State<'_, '_> {
         &self,
         cpp_ffi_type: &CppFfiType,
         argument_meaning: &CppFfiArgumentMeaning,
-        allocation_place: ReturnValueAllocationPlace,
+        allocation_place: &ReturnValueAllocationPlace,
         checks: Option<&CppChecks>,
     ) -> Result<RustFinalType> {
         let rust_ffi_type = self.ffi_type_to_rust_ffi_type(cpp_ffi_type.ffi_type())?;
@@ -768,13 +1057,31 @@ impl State<'_, '_> {
                 false
             };
 
-            if let CppToFfiTypeConversion::ValueToPointer { .. } = cpp_ffi_type.conversion() {
+            if target.is_array() {
+                // arrays are represented as `&[T; N]` in arguments and as
+                // `Ref<[T; N]>` in return values; pointers to arrays are kept raw
+                let is_reference = match cpp_ffi_type.conversion() {
+                    CppToFfiTypeConversion::ValueToPointer { .. }
+                    | CppToFfiTypeConversion::ReferenceToPointer => true,
+                    _ => false,
+                };
+                if is_reference {
+                    api_to_ffi_conversion =
+                        if argument_meaning == &CppFfiArgumentMeaning::ReturnValue {
+                            RustToFfiTypeConversion::UtilsRefToPtr {}
+                        } else {
+                            RustToFfiTypeConversion::RefToPtr { lifetime: None }
+                        };
+                }
+            } else if let CppToFfiTypeConversion::ValueToPointer { .. } = cpp_ffi_type.conversion()
+            {
                 if argument_meaning == &CppFfiArgumentMeaning::ReturnValue {
                     match allocation_place {
                         ReturnValueAllocationPlace::Stack => {
                             api_to_ffi_conversion = RustToFfiTypeConversion::ValueToPtr;
                         }
-                        ReturnValueAllocationPlace::Heap => {
+                        ReturnValueAllocationPlace::Heap
+                        | ReturnValueAllocationPlace::Factory { .. } => {
                             let is_deletable = if let Some(checks) = checks {
                                 self.is_type_deletable(cpp_ffi_type.ffi_type(), checks)?
                             } else {
@@ -784,6 +1091,8 @@ impl State<'_, '_> {
                             api_to_ffi_conversion = if is_deletable {
                                 if inherits_qobject {
                                     RustToFfiTypeConversion::QBoxToPtr
+                                } else if self.is_pinned_class(&target)? {
+                                    RustToFfiTypeConversion::PinnedCppBoxToPtr
                                 } else {
                                     RustToFfiTypeConversion::CppBoxToPtr
                                 }
@@ -818,6 +1127,13 @@ impl State<'_, '_> {
                                     RustToFfiTypeConversion::UtilsPtrToPtr {}
                                 }
                             };
+                    } else if self.data.config.string_encoding().is_some()
+                        && target == CppType::BuiltInNumeric(CppBuiltInNumericType::Char)
+                        && cpp_ffi_type.conversion() == &CppToFfiTypeConversion::NoChange
+                        && rust_ffi_type.is_const_pointer_like()?
+                    {
+                        // C strings returned by the library use its encoding
+                        api_to_ffi_conversion = RustToFfiTypeConversion::DecodedString;
                     } else {
                         api_to_ffi_conversion = RustToFfiTypeConversion::None;
                     }
@@ -828,9 +1144,13 @@ impl State<'_, '_> {
                             if let CppToFfiTypeConversion::ReferenceToPointer { .. } =
                                 cpp_ffi_type.conversion()
                             {
-                                RustToFfiTypeConversion::ImplCastInto(Box::new(
-                                    RustToFfiTypeConversion::UtilsRefToPtr {},
-                                ))
+                                if self.accepts_into_arg(cpp_ffi_type.ffi_type(), checks)? {
+                                    RustToFfiTypeConversion::ImplIntoArg
+                                } else {
+                                    RustToFfiTypeConversion::ImplCastInto(Box::new(
+                                        RustToFfiTypeConversion::UtilsRefToPtr {},
+                                    ))
+                                }
                             } else {
                                 RustToFfiTypeConversion::ImplCastInto(Box::new(
                                     RustToFfiTypeConversion::UtilsPtrToPtr {},
@@ -842,6 +1162,18 @@ impl State<'_, '_> {
                 }
             }
         }
+        if let Some(enum_type) = self.rust_enum_type(cpp_ffi_type.ffi_type())? {
+            // values produced by C++ may be unknown to the Rust enum
+            let api_type = if argument_meaning == &CppFfiArgumentMeaning::ReturnValue {
+                RustType::Common(RustCommonType {
+                    path: RustPath::from_good_str("std::result::Result"),
+                    generic_arguments: Some(vec![enum_type, rust_ffi_type.clone()]),
+                })
+            } else {
+                enum_type
+            };
+            api_to_ffi_conversion = RustToFfiTypeConversion::RustEnumToInt { api_type };
+        }
         if cpp_ffi_type.conversion() == &CppToFfiTypeConversion::QFlagsToInt {
             let qflags_type = match cpp_ffi_type.original_type() {
                 CppType::PointerLike {
@@ -906,7 +1238,7 @@ impl State<'_, '_> {
             args.push(RustFunctionArgument {
                 name: sanitize_rust_identifier(&arg.name, false),
                 argument_type: RustFinalType::new(rust_type, RustToFfiTypeConversion::None)?,
-                ffi_index,
+                ffi_index: Some(ffi_index),
             });
         }
         let return_type = self.ffi_type_to_rust_ffi_type(data.return_type.ffi_type())?;
@@ -980,6 +1312,12 @@ impl State<'_, '_> {
                 other_type1 =
                     RustFinalType::new(other_type1.ffi_type().clone(), (**conversion).clone())?;
             }
+            if other_type1.conversion() == &RustToFfiTypeConversion::ImplIntoArg {
+                other_type1 = RustFinalType::new(
+                    other_type1.ffi_type().clone(),
+                    RustToFfiTypeConversion::UtilsRefToPtr {},
+                )?;
+            }
             trait_args = Some(vec![other_type1.api_type().clone()]);
             other_type = Some(other_type1);
         } else {
@@ -1072,7 +1410,7 @@ impl State<'_, '_> {
 
     fn process_destructor(
         unnamed_function: UnnamedRustFunction,
-        allocation_place: ReturnValueAllocationPlace,
+        allocation_place: &ReturnValueAllocationPlace,
     ) -> Result<RustTraitImpl> {
         if unnamed_function.arguments.len() != 1 {
             bail!("destructor must have one argument");
@@ -1098,7 +1436,7 @@ impl State<'_, '_> {
                 trait_path = RustPath::from_good_str("std::ops::Drop");
                 is_unsafe = false;
             }
-            ReturnValueAllocationPlace::Heap => {
+            ReturnValueAllocationPlace::Heap | ReturnValueAllocationPlace::Factory { .. } => {
                 function_name = "delete";
                 trait_path = RustPath::from_good_str("cpp_core::CppDeletable");
                 is_unsafe = true;
@@ -1153,7 +1491,7 @@ impl State<'_, '_> {
                     cast_function_name_mut = "static_upcast";
                 }
             }
-            CppCast::Dynamic => {
+            CppCast::Dynamic | CppCast::Library { .. } => {
                 trait_path = RustPath::from_good_str("cpp_core::DynamicCast");
                 derived_type = to_type;
                 cast_function_name_mut = "dynamic_cast";
@@ -1260,7 +1598,7 @@ impl State<'_, '_> {
                 // closure argument should be handled in the same way
                 // as return type (value is produced behind FFI)
                 &CppFfiArgumentMeaning::ReturnValue,
-                ReturnValueAllocationPlace::NotApplicable,
+                &ReturnValueAllocationPlace::NotApplicable,
                 Some(&checks),
             )
         })?;
@@ -1268,7 +1606,7 @@ impl State<'_, '_> {
             &CppFfiType::void(),
             // TODO: not sure about the meaning.
             &CppFfiArgumentMeaning::Argument(0),
-            ReturnValueAllocationPlace::NotApplicable,
+            &ReturnValueAllocationPlace::NotApplicable,
             Some(&checks),
         )?;
 
@@ -1302,17 +1640,24 @@ impl State<'_, '_> {
             rust_ffi_function,
         ))];
 
+        let cpp_item = self
+            .data
+            .db
+            .source_cpp_item(&item.id)?
+            .ok_or_else(|| err_msg("source cpp item not found"))?
+            .item;
+
         let mut arguments = Vec::new();
         for (arg_index, arg) in function.arguments.iter().enumerate() {
             if arg.meaning != CppFfiArgumentMeaning::ReturnValue {
                 let arg_type = self.rust_final_type(
                     &arg.argument_type,
                     &arg.meaning,
-                    function.allocation_place,
+                    &function.allocation_place,
                     Some(checks),
                 )?;
                 arguments.push(RustFunctionArgument {
-                    ffi_index: arg_index,
+                    ffi_index: Some(arg_index),
                     argument_type: arg_type,
                     name: if arg.meaning == CppFfiArgumentMeaning::This {
                         "self".to_string()
@@ -1334,7 +1679,7 @@ impl State<'_, '_> {
             self.rust_final_type(
                 &arg.argument_type,
                 &arg.meaning,
-                function.allocation_place,
+                &function.allocation_place,
                 Some(checks),
             )?
         } else {
@@ -1343,10 +1688,16 @@ impl State<'_, '_> {
             self.rust_final_type(
                 &function.return_type,
                 &CppFfiArgumentMeaning::ReturnValue,
-                function.allocation_place,
+                &function.allocation_place,
                 Some(checks),
             )?
         };
+        if self.is_borrowed_return(cpp_item, function)? {
+            return_type = RustFinalType::new(
+                return_type.ffi_type().clone(),
+                RustToFfiTypeConversion::RefToPtr { lifetime: None },
+            )?;
+        }
         if return_type.api_type().is_ref() && return_type.api_type().lifetime().is_none() {
             let mut found = false;
             for arg in &arguments {
@@ -1386,17 +1737,24 @@ impl State<'_, '_> {
             is_public: true,
             arguments,
             return_type,
-            kind: RustFunctionKind::FfiWrapper(RustFfiWrapperData { ffi_function_path }),
+            kind: RustFunctionKind::FfiWrapper(RustFfiWrapperData {
+                ffi_function_path,
+                inline_field_access: self.inline_field_access(&item, &cpp_item)?,
+                safety_reason: None,
+            }),
             is_unsafe: true,
         };
         self.convert_callbacks_to_closure(&item.id, &mut unnamed_function, checks)?;
 
-        let cpp_item = self
-            .data
-            .db
-            .source_cpp_item(&item.id)?
-            .ok_or_else(|| err_msg("source cpp item not found"))?
-            .item;
+        if self.data.config.lifecycle_functions().is_some()
+            && cpp_item
+                .as_function_ref()
+                .map_or(false, |f| f.is_constructor())
+        {
+            unnamed_function
+                .arguments
+                .insert(0, self.library_handle_argument()?);
+        }
 
         if let CppFfiFunctionKind::Function = &function.kind {
             let cpp_function = cpp_item
@@ -1404,7 +1762,7 @@ impl State<'_, '_> {
                 .ok_or_else(|| err_msg("invalid source cpp item type"))?;
 
             if cpp_function.is_destructor() {
-                let item = State::process_destructor(unnamed_function, function.allocation_place)?;
+                let item = State::process_destructor(unnamed_function, &function.allocation_place)?;
                 results.push(ProcessedFfiItem::Item(RustItem::TraitImpl(item)));
                 return Ok(results);
             }
@@ -1445,6 +1803,7 @@ impl State<'_, '_> {
         let cpp_path = cpp_item
             .path()
             .ok_or_else(|| err_msg("cpp item (function or field) expected to have a path"))?;
+        self.apply_safety_policy(cpp_path, cpp_item.as_function_ref(), &mut unnamed_function)?;
 
         if let CppFfiFunctionKind::Function = &function.kind {
             let cpp_function = cpp_item
@@ -1482,7 +1841,59 @@ impl State<'_, '_> {
             }
         }
 
-        let desired_path = self.generate_rust_path(cpp_path, NameType::ApiFunction(item))?;
+        let desired_path =
+            self.generate_rust_path(cpp_path, NameType::ApiFunction(item.clone()))?;
+        let slice_function = match self.data.config.slice_arguments_hook() {
+            Some(hook) if hook(cpp_path)? => slice_arguments_function(function, &unnamed_function)?,
+            _ => None,
+        };
+        if let Some(mut slice_function) = slice_function {
+            self.apply_safety_policy(cpp_path, cpp_item.as_function_ref(), &mut slice_function)?;
+            let mut raw_function = mem::replace(&mut unnamed_function, slice_function);
+            if !raw_function.is_unsafe {
+                raw_function.is_unsafe = true;
+                if let RustFunctionKind::FfiWrapper(data) = &mut raw_function.kind {
+                    data.safety_reason = Some(
+                        "the buffer described by the pointer and length arguments \
+                         is not checked for validity"
+                            .to_string(),
+                    );
+                }
+            }
+            // the raw variant is a free function, so `self` becomes a regular argument
+            for arg in &mut raw_function.arguments {
+                if arg.name == "self" {
+                    arg.name = "this".to_string();
+                }
+            }
+            let unsafe_module = &self.special_module_paths[&RustSpecialModuleKind::Unsafe];
+            results.push(ProcessedFfiItem::Function(FunctionWithDesiredPath {
+                function: raw_function,
+                desired_path: raw_function_path(unsafe_module, &desired_path),
+            }));
+        }
+        let rule = self
+            .data
+            .config
+            .async_function_rules()
+            .iter()
+            .find(|rule| rule.matches(cpp_path));
+        if let Some(rule) = rule {
+            match self.async_completion_function(function, &unnamed_function, rule, checks) {
+                Ok(async_function) => {
+                    let mut async_path = desired_path.clone();
+                    *async_path.last_mut() = format!("{}_async", desired_path.last());
+                    results.push(ProcessedFfiItem::Function(FunctionWithDesiredPath {
+                        function: async_function,
+                        desired_path: async_path,
+                    }));
+                }
+                Err(err) => {
+                    debug!("failed to generate async wrapper: {}", err);
+                    debug!("function: {} {:?}", item.id, function);
+                }
+            }
+        }
         results.push(ProcessedFfiItem::Function(FunctionWithDesiredPath {
             function: unnamed_function,
             desired_path,
@@ -1490,6 +1901,220 @@ impl State<'_, '_> {
         Ok(results)
     }
 
+    /// Checks whether the return value of the method `function` points into `this`
+    /// and should be returned as a reference borrowed from `self`
+    /// (see `Config::set_borrowed_return_hook`).
+    fn is_borrowed_return(&self, cpp_item: &CppItem, function: &CppFfiFunction) -> Result<bool> {
+        let hook = match self.data.config.borrowed_return_hook() {
+            Some(hook) => hook,
+            None => return Ok(false),
+        };
+        let selected = match cpp_item.path() {
+            Some(cpp_path) => hook(cpp_path)?,
+            None => None,
+        };
+        is_borrowed_return(cpp_item, function, selected)
+    }
+
+    /// Decides whether `function` generated for the C++ item `cpp_path` is `unsafe`
+    /// according to the configured safety policy and records the justification.
+    fn apply_safety_policy(
+        &self,
+        cpp_path: &CppPath,
+        cpp_function: Option<&CppFunction>,
+        function: &mut UnnamedRustFunction,
+    ) -> Result<()> {
+        let selected = if let Some(hook) = self.data.config.function_safety_hook() {
+            hook(cpp_path)?
+        } else {
+            None
+        };
+        let (is_unsafe, reason) = if let Some(safety) = selected {
+            let is_unsafe = safety == FunctionSafety::Unsafe;
+            let reason = format!(
+                "it's marked {} in the configuration of the crate",
+                if is_unsafe { "unsafe" } else { "safe" }
+            );
+            (is_unsafe, reason)
+        } else if self.data.config.safety_policy() == SafetyPolicy::UnsafeByDefault {
+            return Ok(());
+        } else {
+            let mut reasons = Vec::new();
+            for arg in &function.arguments {
+                if arg.name != "self" && is_unchecked_pointer(arg.argument_type.conversion()) {
+                    reasons.push(format!(
+                        "argument `{}` is a pointer that is not checked for validity",
+                        arg.name
+                    ));
+                } else if arg.argument_type.api_type().is_unsafe_argument() {
+                    reasons.push(format!("argument `{}` is a raw pointer", arg.name));
+                }
+            }
+            if is_unchecked_pointer(function.return_type.conversion())
+                || function.return_type.api_type().is_unsafe_argument()
+            {
+                reasons.push(
+                    "the returned pointer may be null, dangling or require manual deletion"
+                        .to_string(),
+                );
+            }
+            if cpp_function.map_or(false, transfers_ownership) {
+                reasons.push("the caller takes ownership of the returned object".to_string());
+            }
+            if let Some(class_path) = cpp_function.and_then(|f| f.class_path().ok()) {
+                let is_thread_affine = self
+                    .data
+                    .config
+                    .thread_affine_base_classes()
+                    .iter()
+                    .any(|base| inherits(&self.data.db, &class_path, base));
+                if is_thread_affine {
+                    reasons.push(format!(
+                        "`{}` objects may only be used from the thread they belong to",
+                        class_path.to_cpp_pseudo_code()
+                    ));
+                }
+            }
+            if reasons.is_empty() {
+                (
+                    false,
+                    "its arguments and return value don't contain unchecked pointers".to_string(),
+                )
+            } else {
+                (true, reasons.join("; "))
+            }
+        };
+
+        function.is_unsafe = is_unsafe;
+        if let RustFunctionKind::FfiWrapper(data) = &mut function.kind {
+            data.safety_reason = Some(reason);
+        }
+        Ok(())
+    }
+
+    /// Returns the `&LibraryHandle` argument required by constructors
+    /// (see `Config::set_lifecycle_functions`).
+    fn library_handle_argument(&self) -> Result<RustFunctionArgument> {
+        let handle_type = RustType::Common(RustCommonType {
+            path: RustPath::from_parts(vec![
+                self.data.db.crate_name().to_string(),
+                "LibraryHandle".to_string(),
+            ]),
+            generic_arguments: None,
+        });
+        Ok(RustFunctionArgument {
+            ffi_index: None,
+            argument_type: RustFinalType::new(
+                RustType::unit(),
+                RustToFfiTypeConversion::LibraryHandle {
+                    api_type: RustType::new_reference(true, handle_type),
+                },
+            )?,
+            name: "_library".to_string(),
+        })
+    }
+
+    /// Generates a variant of `wrapper` that passes a completion sender as user data
+    /// of the completion callback described by `rule` and returns the corresponding
+    /// `cpp_core::Completion`.
+    fn async_completion_function(
+        &self,
+        function: &CppFfiFunction,
+        wrapper: &UnnamedRustFunction,
+        rule: &AsyncFunctionRule,
+        checks: &CppChecks,
+    ) -> Result<UnnamedRustFunction> {
+        if function.return_type != CppFfiType::void()
+            || function
+                .arguments
+                .iter()
+                .any(|arg| arg.meaning == CppFfiArgumentMeaning::ReturnValue)
+        {
+            bail!("function starting an asynchronous operation must return void");
+        }
+        let find_argument = |name: &str| {
+            function
+                .arguments
+                .iter()
+                .position(|arg| {
+                    arg.name == name
+                        && if let CppFfiArgumentMeaning::Argument(_) = arg.meaning {
+                            true
+                        } else {
+                            false
+                        }
+                })
+                .ok_or_else(|| format_err!("argument not found: {}", name))
+        };
+        let callback_ffi_index = find_argument(&rule.callback_argument)?;
+        let data_ffi_index = find_argument(&rule.data_argument)?;
+
+        let void_ptr = CppType::new_pointer(false, CppType::Void);
+        if function.arguments[data_ffi_index]
+            .argument_type
+            .original_type()
+            != &void_ptr
+        {
+            bail!("user data argument must be void*");
+        }
+        let callback_type = function.arguments[callback_ffi_index]
+            .argument_type
+            .original_type()
+            .as_function_pointer()
+            .ok_or_else(|| err_msg("completion callback must be a function pointer"))?;
+        if *callback_type.return_type != CppType::Void || callback_type.allows_variadic_arguments {
+            bail!("completion callback must return void and have fixed arguments");
+        }
+        let callback_data_index = callback_type
+            .arguments
+            .iter()
+            .position(|arg| arg == &void_ptr)
+            .ok_or_else(|| err_msg("completion callback must accept void* user data"))?;
+        let completion_arguments = callback_type
+            .arguments
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != callback_data_index)
+            .map_if_ok(|(_, arg)| {
+                self.rust_final_type(
+                    &ffi_type(arg, CppTypeRole::NotReturnType)?,
+                    // callback arguments are produced behind FFI,
+                    // like return values
+                    &CppFfiArgumentMeaning::ReturnValue,
+                    &ReturnValueAllocationPlace::NotApplicable,
+                    Some(checks),
+                )
+            })?;
+
+        let mut async_function = wrapper.clone();
+        async_function.arguments.retain(|arg| {
+            arg.ffi_index != Some(callback_ffi_index) && arg.ffi_index != Some(data_ffi_index)
+        });
+        async_function.return_type = RustFinalType::new(
+            RustType::unit(),
+            RustToFfiTypeConversion::AsyncCompletion(Box::new(RustAsyncCompletionConversion {
+                callback_ffi_index,
+                data_ffi_index,
+                callback_data_index,
+                completion_arguments,
+            })),
+        )?;
+        Ok(async_function)
+    }
+
+    /// Returns true if `cpp_type` is a class whose objects can't be moved
+    /// after construction.
+    fn is_pinned_class(&self, cpp_type: &CppType) -> Result<bool> {
+        if let CppType::Class(path) = cpp_type {
+            let rust_item = self.find_wrapper_type(path)?;
+            Ok(rust_item.item.as_struct_ref().map_or(false, |data| {
+                data.kind == RustStructKind::WrapperType(RustWrapperTypeKind::PinnedClassWrapper)
+            }))
+        } else {
+            Ok(false)
+        }
+    }
+
     fn find_wrapper_type(&self, cpp_path: &CppPath) -> Result<DbItem<&RustItem>> {
         self.data
             .db
@@ -1512,6 +2137,16 @@ impl State<'_, '_> {
             }
         }
 
+        if let Some(depth) = self.data.config.max_module_depth() {
+            if parent_path.items().len() > depth && self.namespaces.contains(parent_path) {
+                if depth == 0 {
+                    return Ok(self.default_path_scope());
+                }
+                let ancestor = CppPath::from_items(parent_path.items()[..depth].to_vec());
+                return self.get_path_scope(&ancestor, name_type);
+            }
+        }
+
         let allow_module_for_nested;
         let allow_wrapper_type;
         match name_type {
@@ -1525,6 +2160,13 @@ impl State<'_, '_> {
             }
         };
 
+        if let Some(path) = self.dependency_namespace_modules.get(parent_path) {
+            return Ok(RustPathScope {
+                path: path.clone(),
+                prefix: None,
+            });
+        }
+
         let mut rust_items = match self
             .data
             .db
@@ -1585,7 +2227,7 @@ impl State<'_, '_> {
             let rust_type = self.rust_final_type(
                 &ffi_type(arg, CppTypeRole::NotReturnType)?,
                 &CppFfiArgumentMeaning::Argument(0),
-                ReturnValueAllocationPlace::NotApplicable,
+                &ReturnValueAllocationPlace::NotApplicable,
                 None,
             )?;
             captions.push(
@@ -1631,7 +2273,7 @@ impl State<'_, '_> {
                             let rust_type = self.rust_final_type(
                                 &ffi_type(type1, CppTypeRole::ReturnType)?,
                                 &CppFfiArgumentMeaning::ReturnValue,
-                                function.allocation_place,
+                                &function.allocation_place,
                                 None,
                             )?;
                             Some(format!(
@@ -1663,6 +2305,12 @@ impl State<'_, '_> {
                 };
                 Some(function_name)
             }
+            CppFfiFunctionKind::VariableAddress | CppFfiFunctionKind::VariableValue => {
+                let variable = cpp_item
+                    .as_variable_ref()
+                    .ok_or_else(|| err_msg("invalid source cpp item type"))?;
+                Some(variable.path.last().name.clone())
+            }
         };
 
         Ok(r)
@@ -1745,7 +2393,9 @@ impl State<'_, '_> {
                 .map_if_ok(|item| self.cpp_path_item_to_name(item, &scope.path, &name_type))?
                 .join("_"),
             NameType::ApiFunction(function) => {
-                let s = if let Some(last_name_override) =
+                let s = if let Some(name) = self.overrides.rename(&self.data.db, &function.id)? {
+                    name.to_string()
+                } else if let Some(last_name_override) =
                     self.special_function_rust_name(function.clone(), &scope.path)?
                 {
                     last_name_override
@@ -1842,7 +2492,105 @@ impl State<'_, '_> {
             CppFfiItem::QtSignalWrapper(_) => {
                 bail!("signal wrappers do not need to be processed here");
             }
+            CppFfiItem::ValueLayout(_) => {
+                bail!("value layouts do not need to be processed here");
+            }
+            CppFfiItem::FieldOffset(_) => {
+                bail!("field offsets do not need to be processed here");
+            }
+        }
+    }
+
+    /// Returns the direct access to the field that should replace the FFI call
+    /// if `item` is a field accessor or a trivial accessor method
+    /// (see `CppFunctionMemberData::trivial_field_access`) of a field
+    /// with an offset verified on all environments.
+    fn inline_field_access(
+        &self,
+        item: &DbItem<&CppFfiFunction>,
+        cpp_item: &CppItem,
+    ) -> Result<Option<RustInlineFieldAccess>> {
+        let (field_path, accessor_type) = match (&item.item.kind, cpp_item) {
+            (CppFfiFunctionKind::FieldAccessor { accessor_type }, CppItem::ClassField(field)) => {
+                (field.path.clone(), *accessor_type)
+            }
+            (CppFfiFunctionKind::Function, CppItem::Function(function)) => {
+                let access = match function
+                    .member
+                    .as_ref()
+                    .and_then(|member| member.trivial_field_access.as_ref())
+                {
+                    Some(access) => access,
+                    None => return Ok(None),
+                };
+                let field_path = function
+                    .class_path()?
+                    .join(CppPathItem::from_good_str(&access.field_name));
+                (field_path, access.accessor_type)
+            }
+            _ => return Ok(None),
+        };
+        let (offset_id, offset) = match self.field_offsets.get(&field_path) {
+            Some(offset) => offset,
+            None => return Ok(None),
+        };
+        let checks = self.data.db.cpp_checks(offset_id)?;
+        if !checks.all_success(self.data.db.environments()) {
+            trace!(
+                "field offset is not verified on all environments: {}",
+                field_path.to_cpp_pseudo_code()
+            );
+            return Ok(None);
         }
+        Ok(Some(RustInlineFieldAccess {
+            offset: *offset,
+            accessor_type,
+        }))
+    }
+
+    /// Returns the wrapper kind for a C++ union or a plain struct (see
+    /// `Config::set_plain_struct_hook`) if its layout was generated.
+    /// Returns `None` if the type should be wrapped as a class.
+    fn value_wrapper_kind(
+        &self,
+        item: DbItem<&CppTypeDeclaration>,
+    ) -> Result<Option<RustWrapperTypeKind>> {
+        let layout = self
+            .data
+            .db
+            .ffi_items()
+            .filter(|ffi_item| ffi_item.source_id.as_ref() == Some(&item.id))
+            .find_map(|ffi_item| ffi_item.filter_map(|i| i.as_value_layout_ref()));
+        let layout = match layout {
+            Some(layout) => layout,
+            None => return Ok(None),
+        };
+        let checks = self.data.db.cpp_checks(&layout.id)?;
+        if !checks.all_success(self.data.db.environments()) {
+            if !layout.item.is_union {
+                // FFI functions already pass values of this type by value
+                bail!(
+                    "plain struct layout is not verified on all environments: {}",
+                    layout.item.path.to_cpp_pseudo_code()
+                );
+            }
+            trace!(
+                "union layout is not verified on all environments: {}",
+                layout.item.path.to_cpp_pseudo_code()
+            );
+            return Ok(None);
+        }
+        let fields = layout.item.fields.iter().map_if_ok(|field| -> Result<_> {
+            Ok(RustLayoutField {
+                name: sanitize_rust_identifier(&field.name.to_snake_case(), false),
+                rust_type: self.ffi_type_to_rust_ffi_type(&field.field_type)?,
+            })
+        })?;
+        Ok(Some(if layout.item.is_union {
+            RustWrapperTypeKind::UnionWrapper { fields }
+        } else {
+            RustWrapperTypeKind::PlainStructWrapper { fields }
+        }))
     }
 
     #[allow(clippy::useless_let_if_seq)]
@@ -1906,10 +2654,19 @@ impl State<'_, '_> {
 
         let mut rust_items = Vec::new();
 
-        let is_movable = false;
+        let value_wrapper_kind = self.value_wrapper_kind(item.clone())?;
+        let is_pinned = self.data.config.is_pinned(data)?;
+        // pinned classes are never allocated in place
+        let is_in_place = self.data.config.allocation_strategy(&data.path)?
+            == AllocationStrategy::InPlace
+            && check_in_place_allocation(data, &self.data.db, self.data.config).is_ok();
+        // values are stored in Rust-managed buffers instead of `CppBox`
+        let is_movable = value_wrapper_kind.is_none() && is_in_place;
 
         let wrapper_kind;
-        if is_movable {
+        if let Some(kind) = value_wrapper_kind {
+            wrapper_kind = kind;
+        } else if is_movable {
             let internal_path = self.generate_rust_path(&data.path, NameType::SizedItem)?;
 
             if internal_path == public_path {
@@ -1933,6 +2690,8 @@ impl State<'_, '_> {
             wrapper_kind = RustWrapperTypeKind::MovableClassWrapper {
                 sized_type_path: internal_path,
             };
+        } else if is_pinned {
+            wrapper_kind = RustWrapperTypeKind::PinnedClassWrapper;
         } else {
             wrapper_kind = RustWrapperTypeKind::ImmovableClassWrapper;
         }
@@ -2018,6 +2777,12 @@ impl State<'_, '_> {
 
         match &cpp_item.item {
             CppItem::Namespace(namespace) => {
+                if let Some(depth) = self.data.config.max_module_depth() {
+                    if namespace.path.items().len() > depth {
+                        // items are placed in the ancestor's module
+                        return Ok(Vec::new());
+                    }
+                }
                 let rust_path = self.generate_rust_path(
                     &namespace.path,
                     NameType::Module {
@@ -2032,19 +2797,30 @@ impl State<'_, '_> {
                 Ok(vec![rust_item])
             }
             CppItem::Type(data) => match data.kind {
-                CppTypeDeclarationKind::Class { .. } => {
+                CppTypeDeclarationKind::Class { .. } | CppTypeDeclarationKind::Union => {
                     self.process_cpp_class(cpp_item.map(|v| v.as_type_ref().unwrap()))
                 }
-                CppTypeDeclarationKind::Enum => {
+                CppTypeDeclarationKind::Enum { is_scoped } => {
                     let rust_path = self.generate_rust_path(
                         &data.path,
                         NameType::Type {
                             is_from_other_crate: false,
                         },
                     )?;
+                    let mut representation = EnumRepresentation::default();
+                    if let Some(hook) = self.data.config.enum_representation_hook() {
+                        if let Some(value) = hook(&data.path)? {
+                            representation = value;
+                        }
+                    }
+                    let underlying_type = self.enum_underlying_type(&data.path);
                     let rust_item = RustItem::Struct(RustStruct {
                         path: rust_path,
-                        kind: RustStructKind::WrapperType(RustWrapperTypeKind::EnumWrapper),
+                        kind: RustStructKind::WrapperType(RustWrapperTypeKind::EnumWrapper {
+                            representation,
+                            is_scoped,
+                            underlying_type,
+                        }),
                         is_public: true,
                         qt_receiver_data: None,
                     });
@@ -2058,6 +2834,7 @@ impl State<'_, '_> {
                 let rust_item = RustItem::EnumValue(RustEnumValue {
                     path: rust_path,
                     value: value.value,
+                    is_alias: value.alias_of.is_some(),
                 });
 
                 Ok(vec![rust_item])
@@ -2150,13 +2927,13 @@ impl State<'_, '_> {
                     arguments: vec![RustFunctionArgument {
                         argument_type: self_type,
                         name: "self".to_string(),
-                        ffi_index: 42,
+                        ffi_index: Some(42),
                     }],
                     return_type,
                 };
                 Ok(vec![RustItem::Function(rust_function)])
             }
-            CppItem::ClassField(_) | CppItem::ClassBase(_) => {
+            CppItem::ClassField(_) | CppItem::ClassBase(_) | CppItem::Variable(_) => {
                 // only need to process FFI items
                 Ok(Vec::new())
             }
@@ -2193,17 +2970,277 @@ impl State<'_, '_> {
         Ok(())
     }
 
+    /// Returns ID and path of the public Rust type generated for the C++ type at `cpp_path`.
+    fn find_public_type(&self, cpp_path: &CppPath) -> Result<(ItemId, RustPath)> {
+        let rust_item = self
+            .data
+            .db
+            .find_rust_items_for_cpp_path(cpp_path, false)?
+            .find(|item| item.item.is_wrapper_type())
+            .ok_or_else(|| {
+                format_err!(
+                    "no Rust type generated for {}",
+                    cpp_path.to_cpp_pseudo_code()
+                )
+            })?;
+        let path = rust_item
+            .item
+            .path()
+            .ok_or_else(|| err_msg("rust type doesn't have path"))?
+            .clone();
+        Ok((rust_item.id, path))
+    }
+
+    /// Returns IDs and paths of the Rust API functions generated for the free C++ function
+    /// `cpp_path`. Overloads of the function are wrapped with separate functions.
+    fn find_free_functions(&self, cpp_path: &CppPath) -> Result<Vec<(ItemId, RustPath)>> {
+        let mut paths = Vec::new();
+        for item in self.data.db.rust_items() {
+            let function = match item.item.as_function_ref() {
+                Some(function) if function.kind.is_ffi_wrapper() => function,
+                _ => continue,
+            };
+            let is_match = self
+                .data
+                .db
+                .source_cpp_item(&item.id)?
+                .and_then(|cpp_item| cpp_item.item.as_function_ref())
+                .map_or(false, |cpp_function| {
+                    cpp_function.member.is_none() && &cpp_function.path == cpp_path
+                });
+            if is_match {
+                paths.push((item.id.clone(), function.path.clone()));
+            }
+        }
+        if paths.is_empty() {
+            bail!(
+                "no Rust type or function generated for {}",
+                cpp_path.to_cpp_pseudo_code()
+            );
+        }
+        Ok(paths)
+    }
+
+    /// Adds a re-export of `target` at `path`. The re-export has the same
+    /// availability condition as the item `source_id`.
+    fn add_layout_reexport(
+        &mut self,
+        source_id: Option<ItemId>,
+        path: RustPath,
+        target: RustPath,
+    ) -> Result<()> {
+        if self
+            .data
+            .db
+            .rust_items()
+            .any(|item| item.item.path() == Some(&path))
+        {
+            bail!("name conflict: {} already exists", path.full_name(None));
+        }
+        let rust_item = RustItem::Reexport(RustReexport {
+            path,
+            target,
+            source: RustReexportSource::CrateLayout,
+        });
+        self.add_rust_item(source_id, rust_item)?;
+        Ok(())
+    }
+
+    /// Generates re-exports configured with `Config::add_prelude_type`
+    /// and `Config::add_root_reexport`.
+    fn generate_layout_reexports(&mut self) -> Result<()> {
+        let prelude_types = self.data.config.prelude_types().to_vec();
+        if !prelude_types.is_empty() {
+            let prelude_path = self.special_module_paths[&RustSpecialModuleKind::Prelude].clone();
+            for &name in CPP_CORE_PRELUDE_ITEMS {
+                let target = RustPath::from_parts(vec!["cpp_core".to_string(), name.to_string()]);
+                self.add_layout_reexport(None, prelude_path.join(name), target)?;
+            }
+            for cpp_path in &prelude_types {
+                let result = self.find_public_type(cpp_path).and_then(|(id, target)| {
+                    self.add_layout_reexport(Some(id), prelude_path.join(target.last()), target)
+                });
+                if let Err(err) = result {
+                    warn!(
+                        "failed to add {} to prelude: {}",
+                        cpp_path.to_cpp_pseudo_code(),
+                        err
+                    );
+                }
+            }
+        }
+
+        let crate_root_path = self.special_module_paths[&RustSpecialModuleKind::CrateRoot].clone();
+        for cpp_path in &self.data.config.root_reexports().to_vec() {
+            let result = self
+                .find_public_type(cpp_path)
+                .map(|target| vec![target])
+                .or_else(|_| self.find_free_functions(cpp_path))
+                .and_then(|targets| {
+                    for (id, target) in targets {
+                        if target.parent()? == crate_root_path {
+                            // already in the crate root
+                            continue;
+                        }
+                        let path = crate_root_path.join(target.last());
+                        self.add_layout_reexport(Some(id), path, target)?;
+                    }
+                    Ok(())
+                });
+            if let Err(err) = result {
+                warn!(
+                    "failed to re-export {} at crate root: {}",
+                    cpp_path.to_cpp_pseudo_code(),
+                    err
+                );
+            }
+        }
+        Ok(())
+    }
+
     fn add_rust_item(
         &mut self,
         source_id: Option<ItemId>,
         mut item: RustItem,
     ) -> Result<Option<ItemId>> {
+        if let Some(source_id) = &source_id {
+            if !self.overrides.apply(&self.data.db, source_id, &mut item)? {
+                return Ok(None);
+            }
+        }
         if let Some(hook) = self.data.config.rust_item_hook() {
             hook(&mut item, &self.data)?;
         }
         self.data.db.add_rust_item(source_id, item)
     }
 
+    /// Adds modules mirroring the namespaces of free functions of the current crate
+    /// that don't have a namespace item in the current crate (e.g. the namespace
+    /// is only declared in a dependency or was rejected by
+    /// `Config::set_cpp_item_filter_hook`). Without a module, such functions
+    /// couldn't be placed.
+    fn generate_dependency_namespace_modules(&mut self) -> Result<()> {
+        let own_namespaces = self
+            .data
+            .db
+            .cpp_items()
+            .filter_map(|item| item.item.as_namespace_ref())
+            .map(|namespace| namespace.path.clone())
+            .collect::<HashSet<_>>();
+        let max_depth = self.data.config.max_module_depth();
+        let mut paths = HashSet::new();
+        for item in self.data.db.cpp_items() {
+            let function = match item.item.as_function_ref() {
+                Some(function) if function.member.is_none() && !function.is_operator() => function,
+                _ => continue,
+            };
+            let mut parent = function.path.parent().ok();
+            while let Some(path) = parent {
+                // free functions can only be declared in namespaces
+                self.namespaces.insert(path.clone());
+                let is_missing = !own_namespaces.contains(&path)
+                    // deeper namespaces are placed in the ancestor's module
+                    && max_depth.map_or(true, |depth| path.items().len() <= depth);
+                parent = path.parent().ok();
+                if is_missing {
+                    paths.insert(path);
+                }
+            }
+        }
+
+        // parent modules must be added before their children
+        let paths = paths
+            .into_iter()
+            .sorted_by_key(|path| (path.items().len(), path.to_cpp_pseudo_code()));
+        for path in paths {
+            let rust_path = self.generate_rust_path(
+                &path,
+                NameType::Module {
+                    is_from_other_crate: true,
+                },
+            )?;
+            let rust_item = RustItem::Module(RustModule {
+                is_public: true,
+                path: rust_path.clone(),
+                kind: RustModuleKind::CppNamespace,
+            });
+            if self.add_rust_item(None, rust_item)?.is_some() {
+                self.dependency_namespace_modules.insert(path, rust_path);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds newtypes of the handle types and their owning guards
+    /// (see `Config::add_handle_type`). Newtypes generated by a dependency are reused.
+    /// The items are added before processing C++ items, so that other items
+    /// can't take their names.
+    fn generate_handle_types(&mut self) -> Result<()> {
+        let config = self.data.config;
+        if config.handle_types().is_empty() {
+            return Ok(());
+        }
+        let numeric_types = handle_numeric_types(
+            self.data.db.cpp_items().map(|item| item.item),
+            config.handle_types(),
+        );
+        let crate_root = self.special_module_paths[&RustSpecialModuleKind::CrateRoot].clone();
+        for handle_type in config.handle_types() {
+            if let Some(item) = self.data.db.find_handle_type(&handle_type.typedef) {
+                let path = item.item.path.clone();
+                self.handle_types.insert(handle_type.typedef.clone(), path);
+                continue;
+            }
+            let numeric_type = match numeric_types.get(&handle_type.typedef) {
+                Some(numeric_type) => numeric_type,
+                // the handle type is not used by any function
+                None => continue,
+            };
+            let raw_type = match numeric_type.kind {
+                CppSpecificNumericTypeKind::Integer { is_signed } => {
+                    format!("{}{}", if is_signed { "i" } else { "u" }, numeric_type.bits)
+                }
+                CppSpecificNumericTypeKind::FloatingPoint => {
+                    bail!("handle type must be an integer: {:?}", numeric_type);
+                }
+            };
+
+            let path = crate_root.join(handle_type.rust_name.clone());
+            let mut items = vec![RustItem::Struct(RustStruct {
+                path: path.clone(),
+                kind: RustStructKind::HandleType(RustHandleType {
+                    cpp_path: handle_type.typedef.clone(),
+                    raw_type,
+                }),
+                is_public: true,
+                qt_receiver_data: None,
+            })];
+            if let Some(release) = &handle_type.release {
+                items.push(RustItem::Struct(RustStruct {
+                    path: crate_root.join(format!("Owned{}", handle_type.rust_name)),
+                    kind: RustStructKind::OwnedHandleType(RustOwnedHandleType {
+                        handle_path: path.clone(),
+                        release: release.clone(),
+                    }),
+                    is_public: true,
+                    qt_receiver_data: None,
+                }));
+            }
+            for item in items {
+                let item_path = item.path().expect("struct must have path");
+                if self.data.db.find_rust_item(item_path).is_some() {
+                    bail!(
+                        "name of handle type is already taken: {}",
+                        item_path.full_name(None)
+                    );
+                }
+                self.add_rust_item(None, item)?;
+            }
+            self.handle_types.insert(handle_type.typedef.clone(), path);
+        }
+        Ok(())
+    }
+
     fn generate_special_module(&mut self, kind: RustSpecialModuleKind) -> Result<()> {
         let crate_name = self.data.config.crate_properties().name().to_string();
         let rust_path_parts = match kind {
@@ -2211,12 +3248,17 @@ impl State<'_, '_> {
             RustSpecialModuleKind::Ffi => vec![crate_name, "__ffi".to_string()],
             RustSpecialModuleKind::Ops => vec![crate_name, "ops".to_string()],
             RustSpecialModuleKind::SizedTypes => vec![crate_name, "__sized_types".to_string()],
+            RustSpecialModuleKind::Prelude => vec![crate_name, "prelude".to_string()],
+            RustSpecialModuleKind::Unsafe => vec![crate_name, "unsafe_".to_string()],
         };
         let rust_path = RustPath::from_parts(rust_path_parts);
 
         let rust_item = RustItem::Module(RustModule {
             is_public: match kind {
-                RustSpecialModuleKind::CrateRoot | RustSpecialModuleKind::Ops => true,
+                RustSpecialModuleKind::CrateRoot
+                | RustSpecialModuleKind::Ops
+                | RustSpecialModuleKind::Prelude
+                | RustSpecialModuleKind::Unsafe => true,
                 RustSpecialModuleKind::Ffi | RustSpecialModuleKind::SizedTypes => false,
             },
             path: rust_path.clone(),
@@ -2268,8 +3310,9 @@ impl State<'_, '_> {
             let cpp_item = self.data.db.cpp_item(&cpp_item_id)?;
             if let Err(err) = self.process_cpp_item(cpp_item.clone()) {
                 debug!(
-                    "failed to process cpp item: {} {}: {}",
-                    cpp_item.id, &cpp_item.item, err
+                    "failed to process cpp item {}: {}",
+                    self.data.db.item_context(&cpp_item.id),
+                    err
                 );
                 print_trace(&err, Some(log::Level::Trace));
             }
@@ -2322,9 +3365,8 @@ impl State<'_, '_> {
                 }
                 Err(err) => {
                     debug!(
-                        "failed to process ffi item: {} {}: {}",
-                        ffi_item.id,
-                        ffi_item.item.short_text(),
+                        "failed to process ffi item {}: {}",
+                        self.data.db.item_context(&ffi_item.id),
                         err
                     );
                     print_trace(&err, Some(log::Level::Trace));
@@ -2336,13 +3378,14 @@ impl State<'_, '_> {
 
     fn try_caption_strategy(
         &self,
-        functions: &[ItemWithSource<FunctionWithDesiredPath>],
+        functions: &[&FunctionWithDesiredPath],
         strategy: &RustFunctionCaptionStrategy,
+        reserved_path: Option<&RustPath>,
     ) -> Result<()> {
         let mut paths = BTreeSet::new();
         for function in functions {
-            let path = function.item.apply_strategy(strategy)?;
-            if paths.contains(&path) {
+            let path = function.apply_strategy(strategy)?;
+            if paths.contains(&path) || reserved_path == Some(&path) {
                 bail!("conflicting path: {:?}", path);
             }
             if self.data.db.find_rust_item(&path).is_some() {
@@ -2354,21 +3397,187 @@ impl State<'_, '_> {
         Ok(())
     }
 
+    /// Returns naming strategy selected for the overloads of the source C++ function
+    /// of `function` and the text of the C++ function.
+    fn overload_info(
+        &self,
+        function: &ItemWithSource<FunctionWithDesiredPath>,
+    ) -> Result<(Option<OverloadStrategy>, String)> {
+        let cpp_item = self
+            .data
+            .db
+            .source_cpp_item(&function.source_id)?
+            .ok_or_else(|| err_msg("source cpp item not found"))?;
+        let strategy = match (
+            self.data.config.overload_strategy_hook(),
+            cpp_item.item.path(),
+        ) {
+            (Some(hook), Some(path)) => hook(path)?,
+            _ => None,
+        };
+        Ok((strategy, cpp_item.item.to_string()))
+    }
+
+    /// Returns path of the trait used by the generic method `method_path` taking a tuple
+    /// of arguments of `functions` (see `OverloadStrategy::ArgsTuple`).
+    /// Returns an error if the overloads can't be called through such a method.
+    fn args_tuple_trait_path(
+        &self,
+        method_path: &RustPath,
+        functions: &[&FunctionWithDesiredPath],
+    ) -> Result<RustPath> {
+        if self.data.db.find_rust_item(method_path).is_some() {
+            bail!("path already taken by an existing item: {:?}", method_path);
+        }
+        let self_arg_kinds = functions
+            .iter()
+            .map_if_ok(|function| function.function.self_arg_kind())?;
+        if self_arg_kinds.iter().any(|kind| kind != &self_arg_kinds[0]) {
+            bail!("overloads have different kinds of self argument");
+        }
+        let mut tuples = Vec::new();
+        for function in functions {
+            if !function.function.kind.is_ffi_wrapper() {
+                bail!(
+                    "unsupported function kind: {}",
+                    function.function.kind.short_text()
+                );
+            }
+            let return_type = function.function.return_type.api_type();
+            if contains_reference(return_type) || contains_generic_type(return_type) {
+                bail!(
+                    "unsupported return type: {}",
+                    rust_type_to_code(return_type, None)
+                );
+            }
+            let mut types = Vec::new();
+            for arg in &function.function.arguments {
+                match arg.argument_type.conversion() {
+                    RustToFfiTypeConversion::ClosureToCallback(_)
+                    | RustToFfiTypeConversion::LibraryHandle { .. } => {
+                        bail!("unsupported type of argument {}", arg.name);
+                    }
+                    _ => {}
+                }
+                if arg.name == "self" {
+                    if arg.argument_type.api_type().lifetime().is_some() {
+                        bail!("self argument with a lifetime is not supported");
+                    }
+                } else {
+                    types.push(arg.argument_type.api_type().clone());
+                }
+            }
+            tuples.push(types);
+        }
+        for (index, tuple) in tuples.iter().enumerate() {
+            for other in &tuples[index + 1..] {
+                if args_tuples_overlap(tuple, other) {
+                    let text = |types: &[RustType]| {
+                        types.iter().map(|t| rust_type_to_code(t, None)).join(", ")
+                    };
+                    bail!(
+                        "overloads taking ({}) and ({}) can't be distinguished by argument types",
+                        text(tuple),
+                        text(other)
+                    );
+                }
+            }
+        }
+
+        let parent = method_path.parent()?;
+        let trait_name = format!("{}Args", method_path.last().to_class_case());
+        let trait_path =
+            match self.data.db.find_rust_item(&parent) {
+                Some(item) if item.item.as_struct_ref().is_some() => parent
+                    .parent()?
+                    .join(format!("{}{}", parent.last(), trait_name)),
+                _ => parent.join(trait_name),
+            };
+        if self.data.db.find_rust_item(&trait_path).is_some() {
+            bail!("path already taken by an existing item: {:?}", trait_path);
+        }
+        Ok(trait_path)
+    }
+
     fn finalize_functions(
         &mut self,
         grouped_functions: BTreeMap<RustPath, Vec<ItemWithSource<FunctionWithDesiredPath>>>,
     ) -> Result<()> {
         let all_strategies = RustFunctionCaptionStrategy::all();
+        let crate_name = self.data.config.crate_properties().name().to_string();
+        let numbers_path = overload_numbers_path(&self.data.workspace, &crate_name);
+        let mut all_numbers: OverloadNumbers = if numbers_path.exists() {
+            load_json(&numbers_path)?
+        } else {
+            OverloadNumbers::new()
+        };
+        let mut diagnostics = Vec::new();
+
+        for (group_path, functions) in grouped_functions {
+            // Sort the overloads by signature so that the names don't depend
+            // on the order of items in the database.
+            let mut functions = functions.into_iter().map_if_ok(|function| -> Result<_> {
+                let (strategy, cpp_text) = self.overload_info(&function)?;
+                let args_count = function
+                    .item
+                    .function
+                    .arguments
+                    .iter()
+                    .filter(|arg| arg.name != "self")
+                    .count();
+                Ok((args_count, cpp_text, strategy, function))
+            })?;
+            functions.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+
+            let mut problems = Vec::new();
+            let strategies = functions.iter().map(|f| f.2).unique().collect_vec();
+            let mut overload_strategy = if strategies.len() == 1 {
+                strategies[0].unwrap_or(OverloadStrategy::Caption)
+            } else {
+                problems.push(format!(
+                    "overload strategy hook selected different strategies: {:?}",
+                    strategies
+                ));
+                OverloadStrategy::Caption
+            };
+
+            let items = functions.iter().map(|f| &f.3.item).collect_vec();
+            let mut args_tuple_trait = None;
+            if overload_strategy == OverloadStrategy::ArgsTuple {
+                if functions.len() > 1 {
+                    match self.args_tuple_trait_path(&group_path, &items) {
+                        Ok(path) => args_tuple_trait = Some(path),
+                        Err(err) => problems.push(format!(
+                            "can't generate a method taking a tuple of arguments: {}",
+                            err
+                        )),
+                    }
+                }
+                overload_strategy = OverloadStrategy::Caption;
+            }
 
-        for (_group_path, functions) in grouped_functions {
             let mut chosen_strategy = None;
-            if functions.len() > 1 {
+            let mut has_collision = false;
+            // `T& thing()` and `const T& thing() const` become `thing_mut` and `thing`
+            // regardless of the selected overload strategy
+            let is_constness_group = args_tuple_trait.is_none()
+                && is_constness_overload_group(&items.iter().map(|f| &f.function).collect_vec())?;
+            if is_constness_group
+                || (functions.len() > 1 && overload_strategy == OverloadStrategy::Caption)
+            {
                 trace!("choosing caption strategy for:");
                 for function in &functions {
-                    trace!("* {}", function.item.function.kind.short_text());
+                    trace!("* {}", function.3.item.function.kind.short_text());
                 }
-                for strategy in &all_strategies {
-                    match self.try_caption_strategy(&functions, strategy) {
+                let strategies = all_strategies
+                    .iter()
+                    .filter(|strategy| !is_constness_group || strategy.mut_);
+                for strategy in strategies {
+                    match self.try_caption_strategy(
+                        &items,
+                        strategy,
+                        args_tuple_trait.as_ref().map(|_| &group_path),
+                    ) {
                         Ok(_) => {
                             trace!("  chosen strategy: {:?}", strategy);
                             chosen_strategy = Some(strategy.clone());
@@ -2381,8 +3590,16 @@ impl State<'_, '_> {
                 }
                 if chosen_strategy.is_none() {
                     trace!("  all strategies failed, using default strategy");
+                    has_collision = true;
+                    if args_tuple_trait.take().is_some() {
+                        problems.push(
+                            "can't generate a method taking a tuple of arguments: \
+                             overloads can't be named"
+                                .to_string(),
+                        );
+                    }
                     chosen_strategy = Some(RustFunctionCaptionStrategy {
-                        mut_: false,
+                        mut_: is_constness_group,
                         args_count: false,
                         arg_names: false,
                         arg_types: Some(RustTypeCaptionStrategy::LastName),
@@ -2391,25 +3608,332 @@ impl State<'_, '_> {
                 }
             }
 
-            for function in functions {
+            let numbers =
+                if chosen_strategy.is_none() && overload_strategy == OverloadStrategy::Numbered {
+                    let keys = overload_keys(functions.iter().map(|f| f.1.as_str()));
+                    let numbers = all_numbers
+                        .entry(group_path.full_name(None))
+                        .or_insert_with(Vec::new);
+                    assign_overload_numbers(numbers, &keys)
+                } else {
+                    Vec::new()
+                };
+
+            let self_arg_kind = match items.first() {
+                Some(function) => function.function.self_arg_kind()?,
+                None => RustFunctionSelfArgKind::None,
+            };
+            let mut assigned_names = Vec::new();
+            let mut added_paths = Vec::new();
+            let mut is_unsafe = false;
+            let mut first_source_id = None;
+            for (index, (_, cpp_text, _, function)) in functions.into_iter().enumerate() {
                 let path = if let Some(strategy) = &chosen_strategy {
                     function.item.apply_strategy(strategy).unwrap()
+                } else if let Some(&number) = numbers.get(index) {
+                    numbered_path(&function.item.desired_path, number)
                 } else {
                     function.item.desired_path
                 };
                 let final_path = self.data.db.make_unique_rust_path(&path);
+                if final_path != path {
+                    has_collision = true;
+                }
+                assigned_names.push((cpp_text, final_path.last().to_string()));
+                if first_source_id.is_none() {
+                    first_source_id = Some(function.source_id.clone());
+                }
                 let item = RustItem::Function(function.item.function.with_path(final_path));
-                self.add_rust_item(Some(function.source_id), item)?;
+                if let Some(id) = self.add_rust_item(Some(function.source_id), item)? {
+                    let item = self.data.db.rust_item(&id)?;
+                    let function = item
+                        .item
+                        .as_function_ref()
+                        .ok_or_else(|| err_msg("rust item hook changed kind of the function"))?;
+                    is_unsafe = is_unsafe || function.is_unsafe;
+                    added_paths.push(function.path.clone());
+                }
+            }
+
+            if let Some(trait_path) = args_tuple_trait {
+                let item = RustItem::ExtraImpl(RustExtraImpl {
+                    parent_path: trait_path.parent()?,
+                    kind: RustExtraImplKind::OverloadTrait(RustOverloadTrait {
+                        trait_path,
+                        method_path: group_path.clone(),
+                        self_arg_kind,
+                        is_unsafe,
+                        overloads: added_paths,
+                    }),
+                });
+                self.add_rust_item(first_source_id, item)?;
+            }
+
+            if has_collision {
+                problems.push("names were made unique with fallback suffixes".to_string());
+            }
+            if !problems.is_empty() {
+                diagnostics.push(OverloadDiagnostic {
+                    path: group_path.full_name(None),
+                    functions: assigned_names,
+                    problems,
+                });
             }
         }
-        Ok(())
+
+        if !all_numbers.is_empty() {
+            create_dir_all(self.data.workspace.lock_path())?;
+            save_json(&numbers_path, &all_numbers, None)?;
+        }
+        self.write_overload_report(&diagnostics)
+    }
+
+    /// Writes the report of groups of overloads that couldn't be named
+    /// as requested by the overload strategy.
+    fn write_overload_report(&self, diagnostics: &[OverloadDiagnostic]) -> Result<()> {
+        let crate_name = self.data.config.crate_properties().name();
+        let report_dir = self
+            .data
+            .workspace
+            .log_path()
+            .join(format!("rust_generator_{}", crate_name));
+        if diagnostics.is_empty() {
+            if report_dir.exists() {
+                remove_dir_all(&report_dir)?;
+            }
+            return Ok(());
+        }
+        warn!(
+            "{} groups of overloads couldn't be named as requested (see {})",
+            diagnostics.len(),
+            report_dir.join("index.html").display()
+        );
+        create_dir_all(&report_dir)?;
+        let title = format!("Overload naming problems: {}", crate_name);
+        let templates = HtmlTemplates::load(
+            self.data
+                .config
+                .html_report_theme_path()
+                .map(PathBuf::as_path),
+        )?;
+        let mut logger = HtmlLogger::new(report_dir.join("index.html"), &title, templates)?;
+        logger.add_header(&["Rust path", "Overloads", "Problems"])?;
+        for diagnostic in diagnostics {
+            let functions = diagnostic
+                .functions
+                .iter()
+                .map(|(cpp_text, name)| {
+                    format!(
+                        "<code>{}</code>: <code>{}</code>",
+                        escape_html(name),
+                        escape_html(cpp_text)
+                    )
+                })
+                .join("<br>");
+            let problems = diagnostic
+                .problems
+                .iter()
+                .map(|problem| escape_html(problem))
+                .join("<br>");
+            logger.add_item(
+                &anchor(&diagnostic.path),
+                Severity::Warning,
+                &[
+                    &format!("<code>{}</code>", escape_html(&diagnostic.path)),
+                    &functions,
+                    &problems,
+                ],
+            )?;
+        }
+        logger.finish()
+    }
+}
+
+/// Checks whether the return value of the method `function` can be returned
+/// as a reference borrowed from `self`. `selected` is the value returned by
+/// the hook set with `Config::set_borrowed_return_hook`. If it's `None`,
+/// a returned C++ reference is assumed to point into `this` if no other argument
+/// is a pointer or a reference.
+///
+/// A mutable reference can only be borrowed from `&mut self`, so non-const
+/// references returned by const methods are never borrowed.
+pub fn is_borrowed_return(
+    cpp_item: &CppItem,
+    function: &CppFfiFunction,
+    selected: Option<bool>,
+) -> Result<bool> {
+    if cpp_item
+        .as_function_ref()
+        .map_or(false, |f| f.is_operator())
+    {
+        // references can't be used in associated types of operator traits
+        return Ok(false);
+    }
+    let this_arg = match function
+        .arguments
+        .iter()
+        .find(|arg| arg.meaning == CppFfiArgumentMeaning::This)
+    {
+        Some(arg) => arg,
+        None => return Ok(false),
+    };
+    if function
+        .arguments
+        .iter()
+        .any(|arg| arg.meaning == CppFfiArgumentMeaning::ReturnValue)
+    {
+        // returned by value
+        return Ok(false);
+    }
+    let return_type = &function.return_type;
+    let is_reference = match return_type.original_type() {
+        CppType::PointerLike { kind, target, .. } => match kind {
+            CppPointerLikeTypeKind::Reference => true,
+            CppPointerLikeTypeKind::Pointer if !target.is_void() => false,
+            _ => return Ok(false),
+        },
+        _ => return Ok(false),
+    };
+    if !return_type.ffi_type().pointer_like_is_const()?
+        && this_arg.argument_type.ffi_type().pointer_like_is_const()?
+    {
+        return Ok(false);
+    }
+
+    if let Some(value) = selected {
+        return Ok(value);
+    }
+    // If no other argument is a pointer or a reference, the returned reference
+    // can only point into `this` or into a static object.
+    Ok(is_reference
+        && function.arguments.iter().all(|arg| {
+            arg.meaning == CppFfiArgumentMeaning::This
+                || match arg.argument_type.original_type() {
+                    CppType::PointerLike { .. } => false,
+                    _ => true,
+                }
+        }))
+}
+
+/// Returns a copy of `wrapper` with `(const T* data, size_t len)` argument pairs
+/// replaced by `&[T]` (and `(T* data, size_t len)` pairs by `&mut [T]`),
+/// or `None` if `function` doesn't have such pairs
+/// (see `Config::set_slice_arguments_hook`).
+pub fn slice_arguments_function(
+    function: &CppFfiFunction,
+    wrapper: &UnnamedRustFunction,
+) -> Result<Option<UnnamedRustFunction>> {
+    let is_slice_pair = |data: &RustFunctionArgument, len: &RustFunctionArgument| {
+        let data_ffi_index = match data.ffi_index {
+            Some(index) => index,
+            None => return false,
+        };
+        let cpp_data = &function.arguments[data_ffi_index];
+        let is_numeric_pointer = match data.argument_type.ffi_type() {
+            RustType::PointerLike {
+                kind: RustPointerLikeTypeKind::Pointer,
+                target,
+                ..
+            } => match &**target {
+                RustType::Primitive(_) => true,
+                RustType::Common(target) => {
+                    target.generic_arguments.is_none()
+                        && target
+                            .path
+                            .parent_parts()
+                            .map_or(false, |parts| *parts == ["std", "os", "raw"])
+                        && target.path.last() != "c_void"
+                }
+                _ => false,
+            },
+            _ => false,
+        };
+        is_numeric_pointer
+            && len.ffi_index == Some(data_ffi_index + 1)
+            && cpp_data.meaning != CppFfiArgumentMeaning::This
+            && cpp_data.argument_type.conversion() == &CppToFfiTypeConversion::NoChange
+            && data.argument_type.conversion() == &RustToFfiTypeConversion::None
+            && len.argument_type.ffi_type() == &RustType::Primitive("usize".into())
+            && len.argument_type.conversion() == &RustToFfiTypeConversion::None
+    };
+
+    let mut result = wrapper.clone();
+    let mut found = false;
+    let mut index = 0;
+    while index + 1 < result.arguments.len() {
+        if is_slice_pair(&result.arguments[index], &result.arguments[index + 1]) {
+            let len = result.arguments.remove(index + 1);
+            let len_ffi_index = len
+                .ffi_index
+                .ok_or_else(|| err_msg("slice length must be passed to FFI"))?;
+            let data = &mut result.arguments[index];
+            data.argument_type = RustFinalType::new(
+                data.argument_type.ffi_type().clone(),
+                RustToFfiTypeConversion::SliceToPtr { len_ffi_index },
+            )?;
+            found = true;
+        }
+        index += 1;
+    }
+    if found {
+        Ok(Some(result))
+    } else {
+        Ok(None)
     }
 }
 
+/// Returns the path of the raw variant of the function at `desired_path`
+/// in the `unsafe_` module (see `Config::set_slice_arguments_hook`).
+/// For example, the raw variant of `foo::ns::Buffer::write` is `foo::unsafe_::ns_buffer_write`.
+pub fn raw_function_path(unsafe_module: &RustPath, desired_path: &RustPath) -> RustPath {
+    let name = desired_path.parts[1..]
+        .iter()
+        .map(|part| part.to_snake_case())
+        .join("_");
+    unsafe_module.join(name)
+}
+
+/// Returns classes that have converting constructors. `IntoArg` accepts
+/// references to derived classes, so derived classes don't need to be checked.
+fn into_arg_classes(data: &ProcessorData<'_>) -> HashSet<CppPath> {
+    data.db
+        .all_cpp_items()
+        .filter_map(|item| item.item.as_function_ref())
+        .filter(|function| function.is_converting_constructor())
+        .filter_map(|function| function.class_path().ok())
+        .collect()
+}
+
 pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
+    let into_arg_classes = into_arg_classes(data);
+    let overrides = if let Some(path) = data.config.overrides_path() {
+        let overrides = Overrides::load(path)?;
+        overrides.validate(&data.db);
+        overrides
+    } else {
+        Overrides::default()
+    };
+    let namespaces = data
+        .db
+        .cpp_items()
+        .filter_map(|item| item.item.as_namespace_ref())
+        .map(|namespace| namespace.path.clone())
+        .collect();
+    let field_offsets = data
+        .db
+        .ffi_items()
+        .filter_map(|item| item.filter_map(|i| i.as_field_offset_ref()))
+        .map(|item| (item.item.path.clone(), (item.id, item.item.offset)))
+        .collect();
     let mut state = State {
         data,
         special_module_paths: HashMap::new(),
+        into_arg_classes,
+        overrides,
+        namespaces,
+        dependency_namespace_modules: HashMap::new(),
+        field_offsets,
+        handle_types: HashMap::new(),
     };
     for &module in &[
         RustSpecialModuleKind::CrateRoot,
@@ -2419,6 +3943,12 @@ pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
     ] {
         state.generate_special_module(module)?;
     }
+    if !state.data.config.prelude_types().is_empty() {
+        state.generate_special_module(RustSpecialModuleKind::Prelude)?;
+    }
+    if state.data.config.slice_arguments_hook().is_some() {
+        state.generate_special_module(RustSpecialModuleKind::Unsafe)?;
+    }
 
     state.generate_crate_reexport("cpp_core")?;
     let dependencies = state
@@ -2432,13 +3962,86 @@ pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
         state.generate_crate_reexport(dependency.name())?;
     }
 
+    state.generate_handle_types()?;
     state.process_cpp_items()?;
+    state.generate_dependency_namespace_modules()?;
     let grouped_functions = state.process_ffi_items()?;
     state.finalize_functions(grouped_functions)?;
+    state.generate_layout_reexports()?;
 
     Ok(())
 }
 
+/// Returns the numeric types of the handle typedefs used in `items`
+/// (see `Config::add_handle_type`).
+pub fn handle_numeric_types<'a>(
+    items: impl Iterator<Item = &'a CppItem>,
+    handle_types: &[HandleType],
+) -> HashMap<CppPath, CppSpecificNumericType> {
+    fn collect(
+        cpp_type: &CppType,
+        handle_types: &[HandleType],
+        output: &mut HashMap<CppPath, CppSpecificNumericType>,
+    ) {
+        match cpp_type {
+            CppType::SpecificNumeric(numeric_type) => {
+                if handle_types
+                    .iter()
+                    .any(|handle| handle.typedef == numeric_type.path)
+                {
+                    output
+                        .entry(numeric_type.path.clone())
+                        .or_insert_with(|| numeric_type.clone());
+                }
+            }
+            CppType::PointerLike { target, .. } => collect(target, handle_types, output),
+            CppType::Array { element_type, .. } => collect(element_type, handle_types, output),
+            _ => {}
+        }
+    }
+
+    let mut output = HashMap::new();
+    for item in items {
+        match item {
+            CppItem::Function(function) => {
+                collect(&function.return_type, handle_types, &mut output);
+                for arg in &function.arguments {
+                    collect(&arg.argument_type, handle_types, &mut output);
+                }
+            }
+            CppItem::ClassField(field) => collect(&field.field_type, handle_types, &mut output),
+            _ => {}
+        }
+    }
+    output
+}
+
+/// Returns true if `function` is likely to transfer ownership of the returned object
+/// to the caller (e.g. `QLayout::takeAt` or `std::unique_ptr::release`).
+pub fn transfers_ownership(function: &CppFunction) -> bool {
+    let name = &function.path.last().name;
+    let is_take_name = name.starts_with("take")
+        && name[4..]
+            .chars()
+            .next()
+            .map_or(true, |c| c.is_ascii_uppercase());
+    (is_take_name || name == "release") && function.return_type.is_pointer()
+}
+
+/// Returns true if `conversion` produces a pointer type that is not checked
+/// for validity (e.g. `Ptr<T>` or `Ref<T>`).
+fn is_unchecked_pointer(conversion: &RustToFfiTypeConversion) -> bool {
+    match conversion {
+        RustToFfiTypeConversion::UtilsPtrToPtr {}
+        | RustToFfiTypeConversion::UtilsRefToPtr {}
+        | RustToFfiTypeConversion::OptionUtilsRefToPtr {}
+        | RustToFfiTypeConversion::ImplCastInto(_)
+        | RustToFfiTypeConversion::ImplIntoArg => true,
+        RustToFfiTypeConversion::RefTo(conversion) => is_unchecked_pointer(conversion),
+        _ => false,
+    }
+}
+
 fn detect_callback_function(function: &UnnamedRustFunction) -> Option<&RustFunctionPointerType> {
     if function.arguments.len() < 3 {
         return None;
@@ -2494,6 +4097,41 @@ fn detect_callback_function(function: &UnnamedRustFunction) -> Option<&RustFunct
     Some(callback_type)
 }
 
+/// Returns true if `functions` consist of pairs of a const and a non-const overload
+/// of a method with the same arguments.
+pub fn is_constness_overload_group(functions: &[&UnnamedRustFunction]) -> Result<bool> {
+    if functions.is_empty() || functions.len() % 2 != 0 {
+        return Ok(false);
+    }
+    let arg_types = |function: &UnnamedRustFunction| {
+        function
+            .arguments
+            .iter()
+            .filter(|arg| arg.name != "self")
+            .map(|arg| arg.argument_type.api_type().clone())
+            .collect_vec()
+    };
+    for &function in functions {
+        let counterpart_kind = match function.self_arg_kind()? {
+            RustFunctionSelfArgKind::ConstRef => RustFunctionSelfArgKind::MutRef,
+            RustFunctionSelfArgKind::MutRef => RustFunctionSelfArgKind::ConstRef,
+            _ => return Ok(false),
+        };
+        let args = arg_types(function);
+        let mut has_counterpart = false;
+        for &other in functions {
+            if other.self_arg_kind()? == counterpart_kind && arg_types(other) == args {
+                has_counterpart = true;
+                break;
+            }
+        }
+        if !has_counterpart {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
 impl FunctionWithDesiredPath {
     fn apply_strategy(&self, strategy: &RustFunctionCaptionStrategy) -> Result<RustPath> {
         let mut suffix = String::new();
@@ -2501,7 +4139,16 @@ impl FunctionWithDesiredPath {
             .function
             .arguments
             .iter()
-            .filter(|arg| arg.name != "self")
+            .filter(|arg| {
+                arg.name != "self"
+                    && if let RustToFfiTypeConversion::LibraryHandle { .. } =
+                        arg.argument_type.conversion()
+                    {
+                        false
+                    } else {
+                        true
+                    }
+            })
             .collect_vec();
         if strategy.args_count {
             suffix.push_str(&format!("_{}a", normal_args.len()));
@@ -1,7 +1,7 @@
 //! Types holding information about generates Rust API.
 
 use crate::cpp_data::CppPath;
-use crate::cpp_ffi_data::CppFfiFunction;
+use crate::cpp_ffi_data::{CppFfiFunction, CppFieldAccessorType};
 use crate::cpp_type::CppType;
 use crate::database::DbItem;
 use crate::rust_code_generator::{rust_common_type_to_code, rust_type_to_code};
@@ -9,6 +9,7 @@ use crate::rust_type::{
     RustCommonType, RustFinalType, RustPath, RustPointerLikeTypeKind, RustType,
 };
 use ritual_common::errors::{bail, Result};
+use serde::de::{self, Deserialize, Deserializer};
 use serde_derive::{Deserialize, Serialize};
 
 /// One variant of a Rust enum
@@ -17,6 +18,10 @@ pub struct RustEnumValue {
     pub path: RustPath,
     /// Corresponding value
     pub value: i64,
+    /// True if the C++ variant is an alias of another variant with the same value.
+    /// Aliases are generated as associated constants instead of enum variants.
+    #[serde(default)]
+    pub is_alias: bool,
 }
 
 /// Information about a Qt slot wrapper on Rust side
@@ -27,11 +32,79 @@ pub struct RustQtSlotWrapper {
     pub raw_slot_wrapper: RustPath,
 }
 
+/// Representation of a C++ enum in the Rust API.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum EnumRepresentation {
+    /// A newtype around `c_int` with associated constants for the values.
+    /// Any integer value can be represented.
+    Newtype,
+    /// A Rust enum with a variant for each value. Conversion from integer
+    /// is fallible (`TryFrom<c_int>`), so values received from C++
+    /// are returned as `Result<T, c_int>` containing unknown values in `Err`.
+    RustEnum,
+    /// A newtype like `Newtype` that also supports bit operations
+    /// (`|`, `&`, `^`, `!`) and `contains`.
+    Bitflags,
+}
+
+impl Default for EnumRepresentation {
+    fn default() -> Self {
+        EnumRepresentation::Newtype
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub enum RustWrapperTypeKind {
-    EnumWrapper,
+    EnumWrapper {
+        #[serde(default)]
+        representation: EnumRepresentation,
+        /// True if the C++ enum is scoped (`enum class`).
+        #[serde(default)]
+        is_scoped: bool,
+        /// Rust primitive type matching the underlying type of the C++ enum
+        /// on all checked targets. `c_int` is used if it's unknown.
+        #[serde(default)]
+        underlying_type: Option<String>,
+    },
     ImmovableClassWrapper,
-    MovableClassWrapper { sized_type_path: RustPath },
+    /// Wrapper of a class whose objects can't be moved after construction.
+    /// Owned objects are returned as `Pin<CppBox<T>>`.
+    PinnedClassWrapper,
+    MovableClassWrapper {
+        sized_type_path: RustPath,
+    },
+    /// `#[repr(C)]` Rust union with the same layout as the C++ union
+    UnionWrapper {
+        fields: Vec<RustLayoutField>,
+    },
+    /// `#[repr(C)]` Rust struct implementing `Copy` with the same layout
+    /// as the trivially copyable C++ class
+    PlainStructWrapper {
+        fields: Vec<RustLayoutField>,
+    },
+}
+
+/// Field of a Rust union or plain struct
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct RustLayoutField {
+    pub name: String,
+    pub rust_type: RustType,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct RustHandleType {
+    /// Path of the C++ typedef.
+    pub cpp_path: CppPath,
+    /// Primitive integer type holding the value of the handle (e.g. `u32`).
+    pub raw_type: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct RustOwnedHandleType {
+    /// Path of the handle newtype.
+    pub handle_path: RustPath,
+    /// Function that releases the resource.
+    pub release: CppPath,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
@@ -39,12 +112,32 @@ pub struct RustSizedType {
     pub cpp_path: CppPath,
 }
 
+/// Deserializes `RustWrapperTypeKind`, also accepting `EnumWrapper` without fields
+/// saved by older versions.
+fn deserialize_wrapper_type_kind<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<RustWrapperTypeKind, D::Error> {
+    let value = serde_json::Value::deserialize(deserializer)?;
+    if value.as_str() == Some("EnumWrapper") {
+        return Ok(RustWrapperTypeKind::EnumWrapper {
+            representation: EnumRepresentation::default(),
+            is_scoped: false,
+            underlying_type: None,
+        });
+    }
+    RustWrapperTypeKind::deserialize(value).map_err(de::Error::custom)
+}
+
 /// Information about a Rust type wrapper
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub enum RustStructKind {
-    WrapperType(RustWrapperTypeKind),
+    WrapperType(#[serde(deserialize_with = "deserialize_wrapper_type_kind")] RustWrapperTypeKind),
     QtSlotWrapper(RustQtSlotWrapper), // TODO: remove
     SizedType(RustSizedType),
+    /// Newtype of an integer handle typedef (see `Config::add_handle_type`)
+    HandleType(RustHandleType),
+    /// Guard releasing a handle when dropped (see `Config::add_handle_type`)
+    OwnedHandleType(RustOwnedHandleType),
 }
 
 impl RustStructKind {
@@ -86,6 +179,21 @@ impl RustStructKind {
                     false
                 }
             }
+            // handle types don't have a source item, so they are told apart by the typedef
+            RustStructKind::HandleType(data) => {
+                if let RustStructKind::HandleType(other) = other {
+                    data.cpp_path == other.cpp_path
+                } else {
+                    false
+                }
+            }
+            RustStructKind::OwnedHandleType(data) => {
+                if let RustStructKind::OwnedHandleType(other) = other {
+                    data.handle_path == other.handle_path
+                } else {
+                    false
+                }
+            }
         }
     }
 }
@@ -126,8 +234,9 @@ pub struct RustFunctionArgument {
     pub argument_type: RustFinalType,
     /// Rust argument name.
     pub name: String,
-    /// Index of the corresponding argument of the FFI function.
-    pub ffi_index: usize,
+    /// Index of the corresponding argument of the FFI function,
+    /// or `None` if the argument is not passed to FFI (e.g. `&LibraryHandle`).
+    pub ffi_index: Option<usize>,
 }
 
 /// Type of a receiver in Qt connection system.
@@ -137,9 +246,28 @@ pub enum RustQtReceiverType {
     Slot,
 }
 
+/// Direct access to a field of a class from Rust
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct RustInlineFieldAccess {
+    /// Offset of the field verified by the C++ checker
+    pub offset: usize,
+    /// How the field is accessed
+    pub accessor_type: CppFieldAccessorType,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct RustFfiWrapperData {
     pub ffi_function_path: RustPath,
+    /// Set if the wrapper of a field accessor or a trivial accessor method
+    /// accesses the field directly instead of calling the FFI function
+    /// (see `FfiFunctionOptions::inline_field_access`).
+    #[serde(default)]
+    pub inline_field_access: Option<RustInlineFieldAccess>,
+    /// Justification of the function's safety selected by the safety policy
+    /// (see `Config::set_safety_policy`). Not set if all functions are
+    /// `unsafe` by default.
+    #[serde(default)]
+    pub safety_reason: Option<String>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
@@ -154,6 +282,14 @@ pub enum RustFunctionKind {
 }
 
 impl RustFunctionKind {
+    pub fn is_ffi_wrapper(&self) -> bool {
+        if let RustFunctionKind::FfiWrapper(_) = self {
+            true
+        } else {
+            false
+        }
+    }
+
     pub fn short_text(&self) -> String {
         match self {
             RustFunctionKind::FfiWrapper(data) => {
@@ -380,6 +516,8 @@ pub enum RustSpecialModuleKind {
     Ffi,
     Ops,
     SizedTypes,
+    Prelude,
+    Unsafe,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
@@ -397,6 +535,8 @@ impl RustModuleKind {
                 RustSpecialModuleKind::Ffi => false,
                 RustSpecialModuleKind::Ops => true,
                 RustSpecialModuleKind::SizedTypes => false,
+                RustSpecialModuleKind::Prelude => false,
+                RustSpecialModuleKind::Unsafe => true,
             },
             RustModuleKind::CppNamespace { .. } => true,
             RustModuleKind::CppNestedTypes { .. } => false,
@@ -511,10 +651,28 @@ pub struct RustFlagEnumImpl {
     pub enum_path: RustPath,
 }
 
+/// Trait implemented for tuples of arguments of overloaded functions
+/// and a generic method that calls the overload matching the type of the tuple
+/// (see `OverloadStrategy::ArgsTuple`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RustOverloadTrait {
+    /// Path of the trait.
+    pub trait_path: RustPath,
+    /// Path of the generic method.
+    pub method_path: RustPath,
+    /// Kind of `self` argument shared by all overloads.
+    pub self_arg_kind: RustFunctionSelfArgKind,
+    /// True if any of the overloads is `unsafe`.
+    pub is_unsafe: bool,
+    /// Paths of the overloads.
+    pub overloads: Vec<RustPath>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RustExtraImplKind {
     FlagEnum(RustFlagEnumImpl),
     QtReceiverImpl(RustQtReceiverImpl),
+    OverloadTrait(RustOverloadTrait),
 }
 
 impl RustExtraImplKind {
@@ -534,6 +692,13 @@ impl RustExtraImplKind {
                     false
                 }
             }
+            RustExtraImplKind::OverloadTrait(_) => {
+                if let RustExtraImplKind::OverloadTrait(_) = other {
+                    true
+                } else {
+                    false
+                }
+            }
         }
     }
 }
@@ -546,7 +711,12 @@ pub struct RustExtraImpl {
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum RustReexportSource {
-    DependencyCrate { crate_name: String },
+    DependencyCrate {
+        crate_name: String,
+    },
+    /// Re-export configured with `Config::add_prelude_type`
+    /// or `Config::add_root_reexport`.
+    CrateLayout,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -660,7 +830,9 @@ impl RustItem {
         match self {
             RustItem::Module(data) => {
                 if let RustItem::Module(other) = other {
+                    // modules of dependency namespaces don't have a source item
                     data.kind == other.kind
+                        && (data.kind != RustModuleKind::CppNamespace || data.path == other.path)
                 } else {
                     false
                 }
@@ -730,7 +902,7 @@ impl RustItem {
             },
             RustItem::Reexport(data) => {
                 if let RustItem::Reexport(other) = other {
-                    data.source == other.source
+                    data.source == other.source && data.path == other.path
                 } else {
                     false
                 }
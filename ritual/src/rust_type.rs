@@ -151,12 +151,18 @@ pub enum RustToFfiTypeConversion {
     ValueToPtr,
     /// `CppBox<T>` to `*mut T`
     CppBoxToPtr,
+    /// `Pin<CppBox<T>>` to `*mut T`, used for classes that can't be moved
+    PinnedCppBoxToPtr,
     /// `QBox<T>` to `*mut T`
     QBoxToPtr,
     /// `qt_core::flags::Flags<T>` to `c_int`
     QFlagsToUInt {
         api_type: RustType,
     },
+    /// Rust enum to `c_int`
+    RustEnumToInt {
+        api_type: RustType,
+    },
     /// `()` to any type
     UnitToAnything,
     /// Primitive to another primitive using `as`
@@ -166,7 +172,27 @@ pub enum RustToFfiTypeConversion {
     /// Rust public type has an additional reference (`&`)
     RefTo(Box<RustToFfiTypeConversion>),
     ImplCastInto(Box<RustToFfiTypeConversion>),
+    /// `impl cpp_core::IntoArg<T>` to `*const T`, allowing implicit conversions
+    /// through non-explicit constructors of `T`
+    ImplIntoArg,
     ClosureToCallback(Box<RustClosureToCallbackConversion>),
+    /// `void` return type of a function starting an asynchronous operation
+    /// to `cpp_core::Completion` resolved by the completion callback
+    AsyncCompletion(Box<RustAsyncCompletionConversion>),
+    /// `&LibraryHandle` argument that is not passed to FFI
+    /// (see `Config::set_lifecycle_functions`)
+    LibraryHandle {
+        api_type: RustType,
+    },
+    /// `&[T]` to `*const T` (or `&mut [T]` to `*mut T`) and the slice's length
+    /// passed as another FFI argument (see `Config::set_slice_arguments_hook`)
+    SliceToPtr {
+        /// Index of the `size_t` length in FFI function arguments
+        len_ffi_index: usize,
+    },
+    /// `Option<String>` to `*const c_char` return value decoded with
+    /// the encoding of the library (see `Config::set_string_encoding`)
+    DecodedString,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -175,6 +201,37 @@ pub struct RustClosureToCallbackConversion {
     pub closure_return_type: RustFinalType,
 }
 
+/// Completion callback of an asynchronous operation (see `Config::add_async_function_rule`).
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RustAsyncCompletionConversion {
+    /// Index of the callback in FFI function arguments
+    pub callback_ffi_index: usize,
+    /// Index of the user data in FFI function arguments
+    pub data_ffi_index: usize,
+    /// Index of the user data in the callback's arguments
+    pub callback_data_index: usize,
+    /// Other arguments of the callback. The completion resolves to
+    /// the converted value of the argument (or a tuple of values
+    /// if there are multiple arguments).
+    pub completion_arguments: Vec<RustFinalType>,
+}
+
+impl RustAsyncCompletionConversion {
+    /// Returns Rust type of the value the completion resolves to.
+    pub fn value_type(&self) -> RustType {
+        if self.completion_arguments.len() == 1 {
+            self.completion_arguments[0].api_type().clone()
+        } else {
+            RustType::Tuple(
+                self.completion_arguments
+                    .iter()
+                    .map(|arg| arg.api_type().clone())
+                    .collect(),
+            )
+        }
+    }
+}
+
 impl RustToFfiTypeConversion {
     pub fn is_option_utils_ref_to_ptr(&self) -> bool {
         if let RustToFfiTypeConversion::OptionUtilsRefToPtr { .. } = self {
@@ -199,6 +256,14 @@ impl RustToFfiTypeConversion {
             None
         }
     }
+
+    pub fn as_async_completion_ref(&self) -> Option<&RustAsyncCompletionConversion> {
+        if let RustToFfiTypeConversion::AsyncCompletion(x) = self {
+            Some(x)
+        } else {
+            None
+        }
+    }
 }
 
 /// Information about a completely processed type
@@ -222,10 +287,16 @@ fn utils_ptr(ffi_type: &RustType) -> Result<RustType> {
     }))
 }
 
+/// Returns `Ref<T>` for `*const T` and `MutRef<T>` for `*mut T`.
 fn utils_ref(ffi_type: &RustType) -> Result<RustType> {
     let target = ffi_type.pointer_like_to_target()?;
+    let path = if ffi_type.is_const_pointer_like()? {
+        "cpp_core::Ref"
+    } else {
+        "cpp_core::MutRef"
+    };
     Ok(RustType::Common(RustCommonType {
-        path: RustPath::from_good_str("cpp_core::Ref"),
+        path: RustPath::from_good_str(path),
         generic_arguments: Some(vec![target]),
     }))
 }
@@ -273,6 +344,17 @@ impl RustFinalType {
                     generic_arguments: Some(vec![target]),
                 })
             }
+            RustToFfiTypeConversion::PinnedCppBoxToPtr => {
+                let target = ffi_type.pointer_like_to_target()?;
+                let cpp_box = RustType::Common(RustCommonType {
+                    path: RustPath::from_good_str("cpp_core::CppBox"),
+                    generic_arguments: Some(vec![target]),
+                });
+                RustType::Common(RustCommonType {
+                    path: RustPath::from_good_str("std::pin::Pin"),
+                    generic_arguments: Some(vec![cpp_box]),
+                })
+            }
             RustToFfiTypeConversion::QBoxToPtr => {
                 let target = ffi_type.pointer_like_to_target()?;
                 RustType::Common(RustCommonType {
@@ -288,6 +370,7 @@ impl RustFinalType {
                 })
             }
             RustToFfiTypeConversion::QFlagsToUInt { api_type }
+            | RustToFfiTypeConversion::RustEnumToInt { api_type }
             | RustToFfiTypeConversion::AsCast { api_type } => api_type.clone(),
             RustToFfiTypeConversion::UnitToAnything => RustType::unit(),
             RustToFfiTypeConversion::RefTo(conversion) => {
@@ -302,9 +385,35 @@ impl RustFinalType {
                 };
                 RustType::ImplTrait(trait_type)
             }
+            RustToFfiTypeConversion::ImplIntoArg => {
+                let trait_type = RustCommonType {
+                    path: RustPath::from_good_str("cpp_core::IntoArg"),
+                    generic_arguments: Some(vec![ffi_type.pointer_like_to_target()?]),
+                };
+                RustType::ImplTrait(trait_type)
+            }
             RustToFfiTypeConversion::ClosureToCallback { .. } => {
                 RustType::GenericParameter("T".into())
             }
+            RustToFfiTypeConversion::LibraryHandle { api_type } => api_type.clone(),
+            RustToFfiTypeConversion::SliceToPtr { .. } => RustType::new_reference(
+                ffi_type.is_const_pointer_like()?,
+                RustType::Slice {
+                    element_type: Box::new(ffi_type.pointer_like_to_target()?),
+                },
+            ),
+            RustToFfiTypeConversion::AsyncCompletion(conversion) => {
+                RustType::Common(RustCommonType {
+                    path: RustPath::from_good_str("cpp_core::Completion"),
+                    generic_arguments: Some(vec![conversion.value_type()]),
+                })
+            }
+            RustToFfiTypeConversion::DecodedString => {
+                RustType::new_option(RustType::Common(RustCommonType {
+                    path: RustPath::from_good_str("std::string::String"),
+                    generic_arguments: None,
+                }))
+            }
         };
         Ok(RustFinalType {
             api_type,
@@ -325,6 +434,21 @@ impl RustFinalType {
         &self.conversion
     }
 
+    /// Returns a copy of a `RefToPtr` type with the public reference type
+    /// changed to `&T` or `&mut T`.
+    pub fn with_api_mutability(&self, is_const: bool) -> Result<Self> {
+        if let RustToFfiTypeConversion::RefToPtr { .. } = &self.conversion {
+            let mut api_type = self.api_type.clone();
+            api_type.set_const(is_const)?;
+            Ok(RustFinalType {
+                api_type,
+                ..self.clone()
+            })
+        } else {
+            bail!("not a RefToPtr type");
+        }
+    }
+
     pub fn with_lifetime(&self, lifetime: String) -> Result<Self> {
         if let RustToFfiTypeConversion::RefToPtr { .. } = &self.conversion {
             RustFinalType::new(
@@ -472,6 +596,15 @@ pub enum RustType {
     },
     ImplTrait(RustCommonType),
     GenericParameter(String),
+    /// Fixed size array (`[T; N]`)
+    Array {
+        element_type: Box<RustType>,
+        size: usize,
+    },
+    /// Slice (`[T]`), only used as target of a reference
+    Slice {
+        element_type: Box<RustType>,
+    },
 }
 
 impl RustType {
@@ -544,6 +677,7 @@ impl RustType {
             }) => {
                 if path == &RustPath::from_good_str("cpp_core::Ptr")
                     || path == &RustPath::from_good_str("cpp_core::Ref")
+                    || path == &RustPath::from_good_str("cpp_core::MutRef")
                     || path == &RustPath::from_good_str("cpp_core::CppBox")
                 {
                     let arg = &generic_arguments.as_ref().unwrap()[0];
@@ -595,6 +729,12 @@ impl RustType {
                 name
             }
             RustType::FunctionPointer { .. } => "fn".to_string(),
+            RustType::Array { element_type, size } => {
+                format!("{}_array{}", element_type.caption(context, strategy)?, size)
+            }
+            RustType::Slice { element_type } => {
+                format!("{}_slice", element_type.caption(context, strategy)?)
+            }
             RustType::ImplTrait(trait_type) => {
                 if trait_type.path == RustPath::from_good_str("cpp_core::CastInto") {
                     trait_type
@@ -603,6 +743,13 @@ impl RustType {
                         .flatten()
                         .map_if_ok(|x| x.caption(context, strategy))?
                         .join("_")
+                } else if trait_type.path == RustPath::from_good_str("cpp_core::IntoArg") {
+                    // keep the same caption as `impl CastInto<Ref<T>>`
+                    RustType::Common(RustCommonType {
+                        path: RustPath::from_good_str("cpp_core::Ref"),
+                        generic_arguments: trait_type.generic_arguments.clone(),
+                    })
+                    .caption(context, strategy)?
                 } else {
                     RustType::Common(trait_type.clone()).caption(context, strategy)?
                 }
@@ -687,6 +834,9 @@ impl RustType {
                     || function.arguments.iter().any(RustType::is_unsafe_argument)
             }
             RustType::ImplTrait(_) => true,
+            RustType::Array { element_type, .. } | RustType::Slice { element_type } => {
+                element_type.is_unsafe_argument()
+            }
         }
     }
 
@@ -788,6 +938,27 @@ impl RustType {
                 }
             }
             RustType::GenericParameter(_) => self == other,
+            RustType::Array { element_type, size } => {
+                if let RustType::Array {
+                    element_type: other_element_type,
+                    size: other_size,
+                } = other
+                {
+                    size == other_size && element_type.can_be_same_as(other_element_type)
+                } else {
+                    false
+                }
+            }
+            RustType::Slice { element_type } => {
+                if let RustType::Slice {
+                    element_type: other_element_type,
+                } = other
+                {
+                    element_type.can_be_same_as(other_element_type)
+                } else {
+                    false
+                }
+            }
         }
     }
 }
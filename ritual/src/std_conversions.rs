@@ -0,0 +1,538 @@
+//! Built-in conversions between wrappers of C++ date/time, path and wide string
+//! types and their Rust equivalents.
+//!
+//! If the crate contains a wrapper type for one of the supported C++ types,
+//! the C++ wrapper library gets helper functions that convert the value
+//! to or from a plain representation (nanoseconds or UTF-8 bytes),
+//! and the Rust crate gets conversion impls based on them:
+//!
+//! | C++ type                                  | Rust type                 |
+//! |-------------------------------------------|---------------------------|
+//! | `std::chrono::duration<Rep, Period>`      | `std::time::Duration`     |
+//! | `std::chrono::time_point<system_clock>`   | `std::time::SystemTime`   |
+//! | `std::filesystem::path`                   | `std::path::PathBuf`      |
+//! | `std::wstring`                            | `String`, `OsString`      |
+//! | `std::u16string`                          | `String`                  |
+//!
+//! Conversions are only available on targets where the destructor
+//! of the C++ type is available.
+//!
+//! Conversion from C++ types is done with `TryFrom` (negative durations can't
+//! be converted). Conversion to C++ types is provided by `from_std_*` associated
+//! functions that return a `CppBox`. Wide string arguments also accept `&str`
+//! through `IntoArg` generated from their converting constructors, so only returned
+//! wide strings need explicit conversions. Durations and time points are converted
+//! with nanosecond precision, so values more than 292 years away from zero
+//! (or the epoch) are not supported.
+
+use crate::cpp_checks::Condition;
+use crate::cpp_data::CppPath;
+use crate::cpp_type::{CppBuiltInNumericType, CppType};
+use crate::database::DatabaseClient;
+use ritual_common::errors::{err_msg, Result};
+use std::fmt::Write;
+
+/// Kind of a supported C++ type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StdConversionKind {
+    /// `std::chrono::duration` instantiation
+    Duration,
+    /// `std::chrono::time_point` instantiation using `std::chrono::system_clock`
+    SystemTimePoint,
+    /// `std::filesystem::path`
+    FilesystemPath,
+    /// `std::basic_string<wchar_t>`
+    WideString,
+    /// `std::basic_string<char16_t>`
+    U16String,
+}
+
+impl StdConversionKind {
+    fn from_cpp_path(path: &CppPath) -> Option<Self> {
+        let kind = match path.to_templateless_string().as_str() {
+            "std::chrono::duration" => StdConversionKind::Duration,
+            "std::chrono::time_point" => {
+                let args = path.last().template_arguments.as_ref()?;
+                match args.get(0)? {
+                    CppType::Class(clock)
+                        if clock == &CppPath::from_good_str("std::chrono::system_clock") =>
+                    {
+                        StdConversionKind::SystemTimePoint
+                    }
+                    _ => return None,
+                }
+            }
+            "std::filesystem::path" | "std::__fs::filesystem::path" => {
+                StdConversionKind::FilesystemPath
+            }
+            "std::basic_string" | "std::__cxx11::basic_string" => {
+                let args = path.last().template_arguments.as_ref()?;
+                match args.get(0)? {
+                    CppType::BuiltInNumeric(CppBuiltInNumericType::WChar) => {
+                        StdConversionKind::WideString
+                    }
+                    CppType::BuiltInNumeric(CppBuiltInNumericType::Char16) => {
+                        StdConversionKind::U16String
+                    }
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+/// A C++ type of the current crate that has built-in conversions.
+#[derive(Debug, Clone)]
+pub struct StdConversion {
+    pub kind: StdConversionKind,
+    pub cpp_type: CppPath,
+    /// Full path of the Rust wrapper type (e.g. `crate::std::filesystem::Path`).
+    pub rust_type: String,
+    /// Prefix of names of the C++ helper functions.
+    pub helper_prefix: String,
+    /// Condition of availability of the conversion.
+    pub condition: Condition,
+}
+
+/// Returns the condition of availability of the FFI wrapper of the destructor
+/// of `class_path`, or `None` if it's not available on any target.
+fn destructor_condition(db: &DatabaseClient, class_path: &CppPath) -> Result<Option<Condition>> {
+    let destructor = db.cpp_items().find(|item| {
+        item.item.as_function_ref().map_or(false, |function| {
+            function.is_destructor()
+                && function
+                    .class_path_parts()
+                    .map_or(false, |parts| parts == class_path.items())
+        })
+    });
+    let destructor = match destructor {
+        Some(item) => item,
+        None => return Ok(None),
+    };
+    let ffi_item = db
+        .ffi_items()
+        .find(|item| item.item.is_function() && item.source_id.as_ref() == Some(&destructor.id));
+    let ffi_item = match ffi_item {
+        Some(item) => item,
+        None => return Ok(None),
+    };
+    let checks = db.cpp_checks(&ffi_item.id)?;
+    if !checks.any_success() {
+        return Ok(None);
+    }
+    Ok(Some(checks.condition(db.environments())))
+}
+
+/// Returns the types of the current crate that have built-in conversions.
+/// The order is stable, so the names of helper functions match
+/// between the C++ library and the Rust crate.
+///
+/// The conversion has the condition of the destructor of the type
+/// because the wrapper type is only usable where it can be deleted.
+pub fn find_std_conversions(db: &DatabaseClient) -> Result<Vec<StdConversion>> {
+    let crate_name = db.crate_name().to_string();
+    let mut output = Vec::new();
+    for cpp_item in db.cpp_items() {
+        let declaration = match cpp_item.item.as_type_ref() {
+            Some(declaration) if declaration.kind.is_class() => declaration,
+            _ => continue,
+        };
+        let kind = match StdConversionKind::from_cpp_path(&declaration.path) {
+            Some(kind) => kind,
+            None => continue,
+        };
+        let rust_item = db
+            .find_rust_items_for_cpp_path(&declaration.path, false)?
+            .find(|item| item.item.is_wrapper_type());
+        let rust_type = match rust_item {
+            Some(item) => item
+                .item
+                .path()
+                .ok_or_else(|| err_msg("wrapper type must have path"))?
+                .full_name(Some(&crate_name)),
+            None => continue,
+        };
+        let condition = match destructor_condition(db, &declaration.path)? {
+            Some(condition) => condition,
+            None => continue,
+        };
+        output.push(StdConversion {
+            kind,
+            cpp_type: declaration.path.clone(),
+            rust_type,
+            helper_prefix: format!("{}_std_conversion_{}", crate_name, output.len()),
+            condition,
+        });
+    }
+    Ok(output)
+}
+
+/// Generates C++ helper functions for `conversion`.
+/// The code must be placed in an `extern "C"` block.
+pub fn cpp_helpers(conversion: &StdConversion) -> Result<String> {
+    let mut code = String::new();
+    let cpp_type = CppType::Class(conversion.cpp_type.clone()).to_cpp_code(None)?;
+    let prefix = &conversion.helper_prefix;
+    match conversion.kind {
+        StdConversionKind::Duration => {
+            writeln!(
+                code,
+                "RITUAL_EXPORT long long {prefix}_to_nanos(const {t}* value) {{
+  return std::chrono::duration_cast<std::chrono::nanoseconds>(*value).count();
+}}
+RITUAL_EXPORT {t}* {prefix}_from_nanos(long long nanos) {{
+  return new {t}(std::chrono::duration_cast<{t}>(std::chrono::nanoseconds(nanos)));
+}}",
+                prefix = prefix,
+                t = cpp_type
+            )?;
+        }
+        StdConversionKind::SystemTimePoint => {
+            writeln!(
+                code,
+                "RITUAL_EXPORT long long {prefix}_to_nanos(const {t}* value) {{
+  return std::chrono::duration_cast<std::chrono::nanoseconds>(
+    value->time_since_epoch()).count();
+}}
+RITUAL_EXPORT {t}* {prefix}_from_nanos(long long nanos) {{
+  return new {t}(std::chrono::duration_cast<{t}::duration>(std::chrono::nanoseconds(nanos)));
+}}",
+                prefix = prefix,
+                t = cpp_type
+            )?;
+        }
+        StdConversionKind::FilesystemPath => {
+            writeln!(
+                code,
+                "RITUAL_EXPORT void {prefix}_to_bytes(const {t}* value, void* output,
+  void (*callback)(void*, const char*, size_t)) {{
+  auto bytes = value->u8string();
+  callback(output, reinterpret_cast<const char*>(bytes.data()), bytes.size());
+}}
+RITUAL_EXPORT {t}* {prefix}_from_bytes(const char* data, size_t len) {{
+#if defined(__cpp_char8_t)
+  return new {t}(std::u8string(reinterpret_cast<const char8_t*>(data), len));
+#else
+  return new {t}(std::filesystem::u8path(std::string(data, len)));
+#endif
+}}",
+                prefix = prefix,
+                t = cpp_type
+            )?;
+        }
+        StdConversionKind::WideString | StdConversionKind::U16String => {
+            let unit = if conversion.kind == StdConversionKind::WideString {
+                "wchar_t"
+            } else {
+                "char16_t"
+            };
+            writeln!(
+                code,
+                "RITUAL_EXPORT const {unit}* {prefix}_data(const {t}* value, size_t* len) {{
+  *len = value->size();
+  return value->data();
+}}
+RITUAL_EXPORT {t}* {prefix}_from_units(const {unit}* data, size_t len) {{
+  return new {t}(data, len);
+}}",
+                prefix = prefix,
+                t = cpp_type,
+                unit = unit
+            )?;
+        }
+    }
+    Ok(code)
+}
+
+/// Generates Rust conversion impls for `conversion`. The code is a single item,
+/// so it can be preceded by a `cfg` attribute.
+pub fn rust_impls(conversion: &StdConversion) -> Result<String> {
+    let mut code = String::new();
+    let prefix = &conversion.helper_prefix;
+    let t = &conversion.rust_type;
+    match conversion.kind {
+        StdConversionKind::Duration => {
+            writeln!(
+                code,
+                "const _: () = {{
+    extern \"C\" {{
+        fn {prefix}_to_nanos(value: *const {t}) -> i64;
+        fn {prefix}_from_nanos(nanos: i64) -> *mut {t};
+    }}
+
+    impl<'a> ::std::convert::TryFrom<&'a {t}> for ::std::time::Duration {{
+        type Error = ::std::num::TryFromIntError;
+
+        /// Converts the duration to `Duration`. Fails if the duration is negative.
+        fn try_from(value: &'a {t}) -> Result<Self, Self::Error> {{
+            let nanos = unsafe {{ {prefix}_to_nanos(value) }};
+            Ok(::std::time::Duration::from_nanos(::std::convert::TryFrom::try_from(nanos)?))
+        }}
+    }}
+
+    impl {t} {{
+        /// Creates a C++ duration from `Duration`. The value is truncated
+        /// if the C++ type has a lower precision. Fails if the duration
+        /// doesn't fit in `i64` nanoseconds.
+        pub fn from_std_duration(
+            value: ::std::time::Duration,
+        ) -> Result<::cpp_core::CppBox<Self>, ::std::num::TryFromIntError> {{
+            let nanos = ::std::convert::TryFrom::try_from(value.as_nanos())?;
+            unsafe {{
+                Ok(::cpp_core::CppBox::new(::cpp_core::Ptr::from_raw({prefix}_from_nanos(nanos)))
+                    .expect(\"conversion returned null\"))
+            }}
+        }}
+    }}
+}};",
+                prefix = prefix,
+                t = t
+            )?;
+        }
+        StdConversionKind::SystemTimePoint => {
+            writeln!(
+                code,
+                "const _: () = {{
+    extern \"C\" {{
+        fn {prefix}_to_nanos(value: *const {t}) -> i64;
+        fn {prefix}_from_nanos(value: i64) -> *mut {t};
+    }}
+
+    impl<'a> ::std::convert::TryFrom<&'a {t}> for ::std::time::SystemTime {{
+        type Error = ::cpp_core::TimeConversionError;
+
+        /// Converts the time to `SystemTime`. Fails if the value is out of range
+        /// of `SystemTime`.
+        fn try_from(value: &'a {t}) -> Result<Self, ::cpp_core::TimeConversionError> {{
+            let offset = unsafe {{ {prefix}_to_nanos(value) }};
+            let duration = ::std::time::Duration::from_nanos(i128::from(offset).abs() as u64);
+            if offset < 0 {{
+                ::std::time::UNIX_EPOCH.checked_sub(duration)
+            }} else {{
+                ::std::time::UNIX_EPOCH.checked_add(duration)
+            }}
+            .ok_or(::cpp_core::TimeConversionError::OutOfRange)
+        }}
+    }}
+
+    impl {t} {{
+        /// Creates a C++ value from `SystemTime`. The value is truncated
+        /// if the C++ type has a lower precision. Fails if the time doesn't fit
+        /// in `i64` nanoseconds since the epoch.
+        pub fn from_std_system_time(
+            value: ::std::time::SystemTime,
+        ) -> Result<::cpp_core::CppBox<Self>, ::std::num::TryFromIntError> {{
+            // `Duration` always fits in `i128` nanoseconds
+            let offset = match value.duration_since(::std::time::UNIX_EPOCH) {{
+                Ok(duration) => duration.as_nanos() as i128,
+                Err(err) => -(err.duration().as_nanos() as i128),
+            }};
+            let offset = ::std::convert::TryFrom::try_from(offset)?;
+            unsafe {{
+                Ok(::cpp_core::CppBox::new(::cpp_core::Ptr::from_raw({prefix}_from_nanos(offset)))
+                    .expect(\"conversion returned null\"))
+            }}
+        }}
+    }}
+}};",
+                prefix = prefix,
+                t = t
+            )?;
+        }
+        StdConversionKind::FilesystemPath => {
+            writeln!(
+                code,
+                "const _: () = {{
+    extern \"C\" {{
+        fn {prefix}_to_bytes(
+            value: *const {t},
+            output: *mut ::std::ffi::c_void,
+            callback: extern \"C\" fn(*mut ::std::ffi::c_void, *const u8, usize),
+        );
+        fn {prefix}_from_bytes(data: *const u8, len: usize) -> *mut {t};
+    }}
+
+    extern \"C\" fn copy_bytes(output: *mut ::std::ffi::c_void, data: *const u8, len: usize) {{
+        unsafe {{
+            let output = &mut *(output as *mut Vec<u8>);
+            output.extend_from_slice(::std::slice::from_raw_parts(data, len));
+        }}
+    }}
+
+    impl<'a> From<&'a {t}> for ::std::path::PathBuf {{
+        /// Converts the path to `PathBuf`. On platforms other than Unix,
+        /// invalid UTF-8 sequences are replaced.
+        fn from(value: &'a {t}) -> Self {{
+            let mut bytes = Vec::new();
+            unsafe {{
+                {prefix}_to_bytes(value, &mut bytes as *mut Vec<u8> as *mut _, copy_bytes);
+            }}
+            #[cfg(unix)]
+            {{
+                use ::std::os::unix::ffi::OsStringExt;
+                ::std::ffi::OsString::from_vec(bytes).into()
+            }}
+            #[cfg(not(unix))]
+            {{
+                String::from_utf8_lossy(&bytes).into_owned().into()
+            }}
+        }}
+    }}
+
+    impl {t} {{
+        /// Creates a C++ path from a Rust path. On platforms other than Unix,
+        /// the path must be valid Unicode.
+        pub fn from_std_path(
+            value: impl AsRef<::std::path::Path>,
+        ) -> ::cpp_core::CppBox<Self> {{
+            #[cfg(unix)]
+            let bytes = {{
+                use ::std::os::unix::ffi::OsStrExt;
+                value.as_ref().as_os_str().as_bytes().to_vec()
+            }};
+            #[cfg(not(unix))]
+            let bytes = value.as_ref().to_string_lossy().into_owned().into_bytes();
+            unsafe {{
+                ::cpp_core::CppBox::new(::cpp_core::Ptr::from_raw(
+                    {prefix}_from_bytes(bytes.as_ptr(), bytes.len()),
+                ))
+                .expect(\"conversion returned null\")
+            }}
+        }}
+    }}
+}};",
+                prefix = prefix,
+                t = t
+            )?;
+        }
+        StdConversionKind::WideString => {
+            writeln!(
+                code,
+                "const _: () = {{
+    extern \"C\" {{
+        fn {prefix}_data(value: *const {t}, len: *mut usize) -> *const ::cpp_core::wchar_t;
+        fn {prefix}_from_units(data: *const ::cpp_core::wchar_t, len: usize) -> *mut {t};
+    }}
+
+    fn units(value: &{t}) -> &[::cpp_core::wchar_t] {{
+        let mut len = 0;
+        unsafe {{
+            let data = {prefix}_data(value, &mut len);
+            if len == 0 {{
+                &[]
+            }} else {{
+                ::std::slice::from_raw_parts(data, len)
+            }}
+        }}
+    }}
+
+    fn from_units(units: &[::cpp_core::wchar_t]) -> ::cpp_core::CppBox<{t}> {{
+        unsafe {{
+            ::cpp_core::CppBox::new(::cpp_core::Ptr::from_raw(
+                {prefix}_from_units(units.as_ptr(), units.len()),
+            ))
+            .expect(\"conversion returned null\")
+        }}
+    }}
+
+    impl<'a> ::std::convert::TryFrom<&'a {t}> for ::std::string::String {{
+        type Error = ::cpp_core::wide_string::FromWideStringError;
+
+        /// Converts the string to `String`. Fails if it's not valid UTF-16 (on Windows)
+        /// or UTF-32 (on other platforms).
+        fn try_from(
+            value: &'a {t},
+        ) -> Result<Self, ::cpp_core::wide_string::FromWideStringError> {{
+            ::cpp_core::wide_string::try_wchar_slice_to_string(units(value))
+        }}
+    }}
+
+    impl<'a> From<&'a {t}> for ::std::ffi::OsString {{
+        /// Converts the string to `OsString`. On Windows, the conversion is lossless.
+        /// On other platforms, invalid code units are replaced with U+FFFD.
+        fn from(value: &'a {t}) -> Self {{
+            ::cpp_core::wide_string::wchar_slice_to_os_string(units(value))
+        }}
+    }}
+
+    impl {t} {{
+        /// Converts the string to `String`, replacing invalid code units with U+FFFD.
+        pub fn to_std_string_lossy(&self) -> ::std::string::String {{
+            ::cpp_core::wide_string::wchar_slice_to_string_lossy(units(self))
+        }}
+
+        /// Creates a C++ string from a Rust string.
+        pub fn from_std_str(text: &str) -> ::cpp_core::CppBox<Self> {{
+            let units = ::cpp_core::wide_string::to_wchar_nul(text);
+            from_units(&units[..units.len() - 1])
+        }}
+
+        /// Creates a C++ string from an OS string. On platforms other than Windows,
+        /// invalid Unicode sequences are replaced with U+FFFD.
+        pub fn from_std_os_str(text: impl AsRef<::std::ffi::OsStr>) -> ::cpp_core::CppBox<Self> {{
+            let units = ::cpp_core::wide_string::os_str_to_wchar_nul(text.as_ref());
+            from_units(&units[..units.len() - 1])
+        }}
+    }}
+}};",
+                prefix = prefix,
+                t = t
+            )?;
+        }
+        StdConversionKind::U16String => {
+            writeln!(
+                code,
+                "const _: () = {{
+    extern \"C\" {{
+        fn {prefix}_data(value: *const {t}, len: *mut usize) -> *const ::cpp_core::char16_t;
+        fn {prefix}_from_units(data: *const ::cpp_core::char16_t, len: usize) -> *mut {t};
+    }}
+
+    fn units(value: &{t}) -> &[::cpp_core::char16_t] {{
+        let mut len = 0;
+        unsafe {{
+            let data = {prefix}_data(value, &mut len);
+            if len == 0 {{
+                &[]
+            }} else {{
+                ::std::slice::from_raw_parts(data, len)
+            }}
+        }}
+    }}
+
+    impl<'a> ::std::convert::TryFrom<&'a {t}> for ::std::string::String {{
+        type Error = ::std::string::FromUtf16Error;
+
+        /// Converts the string to `String`. Fails if it's not valid UTF-16.
+        fn try_from(value: &'a {t}) -> Result<Self, Self::Error> {{
+            ::std::string::String::from_utf16(units(value))
+        }}
+    }}
+
+    impl {t} {{
+        /// Converts the string to `String`, replacing invalid code units with U+FFFD.
+        pub fn to_std_string_lossy(&self) -> ::std::string::String {{
+            ::std::string::String::from_utf16_lossy(units(self))
+        }}
+
+        /// Creates a C++ string from a Rust string.
+        pub fn from_std_str(text: &str) -> ::cpp_core::CppBox<Self> {{
+            let units = text.encode_utf16().collect::<Vec<_>>();
+            unsafe {{
+                ::cpp_core::CppBox::new(::cpp_core::Ptr::from_raw(
+                    {prefix}_from_units(units.as_ptr(), units.len()),
+                ))
+                .expect(\"conversion returned null\")
+            }}
+        }}
+    }}
+}};",
+                prefix = prefix,
+                t = t
+            )?;
+        }
+    }
+    Ok(code)
+}
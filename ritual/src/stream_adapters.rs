@@ -0,0 +1,453 @@
+//! Adapters that allow to pass Rust readers and writers to C++ functions
+//! accepting `std::istream&` or `std::ostream&`.
+//!
+//! If a function of the crate accepts one of these streams and the stream type
+//! has a Rust wrapper (in this crate or a dependency), the C++ wrapper library gets
+//! a stream class with a custom `std::streambuf` that forwards all reads
+//! or writes to Rust callbacks, and the crate gets the `io_adapters` module
+//! with `ReadStream` and `WriteStream` types owning such streams.
+
+use crate::cpp_data::CppPath;
+use crate::cpp_type::{CppBuiltInNumericType, CppType};
+use crate::database::DatabaseClient;
+use ritual_common::errors::{err_msg, Result};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// Rust wrappers of the stream types used by the crate.
+#[derive(Debug, Clone, Default)]
+pub struct StreamTypes {
+    /// Full path of the Rust wrapper of `std::istream`.
+    pub istream: Option<String>,
+    /// Full path of the Rust wrapper of `std::ostream`.
+    pub ostream: Option<String>,
+}
+
+impl StreamTypes {
+    pub fn is_empty(&self) -> bool {
+        self.istream.is_none() && self.ostream.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum StreamKind {
+    Input,
+    Output,
+}
+
+fn stream_kind(path: &CppPath) -> Option<StreamKind> {
+    let kind = match path.to_templateless_string().as_str() {
+        "std::istream" => return Some(StreamKind::Input),
+        "std::ostream" => return Some(StreamKind::Output),
+        "std::basic_istream" => StreamKind::Input,
+        "std::basic_ostream" => StreamKind::Output,
+        _ => return None,
+    };
+    // only `char` streams are supported
+    let args = path.last().template_arguments.as_ref()?;
+    if args.get(0) == Some(&CppType::BuiltInNumeric(CppBuiltInNumericType::Char)) {
+        Some(kind)
+    } else {
+        None
+    }
+}
+
+/// Finds stream types accepted by reference in functions of the current crate.
+pub fn find_stream_types(db: &DatabaseClient) -> Result<StreamTypes> {
+    let mut paths = BTreeSet::new();
+    for item in db.cpp_items() {
+        let function = match item.item.as_function_ref() {
+            Some(function) => function,
+            None => continue,
+        };
+        for arg in &function.arguments {
+            if let CppType::PointerLike { target, .. } = &arg.argument_type {
+                if let CppType::Class(path) = &**target {
+                    if let Some(kind) = stream_kind(path) {
+                        paths.insert((kind, path.clone()));
+                    }
+                }
+            }
+        }
+    }
+
+    let crate_name = db.crate_name().to_string();
+    let mut output = StreamTypes::default();
+    for (kind, path) in paths {
+        let slot = match kind {
+            StreamKind::Input => &mut output.istream,
+            StreamKind::Output => &mut output.ostream,
+        };
+        if slot.is_some() {
+            continue;
+        }
+        let rust_item = match db.find_rust_items_for_cpp_path(&path, true) {
+            Ok(mut items) => items.find(|item| item.item.is_wrapper_type()),
+            Err(_) => None,
+        };
+        if let Some(rust_item) = rust_item {
+            let rust_path = rust_item
+                .item
+                .path()
+                .ok_or_else(|| err_msg("wrapper type must have path"))?;
+            *slot = Some(rust_path.full_name(Some(&crate_name)));
+        }
+    }
+    Ok(output)
+}
+
+fn helper_prefix(crate_name: &str) -> String {
+    format!("{}_stream_adapter", crate_name)
+}
+
+/// Returns C++ code of the stream classes. It must be placed
+/// outside of `extern "C"` blocks.
+pub fn cpp_classes(types: &StreamTypes, crate_name: &str) -> Result<String> {
+    let prefix = helper_prefix(crate_name);
+    let mut code = String::new();
+    if types.is_empty() {
+        return Ok(code);
+    }
+    writeln!(
+        code,
+        "#include <istream>\n#include <ostream>\n#include <streambuf>"
+    )?;
+    if types.istream.is_some() {
+        writeln!(
+            code,
+            "class {prefix}_read_buf : public std::streambuf {{
+public:
+  typedef size_t (*ReadFn)(void*, char*, size_t);
+  {prefix}_read_buf(void* data, ReadFn read) : m_data(data), m_read(read) {{}}
+
+protected:
+  int_type underflow() {{
+    if (gptr() < egptr()) {{
+      return traits_type::to_int_type(*gptr());
+    }}
+    size_t len = m_read(m_data, m_buf, sizeof(m_buf));
+    if (len == 0 || len == static_cast<size_t>(-1)) {{
+      return traits_type::eof();
+    }}
+    setg(m_buf, m_buf, m_buf + len);
+    return traits_type::to_int_type(*gptr());
+  }}
+
+private:
+  void* m_data;
+  ReadFn m_read;
+  char m_buf[4096];
+}};
+
+class {prefix}_istream : public std::istream {{
+public:
+  {prefix}_istream(void* data, {prefix}_read_buf::ReadFn read)
+    : std::istream(nullptr), m_buf(data, read) {{
+    rdbuf(&m_buf);
+  }}
+
+private:
+  {prefix}_read_buf m_buf;
+}};
+",
+            prefix = prefix
+        )?;
+    }
+    if types.ostream.is_some() {
+        writeln!(
+            code,
+            "class {prefix}_write_buf : public std::streambuf {{
+public:
+  typedef size_t (*WriteFn)(void*, const char*, size_t);
+  typedef int (*FlushFn)(void*);
+  {prefix}_write_buf(void* data, WriteFn write, FlushFn flush)
+    : m_data(data), m_write(write), m_flush(flush) {{}}
+
+protected:
+  int_type overflow(int_type c) {{
+    if (traits_type::eq_int_type(c, traits_type::eof())) {{
+      return traits_type::not_eof(c);
+    }}
+    char ch = traits_type::to_char_type(c);
+    return m_write(m_data, &ch, 1) == 1 ? c : traits_type::eof();
+  }}
+
+  std::streamsize xsputn(const char* s, std::streamsize n) {{
+    size_t len = m_write(m_data, s, static_cast<size_t>(n));
+    return len == static_cast<size_t>(-1) ? 0 : static_cast<std::streamsize>(len);
+  }}
+
+  int sync() {{
+    return m_flush(m_data);
+  }}
+
+private:
+  void* m_data;
+  WriteFn m_write;
+  FlushFn m_flush;
+}};
+
+class {prefix}_ostream : public std::ostream {{
+public:
+  {prefix}_ostream(void* data, {prefix}_write_buf::WriteFn write,
+                   {prefix}_write_buf::FlushFn flush)
+    : std::ostream(nullptr), m_buf(data, write, flush) {{
+    rdbuf(&m_buf);
+  }}
+
+private:
+  {prefix}_write_buf m_buf;
+}};
+",
+            prefix = prefix
+        )?;
+    }
+    Ok(code)
+}
+
+/// Returns C++ functions creating and deleting the streams.
+/// The code must be placed in an `extern "C"` block.
+pub fn cpp_helpers(types: &StreamTypes, crate_name: &str) -> Result<String> {
+    let prefix = helper_prefix(crate_name);
+    let mut code = String::new();
+    if types.istream.is_some() {
+        writeln!(
+            code,
+            "RITUAL_EXPORT std::istream* {prefix}_istream_new(void* data,
+                                                  {prefix}_read_buf::ReadFn read) {{
+  return new {prefix}_istream(data, read);
+}}
+RITUAL_EXPORT void {prefix}_istream_delete(std::istream* stream) {{
+  delete stream;
+}}",
+            prefix = prefix
+        )?;
+    }
+    if types.ostream.is_some() {
+        writeln!(
+            code,
+            "RITUAL_EXPORT std::ostream* {prefix}_ostream_new(void* data,
+                                                  {prefix}_write_buf::WriteFn write,
+                                                  {prefix}_write_buf::FlushFn flush) {{
+  return new {prefix}_ostream(data, write, flush);
+}}
+RITUAL_EXPORT void {prefix}_ostream_delete(std::ostream* stream) {{
+  stream->flush();
+  delete stream;
+}}",
+            prefix = prefix
+        )?;
+    }
+    Ok(code)
+}
+
+/// Returns Rust code of the `io_adapters` module.
+pub fn rust_module(types: &StreamTypes, crate_name: &str) -> Result<String> {
+    let prefix = helper_prefix(crate_name);
+    let mut code = String::new();
+    if types.is_empty() {
+        return Ok(code);
+    }
+    writeln!(
+        code,
+        "/// Adapters passing Rust readers and writers to C++ functions that accept streams.
+///
+/// I/O errors and panics in the reader or writer are reported to the C++ stream
+/// as a failure (end of file or bad state). The last error can be retrieved with
+/// `take_error`.
+pub mod io_adapters {{
+    use ::std::ffi::c_void;
+    use ::std::io;
+    use ::std::os::raw::c_char;
+    use ::std::panic::{{catch_unwind, AssertUnwindSafe}};
+
+    struct State<T> {{
+        inner: T,
+        error: Option<io::Error>,
+    }}
+
+    impl<T> State<T> {{
+        fn call(&mut self, f: impl FnOnce(&mut T) -> io::Result<usize>) -> usize {{
+            let inner = &mut self.inner;
+            let result = catch_unwind(AssertUnwindSafe(|| f(inner))).unwrap_or_else(|_| {{
+                Err(io::Error::new(io::ErrorKind::Other, \"panic in stream adapter\"))
+            }});
+            match result {{
+                Ok(len) => len,
+                Err(err) => {{
+                    self.error = Some(err);
+                    usize::max_value()
+                }}
+            }}
+        }}
+    }}"
+    )?;
+    if let Some(istream) = &types.istream {
+        writeln!(
+            code,
+            "
+    extern \"C\" {{
+        fn {prefix}_istream_new(
+            data: *mut c_void,
+            read: extern \"C\" fn(*mut c_void, *mut c_char, usize) -> usize,
+        ) -> *mut {t};
+        fn {prefix}_istream_delete(stream: *mut {t});
+    }}
+
+    extern \"C\" fn read_callback<R: io::Read>(
+        data: *mut c_void,
+        buf: *mut c_char,
+        len: usize,
+    ) -> usize {{
+        let state = unsafe {{ &mut *(data as *mut State<R>) }};
+        let buf = unsafe {{ ::std::slice::from_raw_parts_mut(buf as *mut u8, len) }};
+        state.call(|reader| loop {{
+            match reader.read(buf) {{
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                result => return result,
+            }}
+        }})
+    }}
+
+    /// C++ input stream that reads data from a Rust reader.
+    pub struct ReadStream<R: io::Read> {{
+        stream: ::cpp_core::Ptr<{t}>,
+        state: Box<State<R>>,
+    }}
+
+    impl<R: io::Read> ReadStream<R> {{
+        /// Creates a stream reading from `reader`.
+        pub fn new(reader: R) -> Self {{
+            let mut state = Box::new(State {{
+                inner: reader,
+                error: None,
+            }});
+            let stream = unsafe {{
+                ::cpp_core::Ptr::from_raw({prefix}_istream_new(
+                    &mut *state as *mut State<R> as *mut c_void,
+                    read_callback::<R>,
+                ))
+            }};
+            ReadStream {{ stream, state }}
+        }}
+
+        /// Returns a reference to the C++ stream that can be passed to C++ functions.
+        /// The reference must not be used after the adapter is dropped.
+        pub fn as_stream(&mut self) -> ::cpp_core::MutRef<{t}> {{
+            unsafe {{ self.stream.as_mut_ref().expect(\"stream is null\") }}
+        }}
+
+        /// Returns the last error returned by the reader, if any.
+        pub fn take_error(&mut self) -> Option<io::Error> {{
+            self.state.error.take()
+        }}
+
+        /// Destroys the C++ stream and returns the reader.
+        pub fn into_inner(self) -> R {{
+            let mut this = ::std::mem::ManuallyDrop::new(self);
+            unsafe {{
+                {prefix}_istream_delete(this.stream.as_mut_raw_ptr());
+                let state = ::std::ptr::read(&this.state);
+                state.inner
+            }}
+        }}
+    }}
+
+    impl<R: io::Read> Drop for ReadStream<R> {{
+        fn drop(&mut self) {{
+            unsafe {{ {prefix}_istream_delete(self.stream.as_mut_raw_ptr()) }}
+        }}
+    }}",
+            prefix = prefix,
+            t = istream
+        )?;
+    }
+    if let Some(ostream) = &types.ostream {
+        writeln!(
+            code,
+            "
+    extern \"C\" {{
+        fn {prefix}_ostream_new(
+            data: *mut c_void,
+            write: extern \"C\" fn(*mut c_void, *const c_char, usize) -> usize,
+            flush: extern \"C\" fn(*mut c_void) -> ::std::os::raw::c_int,
+        ) -> *mut {t};
+        fn {prefix}_ostream_delete(stream: *mut {t});
+    }}
+
+    extern \"C\" fn write_callback<W: io::Write>(
+        data: *mut c_void,
+        buf: *const c_char,
+        len: usize,
+    ) -> usize {{
+        let state = unsafe {{ &mut *(data as *mut State<W>) }};
+        let buf = unsafe {{ ::std::slice::from_raw_parts(buf as *const u8, len) }};
+        state.call(|writer| writer.write_all(buf).map(|()| buf.len()))
+    }}
+
+    extern \"C\" fn flush_callback<W: io::Write>(data: *mut c_void) -> ::std::os::raw::c_int {{
+        let state = unsafe {{ &mut *(data as *mut State<W>) }};
+        if state.call(|writer| writer.flush().map(|()| 0)) == 0 {{
+            0
+        }} else {{
+            -1
+        }}
+    }}
+
+    /// C++ output stream that writes data to a Rust writer.
+    pub struct WriteStream<W: io::Write> {{
+        stream: ::cpp_core::Ptr<{t}>,
+        state: Box<State<W>>,
+    }}
+
+    impl<W: io::Write> WriteStream<W> {{
+        /// Creates a stream writing to `writer`.
+        pub fn new(writer: W) -> Self {{
+            let mut state = Box::new(State {{
+                inner: writer,
+                error: None,
+            }});
+            let stream = unsafe {{
+                ::cpp_core::Ptr::from_raw({prefix}_ostream_new(
+                    &mut *state as *mut State<W> as *mut c_void,
+                    write_callback::<W>,
+                    flush_callback::<W>,
+                ))
+            }};
+            WriteStream {{ stream, state }}
+        }}
+
+        /// Returns a reference to the C++ stream that can be passed to C++ functions.
+        /// The reference must not be used after the adapter is dropped.
+        pub fn as_stream(&mut self) -> ::cpp_core::MutRef<{t}> {{
+            unsafe {{ self.stream.as_mut_ref().expect(\"stream is null\") }}
+        }}
+
+        /// Returns the last error returned by the writer, if any.
+        pub fn take_error(&mut self) -> Option<io::Error> {{
+            self.state.error.take()
+        }}
+
+        /// Flushes and destroys the C++ stream and returns the writer.
+        pub fn into_inner(self) -> W {{
+            let mut this = ::std::mem::ManuallyDrop::new(self);
+            unsafe {{
+                {prefix}_ostream_delete(this.stream.as_mut_raw_ptr());
+                let state = ::std::ptr::read(&this.state);
+                state.inner
+            }}
+        }}
+    }}
+
+    impl<W: io::Write> Drop for WriteStream<W> {{
+        fn drop(&mut self) {{
+            unsafe {{ {prefix}_ostream_delete(self.stream.as_mut_raw_ptr()) }}
+        }}
+    }}",
+            prefix = prefix,
+            t = ostream
+        )?;
+    }
+    writeln!(code, "}}")?;
+    Ok(code)
+}
@@ -0,0 +1,382 @@
+//! Generation of the `string_encoding` module of the crate
+//! (see `Config::set_string_encoding`).
+//!
+//! Besides functions encoding and decoding C strings, the module contains
+//! functions decoding `std::string` values if the crate or its dependencies
+//! contain a wrapper of `std::string`.
+
+use crate::config::StringEncoding;
+use crate::cpp_data::CppPath;
+use crate::cpp_type::{CppBuiltInNumericType, CppType};
+use crate::database::DatabaseClient;
+use ritual_common::errors::{err_msg, Result};
+
+/// The wrapper of `std::string` available to the crate.
+#[derive(Debug, Clone)]
+pub struct StdStringType {
+    pub cpp_type: CppPath,
+    /// Full path of the Rust wrapper type (e.g. `cpp_std::String`).
+    pub rust_type: String,
+}
+
+/// Returns the wrapper of `std::string` declared in the current crate
+/// or one of its dependencies.
+pub fn find_std_string_type(db: &DatabaseClient) -> Result<Option<StdStringType>> {
+    let cpp_type = db.all_cpp_items().find_map(|item| {
+        let declaration = item.item.as_type_ref()?;
+        if !declaration.kind.is_class() {
+            return None;
+        }
+        let path = &declaration.path;
+        match path.to_templateless_string().as_str() {
+            "std::basic_string" | "std::__cxx11::basic_string" => {}
+            _ => return None,
+        }
+        let args = path.last().template_arguments.as_ref()?;
+        if args.get(0)? == &CppType::BuiltInNumeric(CppBuiltInNumericType::Char) {
+            Some(path.clone())
+        } else {
+            None
+        }
+    });
+    let cpp_type = match cpp_type {
+        Some(cpp_type) => cpp_type,
+        None => return Ok(None),
+    };
+    let rust_item = db
+        .find_rust_items_for_cpp_path(&cpp_type, true)?
+        .find(|item| item.item.is_wrapper_type());
+    let rust_type = match rust_item {
+        Some(item) => item
+            .item
+            .path()
+            .ok_or_else(|| err_msg("wrapper type must have path"))?
+            .full_name(Some(db.crate_name())),
+        None => return Ok(None),
+    };
+    Ok(Some(StdStringType {
+        cpp_type,
+        rust_type,
+    }))
+}
+
+/// Returns Rust code of the `string_encoding` module.
+pub fn rust_module(
+    encoding: StringEncoding,
+    crate_name: &str,
+    std_string: Option<&StdStringType>,
+) -> String {
+    let (name, encode, decode) = match encoding {
+        StringEncoding::Utf8 => ("UTF-8", UTF8_ENCODE, UTF8_DECODE),
+        StringEncoding::Latin1 => ("Latin-1 (ISO 8859-1)", LATIN1_ENCODE, LATIN1_DECODE),
+        StringEncoding::Locale => (
+            "the encoding of the current C locale",
+            LOCALE_ENCODE,
+            LOCALE_DECODE,
+        ),
+    };
+    let prefix = helper_prefix(crate_name);
+    format!(
+        "/// Conversions between Rust strings and C strings in {name},
+/// the encoding used by the C++ library.
+///
+/// Strict functions fail if the string can't be represented in the target encoding
+/// (or contains a null character). Lossy functions replace such characters
+/// with `?` (when encoding) or U+FFFD (when decoding) and drop null characters.
+pub mod string_encoding {{
+    use ::std::ffi::{{CStr, CString}};
+    use ::std::os::raw::c_char;
+
+    /// Encodes `text`. Returns `None` if it can't be represented.
+    pub fn encode(text: &str) -> Option<CString> {{
+        encode_impl(text, false)
+    }}
+
+    /// Encodes `text`, replacing characters that can't be represented.
+    pub fn encode_lossy(text: &str) -> CString {{
+        encode_impl(text, true).expect(\"lossy encoding can't fail\")
+    }}
+
+    /// Decodes a null-terminated string. Returns `None` if it's not valid.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be a valid pointer to a null-terminated string.
+    pub unsafe fn decode(ptr: *const c_char) -> Option<String> {{
+        decode_impl(CStr::from_ptr(ptr).to_bytes(), false)
+    }}
+
+    /// Decodes a null-terminated string, replacing invalid sequences.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be a valid pointer to a null-terminated string.
+    pub unsafe fn decode_lossy(ptr: *const c_char) -> String {{
+        decode_impl(CStr::from_ptr(ptr).to_bytes(), true).expect(\"lossy decoding can't fail\")
+    }}
+
+    /// Decodes `bytes` (not including a null terminator). Returns `None` if it's not valid.
+    pub fn decode_bytes(bytes: &[u8]) -> Option<String> {{
+        decode_impl(bytes, false)
+    }}
+
+    /// Decodes `bytes` (not including a null terminator), replacing invalid sequences.
+    pub fn decode_bytes_lossy(bytes: &[u8]) -> String {{
+        decode_impl(bytes, true).expect(\"lossy decoding can't fail\")
+    }}
+{std_string}
+    fn to_c_string(mut bytes: Vec<u8>, lossy: bool) -> Option<CString> {{
+        if lossy {{
+            bytes.retain(|&byte| byte != 0);
+        }}
+        CString::new(bytes).ok()
+    }}
+
+{encode}
+{decode}
+}}
+",
+        name = name,
+        std_string = std_string.map_or(String::new(), |std_string| {
+            STD_STRING_DECODE
+                .replace("{prefix}", &prefix)
+                .replace("{t}", &std_string.rust_type)
+        }),
+        encode = encode.replace("{prefix}", &prefix),
+        decode = decode.replace("{prefix}", &prefix),
+    )
+}
+
+/// Returns C++ helper functions used by the `string_encoding` module.
+/// `StringEncoding::Locale` and decoding of `std::string` require them.
+/// The code must be placed in an `extern "C"` block.
+pub fn cpp_helpers(
+    encoding: StringEncoding,
+    crate_name: &str,
+    std_string: Option<&StdStringType>,
+) -> Result<String> {
+    let prefix = helper_prefix(crate_name);
+    let mut code = String::new();
+    if encoding == StringEncoding::Locale {
+        code.push_str(&LOCALE_CPP_HELPERS.replace("{prefix}", &prefix));
+    }
+    if let Some(std_string) = std_string {
+        let cpp_type = CppType::Class(std_string.cpp_type.clone()).to_cpp_code(None)?;
+        code.push_str(
+            &STD_STRING_CPP_HELPERS
+                .replace("{prefix}", &prefix)
+                .replace("{t}", &cpp_type),
+        );
+    }
+    Ok(code)
+}
+
+/// Returns include directives required by `cpp_helpers`.
+pub fn cpp_includes(encoding: StringEncoding) -> &'static [&'static str] {
+    if encoding == StringEncoding::Locale {
+        &["cwchar", "climits"]
+    } else {
+        &[]
+    }
+}
+
+fn helper_prefix(crate_name: &str) -> String {
+    format!("{}_string_encoding", crate_name)
+}
+
+const STD_STRING_DECODE: &str = "
+    extern \"C\" {
+        fn {prefix}_std_string_data(value: *const {t}, len: *mut usize) -> *const c_char;
+    }
+
+    /// Decodes content of a `std::string`. Returns `None` if it's not valid.
+    pub fn decode_std_string(value: &{t}) -> Option<String> {
+        decode_bytes(std_string_bytes(value))
+    }
+
+    /// Decodes content of a `std::string`, replacing invalid sequences.
+    pub fn decode_std_string_lossy(value: &{t}) -> String {
+        decode_bytes_lossy(std_string_bytes(value))
+    }
+
+    fn std_string_bytes(value: &{t}) -> &[u8] {
+        let mut len = 0;
+        unsafe {
+            let data = {prefix}_std_string_data(value, &mut len);
+            if len == 0 {
+                &[]
+            } else {
+                ::std::slice::from_raw_parts(data as *const u8, len)
+            }
+        }
+    }
+";
+
+const STD_STRING_CPP_HELPERS: &str =
+    "RITUAL_EXPORT const char* {prefix}_std_string_data(const {t}* value,
+                                                   size_t* len) {
+  *len = value->size();
+  return value->data();
+}
+";
+
+const UTF8_ENCODE: &str = "    fn encode_impl(text: &str, lossy: bool) -> Option<CString> {
+        to_c_string(text.as_bytes().to_vec(), lossy)
+    }
+";
+
+const UTF8_DECODE: &str = "    fn decode_impl(bytes: &[u8], lossy: bool) -> Option<String> {
+        if lossy {
+            Some(String::from_utf8_lossy(bytes).into_owned())
+        } else {
+            ::std::str::from_utf8(bytes).ok().map(String::from)
+        }
+    }
+";
+
+const LATIN1_ENCODE: &str = "    fn encode_impl(text: &str, lossy: bool) -> Option<CString> {
+        let mut bytes = Vec::with_capacity(text.len());
+        for c in text.chars() {
+            if (c as u32) < 0x100 {
+                bytes.push(c as u32 as u8);
+            } else if lossy {
+                bytes.push(b'?');
+            } else {
+                return None;
+            }
+        }
+        to_c_string(bytes, lossy)
+    }
+";
+
+const LATIN1_DECODE: &str = "    fn decode_impl(bytes: &[u8], _lossy: bool) -> Option<String> {
+        // all byte values are valid Latin-1 characters
+        Some(bytes.iter().map(|&byte| char::from(byte)).collect())
+    }
+";
+
+const LOCALE_ENCODE: &str = "    extern \"C\" {
+        fn {prefix}_encode(
+            input: *const ::cpp_core::wchar_t,
+            input_len: usize,
+            output: *mut c_char,
+            lossy: bool,
+        ) -> usize;
+        fn {prefix}_decode(
+            input: *const c_char,
+            input_len: usize,
+            output: *mut ::cpp_core::wchar_t,
+            lossy: bool,
+        ) -> usize;
+        fn {prefix}_max_char_len() -> usize;
+    }
+
+    fn encode_impl(text: &str, lossy: bool) -> Option<CString> {
+        let wide: Vec<::cpp_core::wchar_t> = if ::std::mem::size_of::<::cpp_core::wchar_t>() == 2 {
+            text.encode_utf16().map(|unit| unit as ::cpp_core::wchar_t).collect()
+        } else {
+            text.chars().map(|c| c as u32 as ::cpp_core::wchar_t).collect()
+        };
+        let mut output = vec![0u8; wide.len() * unsafe { {prefix}_max_char_len() }];
+        let len = unsafe {
+            {prefix}_encode(wide.as_ptr(), wide.len(), output.as_mut_ptr() as *mut c_char, lossy)
+        };
+        if len == usize::max_value() {
+            return None;
+        }
+        output.truncate(len);
+        to_c_string(output, lossy)
+    }
+";
+
+const LOCALE_DECODE: &str = "    fn decode_impl(bytes: &[u8], lossy: bool) -> Option<String> {
+        let mut wide: Vec<::cpp_core::wchar_t> = vec![0; bytes.len()];
+        let len = unsafe {
+            {prefix}_decode(bytes.as_ptr() as *const c_char, bytes.len(), wide.as_mut_ptr(), lossy)
+        };
+        if len == usize::max_value() {
+            return None;
+        }
+        wide.truncate(len);
+        if ::std::mem::size_of::<::cpp_core::wchar_t>() == 2 {
+            let units = wide.iter().map(|&unit| unit as u16).collect::<Vec<_>>();
+            if lossy {
+                Some(String::from_utf16_lossy(&units))
+            } else {
+                String::from_utf16(&units).ok()
+            }
+        } else {
+            let mut output = String::with_capacity(wide.len());
+            for &c in &wide {
+                match ::std::char::from_u32(c as u32) {
+                    Some(c) => output.push(c),
+                    None if lossy => output.push(::std::char::REPLACEMENT_CHARACTER),
+                    None => return None,
+                }
+            }
+            Some(output)
+        }
+    }
+";
+
+const LOCALE_CPP_HELPERS: &str = "RITUAL_EXPORT size_t {prefix}_max_char_len() {
+  return MB_LEN_MAX;
+}
+
+RITUAL_EXPORT size_t {prefix}_encode(const wchar_t* input, size_t input_len,
+                                     char* output, bool lossy) {
+  std::mbstate_t state = std::mbstate_t();
+  size_t len = 0;
+  for (size_t i = 0; i < input_len; i++) {
+    size_t r;
+    unsigned long unit = static_cast<unsigned long>(input[i]);
+    if (sizeof(wchar_t) == 2 && unit >= 0xD800 && unit <= 0xDFFF) {
+      // wcrtomb can't convert a surrogate pair one code unit at a time,
+      // so characters outside of the BMP are considered unrepresentable
+      if (unit <= 0xDBFF && i + 1 < input_len) {
+        unsigned long next = static_cast<unsigned long>(input[i + 1]);
+        if (next >= 0xDC00 && next <= 0xDFFF) {
+          i++;
+        }
+      }
+      r = static_cast<size_t>(-1);
+    } else {
+      r = std::wcrtomb(output + len, input[i], &state);
+    }
+    if (r == static_cast<size_t>(-1)) {
+      if (!lossy) {
+        return static_cast<size_t>(-1);
+      }
+      output[len] = '?';
+      r = 1;
+      state = std::mbstate_t();
+    }
+    len += r;
+  }
+  return len;
+}
+
+RITUAL_EXPORT size_t {prefix}_decode(const char* input, size_t input_len,
+                                     wchar_t* output, bool lossy) {
+  std::mbstate_t state = std::mbstate_t();
+  size_t len = 0;
+  size_t pos = 0;
+  while (pos < input_len) {
+    wchar_t c;
+    size_t r = std::mbrtowc(&c, input + pos, input_len - pos, &state);
+    if (r == static_cast<size_t>(-1) || r == static_cast<size_t>(-2)) {
+      if (!lossy) {
+        return static_cast<size_t>(-1);
+      }
+      c = static_cast<wchar_t>(0xFFFD);
+      r = 1;
+      state = std::mbstate_t();
+    } else if (r == 0) {
+      r = 1;
+    }
+    output[len++] = c;
+    pos += r;
+  }
+  return len;
+}
+";
@@ -0,0 +1,203 @@
+//! Checks that the built C++ wrapper library exports all FFI functions
+//! declared in the generated crate.
+
+use crate::processor::ProcessorData;
+use itertools::Itertools;
+use log::{debug, info, warn};
+use ritual_common::errors::{bail, Result};
+use ritual_common::file_utils::read_dir;
+use ritual_common::target::{Env, LibraryTarget};
+use ritual_common::utils::get_command_output;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Discrepancies between the FFI functions expected in the C++ wrapper library
+/// and the symbols exported by the library built on a library target.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolAudit {
+    pub env: LibraryTarget,
+    /// FFI functions used by the crate that the library doesn't export.
+    pub missing: Vec<String>,
+    /// Exported FFI functions that the crate doesn't use.
+    pub unexpected: Vec<String>,
+}
+
+/// Returns names of defined external symbols in the output of `nm -g --defined-only`.
+pub fn parse_nm_output(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let parts = line.split_whitespace().collect_vec();
+            match parts.as_slice() {
+                [_, kind, name] | [kind, name] if kind.len() == 1 && *kind != "U" => {
+                    Some(name.to_string())
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Returns names of defined external symbols in the output of `dumpbin /SYMBOLS`.
+pub fn parse_dumpbin_output(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| line.contains(" External ") && !line.contains(" UNDEF "))
+        .filter_map(|line| line.split('|').nth(1))
+        .filter_map(|name| name.split_whitespace().next())
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Returns build script output directories of all profiles
+/// (`target/<profile>/build`) and cross-compilation targets
+/// (`target/<triple>/<profile>/build`) in `target_dir`.
+fn build_dirs(target_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut result = Vec::new();
+    if !target_dir.is_dir() {
+        return Ok(result);
+    }
+    for item in read_dir(target_dir)? {
+        let path = item?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if path.join("build").is_dir() {
+            result.push(path.join("build"));
+        } else {
+            for sub_item in read_dir(&path)? {
+                let build_dir = sub_item?.path().join("build");
+                if build_dir.is_dir() {
+                    result.push(build_dir);
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Finds files of the C++ wrapper library installed by the build script
+/// in any profile or target of `target_dir`.
+pub fn find_library_files(target_dir: &Path, crate_name: &str) -> Result<Vec<PathBuf>> {
+    let lib_name = format!("{}_c", crate_name);
+    let file_names = [
+        format!("lib{}.a", lib_name),
+        format!("lib{}.so", lib_name),
+        format!("lib{}.dylib", lib_name),
+        format!("{}.lib", lib_name),
+    ];
+    let mut result = Vec::new();
+    for build_dir in build_dirs(target_dir)? {
+        for item in read_dir(&build_dir)? {
+            let item = item?;
+            let dir_name = item.file_name().to_string_lossy().into_owned();
+            if !dir_name.starts_with(&format!("{}-", crate_name)) {
+                continue;
+            }
+            for subdir in &["lib", "lib64", "."] {
+                let dir = item.path().join("out").join("c_lib_install").join(subdir);
+                for file_name in &file_names {
+                    let path = dir.join(file_name);
+                    if path.exists() {
+                        result.push(path);
+                    }
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn exported_symbols(path: &Path, env: Env) -> Result<Vec<String>> {
+    if env == Env::Msvc {
+        let output = get_command_output(Command::new("dumpbin").arg("/SYMBOLS").arg(path))?;
+        Ok(parse_dumpbin_output(&output))
+    } else {
+        let mut command = Command::new("nm");
+        command.arg("-g").arg("--defined-only");
+        if path.extension().map_or(false, |ext| ext != "a") {
+            command.arg("-D");
+        }
+        let output = get_command_output(command.arg(path))?;
+        Ok(parse_nm_output(&output))
+    }
+}
+
+/// Compares symbols exported by the built C++ wrapper library with
+/// FFI functions used by the crate on the current target and records
+/// the discrepancies in the database.
+pub fn run(data: &mut ProcessorData<'_>) -> Result<()> {
+    let crate_name = data.config.crate_properties().name().to_string();
+    let target_dir = data.workspace.target_path();
+    let library = match find_library_files(&target_dir, &crate_name)?
+        .into_iter()
+        .max_by_key(|path| path.metadata().and_then(|m| m.modified()).ok())
+    {
+        Some(path) => path,
+        None => bail!(
+            "symbol audit: C++ wrapper library of {} not found in {} (run build_crate first)",
+            crate_name,
+            target_dir.display()
+        ),
+    };
+    debug!("symbol audit: checking {}", library.display());
+
+    let env = data.config.current_library_target()?;
+    let prefix = format!("ctr_{}_ffi_", crate_name);
+    let exported = exported_symbols(&library, env.target.env)?
+        .into_iter()
+        .map(|name| {
+            // symbols of C functions have an extra underscore on some platforms
+            if name.starts_with('_') && name[1..].starts_with(&prefix) {
+                name[1..].to_string()
+            } else {
+                name
+            }
+        })
+        .filter(|name| name.starts_with(&prefix))
+        .collect::<BTreeSet<_>>();
+
+    let used = data
+        .db
+        .rust_items()
+        .filter_map(|item| item.item.as_function_ref())
+        .filter(|item| item.kind.is_ffi_function())
+        .map(|item| item.path.last().to_string())
+        .collect::<BTreeSet<_>>();
+    let mut expected = BTreeSet::new();
+    for item in data.db.ffi_items() {
+        let function = match item.item.as_function_ref() {
+            Some(function) => function,
+            None => continue,
+        };
+        let name = function.path.last().name.clone();
+        if !used.contains(&name) && !function.out_of_line {
+            continue;
+        }
+        if data.db.cpp_checks(&item.id)?.is_success(&env) {
+            expected.insert(name);
+        }
+    }
+
+    let audit = SymbolAudit {
+        env,
+        missing: expected.difference(&exported).cloned().collect(),
+        unexpected: exported.difference(&expected).cloned().collect(),
+    };
+    for name in &audit.missing {
+        warn!("symbol audit: FFI function is not exported: {}", name);
+    }
+    for name in &audit.unexpected {
+        warn!("symbol audit: unexpected exported FFI function: {}", name);
+    }
+    info!(
+        "symbol audit: {} expected, {} missing, {} unexpected",
+        expected.len(),
+        audit.missing.len(),
+        audit.unexpected.len()
+    );
+    data.db.set_symbol_audit(audit);
+    Ok(())
+}
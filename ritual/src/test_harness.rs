@@ -0,0 +1,211 @@
+//! End-to-end test harness.
+//!
+//! `EndToEndTest` runs all processing steps for a crate, including building
+//! and testing the generated crate, and compares the generated Rust sources
+//! with golden files. Binding projects can use it to regression-test their
+//! configuration. `bundled_library_config` provides a small C++ library
+//! that exercises the common features of the generator.
+//!
+//! Set the `RITUAL_UPDATE_GOLDEN` environment variable to write the generated
+//! sources to the golden directory instead of comparing them.
+
+use crate::config::{Config, CrateProperties};
+use crate::processor;
+use crate::workspace::Workspace;
+use itertools::Itertools;
+use log::info;
+use ritual_common::cpp_build_config::CppBuildPaths;
+use ritual_common::errors::{bail, Result};
+use ritual_common::file_utils::{
+    create_dir_all, create_file, file_to_string, os_string_into_string, read_dir, remove_dir_all,
+};
+use std::collections::BTreeMap;
+use std::env;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Name of the environment variable that enables updating golden files.
+pub const UPDATE_GOLDEN_ENV_VAR_NAME: &str = "RITUAL_UPDATE_GOLDEN";
+
+/// Name of the crate generated for the bundled C++ library.
+pub const BUNDLED_LIBRARY_CRATE_NAME: &str = "ritual_test_lib";
+
+/// Writes the bundled C++ test library to `dir` and returns the config
+/// for generating its crate.
+pub fn bundled_library_config(dir: &Path) -> Result<Config> {
+    let include_dir = dir.join("include");
+    create_dir_all(&include_dir)?;
+    let header_name = "ritual_test_lib.h";
+    let header_path = include_dir.join(header_name);
+    write!(
+        create_file(&header_path)?,
+        "{}",
+        include_str!("../templates/test_lib/ritual_test_lib.h")
+    )?;
+
+    let mut paths = CppBuildPaths::new();
+    paths.add_include_path(include_dir);
+
+    let mut config = Config::new(CrateProperties::new(BUNDLED_LIBRARY_CRATE_NAME, "0.0.0"));
+    config.add_include_directive(header_name);
+    config.set_cpp_build_paths(paths);
+    config.add_target_include_path(header_path);
+    config.set_write_dependencies_local_paths(true);
+    Ok(config)
+}
+
+/// End-to-end test of a generated crate.
+pub struct EndToEndTest {
+    config: Config,
+    tests: Vec<(String, String)>,
+    golden_dir: Option<PathBuf>,
+}
+
+impl EndToEndTest {
+    /// Creates a test of the crate generated with `config`.
+    pub fn new(config: Config) -> Self {
+        EndToEndTest {
+            config,
+            tests: Vec::new(),
+            golden_dir: None,
+        }
+    }
+
+    /// Adds a Rust test to the generated crate. `body` is placed inside
+    /// an `unsafe` block of the test function and can use the crate's API
+    /// by its name. The test is run by the `build_crate` step.
+    pub fn add_test(&mut self, name: impl Into<String>, body: impl Into<String>) {
+        self.tests.push((name.into(), body.into()));
+    }
+
+    /// Sets the directory containing the expected Rust sources of the crate.
+    /// If not set, the sources are not compared.
+    pub fn set_golden_dir(&mut self, path: impl Into<PathBuf>) {
+        self.golden_dir = Some(path.into());
+    }
+
+    /// Runs all processing steps in the workspace at `workspace_path`
+    /// and compares the generated sources with the golden files.
+    pub fn run(mut self, workspace_path: &Path) -> Result<()> {
+        let crate_name = self.config.crate_properties().name().to_string();
+        let tests = self.tests;
+        self.config.processing_steps_mut().add_after(
+            &["crate_writer"],
+            "write_harness_tests",
+            move |data| {
+                let path = data
+                    .workspace
+                    .crate_path(data.config.crate_properties().name())
+                    .join("tests");
+                create_dir_all(&path)?;
+                let mut file = create_file(path.join("ritual_harness.rs"))?;
+                for (name, body) in &tests {
+                    writeln!(
+                        file,
+                        "#[test]\n#[allow(unused_unsafe)]\nfn {}() {{\nunsafe {{\n{}\n}}\n}}\n",
+                        name, body
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+
+        create_dir_all(workspace_path)?;
+        let mut workspace = Workspace::new(workspace_path.to_path_buf())?;
+        processor::process(
+            &mut workspace,
+            &self.config,
+            &["discard".to_string(), "main".to_string()],
+            None,
+        )?;
+
+        if let Some(golden_dir) = &self.golden_dir {
+            let src_path = workspace.crate_path(&crate_name).join("src");
+            if env::var(UPDATE_GOLDEN_ENV_VAR_NAME).is_ok() {
+                update_golden(&src_path, golden_dir)?;
+            } else {
+                compare_with_golden(&src_path, golden_dir)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns contents of all files in `dir` and its subdirectories
+/// by their path relative to `dir`.
+fn read_files(dir: &Path) -> Result<BTreeMap<PathBuf, String>> {
+    fn add_files(
+        dir: &Path,
+        relative_path: &Path,
+        output: &mut BTreeMap<PathBuf, String>,
+    ) -> Result<()> {
+        for item in read_dir(dir)? {
+            let item = item?;
+            let relative_path = relative_path.join(os_string_into_string(item.file_name())?);
+            if item.path().is_dir() {
+                add_files(&item.path(), &relative_path, output)?;
+            } else {
+                output.insert(relative_path, file_to_string(item.path())?);
+            }
+        }
+        Ok(())
+    }
+
+    let mut output = BTreeMap::new();
+    add_files(dir, Path::new(""), &mut output)?;
+    Ok(output)
+}
+
+/// Compares files in `actual_dir` with files in `golden_dir`.
+/// Returns an error listing the files that are different, missing or unexpected.
+pub fn compare_with_golden(actual_dir: &Path, golden_dir: &Path) -> Result<()> {
+    let actual = read_files(actual_dir)?;
+    let golden = if golden_dir.exists() {
+        read_files(golden_dir)?
+    } else {
+        BTreeMap::new()
+    };
+    let mut errors = Vec::new();
+    for (path, content) in &actual {
+        match golden.get(path) {
+            None => errors.push(format!("unexpected file: {}", path.display())),
+            Some(golden_content) if golden_content != content => {
+                errors.push(format!("file is different: {}", path.display()));
+            }
+            Some(_) => {}
+        }
+    }
+    for path in golden.keys() {
+        if !actual.contains_key(path) {
+            errors.push(format!("missing file: {}", path.display()));
+        }
+    }
+    if !errors.is_empty() {
+        bail!(
+            "generated files don't match golden files in {}:\n{}\n\
+             Set {} environment variable to update golden files.",
+            golden_dir.display(),
+            errors.iter().join("\n"),
+            UPDATE_GOLDEN_ENV_VAR_NAME
+        );
+    }
+    Ok(())
+}
+
+/// Replaces contents of `golden_dir` with files from `actual_dir`.
+fn update_golden(actual_dir: &Path, golden_dir: &Path) -> Result<()> {
+    if golden_dir.exists() {
+        remove_dir_all(golden_dir)?;
+    }
+    for (path, content) in read_files(actual_dir)? {
+        let golden_path = golden_dir.join(path);
+        create_dir_all(
+            golden_path
+                .parent()
+                .expect("golden file path must have parent"),
+        )?;
+        write!(create_file(&golden_path)?, "{}", content)?;
+    }
+    info!("Updated golden files in {}", golden_dir.display());
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use crate::clang_cache::ClangCache;
+use std::fs;
+
+#[test]
+fn key_mismatch_is_a_miss() {
+    let dir = tempdir::TempDir::new("test_clang_cache").unwrap();
+    let cache = ClangCache::new(dir.path().join("cache")).unwrap();
+    let key = "0123abcd";
+    let ast_path = cache.path().join(format!("{}.ast", key));
+    let entry_path = cache.path().join(format!("{}.json", key));
+
+    fs::write(&ast_path, "").unwrap();
+    fs::write(&entry_path, r#"{"key":"0123abcd","dependencies":[]}"#).unwrap();
+    assert!(cache.contains(key).unwrap());
+
+    fs::write(&entry_path, r#"{"key":"4567cdef","dependencies":[]}"#).unwrap();
+    assert!(!cache.contains(key).unwrap());
+    assert!(!ast_path.exists());
+    assert!(!entry_path.exists());
+
+    // entries saved without a key are outdated
+    fs::write(&ast_path, "").unwrap();
+    fs::write(&entry_path, r#"{"dependencies":[]}"#).unwrap();
+    assert!(!cache.contains(key).unwrap());
+}
@@ -0,0 +1,34 @@
+use crate::clang_compat::{ClangCompat, ClangFeature, ClangVersion};
+
+#[test]
+fn parse_version() {
+    assert_eq!(
+        ClangVersion::parse("clang version 8.0.0 (tags/RELEASE_800/final)"),
+        Some(ClangVersion::new(8, 0))
+    );
+    assert_eq!(
+        ClangVersion::parse("Ubuntu clang version 3.8.1-24 (tags/RELEASE_381/final)"),
+        Some(ClangVersion::new(3, 8))
+    );
+    assert_eq!(
+        ClangVersion::parse("Apple LLVM version 10.0.1 (clang-1001.0.46.4)"),
+        Some(ClangVersion::new(6, 0))
+    );
+    assert_eq!(
+        ClangVersion::parse("Apple clang version 11.0.0 (clang-1100.0.33.8)"),
+        Some(ClangVersion::new(8, 0))
+    );
+    assert_eq!(ClangVersion::parse("clang"), None);
+}
+
+#[test]
+fn degraded_features() {
+    let old = ClangCompat::for_version(ClangVersion::new(3, 8));
+    assert!(old.supports(ClangFeature::FieldLayout));
+    assert_eq!(
+        old.degraded_features(),
+        vec![ClangFeature::TypeTemplateArguments]
+    );
+    let new = ClangCompat::for_version(ClangVersion::new(9, 0));
+    assert!(new.degraded_features().is_empty());
+}
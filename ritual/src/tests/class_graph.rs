@@ -0,0 +1,68 @@
+use crate::class_graph::{ClassGraph, ClassGraphEdge, ClassGraphEdgeKind};
+use crate::cpp_data::{CppBaseSpecifier, CppItem, CppPath, CppVisibility};
+use crate::cpp_type::{CppPointerLikeTypeKind, CppType};
+use crate::tests::class;
+use crate::tests::cpp_method::{empty_membership, empty_regular_method};
+use itertools::Itertools;
+
+fn items() -> Vec<CppItem> {
+    let mut method = empty_regular_method();
+    method.path = CppPath::from_good_str("A::get_c");
+    method.member = Some(empty_membership());
+    method.return_type = CppType::PointerLike {
+        kind: CppPointerLikeTypeKind::Pointer,
+        is_const: false,
+        target: Box::new(CppType::Class(CppPath::from_good_str("C"))),
+    };
+    vec![
+        class("A"),
+        class("B"),
+        class("C"),
+        CppItem::ClassBase(CppBaseSpecifier {
+            base_class_type: CppPath::from_good_str("B"),
+            base_index: 0,
+            is_virtual: false,
+            visibility: CppVisibility::Public,
+            derived_class_type: CppPath::from_good_str("A"),
+        }),
+        CppItem::Function(method),
+    ]
+}
+
+#[test]
+fn inheritance_graph() {
+    let items = items();
+    let graph = ClassGraph::from_items(&items.iter().collect_vec(), false);
+    assert_eq!(graph.classes.iter().collect_vec(), vec!["A", "B", "C"]);
+    assert_eq!(
+        graph.edges.iter().collect_vec(),
+        vec![&ClassGraphEdge {
+            from: "B".into(),
+            to: "A".into(),
+            kind: ClassGraphEdgeKind::Inheritance,
+        }]
+    );
+
+    let mut dot = Vec::new();
+    graph.write_dot(&mut dot).unwrap();
+    let dot = String::from_utf8(dot).unwrap();
+    assert!(dot.starts_with("digraph classes {\n"));
+    assert!(dot.contains("    \"B\" -> \"A\";\n"));
+}
+
+#[test]
+fn type_graph() {
+    let items = items();
+    let graph = ClassGraph::from_items(&items.iter().collect_vec(), true);
+    assert_eq!(graph.edges.len(), 2);
+    assert!(graph.edges.contains(&ClassGraphEdge {
+        from: "A".into(),
+        to: "C".into(),
+        kind: ClassGraphEdgeKind::MemberType,
+    }));
+
+    let mut dot = Vec::new();
+    graph.write_dot(&mut dot).unwrap();
+    let dot = String::from_utf8(dot).unwrap();
+    assert!(dot.contains("    \"A\" -> \"C\" [style=dashed];\n"));
+}
@@ -0,0 +1,64 @@
+use crate::cli::{cargo_ritual_metadata, CargoRitualMetadata, Options};
+use crate::workspace::CleanScope;
+use serde_json::json;
+use structopt::StructOpt;
+
+#[test]
+fn cargo_metadata_of_package() {
+    let metadata = json!({
+        "packages": [{
+            "manifest_path": "/ws/foo/Cargo.toml",
+            "version": "0.3.0",
+            "metadata": { "ritual": { "crates": ["moqt_core"] } }
+        }],
+        "metadata": { "ritual": { "crates": ["other"] } }
+    });
+    let (ritual, version) = cargo_ritual_metadata(&metadata, "/ws/foo/Cargo.toml").unwrap();
+    assert_eq!(ritual.crates, vec!["moqt_core".to_string()]);
+    assert_eq!(version, Some("0.3.0".to_string()));
+}
+
+#[test]
+fn cargo_metadata_of_virtual_workspace() {
+    let metadata = json!({
+        "packages": [{
+            "manifest_path": "/ws/foo/Cargo.toml",
+            "version": "0.3.0",
+            "metadata": null
+        }],
+        "metadata": { "ritual": { "crates": ["moqt_core"], "version": "0.1.0" } }
+    });
+    let (ritual, version) = cargo_ritual_metadata(&metadata, "/ws/Cargo.toml").unwrap();
+    assert_eq!(
+        ritual,
+        CargoRitualMetadata {
+            crates: vec!["moqt_core".to_string()],
+            operations: Vec::new(),
+            version: Some("0.1.0".to_string()),
+            local_paths: None,
+        }
+    );
+    assert_eq!(version, None);
+
+    let empty = json!({ "packages": [], "metadata": null });
+    let (ritual, version) = cargo_ritual_metadata(&empty, "/ws/Cargo.toml").unwrap();
+    assert_eq!(ritual, CargoRitualMetadata::default());
+    assert_eq!(version, None);
+}
+
+#[test]
+fn retry_crashed_headers_option() {
+    let args = ["ritual", "ws", "-c", "foo", "-o", "main", "-v", "0.1.0"];
+    let options = Options::from_iter(args.iter());
+    assert!(!options.retry_crashed_headers);
+    let options = Options::from_iter(args.iter().chain(&["--retry-crashed-headers"]));
+    assert!(options.retry_crashed_headers);
+}
+
+#[test]
+fn clean_without_version() {
+    let options = Options::from_iter(&["ritual", "ws", "--clean", "logs"]);
+    assert_eq!(options.clean, Some(CleanScope::Logs));
+    assert!(options.output_crates_version.is_none());
+    assert!(options.crates.is_empty());
+}
@@ -0,0 +1,72 @@
+use crate::config::{AsyncFunctionRule, Config, CrateProperties};
+use crate::cpp_data::{CppPath, CppPathItem};
+use crate::cpp_type::{CppBuiltInNumericType, CppType};
+use ritual_common::file_utils::{create_dir_all, create_file};
+use ritual_common::target::current_target;
+use serde_json::json;
+use std::io::Write;
+
+fn start_instantiation(argument: CppBuiltInNumericType) -> CppPath {
+    CppPath::from_good_str("ns").join(CppPathItem {
+        name: "start".into(),
+        template_arguments: Some(vec![CppType::BuiltInNumeric(argument)]),
+    })
+}
+
+#[test]
+fn async_function_rule_matches() {
+    let rule = AsyncFunctionRule {
+        function: CppPath::from_good_str("ns::start"),
+        callback_argument: "callback".into(),
+        data_argument: "data".into(),
+    };
+    assert!(rule.matches(&CppPath::from_good_str("ns::start")));
+    assert!(rule.matches(&start_instantiation(CppBuiltInNumericType::Int)));
+    assert!(!rule.matches(&CppPath::from_good_str("start")));
+    assert!(!rule.matches(&CppPath::from_good_str("ns::stop")));
+
+    let instantiation_rule = AsyncFunctionRule {
+        function: start_instantiation(CppBuiltInNumericType::Int),
+        ..rule
+    };
+    assert!(instantiation_rule.matches(&start_instantiation(CppBuiltInNumericType::Int)));
+    assert!(!instantiation_rule.matches(&start_instantiation(CppBuiltInNumericType::Double)));
+    assert!(!instantiation_rule.matches(&CppPath::from_good_str("ns::start")));
+}
+
+#[test]
+fn compile_commands_flags() {
+    let dir = tempdir::TempDir::new("test_compile_commands").unwrap();
+    let include = dir.path().join("include");
+    create_dir_all(&include).unwrap();
+    create_file(include.join("widget.h")).unwrap();
+    create_file(include.join("label.h")).unwrap();
+    let database = json!([
+        {
+            "directory": dir.path(),
+            "file": "src/widget.cpp",
+            "arguments": ["c++", "-Iinclude", "-DWIDGET", "-Dfoo_EXPORTS", "-c", "src/widget.cpp"]
+        },
+        {
+            "directory": dir.path(),
+            "file": "src/label.cpp",
+            "arguments": ["c++", "-Iinclude", "-DLABEL", "-Dfoo_EXPORTS", "-c", "src/label.cpp"]
+        }
+    ]);
+    let database_path = dir.path().join("compile_commands.json");
+    write!(create_file(&database_path).unwrap(), "{}", database).unwrap();
+
+    let mut config = Config::new(CrateProperties::new("foo", "0.0.0"));
+    config.add_include_directive("widget.h");
+    config.add_compile_commands(&database_path, None).unwrap();
+    assert_eq!(config.cpp_build_paths().include_paths(), &[include]);
+    assert_eq!(
+        config.compile_commands_flags(&["label.h".into()]),
+        vec!["-DLABEL".to_string()]
+    );
+    let data = config
+        .full_cpp_build_config()
+        .eval(&current_target())
+        .unwrap();
+    assert_eq!(data.compiler_flags(), &["-DWIDGET"]);
+}
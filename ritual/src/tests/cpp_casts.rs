@@ -0,0 +1,150 @@
+use crate::config::{Config, CrateProperties};
+use crate::cpp_casts::generate_casts;
+use crate::cpp_data::{CppBaseSpecifier, CppItem, CppPath, CppVisibility};
+use crate::cpp_type::CppType;
+use std::collections::{HashMap, HashSet};
+
+fn config() -> Config {
+    Config::new(CrateProperties::new("A", "0.0.0"))
+}
+
+fn involves_class(item: &CppItem, name: &str) -> bool {
+    let class_type = CppType::Class(CppPath::from_good_str(name));
+    item.all_involved_types()
+        .iter()
+        .any(|type1| type1.pointer_like_to_target().ok() == Some(&class_type))
+}
+
+fn polymorphic_types() -> HashSet<CppPath> {
+    ["A", "B", "C", "D"]
+        .iter()
+        .map(|name| CppPath::from_good_str(name))
+        .collect()
+}
+
+fn diamond(is_virtual: bool) -> HashMap<CppPath, Vec<CppBaseSpecifier>> {
+    let base = |derived: &str, base: &str, base_index: usize| CppBaseSpecifier {
+        base_class_type: CppPath::from_good_str(base),
+        base_index,
+        is_virtual,
+        visibility: CppVisibility::Public,
+        derived_class_type: CppPath::from_good_str(derived),
+    };
+    let mut bases = HashMap::new();
+    bases.insert(
+        CppPath::from_good_str("D"),
+        vec![base("D", "B", 0), base("D", "C", 1)],
+    );
+    bases.insert(CppPath::from_good_str("B"), vec![base("B", "A", 0)]);
+    bases.insert(CppPath::from_good_str("C"), vec![base("C", "A", 0)]);
+    bases
+}
+
+#[test]
+fn ambiguous_base() {
+    let mut skipped = Vec::new();
+    let casts = generate_casts(
+        &CppPath::from_good_str("D"),
+        &diamond(false),
+        &polymorphic_types(),
+        &config(),
+        &mut skipped,
+    )
+    .unwrap();
+    // static downcast, static upcast and dynamic cast for each of B and C
+    assert_eq!(casts.len(), 6);
+    assert!(casts.iter().all(|(_, item)| !involves_class(item, "A")));
+    assert_eq!(skipped.len(), 1);
+    assert!(skipped[0].reason.contains("D -> B -> A, D -> C -> A"));
+}
+
+#[test]
+fn virtual_base() {
+    let mut skipped = Vec::new();
+    let casts = generate_casts(
+        &CppPath::from_good_str("D"),
+        &diamond(true),
+        &polymorphic_types(),
+        &config(),
+        &mut skipped,
+    )
+    .unwrap();
+    assert_eq!(casts.len(), 9);
+    assert!(skipped.is_empty());
+    let downcasts = casts
+        .iter()
+        .filter_map(|(_, item)| item.as_function_ref())
+        .filter(|function| {
+            function
+                .cast
+                .as_ref()
+                .map_or(false, |cast| cast.is_unsafe_static_cast())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(downcasts.len(), 3);
+    for function in downcasts {
+        assert_eq!(function.path.items()[0].name, "ritual");
+        assert_eq!(function.path.last().name, "virtual_base_downcast");
+    }
+}
+
+#[test]
+fn non_polymorphic_virtual_base() {
+    let mut skipped = Vec::new();
+    let casts = generate_casts(
+        &CppPath::from_good_str("D"),
+        &diamond(true),
+        &HashSet::new(),
+        &config(),
+        &mut skipped,
+    )
+    .unwrap();
+    // only upcasts
+    assert_eq!(casts.len(), 3);
+    assert!(casts.iter().all(|(_, item)| {
+        item.as_function_ref().map_or(false, |function| {
+            function.path.last().name != "virtual_base_downcast"
+        })
+    }));
+    assert_eq!(skipped.len(), 3);
+    assert!(skipped
+        .iter()
+        .all(|skipped| skipped.reason == "virtual base class is not polymorphic"));
+}
+
+#[test]
+fn rtti_free() {
+    let mut config = config();
+    config.set_rtti_free(true);
+    config.add_library_cast(
+        CppPath::from_good_str("B"),
+        CppPath::from_good_str("lib::cast"),
+    );
+    let mut skipped = Vec::new();
+    let casts = generate_casts(
+        &CppPath::from_good_str("D"),
+        &diamond(true),
+        &polymorphic_types(),
+        &config,
+        &mut skipped,
+    )
+    .unwrap();
+    let functions = casts
+        .iter()
+        .filter_map(|(_, item)| item.as_function_ref())
+        .collect::<Vec<_>>();
+    // only upcasts and the library cast from B
+    assert_eq!(functions.len(), 4);
+    assert!(functions
+        .iter()
+        .all(|function| function.path.last().name != "dynamic_cast"));
+    let library_casts = functions
+        .iter()
+        .filter(|function| function.path.to_templateless_string() == "lib::cast")
+        .collect::<Vec<_>>();
+    assert_eq!(library_casts.len(), 1);
+    assert!(involves_class(
+        &CppItem::Function((*library_casts[0]).clone()),
+        "B"
+    ));
+}
@@ -0,0 +1,65 @@
+use crate::config::{Config, CrateProperties};
+use crate::cpp_checker::{env_cpp_standard, unavailable_std_types};
+use crate::cpp_data::CppPath;
+use crate::cpp_type::CppType;
+use ritual_common::cpp_build_config::{CompilerFlagSet, CppStandard};
+use ritual_common::target::{current_target, LibraryTarget};
+
+fn template_type(path: &str, arguments: Vec<CppType>) -> CppType {
+    let mut path = CppPath::from_good_str(path);
+    path.last_mut().template_arguments = Some(arguments);
+    CppType::Class(path)
+}
+
+#[test]
+fn std_types_of_newer_standard() {
+    let optional = template_type(
+        "std::optional",
+        vec![CppType::Class(CppPath::from_good_str("A"))],
+    );
+    let vector = template_type("std::vector", vec![optional.clone()]);
+    let type1 = CppType::new_reference(true, vector);
+
+    let paths = unavailable_std_types(&type1, CppStandard::Cpp11);
+    assert_eq!(paths.len(), 1);
+    assert_eq!(paths[0].to_templateless_string(), "std::optional");
+    assert!(unavailable_std_types(&type1, CppStandard::Cpp17).is_empty());
+    assert!(unavailable_std_types(
+        &CppType::Class(CppPath::from_good_str("optional")),
+        CppStandard::Cpp11
+    )
+    .is_empty());
+}
+
+#[test]
+fn standard_of_environment() {
+    let mut config = Config::new(CrateProperties::new("A", "0.0.0"));
+    config.add_compiler_flag_set(CompilerFlagSet::new("cpp17", vec!["-std=c++17"]));
+    config.add_compiler_flag_set(CompilerFlagSet::new("extra", vec!["-DFOO"]));
+    let env = |flag_set: Option<&str>| LibraryTarget {
+        target: current_target(),
+        cpp_library_version: None,
+        enabled_modules: Default::default(),
+        flag_set: flag_set.map(String::from),
+    };
+
+    assert_eq!(env_cpp_standard(&config, &env(None)), None);
+    assert_eq!(
+        env_cpp_standard(&config, &env(Some("cpp17"))),
+        Some(CppStandard::Cpp17)
+    );
+
+    config.set_cpp_standard(CppStandard::Cpp14);
+    assert_eq!(
+        env_cpp_standard(&config, &env(None)),
+        Some(CppStandard::Cpp14)
+    );
+    assert_eq!(
+        env_cpp_standard(&config, &env(Some("extra"))),
+        Some(CppStandard::Cpp14)
+    );
+    assert_eq!(
+        env_cpp_standard(&config, &env(Some("cpp17"))),
+        Some(CppStandard::Cpp17)
+    );
+}
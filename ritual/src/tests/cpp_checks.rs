@@ -0,0 +1,145 @@
+use crate::cpp_checks::{Condition, CppChecks, CppChecksItem};
+use ritual_common::target::{current_target, Arch, Env, LibraryTarget, Target, OS};
+
+fn env(target: Target) -> LibraryTarget {
+    LibraryTarget {
+        target,
+        cpp_library_version: None,
+        enabled_modules: Default::default(),
+        flag_set: None,
+    }
+}
+
+#[test]
+fn os_condition() {
+    let mut host = current_target();
+    host.os = OS::Linux;
+    let envs = vec![env(host), env(Target::emscripten())];
+
+    let checks = CppChecks::new(envs.iter().map(|env| CppChecksItem {
+        env: env.clone(),
+        is_success: env.target.os != OS::Emscripten,
+    }));
+    assert_eq!(
+        checks.condition(&envs),
+        Condition::Not(Box::new(Condition::OS(OS::Emscripten)))
+    );
+
+    let checks = CppChecks::new(envs.iter().map(|env| CppChecksItem {
+        env: env.clone(),
+        is_success: true,
+    }));
+    assert_eq!(checks.condition(&envs), Condition::True);
+}
+
+#[test]
+fn lib_version_condition_order() {
+    let envs = ["5.13", "5.11", "5.12", "5.14"]
+        .iter()
+        .map(|version| LibraryTarget {
+            target: current_target(),
+            cpp_library_version: Some(version.to_string()),
+            enabled_modules: Default::default(),
+            flag_set: None,
+        })
+        .collect::<Vec<_>>();
+    let checks = CppChecks::new(envs.iter().map(|env| CppChecksItem {
+        env: env.clone(),
+        is_success: env.cpp_library_version.as_ref().unwrap() != "5.11",
+    }));
+    assert_eq!(
+        checks.condition(&envs),
+        Condition::Or(vec![
+            Condition::CppLibraryVersion("5.12".into()),
+            Condition::CppLibraryVersion("5.13".into()),
+            Condition::CppLibraryVersion("5.14".into()),
+        ])
+    );
+}
+
+#[test]
+fn feature_condition() {
+    let with_module = LibraryTarget {
+        target: current_target(),
+        cpp_library_version: None,
+        enabled_modules: vec!["opengl".to_string()].into_iter().collect(),
+        flag_set: None,
+    };
+    let envs = vec![env(current_target()), with_module];
+    let checks = CppChecks::new(envs.iter().map(|env| CppChecksItem {
+        env: env.clone(),
+        is_success: !env.enabled_modules.is_empty(),
+    }));
+    assert_eq!(checks.condition(&envs), Condition::Feature("opengl".into()));
+}
+
+#[test]
+fn windows_env_condition() {
+    let mut linux = current_target();
+    linux.os = OS::Linux;
+    let envs = vec![
+        env(linux),
+        env(Target::msvc(Arch::X86_64)),
+        env(Target::mingw(Arch::X86_64)),
+    ];
+    let checks = CppChecks::new(envs.iter().map(|env| CppChecksItem {
+        env: env.clone(),
+        is_success: env.target.env != Env::Gnu || env.target.os != OS::Windows,
+    }));
+    assert_eq!(
+        checks.condition(&envs),
+        Condition::Not(Box::new(Condition::And(vec![
+            Condition::OS(OS::Windows),
+            Condition::Env(Env::Gnu),
+        ])))
+    );
+}
+
+#[test]
+fn flag_set_condition() {
+    let with_flag_set = |name: &str| LibraryTarget {
+        flag_set: Some(name.to_string()),
+        ..env(current_target())
+    };
+    let envs = vec![
+        env(current_target()),
+        with_flag_set("cpp17"),
+        with_flag_set("cpp20"),
+    ];
+    let checks = CppChecks::new(envs.iter().map(|env| CppChecksItem {
+        env: env.clone(),
+        is_success: env.flag_set.as_ref().map(String::as_str) == Some("cpp20"),
+    }));
+    assert_eq!(checks.condition(&envs), Condition::FlagSet("cpp20".into()));
+
+    let checks = CppChecks::new(envs.iter().map(|env| CppChecksItem {
+        env: env.clone(),
+        is_success: env.flag_set.is_some(),
+    }));
+    assert_eq!(
+        checks.condition(&envs),
+        Condition::Or(vec![
+            Condition::FlagSet("cpp17".into()),
+            Condition::FlagSet("cpp20".into()),
+        ])
+    );
+}
+
+#[test]
+fn all_conditions() {
+    let linux = Condition::OS(OS::Linux);
+    let feature = Condition::Feature("gui".into());
+    assert_eq!(Condition::all(vec![]), Condition::True);
+    assert_eq!(
+        Condition::all(vec![Condition::True, linux.clone(), linux.clone()]),
+        linux
+    );
+    assert_eq!(
+        Condition::all(vec![linux.clone(), Condition::True, feature.clone()]),
+        Condition::And(vec![linux.clone(), feature])
+    );
+    assert_eq!(
+        Condition::all(vec![linux, Condition::False]),
+        Condition::False
+    );
+}
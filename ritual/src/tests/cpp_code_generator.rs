@@ -0,0 +1,328 @@
+use crate::config::{Config, CppInjectionPoint, CrateProperties};
+use crate::cpp_checks::{Condition, CppChecksItem};
+use crate::cpp_code_generator::{
+    function_implementation, generate_cpp_files, shared_thunk_indexes, write_functions,
+};
+use crate::cpp_data::{CppItem, CppPath, CppVariable, CppVisibility};
+use crate::cpp_ffi_data::{CppFfiItem, QtSignalWrapper};
+use crate::cpp_ffi_generator::{to_ffi_method, FfiNameProvider, NewFfiFunctionKind};
+use crate::cpp_type::{CppBuiltInNumericType, CppType};
+use crate::tests::cpp_method::empty_regular_method;
+use crate::tests::empty_db_client;
+use ritual_common::target::{current_target, LibraryTarget};
+use std::collections::HashMap;
+use std::fs;
+
+fn variable_implementation(has_in_class_initializer: bool) -> String {
+    let mut db = empty_db_client();
+    let variable = CppVariable {
+        path: CppPath::from_good_str("Limits::max"),
+        variable_type: CppType::BuiltInNumeric(CppBuiltInNumericType::Int),
+        is_const: true,
+        is_static_member: true,
+        has_in_class_initializer,
+        visibility: CppVisibility::Public,
+    };
+    let kind = if has_in_class_initializer {
+        NewFfiFunctionKind::VariableValue {
+            variable: variable.clone(),
+        }
+    } else {
+        NewFfiFunctionKind::VariableAddress {
+            variable: variable.clone(),
+        }
+    };
+    let source_id = db
+        .add_cpp_item_without_hook(None, CppItem::Variable(variable))
+        .unwrap()
+        .unwrap();
+    let function = to_ffi_method(kind, &HashMap::new(), &mut FfiNameProvider::testing()).unwrap();
+    let id = db
+        .add_ffi_item(Some(source_id), CppFfiItem::Function(function))
+        .unwrap()
+        .unwrap();
+    let item = db.ffi_item(&id).unwrap();
+    function_implementation(&db, item.filter_map(|i| i.as_function_ref()).unwrap()).unwrap()
+}
+
+#[test]
+fn variable_address_and_value() {
+    assert!(variable_implementation(false).contains("&Limits::max"));
+    let value = variable_implementation(true);
+    assert!(value.contains("Limits::max"));
+    assert!(!value.contains("&Limits::max"));
+}
+
+#[test]
+fn shared_thunks() {
+    let keys = vec![
+        Some("a".to_string()),
+        Some("b".to_string()),
+        None,
+        Some("c".to_string()),
+        Some("b".to_string()),
+        Some("a".to_string()),
+        None,
+        Some("c".to_string()),
+        Some("d".to_string()),
+    ];
+    assert_eq!(
+        shared_thunk_indexes(&keys),
+        vec![
+            Some(0),
+            Some(1),
+            None,
+            Some(2),
+            Some(1),
+            Some(0),
+            None,
+            Some(2),
+            None
+        ]
+    );
+}
+
+#[test]
+fn shared_thunk_code() {
+    let mut db = empty_db_client();
+    let variable = CppVariable {
+        path: CppPath::from_good_str("Limits::max"),
+        variable_type: CppType::BuiltInNumeric(CppBuiltInNumericType::Int),
+        is_const: true,
+        is_static_member: true,
+        has_in_class_initializer: false,
+        visibility: CppVisibility::Public,
+    };
+    let source_id = db
+        .add_cpp_item_without_hook(None, CppItem::Variable(variable.clone()))
+        .unwrap()
+        .unwrap();
+    let kinds = vec![
+        (
+            "foo_a",
+            NewFfiFunctionKind::VariableValue {
+                variable: variable.clone(),
+            },
+        ),
+        (
+            "foo_b",
+            NewFfiFunctionKind::VariableValue {
+                variable: variable.clone(),
+            },
+        ),
+        ("foo_c", NewFfiFunctionKind::VariableAddress { variable }),
+    ];
+    let mut ids = Vec::new();
+    for (name, kind) in kinds {
+        let mut function =
+            to_ffi_method(kind, &HashMap::new(), &mut FfiNameProvider::testing()).unwrap();
+        function.path = CppPath::from_good_str(name);
+        let id = db
+            .add_ffi_item(Some(source_id.clone()), CppFfiItem::Function(function))
+            .unwrap()
+            .unwrap();
+        ids.push(id);
+    }
+    let items = ids
+        .iter()
+        .map(|id| db.ffi_item(id).unwrap())
+        .collect::<Vec<_>>();
+    let functions = items
+        .iter()
+        .map(|item| {
+            let function = item.clone().filter_map(|i| i.as_function_ref()).unwrap();
+            (function, Condition::True)
+        })
+        .collect::<Vec<_>>();
+
+    let mut output = Vec::new();
+    write_functions(&db, &mut output, &functions).unwrap();
+    let code = String::from_utf8(output).unwrap();
+    assert_eq!(code.matches("static RITUAL_NOINLINE").count(), 1);
+    assert!(code.contains("ritual_thunk_0()"));
+    assert!(code.contains("foo_a() RITUAL_SHARED_THUNK(ritual_thunk_0, )"));
+    assert!(code.contains("foo_b() RITUAL_SHARED_THUNK(ritual_thunk_0, )"));
+    assert!(code.contains("foo_c() {"));
+    assert!(!code.contains("ritual_thunk_1"));
+    // the body of `foo_a` and `foo_b` is only emitted in the thunk
+    assert_eq!(code.matches("Limits::max").count(), 2);
+}
+
+#[test]
+fn injected_code() {
+    let db = empty_db_client();
+    let mut config = Config::new(CrateProperties::new("foo", "0.0.0"));
+    config.add_cpp_code_injection(
+        CppInjectionPoint::BeforeWrappers,
+        "static int foo_helper() { return 1; }",
+    );
+    config.add_cpp_code_injection(
+        CppInjectionPoint::ExternC,
+        "int foo_custom() { return foo_helper(); }",
+    );
+
+    let dir = tempdir::TempDir::new("test_injected_code").unwrap();
+    generate_cpp_files(
+        &db,
+        dir.path(),
+        "foo_global.h",
+        &config,
+        None,
+        &HashMap::new(),
+    )
+    .unwrap();
+    let code = fs::read_to_string(dir.path().join("file1.cpp")).unwrap();
+    let helper = code.find("static int foo_helper()").unwrap();
+    let extern_c = code.find("extern \"C\" {").unwrap();
+    let custom = code.find("int foo_custom()").unwrap();
+    let extern_c_end = code.find("} // extern \"C\"").unwrap();
+    assert!(helper < extern_c);
+    assert!(extern_c < custom && custom < extern_c_end);
+}
+
+#[test]
+fn header_group_files() {
+    let mut db = empty_db_client();
+    let variable = CppVariable {
+        path: CppPath::from_good_str("Limits::max"),
+        variable_type: CppType::BuiltInNumeric(CppBuiltInNumericType::Int),
+        is_const: true,
+        is_static_member: true,
+        has_in_class_initializer: true,
+        visibility: CppVisibility::Public,
+    };
+    let kind = NewFfiFunctionKind::VariableValue {
+        variable: variable.clone(),
+    };
+    let source_id = db
+        .add_cpp_item_without_hook(None, CppItem::Variable(variable))
+        .unwrap()
+        .unwrap();
+    let mut function =
+        to_ffi_method(kind, &HashMap::new(), &mut FfiNameProvider::testing()).unwrap();
+    function.out_of_line = true;
+    let id = db
+        .add_ffi_item(Some(source_id), CppFfiItem::Function(function))
+        .unwrap()
+        .unwrap();
+    let env = LibraryTarget {
+        target: current_target(),
+        cpp_library_version: None,
+        enabled_modules: Default::default(),
+        flag_set: None,
+    };
+    db.add_environment(env.clone());
+    db.add_cpp_checks_item(
+        id.clone(),
+        CppChecksItem {
+            env,
+            is_success: true,
+        },
+    );
+
+    let mut config = Config::new(CrateProperties::new("foo", "0.0.0"));
+    config.add_include_directive("foo.h");
+    config.add_header_group(vec!["foo_a.h"]);
+    config.add_header_group(vec!["foo_b.h"]);
+    let mut item_header_groups = HashMap::new();
+    item_header_groups.insert(id, 1);
+
+    let dir = tempdir::TempDir::new("test_header_group_files").unwrap();
+    generate_cpp_files(
+        &db,
+        dir.path(),
+        "foo_global.h",
+        &config,
+        None,
+        &item_header_groups,
+    )
+    .unwrap();
+    let main_code = fs::read_to_string(dir.path().join("file1.cpp")).unwrap();
+    assert!(!main_code.contains("Limits::max"));
+    assert!(!dir.path().join("ffi_limits.cpp").exists());
+    let code = fs::read_to_string(dir.path().join("group1_ffi_limits.cpp")).unwrap();
+    let global_header = code.find("#include \"foo_global.h\"").unwrap();
+    let group_header = code.find("#include <foo_b.h>").unwrap();
+    assert!(global_header < group_header);
+    assert!(!code.contains("foo_a.h"));
+    assert!(code.contains("Limits::max"));
+}
+
+#[test]
+fn qt_wrapper_methods_in_main_file() {
+    let mut db = empty_db_client();
+    let env = LibraryTarget {
+        target: current_target(),
+        cpp_library_version: None,
+        enabled_modules: Default::default(),
+        flag_set: None,
+    };
+    db.add_environment(env.clone());
+
+    let wrapper_id = db
+        .add_ffi_item(
+            None,
+            CppFfiItem::QtSignalWrapper(QtSignalWrapper {
+                signal_arguments: Vec::new(),
+                class_path: CppPath::from_good_str("ctr_foo_SignalWrapper"),
+            }),
+        )
+        .unwrap()
+        .unwrap();
+    let mut method = empty_regular_method();
+    method.path = CppPath::from_good_str("ctr_foo_SignalWrapper::custom_signal");
+    let mut free_function = empty_regular_method();
+    free_function.path = CppPath::from_good_str("ns::free_function");
+    let sources = vec![(Some(wrapper_id.clone()), method), (None, free_function)];
+    let mut ids = vec![wrapper_id];
+    for (source_id, function) in sources {
+        let cpp_id = db
+            .add_cpp_item_without_hook(source_id, CppItem::Function(function.clone()))
+            .unwrap()
+            .unwrap();
+        let kind = NewFfiFunctionKind::Function {
+            cpp_function: function,
+        };
+        let mut function =
+            to_ffi_method(kind, &HashMap::new(), &mut FfiNameProvider::testing()).unwrap();
+        function.out_of_line = true;
+        ids.push(
+            db.add_ffi_item(Some(cpp_id), CppFfiItem::Function(function))
+                .unwrap()
+                .unwrap(),
+        );
+    }
+    for id in ids {
+        db.add_cpp_checks_item(
+            id,
+            CppChecksItem {
+                env: env.clone(),
+                is_success: true,
+            },
+        );
+    }
+
+    let config = Config::new(CrateProperties::new("foo", "0.0.0"));
+    let dir = tempdir::TempDir::new("test_qt_wrapper_methods_in_main_file").unwrap();
+    generate_cpp_files(
+        &db,
+        dir.path(),
+        "foo_global.h",
+        &config,
+        None,
+        &HashMap::new(),
+    )
+    .unwrap();
+    let main_code = fs::read_to_string(dir.path().join("file1.cpp")).unwrap();
+    // the wrapper class is defined before the wrapper method is used
+    let class = main_code.find("class ctr_foo_SignalWrapper").unwrap();
+    let method = main_code
+        .find("ctr_foo_SignalWrapper::custom_signal()")
+        .unwrap();
+    assert!(class < method);
+    assert!(!dir.path().join("ffi_ctr_foo_signalwrapper.cpp").exists());
+
+    let code = fs::read_to_string(dir.path().join("ffi_ns.cpp")).unwrap();
+    assert!(code.contains("ns::free_function()"));
+    assert!(!main_code.contains("ns::free_function()"));
+}
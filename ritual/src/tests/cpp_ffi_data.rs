@@ -68,3 +68,20 @@ fn cpp_ffi_type_void() {
     assert!(t.ffi_type().is_void());
     assert_eq!(t.conversion(), &CppToFfiTypeConversion::NoChange);
 }
+
+#[test]
+fn calling_convention_macros() {
+    let global_header = include_str!("../../templates/c_lib/global.h");
+    for &convention in &[
+        FfiCallingConvention::C,
+        FfiCallingConvention::Stdcall,
+        FfiCallingConvention::Fastcall,
+        FfiCallingConvention::System,
+    ] {
+        if let Some(attribute) = convention.cpp_attribute() {
+            assert!(attribute.starts_with("RITUAL_"));
+            assert!(global_header.contains(&format!("#define {}", attribute)));
+        }
+    }
+    assert_eq!(FfiCallingConvention::C.cpp_attribute(), None);
+}
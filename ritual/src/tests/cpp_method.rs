@@ -1,9 +1,14 @@
+use crate::config::{AllocationStrategy, Config, CrateProperties};
 use crate::cpp_data::*;
 use crate::cpp_ffi_data::*;
-use crate::cpp_ffi_generator::NewFfiFunctionKind;
+use crate::cpp_ffi_generator::{
+    check_in_place_allocation, is_plain_struct_candidate, NewFfiFunctionKind,
+};
 use crate::cpp_function::*;
 use crate::cpp_type::*;
-use itertools::Itertools;
+use crate::tests::empty_db_client;
+use ritual_common::errors::Result;
+use std::collections::HashMap;
 
 #[test]
 fn cpp_method_kind() {
@@ -30,6 +35,8 @@ pub fn empty_membership() -> CppFunctionMemberData {
         visibility: CppVisibility::Public,
         is_signal: false,
         is_slot: false,
+        is_explicit: false,
+        trivial_field_access: None,
     }
 }
 
@@ -169,16 +176,25 @@ fn argument_types_equal8() {
     assert!(method2.argument_types_equal(&method1));
 }
 
-fn to_ffi(function: &CppFunction, force_stack: Option<CppPath>) -> CppFfiFunction {
-    let movable_types = force_stack.into_iter().collect_vec();
+fn to_ffi_with_strategies(
+    function: &CppFunction,
+    allocation_strategies: &HashMap<CppPath, AllocationStrategy>,
+) -> Result<CppFfiFunction> {
     crate::cpp_ffi_generator::to_ffi_method(
         NewFfiFunctionKind::Function {
             cpp_function: function.clone(),
         },
-        &movable_types,
+        allocation_strategies,
         &mut crate::cpp_ffi_generator::FfiNameProvider::testing(),
     )
-    .unwrap()
+}
+
+fn to_ffi(function: &CppFunction, force_stack: Option<CppPath>) -> CppFfiFunction {
+    let allocation_strategies = force_stack
+        .into_iter()
+        .map(|path| (path, AllocationStrategy::InPlace))
+        .collect();
+    to_ffi_with_strategies(function, &allocation_strategies).unwrap()
 }
 
 #[test]
@@ -548,6 +564,116 @@ fn c_signature_method_returning_class() {
     );
 }
 
+#[test]
+fn c_signature_factory() {
+    let class_path = CppPath::from_good_str("MyClass");
+    let mut allocation_strategies = HashMap::new();
+    allocation_strategies.insert(
+        class_path.clone(),
+        AllocationStrategy::Factory {
+            create: CppPath::from_good_str("MyClass::create"),
+            destroy: CppPath::from_good_str("MyClass::destroy"),
+        },
+    );
+
+    let mut constructor = empty_regular_method();
+    constructor.path = CppPath::from_good_str("MyClass::MyClass");
+    constructor.member = Some({
+        let mut info = empty_membership();
+        info.kind = CppFunctionKind::Constructor;
+        info
+    });
+    let r = to_ffi_with_strategies(&constructor, &allocation_strategies).unwrap();
+    assert!(r.arguments.is_empty());
+    assert_eq!(
+        r.return_type.ffi_type(),
+        &CppType::new_pointer(false, CppType::Class(class_path.clone())),
+    );
+    assert_eq!(
+        r.allocation_place,
+        ReturnValueAllocationPlace::Factory {
+            function: CppPath::from_good_str("MyClass::create")
+        }
+    );
+
+    let mut destructor = constructor.clone();
+    destructor.path = CppPath::from_good_str("MyClass::~MyClass");
+    destructor.member.as_mut().unwrap().kind = CppFunctionKind::Destructor;
+    let r = to_ffi_with_strategies(&destructor, &allocation_strategies).unwrap();
+    assert_eq!(r.arguments.len(), 1);
+    assert_eq!(
+        r.allocation_place,
+        ReturnValueAllocationPlace::Factory {
+            function: CppPath::from_good_str("MyClass::destroy")
+        }
+    );
+
+    // values returned by other functions are copied by the factory
+    let mut getter = empty_regular_method();
+    getter.return_type = CppType::Class(class_path.clone());
+    let r = to_ffi_with_strategies(&getter, &allocation_strategies).unwrap();
+    assert!(r.arguments.is_empty());
+    assert_eq!(
+        r.return_type.ffi_type(),
+        &CppType::new_pointer(false, CppType::Class(class_path))
+    );
+    assert_eq!(
+        r.allocation_place,
+        ReturnValueAllocationPlace::Factory {
+            function: CppPath::from_good_str("MyClass::create")
+        }
+    );
+}
+
+#[test]
+fn in_place_allocation() {
+    let db = empty_db_client();
+    let config = Config::new(CrateProperties::new("foo", "0.0.0"));
+    let mut type1 = CppTypeDeclaration {
+        path: CppPath::from_good_str("MyClass"),
+        kind: CppTypeDeclarationKind::Class,
+        specialization: None,
+        is_trivially_copyable: true,
+        is_immovable: false,
+    };
+    assert!(check_in_place_allocation(&type1, &db, &config).is_ok());
+    type1.is_trivially_copyable = false;
+    assert!(check_in_place_allocation(&type1, &db, &config).is_err());
+}
+
+#[test]
+fn plain_struct_candidate() {
+    let mut db = empty_db_client();
+    let config = Config::new(CrateProperties::new("foo", "0.0.0"));
+    let env = config.current_library_target().unwrap();
+    let type1 = CppTypeDeclaration {
+        path: CppPath::from_good_str("MyClass"),
+        kind: CppTypeDeclarationKind::Class,
+        specialization: None,
+        is_trivially_copyable: true,
+        is_immovable: false,
+    };
+    // the parser's guess is used if the type wasn't probed
+    assert!(is_plain_struct_candidate(&type1, &db, &env));
+
+    let mut properties = CppTypeProperties {
+        path: type1.path.clone(),
+        env: env.clone(),
+        size: 8,
+        alignment: 4,
+        is_trivially_copyable: true,
+        is_polymorphic: false,
+        is_standard_layout: false,
+        is_underlying_type_signed: None,
+    };
+    db.set_cpp_type_properties(properties.clone());
+    assert!(!is_plain_struct_candidate(&type1, &db, &env));
+
+    properties.is_standard_layout = true;
+    db.set_cpp_type_properties(properties);
+    assert!(is_plain_struct_candidate(&type1, &db, &env));
+}
+
 #[test]
 fn full_name_free_function_in_namespace() {
     let mut method1 = empty_regular_method();
@@ -605,6 +731,8 @@ fn short_text1() {
             visibility: CppVisibility::Protected,
             is_signal: false,
             is_slot: false,
+            is_explicit: false,
+            trivial_field_access: None,
         }),
         operator: None,
         return_type: CppType::BuiltInNumeric(CppBuiltInNumericType::Int),
@@ -629,3 +757,79 @@ fn short_text1() {
         "protected int Class1::method1(int arg1, double arg2 = …) const"
     );
 }
+
+#[test]
+fn ffi_names_of_overloads() {
+    let mut provider = crate::cpp_ffi_generator::FfiNameProvider::testing();
+    let first = provider.create_path("f", "f(int)");
+    let second = provider.create_path("f", "f(double)");
+    assert_eq!(first.to_cpp_pseudo_code(), "_f");
+    assert_ne!(second, first);
+    assert!(second.to_cpp_pseudo_code().starts_with("_f_"));
+    assert_eq!(provider.create_path("f", "f(double)"), second);
+
+    let mut provider2 = crate::cpp_ffi_generator::FfiNameProvider::testing();
+    provider2.create_path("f", "f()");
+    assert_eq!(provider2.create_path("f", "f(double)"), second);
+}
+
+fn variable_to_ffi(variable: CppVariable) -> Result<CppFfiFunction> {
+    let kind = if variable.has_in_class_initializer {
+        NewFfiFunctionKind::VariableValue { variable }
+    } else {
+        NewFfiFunctionKind::VariableAddress { variable }
+    };
+    crate::cpp_ffi_generator::to_ffi_method(
+        kind,
+        &HashMap::new(),
+        &mut crate::cpp_ffi_generator::FfiNameProvider::testing(),
+    )
+}
+
+#[test]
+fn variable_accessors() {
+    let int = CppType::BuiltInNumeric(CppBuiltInNumericType::Int);
+    let variable = CppVariable {
+        path: CppPath::from_good_str("ns::counter"),
+        variable_type: int.clone(),
+        is_const: false,
+        is_static_member: false,
+        has_in_class_initializer: false,
+        visibility: CppVisibility::Public,
+    };
+    let address = variable_to_ffi(variable.clone()).unwrap();
+    assert_eq!(address.kind, CppFfiFunctionKind::VariableAddress);
+    assert!(address.arguments.is_empty());
+    assert_eq!(
+        address.return_type.ffi_type(),
+        &CppType::new_pointer(false, int.clone())
+    );
+
+    let reference = CppVariable {
+        path: CppPath::from_good_str("ns::counter_ref"),
+        variable_type: CppType::new_reference(true, int.clone()),
+        ..variable.clone()
+    };
+    let reference_address = variable_to_ffi(reference).unwrap();
+    assert_eq!(
+        reference_address.return_type.ffi_type(),
+        &CppType::new_pointer(true, int.clone())
+    );
+
+    let constant = CppVariable {
+        path: CppPath::from_good_str("Limits::max"),
+        is_const: true,
+        is_static_member: true,
+        has_in_class_initializer: true,
+        ..variable.clone()
+    };
+    let value = variable_to_ffi(constant.clone()).unwrap();
+    assert_eq!(value.kind, CppFfiFunctionKind::VariableValue);
+    assert_eq!(value.return_type.ffi_type(), &int);
+
+    let class_constant = CppVariable {
+        variable_type: CppType::Class(CppPath::from_good_str("Color")),
+        ..constant
+    };
+    assert!(variable_to_ffi(class_constant).is_err());
+}
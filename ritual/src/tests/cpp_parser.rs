@@ -1,25 +1,35 @@
 use crate::config::{Config, CrateProperties};
 use crate::cpp_data::*;
+use crate::cpp_ffi_data::CppFieldAccessorType;
 use crate::cpp_function::*;
 use crate::cpp_operator::CppOperator;
+use crate::cpp_parser::{is_header_file, preprocessed_header_lines, preprocessor_command};
 use crate::cpp_type::*;
-use crate::processor;
+use crate::processor::{self, Inspection};
 use crate::workspace::Workspace;
 use ritual_common::cpp_build_config::CppBuildPaths;
 use ritual_common::file_utils::create_dir;
 use ritual_common::file_utils::create_file;
+use ritual_common::file_utils::file_to_string;
+use std::ffi::OsString;
 use std::io::Write;
+use std::path::Path;
 
 struct ParserCppData {
     types: Vec<CppTypeDeclaration>,
     bases: Vec<CppBaseSpecifier>,
     fields: Vec<CppClassField>,
+    variables: Vec<CppVariable>,
     methods: Vec<CppFunction>,
     enum_values: Vec<CppEnumValue>,
     namespaces: Vec<CppPath>,
 }
 
 fn run_parser(code: &'static str) -> ParserCppData {
+    run_parser_steps(code, &["cpp_parser"])
+}
+
+fn run_parser_steps(code: &'static str, steps: &[&str]) -> ParserCppData {
     let dir = tempdir::TempDir::new("test_cpp_parser_run").unwrap();
 
     let mut workspace = Workspace::new(dir.path().into()).unwrap();
@@ -41,7 +51,11 @@ fn run_parser(code: &'static str) -> ParserCppData {
     config.set_cpp_build_paths(paths);
     config.add_target_include_path(include_file_path);
 
-    processor::process(&mut workspace, &config, &["cpp_parser".into()], None).unwrap();
+    let steps = steps
+        .iter()
+        .map(|step| step.to_string())
+        .collect::<Vec<_>>();
+    processor::process(&mut workspace, &config, &steps, None).unwrap();
 
     let database = workspace
         .get_database_client("A", &[], true, false)
@@ -63,6 +77,11 @@ fn run_parser(code: &'static str) -> ParserCppData {
             .filter_map(|item| item.item.as_field_ref())
             .cloned()
             .collect(),
+        variables: database
+            .cpp_items()
+            .filter_map(|item| item.item.as_variable_ref())
+            .cloned()
+            .collect(),
         enum_values: database
             .cpp_items()
             .filter_map(|item| item.item.as_enum_value_ref())
@@ -155,14 +174,13 @@ fn functions_with_class_arg() {
 
     assert!(data.bases.is_empty());
 
-    assert_eq!(data.fields.len(), 3);
+    assert_eq!(data.fields.len(), 2);
     assert_eq!(data.fields[0].path, CppPath::from_good_str("Magic::a"));
     assert_eq!(
         data.fields[0].field_type,
         CppType::BuiltInNumeric(CppBuiltInNumericType::Int),
     );
     assert_eq!(data.fields[0].visibility, CppVisibility::Public);
-    assert_eq!(data.fields[0].is_static, false);
 
     assert_eq!(data.fields[1].path, CppPath::from_good_str("Magic::b"));
     assert_eq!(
@@ -170,15 +188,18 @@ fn functions_with_class_arg() {
         CppType::BuiltInNumeric(CppBuiltInNumericType::Int),
     );
     assert_eq!(data.fields[1].visibility, CppVisibility::Public);
-    assert_eq!(data.fields[1].is_static, false);
 
-    assert_eq!(data.fields[2].path, CppPath::from_good_str("Magic::c"));
     assert_eq!(
-        data.fields[2].field_type,
-        CppType::BuiltInNumeric(CppBuiltInNumericType::Int),
+        data.variables,
+        vec![CppVariable {
+            path: CppPath::from_good_str("Magic::c"),
+            variable_type: CppType::BuiltInNumeric(CppBuiltInNumericType::Int),
+            is_const: false,
+            is_static_member: true,
+            has_in_class_initializer: false,
+            visibility: CppVisibility::Public,
+        }]
     );
-    assert_eq!(data.fields[2].visibility, CppVisibility::Public);
-    assert_eq!(data.fields[2].is_static, true);
 
     assert_eq!(data.methods.len(), 3);
     assert_eq!(
@@ -415,6 +436,8 @@ fn simple_class_method() {
                 visibility: CppVisibility::Public,
                 is_signal: false,
                 is_slot: false,
+                is_explicit: false,
+                trivial_field_access: None,
             }),
             operator: None,
             return_type: CppType::BuiltInNumeric(CppBuiltInNumericType::Int),
@@ -430,6 +453,27 @@ fn simple_class_method() {
     );
 }
 
+#[test]
+fn explicit_constructors() {
+    let data = run_parser(
+        "
+        class MyClass {
+        public:
+            MyClass(int x);
+            explicit MyClass(bool x);
+            MyClass(const MyClass& other);
+        };
+        ",
+    );
+    assert_eq!(data.methods.len(), 3);
+    assert!(!data.methods[0].member.as_ref().unwrap().is_explicit);
+    assert!(data.methods[0].is_converting_constructor());
+    assert!(data.methods[1].member.as_ref().unwrap().is_explicit);
+    assert!(!data.methods[1].is_converting_constructor());
+    assert!(!data.methods[2].member.as_ref().unwrap().is_explicit);
+    assert!(!data.methods[2].is_converting_constructor());
+}
+
 #[test]
 fn advanced_class_methods() {
     let data = run_parser(
@@ -605,6 +649,8 @@ fn template_class_method() {
                 visibility: CppVisibility::Public,
                 is_signal: false,
                 is_slot: false,
+                is_explicit: false,
+                trivial_field_access: None,
             }),
             operator: None,
             return_type: CppType::TemplateParameter(CppTemplateParameter {
@@ -757,22 +803,261 @@ fn simple_enum() {
     );
     assert_eq!(data.types.len(), 1);
     assert_eq!(data.types[0].path, CppPath::from_good_str("Enum1"));
-    assert_eq!(data.types[0].kind, CppTypeDeclarationKind::Enum);
+    assert_eq!(
+        data.types[0].kind,
+        CppTypeDeclarationKind::Enum { is_scoped: false }
+    );
     assert_eq!(
         data.enum_values,
         vec![
             CppEnumValue {
                 value: 0,
                 path: CppPath::from_good_str("Enum1::Good"),
+                alias_of: None,
             },
             CppEnumValue {
                 value: 1,
                 path: CppPath::from_good_str("Enum1::Bad"),
+                alias_of: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn global_variables() {
+    let data = run_parser(
+        "
+        class Color {};
+        namespace ns {
+            extern const Color white;
+            extern int counter;
+            extern int& counter_ref;
+        }
+        static int hidden;
+        class Limits {
+        public:
+            static constexpr int max = 42;
+        };
+        ",
+    );
+    assert_eq!(
+        data.variables,
+        vec![
+            CppVariable {
+                path: CppPath::from_good_str("ns::white"),
+                variable_type: CppType::Class(CppPath::from_good_str("Color")),
+                is_const: true,
+                is_static_member: false,
+                has_in_class_initializer: false,
+                visibility: CppVisibility::Public,
+            },
+            CppVariable {
+                path: CppPath::from_good_str("ns::counter"),
+                variable_type: CppType::BuiltInNumeric(CppBuiltInNumericType::Int),
+                is_const: false,
+                is_static_member: false,
+                has_in_class_initializer: false,
+                visibility: CppVisibility::Public,
+            },
+            CppVariable {
+                path: CppPath::from_good_str("ns::counter_ref"),
+                variable_type: CppType::new_reference(
+                    false,
+                    CppType::BuiltInNumeric(CppBuiltInNumericType::Int)
+                ),
+                is_const: false,
+                is_static_member: false,
+                has_in_class_initializer: false,
+                visibility: CppVisibility::Public,
+            },
+            CppVariable {
+                path: CppPath::from_good_str("Limits::max"),
+                variable_type: CppType::BuiltInNumeric(CppBuiltInNumericType::Int),
+                is_const: true,
+                is_static_member: true,
+                has_in_class_initializer: true,
+                visibility: CppVisibility::Public,
             },
         ]
     );
 }
 
+#[test]
+fn simple_union() {
+    let data = run_parser(
+        "
+        union Union1 {
+            int x;
+            float y;
+        };
+        ",
+    );
+    assert_eq!(data.types.len(), 1);
+    assert_eq!(data.types[0].path, CppPath::from_good_str("Union1"));
+    assert_eq!(data.types[0].kind, CppTypeDeclarationKind::Union);
+    assert_eq!(data.fields.len(), 2);
+    assert_eq!(data.fields[0].path, CppPath::from_good_str("Union1::x"));
+    assert_eq!(data.fields[1].path, CppPath::from_good_str("Union1::y"));
+}
+
+#[test]
+fn trivially_copyable_classes() {
+    let data = run_parser(
+        "
+        struct Point {
+            Point();
+            Point(int x, int y);
+            int x;
+            int y;
+        };
+        class WithCopyConstructor {
+        public:
+            WithCopyConstructor(const WithCopyConstructor& other);
+        };
+        class WithDestructor {
+        public:
+            ~WithDestructor();
+        };
+        class WithVirtualMethod {
+        public:
+            virtual void f();
+        };
+        class Derived : public Point {};
+        ",
+    );
+    let is_trivially_copyable = |name: &str| {
+        data.types
+            .iter()
+            .find(|t| t.path == CppPath::from_good_str(name))
+            .unwrap()
+            .is_trivially_copyable
+    };
+    assert!(is_trivially_copyable("Point"));
+    assert!(!is_trivially_copyable("WithCopyConstructor"));
+    assert!(!is_trivially_copyable("WithDestructor"));
+    assert!(!is_trivially_copyable("WithVirtualMethod"));
+    assert!(!is_trivially_copyable("Derived"));
+}
+
+#[test]
+fn immovable_classes() {
+    let data = run_parser(
+        "
+        #define DISABLE_COPY_MOVE(Class) \\
+            Class(const Class&) = delete; \\
+            Class(Class&&) = delete;
+        class NonCopyable {
+        public:
+            NonCopyable();
+            NonCopyable(const NonCopyable& other) = delete;
+            NonCopyable& operator=(const NonCopyable& other) = delete;
+        };
+        class Immovable {
+        public:
+            Immovable();
+            Immovable(Immovable&& other) = delete;
+        };
+        class MacroNonMovable {
+        public:
+            MacroNonMovable();
+            DISABLE_COPY_MOVE(MacroNonMovable)
+        };
+        class Movable {
+        public:
+            Movable(Movable&& other);
+        };
+        ",
+    );
+    let is_immovable = |name: &str| {
+        data.types
+            .iter()
+            .find(|t| t.path == CppPath::from_good_str(name))
+            .unwrap()
+            .is_immovable
+    };
+    assert!(!is_immovable("NonCopyable"));
+    assert!(is_immovable("Immovable"));
+    assert!(!is_immovable("MacroNonMovable"));
+    assert!(!is_immovable("Movable"));
+}
+
+#[test]
+fn trivial_field_accessors() {
+    let data = run_parser(
+        "
+        class Point {
+        public:
+            int x() const { return m_x; }
+            const int& x_ref() const { return this->m_x; }
+            int& x_mut() { return m_x; }
+            void set_x(int x) { m_x = x; }
+            long x_long() const { return m_x; }
+            int x_plus_one() const { return m_x + 1; }
+            void compare_x(int x) { m_x == x; }
+            virtual int virtual_x() const { return m_x; }
+            int declared_x() const;
+            int m_x;
+        };
+        ",
+    );
+    let access = |name: &str| {
+        data.methods
+            .iter()
+            .find(|m| m.path.last().name == name)
+            .unwrap()
+            .member
+            .as_ref()
+            .unwrap()
+            .trivial_field_access
+            .clone()
+    };
+    let expected = |accessor_type| {
+        Some(CppTrivialFieldAccess {
+            field_name: "m_x".into(),
+            accessor_type,
+        })
+    };
+    assert_eq!(access("x"), expected(CppFieldAccessorType::CopyGetter));
+    assert_eq!(
+        access("x_ref"),
+        expected(CppFieldAccessorType::ConstRefGetter)
+    );
+    assert_eq!(
+        access("x_mut"),
+        expected(CppFieldAccessorType::MutRefGetter)
+    );
+    assert_eq!(access("set_x"), expected(CppFieldAccessorType::Setter));
+    assert_eq!(access("x_long"), None);
+    assert_eq!(access("x_plus_one"), None);
+    assert_eq!(access("compare_x"), None);
+    assert_eq!(access("virtual_x"), None);
+    assert_eq!(access("declared_x"), None);
+}
+
+#[test]
+fn field_offsets() {
+    let data = run_parser(
+        "
+        struct Struct1 {
+            char a;
+            char b;
+            int c : 3;
+        };
+        ",
+    );
+    let offset = |name: &str| {
+        data.fields
+            .iter()
+            .find(|f| f.path == CppPath::from_good_str(name))
+            .unwrap()
+            .offset
+    };
+    assert_eq!(offset("Struct1::a"), Some(0));
+    assert_eq!(offset("Struct1::b"), Some(1));
+    assert_eq!(offset("Struct1::c"), None);
+}
+
 #[test]
 fn simple_enum2() {
     let data = run_parser(
@@ -788,27 +1073,91 @@ fn simple_enum2() {
     );
     assert_eq!(data.types.len(), 1);
     assert_eq!(data.types[0].path, CppPath::from_good_str("ns1::Enum1"));
-    assert_eq!(data.types[0].kind, CppTypeDeclarationKind::Enum);
+    assert_eq!(
+        data.types[0].kind,
+        CppTypeDeclarationKind::Enum { is_scoped: false }
+    );
     assert_eq!(
         data.enum_values,
         vec![
             CppEnumValue {
                 value: 1,
                 path: CppPath::from_good_str("ns1::Enum1::Good"),
+                alias_of: None,
             },
             CppEnumValue {
                 value: 2,
                 path: CppPath::from_good_str("ns1::Enum1::Bad"),
+                alias_of: None,
             },
             CppEnumValue {
                 value: 3,
                 path: CppPath::from_good_str("ns1::Enum1::Questionable"),
+                alias_of: None,
             },
         ]
     );
     assert_eq!(data.namespaces, vec![CppPath::from_good_str("ns1")]);
 }
 
+#[test]
+fn scoped_enum() {
+    let data = run_parser(
+        "
+        enum class Enum1 { A, B };
+        enum Enum2 { C, D };
+        ",
+    );
+    assert_eq!(data.types.len(), 2);
+    assert_eq!(
+        data.types[0].kind,
+        CppTypeDeclarationKind::Enum { is_scoped: true }
+    );
+    assert_eq!(
+        data.types[1].kind,
+        CppTypeDeclarationKind::Enum { is_scoped: false }
+    );
+}
+
+#[test]
+fn enum_aliases() {
+    let data = run_parser(
+        "
+        enum Enum1 {
+            First = 1,
+            Second = 2,
+            Default = First,
+            Last = 2
+        };
+        ",
+    );
+    assert_eq!(
+        data.enum_values,
+        vec![
+            CppEnumValue {
+                value: 1,
+                path: CppPath::from_good_str("Enum1::First"),
+                alias_of: None,
+            },
+            CppEnumValue {
+                value: 2,
+                path: CppPath::from_good_str("Enum1::Second"),
+                alias_of: None,
+            },
+            CppEnumValue {
+                value: 1,
+                path: CppPath::from_good_str("Enum1::Default"),
+                alias_of: Some(CppPath::from_good_str("Enum1::First")),
+            },
+            CppEnumValue {
+                value: 2,
+                path: CppPath::from_good_str("Enum1::Last"),
+                alias_of: Some(CppPath::from_good_str("Enum1::Second")),
+            },
+        ]
+    );
+}
+
 #[test]
 fn template_instantiation() {
     let data = run_parser(
@@ -1024,9 +1373,58 @@ fn template_specialization() {
         };
         ",
     );
+    assert_eq!(data.types.len(), 3);
+    assert_eq!(data.types[1].specialization, None);
+    let specialization_path = CppPath::from_item(CppPathItem {
+        name: "QFutureInterface".into(),
+        template_arguments: Some(vec![CppType::Void]),
+    });
+    assert_eq!(data.types[2].path, specialization_path);
+    assert_eq!(
+        data.types[2].specialization,
+        Some(CppSpecializationKind::Explicit)
+    );
+    assert_eq!(data.methods.len(), 2);
+    assert_eq!(data.methods[1].class_path().unwrap(), specialization_path);
+}
+
+#[test]
+fn partial_template_specialization() {
+    let data = run_parser(
+        "
+        template<typename T>
+        class QList {
+        public:
+            void append(const T& value);
+        };
+
+        template<typename T>
+        class QList<T*> {
+        public:
+            void append_pointer(T* value);
+        };
+        ",
+    );
     assert_eq!(data.types.len(), 2);
-    println!("{:?}", data.methods);
-    assert_eq!(data.methods.len(), 1);
+    let param = CppType::TemplateParameter(CppTemplateParameter {
+        nested_level: 0,
+        index: 0,
+        name: "T".into(),
+    });
+    let specialization_path = CppPath::from_item(CppPathItem {
+        name: "QList".into(),
+        template_arguments: Some(vec![CppType::new_pointer(false, param)]),
+    });
+    assert_eq!(data.types[1].path, specialization_path);
+    assert_eq!(
+        data.types[1].specialization,
+        Some(CppSpecializationKind::Partial)
+    );
+    assert_eq!(data.methods.len(), 2);
+    assert_eq!(
+        data.methods[1].path,
+        specialization_path.join(CppPathItem::from_good_str("append_pointer"))
+    );
 }
 
 #[test]
@@ -1220,3 +1618,153 @@ fn elaborated_enum_type() {
         }
     );
 }
+
+#[test]
+fn dependent_function() {
+    let data = run_parser_steps(
+        "
+        template<typename T>
+        struct Traits { typedef T value_type; };
+
+        template<typename T>
+        class Box {
+        public:
+            typename Traits<T>::value_type get() const;
+        };
+
+        Box<int> make_box();
+        ",
+        &[
+            "cpp_parser",
+            "find_template_instantiations",
+            "instantiate_templates",
+            "resolve_dependent_functions",
+        ],
+    );
+    let path = CppPath::from_items(vec![
+        CppPathItem {
+            name: "Box".into(),
+            template_arguments: Some(vec![CppType::BuiltInNumeric(CppBuiltInNumericType::Int)]),
+        },
+        CppPathItem::from_good_str("get"),
+    ]);
+    let method = data.methods.iter().find(|m| m.path == path).unwrap();
+    assert_eq!(
+        method.return_type,
+        CppType::BuiltInNumeric(CppBuiltInNumericType::Int)
+    );
+    assert!(method.arguments.is_empty());
+    assert!(method.member.as_ref().unwrap().is_const);
+}
+
+#[test]
+fn fuzz_snippets() {
+    use crate::fuzz::FuzzParser;
+
+    let mut parser = FuzzParser::new().unwrap();
+    parser.parse_type_snippet("const int*").unwrap();
+    parser
+        .parse_header_snippet("class A { public: int f(); };")
+        .unwrap();
+    assert!(parser.parse_type_snippet("UnknownType<").is_err());
+    // the parser can be reused after an error
+    parser.parse_type_snippet("int&").unwrap();
+}
+
+#[test]
+fn std_types_of_newer_standard() {
+    let data = run_parser(
+        "
+        namespace std {
+            template<typename T> class optional {};
+        }
+        int func1(std::optional<int> x);
+        int func2(int x);
+        ",
+    );
+    // availability of std::optional is checked by the C++ checker
+    assert_eq!(data.methods.len(), 2);
+    assert_eq!(data.methods[0].path, CppPath::from_good_str("func1"));
+}
+
+#[test]
+fn header_file() {
+    assert!(is_header_file(
+        Path::new("/usr/include/QtCore/qobject.h"),
+        Path::new("QtCore/qobject.h")
+    ));
+    assert!(!is_header_file(
+        Path::new("/usr/include/QtCore/qobject.h"),
+        Path::new("qobject")
+    ));
+    assert!(!is_header_file(
+        Path::new("/usr/include/QtCore/myqobject.h"),
+        Path::new("qobject.h")
+    ));
+}
+
+#[test]
+fn preprocessor() {
+    assert_eq!(preprocessor_command(None), OsString::from("clang++"));
+    assert_eq!(
+        preprocessor_command(Some("".into())),
+        OsString::from("clang++")
+    );
+    assert_eq!(
+        preprocessor_command(Some("/opt/llvm/bin/clang++".into())),
+        OsString::from("/opt/llvm/bin/clang++")
+    );
+}
+
+#[test]
+fn preprocessed_lines() {
+    let preprocessed = r#"# 1 "/tmp/1.cpp"
+# 1 "<built-in>" 1
+# 1 "/tmp/global.h" 1
+# 1 "/include/a.h" 1
+class A {};
+# 2 "/include/a.h" 2
+# 1 "/include/b.h" 1
+class B {};
+# 3 "/include/a.h" 2
+int a();
+# 2 "/tmp/global.h" 2
+int global();
+"#;
+    assert_eq!(
+        preprocessed_header_lines(preprocessed, Path::new("a.h")),
+        "class A {};\nint a();\n"
+    );
+    assert_eq!(
+        preprocessed_header_lines(preprocessed, Path::new("b.h")),
+        "class B {};\n"
+    );
+}
+
+#[test]
+fn dump_preprocessed_declarations() {
+    let dir = tempdir::TempDir::new("test_dump_preprocessed").unwrap();
+    let mut workspace = Workspace::new(dir.path().join("workspace")).unwrap();
+    let include_dir = dir.path().join("include");
+    create_dir(&include_dir).unwrap();
+    {
+        let mut file = create_file(include_dir.join("a.h")).unwrap();
+        writeln!(file, "#include \"b.h\"\nclass A {{}};").unwrap();
+        let mut file = create_file(include_dir.join("b.h")).unwrap();
+        writeln!(file, "class B {{}};").unwrap();
+    }
+
+    let mut paths = CppBuildPaths::new();
+    paths.add_include_path(&include_dir);
+    let mut config = Config::new(CrateProperties::new("A", "0.0.0"));
+    config.add_include_directive("a.h");
+    config.set_cpp_build_paths(paths);
+    config.add_target_include_path(&include_dir);
+
+    let inspection = Inspection::DumpPreprocessed("a.h".into());
+    processor::process(&mut workspace, &config, &[], Some(&inspection)).unwrap();
+
+    let declarations = file_to_string(workspace.log_path().join("declarations_a_h.txt")).unwrap();
+    assert!(declarations.contains("ClassDecl A at "));
+    assert!(!declarations.contains("ClassDecl B at "));
+}
@@ -0,0 +1,70 @@
+use crate::cpp_data::{
+    CppBaseSpecifier, CppClassField, CppItem, CppNamespace, CppPath, CppVisibility,
+};
+use crate::cpp_tree_shaker::required_items;
+use crate::cpp_type::CppType;
+use crate::tests::class;
+use crate::tests::cpp_method::empty_regular_method;
+use itertools::Itertools;
+
+fn function(name: &str, return_type: &str) -> CppItem {
+    let mut function = empty_regular_method();
+    function.path = CppPath::from_good_str(name);
+    function.return_type = CppType::Class(CppPath::from_good_str(return_type));
+    CppItem::Function(function)
+}
+
+#[test]
+fn required_items_closure() {
+    let items = vec![
+        class("A"),
+        class("B"),
+        class("C"),
+        function("f", "A"),
+        function("g", "C"),
+        CppItem::ClassBase(CppBaseSpecifier {
+            base_class_type: CppPath::from_good_str("B"),
+            base_index: 0,
+            is_virtual: false,
+            visibility: CppVisibility::Public,
+            derived_class_type: CppPath::from_good_str("A"),
+        }),
+        CppItem::Namespace(CppNamespace {
+            path: CppPath::from_good_str("ns"),
+        }),
+    ];
+    let entry_points = vec![CppPath::from_good_str("f")];
+    let required = required_items(&items.iter().collect_vec(), &entry_points);
+    assert_eq!(
+        required.into_iter().sorted().collect_vec(),
+        vec![0, 1, 3, 5, 6]
+    );
+}
+
+#[test]
+fn required_field_types() {
+    let field = |path: &str, field_type: &str| {
+        CppItem::ClassField(CppClassField {
+            path: CppPath::from_good_str(path),
+            field_type: CppType::Class(CppPath::from_good_str(field_type)),
+            visibility: CppVisibility::Public,
+            offset: None,
+        })
+    };
+    let items = vec![
+        class("A"),
+        class("B"),
+        class("C"),
+        class("D"),
+        function("f", "A"),
+        field("A::b", "B"),
+        field("B::c", "C"),
+        field("D::a", "A"),
+    ];
+    let entry_points = vec![CppPath::from_good_str("f")];
+    let required = required_items(&items.iter().collect_vec(), &entry_points);
+    assert_eq!(
+        required.into_iter().sorted().collect_vec(),
+        vec![0, 1, 2, 4, 5, 6]
+    );
+}
@@ -360,6 +360,36 @@ fn function1() {
     assert_type_to_ffi_unchanged(&type1);
 }
 
+#[test]
+fn array() {
+    let type1 = CppType::Array {
+        element_type: Box::new(CppType::BuiltInNumeric(CppBuiltInNumericType::Int)),
+        size: 4,
+    };
+    assert_eq!(type1.is_array(), true);
+    assert_eq!(type1.is_class(), false);
+    assert_eq!(
+        type1.to_cpp_code(None).unwrap(),
+        "::ritual::identity<int[4]>"
+    );
+    assert_eq!(type1.to_cpp_pseudo_code(), "int[4]");
+    assert_eq!(type1.ascii_caption(), "int_array4");
+
+    let ffi_arg = ffi_type(&type1, CppTypeRole::NotReturnType).unwrap();
+    assert_eq!(
+        ffi_arg.ffi_type(),
+        &CppType::new_pointer(false, type1.clone())
+    );
+    assert_eq!(
+        ffi_arg.ffi_type().to_cpp_code(None).unwrap(),
+        "::ritual::identity<int[4]> *"
+    );
+    assert!(ffi_type(&type1, CppTypeRole::ReturnType).is_err());
+
+    let type2 = CppType::new_pointer(true, type1.clone());
+    assert_type_to_ffi_unchanged(&type2);
+}
+
 #[test]
 fn instantiate1() {
     let type1 = CppType::new_reference(
@@ -380,3 +410,32 @@ fn instantiate1() {
         )
     );
 }
+
+#[test]
+fn match_template() {
+    let param = CppType::TemplateParameter(CppTemplateParameter {
+        nested_level: 0,
+        index: 0,
+        name: "T".into(),
+    });
+    let int = CppType::BuiltInNumeric(CppBuiltInNumericType::Int);
+    let pattern = CppPath::from_item(CppPathItem {
+        name: "QList".into(),
+        template_arguments: Some(vec![CppType::new_pointer(false, param)]),
+    });
+    let pointer_list = CppPath::from_item(CppPathItem {
+        name: "QList".into(),
+        template_arguments: Some(vec![CppType::new_pointer(false, int.clone())]),
+    });
+    let int_list = CppPath::from_item(CppPathItem {
+        name: "QList".into(),
+        template_arguments: Some(vec![int.clone()]),
+    });
+
+    let mut arguments = Vec::new();
+    assert!(pattern.match_template(&pointer_list, 0, &mut arguments));
+    assert_eq!(arguments, vec![Some(int)]);
+
+    let mut arguments = Vec::new();
+    assert!(!pattern.match_template(&int_list, 0, &mut arguments));
+}
@@ -0,0 +1,51 @@
+use crate::crate_graph::CrateGraph;
+
+fn names(list: &[&str]) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn levels() {
+    let mut graph = CrateGraph::new();
+    graph.add_crate("widgets", names(&["gui", "core"]));
+    graph.add_crate("core", names(&["std"]));
+    graph.add_crate("gui", names(&["core"]));
+    graph.add_crate("charts", names(&["core"]));
+    let levels = graph.levels().unwrap();
+    assert_eq!(
+        levels,
+        vec![
+            names(&["core"]),
+            names(&["gui", "charts"]),
+            names(&["widgets"])
+        ]
+    );
+}
+
+#[test]
+fn cycle() {
+    let mut graph = CrateGraph::new();
+    graph.add_crate("a", names(&["b"]));
+    graph.add_crate("b", names(&["a"]));
+    graph.add_crate("c", names(&[]));
+    assert!(graph.levels().is_err());
+}
+
+#[test]
+fn ready_crates() {
+    let mut graph = CrateGraph::new();
+    graph.add_crate("widgets", names(&["gui", "core"]));
+    graph.add_crate("core", names(&["std"]));
+    graph.add_crate("gui", names(&["core"]));
+    graph.add_crate("charts", names(&["core"]));
+    assert_eq!(graph.ready_crates(&[], &[]), names(&["core"]));
+    assert!(graph.ready_crates(&names(&["core"]), &[]).is_empty());
+    assert_eq!(
+        graph.ready_crates(&names(&["core"]), &names(&["core"])),
+        names(&["gui", "charts"])
+    );
+    assert_eq!(
+        graph.ready_crates(&names(&["core", "gui"]), &names(&["core", "gui"])),
+        names(&["charts", "widgets"])
+    );
+}
@@ -0,0 +1,317 @@
+use crate::cpp_data::{CppItem, CppNamespace, CppOriginLocation, CppPath};
+use crate::cpp_function::CppFunctionArgument;
+use crate::cpp_type::{CppBuiltInNumericType, CppType};
+use crate::database::{
+    crate_db_path, Database, DatabaseReader, CRATE_DB_FILE_NAME, LEGACY_CRATE_DB_FILE_NAME,
+};
+use crate::rust_info::{RustItem, RustModule, RustModuleKind};
+use crate::rust_type::RustPath;
+use crate::tests::cpp_method::empty_regular_method;
+use crate::tests::{db_client, empty_db_client};
+use itertools::Itertools;
+use ritual_common::file_utils::{read_dir, save_json};
+
+#[test]
+fn save_and_load() {
+    let dir = tempdir::TempDir::new("ritual_test_database").unwrap();
+    let mut db = empty_db_client();
+    for name in &["ns1", "ns2"] {
+        let item = CppItem::Namespace(CppNamespace {
+            path: CppPath::from_good_str(name),
+        });
+        db.add_cpp_item_without_hook(None, item).unwrap();
+    }
+
+    let path = dir.path().join("foo.json");
+    db.data().save(&path, None).unwrap();
+    let reader = DatabaseReader::open(&path).unwrap();
+    assert_eq!(reader.crate_name(), "foo");
+    assert_eq!(reader.count(), 2);
+    let loaded = db_client(Database::load(&path).unwrap());
+    assert_eq!(loaded.cpp_items().count(), 2);
+
+    // databases saved as a single JSON object are still supported
+    let legacy_path = dir.path().join("legacy.json");
+    save_json(&legacy_path, db.data(), None).unwrap();
+    let reader = DatabaseReader::open(&legacy_path).unwrap();
+    assert_eq!(reader.count(), 2);
+}
+
+#[test]
+fn sharded() {
+    let dir = tempdir::TempDir::new("ritual_test_database_sharded").unwrap();
+    {
+        let mut client = empty_db_client();
+        for name in &["ns1", "ns1::f", "ns2", "NS2"] {
+            let item = CppItem::Namespace(CppNamespace {
+                path: CppPath::from_good_str(name),
+            });
+            client.add_cpp_item_without_hook(None, item).unwrap();
+        }
+        let path = dir.path().join("foo");
+        client.save_sharded(&path, None).unwrap();
+        // names are followed by a hash of the original path
+        let shards = read_dir(path.join("items"))
+            .unwrap()
+            .map(|entry| {
+                let name = entry.unwrap().file_name().into_string().unwrap();
+                assert!(name.ends_with(".json"));
+                name[..name.len() - "_00000000.json".len()].to_string()
+            })
+            .sorted()
+            .collect_vec();
+        assert_eq!(shards, vec!["ns1", "ns1_f", "ns2", "ns2"]);
+    }
+
+    let loaded = db_client(Database::load(dir.path().join("foo")).unwrap());
+    assert_eq!(loaded.crate_name(), "foo");
+    assert_eq!(loaded.cpp_items().count(), 4);
+}
+
+#[test]
+fn conflicts() {
+    let mut function = empty_regular_method();
+    function.arguments.push(CppFunctionArgument {
+        name: "arg".into(),
+        argument_type: CppType::BuiltInNumeric(CppBuiltInNumericType::Int),
+        has_default_value: false,
+    });
+    let mut function2 = function.clone();
+    function2.arguments[0].name = "other".into();
+    let mut function3 = function.clone();
+    function3.arguments[0].has_default_value = true;
+
+    let mut client = empty_db_client();
+    let id = client
+        .add_cpp_item_without_hook(None, CppItem::Function(function))
+        .unwrap()
+        .unwrap();
+    // different argument names are not a conflict
+    let r = client.add_cpp_item_without_hook(None, CppItem::Function(function2));
+    assert_eq!(r.unwrap(), None);
+    assert_eq!(client.cpp_conflict_items().count(), 0);
+
+    for _ in 0..2 {
+        let r = client.add_cpp_item_without_hook(None, CppItem::Function(function3.clone()));
+        assert_eq!(r.unwrap(), None);
+    }
+    let conflicts = client.cpp_conflict_items().collect::<Vec<_>>();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].source_id, Some(id));
+    assert_eq!(conflicts[0].item.item, CppItem::Function(function3));
+}
+
+#[test]
+fn item_context() {
+    let mut client = empty_db_client();
+    let id1 = client
+        .add_cpp_item_without_hook(
+            None,
+            CppItem::Namespace(CppNamespace {
+                path: CppPath::from_good_str("ns1"),
+            }),
+        )
+        .unwrap()
+        .unwrap();
+    client
+        .set_cpp_origin_location(
+            &id1,
+            CppOriginLocation {
+                include_file_path: "/include/ns1.h".into(),
+                line: 10,
+                column: 1,
+            },
+        )
+        .unwrap();
+    let id2 = client
+        .add_cpp_item_without_hook(
+            Some(id1.clone()),
+            CppItem::Namespace(CppNamespace {
+                path: CppPath::from_good_str("ns1::ns2"),
+            }),
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        client.item_context(&id2),
+        format!(
+            "while processing {} namespace ns1::ns2, \
+             derived from {} namespace ns1 (at /include/ns1.h:10:1)",
+            id2, id1
+        )
+    );
+}
+
+#[test]
+fn known_crashes() {
+    let dir = tempdir::TempDir::new("ritual_test_database_crashes").unwrap();
+    let mut db = empty_db_client();
+    db.set_known_crash("QFoo::bar", Some("crashes without QApplication"));
+    let path = dir.path().join("foo.json");
+    db.data().save(&path, None).unwrap();
+
+    let mut loaded = db_client(Database::load(&path).unwrap());
+    assert_eq!(
+        loaded.known_crash_reason(&CppPath::from_good_str("QFoo::bar")),
+        Some("crashes without QApplication")
+    );
+    assert_eq!(
+        loaded.known_crash_reason(&CppPath::from_good_str("QFoo::baz")),
+        None
+    );
+    loaded.set_known_crash("QFoo::bar", None);
+    assert_eq!(loaded.known_crashes().count(), 0);
+}
+
+#[test]
+fn annotations() {
+    let mut db = empty_db_client();
+    let item = CppItem::Namespace(CppNamespace {
+        path: CppPath::from_good_str("ns1"),
+    });
+    let id = db.add_cpp_item_without_hook(None, item).unwrap().unwrap();
+    db.add_annotation(&id, "first").unwrap();
+    db.add_annotation(&id, "second").unwrap();
+    let annotations = db.annotations(&id).unwrap();
+    assert_eq!(
+        annotations
+            .iter()
+            .map(|a| (a.revision, a.text.as_str()))
+            .collect_vec(),
+        vec![(1, "first"), (2, "second")]
+    );
+    assert_eq!(db.all_annotations().count(), 1);
+    assert_eq!(db.all_annotations().next().unwrap().0, "cpp: namespace ns1");
+
+    let item2 = CppItem::Namespace(CppNamespace {
+        path: CppPath::from_good_str("ns2"),
+    });
+    let id2 = db.add_cpp_item_without_hook(None, item2).unwrap().unwrap();
+    assert!(db.annotations(&id2).unwrap().is_empty());
+    db.add_annotation(&id2, "other").unwrap();
+    assert_eq!(db.annotations(&id).unwrap().len(), 2);
+    assert_eq!(db.annotations(&id2).unwrap().len(), 1);
+    db.clear_annotations(&id2).unwrap();
+
+    // annotations are bound to the item's text, not its id
+    db.delete_items(|_| true);
+    let item = CppItem::Namespace(CppNamespace {
+        path: CppPath::from_good_str("ns1"),
+    });
+    let new_id = db.add_cpp_item_without_hook(None, item).unwrap().unwrap();
+    assert_ne!(id, new_id);
+    assert_eq!(db.annotations(&new_id).unwrap().len(), 2);
+    db.clear_annotations(&new_id).unwrap();
+    assert_eq!(db.all_annotations().count(), 0);
+}
+
+#[test]
+fn legacy_enum_wrapper() {
+    use crate::rust_info::{EnumRepresentation, RustStructKind, RustWrapperTypeKind};
+
+    let kind: RustStructKind = serde_json::from_str(r#"{"WrapperType":"EnumWrapper"}"#).unwrap();
+    assert_eq!(
+        kind,
+        RustStructKind::WrapperType(RustWrapperTypeKind::EnumWrapper {
+            representation: EnumRepresentation::Newtype,
+            is_scoped: false,
+            underlying_type: None,
+        })
+    );
+    let json = serde_json::to_string(&kind).unwrap();
+    assert_eq!(serde_json::from_str::<RustStructKind>(&json).unwrap(), kind);
+}
+
+#[test]
+fn legacy_crate_db_file() {
+    let dir = tempdir::TempDir::new("ritual_test_database").unwrap();
+    let legacy_path = dir.path().join(LEGACY_CRATE_DB_FILE_NAME);
+    assert_eq!(
+        crate_db_path(dir.path()),
+        dir.path().join(CRATE_DB_FILE_NAME)
+    );
+
+    save_json(&legacy_path, &Database::empty("foo".into()), None).unwrap();
+    assert_eq!(crate_db_path(dir.path()), legacy_path);
+    let db = Database::load(crate_db_path(dir.path())).unwrap();
+    assert_eq!(db.crate_name(), "foo");
+
+    db.save(dir.path().join(CRATE_DB_FILE_NAME), None).unwrap();
+    assert_eq!(
+        crate_db_path(dir.path()),
+        dir.path().join(CRATE_DB_FILE_NAME)
+    );
+}
+
+#[test]
+fn return_type_conflicts() {
+    let function = empty_regular_method();
+    let mut function2 = function.clone();
+    function2.return_type = CppType::BuiltInNumeric(CppBuiltInNumericType::Int);
+    assert!(function.is_same(&function2));
+
+    let mut client = empty_db_client();
+    let id = client
+        .add_cpp_item_without_hook(None, CppItem::Function(function))
+        .unwrap()
+        .unwrap();
+    let r = client.add_cpp_item_without_hook(None, CppItem::Function(function2.clone()));
+    assert_eq!(r.unwrap(), None);
+    let conflicts = client.cpp_conflict_items().collect::<Vec<_>>();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].source_id, Some(id));
+    assert_eq!(conflicts[0].item.item, CppItem::Function(function2));
+}
+
+#[test]
+fn origin_location_marks_modified() {
+    let mut client = empty_db_client();
+    let id = client
+        .add_cpp_item_without_hook(
+            None,
+            CppItem::Namespace(CppNamespace {
+                path: CppPath::from_good_str("ns1"),
+            }),
+        )
+        .unwrap()
+        .unwrap();
+    let location = CppOriginLocation {
+        include_file_path: "/include/ns1.h".into(),
+        line: 10,
+        column: 1,
+    };
+    client.set_saved();
+    client
+        .set_cpp_origin_location(&id, location.clone())
+        .unwrap();
+    assert!(client.is_modified());
+    assert_eq!(client.cpp_origin_location(&id).unwrap(), Some(&location));
+
+    client.set_saved();
+    client.set_cpp_origin_location(&id, location).unwrap();
+    assert!(!client.is_modified());
+}
+
+#[test]
+fn namespace_modules_without_source() {
+    let mut db = empty_db_client();
+    let module = |path: &str| {
+        RustItem::Module(RustModule {
+            is_public: true,
+            path: RustPath::from_good_str(path),
+            kind: RustModuleKind::CppNamespace,
+        })
+    };
+    assert!(db
+        .add_rust_item(None, module("foo::ns1"))
+        .unwrap()
+        .is_some());
+    assert!(db
+        .add_rust_item(None, module("foo::ns2"))
+        .unwrap()
+        .is_some());
+    assert!(db
+        .add_rust_item(None, module("foo::ns1"))
+        .unwrap()
+        .is_none());
+}
@@ -0,0 +1,47 @@
+use crate::cpp_data::CppPath;
+use crate::cpp_ffi_data::{CppFfiItem, CppFieldOffset};
+use crate::database::Database;
+use crate::ffi_spec::{apply_ffi_spec, ffi_spec, FfiSpec};
+use crate::tests::{db_client, empty_db_client};
+
+fn field_offset(path: &str, offset: usize) -> CppFfiItem {
+    CppFfiItem::FieldOffset(CppFieldOffset {
+        path: CppPath::from_good_str(path),
+        offset,
+    })
+}
+
+#[test]
+fn rejected_items_are_persisted() {
+    let dir = tempdir::TempDir::new("ritual_test_ffi_spec").unwrap();
+    let mut db = empty_db_client();
+    db.add_ffi_item(None, field_offset("A::x", 0)).unwrap();
+    db.add_ffi_item(None, field_offset("A::y", 4)).unwrap();
+
+    let mut spec = ffi_spec(&db).unwrap();
+    assert_eq!(spec.items.len(), 2);
+    spec.items
+        .retain(|item| item.item.path().last().name == "x");
+    // round trip through the file format
+    let spec: FfiSpec = serde_json::from_str(&serde_json::to_string(&spec).unwrap()).unwrap();
+
+    assert_eq!(apply_ffi_spec(&mut db, spec).unwrap(), (1, 0));
+    assert_eq!(db.ffi_items().count(), 1);
+    let rejected = field_offset("A::y", 4).spec_key();
+    assert!(db.is_ffi_item_rejected(&rejected));
+    assert!(!db.is_ffi_item_rejected(&field_offset("A::x", 0).spec_key()));
+
+    let path = dir.path().join("foo.json");
+    db.data().save(&path, None).unwrap();
+    let loaded = db_client(Database::load(&path).unwrap());
+    assert!(loaded.is_ffi_item_rejected(&rejected));
+    assert_eq!(loaded.ffi_items().count(), 1);
+}
+
+#[test]
+fn spec_for_other_crate() {
+    let mut db = empty_db_client();
+    let mut spec = ffi_spec(&db).unwrap();
+    spec.crate_name = "bar".into();
+    assert!(apply_ffi_spec(&mut db, spec).is_err());
+}
@@ -0,0 +1,68 @@
+use crate::html_logger::{anchor, HtmlLogger, HtmlTemplates, Severity};
+use std::fs;
+use std::time::Duration;
+
+#[test]
+fn severities_and_summary() {
+    let dir = tempdir::TempDir::new("test_html_logger").unwrap();
+    let report_path = dir.path().join("index.html");
+    let mut logger = HtmlLogger::new(&report_path, "A & B", HtmlTemplates::default()).unwrap();
+    logger.add_stage("parser", Duration::from_millis(1500));
+    logger.add_header(&["Item"]).unwrap();
+    logger
+        .add_item(&anchor("foo#1 a/b"), Severity::Error, &["x"])
+        .unwrap();
+    logger.add_item("item-2", Severity::Error, &["y"]).unwrap();
+    logger
+        .add_item("item-3", Severity::Warning, &["z"])
+        .unwrap();
+    logger.finish().unwrap();
+
+    let report = fs::read_to_string(&report_path).unwrap();
+    assert!(report.contains("<title>A &amp; B</title>"));
+    assert!(report.contains("<tr><th>Item</th></tr>"));
+    assert!(report.contains("<tr id=\"foo-1-a-b\" class=\"error\"><td>x</td></tr>"));
+    assert!(report.contains("<tr id=\"item-3\" class=\"warning\"><td>z</td></tr>"));
+    assert!(report.contains("<span class=\"error\">error: 2</span>"));
+    assert!(report.contains("<span class=\"warning\">warning: 1</span>"));
+    assert!(report.contains("<span class=\"info\">info: 0</span>"));
+    assert!(report.contains("<li>parser: 1.5 s</li>"));
+    assert!(report.find("class=\"summary\"").unwrap() < report.find("<table>").unwrap());
+    assert!(dir.path().join("style.css").exists());
+}
+
+#[test]
+fn theme_overrides_templates() {
+    let dir = tempdir::TempDir::new("test_html_logger").unwrap();
+    let theme_path = dir.path().join("theme");
+    fs::create_dir(&theme_path).unwrap();
+    fs::write(
+        theme_path.join("row.html"),
+        "<tr{attributes} data-theme=\"custom\">{cells}</tr>\n",
+    )
+    .unwrap();
+    fs::write(theme_path.join("style.css"), "body { color: red; }\n").unwrap();
+
+    let templates = HtmlTemplates::load(Some(theme_path.as_path())).unwrap();
+    let default = HtmlTemplates::default();
+    assert_eq!(templates.header, default.header);
+    assert_eq!(templates.footer, default.footer);
+    assert_eq!(templates.script, default.script);
+    assert_ne!(templates.row, default.row);
+    assert_ne!(templates.style, default.style);
+
+    let report_path = dir.path().join("index.html");
+    let mut logger = HtmlLogger::new(&report_path, "report", templates).unwrap();
+    logger.add_item("x", Severity::Error, &["y"]).unwrap();
+    logger.finish().unwrap();
+
+    let report = fs::read_to_string(&report_path).unwrap();
+    assert!(
+        report.contains("<tr id=\"x\" class=\"error\" data-theme=\"custom\"><td>y</td></tr>")
+    );
+    assert!(report.ends_with(&default.footer));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("style.css")).unwrap(),
+        "body { color: red; }\n"
+    );
+}
@@ -0,0 +1,128 @@
+use crate::include_graph::{EntryHeaderResolver, IncludeGraph};
+use ritual_common::file_utils::canonicalize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn qlist_graph() -> IncludeGraph {
+    let mut graph = IncludeGraph::default();
+    graph.roots.insert("/inc/QtCore".into());
+    graph
+        .includes
+        .entry("/inc/QtCore".into())
+        .or_default()
+        .insert("/inc/qlist.h".into());
+    graph
+        .includes
+        .entry("/inc/qlist.h".into())
+        .or_default()
+        .insert("/inc/qlist_impl.h".into());
+    graph
+        .includes
+        .entry("/inc/qlist_impl.h".into())
+        .or_default()
+        .insert("/inc/qlist_detail.h".into());
+    graph
+}
+
+#[test]
+fn entry_header() {
+    let graph = qlist_graph();
+    let qlist = Some(PathBuf::from("/inc/qlist.h"));
+    assert_eq!(graph.entry_header(Path::new("/inc/qlist.h")), qlist);
+    assert_eq!(graph.entry_header(Path::new("/inc/qlist_impl.h")), qlist);
+    assert_eq!(graph.entry_header(Path::new("/inc/qlist_detail.h")), qlist);
+    assert_eq!(
+        graph.entry_header(Path::new("/inc/QtCore")),
+        Some(PathBuf::from("/inc/QtCore"))
+    );
+    assert_eq!(graph.entry_header(Path::new("/inc/other.h")), None);
+}
+
+#[test]
+fn entry_header_resolver() {
+    let graph = qlist_graph();
+    let mut resolver = EntryHeaderResolver::new(&graph);
+    let qlist = Some(PathBuf::from("/inc/qlist.h"));
+    for _ in 0..2 {
+        assert_eq!(
+            resolver.entry_header(Path::new("/inc/qlist_detail.h")),
+            qlist
+        );
+        assert_eq!(resolver.entry_header(Path::new("/inc/other.h")), None);
+    }
+}
+
+#[test]
+fn files() {
+    let graph = qlist_graph();
+    assert_eq!(
+        graph.files().into_iter().collect::<Vec<_>>(),
+        vec![
+            Path::new("/inc/QtCore"),
+            Path::new("/inc/qlist.h"),
+            Path::new("/inc/qlist_detail.h"),
+            Path::new("/inc/qlist_impl.h"),
+        ]
+    );
+}
+
+#[test]
+fn merge() {
+    let mut graph = IncludeGraph::default();
+    graph.roots.insert("/inc/QtGui".into());
+    graph
+        .includes
+        .entry("/inc/qlist.h".into())
+        .or_default()
+        .insert("/inc/qvector.h".into());
+    graph.merge(qlist_graph());
+    assert_eq!(graph.roots.len(), 2);
+    assert_eq!(
+        graph.includes[Path::new("/inc/qlist.h")],
+        vec![
+            PathBuf::from("/inc/qlist_impl.h"),
+            PathBuf::from("/inc/qvector.h")
+        ]
+        .into_iter()
+        .collect()
+    );
+}
+
+#[test]
+fn header_groups() {
+    let dir = tempdir::TempDir::new("test_header_groups").unwrap();
+    let main_file = dir.path().join("1.cpp");
+    fs::write(&main_file, "#include <qfoo_b.h>").unwrap();
+
+    let mut group_graph = IncludeGraph::default();
+    group_graph
+        .includes
+        .entry(canonicalize(&main_file).unwrap())
+        .or_default()
+        .insert("/inc/qfoo_b.h".into());
+    group_graph
+        .includes
+        .entry("/inc/qfoo_b.h".into())
+        .or_default()
+        .insert("/inc/qfoo_impl.h".into());
+    group_graph.move_to_group(1, &main_file).unwrap();
+    assert_eq!(group_graph.groups.len(), 2);
+    assert!(group_graph.groups[0].is_empty());
+    assert_eq!(
+        group_graph.groups[1],
+        vec![PathBuf::from("/inc/qfoo_b.h")].into_iter().collect()
+    );
+    assert_eq!(group_graph.includes.len(), 1);
+
+    let mut graph = qlist_graph();
+    graph.merge(group_graph.clone());
+    // merging the same group again doesn't add a new group
+    graph.merge(group_graph);
+    assert_eq!(graph.groups.len(), 2);
+    assert_eq!(graph.header_group(Path::new("/inc/qfoo_b.h")), Some(1));
+    assert_eq!(graph.header_group(Path::new("/inc/qlist.h")), None);
+    assert_eq!(
+        graph.entry_header(Path::new("/inc/qfoo_impl.h")),
+        Some(PathBuf::from("/inc/qfoo_b.h"))
+    );
+}
@@ -0,0 +1,35 @@
+use crate::lockfile::Lockfile;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+fn lockfile(headers: &[(&str, &str)]) -> Lockfile {
+    Lockfile {
+        crate_name: "foo".into(),
+        generator_version: "0.1.0".into(),
+        clang_version: "clang 8".into(),
+        cpp_lib_version: Some("5.13".into()),
+        config_hash: "00000001".into(),
+        headers: headers
+            .iter()
+            .map(|(path, hash)| (PathBuf::from(path), hash.to_string()))
+            .collect::<BTreeMap<_, _>>(),
+    }
+}
+
+#[test]
+fn lockfile_differences() {
+    let old = lockfile(&[("/a.h", "1"), ("/b.h", "2")]);
+    assert!(old.differences(&old).is_empty());
+
+    let mut new = lockfile(&[("/a.h", "3"), ("/c.h", "4")]);
+    new.cpp_lib_version = Some("5.14".into());
+    assert_eq!(
+        old.differences(&new),
+        vec![
+            "library version: Some(\"5.13\") -> Some(\"5.14\")".to_string(),
+            "header changed: /a.h".to_string(),
+            "header removed: /b.h".to_string(),
+            "header added: /c.h".to_string(),
+        ]
+    );
+}
@@ -1,7 +1,62 @@
 #![allow(clippy::cognitive_complexity)]
 
+mod clang_cache;
+mod clang_compat;
+mod cli;
+mod class_graph;
+mod config;
+mod cpp_casts;
+mod cpp_checker;
+mod cpp_checks;
+mod cpp_code_generator;
 mod cpp_ffi_data;
 mod cpp_method;
 mod cpp_operator;
 mod cpp_parser;
+mod cpp_tree_shaker;
 mod cpp_type;
+mod crate_graph;
+mod database;
+mod ffi_spec;
+mod html_logger;
+mod include_graph;
+mod lockfile;
+mod numeric_types;
+mod overrides;
+mod processor;
+mod rust_checker;
+mod rust_code_generator;
+mod rust_generator;
+mod std_conversions;
+mod string_encoding;
+mod symbol_audit;
+mod test_harness;
+mod workspace;
+
+use crate::cpp_data::{CppItem, CppPath, CppTypeDeclaration, CppTypeDeclarationKind};
+use crate::database::{Database, DatabaseClient, IndexedDatabase};
+use ritual_common::ReadOnly;
+
+/// Creates a client of `db` without dependencies.
+pub fn db_client(db: Database) -> DatabaseClient {
+    DatabaseClient::new(
+        IndexedDatabase::new(db, Default::default()),
+        ReadOnly::new(Vec::new()),
+    )
+}
+
+/// Creates a client of an empty database of the crate `foo`.
+pub fn empty_db_client() -> DatabaseClient {
+    db_client(Database::empty("foo".into()))
+}
+
+/// Returns a declaration of the class `name`.
+pub fn class(name: &str) -> CppItem {
+    CppItem::Type(CppTypeDeclaration {
+        path: CppPath::from_good_str(name),
+        kind: CppTypeDeclarationKind::Class,
+        specialization: None,
+        is_trivially_copyable: false,
+        is_immovable: false,
+    })
+}
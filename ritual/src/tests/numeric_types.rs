@@ -0,0 +1,55 @@
+use crate::cpp_type::{CppBuiltInNumericType, CppPointerLikeTypeKind, CppType};
+use crate::numeric_types::PlatformNumericType;
+use ritual_common::target::{current_target, Arch, Env, PointerWidth, OS};
+
+#[test]
+fn resolve() {
+    let mut linux = current_target();
+    linux.arch = Arch::X86_64;
+    linux.os = OS::Linux;
+    linux.env = Env::Gnu;
+    linux.pointer_width = PointerWidth::P64;
+
+    let mut windows = linux.clone();
+    windows.os = OS::Windows;
+    windows.env = Env::Msvc;
+
+    assert_eq!(PlatformNumericType::LongDouble.resolve(&linux), None);
+    assert_eq!(
+        PlatformNumericType::LongDouble.resolve(&windows),
+        Some("f64")
+    );
+    assert_eq!(PlatformNumericType::Int128.resolve(&linux), None);
+    assert_eq!(PlatformNumericType::UInt128.resolve(&linux), None);
+    assert_eq!(PlatformNumericType::Int128.resolve(&windows), None);
+    assert_eq!(PlatformNumericType::WChar.resolve(&linux), Some("i32"));
+    assert_eq!(PlatformNumericType::WChar.resolve(&windows), Some("u16"));
+
+    let mut linux_arm = linux.clone();
+    linux_arm.arch = Arch::AArch64;
+    assert_eq!(PlatformNumericType::WChar.resolve(&linux_arm), Some("u32"));
+
+    let mut mac_arm = linux_arm.clone();
+    mac_arm.os = OS::MacOS;
+    mac_arm.env = Env::None;
+    assert_eq!(PlatformNumericType::WChar.resolve(&mac_arm), Some("i32"));
+    assert_eq!(
+        PlatformNumericType::LongDouble.resolve(&mac_arm),
+        Some("f64")
+    );
+}
+
+#[test]
+fn used_in() {
+    let type1 = CppType::PointerLike {
+        kind: CppPointerLikeTypeKind::Pointer,
+        is_const: true,
+        target: Box::new(CppType::BuiltInNumeric(CppBuiltInNumericType::LongDouble)),
+    };
+    assert_eq!(
+        PlatformNumericType::used_in(&type1),
+        vec![PlatformNumericType::LongDouble]
+    );
+    let type2 = CppType::BuiltInNumeric(CppBuiltInNumericType::Double);
+    assert!(PlatformNumericType::used_in(&type2).is_empty());
+}
@@ -0,0 +1,133 @@
+use crate::cpp_data::{CppItem, CppPath};
+use crate::database::{DatabaseClient, ItemId};
+use crate::overrides::{ItemOverride, Overrides, ReceiverMutability};
+use crate::rust_info::{RustFfiWrapperData, RustFunction, RustFunctionKind, RustItem};
+use crate::rust_type::{
+    RustCommonType, RustFinalType, RustPath, RustToFfiTypeConversion, RustType,
+};
+use crate::tests::cpp_method::empty_regular_method;
+use crate::tests::empty_db_client;
+use ritual_common::file_utils::create_file;
+use std::io::Write;
+
+#[test]
+fn load_overrides() {
+    let dir = tempdir::TempDir::new("test_overrides").unwrap();
+    let path = dir.path().join("overrides.toml");
+    write!(
+        create_file(&path).unwrap(),
+        "[\"ns::Class::method\"]\nforce_unsafe = true\nreceiver = \"mut\"\n\n\
+         [\"ns::Class\"]\nskip = true\n"
+    )
+    .unwrap();
+
+    let overrides = Overrides::load(&path).unwrap();
+    assert_eq!(
+        overrides.get(&CppPath::from_good_str("ns::Class::method")),
+        Some(&ItemOverride {
+            force_unsafe: true,
+            receiver: Some(ReceiverMutability::Mut),
+            ..ItemOverride::default()
+        })
+    );
+    assert!(
+        overrides
+            .get(&CppPath::from_good_str("ns::Class"))
+            .unwrap()
+            .skip
+    );
+    assert_eq!(overrides.get(&CppPath::from_good_str("ns::Other")), None);
+
+    write!(
+        create_file(&path).unwrap(),
+        "[\"ns::Class\"]\nunknown_directive = true\n"
+    )
+    .unwrap();
+    assert!(Overrides::load(&path).is_err());
+}
+
+fn load(text: &str) -> Overrides {
+    let dir = tempdir::TempDir::new("test_overrides").unwrap();
+    let path = dir.path().join("overrides.toml");
+    write!(create_file(&path).unwrap(), "{}", text).unwrap();
+    Overrides::load(&path).unwrap()
+}
+
+/// Returns a database containing C++ functions `names`, and their ids.
+fn database(names: &[&str]) -> (DatabaseClient, Vec<ItemId>) {
+    let mut db = empty_db_client();
+    let ids = names
+        .iter()
+        .map(|name| {
+            let mut function = empty_regular_method();
+            function.path = CppPath::from_good_str(name);
+            db.add_cpp_item_without_hook(None, CppItem::Function(function))
+                .unwrap()
+                .unwrap()
+        })
+        .collect();
+    (db, ids)
+}
+
+fn rust_function(conversion: RustToFfiTypeConversion) -> RustItem {
+    let ffi_type = RustType::new_pointer(
+        true,
+        RustType::Common(RustCommonType {
+            path: RustPath::from_good_str("foo::Class"),
+            generic_arguments: None,
+        }),
+    );
+    RustItem::Function(RustFunction {
+        is_public: true,
+        is_unsafe: false,
+        path: RustPath::from_good_str("foo::Class::method"),
+        kind: RustFunctionKind::FfiWrapper(RustFfiWrapperData {
+            ffi_function_path: RustPath::from_good_str("foo::__ffi::method"),
+            inline_field_access: None,
+            safety_reason: None,
+        }),
+        arguments: Vec::new(),
+        return_type: RustFinalType::new(ffi_type, conversion).unwrap(),
+    })
+}
+
+#[test]
+fn apply_overrides() {
+    let overrides = load(
+        "[\"Class::skipped\"]\nskip = true\n\n\
+         [\"Class::nullable\"]\nnullable = true\nforce_unsafe = true\n\n\
+         [\"Class::renamed\"]\nrename = \"new_name\"\n",
+    );
+    let (db, ids) = database(&[
+        "Class::skipped",
+        "Class::nullable",
+        "Class::renamed",
+        "Class::other",
+    ]);
+
+    let mut item = rust_function(RustToFfiTypeConversion::UtilsRefToPtr {});
+    assert!(!overrides.apply(&db, &ids[0], &mut item).unwrap());
+
+    assert!(overrides.apply(&db, &ids[1], &mut item).unwrap());
+    let function = item.as_function_ref().unwrap();
+    assert!(function.is_unsafe);
+    assert_eq!(
+        function.return_type.conversion(),
+        &RustToFfiTypeConversion::OptionUtilsRefToPtr {}
+    );
+
+    // only references can be nullable
+    let mut item = rust_function(RustToFfiTypeConversion::None);
+    assert!(overrides.apply(&db, &ids[1], &mut item).is_err());
+
+    // the new name is used by the generator before resolving name collisions
+    let mut item = rust_function(RustToFfiTypeConversion::None);
+    assert!(overrides.apply(&db, &ids[2], &mut item).unwrap());
+    assert_eq!(
+        item.as_function_ref().unwrap().path,
+        RustPath::from_good_str("foo::Class::method")
+    );
+    assert_eq!(overrides.rename(&db, &ids[2]).unwrap(), Some("new_name"));
+    assert_eq!(overrides.rename(&db, &ids[1]).unwrap(), None);
+    assert_eq!(overrides.rename(&db, &ids[3]).unwrap(), None);
+}
@@ -0,0 +1,131 @@
+use crate::cpp_data::{CppItem, CppNamespace, CppOriginLocation, CppPath};
+use crate::cpp_function::CppOperator;
+use crate::processor::{
+    delete_parsed_item, function_coverage, slowest_items, FunctionCoverage, ItemTiming,
+};
+use crate::rust_info::{RustFfiWrapperData, RustFunction, RustFunctionKind, RustItem};
+use crate::rust_type::{RustFinalType, RustPath, RustToFfiTypeConversion, RustType};
+use crate::tests::cpp_method::{empty_membership, empty_regular_method};
+use crate::tests::empty_db_client;
+use std::path::PathBuf;
+use std::time::Duration;
+
+fn namespace(name: &str) -> CppItem {
+    CppItem::Namespace(CppNamespace {
+        path: CppPath::from_good_str(name),
+    })
+}
+
+#[test]
+fn delete_parsed_item_with_derived_items() {
+    let mut db = empty_db_client();
+    let id = db
+        .add_cpp_item_without_hook(None, namespace("ns1"))
+        .unwrap()
+        .unwrap();
+    db.set_cpp_origin_location(
+        &id,
+        CppOriginLocation {
+            include_file_path: "/include/ns1.h".into(),
+            line: 1,
+            column: 1,
+        },
+    )
+    .unwrap();
+    db.add_cpp_item_without_hook(Some(id), namespace("ns1::inner"))
+        .unwrap()
+        .unwrap();
+    db.add_cpp_item_without_hook(None, namespace("ns2"))
+        .unwrap()
+        .unwrap();
+
+    let headers = delete_parsed_item(&mut db, "ns1").unwrap();
+    assert_eq!(headers, vec![PathBuf::from("/include/ns1.h")]);
+    let names = db
+        .cpp_items()
+        .map(|item| item.item.path().unwrap().to_templateless_string())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["ns2".to_string()]);
+
+    assert!(delete_parsed_item(&mut db, "ns1").is_err());
+}
+
+#[test]
+fn coverage() {
+    let mut db = empty_db_client();
+    db.add_cpp_item_without_hook(None, namespace("ns")).unwrap();
+    let mut add_function = |path: &str, is_member: bool, is_operator: bool| {
+        let mut function = empty_regular_method();
+        function.path = CppPath::from_good_str(path);
+        if is_member {
+            function.member = Some(empty_membership());
+        }
+        if is_operator {
+            function.operator = Some(CppOperator::Addition);
+        }
+        db.add_cpp_item_without_hook(None, CppItem::Function(function))
+            .unwrap()
+            .unwrap()
+    };
+    add_function("ns::Class::method", true, false);
+    let free_id = add_function("ns::free", false, false);
+    add_function("ns::other", false, false);
+    add_function("ns::operator+", false, true);
+
+    db.add_rust_item(
+        Some(free_id),
+        RustItem::Function(RustFunction {
+            is_public: true,
+            is_unsafe: true,
+            path: RustPath::from_good_str("foo::free"),
+            kind: RustFunctionKind::FfiWrapper(RustFfiWrapperData {
+                ffi_function_path: RustPath::from_good_str("foo::__ffi::ns_free"),
+                inline_field_access: None,
+                safety_reason: None,
+            }),
+            arguments: Vec::new(),
+            return_type: RustFinalType::new(RustType::unit(), RustToFfiTypeConversion::None)
+                .unwrap(),
+        }),
+    )
+    .unwrap();
+
+    let coverage = |kind, total, wrapped| FunctionCoverage {
+        kind,
+        total,
+        wrapped,
+    };
+    assert_eq!(
+        function_coverage(&db).unwrap(),
+        vec![
+            coverage("methods", 1, 0),
+            coverage("free functions", 2, 1),
+            coverage("operators", 1, 0),
+        ]
+    );
+}
+
+#[test]
+fn slowest_items_of_step() {
+    let mut db = empty_db_client();
+    let mut timings = Vec::new();
+    for i in 0..15 {
+        let id = db
+            .add_cpp_item_without_hook(None, namespace(&format!("ns{}", i)))
+            .unwrap()
+            .unwrap();
+        timings.push(ItemTiming {
+            item_id: id,
+            context: if i == 14 { Some("env".into()) } else { None },
+            duration: Duration::from_millis(i * 10),
+        });
+    }
+    let items = slowest_items(&db, timings);
+    assert_eq!(items.len(), 10);
+    assert!(items[0].item.contains("ns14"));
+    assert!(items[0].item.ends_with(" (env)"));
+    assert!((items[0].seconds - 0.14).abs() < 1e-9);
+    assert!(items[1].item.contains("ns13"));
+    assert!(items[9].item.contains("ns5"));
+    assert!(items.windows(2).all(|w| w[0].seconds >= w[1].seconds));
+}
@@ -0,0 +1,46 @@
+use crate::rust_checker::{enclosing_item_path, module_parts};
+use std::path::Path;
+
+#[test]
+fn module_parts_from_file() {
+    let src = Path::new("/tmp/ws/moqt_core/src");
+    assert_eq!(
+        module_parts("moqt_core", src, &src.join("lib.rs")),
+        Some(vec!["moqt_core".to_string()])
+    );
+    assert_eq!(
+        module_parts("moqt_core", src, &src.join("ffi.in.rs")),
+        Some(vec!["moqt_core".to_string()])
+    );
+    assert_eq!(
+        module_parts("moqt_core", src, &src.join("q_object.rs")),
+        Some(vec!["moqt_core".to_string(), "q_object".to_string()])
+    );
+    assert_eq!(
+        module_parts("moqt_core", src, Path::new("/tmp/other/lib.rs")),
+        None
+    );
+}
+
+#[test]
+fn enclosing_item() {
+    let source = "pub struct QObject(u8);\n\
+                  impl QObject {\n    \
+                  pub unsafe fn name(&self) -> i32 {\n        \
+                  0\n    \
+                  }\n\
+                  }\n\
+                  impl<T: Deref<Target = QObject>> crate::ops::Begin for T {\n    \
+                  fn begin(&self) -> i32 {\n        \
+                  0\n    \
+                  }\n\
+                  }\n";
+    let path = |line| enclosing_item_path(source, line);
+    assert_eq!(path(1), Some(vec!["QObject".to_string()]));
+    assert_eq!(
+        path(4),
+        Some(vec!["QObject".to_string(), "name".to_string()])
+    );
+    assert_eq!(path(9), Some(vec!["T".to_string(), "begin".to_string()]));
+    assert_eq!(enclosing_item_path("", 1), None);
+}
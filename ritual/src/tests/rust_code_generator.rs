@@ -0,0 +1,341 @@
+use crate::config::{
+    Config, CrateDependencySource, CrateProperties, OptionalTypeConversion, PluginRegistry,
+};
+use crate::cpp_checks::Condition;
+use crate::cpp_checks::CppChecksItem;
+use crate::cpp_data::{CppPath, CppTypeProperties};
+use crate::cpp_ffi_data::{
+    CppFfiArgumentMeaning, CppFfiFunction, CppFfiFunctionArgument, CppFfiFunctionKind, CppFfiItem,
+    CppFfiType, CppToFfiTypeConversion, CppValueLayout,
+};
+use crate::cpp_function::ReturnValueAllocationPlace;
+use crate::cpp_type::{CppBuiltInNumericType, CppFunctionPointerType, CppType};
+use crate::database::DatabaseClient;
+use crate::rust_code_generator::{
+    enum_to_int_code, generate, layout_assertions_code, optional_type_conversion_code,
+    plugin_registry_code, polymorphic_classes,
+};
+use crate::rust_info::{
+    RustItem, RustModule, RustModuleKind, RustReexport, RustReexportSource, RustSpecialModuleKind,
+};
+use crate::rust_type::RustPath;
+use crate::tests::empty_db_client;
+use ritual_common::target::{current_target, LibraryTarget, Target, OS};
+use std::fs;
+use std::path::PathBuf;
+
+fn add_special_module(db: &mut DatabaseClient, path: &str, kind: RustSpecialModuleKind) {
+    let module = RustModule {
+        is_public: kind == RustSpecialModuleKind::CrateRoot,
+        path: RustPath::from_good_str(path),
+        kind: RustModuleKind::Special(kind),
+    };
+    db.add_rust_item(None, RustItem::Module(module))
+        .unwrap()
+        .unwrap();
+}
+
+#[test]
+fn ffi_declarations() {
+    let mut db = empty_db_client();
+    add_special_module(&mut db, "foo", RustSpecialModuleKind::CrateRoot);
+    add_special_module(&mut db, "foo::__ffi", RustSpecialModuleKind::Ffi);
+
+    let mut config = Config::new(CrateProperties::new("foo", "0.0.0"));
+    config.add_ffi_declarations("pub fn foo_custom() -> ::std::os::raw::c_int;");
+
+    let dir = tempdir::TempDir::new("test_ffi_declarations").unwrap();
+    generate(&db, dir.path(), None::<PathBuf>, &config).unwrap();
+    let code = fs::read_to_string(dir.path().join("ffi.in.rs")).unwrap();
+    let extern_c = code.find("extern \"C\" {").unwrap();
+    let declaration = code
+        .find("pub fn foo_custom() -> ::std::os::raw::c_int;")
+        .unwrap();
+    assert!(extern_c < declaration);
+    assert!(code.trim_end().ends_with('}'));
+}
+
+#[test]
+fn conditional_reexports() {
+    let mut db = empty_db_client();
+    add_special_module(&mut db, "foo", RustSpecialModuleKind::CrateRoot);
+    add_special_module(&mut db, "foo::prelude", RustSpecialModuleKind::Prelude);
+
+    let layout = CppFfiItem::ValueLayout(CppValueLayout {
+        path: CppPath::from_good_str("Point"),
+        is_union: false,
+        fields: Vec::new(),
+    });
+    let ffi_id = db.add_ffi_item(None, layout).unwrap().unwrap();
+    let mut host = current_target();
+    host.os = OS::Linux;
+    for target in vec![host, Target::emscripten()] {
+        let env = LibraryTarget {
+            target,
+            cpp_library_version: None,
+            enabled_modules: Default::default(),
+            flag_set: None,
+        };
+        db.add_environment(env.clone());
+        let item = CppChecksItem {
+            is_success: env.target.os != OS::Emscripten,
+            env,
+        };
+        db.add_cpp_checks_item(ffi_id.clone(), item);
+    }
+
+    for path in &["foo::prelude::Point", "foo::Point"] {
+        let reexport = RustReexport {
+            path: RustPath::from_good_str(path),
+            target: RustPath::from_good_str("foo::geometry::Point"),
+            source: RustReexportSource::CrateLayout,
+        };
+        db.add_rust_item(Some(ffi_id.clone()), RustItem::Reexport(reexport))
+            .unwrap()
+            .unwrap();
+    }
+
+    let config = Config::new(CrateProperties::new("foo", "0.0.0"));
+    let dir = tempdir::TempDir::new("test_conditional_reexports").unwrap();
+    generate(&db, dir.path(), None::<PathBuf>, &config).unwrap();
+    let expected = "#[cfg(any(not(target_os = \"emscripten\"), feature = \"ritual_rustdoc\"))]\n\
+                    pub use crate::geometry::Point as Point;";
+    // the prelude is an inline module of the crate root
+    let lib = fs::read_to_string(dir.path().join("lib.rs")).unwrap();
+    assert!(lib.contains("pub mod prelude {"));
+    assert_eq!(lib.matches(expected).count(), 2);
+}
+
+#[test]
+fn optional_type_conversion() {
+    let mut conversion = OptionalTypeConversion {
+        cpp_type: CppPath::from_good_str("QDateTime"),
+        feature: "chrono_support".to_string(),
+        dependency: "chrono".to_string(),
+        dependency_source: CrateDependencySource::CratesIo {
+            version: "0.4".to_string(),
+        },
+        rust_type: RustPath::from_good_str("chrono::NaiveDateTime"),
+        to_rust: Some(RustPath::from_good_str("foo::chrono_support::to_chrono")),
+        from_rust: Some(RustPath::from_good_str("foo::chrono_support::from_chrono")),
+    };
+    let wrapper_path = RustPath::from_good_str("foo::QDateTime");
+    let code = optional_type_conversion_code(&conversion, &wrapper_path, "foo").unwrap();
+    assert_eq!(
+        code.matches("#[cfg(feature = \"chrono_support\")]").count(),
+        2
+    );
+    assert!(code.contains(
+        "impl<'a> ::std::convert::From<&'a crate::QDateTime> for ::chrono::NaiveDateTime {"
+    ));
+    assert!(code.contains("crate::chrono_support::to_chrono(value)"));
+    assert!(code.contains(
+        "pub fn from_chrono(value: &::chrono::NaiveDateTime) -> ::cpp_core::CppBox<Self> {"
+    ));
+
+    conversion.from_rust = None;
+    let code = optional_type_conversion_code(&conversion, &wrapper_path, "foo").unwrap();
+    assert!(!code.contains("from_chrono"));
+
+    // the mapped type must be gated by the same dependency
+    conversion.rust_type = RustPath::from_good_str("image::RgbaImage");
+    assert!(optional_type_conversion_code(&conversion, &wrapper_path, "foo").is_err());
+}
+
+fn ffi_function(arguments: Vec<(&str, CppType, CppFfiArgumentMeaning)>) -> CppFfiFunction {
+    CppFfiFunction {
+        arguments: arguments
+            .into_iter()
+            .map(|(name, cpp_type, meaning)| CppFfiFunctionArgument {
+                name: name.into(),
+                argument_type: CppFfiType::new(cpp_type, CppToFfiTypeConversion::NoChange).unwrap(),
+                meaning,
+            })
+            .collect(),
+        return_type: CppFfiType::void(),
+        allocation_place: ReturnValueAllocationPlace::NotApplicable,
+        path: CppPath::from_good_str("ffi_register"),
+        kind: CppFfiFunctionKind::Function,
+        calling_convention: Default::default(),
+        out_of_line: false,
+        main_thread_only: false,
+    }
+}
+
+#[test]
+fn plugin_registry() {
+    let void_ptr = CppType::new_pointer(false, CppType::Void);
+    let key = CppType::new_pointer(true, CppType::BuiltInNumeric(CppBuiltInNumericType::Char));
+    let factory = |arguments: Vec<CppType>| {
+        CppType::FunctionPointer(CppFunctionPointerType {
+            return_type: Box::new(void_ptr.clone()),
+            arguments,
+            allows_variadic_arguments: false,
+        })
+    };
+    let registry = PluginRegistry {
+        rust_name: "plugins".to_string(),
+        register: CppPath::from_good_str("PluginRegistrar::PluginRegistrar"),
+        create: None,
+    };
+    let on_panic = "::std::process::abort();";
+
+    let with_data = ffi_function(vec![
+        ("key", key.clone(), CppFfiArgumentMeaning::Argument(0)),
+        (
+            "factory",
+            factory(vec![void_ptr.clone()]),
+            CppFfiArgumentMeaning::Argument(1),
+        ),
+        ("data", void_ptr.clone(), CppFfiArgumentMeaning::Argument(2)),
+    ]);
+    let code = plugin_registry_code(
+        &registry,
+        (&with_data, "crate::__ffi::ffi_register"),
+        None,
+        on_panic,
+    )
+    .unwrap();
+    assert!(code.contains("pub mod plugins {"));
+    assert!(code.contains("pub unsafe fn register<F>"));
+    assert!(code.contains("crate::__ffi::ffi_register(key.as_ptr(), Some(ffi_factory), data)"));
+    assert!(!code.contains("macro_rules!"));
+
+    let without_data = ffi_function(vec![
+        ("key", key.clone(), CppFfiArgumentMeaning::Argument(0)),
+        (
+            "factory",
+            factory(Vec::new()),
+            CppFfiArgumentMeaning::Argument(1),
+        ),
+    ]);
+    let code = plugin_registry_code(
+        &registry,
+        (&without_data, "crate::__ffi::ffi_register"),
+        None,
+        on_panic,
+    )
+    .unwrap();
+    assert!(code.contains("pub unsafe fn register_raw("));
+    assert!(code.contains("#[macro_export]"));
+    assert!(code.contains("macro_rules! register_plugins {"));
+    assert!(code.contains("$crate::plugins::register_raw($key, ffi_factory)"));
+    assert!(code.contains(on_panic));
+
+    // a registrar constructed in place can't outlive the call
+    let mut on_stack = without_data.clone();
+    on_stack.arguments.insert(
+        0,
+        CppFfiFunctionArgument {
+            name: "output".into(),
+            argument_type: CppFfiType::new(void_ptr, CppToFfiTypeConversion::NoChange).unwrap(),
+            meaning: CppFfiArgumentMeaning::ReturnValue,
+        },
+    );
+    assert!(plugin_registry_code(
+        &registry,
+        (&on_stack, "crate::__ffi::ffi_register"),
+        None,
+        on_panic,
+    )
+    .is_err());
+
+    let wrong_factory = ffi_function(vec![
+        ("key", key, CppFfiArgumentMeaning::Argument(0)),
+        (
+            "factory",
+            factory(Vec::new()),
+            CppFfiArgumentMeaning::Argument(1),
+        ),
+        (
+            "data",
+            factory(Vec::new()),
+            CppFfiArgumentMeaning::Argument(2),
+        ),
+    ]);
+    assert!(plugin_registry_code(
+        &registry,
+        (&wrong_factory, "crate::__ffi::ffi_register"),
+        None,
+        on_panic,
+    )
+    .is_err());
+}
+
+fn type_properties(path: &str, env: LibraryTarget, is_polymorphic: bool) -> CppTypeProperties {
+    CppTypeProperties {
+        path: CppPath::from_good_str(path),
+        env,
+        size: 16,
+        alignment: 8,
+        is_trivially_copyable: !is_polymorphic,
+        is_polymorphic,
+        is_standard_layout: !is_polymorphic,
+        is_underlying_type_signed: None,
+    }
+}
+
+#[test]
+fn layout_assertions() {
+    let mut host = current_target();
+    host.os = OS::Linux;
+    let env = LibraryTarget {
+        target: host,
+        cpp_library_version: None,
+        enabled_modules: Default::default(),
+        flag_set: None,
+    };
+    let properties = type_properties("Point", env, false);
+    let code = layout_assertions_code("Point", &[&properties], &Condition::True);
+    assert!(code.contains("const _: [(); 16] = [(); ::std::mem::size_of::<Point>()];"));
+    assert!(code.contains("const _: [(); 8] = [(); ::std::mem::align_of::<Point>()];"));
+    assert_eq!(code.matches("target_os = \"linux\"").count(), 2);
+
+    let code = layout_assertions_code(
+        "Point",
+        &[&properties],
+        &Condition::Feature("geometry".to_string()),
+    );
+    assert_eq!(code.matches("feature = \"geometry\"").count(), 2);
+    assert!(layout_assertions_code("Point", &[], &Condition::True).is_empty());
+}
+
+#[test]
+fn polymorphic_classes_of_env() {
+    let mut db = empty_db_client();
+    let mut host = current_target();
+    host.os = OS::Linux;
+    let env = |target: Target| LibraryTarget {
+        target,
+        cpp_library_version: None,
+        enabled_modules: Default::default(),
+        flag_set: None,
+    };
+    let linux = env(host);
+    let emscripten = env(Target::emscripten());
+    db.set_cpp_type_properties(type_properties("Shape", linux.clone(), true));
+    db.set_cpp_type_properties(type_properties("Handle", emscripten.clone(), true));
+    db.set_cpp_type_properties(type_properties("Handle", linux.clone(), false));
+
+    let classes = polymorphic_classes(&db, &linux);
+    assert!(classes.contains(&CppPath::from_good_str("Shape")));
+    assert!(!classes.contains(&CppPath::from_good_str("Handle")));
+    let classes = polymorphic_classes(&db, &emscripten);
+    assert!(!classes.contains(&CppPath::from_good_str("Shape")));
+    assert!(classes.contains(&CppPath::from_good_str("Handle")));
+}
+
+#[test]
+fn enum_to_int() {
+    let code = enum_to_int_code("Flag", None);
+    assert!(code.contains("impl From<Flag> for ::std::os::raw::c_int"));
+    assert!(code.contains("value.to_int()\n"));
+    assert_eq!(code.matches("impl From").count(), 1);
+
+    // unsigned enums are still convertible to `c_int`
+    let code = enum_to_int_code("WindowType", Some("u32"));
+    assert!(code.contains("impl From<WindowType> for ::std::os::raw::c_int"));
+    assert!(code.contains("value.to_int() as ::std::os::raw::c_int"));
+    assert!(code.contains("impl From<WindowType> for u32"));
+    assert_eq!(code.matches("impl From").count(), 2);
+}
@@ -0,0 +1,433 @@
+use crate::config::HandleType;
+use crate::cpp_data::{CppItem, CppPath};
+use crate::cpp_ffi_data::{
+    CppFfiArgumentMeaning, CppFfiFunction, CppFfiFunctionArgument, CppFfiFunctionKind, CppFfiType,
+    CppToFfiTypeConversion,
+};
+use crate::cpp_function::{CppFunctionArgument, ReturnValueAllocationPlace};
+use crate::cpp_type::{
+    CppBuiltInNumericType, CppSpecificNumericType, CppSpecificNumericTypeKind, CppType,
+};
+use crate::rust_generator::{
+    args_tuples_overlap, assign_overload_numbers, handle_numeric_types, is_borrowed_return,
+    is_constness_overload_group, overload_keys, raw_function_path, slice_arguments_function,
+    transfers_ownership,
+};
+use crate::rust_info::{
+    RustFunctionArgument, RustFunctionKind, RustHandleType, RustItem, RustModule, RustModuleKind,
+    RustSpecialModuleKind, RustStruct, RustStructKind, UnnamedRustFunction,
+};
+use crate::rust_type::{
+    RustCommonType, RustFinalType, RustPath, RustToFfiTypeConversion, RustType,
+};
+use crate::tests::cpp_method::empty_regular_method;
+use crate::tests::empty_db_client;
+
+#[test]
+fn overload_numbers_are_stable() {
+    let mut numbers = Vec::new();
+    let keys = overload_keys(vec!["void f()", "void f(int)"].into_iter());
+    assert_eq!(assign_overload_numbers(&mut numbers, &keys), vec![0, 1]);
+
+    // a new overload doesn't change numbers of the existing overloads
+    let keys = overload_keys(vec!["void f()", "void f(bool)", "void f(int)"].into_iter());
+    assert_eq!(assign_overload_numbers(&mut numbers, &keys), vec![0, 2, 1]);
+
+    // numbers of removed overloads are not reused
+    let keys = overload_keys(vec!["void f(int)", "void f(double)"].into_iter());
+    assert_eq!(assign_overload_numbers(&mut numbers, &keys), vec![1, 3]);
+    assert_eq!(
+        numbers,
+        vec!["void f()", "void f(int)", "void f(bool)", "void f(double)"]
+    );
+}
+
+#[test]
+fn overload_keys_are_unique() {
+    assert_eq!(
+        overload_keys(vec!["void f()", "void f()", "void f(int)", "void f()"].into_iter()),
+        vec!["void f()", "void f() #2", "void f(int)", "void f() #3"]
+    );
+}
+
+#[test]
+fn args_tuples() {
+    let int = RustType::Primitive("i32".into());
+    let double = RustType::Primitive("f64".into());
+    let generic = RustType::ImplTrait(RustCommonType {
+        path: RustPath::from_good_str("cpp_core::CastInto"),
+        generic_arguments: None,
+    });
+    assert!(args_tuples_overlap(&[], &[]));
+    assert!(args_tuples_overlap(&[int.clone()], &[int.clone()]));
+    assert!(!args_tuples_overlap(&[int.clone()], &[double.clone()]));
+    assert!(!args_tuples_overlap(
+        &[int.clone()],
+        &[int.clone(), int.clone()]
+    ));
+    assert!(args_tuples_overlap(&[int.clone()], &[generic.clone()]));
+    assert!(!args_tuples_overlap(
+        &[generic.clone(), int.clone()],
+        &[generic, double]
+    ));
+}
+
+#[test]
+fn slice_arguments_after_library_handle() {
+    let cpp_argument = |name: &str, cpp_type: CppType, index: usize| CppFfiFunctionArgument {
+        name: name.into(),
+        argument_type: CppFfiType::new(cpp_type, CppToFfiTypeConversion::NoChange).unwrap(),
+        meaning: CppFfiArgumentMeaning::Argument(index),
+    };
+    let int = CppType::BuiltInNumeric(CppBuiltInNumericType::Int);
+    let function = CppFfiFunction {
+        arguments: vec![
+            cpp_argument("data", CppType::new_pointer(true, int.clone()), 0),
+            cpp_argument("len", int, 1),
+        ],
+        return_type: CppFfiType::void(),
+        allocation_place: ReturnValueAllocationPlace::NotApplicable,
+        path: CppPath::from_good_str("ffi_create"),
+        kind: CppFfiFunctionKind::Function,
+        calling_convention: Default::default(),
+        out_of_line: false,
+        main_thread_only: false,
+    };
+    let handle = RustFunctionArgument {
+        argument_type: RustFinalType::new(
+            RustType::unit(),
+            RustToFfiTypeConversion::LibraryHandle {
+                api_type: RustType::new_reference(
+                    true,
+                    RustType::Common(RustCommonType {
+                        path: RustPath::from_good_str("foo::LibraryHandle"),
+                        generic_arguments: None,
+                    }),
+                ),
+            },
+        )
+        .unwrap(),
+        name: "_library".into(),
+        ffi_index: None,
+    };
+    let rust_argument = |name: &str, rust_type: RustType, ffi_index: usize| RustFunctionArgument {
+        argument_type: RustFinalType::new(rust_type, RustToFfiTypeConversion::None).unwrap(),
+        name: name.into(),
+        ffi_index: Some(ffi_index),
+    };
+    let wrapper = UnnamedRustFunction {
+        is_public: true,
+        is_unsafe: true,
+        kind: RustFunctionKind::FfiFunction,
+        arguments: vec![
+            handle.clone(),
+            rust_argument(
+                "data",
+                RustType::new_pointer(true, RustType::Primitive("i32".into())),
+                0,
+            ),
+            rust_argument("len", RustType::Primitive("usize".into()), 1),
+        ],
+        return_type: RustFinalType::new(RustType::unit(), RustToFfiTypeConversion::None).unwrap(),
+    };
+
+    let result = slice_arguments_function(&function, &wrapper)
+        .unwrap()
+        .unwrap();
+    assert_eq!(result.arguments.len(), 2);
+    assert_eq!(result.arguments[0], handle);
+    assert_eq!(result.arguments[1].ffi_index, Some(0));
+    assert_eq!(
+        result.arguments[1].argument_type.conversion(),
+        &RustToFfiTypeConversion::SliceToPtr { len_ffi_index: 1 }
+    );
+}
+
+#[test]
+fn slice_arguments() {
+    let cpp_argument = |name: &str, cpp_type: CppType, index: usize| CppFfiFunctionArgument {
+        name: name.into(),
+        argument_type: CppFfiType::new(cpp_type, CppToFfiTypeConversion::NoChange).unwrap(),
+        meaning: CppFfiArgumentMeaning::Argument(index),
+    };
+    let double = CppType::BuiltInNumeric(CppBuiltInNumericType::Double);
+    let size_t = CppType::BuiltInNumeric(CppBuiltInNumericType::ULong);
+    let function = CppFfiFunction {
+        arguments: vec![
+            cpp_argument("output", CppType::new_pointer(false, double.clone()), 0),
+            cpp_argument("len", size_t.clone(), 1),
+            cpp_argument("scale", double, 2),
+        ],
+        return_type: CppFfiType::void(),
+        allocation_place: ReturnValueAllocationPlace::NotApplicable,
+        path: CppPath::from_good_str("ffi_fill"),
+        kind: CppFfiFunctionKind::Function,
+        calling_convention: Default::default(),
+        out_of_line: false,
+        main_thread_only: false,
+    };
+    let rust_argument = |name: &str, rust_type: RustType, ffi_index: usize| RustFunctionArgument {
+        argument_type: RustFinalType::new(rust_type, RustToFfiTypeConversion::None).unwrap(),
+        name: name.into(),
+        ffi_index: Some(ffi_index),
+    };
+    let f64_type = RustType::Primitive("f64".into());
+    let mut wrapper = UnnamedRustFunction {
+        is_public: true,
+        is_unsafe: true,
+        kind: RustFunctionKind::FfiFunction,
+        arguments: vec![
+            rust_argument("output", RustType::new_pointer(false, f64_type.clone()), 0),
+            rust_argument("len", RustType::Primitive("usize".into()), 1),
+            rust_argument("scale", f64_type.clone(), 2),
+        ],
+        return_type: RustFinalType::new(RustType::unit(), RustToFfiTypeConversion::None).unwrap(),
+    };
+
+    let result = slice_arguments_function(&function, &wrapper)
+        .unwrap()
+        .unwrap();
+    assert_eq!(result.arguments.len(), 2);
+    assert_eq!(
+        result.arguments[0].argument_type.api_type(),
+        &RustType::new_reference(
+            false,
+            RustType::Slice {
+                element_type: Box::new(f64_type.clone()),
+            }
+        )
+    );
+    assert_eq!(result.arguments[1].name, "scale");
+
+    // the length must directly follow the pointer
+    wrapper.arguments.swap(1, 2);
+    assert!(slice_arguments_function(&function, &wrapper)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn constness_overloads() {
+    let class_type = RustType::Common(RustCommonType {
+        path: RustPath::from_good_str("foo::Buffer"),
+        generic_arguments: None,
+    });
+    let function = |is_const: bool, arg_type: Option<&str>| {
+        let mut arguments = vec![RustFunctionArgument {
+            argument_type: RustFinalType::new(
+                RustType::new_pointer(is_const, class_type.clone()),
+                RustToFfiTypeConversion::RefToPtr { lifetime: None },
+            )
+            .unwrap(),
+            name: "self".into(),
+            ffi_index: Some(0),
+        }];
+        if let Some(arg_type) = arg_type {
+            arguments.push(RustFunctionArgument {
+                argument_type: RustFinalType::new(
+                    RustType::Primitive(arg_type.into()),
+                    RustToFfiTypeConversion::None,
+                )
+                .unwrap(),
+                name: "index".into(),
+                ffi_index: Some(1),
+            });
+        }
+        UnnamedRustFunction {
+            is_public: true,
+            is_unsafe: true,
+            kind: RustFunctionKind::FfiFunction,
+            arguments,
+            return_type: RustFinalType::new(RustType::unit(), RustToFfiTypeConversion::None)
+                .unwrap(),
+        }
+    };
+    let get = function(true, None);
+    let get_mut = function(false, None);
+    let at_i32 = function(true, Some("i32"));
+    let at_i32_mut = function(false, Some("i32"));
+    let at_usize = function(true, Some("usize"));
+    let at_usize_mut = function(false, Some("usize"));
+
+    assert!(is_constness_overload_group(&[&get, &get_mut]).unwrap());
+    assert!(
+        is_constness_overload_group(&[&at_i32, &at_usize_mut, &at_usize, &at_i32_mut]).unwrap()
+    );
+    assert!(!is_constness_overload_group(&[&get]).unwrap());
+    assert!(!is_constness_overload_group(&[&get, &at_i32_mut]).unwrap());
+    assert!(!is_constness_overload_group(&[&at_i32, &at_i32_mut, &at_usize]).unwrap());
+    assert!(!is_constness_overload_group(&[&at_i32, &at_usize, &at_i32_mut, &get]).unwrap());
+}
+
+#[test]
+fn raw_function_paths() {
+    let unsafe_module = RustPath::from_good_str("foo::unsafe_");
+    assert_eq!(
+        raw_function_path(
+            &unsafe_module,
+            &RustPath::from_good_str("foo::ns::Buffer::write")
+        ),
+        RustPath::from_good_str("foo::unsafe_::ns_buffer_write")
+    );
+    assert_eq!(
+        raw_function_path(&unsafe_module, &RustPath::from_good_str("foo::fill")),
+        RustPath::from_good_str("foo::unsafe_::fill")
+    );
+}
+
+#[test]
+fn handle_types() {
+    let handle = |name: &str, bits| CppSpecificNumericType {
+        path: CppPath::from_good_str(name),
+        bits,
+        kind: CppSpecificNumericTypeKind::Integer { is_signed: false },
+    };
+    let mut function = empty_regular_method();
+    function.return_type = CppType::SpecificNumeric(handle("buffer_id", 32));
+    function.arguments.push(CppFunctionArgument {
+        name: "ids".into(),
+        argument_type: CppType::new_pointer(false, CppType::SpecificNumeric(handle("mesh_id", 64))),
+        has_default_value: false,
+    });
+    function.arguments.push(CppFunctionArgument {
+        name: "size".into(),
+        argument_type: CppType::SpecificNumeric(handle("size_type", 64)),
+        has_default_value: false,
+    });
+    let handle_types = [
+        ("buffer_id", "BufferId"),
+        ("mesh_id", "MeshId"),
+        ("texture_id", "TextureId"),
+    ]
+    .iter()
+    .map(|&(typedef, rust_name)| HandleType {
+        typedef: CppPath::from_good_str(typedef),
+        rust_name: rust_name.into(),
+        release: None,
+    })
+    .collect::<Vec<_>>();
+    let items = [CppItem::Function(function)];
+    let numeric_types = handle_numeric_types(items.iter(), &handle_types);
+    assert_eq!(numeric_types.len(), 2);
+    assert_eq!(
+        numeric_types[&CppPath::from_good_str("buffer_id")],
+        handle("buffer_id", 32)
+    );
+    assert_eq!(
+        numeric_types[&CppPath::from_good_str("mesh_id")],
+        handle("mesh_id", 64)
+    );
+
+    let mut db = empty_db_client();
+    db.add_rust_item(
+        None,
+        RustItem::Module(RustModule {
+            is_public: true,
+            path: RustPath::from_good_str("foo"),
+            kind: RustModuleKind::Special(RustSpecialModuleKind::CrateRoot),
+        }),
+    )
+    .unwrap();
+    for &(typedef, rust_name) in &[("buffer_id", "BufferId"), ("mesh_id", "MeshId")] {
+        let item = RustItem::Struct(RustStruct {
+            path: RustPath::from_parts(vec!["foo".into(), rust_name.into()]),
+            kind: RustStructKind::HandleType(RustHandleType {
+                cpp_path: CppPath::from_good_str(typedef),
+                raw_type: "u32".into(),
+            }),
+            is_public: true,
+            qt_receiver_data: None,
+        });
+        // handle types without a source item must not be merged
+        assert!(db.add_rust_item(None, item).unwrap().is_some());
+    }
+    let mesh_id = db
+        .find_handle_type(&CppPath::from_good_str("mesh_id"))
+        .unwrap();
+    assert_eq!(mesh_id.item.path.full_name(None), "foo::MeshId");
+    assert!(db
+        .find_handle_type(&CppPath::from_good_str("texture_id"))
+        .is_none());
+}
+
+#[test]
+fn borrowed_returns() {
+    let class_type = CppType::Class(CppPath::from_good_str("Buffer"));
+    let int = CppType::BuiltInNumeric(CppBuiltInNumericType::Int);
+    let function = |is_const_method: bool, return_type: CppType, args: Vec<CppType>| {
+        let mut arguments = vec![CppFfiFunctionArgument {
+            name: "this_ptr".into(),
+            argument_type: CppFfiType::new(
+                CppType::new_pointer(is_const_method, class_type.clone()),
+                CppToFfiTypeConversion::NoChange,
+            )
+            .unwrap(),
+            meaning: CppFfiArgumentMeaning::This,
+        }];
+        for (index, arg) in args.into_iter().enumerate() {
+            arguments.push(CppFfiFunctionArgument {
+                name: format!("arg{}", index),
+                argument_type: CppFfiType::new(arg, CppToFfiTypeConversion::NoChange).unwrap(),
+                meaning: CppFfiArgumentMeaning::Argument(index),
+            });
+        }
+        CppFfiFunction {
+            arguments,
+            return_type: CppFfiType::new(return_type, CppToFfiTypeConversion::ReferenceToPointer)
+                .unwrap(),
+            allocation_place: ReturnValueAllocationPlace::NotApplicable,
+            path: CppPath::from_good_str("ffi_at"),
+            kind: CppFfiFunctionKind::Function,
+            calling_convention: Default::default(),
+            out_of_line: false,
+            main_thread_only: false,
+        }
+    };
+    let mut method = empty_regular_method();
+    method.path = CppPath::from_good_str("Buffer::at");
+    let cpp_item = CppItem::Function(method);
+
+    let const_ref = CppType::new_reference(true, int.clone());
+    let mut_ref = CppType::new_reference(false, int.clone());
+    assert!(
+        is_borrowed_return(&cpp_item, &function(true, const_ref.clone(), vec![]), None).unwrap()
+    );
+    assert!(
+        is_borrowed_return(&cpp_item, &function(false, mut_ref.clone(), vec![]), None).unwrap()
+    );
+    // `&mut T` can't be borrowed from `&self`
+    assert!(
+        !is_borrowed_return(&cpp_item, &function(true, mut_ref.clone(), vec![]), None).unwrap()
+    );
+    assert!(!is_borrowed_return(&cpp_item, &function(true, mut_ref, vec![]), Some(true)).unwrap());
+    // the reference may point into the other argument
+    let with_pointer = function(
+        true,
+        const_ref.clone(),
+        vec![CppType::new_pointer(true, int.clone())],
+    );
+    assert!(!is_borrowed_return(&cpp_item, &with_pointer, None).unwrap());
+    assert!(is_borrowed_return(&cpp_item, &with_pointer, Some(true)).unwrap());
+    let with_int = function(true, const_ref.clone(), vec![int]);
+    assert!(is_borrowed_return(&cpp_item, &with_int, None).unwrap());
+    assert!(!is_borrowed_return(&cpp_item, &with_int, Some(false)).unwrap());
+}
+
+#[test]
+fn ownership_transfer() {
+    let function = |name: &str, return_type: CppType| {
+        let mut function = empty_regular_method();
+        function.path = CppPath::from_good_str(&format!("Layout::{}", name));
+        function.return_type = return_type;
+        function
+    };
+    let item = CppType::new_pointer(false, CppType::Class(CppPath::from_good_str("Item")));
+    assert!(transfers_ownership(&function("takeAt", item.clone())));
+    assert!(transfers_ownership(&function("release", item.clone())));
+    assert!(!transfers_ownership(&function("takeover", item.clone())));
+    assert!(!transfers_ownership(&function("itemAt", item)));
+    assert!(!transfers_ownership(&function(
+        "takeAt",
+        CppType::BuiltInNumeric(CppBuiltInNumericType::Int)
+    )));
+}
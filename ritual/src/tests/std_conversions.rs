@@ -0,0 +1,216 @@
+use crate::cpp_checks::{Condition, CppChecksItem};
+use crate::cpp_data::{CppItem, CppPath, CppPathItem, CppTypeDeclaration, CppTypeDeclarationKind};
+use crate::cpp_ffi_data::CppFfiItem;
+use crate::cpp_ffi_generator::{to_ffi_method, FfiNameProvider, NewFfiFunctionKind};
+use crate::cpp_function::CppFunctionKind;
+use crate::cpp_type::{CppBuiltInNumericType, CppType};
+use crate::database::DatabaseClient;
+use crate::rust_info::{
+    RustItem, RustModule, RustModuleKind, RustSpecialModuleKind, RustStruct, RustStructKind,
+    RustWrapperTypeKind,
+};
+use crate::rust_type::RustPath;
+use crate::std_conversions::{cpp_helpers, find_std_conversions, rust_impls, StdConversionKind};
+use crate::tests::cpp_method::{empty_membership, empty_regular_method};
+use crate::tests::empty_db_client;
+use ritual_common::target::{current_target, LibraryTarget, Target, OS};
+use std::collections::HashMap;
+
+/// Creates a database containing the class at `path` and its wrapper
+/// at `foo::{rust_name}`. The destructor is available on targets accepted
+/// by `is_available`.
+fn database(
+    path: CppPath,
+    rust_name: &str,
+    is_available: impl Fn(&Target) -> bool,
+) -> DatabaseClient {
+    let mut db = empty_db_client();
+    let type_id = db
+        .add_cpp_item_without_hook(
+            None,
+            CppItem::Type(CppTypeDeclaration {
+                path: path.clone(),
+                kind: CppTypeDeclarationKind::Class,
+                specialization: None,
+                is_trivially_copyable: false,
+                is_immovable: false,
+            }),
+        )
+        .unwrap()
+        .unwrap();
+
+    let mut destructor = empty_regular_method();
+    let destructor_name = format!("~{}", path.last().name);
+    destructor.path = path.join(CppPathItem::from_good_str(&destructor_name));
+    let mut member = empty_membership();
+    member.kind = CppFunctionKind::Destructor;
+    destructor.member = Some(member);
+    let destructor_id = db
+        .add_cpp_item_without_hook(None, CppItem::Function(destructor.clone()))
+        .unwrap()
+        .unwrap();
+    let kind = NewFfiFunctionKind::Function {
+        cpp_function: destructor,
+    };
+    let function = to_ffi_method(kind, &HashMap::new(), &mut FfiNameProvider::testing()).unwrap();
+    let ffi_id = db
+        .add_ffi_item(Some(destructor_id), CppFfiItem::Function(function))
+        .unwrap()
+        .unwrap();
+
+    let mut host = current_target();
+    host.os = OS::Linux;
+    for target in vec![host, Target::emscripten()] {
+        let env = LibraryTarget {
+            target,
+            cpp_library_version: None,
+            enabled_modules: Default::default(),
+            flag_set: None,
+        };
+        db.add_environment(env.clone());
+        let item = CppChecksItem {
+            is_success: is_available(&env.target),
+            env,
+        };
+        db.add_cpp_checks_item(ffi_id.clone(), item);
+    }
+
+    let crate_root = RustModule {
+        is_public: true,
+        path: RustPath::from_good_str("foo"),
+        kind: RustModuleKind::Special(RustSpecialModuleKind::CrateRoot),
+    };
+    db.add_rust_item(None, RustItem::Module(crate_root))
+        .unwrap()
+        .unwrap();
+    let wrapper = RustStruct {
+        path: RustPath::from_good_str(&format!("foo::{}", rust_name)),
+        kind: RustStructKind::WrapperType(RustWrapperTypeKind::ImmovableClassWrapper),
+        is_public: true,
+        qt_receiver_data: None,
+    };
+    db.add_rust_item(Some(type_id), RustItem::Struct(wrapper))
+        .unwrap()
+        .unwrap();
+    db
+}
+
+#[test]
+fn filesystem_path() {
+    let db = database(
+        CppPath::from_good_str("std::filesystem::path"),
+        "Path",
+        |target| target.os != OS::Emscripten,
+    );
+    let conversions = find_std_conversions(&db).unwrap();
+    assert_eq!(conversions.len(), 1);
+    let conversion = &conversions[0];
+    assert_eq!(conversion.kind, StdConversionKind::FilesystemPath);
+    assert_eq!(conversion.rust_type, "crate::Path");
+    assert_eq!(
+        conversion.condition,
+        Condition::Not(Box::new(Condition::OS(OS::Emscripten)))
+    );
+
+    let cpp_code = cpp_helpers(conversion).unwrap();
+    assert!(cpp_code.contains("value->u8string()"));
+    assert!(!cpp_code.contains("value->string()"));
+    let rust_code = rust_impls(conversion).unwrap();
+    assert!(rust_code.starts_with("const _: () = {"));
+    assert!(rust_code.contains("impl<'a> From<&'a crate::Path> for ::std::path::PathBuf"));
+    assert!(rust_code.contains("pub fn from_std_path("));
+}
+
+#[test]
+fn system_time_point() {
+    let path = CppPath::from_items(vec![
+        CppPathItem::from_good_str("std"),
+        CppPathItem::from_good_str("chrono"),
+        CppPathItem {
+            name: "time_point".into(),
+            template_arguments: Some(vec![CppType::Class(CppPath::from_good_str(
+                "std::chrono::system_clock",
+            ))]),
+        },
+    ]);
+    let db = database(path, "SystemTimePoint", |_| true);
+    let conversions = find_std_conversions(&db).unwrap();
+    assert_eq!(conversions.len(), 1);
+    assert_eq!(conversions[0].kind, StdConversionKind::SystemTimePoint);
+
+    let rust_code = rust_impls(&conversions[0]).unwrap();
+    assert!(rust_code.contains("type Error = ::cpp_core::TimeConversionError;"));
+    assert!(rust_code.contains(".ok_or(::cpp_core::TimeConversionError::OutOfRange)"));
+}
+
+#[test]
+fn unavailable_destructor() {
+    let db = database(
+        CppPath::from_good_str("std::filesystem::path"),
+        "Path",
+        |_| false,
+    );
+    assert!(find_std_conversions(&db).unwrap().is_empty());
+}
+
+fn basic_string(argument: CppBuiltInNumericType) -> CppPath {
+    CppPath::from_items(vec![
+        CppPathItem::from_good_str("std"),
+        CppPathItem {
+            name: "basic_string".into(),
+            template_arguments: Some(vec![CppType::BuiltInNumeric(argument)]),
+        },
+    ])
+}
+
+#[test]
+fn wide_string() {
+    let db = database(
+        basic_string(CppBuiltInNumericType::WChar),
+        "WString",
+        |_| true,
+    );
+    let conversions = find_std_conversions(&db).unwrap();
+    assert_eq!(conversions.len(), 1);
+    let conversion = &conversions[0];
+    assert_eq!(conversion.kind, StdConversionKind::WideString);
+    assert_eq!(conversion.condition, Condition::True);
+
+    let cpp_code = cpp_helpers(conversion).unwrap();
+    assert!(cpp_code.contains("RITUAL_EXPORT const wchar_t* foo_std_conversion_0_data("));
+    assert!(cpp_code.contains("return new std::basic_string< wchar_t >(data, len);"));
+    let rust_code = rust_impls(conversion).unwrap();
+    assert!(rust_code.contains(
+        "impl<'a> ::std::convert::TryFrom<&'a crate::WString> for ::std::string::String"
+    ));
+    assert!(rust_code.contains("type Error = ::cpp_core::wide_string::FromWideStringError;"));
+    assert!(rust_code.contains("impl<'a> From<&'a crate::WString> for ::std::ffi::OsString"));
+    assert!(rust_code.contains("pub fn from_std_str(text: &str)"));
+    assert!(rust_code.contains("pub fn from_std_os_str("));
+}
+
+#[test]
+fn u16_string() {
+    let db = database(
+        basic_string(CppBuiltInNumericType::Char16),
+        "U16String",
+        |_| true,
+    );
+    let conversions = find_std_conversions(&db).unwrap();
+    assert_eq!(conversions.len(), 1);
+    let conversion = &conversions[0];
+    assert_eq!(conversion.kind, StdConversionKind::U16String);
+
+    let cpp_code = cpp_helpers(conversion).unwrap();
+    assert!(cpp_code.contains("RITUAL_EXPORT const char16_t* foo_std_conversion_0_data("));
+    let rust_code = rust_impls(conversion).unwrap();
+    assert!(rust_code.contains("type Error = ::std::string::FromUtf16Error;"));
+    assert!(!rust_code.contains("OsString"));
+
+    let db = database(
+        basic_string(CppBuiltInNumericType::Char32),
+        "U32String",
+        |_| true,
+    );
+    assert!(find_std_conversions(&db).unwrap().is_empty());
+}
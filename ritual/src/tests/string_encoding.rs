@@ -0,0 +1,99 @@
+use crate::config::StringEncoding;
+use crate::cpp_data::{CppItem, CppPath, CppPathItem, CppTypeDeclaration, CppTypeDeclarationKind};
+use crate::cpp_type::{CppBuiltInNumericType, CppType};
+use crate::rust_info::{
+    RustItem, RustModule, RustModuleKind, RustSpecialModuleKind, RustStruct, RustStructKind,
+    RustWrapperTypeKind,
+};
+use crate::rust_type::RustPath;
+use crate::string_encoding::{cpp_helpers, find_std_string_type, rust_module};
+use crate::tests::empty_db_client;
+
+#[test]
+fn utf8() {
+    let code = rust_module(StringEncoding::Utf8, "foo", None);
+    assert!(code.contains("pub mod string_encoding {"));
+    assert!(code.contains("pub fn decode_bytes(bytes: &[u8]) -> Option<String>"));
+    assert!(code.contains("::std::str::from_utf8(bytes)"));
+    assert!(!code.contains("decode_std_string"));
+    assert_eq!(cpp_helpers(StringEncoding::Utf8, "foo", None).unwrap(), "");
+}
+
+#[test]
+fn latin1() {
+    let code = rust_module(StringEncoding::Latin1, "foo", None);
+    assert!(code.contains("Latin-1"));
+    assert!(code.contains("char::from(byte)"));
+    assert!(!code.contains("extern \"C\""));
+}
+
+#[test]
+fn locale() {
+    let code = rust_module(StringEncoding::Locale, "foo", None);
+    assert!(code.contains("fn foo_string_encoding_encode("));
+    let cpp_code = cpp_helpers(StringEncoding::Locale, "foo", None).unwrap();
+    assert!(cpp_code.contains("RITUAL_EXPORT size_t foo_string_encoding_encode("));
+    assert!(cpp_code.contains("RITUAL_EXPORT size_t foo_string_encoding_decode("));
+    // surrogate pairs are not passed to `wcrtomb`
+    let surrogate_check = cpp_code.find("unit >= 0xD800").unwrap();
+    assert!(surrogate_check < cpp_code.find("std::wcrtomb").unwrap());
+}
+
+#[test]
+fn std_string() {
+    let mut db = empty_db_client();
+    assert!(find_std_string_type(&db).unwrap().is_none());
+
+    let path = CppPath::from_items(vec![
+        CppPathItem::from_good_str("std"),
+        CppPathItem {
+            name: "basic_string".into(),
+            template_arguments: Some(vec![CppType::BuiltInNumeric(CppBuiltInNumericType::Char)]),
+        },
+    ]);
+    let type_id = db
+        .add_cpp_item_without_hook(
+            None,
+            CppItem::Type(CppTypeDeclaration {
+                path: path.clone(),
+                kind: CppTypeDeclarationKind::Class,
+                specialization: None,
+                is_trivially_copyable: false,
+                is_immovable: false,
+            }),
+        )
+        .unwrap()
+        .unwrap();
+    let crate_root = RustModule {
+        is_public: true,
+        path: RustPath::from_good_str("foo"),
+        kind: RustModuleKind::Special(RustSpecialModuleKind::CrateRoot),
+    };
+    db.add_rust_item(None, RustItem::Module(crate_root))
+        .unwrap()
+        .unwrap();
+    let wrapper = RustStruct {
+        path: RustPath::from_good_str("foo::String"),
+        kind: RustStructKind::WrapperType(RustWrapperTypeKind::ImmovableClassWrapper),
+        is_public: true,
+        qt_receiver_data: None,
+    };
+    db.add_rust_item(Some(type_id), RustItem::Struct(wrapper))
+        .unwrap()
+        .unwrap();
+
+    let std_string = find_std_string_type(&db).unwrap().unwrap();
+    assert_eq!(std_string.cpp_type, path);
+    assert_eq!(std_string.rust_type, "crate::String");
+
+    let code = rust_module(StringEncoding::Latin1, "foo", Some(&std_string));
+    assert!(code.contains("pub fn decode_std_string(value: &crate::String) -> Option<String>"));
+    assert!(code.contains("pub fn decode_std_string_lossy(value: &crate::String) -> String"));
+    assert!(code.contains("fn foo_string_encoding_std_string_data("));
+
+    let cpp_code = cpp_helpers(StringEncoding::Latin1, "foo", Some(&std_string)).unwrap();
+    assert!(cpp_code.contains(
+        "RITUAL_EXPORT const char* foo_string_encoding_std_string_data(const std::basic_string< char >* value,"
+    ));
+    assert!(!cpp_code.contains("wcrtomb"));
+}
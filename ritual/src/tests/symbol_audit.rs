@@ -0,0 +1,55 @@
+use crate::symbol_audit::{find_library_files, parse_dumpbin_output, parse_nm_output};
+use ritual_common::file_utils::{create_dir_all, create_file};
+
+#[test]
+fn nm_output() {
+    let output = "
+file1.cpp.o:
+0000000000000000 T ctr_foo_ffi_bar
+                 U _ZN3foo3barEv
+0000000000000010 T ctr_foo_ffi_baz
+0000000000000020 W _ZN3foo3bazEv
+";
+    assert_eq!(
+        parse_nm_output(output),
+        vec!["ctr_foo_ffi_bar", "ctr_foo_ffi_baz", "_ZN3foo3bazEv"]
+    );
+}
+
+#[test]
+fn dumpbin_output() {
+    let output = "
+008 00000000 SECT3  notype ()    External     | ctr_foo_ffi_bar
+009 00000000 UNDEF  notype ()    External     | ?bar@foo@@QEAAXXZ
+00A 00000000 SECT4  notype       Static       | .text$mn
+";
+    assert_eq!(parse_dumpbin_output(output), vec!["ctr_foo_ffi_bar"]);
+}
+
+#[test]
+fn library_files() {
+    let dir = tempdir::TempDir::new("test_symbol_audit_library_files").unwrap();
+    assert!(find_library_files(dir.path(), "foo").unwrap().is_empty());
+
+    let release = dir
+        .path()
+        .join("release/build/foo-0123/out/c_lib_install/lib");
+    let cross = dir
+        .path()
+        .join("x86_64-pc-windows-msvc/debug/build/foo-4567/out/c_lib_install");
+    let other = dir
+        .path()
+        .join("debug/build/bar-89ab/out/c_lib_install/lib");
+    for path in &[&release, &cross, &other] {
+        create_dir_all(path).unwrap();
+    }
+    create_file(release.join("libfoo_c.a")).unwrap();
+    create_file(cross.join("foo_c.lib")).unwrap();
+    create_file(other.join("libbar_c.a")).unwrap();
+
+    let mut files = find_library_files(dir.path(), "foo").unwrap();
+    files.sort();
+    let mut expected = vec![release.join("libfoo_c.a"), cross.join("foo_c.lib")];
+    expected.sort();
+    assert_eq!(files, expected);
+}
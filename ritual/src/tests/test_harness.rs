@@ -0,0 +1,42 @@
+use crate::test_harness::{
+    bundled_library_config, compare_with_golden, EndToEndTest, BUNDLED_LIBRARY_CRATE_NAME,
+};
+use ritual_common::file_utils::{create_dir, create_file};
+use std::io::Write;
+
+#[test]
+fn golden_files() {
+    let dir = tempdir::TempDir::new("test_golden_files").unwrap();
+    let actual = dir.path().join("actual");
+    let golden = dir.path().join("golden");
+    create_dir(&actual).unwrap();
+    create_dir(&golden).unwrap();
+    write!(create_file(actual.join("lib.rs")).unwrap(), "fn f() {{}}").unwrap();
+    assert!(compare_with_golden(&actual, &golden).is_err());
+
+    write!(create_file(golden.join("lib.rs")).unwrap(), "fn f() {{}}").unwrap();
+    compare_with_golden(&actual, &golden).unwrap();
+
+    write!(create_file(golden.join("lib.rs")).unwrap(), "fn g() {{}}").unwrap();
+    assert!(compare_with_golden(&actual, &golden).is_err());
+}
+
+#[test]
+fn bundled_library() {
+    let dir = tempdir::TempDir::new("test_bundled_library").unwrap();
+    let config = bundled_library_config(&dir.path().join("lib")).unwrap();
+    let mut test = EndToEndTest::new(config);
+    test.add_test(
+        "add",
+        "assert_eq!(ritual_test_lib::ritual_test_lib::add(1, 2), 3);",
+    );
+    let workspace_path = dir.path().join("workspace");
+    test.run(&workspace_path).unwrap();
+
+    let lib_path = workspace_path
+        .join("out")
+        .join(BUNDLED_LIBRARY_CRATE_NAME)
+        .join("src")
+        .join("lib.rs");
+    assert!(lib_path.exists());
+}
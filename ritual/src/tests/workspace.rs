@@ -0,0 +1,43 @@
+use crate::workspace::{CleanScope, Workspace};
+use fs2::FileExt;
+use std::fs::File;
+use std::str::FromStr;
+
+#[test]
+fn clean_scope() {
+    assert_eq!(CleanScope::from_str("logs").unwrap(), CleanScope::Logs);
+    assert_eq!(CleanScope::from_str("caches").unwrap(), CleanScope::Caches);
+    assert_eq!(CleanScope::from_str("all").unwrap(), CleanScope::Everything);
+    assert!(CleanScope::from_str("db").is_err());
+}
+
+#[test]
+fn lock_and_clean() {
+    let dir = tempdir::TempDir::new("test_workspace_lock").unwrap();
+    let lock_path = dir.path().join("workspace.lock");
+    {
+        let mut workspace = Workspace::new(dir.path().into()).unwrap();
+        assert!(File::open(&lock_path)
+            .unwrap()
+            .try_lock_exclusive()
+            .is_err());
+        // the current process already holds the lock
+        drop(Workspace::new(dir.path().into()).unwrap());
+        assert!(File::open(&lock_path)
+            .unwrap()
+            .try_lock_exclusive()
+            .is_err());
+
+        let log_file = workspace.log_path().join("log.txt");
+        std::fs::write(&log_file, "test").unwrap();
+        workspace.clean(CleanScope::Caches).unwrap();
+        assert!(log_file.exists());
+        workspace.clean(CleanScope::Logs).unwrap();
+        assert!(!log_file.exists());
+        assert!(workspace.log_path().exists());
+    }
+    // the lock is released when the workspace is dropped
+    let file = File::open(&lock_path).unwrap();
+    file.try_lock_exclusive().unwrap();
+    file.unlock().unwrap();
+}
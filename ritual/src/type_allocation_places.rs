@@ -4,6 +4,7 @@ use crate::config::MovableTypesHookOutput;
 use crate::cpp_data::{CppItem, CppPath};
 use crate::cpp_type::{CppPointerLikeTypeKind, CppType};
 use crate::processor::ProcessorData;
+use itertools::Itertools;
 use log::{info, trace};
 use ritual_common::errors::Result;
 use std::collections::HashMap;
@@ -20,7 +21,11 @@ fn log_results(data_map: &HashMap<CppPath, TypeStats>) {
         trace!("type = {}; stats = {:?}", name.to_cpp_pseudo_code(), stats);
     }
 
-    for (path, stats) in data_map {
+    let data = data_map
+        .iter()
+        .sorted_by_key(|(path, _)| path.to_cpp_pseudo_code())
+        .collect_vec();
+    for (path, stats) in data {
         let suggestion = if stats.virtual_functions.is_empty() {
             if stats.pointer_encounters.is_empty() {
                 if stats.non_pointer_encounters.len() == MAX_ITEMS {
@@ -1,39 +1,186 @@
 use crate::config::{CrateDependency, CrateDependencyKind, CrateDependencySource};
-use crate::database::{DatabaseCache, DatabaseClient, CRATE_DB_FILE_NAME};
+use crate::database::{crate_db_path, Database, DatabaseCache, DatabaseClient, IndexedDatabase};
 use crate::download_db::download_db;
-use log::info;
-use ritual_common::errors::{bail, Result};
+use fs2::FileExt;
+use itertools::Itertools;
+use log::{info, warn};
+use ritual_common::env_var_names;
+use ritual_common::errors::{bail, Error, Result, ResultExt};
 use ritual_common::file_utils::{
-    create_dir_all, load_json, os_string_into_string, read_dir, remove_file, save_json,
-    save_toml_table,
+    create_dir_all, create_file, file_to_string, load_json, os_str_to_str, os_string_into_string,
+    read_dir, remove_dir_all, remove_file, save_json, save_toml_table,
 };
 use ritual_common::utils::MapIfOk;
 use ritual_common::{toml, ReadOnly};
 use serde_derive::{Deserialize, Serialize};
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+
+/// Version of the directory layout of the workspace. It's increased when
+/// the layout changes, and older workspaces are migrated when opened.
+const LAYOUT_VERSION: u32 = 1;
+
+/// Subdirectories of the workspace.
+const DIRECTORIES: &[&str] = &[
+    "tmp",
+    "out",
+    "log",
+    "backup",
+    "db",
+    "external_db",
+    "cache",
+    "lock",
+];
 
 #[derive(Debug, Default, Serialize, Deserialize)]
-pub struct WorkspaceConfig {}
+pub struct WorkspaceConfig {
+    /// Version of the directory layout (see `LAYOUT_VERSION`).
+    /// Workspaces created before versioning have version 0.
+    #[serde(default)]
+    layout_version: u32,
+}
+
+/// Part of the workspace removed by `Workspace::clean`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CleanScope {
+    /// Log files.
+    Logs,
+    /// Caches, temporary files and downloaded databases of dependencies.
+    /// They are recreated when needed.
+    Caches,
+    /// All data, including databases, backups and generated crates.
+    Everything,
+}
+
+impl CleanScope {
+    fn directories(self) -> &'static [&'static str] {
+        match self {
+            CleanScope::Logs => &["log"],
+            CleanScope::Caches => &["tmp", "cache", "external_db"],
+            CleanScope::Everything => DIRECTORIES,
+        }
+    }
+}
+
+impl FromStr for CleanScope {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "logs" => Ok(CleanScope::Logs),
+            "caches" => Ok(CleanScope::Caches),
+            "all" => Ok(CleanScope::Everything),
+            _ => bail!(
+                "invalid clean scope: {} (expected \"logs\", \"caches\" or \"all\")",
+                s
+            ),
+        }
+    }
+}
 
 /// Provides access to data stored in the user's project directory.
 /// The directory contains a subdirectory for each crate the user wants
 /// to process. When running any operations, the data is read from and
 /// saved to the workspace files. Global workspace configuration
 /// can also be set through the `Workspace` object.
+/// Only one process can use the workspace at a time, except for the child
+/// processes started by the process holding the lock.
 #[derive(Debug)]
 pub struct Workspace {
     path: PathBuf,
     config: WorkspaceConfig,
+    /// Locked file if the lock was acquired by this object. The lock is released
+    /// when the file is closed.
+    _lock_file: Option<File>,
 }
 
 fn config_path(path: &Path) -> PathBuf {
     path.join("config.json")
 }
 
+fn lock_file_path(path: &Path) -> PathBuf {
+    path.join("workspace.lock")
+}
+
+/// Returns path to the file containing pid of the process holding the lock.
+/// It's separate from the lock file because the locked file
+/// can't be read by other processes on some platforms.
+fn lock_owner_file_path(path: &Path) -> PathBuf {
+    path.join("workspace.lock.owner")
+}
+
+/// Acquires the lock of the workspace at `path`. The lock is held
+/// until the returned file is closed, so it's released even if the process crashes.
+/// Returns `None` if the lock is already held by the current process
+/// or its parent ritual process.
+fn lock(path: &Path) -> Result<Option<File>> {
+    let lock_path = lock_file_path(path);
+    let owner_path = lock_owner_file_path(path);
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .open(&lock_path)
+        .with_context(|_| format!("failed to open {}", lock_path.display()))?;
+    match file.try_lock_exclusive() {
+        Ok(()) => {
+            let mut owner_file = create_file(&owner_path)?;
+            write!(owner_file, "{}", process::id())
+                .with_context(|_| format!("failed to write {}", owner_path.display()))?;
+            return Ok(Some(file));
+        }
+        Err(err) if err.kind() == fs2::lock_contended_error().kind() => {}
+        Err(err) => bail!("failed to lock {}: {}", lock_path.display(), err),
+    }
+    // The owner file may be missing or outdated if the lock was acquired
+    // but the pid is not written yet. The lock is considered held by another
+    // process in this case.
+    let owner = file_to_string(&owner_path)
+        .ok()
+        .and_then(|text| text.trim().parse::<u32>().ok());
+    let allowed_owners = [
+        Some(process::id().to_string()),
+        env::var(env_var_names::WORKSPACE_LOCK_OWNER).ok(),
+    ];
+    match owner {
+        Some(pid) if allowed_owners.contains(&Some(pid.to_string())) => Ok(None),
+        Some(pid) => bail!("workspace is used by another process (pid {})", pid),
+        None => bail!("workspace is used by another process"),
+    }
+}
+
+/// Returns path to the file locked while files shared by all crates
+/// of the workspace are written.
+fn shared_write_lock_path(path: &Path) -> PathBuf {
+    path.join("workspace.write.lock")
+}
+
 fn database_path(workspace_path: &Path, crate_name: &str) -> PathBuf {
-    workspace_path
-        .join("db")
-        .join(format!("{}.json", crate_name))
+    workspace_path.join("db").join(crate_name)
+}
+
+/// Converts databases saved as single files by older versions
+/// to sharded database directories.
+fn migrate_single_file_databases(workspace_path: &Path) -> Result<()> {
+    for item in read_dir(workspace_path.join("db"))? {
+        let path = item?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let crate_name = os_str_to_str(path.file_stem().unwrap())?.to_string();
+        let dir = database_path(workspace_path, &crate_name);
+        if dir.exists() {
+            continue;
+        }
+        info!("Converting database of {} to sharded format", crate_name);
+        Database::load(&path)?.save_sharded(&dir, None)?;
+        remove_file(&path)?;
+    }
+    Ok(())
 }
 
 impl Workspace {
@@ -41,21 +188,66 @@ impl Workspace {
         if !path.is_dir() {
             bail!("No such directory: {}", path.display());
         }
+        let lock_file = lock(&path)?;
         let config_path = config_path(&path);
-        for &dir in &["tmp", "out", "log", "backup", "db", "external_db"] {
-            create_dir_all(path.join(dir))?;
-        }
-        let w = Workspace {
+        let mut config = if config_path.exists() {
+            load_json(&config_path)?
+        } else {
+            WorkspaceConfig::default()
+        };
+        let mut w = Workspace {
             path,
-            config: if config_path.exists() {
-                load_json(config_path)?
-            } else {
-                WorkspaceConfig::default()
-            },
+            config: WorkspaceConfig::default(),
+            _lock_file: lock_file,
         };
+        if config.layout_version > LAYOUT_VERSION {
+            bail!(
+                "workspace layout version {} is not supported by this version of ritual \
+                 (supported version is {})",
+                config.layout_version,
+                LAYOUT_VERSION
+            );
+        }
+        w.create_directories()?;
+        if config.layout_version < LAYOUT_VERSION {
+            migrate_single_file_databases(&w.path)?;
+            config.layout_version = LAYOUT_VERSION;
+            save_json(&config_path, &config, None)?;
+        }
+        w.config = config;
         Ok(w)
     }
 
+    fn create_directories(&self) -> Result<()> {
+        for &dir in DIRECTORIES {
+            create_dir_all(self.path.join(dir))?;
+        }
+        Ok(())
+    }
+
+    /// Removes the data specified by `scope`.
+    pub fn clean(&mut self, scope: CleanScope) -> Result<()> {
+        let mut cache = DatabaseCache::global().lock().unwrap();
+        for &dir in scope.directories() {
+            let path = self.path.join(dir);
+            if dir == "db" {
+                for item in read_dir(&path)? {
+                    cache.remove_if_exists(item?.path());
+                }
+            }
+            if path.exists() {
+                remove_dir_all(&path)?;
+            }
+        }
+        if scope == CleanScope::Everything {
+            let cargo_toml_path = self.path.join("Cargo.toml");
+            if cargo_toml_path.exists() {
+                remove_file(cargo_toml_path)?;
+            }
+        }
+        self.create_directories()
+    }
+
     pub fn database_path(&self, crate_name: &str) -> PathBuf {
         database_path(&self.path, crate_name)
     }
@@ -68,16 +260,47 @@ impl Workspace {
         self.path.join("tmp")
     }
 
+    /// Returns path to the directory for temporary files of the crate.
+    /// Crates processed in parallel don't share temporary files.
+    pub fn crate_tmp_path(&self, crate_name: &str) -> PathBuf {
+        self.tmp_path().join(crate_name)
+    }
+
     pub fn config(&self) -> &WorkspaceConfig {
         &self.config
     }
 
+    /// Returns path to the directory containing cached clang translation units.
+    pub fn clang_cache_path(&self) -> PathBuf {
+        self.path.join("cache").join("clang")
+    }
+
     pub fn log_path(&self) -> PathBuf {
         self.path.join("log")
     }
 
+    /// Returns path to the directory containing generated crates
+    /// and other generated files.
+    pub fn out_path(&self) -> PathBuf {
+        self.path.join("out")
+    }
+
     pub fn crate_path(&self, crate_name: &str) -> PathBuf {
-        self.path.join("out").join(crate_name)
+        self.out_path().join(crate_name)
+    }
+
+    /// Returns path to the directory containing lockfiles of the crates.
+    pub fn lock_path(&self) -> PathBuf {
+        self.path.join("lock")
+    }
+
+    /// Returns path to the cargo target directory used for building the crates.
+    /// It can be overridden with `RITUAL_WORKSPACE_TARGET_DIR` environment variable.
+    pub fn target_path(&self) -> PathBuf {
+        match env::var(env_var_names::WORKSPACE_TARGET_DIR) {
+            Ok(dir) => PathBuf::from(dir),
+            Err(_) => self.path.join("target"),
+        }
     }
 
     pub fn delete_database_if_exists(&mut self, crate_name: &str) -> Result<()> {
@@ -85,7 +308,7 @@ impl Workspace {
         let mut cache = DatabaseCache::global().lock().unwrap();
         cache.remove_if_exists(&path);
         if path.exists() {
-            remove_file(path)?;
+            remove_dir_all(path)?;
         }
         Ok(())
     }
@@ -105,32 +328,72 @@ impl Workspace {
             allow_load,
             allow_create,
         )?;
-        let dependencies = dependencies
+        let direct_dependencies = dependencies
             .iter()
             .filter(|dep| dep.kind() == CrateDependencyKind::Ritual)
-            .map_if_ok(|dependency| {
+            .collect_vec();
+        let mut pending = direct_dependencies
+            .iter()
+            .map_if_ok(|dependency| -> Result<_> {
                 let path = match dependency.source() {
                     CrateDependencySource::CratesIo { version } => {
                         self.external_db_path(dependency.name(), version)?
                     }
-                    CrateDependencySource::Local { path } => path.join(CRATE_DB_FILE_NAME),
+                    CrateDependencySource::Local { path } => crate_db_path(path),
                     CrateDependencySource::CurrentWorkspace => {
                         self.database_path(dependency.name())
                     }
                 };
-
-                cache.get(path, dependency.name(), true, false)
+                Ok((dependency.name().to_string(), path))
             })?;
-        Ok(DatabaseClient::new(
-            current_database,
-            ReadOnly::new(dependencies),
-        ))
+        pending.reverse();
+
+        // Databases of indirect dependencies are loaded as well
+        // because their types may be used in the API of direct dependencies.
+        let mut loaded = Vec::<IndexedDatabase>::new();
+        while let Some((name, path)) = pending.pop() {
+            if name == crate_name {
+                bail!(
+                    "dependency cycle detected: {} depends on itself",
+                    crate_name
+                );
+            }
+            if loaded.iter().any(|db| db.database().crate_name() == name) {
+                continue;
+            }
+            let db = cache.get(path, &name, true, false)?;
+            for dependency in db.database().dependencies().iter().rev() {
+                if dependency.crate_name == crate_name {
+                    bail!(
+                        "dependency cycle detected: {} depends on {}",
+                        name,
+                        crate_name
+                    );
+                }
+                let workspace_path = self.database_path(&dependency.crate_name);
+                let path = if workspace_path.exists() {
+                    workspace_path
+                } else {
+                    self.external_db_path(&dependency.crate_name, &dependency.crate_version)?
+                };
+                pending.push((dependency.crate_name.clone(), path));
+            }
+            loaded.push(db);
+        }
+
+        let mut client = DatabaseClient::new(current_database, ReadOnly::new(loaded));
+        let direct_names = direct_dependencies
+            .iter()
+            .map(|dep| dep.name())
+            .collect_vec();
+        client.set_direct_dependencies(&direct_names)?;
+        Ok(client)
     }
 
     fn database_backup_path(&self, crate_name: &str) -> PathBuf {
         let date = chrono::Local::now();
         self.path.join("backup").join(format!(
-            "db_{}_{}.json",
+            "db_{}_{}",
             crate_name,
             date.format("%Y-%m-%d_%H-%M-%S")
         ))
@@ -140,19 +403,37 @@ impl Workspace {
         if database.is_modified() {
             info!("Saving data");
             let backup_path = self.database_backup_path(database.crate_name());
-            save_json(
-                database_path(&self.path, database.crate_name()),
-                database.data(),
-                Some(&backup_path),
-            )?;
+            let path = database_path(&self.path, database.crate_name());
+            database.save_sharded(&path, Some(&backup_path))?;
             database.set_saved();
         }
         Ok(())
     }
 
+    /// Runs `f` while holding the lock protecting files shared by all crates
+    /// of the workspace (e.g. `Cargo.toml` of the workspace and downloaded databases).
+    /// Child processes processing crates in parallel wait for each other here.
+    fn with_shared_write_lock<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let lock_path = shared_write_lock_path(&self.path);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(&lock_path)
+            .with_context(|_| format!("failed to open {}", lock_path.display()))?;
+        file.lock_exclusive()
+            .with_context(|_| format!("failed to lock {}", lock_path.display()))?;
+        // the lock is released when the file is closed
+        f()
+    }
+
     pub fn update_cargo_toml(&self) -> Result<()> {
+        self.with_shared_write_lock(|| self.update_cargo_toml_locked())
+    }
+
+    fn update_cargo_toml_locked(&self) -> Result<()> {
         let mut members = Vec::new();
-        for item in read_dir(self.path.join("out"))? {
+        for item in read_dir(self.out_path())? {
             let item = item?;
             let path = item.path().join("Cargo.toml");
             if path.exists() {
@@ -178,9 +459,12 @@ impl Workspace {
         let path = self
             .path
             .join(format!("external_db/{}_{}.json", crate_name, crate_version));
-        if !path.exists() {
-            download_db(crate_name, crate_version, &path)?;
-        }
+        self.with_shared_write_lock(|| {
+            if !path.exists() {
+                download_db(crate_name, crate_version, &path)?;
+            }
+            Ok(())
+        })?;
         Ok(path)
     }
 }
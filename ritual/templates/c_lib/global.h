@@ -17,19 +17,98 @@
 // for exit()
 #include <cstdlib>
 
-#ifdef _WIN32
+// for main thread checks
+#include <thread>
+
+// for union and struct layout checks
+#include <type_traits>
+#include <cstddef>
+
+// for `TARGET_OS_IPHONE` used in conditions of items
+#ifdef __APPLE__
+    #include <TargetConditionals.h>
+#endif
+
+// FFI functions are declared inside `extern "C"` blocks and use
+// the default (cdecl) calling convention unless another convention
+// is configured for the function, so their names are decorated
+// in the same way by GCC, Clang and MSVC and match `extern`
+// declarations on the Rust side.
+#if defined(_WIN32)
     #define RITUAL_EXPORT __declspec(dllexport)
+#elif defined(__GNUC__)
+    #define RITUAL_EXPORT __attribute__((visibility("default")))
 #else
     #define RITUAL_EXPORT
 #endif
 
+#if defined(_MSC_VER)
+    #define RITUAL_NOINLINE __declspec(noinline)
+#elif defined(__GNUC__)
+    #define RITUAL_NOINLINE __attribute__((noinline))
+#else
+    #define RITUAL_NOINLINE
+#endif
+
+// FFI functions with identical implementations share a static thunk.
+// On ELF targets the exported function is an alias of the thunk,
+// so no code is generated for it. Otherwise it forwards the call
+// to the thunk (`return` is also allowed for `void` functions).
+#if defined(__GNUC__) && defined(__ELF__)
+    #define RITUAL_SHARED_THUNK(thunk, ...) __attribute__((alias(#thunk)));
+#else
+    #define RITUAL_SHARED_THUNK(thunk, ...) { return thunk(__VA_ARGS__); }
+#endif
+
+// Calling conventions that only exist on 32-bit x86. Other compilers
+// ignore or reject them, and Rust uses the C calling convention
+// for these ABIs on other architectures.
+#if defined(_M_IX86) && defined(_MSC_VER)
+    #define RITUAL_STDCALL __stdcall
+    #define RITUAL_FASTCALL __fastcall
+#elif defined(__i386__) && defined(__GNUC__)
+    #define RITUAL_STDCALL __attribute__((stdcall))
+    #define RITUAL_FASTCALL __attribute__((fastcall))
+#else
+    #define RITUAL_STDCALL
+    #define RITUAL_FASTCALL
+#endif
+
+// Equivalent of Rust's "system" ABI.
+#if defined(_WIN32)
+    #define RITUAL_SYSTEM_CALL RITUAL_STDCALL
+#else
+    #define RITUAL_SYSTEM_CALL
+#endif
+
 #define ritual_assert(x) \
     if (!(x)) { \
         std::cout << "assertion failed: " << #x << "\n"; \
         exit(1); \
     }
 
+#define ritual_assert_main_thread(function) \
+    if (std::this_thread::get_id() != ritual::main_thread_id()) { \
+        std::cerr << function << " must be called from the main thread\n"; \
+        abort(); \
+    }
+
 namespace ritual {
+    // Returns ID of the thread that loaded the library,
+    // which is assumed to be the main thread.
+    inline std::thread::id main_thread_id() {
+        static const std::thread::id id = std::this_thread::get_id();
+        return id;
+    }
+
+    // Makes sure that `main_thread_id()` is initialized when the library is loaded.
+    static const std::thread::id main_thread_id_init = main_thread_id();
+
+    // Allows to spell array types without declarator syntax,
+    // e.g. `identity<int[4]>*` is a pointer to an array of 4 ints.
+    template<typename T>
+    using identity = T;
+
     // Calls destructor of `T` class. This template function
     // is necessary because it's not possible to use `x->~T()`
     // syntax directly if `T` contains `::`.
@@ -38,6 +117,15 @@ namespace ritual {
         x->~T();
     }
 
+    // Casts a pointer to a virtual base class (or to a base of a virtual base)
+    // to a pointer to a derived class. `static_cast` can't be used for this
+    // because the offset of a virtual base is only known at runtime.
+    // The base class must be polymorphic.
+    template<typename T, typename U>
+    T virtual_base_downcast(U* x) {
+        return dynamic_cast<T>(x);
+    }
+
     template<class T>
     class Callback {
     public:
@@ -0,0 +1,52 @@
+// Small C++ library used by the end-to-end test harness.
+// See `ritual::test_harness`.
+
+#ifndef RITUAL_TEST_LIB_H
+#define RITUAL_TEST_LIB_H
+
+namespace ritual_test_lib {
+
+inline int add(int a, int b) {
+    return a + b;
+}
+
+enum class Color {
+    Red,
+    Green,
+    Blue
+};
+
+inline Color next_color(Color color) {
+    switch (color) {
+    case Color::Red:
+        return Color::Green;
+    case Color::Green:
+        return Color::Blue;
+    default:
+        return Color::Red;
+    }
+}
+
+class Counter {
+public:
+    Counter() : m_value(0) {}
+    explicit Counter(int value) : m_value(value) {}
+    virtual ~Counter() {}
+
+    int value() const { return m_value; }
+    void increment() { ++m_value; }
+    void add(int amount) { m_value += amount; }
+    virtual int step() const { return 1; }
+
+private:
+    int m_value;
+};
+
+class DoubleCounter : public Counter {
+public:
+    int step() const override { return 2; }
+};
+
+} // namespace ritual_test_lib
+
+#endif // RITUAL_TEST_LIB_H
@@ -26,11 +26,16 @@
 
 use log::info;
 pub use ritual_common as common;
+use ritual_common::android;
 use ritual_common::cpp_build_config::{CppBuildConfig, CppBuildPaths, CppLibraryType};
 use ritual_common::cpp_lib_builder::{BuildType, CMakeConfigData, CppLibBuilder};
+use ritual_common::emscripten;
 use ritual_common::errors::{bail, err_msg, FancyUnwrap, Result, ResultExt};
 use ritual_common::file_utils::{create_file, file_to_string, load_json, path_to_str};
-use ritual_common::target::{current_target, LibraryTarget, OS};
+use ritual_common::library_locator;
+use ritual_common::mingw;
+use ritual_common::msvc;
+use ritual_common::target::{build_script_target, LibraryTarget, OS};
 use ritual_common::{env_var_names, BuildScriptData};
 use std::env;
 use std::io::Write;
@@ -59,6 +64,30 @@ fn build_script_data() -> Result<BuildScriptData> {
     load_json(manifest_dir()?.join("build_script_data.json"))
 }
 
+/// Returns true if the version of the Rust compiler building the crate
+/// is at least `major.minor`.
+fn rustc_version_at_least(major: u32, minor: u32) -> bool {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let output = match process::Command::new(rustc).arg("--version").output() {
+        Ok(output) => output,
+        Err(_) => return false,
+    };
+    // e.g. "rustc 1.61.0 (fe5b13d68 2022-05-18)"
+    let text = String::from_utf8_lossy(&output.stdout);
+    let version = text
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or("")
+        .split('.')
+        .take(2)
+        .map(|part| part.parse::<u32>().ok())
+        .collect::<Option<Vec<_>>>();
+    match version.as_ref().map(Vec::as_slice) {
+        Some(&[rustc_major, rustc_minor]) => (rustc_major, rustc_minor) >= (major, minor),
+        _ => false,
+    }
+}
+
 impl Config {
     /// Constructs default configuration state based on
     /// information in the generated `build_script_data.json` file
@@ -111,11 +140,66 @@ impl Config {
 
     /// Same as `run()`, but result of the operation is returned to the caller.
     pub fn try_run(mut self) -> Result<()> {
-        let current_target = LibraryTarget {
-            target: current_target(),
+        let mut current_target = LibraryTarget {
+            target: build_script_target(),
             cpp_library_version: self.current_cpp_library_version.clone(),
+            enabled_modules: Default::default(),
+            flag_set: None,
         };
 
+        self.cpp_build_paths.apply_env();
+        let mut cpp_build_config_data = self
+            .build_script_data
+            .cpp_build_config
+            .eval(&current_target.target)?;
+
+        library_locator::apply_library_searches(
+            &mut cpp_build_config_data,
+            &mut self.cpp_build_paths,
+        )?;
+
+        for module in &self.build_script_data.optional_modules {
+            let feature_var = module.cargo_feature_env_var_name();
+            println!("cargo:rerun-if-env-changed={}", feature_var);
+            let is_available = library_locator::is_module_available(
+                &module.probe,
+                self.cpp_build_paths.include_paths(),
+            )?;
+            if !is_available {
+                if env::var_os(&feature_var).is_some() {
+                    bail!(
+                        "feature \"{}\" is enabled, but the optional module is not available",
+                        module.name
+                    );
+                }
+                continue;
+            }
+            info!("Optional module is available: {}", module.name);
+            println!("cargo:rustc-cfg=feature={:?}", module.name);
+            cpp_build_config_data.add_compiler_flag(format!("-D{}", module.macro_name()));
+            current_target.enabled_modules.insert(module.name.clone());
+        }
+
+        for flag_set in &self.build_script_data.compiler_flag_sets {
+            let feature_var = flag_set.cargo_feature_env_var_name();
+            println!("cargo:rerun-if-env-changed={}", feature_var);
+            if env::var_os(&feature_var).is_none() {
+                continue;
+            }
+            if let Some(other) = &current_target.flag_set {
+                bail!(
+                    "features \"{}\" and \"{}\" select different compiler flag sets \
+                     and can't be enabled at the same time",
+                    other,
+                    flag_set.name
+                );
+            }
+            info!("Using compiler flag set: {}", flag_set.name);
+            cpp_build_config_data.add_compiler_flags(flag_set.flags.iter().map(String::as_str));
+            cpp_build_config_data.add_compiler_flag(format!("-D{}", flag_set.macro_name()));
+            current_target.flag_set = Some(flag_set.name.clone());
+        }
+
         let is_known_target = self
             .build_script_data
             .known_targets
@@ -130,12 +214,11 @@ impl Config {
             // TODO: if the current version or target is unknown,
             //       emit a warning and set the closest known version instead
         }
-
-        self.cpp_build_paths.apply_env();
-        let cpp_build_config_data = self
-            .build_script_data
-            .cpp_build_config
-            .eval(&current_target.target)?;
+        for search in cpp_build_config_data.library_searches() {
+            for var in search.env_var_names() {
+                println!("cargo:rerun-if-env-changed={}", var);
+            }
+        }
 
         let out_dir = out_dir()?;
         let c_lib_install_dir = out_dir.join("c_lib_install");
@@ -143,34 +226,56 @@ impl Config {
         let profile = env::var("PROFILE").with_context(|_| "PROFILE env var is missing")?;
         info!("Building C++ wrapper library");
 
-        let library_type = cpp_build_config_data
+        let mut library_type = cpp_build_config_data
             .library_type()
             .ok_or_else(|| err_msg("library type (shared or static) is not set"))?;
+        if current_target.target.os == OS::Emscripten && library_type == CppLibraryType::Shared {
+            // Emscripten doesn't support linking to shared libraries
+            library_type = CppLibraryType::Static;
+            cpp_build_config_data.set_library_type(library_type);
+        }
 
-        let cmake_config = CMakeConfigData {
-            cpp_build_config_data: &cpp_build_config_data,
-            cpp_build_paths: &self.cpp_build_paths,
-            library_type: Some(library_type),
-            cpp_library_version: self.current_cpp_library_version.clone(),
-        };
+        if let Some(flag) = cpp_build_config_data.msvc_runtime_flag(&current_target.target) {
+            cpp_build_config_data.add_compiler_flag(flag);
+        }
 
-        let cmake_vars = cmake_config.cmake_vars()?.into_iter().collect();
-        CppLibBuilder {
-            cmake_source_dir: manifest_dir.join("c_lib"),
-            build_dir: out_dir.join("c_lib_build"),
-            install_dir: Some(c_lib_install_dir.clone()),
-            num_jobs: env::var("NUM_JOBS").ok().and_then(|x| x.parse().ok()),
-            cmake_vars,
-            build_type: match profile.as_str() {
-                "debug" => BuildType::Debug,
-                "release" => BuildType::Release,
-                _ => bail!("unknown value of PROFILE env var: {}", profile),
-            },
-            capture_output: false,
-            skip_cmake: false,
-            skip_cmake_after_first_run: false,
+        let skip_cpp_build = env::var_os(env_var_names::SKIP_CPP_BUILD).is_some();
+        if skip_cpp_build {
+            info!("Skipping C++ wrapper library build");
+        } else {
+            let cmake_config = CMakeConfigData {
+                cpp_build_config_data: &cpp_build_config_data,
+                cpp_build_paths: &self.cpp_build_paths,
+                library_type: Some(library_type),
+                cpp_library_version: self.current_cpp_library_version.clone(),
+            };
+
+            let mut cmake_vars = cmake_config.cmake_vars()?;
+            cmake_vars.extend(android::cmake_vars_for_target(
+                &current_target.target,
+                cpp_build_config_data.cpp_runtime_type(),
+            )?);
+            cmake_vars.extend(emscripten::cmake_vars_for_target(&current_target.target)?);
+            cmake_vars.extend(mingw::cmake_vars_for_target(&current_target.target)?);
+            CppLibBuilder {
+                cmake_source_dir: manifest_dir.join("c_lib"),
+                build_dir: out_dir.join("c_lib_build"),
+                install_dir: Some(c_lib_install_dir.clone()),
+                num_jobs: env::var("NUM_JOBS").ok().and_then(|x| x.parse().ok()),
+                cmake_vars,
+                target: current_target.target.clone(),
+                env_vars: msvc::vcvars_environment(&current_target.target)?,
+                build_type: match profile.as_str() {
+                    "debug" => BuildType::Debug,
+                    "release" => BuildType::Release,
+                    _ => bail!("unknown value of PROFILE env var: {}", profile),
+                },
+                capture_output: false,
+                skip_cmake: false,
+                skip_cmake_after_first_run: false,
+            }
+            .run()?;
         }
-        .run()?;
         {
             info!("Generating ffi.rs file");
             let mut ffi_file = create_file(out_dir.join("ffi.rs"))?;
@@ -194,20 +299,41 @@ impl Config {
             )?;
         }
 
-        for name in cpp_build_config_data.linked_libs() {
-            println!("cargo:rustc-link-lib={}", name);
+        // link modifiers in `cargo:rustc-link-lib` require Rust 1.61 or later
+        let supports_link_modifiers = rustc_version_at_least(1, 61);
+        if cpp_build_config_data.whole_archive()
+            && cpp_build_config_data.linked_libs_type() == Some(CppLibraryType::Static)
+            && !supports_link_modifiers
+        {
+            println!(
+                "cargo:warning=linking static libraries as whole archives \
+                 requires Rust 1.61 or later; linking them normally"
+            );
         }
-
-        // TODO: make it configurable
-        if crate::common::target::current_os() == OS::MacOS {
-            println!("cargo:rustc-link-lib=c++");
-        } else if crate::common::target::current_env() != crate::common::target::Env::Msvc {
-            println!("cargo:rustc-link-lib=stdc++");
+        for value in
+            cpp_build_config_data.cargo_link_libs(&current_target.target, supports_link_modifiers)
+        {
+            println!("cargo:rustc-link-lib={}", value);
         }
 
         for name in cpp_build_config_data.linked_frameworks() {
             println!("cargo:rustc-link-lib=framework={}", name);
         }
+        let link_args = emscripten::link_args(&current_target.target);
+        if !link_args.is_empty() {
+            // `cargo:rustc-link-arg` requires Rust 1.50 or later
+            if rustc_version_at_least(1, 50) {
+                for arg in link_args {
+                    println!("cargo:rustc-link-arg={}", arg);
+                }
+            } else {
+                println!(
+                    "cargo:warning=passing linker flags requires Rust 1.50 or later; \
+                     add `-C link-args=\"{}\"` to RUSTFLAGS",
+                    link_args.join(" ")
+                );
+            }
+        }
         for path in self.cpp_build_paths.lib_paths() {
             println!("cargo:rustc-link-search=native={}", path_to_str(path)?);
         }
@@ -226,9 +352,18 @@ impl Config {
             env_var_names::LIBRARY_PATH,
             env_var_names::FRAMEWORK_PATH,
             env_var_names::INCLUDE_PATH,
+            env_var_names::VCVARSALL,
+            env_var_names::SKIP_CPP_BUILD,
         ] {
             println!("cargo:rerun-if-env-changed={}", var);
         }
+        for var in android::NDK_ENV_VAR_NAMES
+            .iter()
+            .chain(android::API_LEVEL_ENV_VAR_NAMES)
+            .chain(emscripten::ENV_VAR_NAMES)
+        {
+            println!("cargo:rerun-if-env-changed={}", var);
+        }
         info!("ritual build script finished.");
         Ok(())
     }
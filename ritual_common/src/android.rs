@@ -0,0 +1,264 @@
+//! Helpers for building and parsing C++ code for Android targets
+//! using the Android NDK.
+//!
+//! The NDK is located using `ANDROID_NDK_HOME`, `ANDROID_NDK_ROOT` or `ANDROID_NDK`
+//! environment variables (the first one is also set by `cargo ndk`), or inside
+//! the Android SDK pointed to by `ANDROID_SDK_ROOT` or `ANDROID_HOME`.
+
+use crate::cpp_build_config::CppLibraryType;
+use crate::cpp_lib_builder::CMakeVar;
+use crate::errors::{bail, err_msg, format_err, Result};
+use crate::file_utils::{os_str_to_str, path_to_str, read_dir};
+use crate::target::{Arch, Endian, Env, Family, PointerWidth, Target, OS};
+use crate::utils::MapIfOk;
+use serde_derive::{Deserialize, Serialize};
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Android API level used if it's not specified explicitly.
+pub const DEFAULT_API_LEVEL: u32 = 21;
+
+/// Environment variables that specify path to the NDK, in order of priority.
+pub const NDK_ENV_VAR_NAMES: &[&str] = &["ANDROID_NDK_HOME", "ANDROID_NDK_ROOT", "ANDROID_NDK"];
+
+/// Environment variables that specify Android API level, in order of priority.
+/// `CARGO_NDK_ANDROID_PLATFORM` is set by `cargo ndk`.
+pub const API_LEVEL_ENV_VAR_NAMES: &[&str] = &["CARGO_NDK_ANDROID_PLATFORM", "ANDROID_PLATFORM"];
+
+/// Android ABI supported by the NDK.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AndroidAbi {
+    ArmeabiV7a,
+    Arm64V8a,
+    X86,
+    X86_64,
+}
+
+impl AndroidAbi {
+    /// Returns all ABIs supported by the NDK.
+    pub fn all() -> &'static [AndroidAbi] {
+        &[
+            AndroidAbi::ArmeabiV7a,
+            AndroidAbi::Arm64V8a,
+            AndroidAbi::X86,
+            AndroidAbi::X86_64,
+        ]
+    }
+
+    /// Returns the ABI corresponding to `arch`.
+    pub fn from_arch(arch: Arch) -> Result<Self> {
+        Ok(match arch {
+            Arch::Arm => AndroidAbi::ArmeabiV7a,
+            Arch::AArch64 => AndroidAbi::Arm64V8a,
+            Arch::X86 => AndroidAbi::X86,
+            Arch::X86_64 => AndroidAbi::X86_64,
+            _ => bail!("Android doesn't support {:?} architecture", arch),
+        })
+    }
+
+    /// Returns name of the ABI used by the NDK (e.g. `"arm64-v8a"`).
+    pub fn name(self) -> &'static str {
+        match self {
+            AndroidAbi::ArmeabiV7a => "armeabi-v7a",
+            AndroidAbi::Arm64V8a => "arm64-v8a",
+            AndroidAbi::X86 => "x86",
+            AndroidAbi::X86_64 => "x86_64",
+        }
+    }
+
+    pub fn arch(self) -> Arch {
+        match self {
+            AndroidAbi::ArmeabiV7a => Arch::Arm,
+            AndroidAbi::Arm64V8a => Arch::AArch64,
+            AndroidAbi::X86 => Arch::X86,
+            AndroidAbi::X86_64 => Arch::X86_64,
+        }
+    }
+
+    /// Returns target triple used by clang (without the API level suffix).
+    pub fn clang_triple(self) -> &'static str {
+        match self {
+            AndroidAbi::ArmeabiV7a => "armv7a-linux-androideabi",
+            AndroidAbi::Arm64V8a => "aarch64-linux-android",
+            AndroidAbi::X86 => "i686-linux-android",
+            AndroidAbi::X86_64 => "x86_64-linux-android",
+        }
+    }
+
+    pub fn target(self) -> Target {
+        Target {
+            arch: self.arch(),
+            os: OS::Android,
+            family: Family::Unix,
+            env: Env::None,
+            pointer_width: match self {
+                AndroidAbi::ArmeabiV7a | AndroidAbi::X86 => PointerWidth::P32,
+                AndroidAbi::Arm64V8a | AndroidAbi::X86_64 => PointerWidth::P64,
+            },
+            endian: Endian::Little,
+        }
+    }
+}
+
+/// Returns Android API level specified in the environment
+/// (as `21` or `android-21`) or `DEFAULT_API_LEVEL`.
+pub fn api_level() -> Result<u32> {
+    for name in API_LEVEL_ENV_VAR_NAMES {
+        if let Ok(value) = env::var(name) {
+            let number = value.trim_start_matches("android-");
+            return number
+                .parse()
+                .map_err(|_| format_err!("invalid value of {} env var: {}", name, value));
+        }
+    }
+    Ok(DEFAULT_API_LEVEL)
+}
+
+/// An installation of the Android NDK.
+#[derive(Debug, Clone)]
+pub struct AndroidNdk {
+    path: PathBuf,
+}
+
+impl AndroidNdk {
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if !path.join("build").join("cmake").exists() {
+            bail!("not an Android NDK directory: {}", path.display());
+        }
+        Ok(AndroidNdk { path })
+    }
+
+    /// Finds the NDK using environment variables.
+    pub fn find() -> Result<Self> {
+        for name in NDK_ENV_VAR_NAMES {
+            if let Ok(path) = env::var(name) {
+                return Self::new(path);
+            }
+        }
+        for name in &["ANDROID_SDK_ROOT", "ANDROID_HOME"] {
+            if let Ok(sdk) = env::var(name) {
+                let sdk = PathBuf::from(sdk);
+                if let Some(path) = newest_side_by_side_ndk(&sdk)? {
+                    return Self::new(path);
+                }
+                let bundle = sdk.join("ndk-bundle");
+                if bundle.exists() {
+                    return Self::new(bundle);
+                }
+            }
+        }
+        bail!(
+            "Android NDK not found; set {} env var",
+            NDK_ENV_VAR_NAMES[0]
+        );
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns path to the CMake toolchain file shipped with the NDK.
+    pub fn cmake_toolchain_file(&self) -> PathBuf {
+        self.path
+            .join("build")
+            .join("cmake")
+            .join("android.toolchain.cmake")
+    }
+
+    /// Returns path to the prebuilt LLVM toolchain for the current host.
+    pub fn llvm_toolchain_dir(&self) -> Result<PathBuf> {
+        let host = if cfg!(target_os = "windows") {
+            "windows-x86_64"
+        } else if cfg!(target_os = "macos") {
+            "darwin-x86_64"
+        } else {
+            "linux-x86_64"
+        };
+        let path = self
+            .path
+            .join("toolchains")
+            .join("llvm")
+            .join("prebuilt")
+            .join(host);
+        if !path.exists() {
+            bail!("NDK toolchain not found at {}", path.display());
+        }
+        Ok(path)
+    }
+
+    /// Returns path to the unified sysroot of the NDK.
+    pub fn sysroot(&self) -> Result<PathBuf> {
+        Ok(self.llvm_toolchain_dir()?.join("sysroot"))
+    }
+
+    /// Returns CMake variables that select the NDK toolchain for `abi`
+    /// and the C++ runtime of `runtime_type` (see `stl_name`).
+    pub fn cmake_vars(
+        &self,
+        abi: AndroidAbi,
+        api_level: u32,
+        runtime_type: Option<CppLibraryType>,
+    ) -> Result<Vec<CMakeVar>> {
+        Ok(vec![
+            CMakeVar::new(
+                "CMAKE_TOOLCHAIN_FILE",
+                path_to_str(&self.cmake_toolchain_file())?,
+            ),
+            CMakeVar::new("ANDROID_ABI", abi.name()),
+            CMakeVar::new("ANDROID_PLATFORM", format!("android-{}", api_level)),
+            CMakeVar::new("ANDROID_STL", stl_name(runtime_type)),
+        ])
+    }
+
+    /// Returns arguments for clang C++ parser that make it parse headers
+    /// as if they were compiled for `abi`.
+    pub fn clang_arguments(&self, abi: AndroidAbi, api_level: u32) -> Result<Vec<String>> {
+        Ok(vec![
+            format!("--target={}{}", abi.clang_triple(), api_level),
+            format!("--sysroot={}", path_to_str(&self.sysroot()?)?),
+        ])
+    }
+}
+
+/// Returns the newest NDK installed side by side in the `ndk` directory of the SDK.
+fn newest_side_by_side_ndk(sdk: &Path) -> Result<Option<PathBuf>> {
+    let dir = sdk.join("ndk");
+    if !dir.exists() {
+        return Ok(None);
+    }
+    let mut versions = read_dir(&dir)?.map_if_ok(|entry| -> Result<_> {
+        let entry = entry?;
+        let name = os_str_to_str(&entry.file_name())?.to_string();
+        let version = semver::Version::parse(&name).ok();
+        Ok((version, entry.path()))
+    })?;
+    versions.retain(|(version, _)| version.is_some());
+    versions.sort();
+    Ok(versions.pop().map(|(_, path)| path))
+}
+
+/// Returns name of the NDK C++ runtime (`ANDROID_STL` value) corresponding
+/// to `runtime_type`. The shared runtime is used by default.
+pub fn stl_name(runtime_type: Option<CppLibraryType>) -> &'static str {
+    match runtime_type {
+        Some(CppLibraryType::Static) => "c++_static",
+        Some(CppLibraryType::Shared) | None => "c++_shared",
+    }
+}
+
+/// Returns CMake variables required for building C++ code for `target`
+/// with the C++ runtime of `runtime_type` if it's an Android target,
+/// and an empty list otherwise.
+pub fn cmake_vars_for_target(
+    target: &Target,
+    runtime_type: Option<CppLibraryType>,
+) -> Result<Vec<CMakeVar>> {
+    if target.os != OS::Android {
+        return Ok(Vec::new());
+    }
+    let abi = AndroidAbi::from_arch(target.arch)?;
+    AndroidNdk::find()
+        .map_err(|err| err_msg(format!("can't build for Android: {}", err)))?
+        .cmake_vars(abi, api_level()?, runtime_type)
+}
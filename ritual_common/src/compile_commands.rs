@@ -0,0 +1,305 @@
+//! Reading include paths and macro definitions from a compilation database
+//! (`compile_commands.json`) produced by CMake, Meson, Bear and other build tools.
+//! Flags are selected per header, so macros defined only for some
+//! source files of the library don't affect parsing of unrelated headers.
+
+use crate::cpp_build_config::CppBuildPaths;
+use crate::errors::{bail, Result, ResultExt};
+use crate::file_utils::load_json;
+use itertools::Itertools;
+use log::{debug, trace, warn};
+use serde_derive::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// An entry of the compilation database.
+#[derive(Debug, Clone, Deserialize)]
+struct CompileCommand {
+    /// Working directory of the compilation.
+    directory: PathBuf,
+    /// Main source file of the compilation.
+    file: PathBuf,
+    /// Compiler invocation as a list of arguments.
+    #[serde(default)]
+    arguments: Option<Vec<String>>,
+    /// Compiler invocation as a shell command (used if `arguments` is not present).
+    #[serde(default)]
+    command: Option<String>,
+}
+
+impl CompileCommand {
+    fn arguments(&self) -> Result<Vec<String>> {
+        if let Some(arguments) = &self.arguments {
+            Ok(arguments.clone())
+        } else if let Some(command) = &self.command {
+            Ok(shell_words::split(command)
+                .with_context(|_| format!("failed to parse command: {:?}", command))?)
+        } else {
+            bail!("no command for {}", self.file.display());
+        }
+    }
+
+    fn file_path(&self) -> PathBuf {
+        self.directory.join(&self.file)
+    }
+}
+
+/// Compiler flags relevant for parsing headers, collected from a compilation database.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompileFlags {
+    include_paths: Vec<PathBuf>,
+    framework_paths: Vec<PathBuf>,
+    /// `-isystem`, `-D` and `-U` flags.
+    compiler_flags: Vec<String>,
+}
+
+impl CompileFlags {
+    pub fn include_paths(&self) -> &[PathBuf] {
+        &self.include_paths
+    }
+
+    pub fn framework_paths(&self) -> &[PathBuf] {
+        &self.framework_paths
+    }
+
+    /// Flags that should be passed to the C++ compiler in addition to
+    /// include and framework paths (e.g. `-D` definitions).
+    pub fn compiler_flags(&self) -> &[String] {
+        &self.compiler_flags
+    }
+
+    /// Adds collected include and framework paths to `paths`.
+    pub fn apply_paths(&self, paths: &mut CppBuildPaths) {
+        for path in &self.include_paths {
+            paths.add_include_path(path);
+        }
+        for path in &self.framework_paths {
+            paths.add_framework_path(path);
+        }
+    }
+
+    /// Adds flags from compiler arguments of a single compilation.
+    /// Relative paths are resolved against `directory`.
+    fn add_arguments(&mut self, arguments: &[String], directory: &Path) {
+        let mut arguments = arguments.iter().skip(1);
+        while let Some(argument) = arguments.next() {
+            let mut take_value = |prefix: &str| -> Option<String> {
+                if argument == prefix {
+                    arguments.next().cloned()
+                } else if argument.starts_with(prefix) {
+                    Some(argument[prefix.len()..].to_string())
+                } else {
+                    None
+                }
+            };
+            if let Some(path) = take_value("-I") {
+                add_unique(&mut self.include_paths, directory.join(path));
+            } else if let Some(path) = take_value("-F") {
+                add_unique(&mut self.framework_paths, directory.join(path));
+            } else if let Some(path) = take_value("-isystem") {
+                let path = directory.join(path);
+                add_unique(
+                    &mut self.compiler_flags,
+                    format!("-isystem{}", path.display()),
+                );
+            } else if let Some(definition) = take_value("-D") {
+                if macro_name(&definition).ends_with("_EXPORTS") {
+                    // CMake defines `<target>_EXPORTS` only for building the library itself.
+                    debug!("ignoring library-private definition: {}", definition);
+                } else {
+                    self.set_macro_flag(format!("-D{}", definition));
+                }
+            } else if let Some(name) = take_value("-U") {
+                self.set_macro_flag(format!("-U{}", name));
+            } else {
+                debug!("ignoring compiler argument: {}", argument);
+            }
+        }
+    }
+
+    /// Adds a `-D` or `-U` flag, replacing any previous flag for the same macro,
+    /// so that a later `-U` cancels an earlier `-D` and vice versa.
+    fn set_macro_flag(&mut self, flag: String) {
+        let name = flag_macro_name(&flag).map(String::from);
+        self.compiler_flags
+            .retain(|old| flag_macro_name(old) != name.as_ref().map(String::as_str));
+        self.compiler_flags.push(flag);
+    }
+
+    /// Adds paths and flags of `other` that are not present in `self`.
+    /// If `other` defines or undefines a macro differently, the flag of `self` is kept.
+    fn merge(&mut self, other: &CompileFlags) {
+        for path in &other.include_paths {
+            add_unique(&mut self.include_paths, path.clone());
+        }
+        for path in &other.framework_paths {
+            add_unique(&mut self.framework_paths, path.clone());
+        }
+        for flag in &other.compiler_flags {
+            let name = flag_macro_name(flag);
+            let old = self
+                .compiler_flags
+                .iter()
+                .find(|old| name.is_some() && flag_macro_name(old) == name);
+            match old {
+                Some(old) if old != flag => {
+                    warn!(
+                        "conflicting flags for macro {} in compilation database: \
+                         {:?} and {:?}, using the first one",
+                        name.unwrap_or_default(),
+                        old,
+                        flag
+                    );
+                }
+                Some(_) => {}
+                None => add_unique(&mut self.compiler_flags, flag.clone()),
+            }
+        }
+    }
+
+    /// Returns paths and flags that are present in all of `flags`.
+    fn common(flags: &[&CompileFlags]) -> CompileFlags {
+        fn retain_common<T: PartialEq + Clone>(
+            flags: &[&CompileFlags],
+            field: impl Fn(&CompileFlags) -> &Vec<T>,
+        ) -> Vec<T> {
+            match flags.split_first() {
+                Some((first, others)) => field(*first)
+                    .iter()
+                    .filter(|value| others.iter().all(|other| field(*other).contains(value)))
+                    .cloned()
+                    .collect(),
+                None => Vec::new(),
+            }
+        }
+
+        CompileFlags {
+            include_paths: retain_common(flags, |f| &f.include_paths),
+            framework_paths: retain_common(flags, |f| &f.framework_paths),
+            compiler_flags: retain_common(flags, |f| &f.compiler_flags),
+        }
+    }
+}
+
+fn add_unique<T: PartialEq>(vec: &mut Vec<T>, value: T) {
+    if !vec.contains(&value) {
+        vec.push(value);
+    }
+}
+
+/// Returns the name of the macro in a macro definition (`NAME` or `NAME=VALUE`).
+fn macro_name(definition: &str) -> &str {
+    definition.split('=').next().unwrap_or_default()
+}
+
+/// Returns the name of the macro affected by a `-D` or `-U` flag.
+fn flag_macro_name(flag: &str) -> Option<&str> {
+    if flag.starts_with("-D") || flag.starts_with("-U") {
+        Some(macro_name(&flag[2..]))
+    } else {
+        None
+    }
+}
+
+/// Parses compiler `arguments` of a compilation performed in `directory`.
+/// The first argument is the compiler executable.
+pub fn parse_arguments(arguments: &[String], directory: &Path) -> CompileFlags {
+    let mut flags = CompileFlags::default();
+    flags.add_arguments(arguments, directory);
+    flags
+}
+
+/// Compiler flags of compilations read from a compilation database.
+#[derive(Debug, Clone, Default)]
+pub struct CompilationDatabase {
+    /// Main source files of compilations and their flags.
+    compilations: Vec<(PathBuf, CompileFlags)>,
+}
+
+impl CompilationDatabase {
+    /// Reads the compilation database at `path`. If `source_dir` is specified,
+    /// only compilations of the files in this directory (e.g. the sources of the library)
+    /// are used.
+    pub fn load(path: &Path, source_dir: Option<&Path>) -> Result<Self> {
+        let commands: Vec<CompileCommand> = load_json(path)?;
+        let mut compilations = Vec::new();
+        for command in commands {
+            let file_path = command.file_path();
+            if let Some(source_dir) = source_dir {
+                if !file_path.starts_with(source_dir) {
+                    continue;
+                }
+            }
+            let flags = parse_arguments(&command.arguments()?, &command.directory);
+            compilations.push((file_path, flags));
+        }
+        if compilations.is_empty() {
+            bail!("no matching compilations in {}", path.display());
+        }
+        debug!(
+            "used {} compilations from {}",
+            compilations.len(),
+            path.display()
+        );
+        Ok(CompilationDatabase::from_compilations(compilations))
+    }
+
+    /// Creates a database from main source files of compilations and their flags.
+    pub fn from_compilations(compilations: Vec<(PathBuf, CompileFlags)>) -> Self {
+        CompilationDatabase { compilations }
+    }
+
+    /// Returns include and framework paths of all compilations. They are needed
+    /// to find the headers of the library.
+    pub fn all_paths(&self) -> CompileFlags {
+        let mut output = CompileFlags::default();
+        for (_, flags) in &self.compilations {
+            for path in &flags.include_paths {
+                add_unique(&mut output.include_paths, path.clone());
+            }
+            for path in &flags.framework_paths {
+                add_unique(&mut output.framework_paths, path.clone());
+            }
+        }
+        output
+    }
+
+    /// Returns flags that should be used for parsing `header`.
+    ///
+    /// Headers are not compiled on their own, so flags of the compilation of the
+    /// source file with the same name (e.g. `foo.cpp` for `foo.h`) are used,
+    /// preferring the file in the same directory. If there is no such file,
+    /// only the flags shared by all compilations are used.
+    pub fn header_flags(&self, header: &Path) -> CompileFlags {
+        let stem = header.file_stem();
+        let same_stem = self
+            .compilations
+            .iter()
+            .filter(|(file, _)| file.file_stem() == stem)
+            .collect_vec();
+        let matched = same_stem
+            .iter()
+            .find(|(file, _)| file.parent() == header.parent())
+            .or_else(|| same_stem.first());
+        if let Some((file, flags)) = matched {
+            trace!("using flags of {} for {}", file.display(), header.display());
+            flags.clone()
+        } else {
+            CompileFlags::common(
+                &self
+                    .compilations
+                    .iter()
+                    .map(|(_, flags)| flags)
+                    .collect_vec(),
+            )
+        }
+    }
+
+    /// Returns merged flags for parsing `headers` in a single translation unit.
+    pub fn headers_flags(&self, headers: &[PathBuf]) -> CompileFlags {
+        let mut output = CompileFlags::default();
+        for header in headers {
+            output.merge(&self.header_flags(header));
+        }
+        output
+    }
+}
@@ -2,7 +2,8 @@
 
 use crate::cpp_lib_builder::CMakeVar;
 use crate::errors::{bail, Result};
-use crate::target::{Condition, Target};
+use crate::library_locator::LibrarySearch;
+use crate::target::{Condition, Env, Target, OS};
 use serde_derive::{Deserialize, Serialize};
 
 /// Information required to build the C++ wrapper library
@@ -42,6 +43,97 @@ pub enum CppLibraryType {
     Static,
 }
 
+/// Implementation of the C++ standard library linked to the crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CppRuntime {
+    /// GNU `libstdc++`
+    LibStdCpp,
+    /// LLVM `libc++`
+    LibCpp,
+    /// MSVC runtime. It's linked by the Rust compiler automatically,
+    /// the linkage is selected by `/MT` or `/MD` compiler flag.
+    Msvc,
+    /// Don't link any C++ runtime.
+    None,
+}
+
+/// Version of the C++ standard used to compile the C++ code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum CppStandard {
+    Cpp11,
+    Cpp14,
+    Cpp17,
+    Cpp20,
+}
+
+impl CppStandard {
+    /// Returns the version number used in compiler flags (e.g. `17` for C++17).
+    pub fn version(self) -> u32 {
+        match self {
+            CppStandard::Cpp11 => 11,
+            CppStandard::Cpp14 => 14,
+            CppStandard::Cpp17 => 17,
+            CppStandard::Cpp20 => 20,
+        }
+    }
+
+    /// Returns the standard selected by a compiler flag
+    /// (e.g. `-std=c++17`, `-std=gnu++14` or `/std:c++17`).
+    pub fn from_compiler_flag(flag: &str) -> Option<Self> {
+        let value = if flag.starts_with("-std=") || flag.starts_with("/std:") {
+            &flag[5..]
+        } else {
+            return None;
+        };
+        let version = value.trim_start_matches("gnu++").trim_start_matches("c++");
+        match version {
+            "11" | "0x" => Some(CppStandard::Cpp11),
+            "14" | "1y" => Some(CppStandard::Cpp14),
+            "17" | "1z" => Some(CppStandard::Cpp17),
+            "20" | "2a" | "latest" => Some(CppStandard::Cpp20),
+            _ => None,
+        }
+    }
+
+    /// Returns the standard that introduced the standard library type `name`
+    /// (without the namespace and template arguments), if it's newer than C++11.
+    pub fn required_for_std_type(name: &str) -> Option<Self> {
+        match name {
+            "optional" | "nullopt_t" | "variant" | "monostate" | "any" | "basic_string_view"
+            | "string_view" | "wstring_view" | "u16string_view" | "u32string_view" | "byte" => {
+                Some(CppStandard::Cpp17)
+            }
+            "span" | "u8string" | "u8string_view" | "strong_ordering" | "weak_ordering"
+            | "partial_ordering" => Some(CppStandard::Cpp20),
+            _ => None,
+        }
+    }
+}
+
+impl Default for CppStandard {
+    fn default() -> Self {
+        CppStandard::Cpp11
+    }
+}
+
+impl CppRuntime {
+    /// Returns the runtime normally used on `target`.
+    pub fn default_for(target: &Target) -> Self {
+        if target.env == Env::Msvc {
+            CppRuntime::Msvc
+        } else if target.os == OS::MacOS
+            || target.os == OS::IOS
+            || target.os == OS::FreeBSD
+            || target.os == OS::Android
+            || target.os == OS::Emscripten
+        {
+            CppRuntime::LibCpp
+        } else {
+            CppRuntime::LibStdCpp
+        }
+    }
+}
+
 /// Platform-specific information
 /// required to build the C++ wrapper library.
 /// This type contains one configuration item of `CppBuildConfig`.
@@ -52,6 +144,18 @@ pub struct CppBuildConfigData {
     compiler_flags: Vec<String>,
     library_type: Option<CppLibraryType>,
     cmake_vars: Vec<CMakeVar>,
+    #[serde(default)]
+    library_searches: Vec<LibrarySearch>,
+    #[serde(default)]
+    linked_libs_type: Option<CppLibraryType>,
+    #[serde(default)]
+    whole_archive: bool,
+    #[serde(default)]
+    cpp_runtime: Option<CppRuntime>,
+    #[serde(default)]
+    cpp_runtime_type: Option<CppLibraryType>,
+    #[serde(default)]
+    cpp_standard: Option<CppStandard>,
 }
 
 impl CppBuildConfigData {
@@ -90,12 +194,52 @@ impl CppBuildConfigData {
         self.cmake_vars.push(var);
     }
 
+    /// Adds a library that should be found by the build script
+    /// using `pkg-config` or CMake. Discovered paths, libraries and flags
+    /// are added to the build configuration.
+    pub fn add_library_search(&mut self, search: LibrarySearch) {
+        self.library_searches.push(search);
+    }
+
     /// Sets library type. C++ wrapper is static by default.
     /// Shared library can be used to work around MSVC linker's limitations.
     pub fn set_library_type(&mut self, t: CppLibraryType) {
         self.library_type = Some(t);
     }
 
+    /// Sets linkage of the libraries added with `add_linked_lib`.
+    /// If not set, the linker decides based on available library files.
+    pub fn set_linked_libs_type(&mut self, t: CppLibraryType) {
+        self.linked_libs_type = Some(t);
+    }
+
+    /// If enabled, statically linked libraries are linked as whole archives,
+    /// so that objects not referenced by the wrapper (e.g. static initializers
+    /// of plugins) are kept. The generated `cargo:rustc-link-lib` directives
+    /// use link modifiers which require Rust 1.61 or later. With older compilers,
+    /// the libraries are linked normally and the build script prints a warning.
+    pub fn set_whole_archive(&mut self, value: bool) {
+        self.whole_archive = value;
+    }
+
+    /// Selects the C++ standard library implementation linked to the crate.
+    /// By default, the platform's default implementation is used.
+    pub fn set_cpp_runtime(&mut self, runtime: CppRuntime) {
+        self.cpp_runtime = Some(runtime);
+    }
+
+    /// Selects static or dynamic linkage of the C++ standard library.
+    /// Dynamic linkage is used by default.
+    pub fn set_cpp_runtime_type(&mut self, t: CppLibraryType) {
+        self.cpp_runtime_type = Some(t);
+    }
+
+    /// Selects the C++ standard used to compile the wrapper library.
+    /// If not set, the compiler's default standard is used.
+    pub fn set_cpp_standard(&mut self, standard: CppStandard) {
+        self.cpp_standard = Some(standard);
+    }
+
     /// Returns names of linked libraries.
     pub fn linked_libs(&self) -> &[String] {
         &self.linked_libs
@@ -116,6 +260,97 @@ impl CppBuildConfigData {
         self.library_type
     }
 
+    /// Returns linkage of the libraries added with `add_linked_lib`.
+    pub fn linked_libs_type(&self) -> Option<CppLibraryType> {
+        self.linked_libs_type
+    }
+
+    /// Returns true if statically linked libraries should be linked as whole archives.
+    pub fn whole_archive(&self) -> bool {
+        self.whole_archive
+    }
+
+    /// Returns selected C++ standard library implementation.
+    pub fn cpp_runtime(&self) -> Option<CppRuntime> {
+        self.cpp_runtime
+    }
+
+    /// Returns selected linkage of the C++ standard library.
+    pub fn cpp_runtime_type(&self) -> Option<CppLibraryType> {
+        self.cpp_runtime_type
+    }
+
+    /// Returns selected C++ standard.
+    pub fn cpp_standard(&self) -> Option<CppStandard> {
+        self.cpp_standard
+    }
+
+    /// Returns `cargo:rustc-link-lib` values for the libraries added
+    /// with `add_linked_lib` and the C++ standard library.
+    /// `supports_link_modifiers` specifies whether the Rust compiler
+    /// supports link modifiers (Rust 1.61 or later) required by `whole_archive`.
+    pub fn cargo_link_libs(&self, target: &Target, supports_link_modifiers: bool) -> Vec<String> {
+        let lib_value = |name: &str, library_type: Option<CppLibraryType>| match library_type {
+            Some(CppLibraryType::Static) if self.whole_archive && supports_link_modifiers => {
+                format!("static:+whole-archive={}", name)
+            }
+            Some(CppLibraryType::Static) => format!("static={}", name),
+            Some(CppLibraryType::Shared) => format!("dylib={}", name),
+            None => name.to_string(),
+        };
+
+        let mut result = self
+            .linked_libs
+            .iter()
+            .map(|name| lib_value(name, self.linked_libs_type))
+            .collect::<Vec<_>>();
+
+        let runtime = self
+            .cpp_runtime
+            .unwrap_or_else(|| CppRuntime::default_for(target));
+        let runtime_libs: &[&str] = match runtime {
+            CppRuntime::LibStdCpp => &["stdc++"],
+            // Android NDK ships libc++ under different names
+            CppRuntime::LibCpp if target.os == OS::Android => {
+                if self.cpp_runtime_type == Some(CppLibraryType::Static) {
+                    &["c++_static", "c++abi"]
+                } else {
+                    &["c++_shared"]
+                }
+            }
+            // Emscripten only supports static linking
+            CppRuntime::LibCpp if target.os == OS::Emscripten => &["c++", "c++abi"],
+            CppRuntime::LibCpp if self.cpp_runtime_type == Some(CppLibraryType::Static) => {
+                &["c++", "c++abi"]
+            }
+            CppRuntime::LibCpp => &["c++"],
+            CppRuntime::Msvc | CppRuntime::None => &[],
+        };
+        for name in runtime_libs {
+            // static runtime can't be linked as a whole archive
+            result.push(match self.cpp_runtime_type {
+                Some(CppLibraryType::Static) => format!("static={}", name),
+                _ => name.to_string(),
+            });
+        }
+        result
+    }
+
+    /// Returns MSVC compiler flag selecting CRT linkage, if any.
+    pub fn msvc_runtime_flag(&self, target: &Target) -> Option<&'static str> {
+        let runtime = self
+            .cpp_runtime
+            .unwrap_or_else(|| CppRuntime::default_for(target));
+        if runtime != CppRuntime::Msvc {
+            return None;
+        }
+        match self.cpp_runtime_type {
+            Some(CppLibraryType::Static) => Some("/MT"),
+            Some(CppLibraryType::Shared) => Some("/MD"),
+            None => None,
+        }
+    }
+
     fn add_from(&mut self, other: &CppBuildConfigData) -> Result<()> {
         self.linked_libs.append(&mut other.linked_libs.clone());
         self.linked_frameworks
@@ -129,12 +364,47 @@ impl CppBuildConfigData {
             self.library_type = other.library_type;
         }
         self.cmake_vars.extend_from_slice(&other.cmake_vars);
+        self.library_searches
+            .extend_from_slice(&other.library_searches);
+        merge_option(
+            &mut self.linked_libs_type,
+            other.linked_libs_type,
+            "linked libs types",
+        )?;
+        merge_option(&mut self.cpp_runtime, other.cpp_runtime, "C++ runtimes")?;
+        merge_option(
+            &mut self.cpp_runtime_type,
+            other.cpp_runtime_type,
+            "C++ runtime types",
+        )?;
+        merge_option(&mut self.cpp_standard, other.cpp_standard, "C++ standards")?;
+        self.whole_archive |= other.whole_archive;
         Ok(())
     }
 
     pub fn cmake_vars(&self) -> &[CMakeVar] {
         &self.cmake_vars
     }
+
+    /// Returns libraries added with `add_library_search`.
+    pub fn library_searches(&self) -> &[LibrarySearch] {
+        &self.library_searches
+    }
+}
+
+/// Sets `value` to `other` if `value` is not set yet.
+/// Returns an error if both are set to different values.
+fn merge_option<T: PartialEq>(value: &mut Option<T>, other: Option<T>, name: &str) -> Result<()> {
+    if let Some(other) = other {
+        if let Some(value) = value {
+            if *value != other {
+                bail!("conflicting {} specified", name);
+            }
+        } else {
+            *value = Some(other);
+        }
+    }
+    Ok(())
 }
 
 impl CppBuildConfig {
@@ -159,6 +429,47 @@ impl CppBuildConfig {
     }
 }
 
+/// Named set of extra C++ compiler flags (e.g. `-std=c++17` or `-D` feature toggles).
+/// The C++ checker checks all items with each flag set in addition to the default flags.
+/// Items that are only available with a flag set are gated behind
+/// the cargo feature with the same name. If the feature is enabled,
+/// the build script passes the flags to the compiler.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompilerFlagSet {
+    /// Name of the flag set. Must be a valid cargo feature name.
+    pub name: String,
+    pub flags: Vec<String>,
+}
+
+impl CompilerFlagSet {
+    pub fn new<Item, Iter>(name: impl Into<String>, flags: Iter) -> Self
+    where
+        Item: Into<String>,
+        Iter: IntoIterator<Item = Item>,
+    {
+        CompilerFlagSet {
+            name: name.into(),
+            flags: flags.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Name of the C++ macro defined when compiling the C++ wrapper library
+    /// if the flag set is enabled.
+    pub fn macro_name(&self) -> String {
+        flag_set_macro_name(&self.name)
+    }
+
+    /// Name of the environment variable set by cargo if the feature is enabled.
+    pub fn cargo_feature_env_var_name(&self) -> String {
+        env_var_names::cargo_feature(&self.name)
+    }
+}
+
+/// Returns name of the C++ macro corresponding to the compiler flag set `name`.
+pub fn flag_set_macro_name(name: &str) -> String {
+    format!("RITUAL_FLAG_SET_{}", name.to_uppercase().replace('-', "_"))
+}
+
 use crate::env_var_names;
 use std::path::PathBuf;
 
@@ -2,12 +2,14 @@
 
 use crate::cpp_build_config::{CppBuildConfigData, CppBuildPaths, CppLibraryType};
 use crate::errors::{err_msg, Result};
-use crate::file_utils::{create_dir_all, file_to_string, path_to_str};
+use crate::file_utils::{canonicalize, create_dir_all, file_to_string, path_to_str};
+use crate::target::Target;
 use crate::utils::{run_command, run_command_and_capture_output, CommandOutput, MapIfOk};
 use crate::{env_var_names, target};
 use itertools::Itertools;
 use serde_derive::{Deserialize, Serialize};
 use std::env;
+use std::ffi::OsString;
 use std::fmt::Display;
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -73,6 +75,59 @@ pub enum BuildType {
     Release,
 }
 
+/// Returns the compiler cache tool used for all C++ compilations.
+/// Caching is disabled unless `RITUAL_COMPILER_LAUNCHER` env var is set.
+/// It may contain a name or a path of the tool, or `auto` to use `ccache`
+/// or `sccache` if it's found in `PATH`.
+pub fn compiler_launcher() -> Option<PathBuf> {
+    select_compiler_launcher(
+        env::var_os(env_var_names::COMPILER_LAUNCHER).as_ref(),
+        env::var_os("PATH").as_ref(),
+    )
+}
+
+/// Returns the compiler cache tool selected by the value of
+/// `RITUAL_COMPILER_LAUNCHER` env var (see `compiler_launcher`),
+/// searching in `paths` if the value is `auto`.
+pub fn select_compiler_launcher(
+    value: Option<&OsString>,
+    paths: Option<&OsString>,
+) -> Option<PathBuf> {
+    let value = value?;
+    if value.is_empty() || value == "none" {
+        return None;
+    }
+    if value != "auto" {
+        return Some(PathBuf::from(value));
+    }
+    for name in &["ccache", "sccache"] {
+        for dir in env::split_paths(paths?) {
+            let path = dir.join(format!("{}{}", name, env::consts::EXE_SUFFIX));
+            if path.is_file() {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+/// Returns true if `launcher` is ccache (as opposed to sccache or another tool).
+pub fn is_ccache(launcher: &Path) -> bool {
+    launcher
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .map_or(false, |stem| stem == "ccache")
+}
+
+/// Returns the deepest common ancestor of `a` and `b`.
+pub fn common_ancestor(a: &Path, b: &Path) -> PathBuf {
+    a.components()
+        .zip(b.components())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a)
+        .collect()
+}
+
 /// Implements building a CMake-based C++ library.
 /// Construct a value and call `run()` to execute building.
 #[derive(Debug, Clone)]
@@ -90,6 +145,12 @@ pub struct CppLibBuilder {
     pub build_type: BuildType,
     /// Additional variables passed to CMake
     pub cmake_vars: Vec<CMakeVar>,
+    /// Target the library is built for. Its environment selects
+    /// the CMake generator and build tool on Windows.
+    pub target: Target,
+    /// Additional environment variables set for all executed commands
+    /// (e.g. MSVC environment captured from `vcvarsall.bat`)
+    pub env_vars: Vec<(String, String)>,
     pub capture_output: bool,
     pub skip_cmake: bool,
     pub skip_cmake_after_first_run: bool,
@@ -108,26 +169,58 @@ impl CppLibBuilderOutput {
 }
 
 impl CppLibBuilder {
+    fn command(&self, program: &str) -> Command {
+        let mut command = Command::new(program);
+        command.envs(self.env_vars.iter().map(|(name, value)| (name, value)));
+        command
+    }
+
+    /// Returns environment variables for the compiler cache tool.
+    /// Absolute paths of generated files differ between workspaces and checker
+    /// instances, so ccache is asked to use paths relative to the common ancestor
+    /// of the source and build directories. Otherwise, the same file
+    /// compiled in another directory would never produce a cache hit.
+    fn compiler_cache_env_vars(&self, launcher: &Path) -> Result<Vec<(String, String)>> {
+        let mut vars = Vec::new();
+        if is_ccache(launcher) {
+            let base_dir = common_ancestor(
+                &canonicalize(&self.cmake_source_dir)?,
+                &canonicalize(&self.build_dir)?,
+            );
+            if base_dir.components().count() > 1 {
+                vars.push(("CCACHE_BASEDIR".to_string(), path_to_str(&base_dir)?.into()));
+            }
+            // debug info contains the current directory, which is different
+            // for each build directory
+            vars.push(("CCACHE_NOHASHDIR".to_string(), "1".to_string()));
+        }
+        Ok(vars)
+    }
+
     /// Builds the library.
     pub fn run(&mut self) -> Result<CppLibBuilderOutput> {
         if !self.build_dir.exists() {
             create_dir_all(&self.build_dir)?;
         }
+        let launcher = compiler_launcher();
         if !self.skip_cmake {
-            let mut cmake_command = Command::new("cmake");
+            let mut cmake_command = self.command("cmake");
             cmake_command
                 .arg("-Wno-dev")
                 .arg(&self.cmake_source_dir)
                 .current_dir(&self.build_dir);
-            let actual_build_type = if target::current_env() == target::Env::Msvc {
+            let actual_build_type = if self.target.env == target::Env::Msvc {
                 // Rust always links to release version of MSVC runtime, so
                 // link will fail if C library is built in debug mode
                 BuildType::Release
             } else {
                 self.build_type.clone()
             };
-            if target::current_os() == target::OS::Windows {
-                match target::current_env() {
+            // cross-compilers for Windows targets use the default generator
+            // of the host
+            if self.target.os == target::OS::Windows && target::current_os() == target::OS::Windows
+            {
+                match self.target.env {
                     target::Env::Msvc => {
                         cmake_command.arg("-G").arg("NMake Makefiles");
                     }
@@ -151,6 +244,11 @@ impl CppLibBuilder {
                     path_to_str(install_dir)?,
                 ));
             }
+            if let Some(launcher) = &launcher {
+                for name in &["CMAKE_C_COMPILER_LAUNCHER", "CMAKE_CXX_COMPILER_LAUNCHER"] {
+                    actual_cmake_vars.push(CMakeVar::new(*name, path_to_str(launcher)?));
+                }
+            }
 
             for var in actual_cmake_vars {
                 cmake_command.arg(format!("-D{}={}", var.name, var.value));
@@ -173,10 +271,10 @@ impl CppLibBuilder {
             self.skip_cmake = true;
         }
 
-        if target::current_env() == target::Env::Msvc && self.capture_output {
+        if self.target.env == target::Env::Msvc && self.capture_output {
             let path = self.build_dir.join("nmake_output.txt");
             run_command(
-                Command::new("cmd")
+                self.command("cmd")
                     .arg("/C")
                     .arg(format!(
                         "cmake --build . -- clean > {} 2>&1",
@@ -186,7 +284,7 @@ impl CppLibBuilder {
             )?;
         } else {
             run_command(
-                Command::new("cmake")
+                self.command("cmake")
                     .arg("--build")
                     .arg(".")
                     .arg("--")
@@ -202,7 +300,7 @@ impl CppLibBuilder {
             ::num_cpus::get()
         };
 
-        if target::current_env() != target::Env::Msvc {
+        if self.target.env != target::Env::Msvc {
             make_args.push(format!("-j{}", num_jobs));
         }
         if self.install_dir.is_some() {
@@ -210,10 +308,9 @@ impl CppLibBuilder {
         }
 
         let mut capture_output_file = None;
-        let mut make_command = if target::current_env() == target::Env::Msvc && self.capture_output
-        {
+        let mut make_command = if self.target.env == target::Env::Msvc && self.capture_output {
             let path = self.build_dir.join("nmake_output.txt");
-            let mut make_command = Command::new("cmd");
+            let mut make_command = self.command("cmd");
             make_command.arg("/C").arg(format!(
                 "cmake {} > {} 2>&1",
                 make_args.join(" "),
@@ -222,12 +319,15 @@ impl CppLibBuilder {
             capture_output_file = Some(path);
             make_command
         } else {
-            let mut make_command = Command::new("cmake");
+            let mut make_command = self.command("cmake");
             make_command.args(&make_args);
             make_command
         };
 
         make_command.current_dir(&self.build_dir);
+        if let Some(launcher) = &launcher {
+            make_command.envs(self.compiler_cache_env_vars(launcher)?);
+        }
         if self.capture_output {
             if let Some(capture_output_file) = capture_output_file {
                 if let Err(err) = run_command(&mut make_command) {
@@ -311,6 +411,13 @@ impl<'a, 'b> CMakeConfigData<'a, 'b> {
             "RITUAL_COMPILER_FLAGS",
             self.cpp_build_config_data.compiler_flags().join(" "),
         ));
+        if let Some(standard) = self.cpp_build_config_data.cpp_standard() {
+            cmake_vars.push(CMakeVar::new(
+                "CMAKE_CXX_STANDARD",
+                standard.version().to_string(),
+            ));
+            cmake_vars.push(CMakeVar::new("CMAKE_CXX_STANDARD_REQUIRED", "ON"));
+        }
         cmake_vars.extend_from_slice(self.cpp_build_config_data.cmake_vars());
         Ok(cmake_vars)
     }
@@ -0,0 +1,71 @@
+//! Helpers for building C++ code for the `wasm32-unknown-emscripten` target.
+//!
+//! The Emscripten SDK is located using `EMSCRIPTEN` environment variable
+//! (path to the `emscripten` directory) or `EMSDK` environment variable
+//! (path to the SDK root, set by `emsdk_env` script).
+
+use crate::cpp_lib_builder::CMakeVar;
+use crate::errors::{bail, Result};
+use crate::file_utils::path_to_str;
+use crate::target::{Target, OS};
+use std::env;
+use std::path::PathBuf;
+
+/// Environment variables used to locate the Emscripten SDK.
+pub const ENV_VAR_NAMES: &[&str] = &["EMSCRIPTEN", "EMSDK"];
+
+/// Returns path to the CMake toolchain file of the Emscripten SDK.
+pub fn cmake_toolchain_file() -> Result<PathBuf> {
+    let emscripten_dir = if let Ok(path) = env::var("EMSCRIPTEN") {
+        PathBuf::from(path)
+    } else if let Ok(path) = env::var("EMSDK") {
+        PathBuf::from(path).join("upstream").join("emscripten")
+    } else {
+        bail!("Emscripten SDK not found; set EMSDK env var");
+    };
+    let path = emscripten_dir
+        .join("cmake")
+        .join("Modules")
+        .join("Platform")
+        .join("Emscripten.cmake");
+    if !path.exists() {
+        bail!("Emscripten toolchain file not found at {}", path.display());
+    }
+    Ok(path)
+}
+
+/// Returns CMake variables required for building C++ code for `target`
+/// if it's the Emscripten target, and an empty list otherwise.
+pub fn cmake_vars_for_target(target: &Target) -> Result<Vec<CMakeVar>> {
+    if target.os != OS::Emscripten {
+        return Ok(Vec::new());
+    }
+    Ok(vec![CMakeVar::new(
+        "CMAKE_TOOLCHAIN_FILE",
+        path_to_str(&cmake_toolchain_file()?)?,
+    )])
+}
+
+/// Returns flags that must be passed to `emcc` when linking the crate for `target`
+/// if it's the Emscripten target, and an empty list otherwise.
+pub fn link_args(target: &Target) -> Vec<&'static str> {
+    if target.os != OS::Emscripten {
+        return Vec::new();
+    }
+    vec![
+        // C++ libraries often need more memory than the default initial heap
+        "-s",
+        "ALLOW_MEMORY_GROWTH=1",
+        // threads are not supported by the generated crates on this target
+        "-s",
+        "USE_PTHREADS=0",
+    ]
+}
+
+#[test]
+fn link_args_only_for_emscripten() {
+    use crate::target::Arch;
+
+    assert!(link_args(&Target::msvc(Arch::X86_64)).is_empty());
+    assert!(link_args(&Target::emscripten()).contains(&"ALLOW_MEMORY_GROWTH=1"));
+}
@@ -4,3 +4,18 @@ pub const FRAMEWORK_PATH: &str = "RITUAL_FRAMEWORK_PATH";
 pub const INCLUDE_PATH: &str = "RITUAL_INCLUDE_PATH";
 pub const CLANG_SYSTEM_INCLUDE_PATH: &str = "CLANG_SYSTEM_INCLUDE_PATH";
 pub const CMAKE_ARGS: &str = "RITUAL_CMAKE_ARGS";
+pub const CONAN_BUILD_INFO: &str = "RITUAL_CONAN_BUILD_INFO";
+pub const VCVARSALL: &str = "RITUAL_VCVARSALL";
+pub const COMPILER_LAUNCHER: &str = "RITUAL_COMPILER_LAUNCHER";
+pub const CLANG_SUBPROCESS: &str = "RITUAL_CLANG_SUBPROCESS";
+pub const WORKSPACE_LOCK_OWNER: &str = "RITUAL_WORKSPACE_LOCK_OWNER";
+pub const JOB_NAME: &str = "RITUAL_JOB_NAME";
+/// If set, the build script doesn't build the C++ wrapper library.
+/// Used when the generated crate is only checked (e.g. for another target).
+pub const SKIP_CPP_BUILD: &str = "RITUAL_SKIP_CPP_BUILD";
+
+/// Returns name of the environment variable set by cargo
+/// if the feature `name` is enabled.
+pub fn cargo_feature(name: &str) -> String {
+    format!("CARGO_FEATURE_{}", name.to_uppercase().replace('-', "_"))
+}
@@ -206,6 +206,25 @@ pub fn save_json<P: AsRef<Path>, T: ::serde::Serialize>(
     path: P,
     value: &T,
     backup_path: Option<&Path>,
+) -> Result<()> {
+    write_file_atomically(path.as_ref(), backup_path, |file| {
+        ::serde_json::to_writer(file, value).with_context(|_| {
+            format!(
+                "failed to serialize to JSON file: {}",
+                path.as_ref().display()
+            )
+        })?;
+        Ok(())
+    })
+}
+
+/// Writes file `path` using `write`. The data is written to a temporary file
+/// which then replaces `path`, so `path` is never missing or partially written.
+/// If `path` already exists, it's copied to `backup_path` (if specified).
+pub fn write_file_atomically<P: AsRef<Path>>(
+    path: P,
+    backup_path: Option<&Path>,
+    write: impl FnOnce(&mut BufWriter<fs::File>) -> Result<()>,
 ) -> Result<()> {
     let tmp_path = {
         let mut buf = path.as_ref().to_path_buf();
@@ -214,19 +233,15 @@ pub fn save_json<P: AsRef<Path>, T: ::serde::Serialize>(
         buf
     };
     {
-        let file = create_file(&tmp_path)?;
-        ::serde_json::to_writer(&mut file.into_inner(), value).with_context(|_| {
-            format!(
-                "failed to serialize to JSON file: {}",
-                path.as_ref().display()
-            )
-        })?;
+        let mut writer = BufWriter::new(create_file(&tmp_path)?.into_inner());
+        write(&mut writer)?;
+        writer
+            .flush()
+            .with_context(|_| format!("failed to write file: {}", tmp_path.display()))?;
     }
-    if path.as_ref().exists() {
-        if let Some(backup_path) = backup_path {
-            rename_file(path.as_ref(), backup_path)?;
-        } else {
-            remove_file(path.as_ref())?;
+    if let Some(backup_path) = backup_path {
+        if path.as_ref().exists() {
+            copy_file(path.as_ref(), backup_path)?;
         }
     }
     rename_file(&tmp_path, path.as_ref())?;
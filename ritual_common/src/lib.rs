@@ -9,11 +9,17 @@
 use crate::target::LibraryTarget;
 use serde_derive::{Deserialize, Serialize};
 
+pub mod android;
+pub mod compile_commands;
 pub mod cpp_build_config;
 pub mod cpp_lib_builder;
+pub mod emscripten;
 pub mod env_var_names;
 pub mod errors;
 pub mod file_utils;
+pub mod library_locator;
+pub mod mingw;
+pub mod msvc;
 pub mod string_utils;
 pub mod target;
 pub mod utils;
@@ -31,6 +37,12 @@ pub struct BuildScriptData {
     pub cpp_wrapper_lib_name: String,
     /// Environments the generator was used in
     pub known_targets: Vec<LibraryTarget>,
+    /// Optional modules of the C++ library detected by the build script
+    #[serde(default)]
+    pub optional_modules: Vec<library_locator::OptionalModule>,
+    /// Compiler flag sets enabled by the corresponding cargo features
+    #[serde(default)]
+    pub compiler_flag_sets: Vec<cpp_build_config::CompilerFlagSet>,
 }
 
 #[derive(Debug)]
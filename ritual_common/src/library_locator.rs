@@ -0,0 +1,689 @@
+//! Discovery of installed C++ libraries using `pkg-config`, CMake package files,
+//! vcpkg and Conan.
+
+use crate::cpp_build_config::{CppBuildConfigData, CppBuildPaths};
+use crate::env_var_names;
+use crate::errors::{bail, format_err, Result, ResultExt};
+use crate::file_utils::{file_to_string, load_json, os_string_into_string, read_dir};
+use crate::target::{current_arch, current_env, current_os, Arch, Env, OS};
+use crate::utils::{get_command_output, run_command_and_capture_output};
+use log::{debug, info};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+const VCPKG_ROOT_ENV_VAR_NAME: &str = "VCPKG_ROOT";
+
+/// Method of finding an installed library.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LibrarySearch {
+    /// Query `pkg-config` for a package with this name
+    /// (e.g. `"gtk+-3.0"`).
+    PkgConfig { name: String },
+    /// Query CMake for a package with this name (as in `find_package`).
+    /// CMake should be able to find the package's config file
+    /// (e.g. through `CMAKE_PREFIX_PATH`).
+    CMake { package: String },
+    /// Use a package installed with vcpkg. The vcpkg root is taken from
+    /// `VCPKG_ROOT` environment variable. If `triplet` is not specified,
+    /// it's selected based on the current target and CRT linkage.
+    Vcpkg {
+        package: String,
+        triplet: Option<String>,
+    },
+    /// Use a package installed with Conan. The `conanbuildinfo.json` file
+    /// (produced by `conan install -g json`) is read from the path
+    /// specified in `RITUAL_CONAN_BUILD_INFO` environment variable.
+    Conan { package: String },
+}
+
+impl LibrarySearch {
+    pub fn pkg_config(name: impl Into<String>) -> Self {
+        LibrarySearch::PkgConfig { name: name.into() }
+    }
+
+    pub fn cmake(package: impl Into<String>) -> Self {
+        LibrarySearch::CMake {
+            package: package.into(),
+        }
+    }
+
+    pub fn vcpkg(package: impl Into<String>) -> Self {
+        LibrarySearch::Vcpkg {
+            package: package.into(),
+            triplet: None,
+        }
+    }
+
+    pub fn conan(package: impl Into<String>) -> Self {
+        LibrarySearch::Conan {
+            package: package.into(),
+        }
+    }
+
+    /// Returns names of environment variables that affect the result of the search.
+    pub fn env_var_names(&self) -> &'static [&'static str] {
+        match self {
+            LibrarySearch::PkgConfig { .. } => &["PKG_CONFIG_PATH", "PKG_CONFIG_LIBDIR"],
+            LibrarySearch::CMake { .. } => &["CMAKE_PREFIX_PATH"],
+            LibrarySearch::Vcpkg { .. } => &[VCPKG_ROOT_ENV_VAR_NAME],
+            LibrarySearch::Conan { .. } => &[env_var_names::CONAN_BUILD_INFO],
+        }
+    }
+}
+
+/// Method of detecting the version of an installed library.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VersionProbe {
+    /// Query `pkg-config --modversion` for a package with this name.
+    PkgConfig { name: String },
+    /// Read the value of a macro defined in a header file
+    /// (e.g. `QT_VERSION_STR` in `QtCore/qconfig.h`). The header path is relative
+    /// to one of the include paths. The value may be a string literal or a single token.
+    HeaderMacro { header: PathBuf, macro_name: String },
+    /// Run a program and use its standard output as the version.
+    Program { program: PathBuf, args: Vec<String> },
+}
+
+impl VersionProbe {
+    pub fn pkg_config(name: impl Into<String>) -> Self {
+        VersionProbe::PkgConfig { name: name.into() }
+    }
+
+    pub fn header_macro(header: impl Into<PathBuf>, macro_name: impl Into<String>) -> Self {
+        VersionProbe::HeaderMacro {
+            header: header.into(),
+            macro_name: macro_name.into(),
+        }
+    }
+
+    pub fn program(program: impl Into<PathBuf>, args: Vec<String>) -> Self {
+        VersionProbe::Program {
+            program: program.into(),
+            args,
+        }
+    }
+}
+
+/// Method of detecting whether an optional module of a library is available.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ModuleProbe {
+    /// The module is available if the header exists in one of the include paths.
+    Header { header: PathBuf },
+    /// The module is available if the macro is defined in the header with a value
+    /// other than `0` or `-1` (e.g. `QT_FEATURE_opengl` in `QtGui/qtgui-config.h`).
+    /// The header path is relative to one of the include paths.
+    HeaderMacro { header: PathBuf, macro_name: String },
+    /// The module is available if `pkg-config` can find a package with this name.
+    PkgConfig { name: String },
+}
+
+impl ModuleProbe {
+    pub fn header(header: impl Into<PathBuf>) -> Self {
+        ModuleProbe::Header {
+            header: header.into(),
+        }
+    }
+
+    pub fn header_macro(header: impl Into<PathBuf>, macro_name: impl Into<String>) -> Self {
+        ModuleProbe::HeaderMacro {
+            header: header.into(),
+            macro_name: macro_name.into(),
+        }
+    }
+
+    pub fn pkg_config(name: impl Into<String>) -> Self {
+        ModuleProbe::PkgConfig { name: name.into() }
+    }
+}
+
+/// Optional compile-time module of a library (e.g. OpenGL support).
+/// Items that are only available if the module is present are gated
+/// behind the cargo feature with the same name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptionalModule {
+    /// Name of the module. Must be a valid cargo feature name.
+    pub name: String,
+    pub probe: ModuleProbe,
+}
+
+impl OptionalModule {
+    pub fn new(name: impl Into<String>, probe: ModuleProbe) -> Self {
+        OptionalModule {
+            name: name.into(),
+            probe,
+        }
+    }
+
+    /// Name of the C++ macro defined when compiling the C++ wrapper library
+    /// if the module is available.
+    pub fn macro_name(&self) -> String {
+        module_macro_name(&self.name)
+    }
+
+    /// Name of the environment variable set by cargo if the feature is enabled.
+    pub fn cargo_feature_env_var_name(&self) -> String {
+        env_var_names::cargo_feature(&self.name)
+    }
+}
+
+/// Returns name of the C++ macro corresponding to the optional module `name`.
+pub fn module_macro_name(name: &str) -> String {
+    format!("RITUAL_MODULE_{}", name.to_uppercase().replace('-', "_"))
+}
+
+/// Information about an installed library discovered by `locate`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LibraryLocation {
+    version: Option<String>,
+    include_paths: Vec<PathBuf>,
+    lib_paths: Vec<PathBuf>,
+    framework_paths: Vec<PathBuf>,
+    linked_libs: Vec<String>,
+    linked_frameworks: Vec<String>,
+    compiler_flags: Vec<String>,
+    build_flags: Vec<String>,
+}
+
+impl LibraryLocation {
+    /// Version of the library, if the search method reports it.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_ref().map(String::as_str)
+    }
+
+    pub fn include_paths(&self) -> &[PathBuf] {
+        &self.include_paths
+    }
+
+    pub fn lib_paths(&self) -> &[PathBuf] {
+        &self.lib_paths
+    }
+
+    pub fn framework_paths(&self) -> &[PathBuf] {
+        &self.framework_paths
+    }
+
+    pub fn linked_libs(&self) -> &[String] {
+        &self.linked_libs
+    }
+
+    pub fn linked_frameworks(&self) -> &[String] {
+        &self.linked_frameworks
+    }
+
+    /// Compiler flags other than include paths (e.g. `-D` definitions).
+    pub fn compiler_flags(&self) -> &[String] {
+        &self.compiler_flags
+    }
+
+    /// Compiler flags that only affect code generation (e.g. MSVC runtime selection).
+    /// Unlike `compiler_flags`, they are not passed to the C++ parser.
+    pub fn build_flags(&self) -> &[String] {
+        &self.build_flags
+    }
+
+    /// Adds discovered include, library and framework paths to `paths`.
+    pub fn apply_paths(&self, paths: &mut CppBuildPaths) {
+        for path in &self.include_paths {
+            paths.add_include_path(path);
+        }
+        for path in &self.lib_paths {
+            paths.add_lib_path(path);
+        }
+        for path in &self.framework_paths {
+            paths.add_framework_path(path);
+        }
+    }
+
+    /// Adds discovered libraries, frameworks and compiler flags to `data`.
+    pub fn apply_build_config(&self, data: &mut CppBuildConfigData) {
+        for lib in &self.linked_libs {
+            data.add_linked_lib(lib.as_str());
+        }
+        for framework in &self.linked_frameworks {
+            data.add_linked_framework(framework.as_str());
+        }
+        data.add_compiler_flags(self.compiler_flags.iter().map(String::as_str));
+        data.add_compiler_flags(self.build_flags.iter().map(String::as_str));
+    }
+
+    /// Parses compiler and linker flags in the format produced
+    /// by `pkg-config --cflags --libs`.
+    fn add_flags(&mut self, flags: &str) -> Result<()> {
+        let words = shell_words::split(flags)
+            .with_context(|_| format!("failed to parse flags: {:?}", flags))?;
+        let mut words = words.into_iter();
+        while let Some(word) = words.next() {
+            if word.starts_with("-I") {
+                self.include_paths.push(PathBuf::from(&word[2..]));
+            } else if word.starts_with("-L") {
+                self.lib_paths.push(PathBuf::from(&word[2..]));
+            } else if word.starts_with("-F") {
+                self.framework_paths.push(PathBuf::from(&word[2..]));
+            } else if word.starts_with("-l") {
+                self.linked_libs.push(word[2..].to_string());
+            } else if word == "-framework" {
+                if let Some(name) = words.next() {
+                    self.linked_frameworks.push(name);
+                }
+            } else if word.starts_with("-Wl,") || word.starts_with("-rpath") {
+                debug!("ignoring linker flag: {}", word);
+            } else if !word.is_empty() {
+                self.compiler_flags.push(word);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Finds a library using the specified method.
+pub fn locate(search: &LibrarySearch) -> Result<LibraryLocation> {
+    match search {
+        LibrarySearch::PkgConfig { name } => locate_with_pkg_config(name),
+        LibrarySearch::CMake { package } => locate_with_cmake(package),
+        LibrarySearch::Vcpkg { package, triplet } => {
+            locate_with_vcpkg(package, triplet.as_ref().map(String::as_str))
+        }
+        LibrarySearch::Conan { package } => locate_with_conan(package),
+    }
+}
+
+/// Detects the version of a library using `probe`. `include_paths` are used
+/// to find the header for `VersionProbe::HeaderMacro`.
+pub fn detect_version(probe: &VersionProbe, include_paths: &[PathBuf]) -> Result<String> {
+    let version = match probe {
+        VersionProbe::PkgConfig { name } => {
+            get_command_output(Command::new("pkg-config").arg("--modversion").arg(name))
+                .with_context(|_| format!("pkg-config failed to find package: {}", name))?
+        }
+        VersionProbe::HeaderMacro { header, macro_name } => {
+            let path = include_paths
+                .iter()
+                .map(|dir| dir.join(header))
+                .find(|path| path.is_file())
+                .ok_or_else(|| format_err!("header not found: {}", header.display()))?;
+            macro_value(&file_to_string(&path)?, macro_name).ok_or_else(|| {
+                format_err!("macro {} not found in {}", macro_name, path.display())
+            })?
+        }
+        VersionProbe::Program { program, args } => {
+            get_command_output(Command::new(program).args(args))?
+        }
+    };
+    let version = version.trim();
+    if version.is_empty() {
+        bail!("version probe returned empty version: {:?}", probe);
+    }
+    debug!("detected version with {:?}: {}", probe, version);
+    Ok(version.to_string())
+}
+
+/// Checks if an optional module is available using `probe`. `include_paths` are used
+/// to find headers.
+pub fn is_module_available(probe: &ModuleProbe, include_paths: &[PathBuf]) -> Result<bool> {
+    let find_header = |header: &PathBuf| {
+        include_paths
+            .iter()
+            .map(|dir| dir.join(header))
+            .find(|path| path.is_file())
+    };
+    let result = match probe {
+        ModuleProbe::Header { header } => find_header(header).is_some(),
+        ModuleProbe::HeaderMacro { header, macro_name } => match find_header(header) {
+            Some(path) => macro_value(&file_to_string(&path)?, macro_name)
+                .map_or(false, |value| value != "0" && value != "-1"),
+            None => false,
+        },
+        ModuleProbe::PkgConfig { name } => Command::new("pkg-config")
+            .arg("--exists")
+            .arg(name)
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false),
+    };
+    debug!("module probe {:?}: {}", probe, result);
+    Ok(result)
+}
+
+/// Returns the value of `#define macro_name value` in C++ `code`.
+/// Quotes around string literal values are removed.
+pub fn macro_value(code: &str, macro_name: &str) -> Option<String> {
+    for line in code.lines() {
+        let line = line.trim_start();
+        if !line.starts_with('#') {
+            continue;
+        }
+        let line = line[1..].trim_start();
+        if !line.starts_with("define") {
+            continue;
+        }
+        let mut words = line["define".len()..].split_whitespace();
+        if words.next() == Some(macro_name) {
+            return words
+                .next()
+                .map(|value| value.trim_matches('"').to_string());
+        }
+    }
+    None
+}
+
+/// Performs all searches added to `data` with `CppBuildConfigData::add_library_search`
+/// and adds the results to `data` and `paths`.
+pub fn apply_library_searches(
+    data: &mut CppBuildConfigData,
+    paths: &mut CppBuildPaths,
+) -> Result<()> {
+    for search in data.library_searches().to_vec() {
+        info!("Searching for library: {:?}", search);
+        let location = locate(&search)?;
+        location.apply_paths(paths);
+        location.apply_build_config(data);
+    }
+    Ok(())
+}
+
+fn locate_with_pkg_config(name: &str) -> Result<LibraryLocation> {
+    let run = |arg: &str| {
+        get_command_output(Command::new("pkg-config").arg(arg).arg(name))
+            .with_context(|_| format!("pkg-config failed to find package: {}", name))
+    };
+    let mut location = LibraryLocation::default();
+    location.version = Some(run("--modversion")?.trim().to_string());
+    location.add_flags(&run("--cflags")?)?;
+    location.add_flags(&run("--libs")?)?;
+    debug!("pkg-config result for {}: {:?}", name, location);
+    Ok(location)
+}
+
+fn locate_with_cmake(package: &str) -> Result<LibraryLocation> {
+    let run = |mode: &str| -> Result<String> {
+        let output = run_command_and_capture_output(
+            Command::new("cmake")
+                .arg("--find-package")
+                .arg(format!("-DNAME={}", package))
+                .arg("-DCOMPILER_ID=GNU")
+                .arg("-DLANGUAGE=CXX")
+                .arg(format!("-DMODE={}", mode)),
+        )?;
+        if !output.is_success() {
+            bail!(
+                "CMake failed to find package {}: {}",
+                package,
+                output.stdout.trim()
+            );
+        }
+        Ok(output.stdout)
+    };
+    let mut location = LibraryLocation::default();
+    location.add_flags(&run("COMPILE")?)?;
+    location.add_flags(&run("LINK")?)?;
+    debug!("CMake result for {}: {:?}", package, location);
+    Ok(location)
+}
+
+/// Returns true if the C runtime should be linked statically
+/// (`-C target-feature=+crt-static` is set).
+fn is_crt_static() -> bool {
+    env::var("CARGO_CFG_TARGET_FEATURE")
+        .map(|features| features.split(',').any(|f| f == "crt-static"))
+        .unwrap_or(false)
+}
+
+/// Returns MSVC compiler flag that selects CRT linkage corresponding to `runtime`.
+/// MSVC names (e.g. `"MD"` or `"MTd"`) and Conan names (`"dynamic"` or `"static"`,
+/// with `runtime_type` set to `"Debug"` or `"Release"`) are accepted.
+fn msvc_runtime_flag(runtime: &str, runtime_type: Option<&str>) -> Result<String> {
+    let name = match runtime {
+        "MD" | "MDd" | "MT" | "MTd" => runtime.to_string(),
+        "dynamic" | "static" => {
+            let name = if runtime == "dynamic" { "MD" } else { "MT" };
+            match runtime_type {
+                None | Some("Release") => name.to_string(),
+                Some("Debug") => format!("{}d", name),
+                Some(runtime_type) => bail!("unknown MSVC runtime type: {}", runtime_type),
+            }
+        }
+        _ => bail!("unknown MSVC runtime: {}", runtime),
+    };
+    Ok(format!("/{}", name))
+}
+
+fn default_vcpkg_triplet() -> Result<String> {
+    let arch = match current_arch() {
+        Arch::X86 => "x86",
+        Arch::X86_64 => "x64",
+        Arch::Arm => "arm",
+        Arch::AArch64 => "arm64",
+        arch => bail!("vcpkg triplet is unknown for {:?}", arch),
+    };
+    let os = match current_os() {
+        OS::Windows => "windows",
+        OS::Linux => "linux",
+        OS::MacOS => "osx",
+        os => bail!("vcpkg triplet is unknown for {:?}", os),
+    };
+    let suffix = if current_os() == OS::Windows && is_crt_static() {
+        "-static"
+    } else {
+        ""
+    };
+    Ok(format!("{}-{}{}", arch, os, suffix))
+}
+
+fn locate_with_vcpkg(package: &str, triplet: Option<&str>) -> Result<LibraryLocation> {
+    let root = PathBuf::from(env::var(VCPKG_ROOT_ENV_VAR_NAME).with_context(|_| {
+        format_err!("{} env var is required for vcpkg", VCPKG_ROOT_ENV_VAR_NAME)
+    })?);
+    let triplet = match triplet {
+        Some(triplet) => triplet.to_string(),
+        None => default_vcpkg_triplet()?,
+    };
+    let installed_path = root.join("installed");
+    let triplet_path = installed_path.join(&triplet);
+
+    // vcpkg writes list of installed files to
+    // `installed/vcpkg/info/<package>_<version>_<triplet>.list`.
+    let prefix = format!("{}_", package);
+    let suffix = format!("_{}.list", triplet);
+    let mut list_file = None;
+    for item in read_dir(installed_path.join("vcpkg").join("info"))? {
+        let name = os_string_into_string(item?.file_name())?;
+        if name.starts_with(&prefix) && name.ends_with(&suffix) {
+            list_file = Some(name);
+            break;
+        }
+    }
+    let list_file = list_file.ok_or_else(|| {
+        format_err!(
+            "vcpkg package {} is not installed for triplet {}",
+            package,
+            triplet
+        )
+    })?;
+
+    let mut location = LibraryLocation::default();
+    location.version = Some(list_file[prefix.len()..list_file.len() - suffix.len()].to_string());
+    location.include_paths.push(triplet_path.join("include"));
+    location.lib_paths.push(triplet_path.join("lib"));
+
+    let lib_prefix = format!("{}/lib/", triplet);
+    let list = file_to_string(installed_path.join("vcpkg").join("info").join(&list_file))?;
+    for line in list.lines() {
+        if line.starts_with(&lib_prefix) {
+            let file_name = &line[lib_prefix.len()..];
+            if !file_name.contains('/') {
+                if file_name.ends_with(".lib") {
+                    let name = &file_name[..file_name.len() - ".lib".len()];
+                    location.linked_libs.push(name.to_string());
+                } else if file_name.starts_with("lib") && file_name.ends_with(".a") {
+                    let name = &file_name["lib".len()..file_name.len() - ".a".len()];
+                    location.linked_libs.push(name.to_string());
+                }
+            }
+        }
+    }
+    if current_env() == Env::Msvc {
+        let runtime = if triplet.ends_with("-static") {
+            "MT"
+        } else {
+            "MD"
+        };
+        location.build_flags.push(msvc_runtime_flag(runtime, None)?);
+    }
+    debug!("vcpkg result for {}: {:?}", package, location);
+    Ok(location)
+}
+
+#[derive(Debug, Deserialize)]
+struct ConanBuildInfo {
+    dependencies: Vec<ConanDependency>,
+    #[serde(default)]
+    settings: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConanDependency {
+    name: String,
+    version: Option<String>,
+    #[serde(default)]
+    include_paths: Vec<PathBuf>,
+    #[serde(default)]
+    lib_paths: Vec<PathBuf>,
+    #[serde(default)]
+    libs: Vec<String>,
+    #[serde(default)]
+    system_libs: Vec<String>,
+    #[serde(default)]
+    defines: Vec<String>,
+    #[serde(default)]
+    cxxflags: Vec<String>,
+    #[serde(default)]
+    framework_paths: Vec<PathBuf>,
+    #[serde(default)]
+    frameworks: Vec<String>,
+}
+
+fn locate_with_conan(package: &str) -> Result<LibraryLocation> {
+    let path = env::var(env_var_names::CONAN_BUILD_INFO).with_context(|_| {
+        format_err!(
+            "{} env var is required for Conan",
+            env_var_names::CONAN_BUILD_INFO
+        )
+    })?;
+    let build_info: ConanBuildInfo = load_json(path)?;
+    conan_location(&build_info, package)
+}
+
+fn conan_location(build_info: &ConanBuildInfo, package: &str) -> Result<LibraryLocation> {
+    let dependency = build_info
+        .dependencies
+        .iter()
+        .find(|dep| dep.name == package)
+        .ok_or_else(|| format_err!("Conan package {} is not installed", package))?;
+
+    let mut location = LibraryLocation::default();
+    location.version = dependency.version.clone();
+    location.include_paths = dependency.include_paths.clone();
+    location.lib_paths = dependency.lib_paths.clone();
+    location.framework_paths = dependency.framework_paths.clone();
+    location.linked_libs = dependency
+        .libs
+        .iter()
+        .chain(&dependency.system_libs)
+        .cloned()
+        .collect();
+    location.linked_frameworks = dependency.frameworks.clone();
+    location.compiler_flags = dependency
+        .defines
+        .iter()
+        .map(|define| format!("-D{}", define))
+        .chain(dependency.cxxflags.iter().cloned())
+        .collect();
+    if let Some(runtime) = build_info.settings.get("compiler.runtime") {
+        let runtime_type = build_info
+            .settings
+            .get("compiler.runtime_type")
+            .map(String::as_str);
+        location
+            .build_flags
+            .push(msvc_runtime_flag(runtime, runtime_type)?);
+    }
+    debug!("Conan result for {}: {:?}", package, location);
+    Ok(location)
+}
+
+#[test]
+fn header_macro_value() {
+    let code =
+        "#ifndef QCONFIG_H\n#  define QT_VERSION_STR \"5.13.1\"\n#define QT_VERSION 0x050D01\n";
+    assert_eq!(
+        macro_value(code, "QT_VERSION_STR"),
+        Some("5.13.1".to_string())
+    );
+    assert_eq!(
+        macro_value(code, "QT_VERSION"),
+        Some("0x050D01".to_string())
+    );
+    assert_eq!(macro_value(code, "QT_VERSION_MAJOR"), None);
+}
+
+#[test]
+fn parse_flags() {
+    let mut location = LibraryLocation::default();
+    location
+        .add_flags("-I/usr/include/foo -DFOO=1 -pthread\n")
+        .unwrap();
+    location
+        .add_flags("-L/usr/lib/foo -lfoo -lbar -framework Cocoa -Wl,-rpath,/x")
+        .unwrap();
+    assert_eq!(
+        location.include_paths(),
+        &[PathBuf::from("/usr/include/foo")]
+    );
+    assert_eq!(location.lib_paths(), &[PathBuf::from("/usr/lib/foo")]);
+    assert_eq!(location.linked_libs(), &["foo", "bar"]);
+    assert_eq!(location.linked_frameworks(), &["Cocoa"]);
+    assert_eq!(location.compiler_flags(), &["-DFOO=1", "-pthread"]);
+}
+
+#[test]
+fn msvc_runtime_flags() {
+    assert_eq!(msvc_runtime_flag("MDd", None).unwrap(), "/MDd");
+    assert_eq!(msvc_runtime_flag("dynamic", None).unwrap(), "/MD");
+    assert_eq!(msvc_runtime_flag("static", Some("Debug")).unwrap(), "/MTd");
+    assert_eq!(msvc_runtime_flag("static", Some("Release")).unwrap(), "/MT");
+    assert!(msvc_runtime_flag("shared", None).is_err());
+    assert!(msvc_runtime_flag("dynamic", Some("Profile")).is_err());
+}
+
+#[test]
+fn conan_runtime_is_build_only() {
+    let build_info: ConanBuildInfo = serde_json::from_str(
+        r#"{
+            "dependencies": [{
+                "name": "foo",
+                "version": "1.2",
+                "include_paths": ["/conan/foo/include"],
+                "libs": ["foo"],
+                "defines": ["FOO_SHARED"]
+            }],
+            "settings": {
+                "compiler.runtime": "dynamic",
+                "compiler.runtime_type": "Debug"
+            }
+        }"#,
+    )
+    .unwrap();
+    let location = conan_location(&build_info, "foo").unwrap();
+    assert_eq!(location.version(), Some("1.2"));
+    assert_eq!(location.compiler_flags(), &["-DFOO_SHARED"]);
+    assert_eq!(location.build_flags(), &["/MDd"]);
+
+    let mut data = CppBuildConfigData::new();
+    location.apply_build_config(&mut data);
+    assert_eq!(data.compiler_flags(), &["-DFOO_SHARED", "/MDd"]);
+
+    assert!(conan_location(&build_info, "bar").is_err());
+}
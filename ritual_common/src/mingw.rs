@@ -0,0 +1,48 @@
+//! Helpers for building C++ code for the `*-pc-windows-gnu` targets.
+//!
+//! On Windows, the MinGW toolchain found in `PATH` is used as is.
+//! On other hosts, the `<arch>-w64-mingw32` cross-compiler is selected.
+
+use crate::cpp_lib_builder::CMakeVar;
+use crate::errors::{bail, Result};
+use crate::target::{current_os, Arch, Env, Target, OS};
+
+/// Returns the prefix of MinGW-w64 cross-compiler executables for `arch`
+/// (e.g. `x86_64-w64-mingw32`).
+pub fn toolchain_prefix(arch: Arch) -> Result<&'static str> {
+    Ok(match arch {
+        Arch::X86 => "i686-w64-mingw32",
+        Arch::X86_64 => "x86_64-w64-mingw32",
+        Arch::AArch64 => "aarch64-w64-mingw32",
+        _ => bail!("MinGW doesn't support {:?} architecture", arch),
+    })
+}
+
+/// Returns CMake variables that select the MinGW-w64 cross-compiler for `arch`.
+pub fn cmake_vars(arch: Arch) -> Result<Vec<CMakeVar>> {
+    let prefix = toolchain_prefix(arch)?;
+    Ok(vec![
+        CMakeVar::new("CMAKE_SYSTEM_NAME", "Windows"),
+        CMakeVar::new("CMAKE_C_COMPILER", format!("{}-gcc", prefix)),
+        CMakeVar::new("CMAKE_CXX_COMPILER", format!("{}-g++", prefix)),
+        CMakeVar::new("CMAKE_RC_COMPILER", format!("{}-windres", prefix)),
+    ])
+}
+
+/// Returns CMake variables required for building C++ code for `target`
+/// if it's a Windows GNU target and the host is not Windows,
+/// and an empty list otherwise.
+pub fn cmake_vars_for_target(target: &Target) -> Result<Vec<CMakeVar>> {
+    if target.os != OS::Windows || target.env != Env::Gnu || current_os() == OS::Windows {
+        return Ok(Vec::new());
+    }
+    cmake_vars(target.arch)
+}
+
+#[test]
+fn mingw_target() {
+    let target = Target::mingw(Arch::X86_64);
+    assert_eq!(target.env, Env::Gnu);
+    assert_eq!(target.os, OS::Windows);
+    assert_eq!(target.arch, Arch::X86_64);
+}
@@ -0,0 +1,126 @@
+//! Helpers for building C++ code with the MSVC toolchain.
+//!
+//! `cl.exe` can only be used if the environment is set up by one of
+//! Visual Studio's `vcvars*.bat` scripts. If the current process
+//! was not started from a developer command prompt, the environment
+//! is captured from `vcvarsall.bat` and applied to the build commands.
+
+use crate::env_var_names;
+use crate::errors::{bail, format_err, Result};
+use crate::file_utils::path_to_str;
+use crate::target::{Arch, Env, Target};
+use crate::utils::get_command_output;
+use log::trace;
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Returns the argument of `vcvarsall.bat` that selects
+/// the compiler for `arch` on a 64-bit host.
+pub fn vcvars_arch(arch: Arch) -> Result<&'static str> {
+    Ok(match arch {
+        Arch::X86 => "x64_x86",
+        Arch::X86_64 => "x64",
+        Arch::Arm => "x64_arm",
+        Arch::AArch64 => "x64_arm64",
+        _ => bail!("MSVC doesn't support {:?} architecture", arch),
+    })
+}
+
+/// Returns path to `vcvarsall.bat`. The path is taken from
+/// the `RITUAL_VCVARSALL` environment variable if it's set.
+/// Otherwise, `vswhere.exe` is used to find the latest Visual Studio installation.
+pub fn find_vcvarsall() -> Result<PathBuf> {
+    if let Ok(path) = env::var(env_var_names::VCVARSALL) {
+        return Ok(PathBuf::from(path));
+    }
+    let program_files = env::var("ProgramFiles(x86)")
+        .or_else(|_| env::var("ProgramFiles"))
+        .map_err(|_| format_err!("ProgramFiles env var is missing"))?;
+    let vswhere = PathBuf::from(program_files)
+        .join("Microsoft Visual Studio")
+        .join("Installer")
+        .join("vswhere.exe");
+    if !vswhere.exists() {
+        bail!(
+            "vswhere.exe not found at {}; set {} env var to the path of vcvarsall.bat",
+            vswhere.display(),
+            env_var_names::VCVARSALL
+        );
+    }
+    let installation = get_command_output(
+        Command::new(vswhere)
+            .arg("-latest")
+            .arg("-products")
+            .arg("*")
+            .arg("-requires")
+            .arg("Microsoft.VisualStudio.Component.VC.Tools.x86.x64")
+            .arg("-property")
+            .arg("installationPath"),
+    )?;
+    let installation = installation.trim();
+    if installation.is_empty() {
+        bail!("no Visual Studio installation with C++ tools found");
+    }
+    let path = PathBuf::from(installation)
+        .join("VC")
+        .join("Auxiliary")
+        .join("Build")
+        .join("vcvarsall.bat");
+    if !path.exists() {
+        bail!("vcvarsall.bat not found at {}", path.display());
+    }
+    Ok(path)
+}
+
+/// Returns environment variables that need to be set to run MSVC compiler
+/// for `target`. Returns an empty list if the current environment is already
+/// set up by a developer command prompt or if `target` doesn't use MSVC.
+pub fn vcvars_environment(target: &Target) -> Result<Vec<(String, String)>> {
+    if target.env != Env::Msvc || env::var_os("VCINSTALLDIR").is_some() {
+        return Ok(Vec::new());
+    }
+    let vcvarsall = find_vcvarsall()?;
+    let output = get_command_output(Command::new("cmd").arg("/C").arg(format!(
+        "call \"{}\" {} >nul && set",
+        path_to_str(&vcvarsall)?,
+        vcvars_arch(target.arch)?
+    )))?;
+    let vars = parse_set_output(&output);
+    trace!(
+        "captured {} env vars from {}",
+        vars.len(),
+        vcvarsall.display()
+    );
+    Ok(vars)
+}
+
+/// Parses output of `set` command of `cmd.exe`.
+pub fn parse_set_output(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let index = line.find('=')?;
+            // skip special variables like `=C:=C:\`
+            if index == 0 {
+                return None;
+            }
+            Some((line[..index].to_string(), line[index + 1..].to_string()))
+        })
+        .collect()
+}
+
+#[test]
+fn parse_set() {
+    let output = "=C:=C:\\work\nINCLUDE=C:\\VC\\include;C:\\SDK\\include\nPath=C:\\VC\\bin\n";
+    assert_eq!(
+        parse_set_output(output),
+        vec![
+            (
+                "INCLUDE".to_string(),
+                "C:\\VC\\include;C:\\SDK\\include".to_string()
+            ),
+            ("Path".to_string(), "C:\\VC\\bin".to_string()),
+        ]
+    );
+}
@@ -1,6 +1,9 @@
 //! Types for expressing properties of different target platforms and platform-based conditions
 
+use crate::android::AndroidAbi;
 use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::env;
 
 /// CPU architecture, as reported by `target_arch`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash)]
@@ -12,6 +15,7 @@ pub enum Arch {
     PowerPC64,
     Arm,
     AArch64,
+    Wasm32,
 }
 
 /// Operating system, as reported by `target_os`.
@@ -27,6 +31,7 @@ pub enum OS {
     Bitrig,
     OpenBSD,
     NetBSD,
+    Emscripten,
 }
 
 /// Operating system family, as reported by `target_family`.
@@ -79,7 +84,121 @@ pub struct Target {
     pub endian: Endian,
 }
 
+impl Arch {
+    /// Returns value of `target_arch` corresponding to `self`.
+    pub fn cfg_value(self) -> &'static str {
+        match self {
+            Arch::X86 => "x86",
+            Arch::X86_64 => "x86_64",
+            Arch::Mips => "mips",
+            Arch::PowerPC => "powerpc",
+            Arch::PowerPC64 => "powerpc64",
+            Arch::Arm => "arm",
+            Arch::AArch64 => "aarch64",
+            Arch::Wasm32 => "wasm32",
+        }
+    }
+}
+
+impl OS {
+    /// Returns value of `target_os` corresponding to `self`.
+    pub fn cfg_value(self) -> &'static str {
+        match self {
+            OS::Windows => "windows",
+            OS::MacOS => "macos",
+            OS::IOS => "ios",
+            OS::Linux => "linux",
+            OS::Android => "android",
+            OS::FreeBSD => "freebsd",
+            OS::DragonFly => "dragonfly",
+            OS::Bitrig => "bitrig",
+            OS::OpenBSD => "openbsd",
+            OS::NetBSD => "netbsd",
+            OS::Emscripten => "emscripten",
+        }
+    }
+}
+
+impl Family {
+    /// Returns value of `target_family` corresponding to `self`.
+    pub fn cfg_value(self) -> &'static str {
+        match self {
+            Family::Windows => "windows",
+            Family::Unix => "unix",
+        }
+    }
+}
+
+impl Env {
+    /// Returns value of `target_env` corresponding to `self`.
+    pub fn cfg_value(self) -> &'static str {
+        match self {
+            Env::Gnu => "gnu",
+            Env::Msvc => "msvc",
+            Env::Musl => "musl",
+            Env::None => "",
+        }
+    }
+}
+
+impl PointerWidth {
+    /// Returns value of `target_pointer_width` corresponding to `self`.
+    pub fn cfg_value(self) -> &'static str {
+        match self {
+            PointerWidth::P32 => "32",
+            PointerWidth::P64 => "64",
+        }
+    }
+}
+
+impl Endian {
+    /// Returns value of `target_endian` corresponding to `self`.
+    pub fn cfg_value(self) -> &'static str {
+        match self {
+            Endian::Little => "little",
+            Endian::Big => "big",
+        }
+    }
+}
+
 impl Target {
+    /// Returns the `wasm32-unknown-emscripten` target.
+    pub fn emscripten() -> Self {
+        Target {
+            arch: Arch::Wasm32,
+            os: OS::Emscripten,
+            family: Family::Unix,
+            env: Env::None,
+            pointer_width: PointerWidth::P32,
+            endian: Endian::Little,
+        }
+    }
+
+    /// Returns the Windows MSVC target for `arch`.
+    pub fn msvc(arch: Arch) -> Self {
+        Target {
+            arch,
+            os: OS::Windows,
+            family: Family::Windows,
+            env: Env::Msvc,
+            pointer_width: match arch {
+                Arch::X86 | Arch::Arm | Arch::Mips | Arch::PowerPC | Arch::Wasm32 => {
+                    PointerWidth::P32
+                }
+                Arch::X86_64 | Arch::AArch64 | Arch::PowerPC64 => PointerWidth::P64,
+            },
+            endian: Endian::Little,
+        }
+    }
+
+    /// Returns the Windows GNU (MinGW) target for `arch`.
+    pub fn mingw(arch: Arch) -> Self {
+        Target {
+            env: Env::Gnu,
+            ..Target::msvc(arch)
+        }
+    }
+
     pub fn short_text(&self) -> String {
         format!(
             "{:?}-{:?}-{:?}-{:?}",
@@ -87,6 +206,33 @@ impl Target {
         )
         .to_lowercase()
     }
+
+    /// Returns the Rust target triple corresponding to `self`,
+    /// or `None` if there is no such triple or it's ambiguous.
+    pub fn rust_triple(&self) -> Option<String> {
+        let arch = match self.arch {
+            Arch::X86 => "i686",
+            Arch::X86_64 => "x86_64",
+            Arch::AArch64 => "aarch64",
+            Arch::Wasm32 => "wasm32",
+            Arch::Arm | Arch::Mips | Arch::PowerPC | Arch::PowerPC64 => return None,
+        };
+        let triple = match (self.os, self.env) {
+            (OS::Windows, Env::Msvc) => format!("{}-pc-windows-msvc", arch),
+            (OS::Windows, Env::Gnu) => format!("{}-pc-windows-gnu", arch),
+            (OS::Linux, Env::Gnu) => format!("{}-unknown-linux-gnu", arch),
+            (OS::Linux, Env::Musl) => format!("{}-unknown-linux-musl", arch),
+            (OS::MacOS, _) => format!("{}-apple-darwin", arch),
+            (OS::IOS, _) => format!("{}-apple-ios", arch),
+            (OS::Android, _) => format!("{}-linux-android", arch),
+            (OS::FreeBSD, _) => format!("{}-unknown-freebsd", arch),
+            (OS::NetBSD, _) => format!("{}-unknown-netbsd", arch),
+            (OS::OpenBSD, _) => format!("{}-unknown-openbsd", arch),
+            (OS::Emscripten, _) => format!("{}-unknown-emscripten", arch),
+            _ => return None,
+        };
+        Some(triple)
+    }
 }
 
 /// Condition on properties of the target. Simple conditions
@@ -147,6 +293,11 @@ pub fn current_arch() -> Arch {
 pub fn current_arch() -> Arch {
     Arch::AArch64
 }
+#[cfg(target_arch = "wasm32")]
+/// Returns current CPU architecture
+pub fn current_arch() -> Arch {
+    Arch::Wasm32
+}
 
 #[cfg(target_os = "windows")]
 /// Returns current operating system
@@ -198,6 +349,11 @@ pub fn current_os() -> OS {
 pub fn current_os() -> OS {
     OS::NetBSD
 }
+#[cfg(target_os = "emscripten")]
+/// Returns current operating system
+pub fn current_os() -> OS {
+    OS::Emscripten
+}
 
 #[cfg(target_family = "unix")]
 /// Returns current operating system family
@@ -265,6 +421,90 @@ pub fn current_target() -> Target {
     }
 }
 
+/// Returns properties of the target the crate is being built for.
+/// In a build script, this is determined from `CARGO_CFG_*` environment
+/// variables set by `cargo`, so cross-compilation targets are reported correctly.
+/// Properties that are not available are taken from `current_target()`.
+pub fn build_script_target() -> Target {
+    fn cfg(name: &str) -> Option<String> {
+        env::var(format!("CARGO_CFG_TARGET_{}", name)).ok()
+    }
+    let current = current_target();
+    Target {
+        arch: cfg("ARCH")
+            .and_then(|value| {
+                Some(match value.as_str() {
+                    "x86" => Arch::X86,
+                    "x86_64" => Arch::X86_64,
+                    "mips" => Arch::Mips,
+                    "powerpc" => Arch::PowerPC,
+                    "powerpc64" => Arch::PowerPC64,
+                    "arm" => Arch::Arm,
+                    "aarch64" => Arch::AArch64,
+                    "wasm32" => Arch::Wasm32,
+                    _ => return None,
+                })
+            })
+            .unwrap_or(current.arch),
+        os: cfg("OS")
+            .and_then(|value| {
+                Some(match value.as_str() {
+                    "windows" => OS::Windows,
+                    "macos" => OS::MacOS,
+                    "ios" => OS::IOS,
+                    "linux" => OS::Linux,
+                    "android" => OS::Android,
+                    "freebsd" => OS::FreeBSD,
+                    "dragonfly" => OS::DragonFly,
+                    "bitrig" => OS::Bitrig,
+                    "openbsd" => OS::OpenBSD,
+                    "netbsd" => OS::NetBSD,
+                    "emscripten" => OS::Emscripten,
+                    _ => return None,
+                })
+            })
+            .unwrap_or(current.os),
+        family: cfg("FAMILY")
+            .and_then(|value| {
+                Some(match value.as_str() {
+                    "windows" => Family::Windows,
+                    "unix" => Family::Unix,
+                    _ => return None,
+                })
+            })
+            .unwrap_or(current.family),
+        env: cfg("ENV")
+            .and_then(|value| {
+                Some(match value.as_str() {
+                    "gnu" => Env::Gnu,
+                    "msvc" => Env::Msvc,
+                    "musl" => Env::Musl,
+                    "" => Env::None,
+                    _ => return None,
+                })
+            })
+            .unwrap_or(current.env),
+        pointer_width: cfg("POINTER_WIDTH")
+            .and_then(|value| {
+                Some(match value.as_str() {
+                    "32" => PointerWidth::P32,
+                    "64" => PointerWidth::P64,
+                    _ => return None,
+                })
+            })
+            .unwrap_or(current.pointer_width),
+        endian: cfg("ENDIAN")
+            .and_then(|value| {
+                Some(match value.as_str() {
+                    "little" => Endian::Little,
+                    "big" => Endian::Big,
+                    _ => return None,
+                })
+            })
+            .unwrap_or(current.endian),
+    }
+}
+
 impl Condition {
     /// Evaluate the condition for `target`. Returns true if
     /// `target` matches the condition.
@@ -295,14 +535,74 @@ impl Condition {
 pub struct LibraryTarget {
     pub target: Target,
     pub cpp_library_version: Option<String>,
+    /// Names of the optional modules of the library available on this target
+    #[serde(default)]
+    pub enabled_modules: BTreeSet<String>,
+    /// Name of the compiler flag set used on this target, if any
+    #[serde(default)]
+    pub flag_set: Option<String>,
 }
 
 impl LibraryTarget {
+    /// Returns the checker environment preset for the Windows MSVC target for `arch`.
+    pub fn msvc(arch: Arch, cpp_library_version: Option<String>) -> Self {
+        LibraryTarget {
+            target: Target::msvc(arch),
+            cpp_library_version,
+            enabled_modules: Default::default(),
+            flag_set: None,
+        }
+    }
+
+    /// Returns the checker environment preset for the Windows GNU (MinGW) target for `arch`.
+    pub fn mingw(arch: Arch, cpp_library_version: Option<String>) -> Self {
+        LibraryTarget {
+            target: Target::mingw(arch),
+            cpp_library_version,
+            enabled_modules: Default::default(),
+            flag_set: None,
+        }
+    }
+
+    /// Returns the checker environment preset for the `wasm32-unknown-emscripten` target.
+    pub fn emscripten(cpp_library_version: Option<String>) -> Self {
+        LibraryTarget {
+            target: Target::emscripten(),
+            cpp_library_version,
+            enabled_modules: Default::default(),
+            flag_set: None,
+        }
+    }
+
+    /// Returns the checker environment preset for Android `abi`.
+    pub fn android(abi: AndroidAbi, cpp_library_version: Option<String>) -> Self {
+        LibraryTarget {
+            target: abi.target(),
+            cpp_library_version,
+            enabled_modules: Default::default(),
+            flag_set: None,
+        }
+    }
+
     pub fn short_text(&self) -> String {
-        if let Some(cpp_library_version) = &self.cpp_library_version {
+        let mut text = if let Some(cpp_library_version) = &self.cpp_library_version {
             format!("v{} on {}", cpp_library_version, self.target.short_text())
         } else {
             self.target.short_text()
+        };
+        if !self.enabled_modules.is_empty() {
+            text += &format!(
+                " (modules: {})",
+                self.enabled_modules
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        if let Some(flag_set) = &self.flag_set {
+            text += &format!(" (flags: {})", flag_set);
         }
+        text
     }
 }
@@ -0,0 +1,9 @@
+use crate::android::stl_name;
+use crate::cpp_build_config::CppLibraryType;
+
+#[test]
+fn stl_matches_runtime_type() {
+    assert_eq!(stl_name(None), "c++_shared");
+    assert_eq!(stl_name(Some(CppLibraryType::Shared)), "c++_shared");
+    assert_eq!(stl_name(Some(CppLibraryType::Static)), "c++_static");
+}
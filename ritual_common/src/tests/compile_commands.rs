@@ -0,0 +1,90 @@
+use crate::compile_commands::{parse_arguments, CompilationDatabase};
+use std::path::{Path, PathBuf};
+
+#[test]
+fn arguments() {
+    let arguments = [
+        "/usr/bin/c++",
+        "-DFOO",
+        "-D",
+        "BAR=1",
+        "-I/usr/include/foo",
+        "-I",
+        "include",
+        "-isystem",
+        "/opt/bar/include",
+        "-F/Library/Frameworks",
+        "-fPIC",
+        "-o",
+        "file.o",
+        "-c",
+        "../src/file.cpp",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect::<Vec<_>>();
+    let flags = parse_arguments(&arguments, Path::new("/build"));
+    assert_eq!(
+        flags.include_paths(),
+        &[
+            PathBuf::from("/usr/include/foo"),
+            PathBuf::from("/build/include")
+        ]
+    );
+    assert_eq!(
+        flags.framework_paths(),
+        &[PathBuf::from("/Library/Frameworks")]
+    );
+    assert_eq!(
+        flags.compiler_flags(),
+        &["-DFOO", "-DBAR=1", "-isystem/opt/bar/include"]
+    );
+}
+
+fn arguments_vec(arguments: &[&str]) -> Vec<String> {
+    arguments.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn undefine_cancels_define() {
+    let arguments = arguments_vec(&["c++", "-DFOO=1", "-DBAR", "-UFOO", "-DBAZ", "-DBAZ=2"]);
+    let flags = parse_arguments(&arguments, Path::new("/build"));
+    assert_eq!(flags.compiler_flags(), &["-DBAR", "-UFOO", "-DBAZ=2"]);
+}
+
+#[test]
+fn exports_macro_is_ignored() {
+    let arguments = arguments_vec(&["c++", "-Dfoo_EXPORTS", "-DFOO_SHARED"]);
+    let flags = parse_arguments(&arguments, Path::new("/build"));
+    assert_eq!(flags.compiler_flags(), &["-DFOO_SHARED"]);
+}
+
+#[test]
+fn header_flags() {
+    let compilation = |file: &str, arguments: &[&str]| {
+        let flags = parse_arguments(&arguments_vec(arguments), Path::new("/build"));
+        (PathBuf::from(file), flags)
+    };
+    let database = CompilationDatabase::from_compilations(vec![
+        compilation(
+            "/src/a/widget.cpp",
+            &["c++", "-DSHARED", "-DWIDGET_PRIVATE"],
+        ),
+        compilation("/src/b/widget.cpp", &["c++", "-DSHARED", "-DOTHER_WIDGET"]),
+        compilation("/src/a/label.cpp", &["c++", "-DSHARED", "-DLABEL=1"]),
+        compilation("/src/a/button.cpp", &["c++", "-DSHARED", "-DLABEL=2"]),
+    ]);
+
+    let flags = database.header_flags(Path::new("/src/a/widget.h"));
+    assert_eq!(flags.compiler_flags(), &["-DSHARED", "-DWIDGET_PRIVATE"]);
+    let flags = database.header_flags(Path::new("/include/label.h"));
+    assert_eq!(flags.compiler_flags(), &["-DSHARED", "-DLABEL=1"]);
+    let flags = database.header_flags(Path::new("/include/global.h"));
+    assert_eq!(flags.compiler_flags(), &["-DSHARED"]);
+
+    let flags = database.headers_flags(&[
+        PathBuf::from("/src/a/label.h"),
+        PathBuf::from("/src/a/button.h"),
+    ]);
+    assert_eq!(flags.compiler_flags(), &["-DSHARED", "-DLABEL=1"]);
+}
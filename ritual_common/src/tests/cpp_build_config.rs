@@ -0,0 +1,62 @@
+use crate::cpp_build_config::{
+    CompilerFlagSet, CppBuildConfigData, CppLibraryType, CppRuntime, CppStandard,
+};
+use crate::library_locator::{ModuleProbe, OptionalModule};
+use crate::target::current_target;
+
+#[test]
+fn whole_archive_link_libs() {
+    let mut data = CppBuildConfigData::new();
+    data.add_linked_lib("foo");
+    data.set_linked_libs_type(CppLibraryType::Static);
+    data.set_whole_archive(true);
+    data.set_cpp_runtime(CppRuntime::None);
+    let target = current_target();
+    assert_eq!(
+        data.cargo_link_libs(&target, true),
+        vec!["static:+whole-archive=foo".to_string()]
+    );
+    assert_eq!(
+        data.cargo_link_libs(&target, false),
+        vec!["static=foo".to_string()]
+    );
+}
+
+#[test]
+fn flag_set_names() {
+    let flag_set = CompilerFlagSet::new("cpp-17", vec!["-std=c++17"]);
+    let module = OptionalModule::new(
+        "cpp-17",
+        ModuleProbe::Header {
+            header: "a.h".into(),
+        },
+    );
+    assert_eq!(
+        flag_set.cargo_feature_env_var_name(),
+        "CARGO_FEATURE_CPP_17"
+    );
+    assert_eq!(
+        flag_set.cargo_feature_env_var_name(),
+        module.cargo_feature_env_var_name()
+    );
+    assert_eq!(flag_set.macro_name(), "RITUAL_FLAG_SET_CPP_17");
+    assert_ne!(flag_set.macro_name(), module.macro_name());
+}
+
+#[test]
+fn standard_from_compiler_flag() {
+    assert_eq!(
+        CppStandard::from_compiler_flag("-std=c++17"),
+        Some(CppStandard::Cpp17)
+    );
+    assert_eq!(
+        CppStandard::from_compiler_flag("-std=gnu++14"),
+        Some(CppStandard::Cpp14)
+    );
+    assert_eq!(
+        CppStandard::from_compiler_flag("/std:c++latest"),
+        Some(CppStandard::Cpp20)
+    );
+    assert_eq!(CppStandard::from_compiler_flag("-DFOO"), None);
+    assert_eq!(CppStandard::from_compiler_flag("-std=c99"), None);
+}
@@ -0,0 +1,67 @@
+use crate::cpp_lib_builder::{common_ancestor, is_ccache, select_compiler_launcher};
+use crate::file_utils::{create_dir_all, create_file, remove_dir_all};
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process;
+
+#[test]
+fn compiler_launcher_is_opt_in() {
+    let dir = env::temp_dir().join(format!("ritual_test_compiler_launcher_{}", process::id()));
+    let bin_dir = dir.join("bin");
+    let empty_dir = dir.join("empty");
+    create_dir_all(&bin_dir).unwrap();
+    create_dir_all(&empty_dir).unwrap();
+    let ccache = bin_dir.join(format!("ccache{}", env::consts::EXE_SUFFIX));
+    create_file(&ccache).unwrap();
+    let paths = Some(env::join_paths(vec![&bin_dir]).unwrap());
+
+    assert_eq!(select_compiler_launcher(None, paths.as_ref()), None);
+    let none = OsString::from("none");
+    assert_eq!(select_compiler_launcher(Some(&none), paths.as_ref()), None);
+    let empty = OsString::new();
+    assert_eq!(select_compiler_launcher(Some(&empty), paths.as_ref()), None);
+    let auto = OsString::from("auto");
+    assert_eq!(
+        select_compiler_launcher(Some(&auto), paths.as_ref()),
+        Some(ccache)
+    );
+    assert_eq!(select_compiler_launcher(Some(&auto), None), None);
+    let sccache = OsString::from("sccache");
+    assert_eq!(
+        select_compiler_launcher(Some(&sccache), None),
+        Some(PathBuf::from("sccache"))
+    );
+
+    let paths = Some(env::join_paths(vec![&empty_dir]).unwrap());
+    assert_eq!(select_compiler_launcher(Some(&auto), paths.as_ref()), None);
+    remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn ccache_detection() {
+    assert!(is_ccache(Path::new("ccache")));
+    assert!(is_ccache(Path::new("/usr/bin/ccache")));
+    assert!(!is_ccache(Path::new("/usr/bin/sccache")));
+    assert!(!is_ccache(Path::new("/usr/lib/ccache/gcc")));
+}
+
+#[test]
+fn common_ancestors() {
+    assert_eq!(
+        common_ancestor(Path::new("/a/b/c/d"), Path::new("/a/b/e")),
+        PathBuf::from("/a/b")
+    );
+    assert_eq!(
+        common_ancestor(Path::new("/a/b"), Path::new("/a/b/c")),
+        PathBuf::from("/a/b")
+    );
+    assert_eq!(
+        common_ancestor(Path::new("/a/b"), Path::new("/c")),
+        PathBuf::from("/")
+    );
+    assert_eq!(
+        common_ancestor(Path::new("/a/bc"), Path::new("/a/b")),
+        PathBuf::from("/a")
+    );
+}
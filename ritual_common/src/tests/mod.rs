@@ -1 +1,6 @@
+mod android;
+mod compile_commands;
+mod cpp_build_config;
+mod cpp_lib_builder;
 mod utils;
+mod target;
@@ -0,0 +1,31 @@
+use crate::target::{Arch, Env, Target, OS};
+
+#[test]
+fn rust_triples() {
+    assert_eq!(
+        Target::msvc(Arch::X86_64).rust_triple(),
+        Some("x86_64-pc-windows-msvc".to_string())
+    );
+    assert_eq!(
+        Target::mingw(Arch::X86).rust_triple(),
+        Some("i686-pc-windows-gnu".to_string())
+    );
+    assert_eq!(
+        Target::emscripten().rust_triple(),
+        Some("wasm32-unknown-emscripten".to_string())
+    );
+    let linux = Target {
+        os: OS::Linux,
+        env: Env::Musl,
+        ..Target::mingw(Arch::AArch64)
+    };
+    assert_eq!(
+        linux.rust_triple(),
+        Some("aarch64-unknown-linux-musl".to_string())
+    );
+    let arm = Target {
+        arch: Arch::Arm,
+        ..linux
+    };
+    assert_eq!(arm.rust_triple(), None);
+}